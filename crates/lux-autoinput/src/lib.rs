@@ -0,0 +1,116 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::time::Duration;
+
+use async_io::Timer;
+use mlua::prelude::*;
+
+use lux_enum::{KEY_CODES, KeyChord, MOUSE_BUTTONS, item_value_from_lua};
+use lux_utils::TableBuilder;
+
+mod os;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `autoinput` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `autoinput` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_async_function("keyDown", autoinput_key_down)?
+        .with_async_function("keyUp", autoinput_key_up)?
+        .with_async_function("pressKey", autoinput_press_key)?
+        .with_async_function("hotkey", autoinput_hotkey)?
+        .with_async_function("typeText", autoinput_type_text)?
+        .with_async_function("mouseDown", autoinput_mouse_down)?
+        .with_async_function("mouseUp", autoinput_mouse_up)?
+        .with_async_function("click", autoinput_click)?
+        .with_async_function("moveMouse", autoinput_move_mouse)?
+        .with_async_function("scroll", autoinput_scroll)?
+        .build_readonly()
+}
+
+fn key_code(key: &LuaValue) -> LuaResult<i32> {
+    item_value_from_lua(key, "KeyCode", KEY_CODES)
+}
+
+/**
+    Converts an optional `Enum.MouseButton` item into its value, defaulting to the left button.
+*/
+fn button_or_left(button: &LuaValue) -> LuaResult<i32> {
+    match button {
+        LuaValue::Nil => Ok(0),
+        button => item_value_from_lua(button, "MouseButton", MOUSE_BUTTONS),
+    }
+}
+
+async fn autoinput_key_down(lua: Lua, key: LuaValue) -> LuaResult<()> {
+    os::key(&lua, key_code(&key)?, true).await
+}
+
+async fn autoinput_key_up(lua: Lua, key: LuaValue) -> LuaResult<()> {
+    os::key(&lua, key_code(&key)?, false).await
+}
+
+async fn autoinput_press_key(lua: Lua, key: LuaValue) -> LuaResult<()> {
+    os::tap(&lua, key_code(&key)?).await
+}
+
+async fn autoinput_hotkey(lua: Lua, chord: String) -> LuaResult<()> {
+    let chord = chord.parse::<KeyChord>().map_err(LuaError::runtime)?;
+    os::press_chord(&lua, &chord).await
+}
+
+async fn autoinput_type_text(lua: Lua, (text, interval): (String, Option<f64>)) -> LuaResult<()> {
+    let interval = match interval {
+        Some(secs) if secs.is_finite() && secs >= 0.0 => Duration::from_secs_f64(secs),
+        Some(_) => {
+            return Err(LuaError::runtime(
+                "Interval must be a non-negative number of seconds",
+            ));
+        }
+        None => Duration::ZERO,
+    };
+    // NOTE: Carriage returns are skipped so that "\r\n" presses return only once
+    for (index, c) in text.chars().filter(|&c| c != '\r').enumerate() {
+        if index > 0 && !interval.is_zero() {
+            Timer::after(interval).await;
+        }
+        os::type_char(&lua, c).await?;
+    }
+    Ok(())
+}
+
+async fn autoinput_mouse_down(lua: Lua, button: LuaValue) -> LuaResult<()> {
+    os::mouse_button(&lua, button_or_left(&button)?, true).await
+}
+
+async fn autoinput_mouse_up(lua: Lua, button: LuaValue) -> LuaResult<()> {
+    os::mouse_button(&lua, button_or_left(&button)?, false).await
+}
+
+async fn autoinput_click(lua: Lua, button: LuaValue) -> LuaResult<()> {
+    let button = button_or_left(&button)?;
+    os::mouse_button(&lua, button, true).await?;
+    os::mouse_button(&lua, button, false).await
+}
+
+async fn autoinput_move_mouse(lua: Lua, (dx, dy): (i32, i32)) -> LuaResult<()> {
+    os::move_mouse(&lua, dx, dy).await
+}
+
+async fn autoinput_scroll(lua: Lua, (vertical, horizontal): (i32, Option<i32>)) -> LuaResult<()> {
+    os::scroll(&lua, vertical, horizontal.unwrap_or_default()).await
+}
@@ -0,0 +1,551 @@
+//! Platform-specific input synthesis, sending events as if they came from real devices:
+//! - Windows: `SendInput`
+//! - Linux: a uinput virtual device, shared with `@lux/evdev`
+//! - macOS: `CGEventPost`, which needs Accessibility access
+//!
+//! Key codes are `Enum.KeyCode` values, which are the native key codes of each platform,
+//! and mouse buttons are `Enum.MouseButton` values.
+
+use mlua::prelude::*;
+
+use lux_enum::KeyChord;
+
+pub(crate) use self::imp::{key, mouse_button, move_mouse, scroll, type_char};
+
+/**
+    Presses and then releases a key.
+*/
+pub(crate) async fn tap(lua: &Lua, code: i32) -> LuaResult<()> {
+    key(lua, code, true).await?;
+    key(lua, code, false).await
+}
+
+/**
+    Presses the modifiers of a chord in order, taps its key, and then releases the modifiers.
+*/
+pub(crate) async fn press_chord(lua: &Lua, chord: &KeyChord) -> LuaResult<()> {
+    let mut pressed = 0;
+    let mut result = Ok(());
+    for modifier in &chord.modifiers {
+        result = key(lua, modifier.key_code(), true).await;
+        if result.is_err() {
+            break;
+        }
+        pressed += 1;
+    }
+    if result.is_ok() {
+        result = tap(lua, chord.key).await;
+    }
+    // Modifiers are released even if pressing failed, to never leave them held down
+    for modifier in chord.modifiers[..pressed].iter().rev() {
+        let released = key(lua, modifier.key_code(), false).await;
+        result = result.and(released);
+    }
+    result
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use mlua::prelude::*;
+
+    use lux_enum::{KeyChord, Modifier, key_from_char};
+    use lux_evdev::{send_key, send_motion, send_mouse_button, send_scroll};
+
+    use super::{press_chord, tap};
+
+    pub(crate) async fn key(lua: &Lua, code: i32, down: bool) -> LuaResult<()> {
+        send_key(lua, code as u16, down).await
+    }
+
+    pub(crate) async fn mouse_button(lua: &Lua, button: i32, down: bool) -> LuaResult<()> {
+        send_mouse_button(lua, button as u16, down).await
+    }
+
+    pub(crate) async fn move_mouse(lua: &Lua, dx: i32, dy: i32) -> LuaResult<()> {
+        send_motion(lua, dx, dy).await
+    }
+
+    pub(crate) async fn scroll(lua: &Lua, vertical: i32, horizontal: i32) -> LuaResult<()> {
+        send_scroll(lua, vertical, horizontal).await
+    }
+
+    /**
+        Returns the unshifted character on the same key as a
+        character that needs Shift on a US keyboard layout.
+    */
+    fn unshifted(c: char) -> Option<char> {
+        Some(match c {
+            'A'..='Z' => c,
+            '!' => '1',
+            '@' => '2',
+            '#' => '3',
+            '$' => '4',
+            '%' => '5',
+            '^' => '6',
+            '&' => '7',
+            '*' => '8',
+            '(' => '9',
+            ')' => '0',
+            '_' => '-',
+            '+' => '=',
+            '{' => '[',
+            '}' => ']',
+            '|' => '\\',
+            ':' => ';',
+            '"' => '\'',
+            '<' => ',',
+            '>' => '.',
+            '?' => '/',
+            '~' => '`',
+            _ => return None,
+        })
+    }
+
+    /**
+        Types a character by pressing the keys for it on a US keyboard layout.
+
+        Since uinput only knows about keys, characters that are not on the keyboard
+        are typed as their code point using `Ctrl+Shift+U`, which GTK and IBus support.
+    */
+    pub(crate) async fn type_char(lua: &Lua, c: char) -> LuaResult<()> {
+        if let Some(base) = unshifted(c)
+            && let Some(code) = key_from_char(base)
+        {
+            let chord = KeyChord {
+                modifiers: vec![Modifier::Shift],
+                key: code,
+            };
+            return press_chord(lua, &chord).await;
+        }
+        if let Some(code) = key_from_char(c) {
+            return tap(lua, code).await;
+        }
+
+        let unicode = KeyChord {
+            modifiers: vec![Modifier::Ctrl, Modifier::Shift],
+            key: key_from_char('u').expect("U is a key"),
+        };
+        press_chord(lua, &unicode).await?;
+        for digit in format!("{:x}", c as u32).chars() {
+            tap(lua, key_from_char(digit).expect("hex digits are keys")).await?;
+        }
+        tap(lua, key_from_char(' ').expect("space is a key")).await
+    }
+}
+
+#[cfg(windows)]
+#[allow(clippy::unused_async)] // Kept async to match the Linux implementation
+mod imp {
+    use std::{io, mem::size_of};
+
+    use mlua::prelude::*;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        INPUT, INPUT_0, INPUT_KEYBOARD, INPUT_MOUSE, KEYBDINPUT, KEYEVENTF_EXTENDEDKEY,
+        KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, MAPVK_VK_TO_VSC, MOUSEEVENTF_HWHEEL,
+        MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP,
+        MOUSEEVENTF_MOVE, MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL,
+        MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT, MapVirtualKeyW, SendInput, VK_RETURN,
+    };
+
+    // From winuser.h, which windows-sys only has behind its WindowsAndMessaging feature
+    const XBUTTON1: u32 = 0x0001;
+    const XBUTTON2: u32 = 0x0002;
+    const WHEEL_DELTA: i32 = 120;
+
+    fn send(inputs: &[INPUT]) -> LuaResult<()> {
+        let sent = unsafe {
+            SendInput(
+                inputs.len() as u32,
+                inputs.as_ptr(),
+                size_of::<INPUT>() as i32,
+            )
+        };
+        if sent as usize == inputs.len() {
+            Ok(())
+        } else {
+            Err(LuaError::runtime(format!(
+                "Failed to send input: {}",
+                io::Error::last_os_error()
+            )))
+        }
+    }
+
+    fn keyboard_input(vk: u16, scan: u16, flags: u32) -> INPUT {
+        INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: vk,
+                    wScan: scan,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        }
+    }
+
+    fn mouse_input(dx: i32, dy: i32, data: u32, flags: u32) -> INPUT {
+        INPUT {
+            r#type: INPUT_MOUSE,
+            Anonymous: INPUT_0 {
+                mi: MOUSEINPUT {
+                    dx,
+                    dy,
+                    mouseData: data,
+                    dwFlags: flags,
+                    time: 0,
+                    dwExtraInfo: 0,
+                },
+            },
+        }
+    }
+
+    /**
+        Returns whether a virtual key is on the extended part of the keyboard,
+        which it must be flagged as for programs that read scan codes.
+    */
+    fn is_extended(vk: u16) -> bool {
+        matches!(
+            vk,
+            0x21..=0x28 // Page up, page down, end, home, and arrows
+                | 0x2C // Print screen
+                | 0x2D // Insert
+                | 0x2E // Delete
+                | 0x5B..=0x5D // Windows keys and menu
+                | 0x6F // Numpad divide
+                | 0x90 // Num lock
+                | 0xA3 // Right control
+                | 0xA5 // Right alt
+                | 0xAD..=0xB3 // Volume and media keys
+        )
+    }
+
+    pub(crate) async fn key(_: &Lua, code: i32, down: bool) -> LuaResult<()> {
+        let vk = code as u16;
+        let scan = unsafe { MapVirtualKeyW(u32::from(vk), MAPVK_VK_TO_VSC) } as u16;
+        let mut flags = if down { 0 } else { KEYEVENTF_KEYUP };
+        if is_extended(vk) {
+            flags |= KEYEVENTF_EXTENDEDKEY;
+        }
+        send(&[keyboard_input(vk, scan, flags)])
+    }
+
+    pub(crate) async fn mouse_button(_: &Lua, button: i32, down: bool) -> LuaResult<()> {
+        let (data, flags) = match (button, down) {
+            (0, true) => (0, MOUSEEVENTF_LEFTDOWN),
+            (0, false) => (0, MOUSEEVENTF_LEFTUP),
+            (1, true) => (0, MOUSEEVENTF_RIGHTDOWN),
+            (1, false) => (0, MOUSEEVENTF_RIGHTUP),
+            (2, true) => (0, MOUSEEVENTF_MIDDLEDOWN),
+            (2, false) => (0, MOUSEEVENTF_MIDDLEUP),
+            (3, true) => (XBUTTON1, MOUSEEVENTF_XDOWN),
+            (3, false) => (XBUTTON1, MOUSEEVENTF_XUP),
+            (_, true) => (XBUTTON2, MOUSEEVENTF_XDOWN),
+            (_, false) => (XBUTTON2, MOUSEEVENTF_XUP),
+        };
+        send(&[mouse_input(0, 0, data, flags)])
+    }
+
+    pub(crate) async fn move_mouse(_: &Lua, dx: i32, dy: i32) -> LuaResult<()> {
+        send(&[mouse_input(dx, dy, 0, MOUSEEVENTF_MOVE)])
+    }
+
+    pub(crate) async fn scroll(_: &Lua, vertical: i32, horizontal: i32) -> LuaResult<()> {
+        let mut inputs = Vec::with_capacity(2);
+        if vertical != 0 {
+            inputs.push(mouse_input(
+                0,
+                0,
+                (vertical * WHEEL_DELTA) as u32,
+                MOUSEEVENTF_WHEEL,
+            ));
+        }
+        if horizontal != 0 {
+            inputs.push(mouse_input(
+                0,
+                0,
+                (horizontal * WHEEL_DELTA) as u32,
+                MOUSEEVENTF_HWHEEL,
+            ));
+        }
+        send(&inputs)
+    }
+
+    pub(crate) async fn type_char(_: &Lua, c: char) -> LuaResult<()> {
+        // NOTE: Most programs ignore newlines typed as text, so press return instead
+        if c == '\n' {
+            let scan = unsafe { MapVirtualKeyW(u32::from(VK_RETURN), MAPVK_VK_TO_VSC) } as u16;
+            return send(&[
+                keyboard_input(VK_RETURN, scan, 0),
+                keyboard_input(VK_RETURN, scan, KEYEVENTF_KEYUP),
+            ]);
+        }
+        let mut units = [0; 2];
+        let inputs = c
+            .encode_utf16(&mut units)
+            .iter()
+            .flat_map(|&unit| {
+                [
+                    keyboard_input(0, unit, KEYEVENTF_UNICODE),
+                    keyboard_input(0, unit, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP),
+                ]
+            })
+            .collect::<Vec<_>>();
+        send(&inputs)
+    }
+}
+
+#[cfg(target_os = "macos")]
+#[allow(clippy::unused_async)] // Kept async to match the Linux implementation
+mod imp {
+    use std::{
+        ffi::c_void,
+        sync::atomic::{AtomicU8, AtomicU64, Ordering},
+    };
+
+    use mlua::prelude::*;
+
+    use lux_enum::{KEY_CODES, Modifier};
+
+    type CGEventRef = *mut c_void;
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct CGPoint {
+        x: f64,
+        y: f64,
+    }
+
+    // From CGEventTypes.h
+    const HID_EVENT_TAP: u32 = 0;
+    const SCROLL_UNIT_LINE: u32 = 1;
+    const EVENT_LEFT_MOUSE_DOWN: u32 = 1;
+    const EVENT_LEFT_MOUSE_UP: u32 = 2;
+    const EVENT_RIGHT_MOUSE_DOWN: u32 = 3;
+    const EVENT_RIGHT_MOUSE_UP: u32 = 4;
+    const EVENT_MOUSE_MOVED: u32 = 5;
+    const EVENT_LEFT_MOUSE_DRAGGED: u32 = 6;
+    const EVENT_RIGHT_MOUSE_DRAGGED: u32 = 7;
+    const EVENT_OTHER_MOUSE_DOWN: u32 = 25;
+    const EVENT_OTHER_MOUSE_UP: u32 = 26;
+    const EVENT_OTHER_MOUSE_DRAGGED: u32 = 27;
+    const FIELD_MOUSE_CLICK_STATE: u32 = 1;
+    const FIELD_MOUSE_DELTA_X: u32 = 4;
+    const FIELD_MOUSE_DELTA_Y: u32 = 5;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    unsafe extern "C" {
+        fn CGEventCreate(source: *const c_void) -> CGEventRef;
+        fn CGEventGetLocation(event: CGEventRef) -> CGPoint;
+        fn CGEventCreateKeyboardEvent(source: *const c_void, key: u16, down: bool) -> CGEventRef;
+        fn CGEventCreateMouseEvent(
+            source: *const c_void,
+            kind: u32,
+            location: CGPoint,
+            button: u32,
+        ) -> CGEventRef;
+        fn CGEventCreateScrollWheelEvent(
+            source: *const c_void,
+            units: u32,
+            wheel_count: u32,
+            wheel1: i32,
+            ...
+        ) -> CGEventRef;
+        fn CGEventKeyboardSetUnicodeString(event: CGEventRef, length: usize, string: *const u16);
+        fn CGEventSetFlags(event: CGEventRef, flags: u64);
+        fn CGEventSetIntegerValueField(event: CGEventRef, field: u32, value: i64);
+        fn CGEventPost(tap: u32, event: CGEventRef);
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        fn CFRelease(object: *const c_void);
+    }
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    unsafe extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+    }
+
+    /*
+        Posted events don't pick up modifiers or buttons that were pressed by other posted
+        events, so these are tracked here and applied to every event that is posted after
+    */
+    static HELD_MODIFIERS: AtomicU64 = AtomicU64::new(0);
+    static HELD_BUTTONS: AtomicU8 = AtomicU8::new(0);
+
+    /**
+        Posts an event, releasing it after, along with the modifiers that are currently held.
+    */
+    fn post(event: CGEventRef) -> LuaResult<()> {
+        if event.is_null() {
+            return Err(LuaError::runtime("Failed to create input event"));
+        }
+        // NOTE: Without Accessibility access, posted events are silently dropped
+        let trusted = unsafe { AXIsProcessTrusted() };
+        unsafe {
+            if trusted {
+                let modifiers = HELD_MODIFIERS.load(Ordering::SeqCst);
+                if modifiers != 0 {
+                    CGEventSetFlags(event, modifiers);
+                }
+                CGEventPost(HID_EVENT_TAP, event);
+            }
+            CFRelease(event);
+        }
+        if trusted {
+            Ok(())
+        } else {
+            Err(LuaError::runtime(
+                "Synthesizing input requires Accessibility access, which can be granted \
+                to the terminal running Lux in System Settings > Privacy & Security",
+            ))
+        }
+    }
+
+    fn cursor_location() -> LuaResult<CGPoint> {
+        unsafe {
+            let event = CGEventCreate(std::ptr::null());
+            if event.is_null() {
+                return Err(LuaError::runtime("Failed to get the mouse location"));
+            }
+            let location = CGEventGetLocation(event);
+            CFRelease(event);
+            Ok(location)
+        }
+    }
+
+    pub(crate) async fn key(_: &Lua, code: i32, down: bool) -> LuaResult<()> {
+        if let Some(modifier) = Modifier::from_key_code(code) {
+            let mask = modifier.mask() as u64;
+            if down {
+                HELD_MODIFIERS.fetch_or(mask, Ordering::SeqCst);
+            } else {
+                HELD_MODIFIERS.fetch_and(!mask, Ordering::SeqCst);
+            }
+        }
+        post(unsafe { CGEventCreateKeyboardEvent(std::ptr::null(), code as u16, down) })
+    }
+
+    pub(crate) async fn mouse_button(_: &Lua, button: i32, down: bool) -> LuaResult<()> {
+        let kind = match (button, down) {
+            (0, true) => EVENT_LEFT_MOUSE_DOWN,
+            (0, false) => EVENT_LEFT_MOUSE_UP,
+            (1, true) => EVENT_RIGHT_MOUSE_DOWN,
+            (1, false) => EVENT_RIGHT_MOUSE_UP,
+            (_, true) => EVENT_OTHER_MOUSE_DOWN,
+            (_, false) => EVENT_OTHER_MOUSE_UP,
+        };
+        let bit = 1 << button;
+        if down {
+            HELD_BUTTONS.fetch_or(bit, Ordering::SeqCst);
+        } else {
+            HELD_BUTTONS.fetch_and(!bit, Ordering::SeqCst);
+        }
+        let location = cursor_location()?;
+        unsafe {
+            let event = CGEventCreateMouseEvent(std::ptr::null(), kind, location, button as u32);
+            if !event.is_null() {
+                CGEventSetIntegerValueField(event, FIELD_MOUSE_CLICK_STATE, 1);
+            }
+            post(event)
+        }
+    }
+
+    pub(crate) async fn move_mouse(_: &Lua, dx: i32, dy: i32) -> LuaResult<()> {
+        // NOTE: Moving while a button is held is a drag, which programs expect to be
+        // sent as such, otherwise they see the button as having been released
+        let held = HELD_BUTTONS.load(Ordering::SeqCst);
+        let (kind, button) = if held & 1 != 0 {
+            (EVENT_LEFT_MOUSE_DRAGGED, 0)
+        } else if held & 2 != 0 {
+            (EVENT_RIGHT_MOUSE_DRAGGED, 1)
+        } else if held != 0 {
+            (EVENT_OTHER_MOUSE_DRAGGED, held.trailing_zeros())
+        } else {
+            (EVENT_MOUSE_MOVED, 0)
+        };
+        let from = cursor_location()?;
+        let to = CGPoint {
+            x: from.x + f64::from(dx),
+            y: from.y + f64::from(dy),
+        };
+        unsafe {
+            let event = CGEventCreateMouseEvent(std::ptr::null(), kind, to, button);
+            if !event.is_null() {
+                CGEventSetIntegerValueField(event, FIELD_MOUSE_DELTA_X, i64::from(dx));
+                CGEventSetIntegerValueField(event, FIELD_MOUSE_DELTA_Y, i64::from(dy));
+            }
+            post(event)
+        }
+    }
+
+    pub(crate) async fn scroll(_: &Lua, vertical: i32, horizontal: i32) -> LuaResult<()> {
+        // NOTE: Positive horizontal amounts scroll left on macOS, but right everywhere else
+        post(unsafe {
+            CGEventCreateScrollWheelEvent(
+                std::ptr::null(),
+                SCROLL_UNIT_LINE,
+                2,
+                vertical,
+                -horizontal,
+            )
+        })
+    }
+
+    pub(crate) async fn type_char(lua: &Lua, c: char) -> LuaResult<()> {
+        // NOTE: Most programs ignore newlines typed as text, so press return instead
+        if c == '\n' {
+            let (_, code) = KEY_CODES
+                .iter()
+                .find(|&&(name, _)| name == "Return")
+                .expect("Return is a key code");
+            return super::tap(lua, *code).await;
+        }
+        let mut units = [0; 2];
+        let units = c.encode_utf16(&mut units);
+        for down in [true, false] {
+            unsafe {
+                let event = CGEventCreateKeyboardEvent(std::ptr::null(), 0, down);
+                if !event.is_null() {
+                    CGEventKeyboardSetUnicodeString(event, units.len(), units.as_ptr());
+                }
+                post(event)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+#[allow(clippy::unused_async)]
+mod imp {
+    use mlua::prelude::*;
+
+    fn unsupported() -> LuaResult<()> {
+        Err(LuaError::runtime(
+            "Synthesizing input is not supported on this platform",
+        ))
+    }
+
+    pub(crate) async fn key(_: &Lua, _: i32, _: bool) -> LuaResult<()> {
+        unsupported()
+    }
+
+    pub(crate) async fn mouse_button(_: &Lua, _: i32, _: bool) -> LuaResult<()> {
+        unsupported()
+    }
+
+    pub(crate) async fn move_mouse(_: &Lua, _: i32, _: i32) -> LuaResult<()> {
+        unsupported()
+    }
+
+    pub(crate) async fn scroll(_: &Lua, _: i32, _: i32) -> LuaResult<()> {
+        unsupported()
+    }
+
+    pub(crate) async fn type_char(_: &Lua, _: char) -> LuaResult<()> {
+        unsupported()
+    }
+}
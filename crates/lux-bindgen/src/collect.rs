@@ -0,0 +1,786 @@
+//! Walks the parsed header, turning its declarations into declarations that
+//! `ffi.cdef` understands, along with its constants and function signatures.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use lang_c::ast::{
+    ArraySize, Declaration, DeclarationSpecifier, Declarator, DeclaratorKind, DerivedDeclarator,
+    Ellipsis, EnumType, ExternalDeclaration, FunctionDeclarator, PointerQualifier,
+    SpecifierQualifier, StorageClassSpecifier, StructDeclaration, StructKind, StructType,
+    TranslationUnit, TypeQualifier, TypeSpecifier,
+};
+use lang_c::span::Node;
+
+use crate::{
+    eval::{Numeric, Scope, Value, eval_expression, eval_macro},
+    preprocess::Preprocessed,
+};
+
+/**
+    Typedefs from the standard library that `ffi.cdef` understands
+    by name, which are kept as-is instead of being resolved.
+*/
+const BUILTIN_TYPEDEFS: &[&str] = &[
+    "int8_t",
+    "uint8_t",
+    "int16_t",
+    "uint16_t",
+    "int32_t",
+    "uint32_t",
+    "int64_t",
+    "uint64_t",
+    "size_t",
+    "ssize_t",
+    "intptr_t",
+    "uintptr_t",
+    "ptrdiff_t",
+];
+
+/**
+    A C type, as far as the bindings need to know about it.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Void,
+    Bool,
+    /// An integer type, spelled the way that `ffi.cdef` understands it
+    Int(String),
+    Float(&'static str),
+    /// A struct or union, by the name it was defined under
+    Record(String),
+    /// An enum, by the name it was defined under
+    Enum(String),
+    /// A typedef that is part of the bindings, along with the type it resolves to
+    Named(String, Box<Ty>),
+    Pointer {
+        pointee: Box<Ty>,
+        is_const: bool,
+    },
+    Array(Box<Ty>, usize),
+    Function(Box<Signature>),
+}
+
+impl Ty {
+    fn pointer(pointee: Ty, is_const: bool) -> Self {
+        Self::Pointer {
+            pointee: Box::new(pointee),
+            is_const,
+        }
+    }
+
+    /**
+        Returns how the type is spelled in declarations, without any array dimensions.
+    */
+    pub fn spell(&self) -> String {
+        match self {
+            Self::Void => String::from("void"),
+            // NOTE: `bool` is four bytes to ffi.cdef, like the Win32 BOOL, but _Bool is a single byte
+            Self::Bool => String::from("uint8_t"),
+            Self::Int(name) | Self::Record(name) | Self::Enum(name) | Self::Named(name, _) => {
+                name.clone()
+            }
+            Self::Float(name) => (*name).to_string(),
+            Self::Pointer { pointee, is_const } => match pointee.as_ref() {
+                Self::Function(_) => String::from("void*"),
+                Self::Array(element, _) => Self::pointer((**element).clone(), *is_const).spell(),
+                pointee if *is_const => format!("const {}*", pointee.spell()),
+                pointee => format!("{}*", pointee.spell()),
+            },
+            Self::Array(element, _) => element.spell(),
+            Self::Function(_) => String::from("void*"),
+        }
+    }
+
+    /**
+        Returns the type as it should be seen from Luau, looking through typedefs.
+    */
+    pub fn resolved(&self) -> &Ty {
+        match self {
+            Self::Named(_, ty) => ty.resolved(),
+            ty => ty,
+        }
+    }
+
+    fn numeric(&self) -> Option<Numeric> {
+        match self.resolved() {
+            Self::Bool | Self::Int(_) | Self::Enum(_) => Some(Numeric::Int),
+            Self::Float(_) => Some(Numeric::Float),
+            _ => None,
+        }
+    }
+}
+
+/**
+    Spells out a declaration of `name` with the given type, such as `int values[4]`.
+*/
+pub fn declare(ty: &Ty, name: &str) -> String {
+    let mut dims = String::new();
+    let mut element = ty;
+    while let Ty::Array(inner, size) = element {
+        let _ = write!(dims, "[{size}]");
+        element = inner;
+    }
+    format!("{} {name}{dims}", element.spell())
+}
+
+/**
+    The signature of a function or function pointer.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub ret: Ty,
+    pub params: Vec<(String, Ty)>,
+    pub variadic: bool,
+}
+
+impl Signature {
+    fn spell_params(&self, with_names: bool) -> String {
+        let mut params = self
+            .params
+            .iter()
+            .map(|(name, ty)| {
+                if with_names {
+                    declare(ty, name)
+                } else {
+                    ty.spell()
+                }
+            })
+            .collect::<Vec<_>>();
+        if self.variadic {
+            params.push(String::from("..."));
+        }
+        if params.is_empty() {
+            String::from("void")
+        } else {
+            params.join(", ")
+        }
+    }
+}
+
+/**
+    A function from the header, which gets a wrapper in the generated module.
+*/
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub signature: Signature,
+    /// The declaration of the function, as given to `ffi.cdef`
+    pub declaration: String,
+}
+
+/**
+    Everything from the header that ends up in the generated module.
+*/
+#[derive(Debug, Default)]
+pub struct Collected {
+    pub cdef: Vec<String>,
+    pub constants: Vec<(String, Value)>,
+    pub functions: Vec<Function>,
+    pub skipped: Vec<String>,
+}
+
+type Result<T> = std::result::Result<T, String>;
+
+/**
+    The type specifiers and qualifiers of a declaration, such as `static const unsigned int`.
+*/
+#[derive(Default)]
+struct Specifiers<'a> {
+    types: Vec<&'a TypeSpecifier>,
+    is_const: bool,
+    is_typedef: bool,
+    is_static: bool,
+}
+
+impl<'a> Specifiers<'a> {
+    fn from_declaration(specifiers: &'a [Node<DeclarationSpecifier>]) -> Self {
+        let mut out = Self::default();
+        for specifier in specifiers {
+            match &specifier.node {
+                DeclarationSpecifier::TypeSpecifier(ts) => out.types.push(&ts.node),
+                DeclarationSpecifier::TypeQualifier(tq) => {
+                    out.is_const |= matches!(tq.node, TypeQualifier::Const);
+                }
+                DeclarationSpecifier::StorageClass(sc) => match sc.node {
+                    StorageClassSpecifier::Typedef => out.is_typedef = true,
+                    StorageClassSpecifier::Static => out.is_static = true,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        out
+    }
+
+    fn from_qualifiers(specifiers: &'a [Node<SpecifierQualifier>]) -> Self {
+        let mut out = Self::default();
+        for specifier in specifiers {
+            match &specifier.node {
+                SpecifierQualifier::TypeSpecifier(ts) => out.types.push(&ts.node),
+                SpecifierQualifier::TypeQualifier(tq) => {
+                    out.is_const |= matches!(tq.node, TypeQualifier::Const);
+                }
+                SpecifierQualifier::Extension(_) => {}
+            }
+        }
+        out
+    }
+
+    /**
+        Returns the inline struct or union definition of a member, if any.
+    */
+    fn struct_body(&self) -> Option<&'a StructType> {
+        self.types.iter().copied().find_map(|ts| match ts {
+            TypeSpecifier::Struct(st) if st.node.declarations.is_some() => Some(&st.node),
+            _ => None,
+        })
+    }
+}
+
+struct Collector<'a> {
+    pre: &'a Preprocessed,
+    /// Whether the declaration being collected is from the header, as opposed to its dependencies
+    own: bool,
+    typedefs: HashMap<String, Ty>,
+    enums: HashSet<String>,
+    records: HashSet<String>,
+    foreign_records: HashMap<String, StructType>,
+    values: HashMap<String, Value>,
+    functions: HashSet<String>,
+    out: Collected,
+}
+
+impl Scope for Collector<'_> {
+    fn constant(&self, name: &str) -> Option<Value> {
+        self.values.get(name).cloned()
+    }
+
+    fn numeric_type(&self, name: &str) -> Option<Numeric> {
+        if BUILTIN_TYPEDEFS.contains(&name) {
+            return Some(Numeric::Int);
+        }
+        self.typedefs.get(name)?.numeric()
+    }
+}
+
+/**
+    Collects the declarations of a preprocessed and parsed header.
+*/
+pub fn collect(unit: &TranslationUnit, pre: &Preprocessed) -> Collected {
+    let mut collector = Collector {
+        pre,
+        own: false,
+        typedefs: HashMap::new(),
+        enums: HashSet::new(),
+        records: HashSet::new(),
+        foreign_records: HashMap::new(),
+        values: HashMap::new(),
+        functions: HashSet::new(),
+        out: Collected::default(),
+    };
+
+    for external in &unit.0 {
+        // NOTE: Function definitions in headers are static inline, and can't be loaded from libraries
+        if let ExternalDeclaration::Declaration(declaration) = &external.node {
+            collector.own = pre.is_own(declaration.span.start);
+            collector.declaration(&declaration.node);
+        }
+    }
+    collector.own = true;
+    collector.macros();
+
+    collector.out
+}
+
+impl Collector<'_> {
+    fn skip(&mut self, what: impl Into<String>, reason: impl AsRef<str>) {
+        if self.own {
+            let what = what.into();
+            self.out
+                .skipped
+                .push(format!("{what} - {}", reason.as_ref()));
+        }
+    }
+
+    fn declaration(&mut self, declaration: &Declaration) {
+        let specifiers = Specifiers::from_declaration(&declaration.specifiers);
+
+        // Typedefs of inline definitions, like `typedef struct { ... } Foo;`, name the definition
+        let hint = match declaration.declarators.as_slice() {
+            [only] if specifiers.is_typedef && only.node.declarator.node.derived.is_empty() => {
+                identifier(&only.node.declarator.node)
+            }
+            _ => None,
+        };
+
+        let base = match self.base_type(&specifiers, hint.as_deref()) {
+            Ok(base) => base,
+            Err(reason) => {
+                for declarator in &declaration.declarators {
+                    if let Some(name) = identifier(&declarator.node.declarator.node) {
+                        self.skip(name, &reason);
+                    }
+                }
+                return;
+            }
+        };
+
+        for init in &declaration.declarators {
+            let declarator = &init.node.declarator.node;
+            let Some(name) = identifier(declarator) else {
+                continue;
+            };
+            let ty = match self.apply_declarator(base.clone(), specifiers.is_const, declarator) {
+                Ok((_, ty)) => ty,
+                Err(reason) => {
+                    self.skip(name, reason);
+                    continue;
+                }
+            };
+            if specifiers.is_typedef {
+                self.typedef(name, ty);
+            } else if let Ty::Function(signature) = ty {
+                if !specifiers.is_static {
+                    self.function(name, *signature);
+                }
+            } else {
+                self.skip(name, "global variables are not supported");
+            }
+        }
+    }
+
+    fn base_type(&mut self, specifiers: &Specifiers, hint: Option<&str>) -> Result<Ty> {
+        let (mut unsigned, mut signed, mut longs) = (false, false, 0);
+        let (mut is_short, mut is_char, mut is_float, mut is_double) = (false, false, false, false);
+        let (mut is_void, mut is_bool) = (false, false);
+        for ts in &specifiers.types {
+            match ts {
+                TypeSpecifier::Void => is_void = true,
+                TypeSpecifier::Char => is_char = true,
+                TypeSpecifier::Short => is_short = true,
+                TypeSpecifier::Int => {}
+                TypeSpecifier::Long => longs += 1,
+                TypeSpecifier::Float => is_float = true,
+                TypeSpecifier::Double => is_double = true,
+                TypeSpecifier::Signed => signed = true,
+                TypeSpecifier::Unsigned => unsigned = true,
+                TypeSpecifier::Bool => is_bool = true,
+                TypeSpecifier::Struct(st) => return self.struct_type(&st.node, hint),
+                TypeSpecifier::Enum(et) => return self.enum_type(&et.node, hint),
+                TypeSpecifier::TypedefName(id) => return self.typedef_name(&id.node.name),
+                _ => return Err(String::from("unsupported type")),
+            }
+        }
+
+        let sign = if unsigned { "unsigned " } else { "" };
+        Ok(if is_void {
+            Ty::Void
+        } else if is_bool {
+            Ty::Bool
+        } else if is_float {
+            Ty::Float("float")
+        } else if is_double {
+            Ty::Float(if longs > 0 { "long double" } else { "double" })
+        } else if is_char {
+            Ty::Int(String::from(match (unsigned, signed) {
+                (true, _) => "unsigned char",
+                (false, true) => "signed char",
+                (false, false) => "char",
+            }))
+        } else if is_short {
+            Ty::Int(format!("{sign}short"))
+        } else if longs == 1 {
+            Ty::Int(format!("{sign}long"))
+        } else if longs > 1 {
+            Ty::Int(format!("{sign}long long"))
+        } else {
+            Ty::Int(format!("{sign}int"))
+        })
+    }
+
+    fn typedef_name(&self, name: &str) -> Result<Ty> {
+        if BUILTIN_TYPEDEFS.contains(&name) {
+            return Ok(Ty::Int(name.to_string()));
+        }
+        self.typedefs
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("unsupported type '{name}'"))
+    }
+
+    /**
+        Applies pointers, arrays and function parameters from a declarator to its base type.
+    */
+    fn apply_declarator(
+        &mut self,
+        base: Ty,
+        is_const: bool,
+        declarator: &Declarator,
+    ) -> Result<(Option<String>, Ty)> {
+        let mut ty = base;
+        let mut is_const = is_const;
+
+        // Pointers bind looser than arrays and functions, which apply from right to left
+        let (pointers, suffixes): (Vec<_>, Vec<_>) = declarator
+            .derived
+            .iter()
+            .partition(|d| matches!(d.node, DerivedDeclarator::Pointer(_)));
+        for pointer in pointers {
+            if let DerivedDeclarator::Pointer(qualifiers) = &pointer.node {
+                ty = Ty::pointer(ty, is_const);
+                is_const = qualifiers.iter().any(|q| {
+                    matches!(&q.node, PointerQualifier::TypeQualifier(tq) if matches!(tq.node, TypeQualifier::Const))
+                });
+            }
+        }
+        for suffix in suffixes.into_iter().rev() {
+            ty = match &suffix.node {
+                DerivedDeclarator::Array(array) => {
+                    let size = match &array.node.size {
+                        // Flexible array members take up no space
+                        ArraySize::Unknown => 0,
+                        ArraySize::VariableExpression(expr) | ArraySize::StaticExpression(expr) => {
+                            eval_expression(&expr.node, self)
+                                .and_then(|v| v.as_int())
+                                .and_then(|v| usize::try_from(v).ok())
+                                .ok_or("arrays must have a constant size")?
+                        }
+                        ArraySize::VariableUnknown => {
+                            return Err(String::from("arrays must have a constant size"));
+                        }
+                    };
+                    Ty::Array(Box::new(ty), size)
+                }
+                DerivedDeclarator::Function(function) => {
+                    Ty::Function(Box::new(self.signature(ty, &function.node)?))
+                }
+                // NOTE: Old-style declarations like `int foo();` take unknown arguments, but usually mean none
+                DerivedDeclarator::KRFunction(params) if params.is_empty() => {
+                    Ty::Function(Box::new(Signature {
+                        ret: ty,
+                        params: Vec::new(),
+                        variadic: false,
+                    }))
+                }
+                _ => return Err(String::from("unsupported declarator")),
+            };
+        }
+
+        match &declarator.kind.node {
+            DeclaratorKind::Abstract => Ok((None, ty)),
+            DeclaratorKind::Identifier(id) => Ok((Some(id.node.name.clone()), ty)),
+            DeclaratorKind::Declarator(inner) => self.apply_declarator(ty, false, &inner.node),
+        }
+    }
+
+    fn signature(&mut self, ret: Ty, function: &FunctionDeclarator) -> Result<Signature> {
+        let mut params = Vec::new();
+        for (index, param) in function.parameters.iter().enumerate() {
+            let specifiers = Specifiers::from_declaration(&param.node.specifiers);
+            let base = self.base_type(&specifiers, None)?;
+            let (name, ty) = match &param.node.declarator {
+                Some(declarator) => {
+                    self.apply_declarator(base, specifiers.is_const, &declarator.node)?
+                }
+                None => (None, base),
+            };
+            if ty == Ty::Void && name.is_none() {
+                continue; // (void)
+            }
+            // Array and function parameters are really pointers
+            let ty = match ty {
+                Ty::Array(element, _) => Ty::pointer(*element, specifiers.is_const),
+                Ty::Function(_) => Ty::pointer(ty, false),
+                ty => ty,
+            };
+            params.push((name.unwrap_or_else(|| format!("arg{}", index + 1)), ty));
+        }
+        Ok(Signature {
+            ret,
+            params,
+            variadic: matches!(function.ellipsis, Ellipsis::Some),
+        })
+    }
+
+    /*
+        Structs and unions
+    */
+
+    fn struct_type(&mut self, st: &StructType, hint: Option<&str>) -> Result<Ty> {
+        let tag = st.identifier.as_ref().map(|id| id.node.name.clone());
+        if st.declarations.is_none() {
+            return tag
+                .map(Ty::Record)
+                .ok_or_else(|| String::from("unsupported type"));
+        }
+        let name = tag
+            .or_else(|| hint.map(ToString::to_string))
+            .ok_or_else(|| String::from("anonymous structs must be named with a typedef"))?;
+        if self.own {
+            if !self.records.contains(&name) {
+                self.define_record(&name, st);
+            }
+        } else {
+            self.foreign_records.insert(name.clone(), st.clone());
+        }
+        Ok(Ty::Record(name))
+    }
+
+    fn define_record(&mut self, name: &str, st: &StructType) {
+        self.records.insert(name.to_string());
+        let keyword = match st.kind.node {
+            StructKind::Struct => "struct",
+            StructKind::Union => "union",
+        };
+        match self.record_body(name, st, 1) {
+            Ok(body) => self
+                .out
+                .cdef
+                .push(format!("typedef {keyword} {name} {{\n{body}}} {name};")),
+            Err(reason) => {
+                // Opaque records can still be used through pointers
+                self.out
+                    .cdef
+                    .push(format!("typedef {keyword} {name} {name};"));
+                self.skip(format!("{keyword} {name}"), reason);
+            }
+        }
+    }
+
+    fn record_body(&mut self, name: &str, st: &StructType, depth: usize) -> Result<String> {
+        let indent = "\t".repeat(depth);
+        let mut body = String::new();
+        for declaration in st.declarations.iter().flatten() {
+            let StructDeclaration::Field(field) = &declaration.node else {
+                continue;
+            };
+            let specifiers = Specifiers::from_qualifiers(&field.node.specifiers);
+
+            // Anonymous members have their fields accessed as if they were part of the parent
+            if field.node.declarators.is_empty() {
+                if let Some(inner) = specifiers.struct_body()
+                    && inner.identifier.is_none()
+                {
+                    let keyword = match inner.kind.node {
+                        StructKind::Struct => "struct",
+                        StructKind::Union => "union",
+                    };
+                    let inner_body = self.record_body(name, inner, depth + 1)?;
+                    let _ = writeln!(body, "{indent}{keyword} {{\n{inner_body}{indent}}};");
+                }
+                continue;
+            }
+
+            // Other nested definitions without a tag are named after the parent and their field
+            let hint = field.node.declarators.iter().find_map(|d| {
+                let declarator = d.node.declarator.as_ref()?;
+                Some(format!("{name}_{}", identifier(&declarator.node)?))
+            });
+            let base = self.base_type(&specifiers, hint.as_deref())?;
+
+            for declarator in &field.node.declarators {
+                if declarator.node.bit_width.is_some() {
+                    return Err(String::from("bit fields are not supported"));
+                }
+                let Some(declarator) = &declarator.node.declarator else {
+                    continue;
+                };
+                let (field_name, ty) =
+                    self.apply_declarator(base.clone(), specifiers.is_const, &declarator.node)?;
+                let field_name = field_name.ok_or("unnamed field")?;
+                self.require(&ty);
+                let _ = writeln!(body, "{indent}{};", declare(&ty, &field_name));
+            }
+        }
+        Ok(body)
+    }
+
+    /**
+        Makes sure that records used by value are defined, since their size needs to be known.
+        Records from dependencies, like `struct timeval`, are only defined once they are used.
+    */
+    fn require(&mut self, ty: &Ty) {
+        match ty {
+            Ty::Record(name) if !self.records.contains(name) => {
+                if let Some(st) = self.foreign_records.remove(name) {
+                    let own = std::mem::replace(&mut self.own, true);
+                    self.define_record(name, &st);
+                    self.own = own;
+                }
+            }
+            Ty::Array(element, _) => self.require(element),
+            _ => {}
+        }
+    }
+
+    /*
+        Enums
+    */
+
+    fn enum_type(&mut self, et: &EnumType, hint: Option<&str>) -> Result<Ty> {
+        let tag = et.identifier.as_ref().map(|id| id.node.name.clone());
+        if et.enumerators.is_empty() {
+            return Ok(match tag {
+                Some(tag) if self.enums.contains(&tag) => Ty::Enum(tag),
+                _ => Ty::Int(String::from("int")),
+            });
+        }
+
+        let mut values = Vec::new();
+        let mut next = 0i64;
+        for enumerator in &et.enumerators {
+            let name = enumerator.node.identifier.node.name.clone();
+            let value = match &enumerator.node.expression {
+                Some(expr) => eval_expression(&expr.node, self)
+                    .and_then(|v| v.as_int())
+                    .ok_or_else(|| format!("enumerator '{name}' does not have a constant value"))?,
+                None => next,
+            };
+            next = value.wrapping_add(1);
+            self.values.insert(name.clone(), Value::Int(value));
+            values.push((name, value));
+        }
+
+        // Enums from dependencies are not defined, so they are passed around as plain integers
+        if !self.own {
+            return Ok(Ty::Int(String::from("int")));
+        }
+        let Some(name) = hint.map(ToString::to_string).or(tag.clone()) else {
+            self.constants(values);
+            return Ok(Ty::Int(String::from("int")));
+        };
+
+        let tag = tag.unwrap_or_else(|| name.clone());
+        let body = values
+            .iter()
+            .map(|(name, value)| format!("\t{name} = {value}"))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        self.out
+            .cdef
+            .push(format!("typedef enum {tag} {{\n{body}\n}} {name};"));
+        self.enums.insert(tag);
+        self.enums.insert(name.clone());
+        self.constants(values);
+        Ok(Ty::Enum(name))
+    }
+
+    fn constants(&mut self, values: Vec<(String, i64)>) {
+        self.out.constants.extend(
+            values
+                .into_iter()
+                .map(|(name, value)| (name, Value::Int(value))),
+        );
+    }
+
+    /*
+        Typedefs and functions
+    */
+
+    fn typedef(&mut self, name: String, ty: Ty) {
+        if !self.own {
+            self.typedefs.insert(name, ty);
+            return;
+        }
+        match &ty {
+            // Already defined under the same name
+            Ty::Record(record) | Ty::Enum(record) if *record == name => {
+                self.typedefs.insert(name, ty);
+                return;
+            }
+            // Arrays and function types can't be typedefs in cdefs, so they are used as-is instead
+            Ty::Array(..) | Ty::Function(_) => {
+                self.typedefs.insert(name, ty);
+                return;
+            }
+            _ => {}
+        }
+
+        let declaration = if let Ty::Pointer { pointee, .. } = &ty
+            && let Ty::Function(signature) = pointee.as_ref()
+        {
+            for (_, param) in &signature.params {
+                self.require(param);
+            }
+            format!(
+                "typedef {} (*{name})({});",
+                signature.ret.spell(),
+                signature.spell_params(false)
+            )
+        } else {
+            self.require(&ty);
+            format!("typedef {};", declare(&ty, &name))
+        };
+        self.out.cdef.push(declaration);
+        self.typedefs
+            .insert(name.clone(), Ty::Named(name, Box::new(ty)));
+    }
+
+    fn function(&mut self, name: String, signature: Signature) {
+        if !self.own || !self.functions.insert(name.clone()) {
+            return;
+        }
+        self.require(&signature.ret);
+        for (_, param) in &signature.params {
+            self.require(param);
+        }
+        let declaration = format!(
+            "{} {name}({});",
+            signature.ret.spell(),
+            signature.spell_params(true)
+        );
+        self.out.cdef.push(declaration.clone());
+        self.out.functions.push(Function {
+            name,
+            signature,
+            declaration,
+        });
+    }
+
+    /*
+        Macros
+    */
+
+    /**
+        Evaluates the macros defined in the header, which may refer to each
+        other in any order, keeping the ones that are constant values.
+    */
+    fn macros(&mut self) {
+        let pre = self.pre;
+        let macros = pre.own_macros();
+        let mut evaluated = HashMap::new();
+        loop {
+            let mut progress = false;
+            for (name, text) in &macros {
+                if evaluated.contains_key(name) || self.values.contains_key(*name) {
+                    continue;
+                }
+                if let Some(value) = eval_macro(text, self) {
+                    self.values.insert((*name).to_string(), value.clone());
+                    evaluated.insert(*name, value);
+                    progress = true;
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+        for (name, _) in macros {
+            if let Some(value) = evaluated.remove(name) {
+                self.out.constants.push((name.to_string(), value));
+            }
+        }
+    }
+}
+
+/**
+    Returns the name being declared by a declarator, if it has one.
+*/
+fn identifier(declarator: &Declarator) -> Option<String> {
+    match &declarator.kind.node {
+        DeclaratorKind::Abstract => None,
+        DeclaratorKind::Identifier(id) => Some(id.node.name.clone()),
+        DeclaratorKind::Declarator(inner) => identifier(&inner.node),
+    }
+}
@@ -0,0 +1,263 @@
+//! Writes the generated Luau module - the cdefs, constants and a wrapper for every function.
+
+use std::{collections::HashSet, fmt::Write};
+
+use crate::{
+    BindgenOptions,
+    collect::{Collected, Function, Ty},
+    eval::Value,
+};
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "continue", "do", "else", "elseif", "end", "false", "for", "function", "if",
+    "in", "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/**
+    How a C type is passed to and returned from the wrappers.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Void,
+    Number,
+    Boolean,
+    String,
+    Other,
+}
+
+impl Kind {
+    fn of(ty: &Ty) -> Self {
+        match ty.resolved() {
+            Ty::Void => Self::Void,
+            Ty::Bool => Self::Boolean,
+            Ty::Int(_) | Ty::Float(_) | Ty::Enum(_) => Self::Number,
+            Ty::Pointer { pointee, is_const } => match pointee.resolved() {
+                Ty::Int(name) if *is_const && name == "char" => Self::String,
+                _ => Self::Other,
+            },
+            _ => Self::Other,
+        }
+    }
+
+    fn annotation(self) -> &'static str {
+        match self {
+            Self::Void => "()",
+            Self::Number => "number",
+            Self::Boolean => "boolean",
+            Self::String => "string?",
+            Self::Other => "any",
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/**
+    Returns how to index a table with a key, such as `.name` or `["end"]`.
+*/
+fn index(name: &str) -> String {
+    if is_identifier(name) {
+        format!(".{name}")
+    } else {
+        format!("[{}]", lua_string(name))
+    }
+}
+
+fn lua_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/**
+    Wraps text in a long string, such as `[[text]]`, with as many `=` as it needs to not end early.
+*/
+fn long_string(text: &str) -> String {
+    let mut level = 0;
+    while text.contains(&format!("]{}]", "=".repeat(level))) {
+        level += 1;
+    }
+    let equals = "=".repeat(level);
+    format!("[{equals}[\n{text}\n]{equals}]")
+}
+
+fn lua_value(value: &Value) -> String {
+    match value {
+        Value::Int(i) => i.to_string(),
+        Value::Float(f) if f.is_nan() => String::from("0 / 0"),
+        Value::Float(f) if f.is_infinite() && *f > 0.0 => String::from("math.huge"),
+        Value::Float(f) if f.is_infinite() => String::from("-math.huge"),
+        Value::Float(f) => format!("{f:?}"),
+        Value::Str(s) => lua_string(s),
+    }
+}
+
+/**
+    Finds the prefix that all functions share, up to and including its last underscore,
+    such as `sqlite3_` for `sqlite3_open` and `sqlite3_close`.
+*/
+fn common_prefix(functions: &[Function]) -> String {
+    let mut names = functions.iter().map(|f| f.name.as_str());
+    let (Some(first), true) = (names.next(), functions.len() > 1) else {
+        return String::new();
+    };
+    let mut prefix = first;
+    for name in names {
+        let len = prefix
+            .bytes()
+            .zip(name.bytes())
+            .take_while(|(a, b)| a == b)
+            .count();
+        prefix = &prefix[..len];
+    }
+    match prefix.rfind('_') {
+        Some(end) => prefix[..=end].to_string(),
+        None => String::new(),
+    }
+}
+
+/**
+    Writes the Luau module for everything collected from a header.
+*/
+pub fn module(collected: &Collected, header_name: &str, options: &BindgenOptions) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "-- Generated by `lux bindgen` from {header_name}");
+    let _ = writeln!(
+        out,
+        "-- Regenerate this file instead of editing it, since any changes will be lost"
+    );
+    if !collected.skipped.is_empty() {
+        let _ = writeln!(out, "--\n-- Skipped declarations:");
+        for skipped in &collected.skipped {
+            let _ = writeln!(out, "--   {skipped}");
+        }
+    }
+    let _ = writeln!(out, "\nlocal ffi = require(\"@lux/ffi\")\n");
+
+    if !collected.cdef.is_empty() {
+        let _ = writeln!(
+            out,
+            "ffi.cdef({})\n",
+            long_string(&collected.cdef.join("\n"))
+        );
+    }
+
+    let has_functions = !collected.functions.is_empty();
+    if has_functions {
+        match &options.lib {
+            Some(lib) => {
+                let _ = writeln!(out, "local lib = ffi.load({})\n", lua_string(lib));
+            }
+            None => {
+                let _ = writeln!(out, "local lib = ffi.C\n");
+            }
+        }
+        let _ = writeln!(out, "local bindings = {{\n\tlib = lib,\n}}");
+    } else {
+        let _ = writeln!(out, "local bindings = {{}}");
+    }
+
+    let mut taken = HashSet::from([String::from("lib")]);
+    if !collected.constants.is_empty() {
+        let _ = writeln!(out, "\n-- Constants");
+        for (name, value) in &collected.constants {
+            taken.insert(name.clone());
+            let _ = writeln!(out, "bindings{} = {}", index(name), lua_value(value));
+        }
+    }
+
+    if has_functions {
+        let prefix = options
+            .prefix
+            .clone()
+            .unwrap_or_else(|| common_prefix(&collected.functions));
+        taken.extend(collected.functions.iter().map(|f| f.name.clone()));
+
+        let _ = writeln!(out, "\n-- Functions");
+        for function in &collected.functions {
+            let short = function
+                .name
+                .strip_prefix(prefix.as_str())
+                .filter(|short| is_identifier(short) && !taken.contains(*short));
+            let name = match short {
+                Some(short) => {
+                    taken.insert(short.to_string());
+                    short
+                }
+                None => function.name.as_str(),
+            };
+            out.push('\n');
+            write_wrapper(&mut out, name, function);
+        }
+    }
+
+    let _ = write!(out, "\nreturn bindings\n");
+    out
+}
+
+fn write_wrapper(out: &mut String, name: &str, function: &Function) {
+    let signature = &function.signature;
+
+    let mut used = HashSet::new();
+    let mut params = Vec::new();
+    let mut args = Vec::new();
+    for (param, ty) in &signature.params {
+        let mut param = param.clone();
+        while KEYWORDS.contains(&param.as_str()) || !used.insert(param.clone()) {
+            param.push('_');
+        }
+        let kind = Kind::of(ty);
+        args.push(match kind {
+            Kind::Boolean => format!("if {param} then 1 else 0"),
+            _ => param.clone(),
+        });
+        params.push(format!("{param}: {}", kind.annotation()));
+    }
+    if signature.variadic {
+        params.push(String::from("...: any"));
+        args.push(String::from("..."));
+    }
+
+    let call = format!("lib{}({})", index(&function.name), args.join(", "));
+    let ret = Kind::of(&signature.ret);
+    let body = match ret {
+        Kind::Void => call,
+        Kind::Boolean => format!("return {call} ~= 0"),
+        Kind::String => format!("return ffi.string({call})"),
+        Kind::Number | Kind::Other => format!("return {call}"),
+    };
+
+    let _ = writeln!(out, "--- `{}`", function.declaration);
+    let params = params.join(", ");
+    let ret = match ret {
+        Kind::Void => String::new(),
+        ret => format!(": {}", ret.annotation()),
+    };
+    if is_identifier(name) {
+        let _ = writeln!(out, "function bindings.{name}({params}){ret}");
+    } else {
+        let _ = writeln!(
+            out,
+            "bindings[{}] = function({params}){ret}",
+            lua_string(name)
+        );
+    }
+    let _ = writeln!(out, "\t{body}\nend");
+}
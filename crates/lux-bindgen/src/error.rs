@@ -0,0 +1,40 @@
+use std::{error::Error, fmt};
+
+/**
+    An error that prevented bindings from being generated for a header.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindgenError {
+    /// No C compiler could be found to preprocess the header with.
+    NoCompiler,
+    /// The preprocessor failed, usually because of a missing include or a `#error`.
+    Preprocessor(String),
+    /// The preprocessed header contained code that could not be parsed.
+    Syntax {
+        file: String,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for BindgenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoCompiler => write!(
+                f,
+                "No C compiler was found to preprocess the header with, \
+                make sure that clang or gcc is installed or set the CC environment variable"
+            ),
+            Self::Preprocessor(message) => write!(f, "Failed to preprocess header - {message}"),
+            Self::Syntax {
+                file,
+                line,
+                column,
+                message,
+            } => write!(f, "{file}:{line}:{column}: {message}"),
+        }
+    }
+}
+
+impl Error for BindgenError {}
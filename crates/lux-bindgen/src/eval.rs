@@ -0,0 +1,706 @@
+//! Evaluation of C constant expressions, for enum values, array sizes and
+//! macros, which may refer to other constants and use casts and operators.
+
+use lang_c::ast::{
+    BinaryOperator, Constant, Expression, FloatBase, IntegerBase, TypeSpecifier, UnaryOperator,
+};
+
+/**
+    The value of a constant expression.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl Value {
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Self::Int(i) => Some(*i),
+            Self::Float(f) => Some(*f as i64),
+            Self::Str(_) => None,
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn as_float(&self) -> Option<f64> {
+        match self {
+            Self::Int(i) => Some(*i as f64),
+            Self::Float(f) => Some(*f),
+            Self::Str(_) => None,
+        }
+    }
+
+    fn is_truthy(&self) -> Option<bool> {
+        match self {
+            Self::Int(i) => Some(*i != 0),
+            Self::Float(f) => Some(*f != 0.0),
+            Self::Str(_) => None,
+        }
+    }
+}
+
+/**
+    The kind of number that a cast converts a value into.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Numeric {
+    Int,
+    Float,
+}
+
+impl Numeric {
+    fn cast(self, value: &Value) -> Option<Value> {
+        match self {
+            Self::Int => value.as_int().map(Value::Int),
+            Self::Float => value.as_float().map(Value::Float),
+        }
+    }
+}
+
+/**
+    Looks up names while evaluating expressions - constants and the numeric types that casts may use.
+*/
+pub trait Scope {
+    fn constant(&self, name: &str) -> Option<Value>;
+    fn numeric_type(&self, name: &str) -> Option<Numeric>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unary {
+    Plus,
+    Minus,
+    Complement,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Binary {
+    Mul,
+    Div,
+    Mod,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Eq,
+    Ne,
+    BitAnd,
+    BitXor,
+    BitOr,
+    And,
+    Or,
+}
+
+impl Binary {
+    /// Binding power of the operator, higher binds tighter
+    fn precedence(self) -> u8 {
+        match self {
+            Self::Mul | Self::Div | Self::Mod => 10,
+            Self::Add | Self::Sub => 9,
+            Self::Shl | Self::Shr => 8,
+            Self::Lt | Self::Gt | Self::Le | Self::Ge => 7,
+            Self::Eq | Self::Ne => 6,
+            Self::BitAnd => 5,
+            Self::BitXor => 4,
+            Self::BitOr => 3,
+            Self::And => 2,
+            Self::Or => 1,
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        Some(match token {
+            "*" => Self::Mul,
+            "/" => Self::Div,
+            "%" => Self::Mod,
+            "+" => Self::Add,
+            "-" => Self::Sub,
+            "<<" => Self::Shl,
+            ">>" => Self::Shr,
+            "<" => Self::Lt,
+            ">" => Self::Gt,
+            "<=" => Self::Le,
+            ">=" => Self::Ge,
+            "==" => Self::Eq,
+            "!=" => Self::Ne,
+            "&" => Self::BitAnd,
+            "^" => Self::BitXor,
+            "|" => Self::BitOr,
+            "&&" => Self::And,
+            "||" => Self::Or,
+            _ => return None,
+        })
+    }
+}
+
+fn unary(op: Unary, value: &Value) -> Option<Value> {
+    Some(match (op, value) {
+        (Unary::Plus, Value::Int(_) | Value::Float(_)) => value.clone(),
+        (Unary::Minus, Value::Int(i)) => Value::Int(i.wrapping_neg()),
+        (Unary::Minus, Value::Float(f)) => Value::Float(-f),
+        (Unary::Complement, Value::Int(i)) => Value::Int(!i),
+        (Unary::Not, _) => Value::Int(i64::from(!value.is_truthy()?)),
+        _ => return None,
+    })
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::float_cmp
+)]
+fn binary(op: Binary, lhs: &Value, rhs: &Value) -> Option<Value> {
+    let truth = |b: bool| Some(Value::Int(i64::from(b)));
+    match op {
+        Binary::And => return truth(lhs.is_truthy()? && rhs.is_truthy()?),
+        Binary::Or => return truth(lhs.is_truthy()? || rhs.is_truthy()?),
+        _ => {}
+    }
+    if let (Value::Int(a), Value::Int(b)) = (lhs, rhs) {
+        let (a, b) = (*a, *b);
+        return Some(Value::Int(match op {
+            Binary::Mul => a.wrapping_mul(b),
+            Binary::Div => a.checked_div(b)?,
+            Binary::Mod => a.checked_rem(b)?,
+            Binary::Add => a.wrapping_add(b),
+            Binary::Sub => a.wrapping_sub(b),
+            Binary::Shl => a.wrapping_shl(b as u32),
+            Binary::Shr => a.wrapping_shr(b as u32),
+            Binary::Lt => i64::from(a < b),
+            Binary::Gt => i64::from(a > b),
+            Binary::Le => i64::from(a <= b),
+            Binary::Ge => i64::from(a >= b),
+            Binary::Eq => i64::from(a == b),
+            Binary::Ne => i64::from(a != b),
+            Binary::BitAnd => a & b,
+            Binary::BitXor => a ^ b,
+            Binary::BitOr => a | b,
+            Binary::And | Binary::Or => unreachable!(),
+        }));
+    }
+    let (a, b) = (lhs.as_float()?, rhs.as_float()?);
+    match op {
+        Binary::Mul => Some(Value::Float(a * b)),
+        Binary::Div => Some(Value::Float(a / b)),
+        Binary::Add => Some(Value::Float(a + b)),
+        Binary::Sub => Some(Value::Float(a - b)),
+        Binary::Lt => truth(a < b),
+        Binary::Gt => truth(a > b),
+        Binary::Le => truth(a <= b),
+        Binary::Ge => truth(a >= b),
+        Binary::Eq => truth(a == b),
+        Binary::Ne => truth(a != b),
+        _ => None,
+    }
+}
+
+/**
+    Parses an integer literal such as `0x1F`, `010` or `42ull`.
+*/
+#[allow(clippy::cast_possible_wrap)]
+fn parse_int(literal: &str) -> Option<i64> {
+    let literal = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (digits, radix) = if let Some(hex) = literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if let Some(bin) = literal
+        .strip_prefix("0b")
+        .or_else(|| literal.strip_prefix("0B"))
+    {
+        (bin, 2)
+    } else if literal.len() > 1 && literal.starts_with('0') {
+        (&literal[1..], 8)
+    } else {
+        (literal, 10)
+    };
+    // NOTE: Unsigned values above i64::MAX wrap around, the same as casting them would
+    u64::from_str_radix(digits, radix).ok().map(|n| n as i64)
+}
+
+/**
+    Parses a decimal floating point literal such as `1.5f` or `1e-3`.
+*/
+fn parse_float(literal: &str) -> Option<f64> {
+    literal.trim_end_matches(['f', 'F', 'l', 'L']).parse().ok()
+}
+
+/**
+    Unescapes the contents of a character or string literal, without its quotes.
+*/
+fn unescape(contents: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let escaped = match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'a' => '\x07',
+            'b' => '\x08',
+            'f' => '\x0C',
+            'v' => '\x0B',
+            'x' => {
+                let mut code = 0u32;
+                while let Some(digit) = chars.peek().and_then(|c| c.to_digit(16)) {
+                    code = code.checked_mul(16)? + digit;
+                    chars.next();
+                }
+                char::from_u32(code)?
+            }
+            c @ '0'..='7' => {
+                let mut code = c.to_digit(8)?;
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => code = code * 8 + digit,
+                        None => break,
+                    }
+                    chars.next();
+                }
+                char::from_u32(code)?
+            }
+            c => c,
+        };
+        out.push(escaped);
+    }
+    Some(out)
+}
+
+/**
+    Returns the contents of a quoted literal, without any encoding prefix such as `L` or `u8`.
+*/
+fn quoted(literal: &str, quote: char) -> Option<&str> {
+    let start = literal.find(quote)?;
+    literal[start + 1..].strip_suffix(quote)
+}
+
+fn parse_char(literal: &str) -> Option<i64> {
+    let contents = unescape(quoted(literal, '\'')?)?;
+    let mut chars = contents.chars();
+    let c = chars.next()?;
+    chars.next().is_none().then(|| i64::from(u32::from(c)))
+}
+
+/*
+    Expressions from the parsed header
+*/
+
+/**
+    Evaluates an expression from the parsed header, returning `None` if it is not a constant.
+*/
+pub fn eval_expression(expr: &Expression, scope: &dyn Scope) -> Option<Value> {
+    match expr {
+        Expression::Identifier(id) => scope.constant(&id.node.name),
+        Expression::Constant(constant) => match &constant.node {
+            Constant::Integer(int) => {
+                let digits = match int.base {
+                    IntegerBase::Decimal => format!("{}", int.number),
+                    IntegerBase::Hexadecimal => format!("0x{}", int.number),
+                    IntegerBase::Octal => format!("0{}", int.number),
+                    IntegerBase::Binary => format!("0b{}", int.number),
+                };
+                parse_int(&digits).map(Value::Int)
+            }
+            Constant::Float(float) => match float.base {
+                FloatBase::Decimal => parse_float(&float.number).map(Value::Float),
+                FloatBase::Hexadecimal => None,
+            },
+            Constant::Character(c) => parse_char(c).map(Value::Int),
+        },
+        Expression::StringLiteral(literal) => {
+            let mut out = String::new();
+            for part in &literal.node {
+                out.push_str(&unescape(quoted(part, '"')?)?);
+            }
+            Some(Value::Str(out))
+        }
+        Expression::UnaryOperator(unary_expr) => {
+            let op = match unary_expr.node.operator.node {
+                UnaryOperator::Plus => Unary::Plus,
+                UnaryOperator::Minus => Unary::Minus,
+                UnaryOperator::Complement => Unary::Complement,
+                UnaryOperator::Negate => Unary::Not,
+                _ => return None,
+            };
+            unary(op, &eval_expression(&unary_expr.node.operand.node, scope)?)
+        }
+        Expression::BinaryOperator(binary_expr) => {
+            let op = match binary_expr.node.operator.node {
+                BinaryOperator::Multiply => Binary::Mul,
+                BinaryOperator::Divide => Binary::Div,
+                BinaryOperator::Modulo => Binary::Mod,
+                BinaryOperator::Plus => Binary::Add,
+                BinaryOperator::Minus => Binary::Sub,
+                BinaryOperator::ShiftLeft => Binary::Shl,
+                BinaryOperator::ShiftRight => Binary::Shr,
+                BinaryOperator::Less => Binary::Lt,
+                BinaryOperator::Greater => Binary::Gt,
+                BinaryOperator::LessOrEqual => Binary::Le,
+                BinaryOperator::GreaterOrEqual => Binary::Ge,
+                BinaryOperator::Equals => Binary::Eq,
+                BinaryOperator::NotEquals => Binary::Ne,
+                BinaryOperator::BitwiseAnd => Binary::BitAnd,
+                BinaryOperator::BitwiseXor => Binary::BitXor,
+                BinaryOperator::BitwiseOr => Binary::BitOr,
+                BinaryOperator::LogicalAnd => Binary::And,
+                BinaryOperator::LogicalOr => Binary::Or,
+                _ => return None,
+            };
+            let lhs = eval_expression(&binary_expr.node.lhs.node, scope)?;
+            let rhs = eval_expression(&binary_expr.node.rhs.node, scope)?;
+            binary(op, &lhs, &rhs)
+        }
+        Expression::Conditional(cond) => {
+            let condition = eval_expression(&cond.node.condition.node, scope)?;
+            if condition.is_truthy()? {
+                eval_expression(&cond.node.then_expression.node, scope)
+            } else {
+                eval_expression(&cond.node.else_expression.node, scope)
+            }
+        }
+        Expression::Cast(cast) => {
+            let type_name = &cast.node.type_name.node;
+            if type_name.declarator.is_some() {
+                return None; // Pointer casts
+            }
+            let mut numeric = Numeric::Int;
+            for specifier in &type_name.specifiers {
+                if let lang_c::ast::SpecifierQualifier::TypeSpecifier(ts) = &specifier.node {
+                    match &ts.node {
+                        TypeSpecifier::Float | TypeSpecifier::Double => numeric = Numeric::Float,
+                        TypeSpecifier::TypedefName(name) => {
+                            numeric = scope.numeric_type(&name.node.name)?;
+                        }
+                        TypeSpecifier::Void
+                        | TypeSpecifier::Struct(_)
+                        | TypeSpecifier::Enum(_)
+                        | TypeSpecifier::TypeOf(_) => return None,
+                        _ => {}
+                    }
+                }
+            }
+            numeric.cast(&eval_expression(&cast.node.expression.node, scope)?)
+        }
+        _ => None,
+    }
+}
+
+/*
+    Macro values, which are only available as text
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Value(Value),
+    Ident(String),
+    Punct(&'static str),
+}
+
+const PUNCTUATION: &[&str] = &[
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "(", ")", "*", "/", "%", "+", "-", "<", ">",
+    "&", "^", "|", "~", "!", "?", ":",
+];
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let c = rest.chars().next()?;
+        let len = if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let mut len = 0;
+            let bytes = rest.as_bytes();
+            while len < bytes.len() {
+                let b = bytes[len];
+                let exponent_sign = (b == b'+' || b == b'-')
+                    && len > 0
+                    && matches!(bytes[len - 1], b'e' | b'E')
+                    && !rest.starts_with("0x")
+                    && !rest.starts_with("0X");
+                if b.is_ascii_alphanumeric() || b == b'.' || exponent_sign {
+                    len += 1;
+                } else {
+                    break;
+                }
+            }
+            let literal = &rest[..len];
+            let is_hex = literal.starts_with("0x") || literal.starts_with("0X");
+            let value = if !is_hex && literal.contains(['.', 'e', 'E']) {
+                Value::Float(parse_float(literal)?)
+            } else {
+                Value::Int(parse_int(literal)?)
+            };
+            tokens.push(Token::Value(value));
+            len
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            // Encoding prefixes such as L"..." and u8'...'
+            if rest[len..].starts_with(['"', '\''])
+                && matches!(&rest[..len], "L" | "u" | "U" | "u8")
+            {
+                rest = &rest[len..];
+                continue;
+            }
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            len
+        } else if c == '"' || c == '\'' {
+            let mut end = None;
+            let mut escaped = false;
+            for (i, ch) in rest.char_indices().skip(1) {
+                match ch {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    ch if ch == c => {
+                        end = Some(i + 1);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let end = end?;
+            let literal = &rest[..end];
+            if c == '\'' {
+                tokens.push(Token::Value(Value::Int(parse_char(literal)?)));
+            } else {
+                let contents = unescape(quoted(literal, '"')?)?;
+                // Adjacent string literals are joined together
+                if let Some(Token::Value(Value::Str(previous))) = tokens.last_mut() {
+                    previous.push_str(&contents);
+                } else {
+                    tokens.push(Token::Value(Value::Str(contents)));
+                }
+            }
+            end
+        } else {
+            let punct = PUNCTUATION.iter().find(|p| rest.starts_with(**p))?;
+            tokens.push(Token::Punct(punct));
+            punct.len()
+        };
+        rest = rest[len..].trim_start();
+    }
+    Some(tokens)
+}
+
+/**
+    Type names that may appear in casts, which may span multiple tokens such as `unsigned long`.
+*/
+fn keyword_numeric(name: &str) -> Option<Numeric> {
+    match name {
+        "char" | "short" | "int" | "long" | "signed" | "unsigned" | "_Bool" | "const" => {
+            Some(Numeric::Int)
+        }
+        "float" | "double" => Some(Numeric::Float),
+        _ => None,
+    }
+}
+
+struct MacroParser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    scope: &'a dyn Scope,
+}
+
+impl MacroParser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_punct(&self) -> Option<&'static str> {
+        match self.peek()? {
+            Token::Punct(p) => Some(p),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Option<()> {
+        (self.next()? == Token::Punct(punct)).then_some(())
+    }
+
+    fn expression(&mut self) -> Option<Value> {
+        let condition = self.binary(0)?;
+        if self.peek_punct() != Some("?") {
+            return Some(condition);
+        }
+        self.next();
+        let then_value = self.expression()?;
+        self.expect(":")?;
+        let else_value = self.expression()?;
+        Some(if condition.is_truthy()? {
+            then_value
+        } else {
+            else_value
+        })
+    }
+
+    fn binary(&mut self, min_precedence: u8) -> Option<Value> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peek_punct().and_then(Binary::from_token) {
+            if op.precedence() <= min_precedence {
+                break;
+            }
+            self.next();
+            let rhs = self.binary(op.precedence())?;
+            lhs = binary(op, &lhs, &rhs)?;
+        }
+        Some(lhs)
+    }
+
+    fn unary(&mut self) -> Option<Value> {
+        let op = match self.peek_punct() {
+            Some("+") => Unary::Plus,
+            Some("-") => Unary::Minus,
+            Some("~") => Unary::Complement,
+            Some("!") => Unary::Not,
+            Some("(") => return self.parenthesized(),
+            _ => return self.primary(),
+        };
+        self.next();
+        unary(op, &self.unary()?)
+    }
+
+    /**
+        Parses either a cast such as `(unsigned int)1` or a parenthesized expression.
+    */
+    fn parenthesized(&mut self) -> Option<Value> {
+        self.next();
+        let start = self.position;
+        let mut numeric = None;
+        while let Some(Token::Ident(name)) = self.peek() {
+            let kind = keyword_numeric(name).or_else(|| self.scope.numeric_type(name));
+            match (kind, numeric) {
+                (Some(kind), None | Some(Numeric::Int)) => numeric = Some(kind),
+                (Some(_), Some(Numeric::Float)) => {}
+                (None, _) => break,
+            }
+            self.next();
+        }
+        if let Some(numeric) = numeric
+            && self.peek_punct() == Some(")")
+        {
+            self.next();
+            return numeric.cast(&self.unary()?);
+        }
+        self.position = start;
+        let value = self.expression()?;
+        self.expect(")")?;
+        Some(value)
+    }
+
+    fn primary(&mut self) -> Option<Value> {
+        match self.next()? {
+            Token::Value(value) => Some(value),
+            Token::Ident(name) => self.scope.constant(&name),
+            Token::Punct(_) => None,
+        }
+    }
+}
+
+/**
+    Evaluates the value of an object-like macro, returning `None` if it is not a constant.
+*/
+pub fn eval_macro(text: &str, scope: &dyn Scope) -> Option<Value> {
+    let mut parser = MacroParser {
+        tokens: tokenize(text)?,
+        position: 0,
+        scope,
+    };
+    let value = parser.expression()?;
+    (parser.position == parser.tokens.len()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    struct TestScope(HashMap<&'static str, Value>);
+
+    impl Scope for TestScope {
+        fn constant(&self, name: &str) -> Option<Value> {
+            self.0.get(name).cloned()
+        }
+
+        fn numeric_type(&self, name: &str) -> Option<Numeric> {
+            match name {
+                "uint32_t" => Some(Numeric::Int),
+                "real" => Some(Numeric::Float),
+                _ => None,
+            }
+        }
+    }
+
+    fn eval(text: &str) -> Option<Value> {
+        let scope = TestScope(HashMap::from([("A", Value::Int(4)), ("B", Value::Int(1))]));
+        eval_macro(text, &scope)
+    }
+
+    #[test]
+    fn literals() {
+        assert_eq!(eval("42"), Some(Value::Int(42)));
+        assert_eq!(eval("0x1Fu"), Some(Value::Int(31)));
+        assert_eq!(eval("010"), Some(Value::Int(8)));
+        assert_eq!(eval("0b101"), Some(Value::Int(5)));
+        assert_eq!(eval("1.5f"), Some(Value::Float(1.5)));
+        assert_eq!(eval("1e-3"), Some(Value::Float(0.001)));
+        assert_eq!(eval("'A'"), Some(Value::Int(65)));
+        assert_eq!(eval(r"'\n'"), Some(Value::Int(10)));
+        assert_eq!(
+            eval(r#""a\tb" "c""#),
+            Some(Value::Str(String::from("a\tbc")))
+        );
+        assert_eq!(eval(r#"L"wide""#), Some(Value::Str(String::from("wide"))));
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(eval("1 + 2 * 3"), Some(Value::Int(7)));
+        assert_eq!(eval("(1 + 2) * 3"), Some(Value::Int(9)));
+        assert_eq!(eval("1 << 4 | 1"), Some(Value::Int(17)));
+        assert_eq!(eval("-(A)"), Some(Value::Int(-4)));
+        assert_eq!(eval("~0"), Some(Value::Int(-1)));
+        assert_eq!(eval("A > B ? A : B"), Some(Value::Int(4)));
+        assert_eq!(eval("A == 4 && !B"), Some(Value::Int(0)));
+        assert_eq!(eval("1 / 0"), None);
+    }
+
+    #[test]
+    fn casts() {
+        assert_eq!(eval("(uint32_t)A"), Some(Value::Int(4)));
+        assert_eq!(eval("(unsigned long)(A + B)"), Some(Value::Int(5)));
+        assert_eq!(eval("(real)1"), Some(Value::Float(1.0)));
+        assert_eq!(eval("(int)2.5"), Some(Value::Int(2)));
+    }
+
+    #[test]
+    fn non_constants() {
+        assert_eq!(eval(""), None);
+        assert_eq!(eval("UNKNOWN"), None);
+        assert_eq!(eval("__attribute__((visibility(\"default\")))"), None);
+        assert_eq!(eval("extern"), None);
+        assert_eq!(eval("(void*)0"), None);
+        assert_eq!(eval("1 2"), None);
+    }
+}
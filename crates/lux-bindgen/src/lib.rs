@@ -0,0 +1,169 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::path::{Path, PathBuf};
+
+use lang_c::driver::{Config, parse_preprocessed};
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod collect;
+mod emit;
+mod error;
+mod eval;
+mod preprocess;
+
+pub use self::error::BindgenError;
+
+use self::preprocess::Preprocessed;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `bindgen` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `bindgen` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_async_function("generate", bindgen_generate)?
+        .build_readonly()
+}
+
+/**
+    Options for generating bindings from a header.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct BindgenOptions {
+    /// The library to load functions from, or `None` to look them up in the running process
+    pub lib: Option<String>,
+    /// Directories to search for included headers in
+    pub include_paths: Vec<PathBuf>,
+    /// Macros to define before preprocessing, with an optional value
+    pub defines: Vec<(String, Option<String>)>,
+    /// The prefix to strip from function names, found automatically if not given
+    pub prefix: Option<String>,
+}
+
+impl FromLua for BindgenOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let t = match value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::Table(t) => t,
+            other => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: other.type_name(),
+                    to: String::from("BindgenOptions"),
+                    message: Some(String::from("Expected options to be a table")),
+                });
+            }
+        };
+
+        let include_paths = t
+            .get::<Option<Vec<String>>>("includePaths")?
+            .unwrap_or_default()
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+
+        let mut defines = Vec::new();
+        if let Some(table) = t.get::<Option<LuaTable>>("defines")? {
+            for pair in table.pairs::<String, LuaValue>() {
+                let (name, value) = pair?;
+                match value {
+                    LuaValue::Boolean(false) => {}
+                    LuaValue::Boolean(true) => defines.push((name, None)),
+                    LuaValue::String(s) => defines.push((name, Some(s.to_str()?.to_string()))),
+                    LuaValue::Integer(i) => defines.push((name, Some(i.to_string()))),
+                    LuaValue::Number(n) => defines.push((name, Some(n.to_string()))),
+                    other => {
+                        return Err(LuaError::runtime(format!(
+                            "Expected define '{name}' to be a string, number or boolean, got {}",
+                            other.type_name()
+                        )));
+                    }
+                }
+            }
+            // NOTE: Table iteration order is unspecified, sort for reproducible output
+            defines.sort();
+        }
+
+        Ok(Self {
+            lib: t.get("lib")?,
+            include_paths,
+            defines,
+            prefix: t.get("prefix")?,
+        })
+    }
+}
+
+/**
+    Bindings generated from a header.
+*/
+#[derive(Debug, Clone)]
+pub struct Bindings {
+    /// The source code of the generated Luau module
+    pub source: String,
+    /// Declarations that could not be bound, along with why
+    pub skipped: Vec<String>,
+}
+
+/**
+    Generates a Luau module with bindings for a C header, containing its declarations
+    for `ffi.cdef`, its constants and enums, and wrappers for all of its functions.
+
+    The header is preprocessed by the system C compiler, which must be installed.
+
+    # Errors
+
+    Errors if the header could not be preprocessed or parsed.
+*/
+pub fn generate(header: &Path, options: &BindgenOptions) -> Result<Bindings, BindgenError> {
+    let pre = Preprocessed::run(header, options)?;
+
+    let config = Config {
+        flavor: pre.flavor,
+        ..Config::default()
+    };
+    let parsed = parse_preprocessed(&config, pre.source.clone()).map_err(|e| {
+        let (file, line) = pre.location(e.offset);
+        let mut expected = e.expected.into_iter().collect::<Vec<_>>();
+        expected.sort_unstable();
+        BindgenError::Syntax {
+            file: file.to_string(),
+            line,
+            column: e.column,
+            message: format!("Expected one of {}", expected.join(", ")),
+        }
+    })?;
+
+    let collected = collect::collect(&parsed.unit, &pre);
+    let header_name = header.file_name().map_or_else(
+        || header.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    Ok(Bindings {
+        source: emit::module(&collected, &header_name, options),
+        skipped: collected.skipped,
+    })
+}
+
+async fn bindgen_generate(
+    _: Lua,
+    (header, options): (String, BindgenOptions),
+) -> LuaResult<(String, Vec<String>)> {
+    let bindings = blocking::unblock(move || generate(Path::new(&header), &options))
+        .await
+        .into_lua_err()?;
+    Ok((bindings.source, bindings.skipped))
+}
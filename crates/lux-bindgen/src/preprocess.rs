@@ -0,0 +1,269 @@
+//! Runs the system C preprocessor, which resolves includes, conditionals
+//! and macros so that only plain C declarations are left to be parsed.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::ErrorKind,
+    path::Path,
+    process::Command,
+};
+
+use lang_c::driver::Flavor;
+
+use crate::{BindgenError, BindgenOptions};
+
+const COMPILERS: &[&str] = &["clang", "gcc", "cc"];
+
+/**
+    A `# <line> "<file>" <flags>` marker left by the preprocessor,
+    saying where the lines following it originally came from.
+*/
+struct Marker {
+    offset: usize,
+    line: usize,
+    file: usize,
+}
+
+struct SourceFile {
+    path: String,
+    own: bool,
+}
+
+/**
+    A preprocessed header, along with the macros that were defined
+    after preprocessing it and where each of its lines came from.
+*/
+pub struct Preprocessed {
+    pub source: String,
+    pub flavor: Flavor,
+    macros: HashMap<String, String>,
+    markers: Vec<Marker>,
+    files: Vec<SourceFile>,
+}
+
+impl Preprocessed {
+    /**
+        Preprocesses a header using the first C compiler that is installed,
+        preferring the one set in the `CC` environment variable.
+    */
+    pub fn run(header: &Path, options: &BindgenOptions) -> Result<Self, BindgenError> {
+        let mut args = vec![String::from("-E")];
+        for path in &options.include_paths {
+            args.push(format!("-I{}", path.display()));
+        }
+        for (name, value) in &options.defines {
+            match value {
+                Some(value) => args.push(format!("-D{name}={value}")),
+                None => args.push(format!("-D{name}")),
+            }
+        }
+        args.push(header.display().to_string());
+
+        let candidates = std::env::var("CC")
+            .ok()
+            .filter(|cc| !cc.trim().is_empty())
+            .into_iter()
+            .chain(COMPILERS.iter().map(ToString::to_string));
+        for compiler in candidates {
+            let Some(source) = run_compiler(&compiler, &args)? else {
+                continue;
+            };
+
+            // NOTE: Listing macros replaces the regular output, so it needs a second run
+            args.insert(1, String::from("-dM"));
+            let defines = run_compiler(&compiler, &args)?.unwrap_or_default();
+
+            let flavor = if compiler.contains("clang") {
+                Flavor::ClangC11
+            } else {
+                Flavor::GnuC11
+            };
+            let (markers, files) = parse_markers(&source, header);
+            return Ok(Self {
+                source,
+                flavor,
+                macros: parse_defines(&defines),
+                markers,
+                files,
+            });
+        }
+
+        Err(BindgenError::NoCompiler)
+    }
+
+    /**
+        Returns the file and line that a byte offset in the preprocessed source came from.
+    */
+    pub fn location(&self, offset: usize) -> (&str, usize) {
+        let offset = offset.min(self.source.len());
+        let index = self.markers.partition_point(|m| m.offset <= offset);
+        let (start, line, file) = match index.checked_sub(1).map(|i| &self.markers[i]) {
+            Some(marker) => (
+                marker.offset,
+                marker.line,
+                self.files[marker.file].path.as_str(),
+            ),
+            None => (0, 1, "<unknown>"),
+        };
+        let lines = self.source[start..offset].matches('\n').count();
+        (file, line + lines)
+    }
+
+    /**
+        Returns whether a byte offset in the preprocessed source came from the header
+        itself, or from a non-system header next to it, as opposed to its dependencies.
+    */
+    pub fn is_own(&self, offset: usize) -> bool {
+        let index = self.markers.partition_point(|m| m.offset <= offset);
+        index
+            .checked_sub(1)
+            .is_some_and(|i| self.files[self.markers[i].file].own)
+    }
+
+    /**
+        Returns the object-like macros defined in the header and the headers next to
+        it, along with their values, in the order they were first defined in.
+    */
+    pub fn own_macros(&self) -> Vec<(&str, &str)> {
+        let mut seen = HashSet::new();
+        let mut macros = Vec::new();
+        for file in self.files.iter().filter(|f| f.own) {
+            let Ok(contents) = std::fs::read_to_string(&file.path) else {
+                continue;
+            };
+            for name in contents.lines().filter_map(defined_name) {
+                if let Some((name, value)) = self.macros.get_key_value(name)
+                    && !value.is_empty()
+                    && seen.insert(name.as_str())
+                {
+                    macros.push((name.as_str(), value.as_str()));
+                }
+            }
+        }
+        macros
+    }
+}
+
+/**
+    Runs a compiler with the given arguments, returning `None` if it is not installed.
+*/
+fn run_compiler(compiler: &str, args: &[String]) -> Result<Option<String>, BindgenError> {
+    match Command::new(compiler).args(args).output() {
+        Ok(output) if output.status.success() => {
+            Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+        }
+        Ok(output) => Err(BindgenError::Preprocessor(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        )),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(BindgenError::Preprocessor(format!(
+            "Failed to run '{compiler}' - {e}"
+        ))),
+    }
+}
+
+fn parse_markers(source: &str, header: &Path) -> (Vec<Marker>, Vec<SourceFile>) {
+    let header_dir = header
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf));
+
+    let mut markers = Vec::new();
+    let mut files: Vec<SourceFile> = Vec::new();
+    let mut indices = HashMap::new();
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        offset += line.len();
+        let Some((number, path, flags)) = parse_marker(line) else {
+            continue;
+        };
+        let file = *indices.entry(path.clone()).or_insert_with(|| {
+            // The first marker is always for the header itself
+            let own = files.is_empty()
+                || header_dir.as_ref().is_some_and(|dir| {
+                    Path::new(&path)
+                        .canonicalize()
+                        .is_ok_and(|path| path.starts_with(dir))
+                });
+            files.push(SourceFile { path, own });
+            files.len() - 1
+        });
+        // Headers next to the header may still be system headers, such as in /usr/include
+        if flags.contains(&3) && file != 0 {
+            files[file].own = false;
+        }
+        markers.push(Marker {
+            offset,
+            line: number,
+            file,
+        });
+    }
+    (markers, files)
+}
+
+/**
+    Parses a line marker, such as `# 12 "foo.h" 2`, into its line, file and flags.
+*/
+fn parse_marker(line: &str) -> Option<(usize, String, Vec<u8>)> {
+    let rest = line.trim().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("line").unwrap_or(rest).trim_start();
+    let (number, rest) = rest.split_once(char::is_whitespace)?;
+    let number = number.parse().ok()?;
+    let rest = rest.trim_start().strip_prefix('"')?;
+
+    let mut path = String::new();
+    let mut chars = rest.char_indices();
+    let mut end = None;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => path.extend(chars.next().map(|(_, c)| c)),
+            '"' => {
+                end = Some(i + 1);
+                break;
+            }
+            c => path.push(c),
+        }
+    }
+    let flags = rest[end?..]
+        .split_whitespace()
+        .filter_map(|flag| flag.parse().ok())
+        .collect();
+    Some((number, path, flags))
+}
+
+/**
+    Parses the output of `-dM` into the values of object-like macros.
+*/
+fn parse_defines(defines: &str) -> HashMap<String, String> {
+    defines
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("#define ")?;
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let (name, value) = rest.split_at(end);
+            if value.starts_with('(') {
+                return None; // Function-like macro
+            }
+            Some((name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/**
+    Returns the name of the object-like macro defined on a line, if any.
+*/
+fn defined_name(line: &str) -> Option<&str> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("define")?;
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let rest = rest.trim_start();
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let (name, after) = rest.split_at(end);
+    (!name.is_empty() && !after.starts_with('(')).then_some(name)
+}
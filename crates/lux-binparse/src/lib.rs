@@ -0,0 +1,161 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Declarative parsing of binary formats for Lux
+
+use std::{cell::RefCell, collections::HashMap, path::Path, rc::Rc, sync::Arc};
+
+use lux_utils::{TableBuilder, process::ProcessPermissions};
+use mlua::prelude::*;
+
+mod reader;
+mod schema;
+
+pub use self::schema::Schema;
+
+use self::schema::{Kind, Len, Structs, kind_from_lua};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/// A compiled struct, created by `binparse.struct`
+#[derive(Clone)]
+pub struct StructType(Arc<Schema>);
+
+impl StructType {
+    fn parse_bytes(&self, lua: &Lua, data: &[u8], offset: usize) -> LuaResult<(LuaTable, usize)> {
+        reader::parse(lua, &self.0, data, offset)
+    }
+}
+
+impl LuaUserData for StructType {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "BinStruct");
+        fields.add_field_method_get("name", |_, this| Ok(this.0.name.clone()));
+        fields.add_field_method_get("size", |_, this| Ok(this.0.size));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method(
+            "parse",
+            |lua, this, (data, offset): (LuaValue, Option<usize>)| {
+                let offset = offset.unwrap_or(0);
+                match data {
+                    LuaValue::String(s) => this.parse_bytes(lua, &s.as_bytes(), offset),
+                    LuaValue::Buffer(b) => this.parse_bytes(lua, &b.to_vec(), offset),
+                    other => Err(LuaError::runtime(format!(
+                        "Expected data to be a buffer or string, got '{}'",
+                        other.type_name()
+                    ))),
+                }
+            },
+        );
+        methods.add_async_method("parseFile", |lua, this, path: String| {
+            let this = this.clone();
+            async move {
+                ProcessPermissions::check_path(&lua, Path::new(&path), "BinStruct:parseFile")?;
+                let data = async_fs::read(&path)
+                    .await
+                    .map_err(|e| LuaError::runtime(format!("Failed to read '{path}': {e}")))?;
+                let (table, _) = this.parse_bytes(&lua, &data, 0)?;
+                Ok(table)
+            }
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(format!(
+                "BinStruct({})",
+                this.0.name.as_deref().unwrap_or("anonymous")
+            ))
+        });
+    }
+}
+
+/// A type that can not be written as a type name, created by `binparse.array` or `binparse.magic`
+#[derive(Clone)]
+pub struct FieldType(Kind);
+
+impl LuaUserData for FieldType {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "BinType");
+        fields.add_field_method_get("size", |_, this| Ok(this.0.fixed_size()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(format!("BinType({})", this.0))
+        });
+    }
+}
+
+fn binparse_struct(
+    _: &Lua,
+    (first, second): (LuaValue, Option<LuaTable>),
+    structs: &Structs,
+) -> LuaResult<StructType> {
+    let (name, fields) = match (first, second) {
+        (LuaValue::String(name), Some(fields)) => (Some(name.to_str()?.to_string()), fields),
+        (LuaValue::Table(fields), None) => (None, fields),
+        _ => {
+            return Err(LuaError::runtime(
+                "Expected a list of fields, or a name followed by a list of fields",
+            ));
+        }
+    };
+    let schema = Arc::new(Schema::compile(name.clone(), &fields, structs)?);
+    if let Some(name) = name {
+        structs.borrow_mut().insert(name, Arc::clone(&schema));
+    }
+    Ok(StructType(schema))
+}
+
+fn binparse_array(
+    _: &Lua,
+    (elem, count): (LuaValue, LuaValue),
+    structs: &Structs,
+) -> LuaResult<FieldType> {
+    let elem = kind_from_lua(&elem, structs)?;
+    let len = match count {
+        LuaValue::Integer(n) if n >= 0 => Len::Fixed(n as usize),
+        LuaValue::Number(n) if n >= 0.0 && n.fract() == 0.0 => Len::Fixed(n as usize),
+        LuaValue::String(s) => Len::Field(s.to_str()?.to_string()),
+        other => {
+            return Err(LuaError::runtime(format!(
+                "Expected count to be a whole number or the name of an earlier field, got '{}'",
+                other.type_name()
+            )));
+        }
+    };
+    Ok(FieldType(Kind::Array(Box::new(elem), len)))
+}
+
+fn binparse_magic(_: &Lua, bytes: LuaString) -> LuaResult<FieldType> {
+    let bytes = bytes.as_bytes().to_vec();
+    if bytes.is_empty() {
+        return Err(LuaError::runtime("Expected magic bytes to not be empty"));
+    }
+    Ok(FieldType(Kind::Magic(bytes)))
+}
+
+/**
+    Creates the `binparse` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let structs: Structs = Rc::new(RefCell::new(HashMap::new()));
+    let struct_names = Rc::clone(&structs);
+    TableBuilder::new(lua)?
+        .with_function("struct", move |lua, args: (LuaValue, Option<LuaTable>)| {
+            binparse_struct(lua, args, &struct_names)
+        })?
+        .with_function("array", move |lua, args: (LuaValue, LuaValue)| {
+            binparse_array(lua, args, &structs)
+        })?
+        .with_function("magic", binparse_magic)?
+        .build_readonly()
+}
@@ -0,0 +1,247 @@
+use std::fmt::{self, Write};
+
+use mlua::prelude::*;
+
+use crate::schema::{Endian, Kind, Len, Schema};
+
+/// Where in the result a value is being read to, for error messages
+enum Segment<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+/// Reads values described by a schema out of bytes, keeping track of where it is for errors
+pub(crate) struct Reader<'a> {
+    lua: &'a Lua,
+    data: &'a [u8],
+    pos: usize,
+    path: Vec<Segment<'a>>,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(lua: &'a Lua, data: &'a [u8], pos: usize) -> Self {
+        Self {
+            lua,
+            data,
+            pos,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Creates an error at the current offset, naming the value being read
+    fn error(&self, message: impl fmt::Display) -> LuaError {
+        let mut path = String::new();
+        for segment in &self.path {
+            match segment {
+                Segment::Field(name) if path.is_empty() => path.push_str(name),
+                Segment::Field(name) => {
+                    path.push('.');
+                    path.push_str(name);
+                }
+                Segment::Index(index) => {
+                    let _ = write!(path, "[{index}]");
+                }
+            }
+        }
+        if path.is_empty() {
+            LuaError::runtime(format!("{message} at byte offset {}", self.pos))
+        } else {
+            LuaError::runtime(format!(
+                "{message} at byte offset {} (reading '{path}')",
+                self.pos
+            ))
+        }
+    }
+
+    fn take(&mut self, len: usize, kind: &Kind) -> LuaResult<&'a [u8]> {
+        let remaining = self.data.len().saturating_sub(self.pos);
+        if len > remaining {
+            return Err(self.error(format!(
+                "Unexpected end of data, {kind} needs {len} bytes but only {remaining} remain"
+            )));
+        }
+        let bytes = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Reads an unsigned integer of up to 8 bytes
+    fn read_raw(&mut self, size: usize, endian: Endian, kind: &Kind) -> LuaResult<u64> {
+        let bytes = self.take(size, kind)?;
+        let mut buf = [0u8; 8];
+        Ok(match endian {
+            Endian::Little => {
+                buf[..size].copy_from_slice(bytes);
+                u64::from_le_bytes(buf)
+            }
+            Endian::Big => {
+                buf[8 - size..].copy_from_slice(bytes);
+                u64::from_be_bytes(buf)
+            }
+        })
+    }
+
+    pub fn read_struct(&mut self, schema: &'a Schema) -> LuaResult<LuaTable> {
+        let table = self
+            .lua
+            .create_table_with_capacity(0, schema.fields.len())?;
+        // NOTE: Only fields that later fields take their length from are kept around
+        let mut lens: Vec<(&str, u64)> = Vec::new();
+
+        for field in &schema.fields {
+            self.path.push(Segment::Field(&field.name));
+            let value = self.read_kind(&field.kind, &lens)?;
+            if field.is_len {
+                let len = match &value {
+                    LuaValue::Integer(n) => u64::try_from(*n).ok(),
+                    LuaValue::Number(n) if *n >= 0.0 => Some(*n as u64),
+                    _ => None,
+                };
+                let Some(len) = len else {
+                    return Err(self.error(format!(
+                        "Field '{}' is used as a length, but is negative",
+                        field.name
+                    )));
+                };
+                lens.push((&field.name, len));
+            }
+            if !matches!(field.kind, Kind::Pad(_)) {
+                table.raw_set(field.name.as_str(), value)?;
+            }
+            self.path.pop();
+        }
+        Ok(table)
+    }
+
+    fn resolve_len(&self, len: &Len, lens: &[(&str, u64)]) -> LuaResult<usize> {
+        match len {
+            Len::Fixed(n) => Ok(*n),
+            Len::Field(name) => {
+                let (_, value) = lens
+                    .iter()
+                    .find(|(field, _)| field == name)
+                    .expect("length fields are checked when compiling");
+                usize::try_from(*value)
+                    .map_err(|_| self.error(format!("Length {value} from '{name}' is too large")))
+            }
+        }
+    }
+
+    fn read_kind(&mut self, kind: &'a Kind, lens: &[(&str, u64)]) -> LuaResult<LuaValue> {
+        Ok(match kind {
+            Kind::Int {
+                size,
+                signed,
+                endian,
+            } => {
+                let value = self.read_raw(*size, *endian, kind)?;
+                if *signed {
+                    // Sign-extend from the size that was read
+                    let shift = 64 - size * 8;
+                    LuaValue::Integer(((value << shift) as i64) >> shift)
+                } else if let Ok(value) = i64::try_from(value) {
+                    LuaValue::Integer(value)
+                } else {
+                    LuaValue::Number(value as f64)
+                }
+            }
+            Kind::Float { size, endian } => {
+                let bits = self.read_raw(*size, *endian, kind)?;
+                if *size == 4 {
+                    LuaValue::Number(f64::from(f32::from_bits(bits as u32)))
+                } else {
+                    LuaValue::Number(f64::from_bits(bits))
+                }
+            }
+            Kind::Bool => LuaValue::Boolean(self.take(1, kind)?[0] != 0),
+            Kind::Bytes(len) => {
+                let len = self.resolve_len(len, lens)?;
+                let bytes = self.take(len, kind)?;
+                LuaValue::String(self.lua.create_string(bytes)?)
+            }
+            Kind::String(len) => {
+                let len = self.resolve_len(len, lens)?;
+                let bytes = self.take(len, kind)?;
+                let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+                LuaValue::String(self.lua.create_string(&bytes[..end])?)
+            }
+            Kind::CString => {
+                let rest = &self.data[self.pos.min(self.data.len())..];
+                let Some(end) = rest.iter().position(|&b| b == 0) else {
+                    return Err(self.error("Unexpected end of data, cstring is not terminated"));
+                };
+                let value = self.lua.create_string(&rest[..end])?;
+                self.pos += end + 1;
+                LuaValue::String(value)
+            }
+            Kind::Pad(n) => {
+                self.take(*n, kind)?;
+                LuaValue::Nil
+            }
+            Kind::Magic(expected) => {
+                let start = self.pos;
+                let bytes = self.take(expected.len(), kind)?;
+                if bytes != expected.as_slice() {
+                    self.pos = start;
+                    return Err(self.error(format!(
+                        "Expected {kind}, got {:?}",
+                        String::from_utf8_lossy(bytes)
+                    )));
+                }
+                LuaValue::String(self.lua.create_string(bytes)?)
+            }
+            Kind::Array(elem, len) => {
+                let count = self.resolve_len(len, lens)?;
+                // NOTE: Counts come from the data, so check them before allocating anything
+                let remaining = self.data.len().saturating_sub(self.pos);
+                if let Some(size) = elem.fixed_size()
+                    && count
+                        .checked_mul(size)
+                        .is_none_or(|total| total > remaining)
+                {
+                    return Err(self.error(format!(
+                        "Unexpected end of data, {count} entries of {elem} need {} bytes but only {remaining} remain",
+                        count.saturating_mul(size)
+                    )));
+                }
+                let array = self
+                    .lua
+                    .create_table_with_capacity(count.min(remaining), 0)?;
+                for index in 0..count {
+                    self.path.push(Segment::Index(index + 1));
+                    let value = self.read_kind(elem, lens)?;
+                    array.raw_set(index + 1, value)?;
+                    self.path.pop();
+                }
+                LuaValue::Table(array)
+            }
+            Kind::Struct(schema) => LuaValue::Table(self.read_struct(schema)?),
+        })
+    }
+}
+
+/**
+    Parses bytes using a schema, starting at `offset`.
+
+    Returns the parsed struct and the offset right after it.
+*/
+pub(crate) fn parse(
+    lua: &Lua,
+    schema: &Schema,
+    data: &[u8],
+    offset: usize,
+) -> LuaResult<(LuaTable, usize)> {
+    if offset > data.len() {
+        return Err(LuaError::runtime(format!(
+            "Offset {offset} is past the end of {} bytes of data",
+            data.len()
+        )));
+    }
+    let mut reader = Reader::new(lua, data, offset);
+    let table = reader.read_struct(schema)?;
+    Ok((table, reader.pos()))
+}
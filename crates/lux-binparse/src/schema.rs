@@ -0,0 +1,346 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc, sync::Arc};
+
+use mlua::prelude::*;
+
+/// Structs that were given a name, so that other types can refer to them by it
+pub(crate) type Structs = Rc<RefCell<HashMap<String, Arc<Schema>>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Endian {
+    Little,
+    Big,
+}
+
+/// How many bytes or entries something has, either fixed or read from an earlier field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Len {
+    Fixed(usize),
+    Field(String),
+}
+
+impl fmt::Display for Len {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(n) => write!(f, "{n}"),
+            Self::Field(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Kind {
+    Int {
+        size: usize,
+        signed: bool,
+        endian: Endian,
+    },
+    Float {
+        size: usize,
+        endian: Endian,
+    },
+    Bool,
+    /// Raw bytes, returned as a string
+    Bytes(Len),
+    /// A string padded with NUL bytes to a length, which are removed
+    String(Len),
+    /// A string ending at a NUL byte
+    CString,
+    /// Bytes that are skipped, and not included in the result
+    Pad(usize),
+    /// Bytes that must match exactly, such as the signature of a file format
+    Magic(Vec<u8>),
+    Array(Box<Kind>, Len),
+    Struct(Arc<Schema>),
+}
+
+impl Kind {
+    /// The number of bytes this always takes up, if it does not depend on the data
+    pub fn fixed_size(&self) -> Option<usize> {
+        match self {
+            Self::Int { size, .. } | Self::Float { size, .. } => Some(*size),
+            Self::Bool => Some(1),
+            Self::Bytes(Len::Fixed(n)) | Self::String(Len::Fixed(n)) | Self::Pad(n) => Some(*n),
+            Self::Magic(bytes) => Some(bytes.len()),
+            Self::Array(elem, Len::Fixed(n)) => elem.fixed_size()?.checked_mul(*n),
+            Self::Struct(schema) => schema.size,
+            Self::Bytes(_) | Self::String(_) | Self::CString | Self::Array(..) => None,
+        }
+    }
+
+    /// Calls `f` with every earlier field that this needs the value of to be read
+    fn for_each_len_field(&self, f: &mut impl FnMut(&str) -> LuaResult<()>) -> LuaResult<()> {
+        match self {
+            Self::Bytes(Len::Field(name)) | Self::String(Len::Field(name)) => f(name),
+            Self::Array(elem, len) => {
+                if let Len::Field(name) = len {
+                    f(name)?;
+                }
+                elem.for_each_len_field(f)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = |endian: Endian| match endian {
+            Endian::Little => "le",
+            Endian::Big => "be",
+        };
+        match self {
+            Self::Int {
+                size: 1, signed, ..
+            } => write!(f, "{}8", if *signed { 'i' } else { 'u' }),
+            Self::Int {
+                size,
+                signed,
+                endian,
+            } => write!(
+                f,
+                "{}{}{}",
+                if *signed { 'i' } else { 'u' },
+                size * 8,
+                suffix(*endian)
+            ),
+            Self::Float { size, endian } => write!(f, "f{}{}", size * 8, suffix(*endian)),
+            Self::Bool => write!(f, "bool"),
+            Self::Bytes(len) => write!(f, "bytes({len})"),
+            Self::String(len) => write!(f, "string({len})"),
+            Self::CString => write!(f, "cstring"),
+            Self::Pad(n) => write!(f, "pad({n})"),
+            Self::Magic(bytes) => write!(f, "magic({:?})", String::from_utf8_lossy(bytes)),
+            Self::Array(elem, len) => write!(f, "{elem}[{len}]"),
+            Self::Struct(schema) => write!(f, "{}", schema.name.as_deref().unwrap_or("struct")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Field {
+    pub name: String,
+    pub kind: Kind,
+    /// Whether a later field uses the value of this one as a length
+    pub is_len: bool,
+}
+
+/// The compiled layout of a struct
+#[derive(Debug)]
+pub struct Schema {
+    pub(crate) name: Option<String>,
+    pub(crate) fields: Vec<Field>,
+    pub(crate) size: Option<usize>,
+}
+
+impl Schema {
+    /**
+        Compiles a list of fields, such as `{ { magic = "u32le" }, { count = "u16" } }`.
+
+        Fields are a list of single-field tables, since tables do not keep the order of their keys.
+    */
+    pub(crate) fn compile(
+        name: Option<String>,
+        fields: &LuaTable,
+        structs: &Structs,
+    ) -> LuaResult<Self> {
+        let count = fields.raw_len();
+        if count == 0 && !fields.is_empty() {
+            return Err(LuaError::runtime(
+                "Expected fields to be a list such as { { magic = \"u32le\" }, { count = \"u16\" } }, \
+                since tables do not keep the order of their keys",
+            ));
+        }
+
+        let mut compiled: Vec<Field> = Vec::with_capacity(count);
+        for (index, entry) in fields.sequence_values::<LuaValue>().enumerate() {
+            let entry = match entry? {
+                LuaValue::Table(t) => t,
+                other => {
+                    return Err(LuaError::runtime(format!(
+                        "Expected field {} to be a table such as {{ count = \"u16\" }}, got '{}'",
+                        index + 1,
+                        other.type_name()
+                    )));
+                }
+            };
+            let mut pairs = entry
+                .pairs::<String, LuaValue>()
+                .collect::<LuaResult<Vec<_>>>()?;
+            if pairs.len() != 1 {
+                return Err(LuaError::runtime(format!(
+                    "Expected field {} to have exactly one name and type, got {}",
+                    index + 1,
+                    pairs.len()
+                )));
+            }
+            let (field_name, ty) = pairs.remove(0);
+            if compiled.iter().any(|f| f.name == field_name) {
+                return Err(LuaError::runtime(format!(
+                    "Field '{field_name}' is declared more than once"
+                )));
+            }
+
+            let kind = kind_from_lua(&ty, structs).map_err(|e| {
+                LuaError::runtime(format!("Invalid type for field '{field_name}': {e}"))
+            })?;
+            kind.for_each_len_field(&mut |len_field| {
+                match compiled.iter_mut().find(|f| f.name == len_field) {
+                    Some(f) if matches!(f.kind, Kind::Int { .. }) => {
+                        f.is_len = true;
+                        Ok(())
+                    }
+                    Some(_) => Err(LuaError::runtime(format!(
+                        "Field '{field_name}' takes its length from '{len_field}', which is not an integer"
+                    ))),
+                    None => Err(LuaError::runtime(format!(
+                        "Field '{field_name}' takes its length from '{len_field}', which must be declared before it"
+                    ))),
+                }
+            })?;
+            compiled.push(Field {
+                name: field_name,
+                kind,
+                is_len: false,
+            });
+        }
+
+        let size = compiled
+            .iter()
+            .try_fold(0usize, |total, f| total.checked_add(f.kind.fixed_size()?));
+        Ok(Self {
+            name,
+            fields: compiled,
+            size,
+        })
+    }
+}
+
+/// Converts a type given from Lua - a type name, a struct, or a type created by the module
+pub(crate) fn kind_from_lua(value: &LuaValue, structs: &Structs) -> LuaResult<Kind> {
+    match value {
+        LuaValue::String(s) => parse_type(&s.to_str()?, structs).map_err(LuaError::runtime),
+        LuaValue::UserData(ud) => {
+            if let Ok(schema) = ud.borrow::<crate::StructType>() {
+                Ok(Kind::Struct(Arc::clone(&schema.0)))
+            } else if let Ok(ty) = ud.borrow::<crate::FieldType>() {
+                Ok(ty.0.clone())
+            } else {
+                Err(LuaError::runtime(
+                    "Expected a type name, a struct, or a type created by binparse",
+                ))
+            }
+        }
+        other => Err(LuaError::runtime(format!(
+            "Expected a type name, a struct, or a type created by binparse, got '{}'",
+            other.type_name()
+        ))),
+    }
+}
+
+/**
+    Parses a type name such as `u32le`, `string(16)`, `bytes(size)` or `u16[count]`.
+
+    Names of structs that were given one may be used as well.
+*/
+pub(crate) fn parse_type(source: &str, structs: &Structs) -> Result<Kind, String> {
+    let source = source.trim();
+
+    if let Some(inner) = source.strip_suffix(']')
+        && let Some((elem, len)) = inner.rsplit_once('[')
+    {
+        let elem = parse_type(elem, structs)?;
+        return Ok(Kind::Array(Box::new(elem), parse_len(len)?));
+    }
+
+    if let Some(inner) = source.strip_suffix(')')
+        && let Some((name, arg)) = inner.split_once('(')
+    {
+        return match name.trim() {
+            "bytes" => Ok(Kind::Bytes(parse_len(arg)?)),
+            "string" => Ok(Kind::String(parse_len(arg)?)),
+            "pad" => match parse_len(arg)? {
+                Len::Fixed(n) => Ok(Kind::Pad(n)),
+                Len::Field(_) => Err(format!(
+                    "Padding must be a fixed number of bytes, got '{source}'"
+                )),
+            },
+            other => Err(format!("Unknown type '{other}'")),
+        };
+    }
+
+    if let Some(kind) = parse_primitive(source) {
+        return Ok(kind);
+    }
+    match structs.borrow().get(source) {
+        Some(schema) => Ok(Kind::Struct(Arc::clone(schema))),
+        None => Err(format!("Unknown type '{source}'")),
+    }
+}
+
+fn parse_primitive(name: &str) -> Option<Kind> {
+    // C names from ffi declarations are accepted too, and are little-endian
+    let name = match name {
+        "uint8_t" => "u8",
+        "int8_t" => "i8",
+        "uint16_t" => "u16",
+        "int16_t" => "i16",
+        "uint32_t" => "u32",
+        "int32_t" => "i32",
+        "uint64_t" => "u64",
+        "int64_t" => "i64",
+        "float" => "f32",
+        "double" => "f64",
+        name => name,
+    };
+    match name {
+        "bool" => return Some(Kind::Bool),
+        "cstring" => return Some(Kind::CString),
+        _ => {}
+    }
+
+    let (base, endian) = if let Some(base) = name.strip_suffix("le") {
+        (base, Endian::Little)
+    } else if let Some(base) = name.strip_suffix("be") {
+        (base, Endian::Big)
+    } else {
+        (name, Endian::Little)
+    };
+    let int = |size, signed| {
+        Some(Kind::Int {
+            size,
+            signed,
+            endian,
+        })
+    };
+    match base {
+        "u8" => int(1, false),
+        "i8" => int(1, true),
+        "u16" => int(2, false),
+        "i16" => int(2, true),
+        "u32" => int(4, false),
+        "i32" => int(4, true),
+        "u64" => int(8, false),
+        "i64" => int(8, true),
+        "f32" => Some(Kind::Float { size: 4, endian }),
+        "f64" => Some(Kind::Float { size: 8, endian }),
+        _ => None,
+    }
+}
+
+fn parse_len(source: &str) -> Result<Len, String> {
+    let source = source.trim();
+    if let Ok(n) = source.parse::<usize>() {
+        Ok(Len::Fixed(n))
+    } else if !source.is_empty()
+        && !source.starts_with(|c: char| c.is_ascii_digit())
+        && source
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Ok(Len::Field(source.to_string()))
+    } else {
+        Err(format!(
+            "Expected a length or the name of an earlier field, got '{source}'"
+        ))
+    }
+}
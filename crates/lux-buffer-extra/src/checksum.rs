@@ -0,0 +1,57 @@
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/**
+    Computes the CRC-32 (IEEE) checksum of the given bytes, continuing from a previous checksum.
+
+    The checksum of no bytes is `0`, which is also what to start from.
+*/
+pub fn crc32(bytes: &[u8], previous: u32) -> u32 {
+    let mut crc = !previous;
+    for &byte in bytes {
+        crc = CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/**
+    Computes the Adler-32 checksum of the given bytes, continuing from a previous checksum.
+
+    The checksum of no bytes is `1`, which is also what to start from.
+*/
+pub fn adler32(bytes: &[u8], previous: u32) -> u32 {
+    const MOD: u32 = 65521;
+    // Largest number of bytes that can be summed before the sums may overflow
+    const CHUNK: usize = 5552;
+
+    let mut a = previous & 0xFFFF;
+    let mut b = previous >> 16;
+    for chunk in bytes.chunks(CHUNK) {
+        for &byte in chunk {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
@@ -0,0 +1,227 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Binary data utilities for Lux: hex, checksums, search and struct packing
+
+use std::{
+    cmp::Ordering,
+    io::{Read, Seek, SeekFrom},
+};
+
+use lux_utils::TableBuilder;
+use mlua::prelude::*;
+
+mod checksum;
+mod pack;
+mod scalar;
+
+use self::scalar::{Endian, Scalar};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/// Gets the bytes of a string or buffer argument
+fn bytes_of(value: &LuaValue) -> LuaResult<Vec<u8>> {
+    match value {
+        LuaValue::String(s) => Ok(s.as_bytes().to_vec()),
+        LuaValue::Buffer(b) => Ok(b.to_vec()),
+        _ => Err(LuaError::external("Expected string or buffer")),
+    }
+}
+
+/// Converts an optional zero-based offset argument
+fn offset_of(offset: Option<i64>) -> LuaResult<usize> {
+    let offset = offset.unwrap_or(0);
+    usize::try_from(offset)
+        .map_err(|_| LuaError::runtime(format!("Offset must not be negative, got {offset}")))
+}
+
+/// Encode data to a hex string
+fn to_hex(_: &Lua, (data, upper): (LuaValue, Option<bool>)) -> LuaResult<String> {
+    const LOWER: &[u8; 16] = b"0123456789abcdef";
+    const UPPER: &[u8; 16] = b"0123456789ABCDEF";
+    let digits = if upper.unwrap_or(false) { UPPER } else { LOWER };
+
+    let bytes = bytes_of(&data)?;
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        hex.push(digits[usize::from(byte >> 4)] as char);
+        hex.push(digits[usize::from(byte & 0xF)] as char);
+    }
+    Ok(hex)
+}
+
+/// Decode a hex string to a buffer
+fn from_hex(lua: &Lua, hex: LuaString) -> LuaResult<mlua::Buffer> {
+    let hex = hex.as_bytes();
+    if !hex.len().is_multiple_of(2) {
+        return Err(LuaError::runtime(format!(
+            "Invalid hex: expected an even number of digits, got {}",
+            hex.len()
+        )));
+    }
+    let digit = |pos: usize| {
+        char::from(hex[pos]).to_digit(16).ok_or_else(|| {
+            LuaError::runtime(format!(
+                "Invalid hex: unexpected character '{}' at position {}",
+                char::from(hex[pos]).escape_default(),
+                pos + 1
+            ))
+        })
+    };
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|pos| Ok(u8::try_from(digit(pos)? << 4 | digit(pos + 1)?).expect("two hex digits")))
+        .collect::<LuaResult<Vec<u8>>>()?;
+    lua.create_buffer(bytes)
+}
+
+/// Check if two strings or buffers contain the same bytes
+fn equals(_: &Lua, (a, b): (LuaValue, LuaValue)) -> LuaResult<bool> {
+    Ok(bytes_of(&a)? == bytes_of(&b)?)
+}
+
+/// Compare two strings or buffers byte by byte
+fn compare(_: &Lua, (a, b): (LuaValue, LuaValue)) -> LuaResult<i32> {
+    Ok(match bytes_of(&a)?.cmp(&bytes_of(&b)?) {
+        Ordering::Less => -1,
+        Ordering::Equal => 0,
+        Ordering::Greater => 1,
+    })
+}
+
+/// Find the first offset of a byte pattern, starting at an offset
+fn index_of(
+    _: &Lua,
+    (haystack, needle, init): (LuaValue, LuaValue, Option<i64>),
+) -> LuaResult<Option<usize>> {
+    let haystack = bytes_of(&haystack)?;
+    let needle = bytes_of(&needle)?;
+    let start = offset_of(init)?;
+    if start > haystack.len() {
+        return Ok(None);
+    }
+    if needle.is_empty() {
+        return Ok(Some(start));
+    }
+    Ok(haystack[start..]
+        .windows(needle.len())
+        .position(|window| window == needle.as_slice())
+        .map(|pos| pos + start))
+}
+
+/// Find the last offset of a byte pattern
+fn last_index_of(_: &Lua, (haystack, needle): (LuaValue, LuaValue)) -> LuaResult<Option<usize>> {
+    let haystack = bytes_of(&haystack)?;
+    let needle = bytes_of(&needle)?;
+    if needle.is_empty() {
+        return Ok(Some(haystack.len()));
+    }
+    Ok(haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle.as_slice()))
+}
+
+/// Compute the CRC-32 checksum, optionally continuing from a previous one
+fn crc32(_: &Lua, (data, previous): (LuaValue, Option<u32>)) -> LuaResult<u32> {
+    Ok(checksum::crc32(&bytes_of(&data)?, previous.unwrap_or(0)))
+}
+
+/// Compute the Adler-32 checksum, optionally continuing from a previous one
+fn adler32(_: &Lua, (data, previous): (LuaValue, Option<u32>)) -> LuaResult<u32> {
+    Ok(checksum::adler32(&bytes_of(&data)?, previous.unwrap_or(1)))
+}
+
+/// Pack values into a buffer using a format string
+fn pack(lua: &Lua, (format, values): (String, LuaMultiValue)) -> LuaResult<mlua::Buffer> {
+    lua.create_buffer(pack::pack(&format, values)?)
+}
+
+/// Unpack values from a string or buffer using a format string
+fn unpack(
+    lua: &Lua,
+    (format, data, offset): (String, LuaValue, Option<i64>),
+) -> LuaResult<LuaMultiValue> {
+    pack::unpack(lua, &format, &bytes_of(&data)?, offset_of(offset)?)
+}
+
+/// Get the number of bytes used by a format string
+fn pack_size(_: &Lua, format: String) -> LuaResult<usize> {
+    pack::size(&format)
+}
+
+fn scalar_of(type_name: &str) -> LuaResult<Scalar> {
+    Scalar::from_type_name(type_name)
+        .ok_or_else(|| LuaError::runtime(format!("Unknown or unsized type '{type_name}'")))
+}
+
+/// Get the size of a fixed-size type, using FFI type names
+fn size_of(_: &Lua, type_name: String) -> LuaResult<usize> {
+    Ok(scalar_of(&type_name)?.size())
+}
+
+/// Read a fixed-size number from a buffer, using FFI type names
+fn read(
+    _: &Lua,
+    (buffer, offset, type_name, endian): (mlua::Buffer, usize, String, Endian),
+) -> LuaResult<f64> {
+    let scalar = scalar_of(&type_name)?;
+    if offset + scalar.size() > buffer.len() {
+        return Err(LuaError::runtime("Buffer access out of bounds"));
+    }
+    Ok(scalar.read(&buffer_slice(&buffer, offset, scalar.size()), endian))
+}
+
+/// Write a fixed-size number to a buffer, using FFI type names
+fn write(
+    _: &Lua,
+    (buffer, offset, type_name, value, endian): (mlua::Buffer, usize, String, f64, Endian),
+) -> LuaResult<()> {
+    let scalar = scalar_of(&type_name)?;
+    if offset + scalar.size() > buffer.len() {
+        return Err(LuaError::runtime("Buffer access out of bounds"));
+    }
+    let mut data = [0u8; 8];
+    scalar.write(&mut data, endian, value)?;
+    buffer.write_bytes(offset, &data[..scalar.size()]);
+    Ok(())
+}
+
+fn buffer_slice(buffer: &mlua::Buffer, offset: usize, len: usize) -> Vec<u8> {
+    let mut cursor = buffer.clone().cursor();
+    let mut data = vec![0u8; len];
+    cursor
+        .seek(SeekFrom::Start(offset as u64))
+        .and_then(|_| cursor.read_exact(&mut data))
+        .expect("bounds were checked");
+    data
+}
+
+/**
+    Creates the `buffer-extra` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("toHex", to_hex)?
+        .with_function("fromHex", from_hex)?
+        .with_function("equals", equals)?
+        .with_function("compare", compare)?
+        .with_function("indexOf", index_of)?
+        .with_function("lastIndexOf", last_index_of)?
+        .with_function("crc32", crc32)?
+        .with_function("adler32", adler32)?
+        .with_function("pack", pack)?
+        .with_function("unpack", unpack)?
+        .with_function("packSize", pack_size)?
+        .with_function("sizeof", size_of)?
+        .with_function("read", read)?
+        .with_function("write", write)?
+        .build_readonly()
+}
@@ -0,0 +1,293 @@
+use mlua::prelude::*;
+
+use crate::scalar::{Endian, Scalar};
+
+/**
+    A single item of a pack format string.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Item {
+    /// Changes the byte order for the items after it
+    Endian(Endian),
+    /// A fixed-size number
+    Scalar(Scalar),
+    /// A boolean, stored as a single byte
+    Bool,
+    /// A string of exactly this many bytes, padded with zeros
+    FixedString(usize),
+    /// A zero-terminated string
+    ZeroString,
+    /// This many zero bytes, skipped when unpacking
+    Padding(usize),
+}
+
+/**
+    Parses a format string such as `"<i4f8s16"` into its items.
+
+    - `<`, `>` and `=` switch to little, big and native byte order
+    - `i1`, `i2`, `i4`, `i8` are signed integers, `i` alone is `i4`
+    - `u1`, `u2`, `u4`, `u8` are unsigned integers, `u` alone is `u4`
+    - `f4` and `f8` are floats, `f` alone is `f4`
+    - `b` is a boolean byte
+    - `sN` is a string of exactly `N` bytes, padded with zeros
+    - `z` is a zero-terminated string
+    - `xN` is `N` padding bytes, `x` alone is a single byte
+
+    Whitespace between items is ignored.
+*/
+fn parse(format: &str) -> LuaResult<Vec<Item>> {
+    let mut items = Vec::new();
+    let mut chars = format.char_indices().peekable();
+
+    while let Some((pos, c)) = chars.next() {
+        let mut size = None;
+        while let Some(&(_, digit)) = chars.peek() {
+            let Some(d) = digit.to_digit(10) else {
+                break;
+            };
+            chars.next();
+            size = Some(
+                size.unwrap_or(0usize)
+                    .saturating_mul(10)
+                    .saturating_add(d as usize),
+            );
+        }
+
+        let invalid_size = |size: usize| {
+            LuaError::runtime(format!(
+                "Invalid size {size} for '{c}' at position {} in format string",
+                pos + 1
+            ))
+        };
+        let item = match c {
+            c if c.is_whitespace() => continue,
+            '<' => Item::Endian(Endian::Little),
+            '>' => Item::Endian(Endian::Big),
+            '=' => Item::Endian(Endian::NATIVE),
+            'i' | 'u' => {
+                let size = size.unwrap_or(4);
+                if !matches!(size, 1 | 2 | 4 | 8) {
+                    return Err(invalid_size(size));
+                }
+                Item::Scalar(if c == 'i' {
+                    Scalar::Int(size)
+                } else {
+                    Scalar::UInt(size)
+                })
+            }
+            'f' => {
+                let size = size.unwrap_or(4);
+                if !matches!(size, 4 | 8) {
+                    return Err(invalid_size(size));
+                }
+                Item::Scalar(Scalar::Float(size))
+            }
+            'b' => Item::Bool,
+            's' => match size {
+                Some(size) => Item::FixedString(size),
+                None => {
+                    return Err(LuaError::runtime(format!(
+                        "Missing size for 's' at position {} in format string",
+                        pos + 1
+                    )));
+                }
+            },
+            'z' => Item::ZeroString,
+            'x' => Item::Padding(size.unwrap_or(1)),
+            _ => {
+                return Err(LuaError::runtime(format!(
+                    "Invalid option '{c}' at position {} in format string",
+                    pos + 1
+                )));
+            }
+        };
+        if size.is_some() && matches!(item, Item::Endian(_) | Item::Bool | Item::ZeroString) {
+            return Err(LuaError::runtime(format!(
+                "Option '{c}' at position {} in format string does not take a size",
+                pos + 1
+            )));
+        }
+        items.push(item);
+    }
+
+    Ok(items)
+}
+
+/**
+    Returns the number of bytes that values packed with the given format take up.
+
+    # Errors
+
+    Errors if the format is invalid, or contains variable-length items.
+*/
+pub fn size(format: &str) -> LuaResult<usize> {
+    parse(format)?.into_iter().try_fold(0, |total, item| {
+        Ok(total
+            + match item {
+                Item::Endian(_) => 0,
+                Item::Scalar(scalar) => scalar.size(),
+                Item::Bool => 1,
+                Item::FixedString(size) | Item::Padding(size) => size,
+                Item::ZeroString => {
+                    return Err(LuaError::runtime(
+                        "Format strings with 'z' do not have a fixed size",
+                    ));
+                }
+            })
+    })
+}
+
+/**
+    Packs the given values into bytes, using the given format.
+
+    # Errors
+
+    Errors if the format is invalid, or if the values do not match it.
+*/
+pub fn pack(format: &str, values: LuaMultiValue) -> LuaResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut endian = Endian::Little;
+    let mut values = values.into_iter().enumerate();
+
+    for item in parse(format)? {
+        if let Item::Endian(e) = item {
+            endian = e;
+            continue;
+        }
+        if let Item::Padding(size) = item {
+            bytes.resize(bytes.len() + size, 0);
+            continue;
+        }
+
+        let (index, value) = values
+            .next()
+            .ok_or_else(|| LuaError::runtime("Not enough values for format string"))?;
+        let argument = |message: String| LuaError::BadArgument {
+            to: Some("pack".to_string()),
+            pos: index + 2,
+            name: None,
+            cause: LuaError::runtime(message).into(),
+        };
+
+        match item {
+            Item::Scalar(scalar) => {
+                let number = match value {
+                    LuaValue::Integer(i) => i as f64,
+                    LuaValue::Number(n) => n,
+                    other => {
+                        return Err(argument(format!(
+                            "expected number, got {}",
+                            other.type_name()
+                        )));
+                    }
+                };
+                let start = bytes.len();
+                bytes.resize(start + scalar.size(), 0);
+                scalar
+                    .write(&mut bytes[start..], endian, number)
+                    .map_err(|e| argument(e.to_string()))?;
+            }
+            Item::Bool => match value {
+                LuaValue::Boolean(b) => bytes.push(u8::from(b)),
+                other => {
+                    return Err(argument(format!(
+                        "expected boolean, got {}",
+                        other.type_name()
+                    )));
+                }
+            },
+            Item::FixedString(size) => {
+                let data = string_bytes(&value).map_err(argument)?;
+                if data.len() > size {
+                    return Err(argument(format!(
+                        "string of length {} does not fit in {size} bytes",
+                        data.len()
+                    )));
+                }
+                bytes.extend_from_slice(&data);
+                bytes.resize(bytes.len() + size - data.len(), 0);
+            }
+            Item::ZeroString => {
+                let data = string_bytes(&value).map_err(argument)?;
+                if data.contains(&0) {
+                    return Err(argument("string contains zeros".to_string()));
+                }
+                bytes.extend_from_slice(&data);
+                bytes.push(0);
+            }
+            Item::Endian(_) | Item::Padding(_) => unreachable!(),
+        }
+    }
+
+    Ok(bytes)
+}
+
+/**
+    Unpacks values from the given bytes, starting at the given offset.
+
+    Returns the unpacked values, followed by the offset right after them.
+
+    # Errors
+
+    Errors if the format is invalid, or if the bytes are too short for it.
+*/
+pub fn unpack(lua: &Lua, format: &str, bytes: &[u8], offset: usize) -> LuaResult<LuaMultiValue> {
+    let mut values = LuaMultiValue::new();
+    let mut endian = Endian::Little;
+    let mut pos = offset;
+
+    let take = |pos: usize, size: usize| {
+        bytes
+            .get(pos..pos.saturating_add(size))
+            .ok_or_else(|| LuaError::runtime("Data is too short for format string"))
+    };
+
+    for item in parse(format)? {
+        match item {
+            Item::Endian(e) => endian = e,
+            Item::Padding(size) => {
+                take(pos, size)?;
+                pos += size;
+            }
+            Item::Scalar(scalar) => {
+                let data = take(pos, scalar.size())?;
+                values.push_back(LuaValue::Number(scalar.read(data, endian)));
+                pos += scalar.size();
+            }
+            Item::Bool => {
+                let data = take(pos, 1)?;
+                values.push_back(LuaValue::Boolean(data[0] != 0));
+                pos += 1;
+            }
+            Item::FixedString(size) => {
+                let data = take(pos, size)?;
+                let end = data.iter().position(|&b| b == 0).unwrap_or(size);
+                values.push_back(LuaValue::String(lua.create_string(&data[..end])?));
+                pos += size;
+            }
+            Item::ZeroString => {
+                let rest = bytes.get(pos..).unwrap_or_default();
+                let end = rest
+                    .iter()
+                    .position(|&b| b == 0)
+                    .ok_or_else(|| LuaError::runtime("Unfinished zero-terminated string"))?;
+                values.push_back(LuaValue::String(lua.create_string(&rest[..end])?));
+                pos += end + 1;
+            }
+        }
+    }
+
+    values.push_back(LuaValue::Number(pos as f64));
+    Ok(values)
+}
+
+fn string_bytes(value: &LuaValue) -> Result<Vec<u8>, String> {
+    match value {
+        LuaValue::String(s) => Ok(s.as_bytes().to_vec()),
+        LuaValue::Buffer(b) => Ok(b.to_vec()),
+        other => Err(format!(
+            "expected string or buffer, got {}",
+            other.type_name()
+        )),
+    }
+}
@@ -0,0 +1,163 @@
+use mlua::prelude::*;
+
+/**
+    Byte order used when reading and writing multi-byte values.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Self = Self::Little;
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Self = Self::Big;
+}
+
+impl FromLua for Endian {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::Little),
+            LuaValue::String(s) => match s.to_str()?.as_ref() {
+                "little" => Ok(Self::Little),
+                "big" => Ok(Self::Big),
+                "native" => Ok(Self::NATIVE),
+                other => Err(LuaError::runtime(format!(
+                    "Invalid endianness '{other}', expected 'little', 'big' or 'native'"
+                ))),
+            },
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Endian".to_string(),
+                message: Some("expected 'little', 'big' or 'native'".to_string()),
+            }),
+        }
+    }
+}
+
+/**
+    A fixed-size number stored in binary data.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scalar {
+    Int(usize),
+    UInt(usize),
+    Float(usize),
+}
+
+impl Scalar {
+    /**
+        Parses a type name, using the same names as `@lux/ffi` for fixed-size types.
+    */
+    pub fn from_type_name(name: &str) -> Option<Self> {
+        Some(match name.trim() {
+            "int8_t" | "char" | "signed char" => Self::Int(1),
+            "uint8_t" | "unsigned char" | "bool" => Self::UInt(1),
+            "int16_t" | "short" => Self::Int(2),
+            "uint16_t" | "unsigned short" => Self::UInt(2),
+            "int32_t" | "int" => Self::Int(4),
+            "uint32_t" | "unsigned" | "unsigned int" => Self::UInt(4),
+            "int64_t" | "long long" => Self::Int(8),
+            "uint64_t" | "unsigned long long" => Self::UInt(8),
+            "float" => Self::Float(4),
+            "double" => Self::Float(8),
+            _ => return None,
+        })
+    }
+
+    pub const fn size(self) -> usize {
+        match self {
+            Self::Int(size) | Self::UInt(size) | Self::Float(size) => size,
+        }
+    }
+
+    /**
+        Reads the value from the start of the given bytes, which must be at least [`Scalar::size`] long.
+    */
+    #[allow(clippy::cast_precision_loss)]
+    pub fn read(self, bytes: &[u8], endian: Endian) -> f64 {
+        let size = self.size();
+        let mut raw = [0u8; 8];
+        match endian {
+            Endian::Little => raw[..size].copy_from_slice(&bytes[..size]),
+            Endian::Big => {
+                raw[..size].copy_from_slice(&bytes[..size]);
+                raw[..size].reverse();
+            }
+        }
+        let bits = u64::from_le_bytes(raw);
+        match self {
+            Self::UInt(_) => bits as f64,
+            Self::Int(size) => {
+                // Sign-extend from the highest bit of the value
+                let shift = 64 - size * 8;
+                (((bits << shift) as i64) >> shift) as f64
+            }
+            Self::Float(4) => f64::from(f32::from_bits(u32::from_le_bytes([
+                raw[0], raw[1], raw[2], raw[3],
+            ]))),
+            Self::Float(_) => f64::from_bits(bits),
+        }
+    }
+
+    /**
+        Writes the value to the start of the given bytes, which must be at least [`Scalar::size`] long.
+
+        # Errors
+
+        Errors if the value does not fit in the type.
+    */
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn write(self, bytes: &mut [u8], endian: Endian, value: f64) -> LuaResult<()> {
+        let size = self.size();
+        let bits = match self {
+            Self::Float(4) => u64::from((value as f32).to_bits()),
+            Self::Float(_) => value.to_bits(),
+            Self::Int(_) | Self::UInt(_) => {
+                if value.fract() != 0.0 || !value.is_finite() {
+                    return Err(LuaError::runtime(format!(
+                        "Expected an integer, got {value}"
+                    )));
+                }
+                let (min, max) = self.range();
+                if value < min || value > max {
+                    return Err(LuaError::runtime(format!(
+                        "Value {value} is out of range for a {size}-byte {} integer",
+                        if matches!(self, Self::Int(_)) {
+                            "signed"
+                        } else {
+                            "unsigned"
+                        }
+                    )));
+                }
+                if value < 0.0 {
+                    (value as i64) as u64
+                } else {
+                    value as u64
+                }
+            }
+        };
+        let raw = bits.to_le_bytes();
+        bytes[..size].copy_from_slice(&raw[..size]);
+        if endian == Endian::Big {
+            bytes[..size].reverse();
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn range(self) -> (f64, f64) {
+        match self {
+            Self::Int(8) => (i64::MIN as f64, i64::MAX as f64),
+            Self::UInt(8) => (0.0, u64::MAX as f64),
+            Self::Int(size) => {
+                let half = (1u64 << (size * 8 - 1)) as f64;
+                (-half, half - 1.0)
+            }
+            Self::UInt(size) => (0.0, ((1u64 << (size * 8)) - 1) as f64),
+            Self::Float(_) => (f64::NEG_INFINITY, f64::INFINITY),
+        }
+    }
+}
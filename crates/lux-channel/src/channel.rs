@@ -0,0 +1,93 @@
+use async_channel::{Receiver, Sender, TrySendError};
+
+use mlua::prelude::*;
+
+use crate::sendable::check_sendable;
+
+/**
+    A channel that values can be sent through from one task to another.
+
+    Both ends of the channel live in the same userdata, so any task holding
+    the channel may send and receive. Tasks waiting to send or receive are
+    woken in the order that they started waiting.
+*/
+#[derive(Debug, Clone)]
+pub struct Channel {
+    sender: Sender<LuaValue>,
+    receiver: Receiver<LuaValue>,
+}
+
+impl Channel {
+    /**
+        Creates a new channel that holds at most `capacity` values, or any number of values for `None`.
+    */
+    #[must_use]
+    pub fn new(capacity: Option<usize>) -> Self {
+        let (sender, receiver) = match capacity {
+            Some(capacity) => async_channel::bounded(capacity),
+            None => async_channel::unbounded(),
+        };
+        Self { sender, receiver }
+    }
+
+    pub(crate) fn receiver(&self) -> &Receiver<LuaValue> {
+        &self.receiver
+    }
+
+    async fn send(&self, value: LuaValue) -> LuaResult<()> {
+        check_sendable(&value)?;
+        self.sender.send(value).await.map_err(|_| closed_error())
+    }
+
+    fn try_send(&self, value: LuaValue) -> LuaResult<bool> {
+        check_sendable(&value)?;
+        match self.sender.try_send(value) {
+            Ok(()) => Ok(true),
+            Err(TrySendError::Full(_)) => Ok(false),
+            Err(TrySendError::Closed(_)) => Err(closed_error()),
+        }
+    }
+
+    async fn receive(&self) -> LuaValue {
+        self.receiver.recv().await.unwrap_or(LuaValue::Nil)
+    }
+
+    fn try_receive(&self) -> LuaValue {
+        self.receiver.try_recv().unwrap_or(LuaValue::Nil)
+    }
+}
+
+fn closed_error() -> LuaError {
+    LuaError::runtime("Cannot send through a channel that is closed")
+}
+
+impl LuaUserData for Channel {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Channel");
+        fields.add_field_method_get("capacity", |_, this| Ok(this.sender.capacity()));
+        fields.add_field_method_get("isClosed", |_, this| Ok(this.sender.is_closed()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("send", |_, this, value: LuaValue| {
+            let this = this.clone();
+            async move { this.send(value).await }
+        });
+        methods.add_method("trySend", |_, this, value: LuaValue| this.try_send(value));
+        methods.add_async_method("receive", |_, this, (): ()| {
+            let this = this.clone();
+            async move { Ok(this.receive().await) }
+        });
+        methods.add_method("tryReceive", |_, this, (): ()| Ok(this.try_receive()));
+        methods.add_method("close", |_, this, (): ()| Ok(this.sender.close()));
+        methods.add_meta_method(LuaMetaMethod::Len, |_, this, (): ()| {
+            Ok(this.receiver.len())
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(match this.sender.capacity() {
+                Some(capacity) => format!("Channel({}/{capacity})", this.receiver.len()),
+                None => format!("Channel({})", this.receiver.len()),
+            })
+        });
+    }
+}
@@ -0,0 +1,43 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Channels for sending values between tasks in Lux
+
+use lux_utils::TableBuilder;
+use mlua::prelude::*;
+
+mod channel;
+mod select;
+mod sendable;
+
+pub use self::channel::Channel;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+fn channel_new(_: &Lua, capacity: Option<f64>) -> LuaResult<Channel> {
+    match capacity {
+        None => Ok(Channel::new(None)),
+        Some(n) if n >= 1.0 && n.fract() == 0.0 => Ok(Channel::new(Some(n as usize))),
+        Some(n) => Err(LuaError::runtime(format!(
+            "Expected capacity to be a positive integer, got {n}"
+        ))),
+    }
+}
+
+/**
+    Creates the `channel` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("new", channel_new)?
+        .with_async_function("select", select::channel_select)?
+        .build_readonly()
+}
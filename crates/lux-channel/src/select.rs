@@ -0,0 +1,102 @@
+use std::{
+    future::Future,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::Poll,
+    time::Duration,
+};
+
+use async_io::Timer;
+use futures_lite::future;
+
+use mlua::prelude::*;
+
+use crate::channel::Channel;
+
+/// Where the next select starts looking for a value, so that no channel is always checked first
+static NEXT_START: AtomicUsize = AtomicUsize::new(0);
+
+fn to_duration(secs: f64) -> LuaResult<Duration> {
+    Duration::try_from_secs_f64(secs).map_err(|_| {
+        LuaError::runtime(format!(
+            "Timeout must be a finite number of seconds that is not negative, got {secs}"
+        ))
+    })
+}
+
+/**
+    Waits until any of the given channels has a value, then receives it.
+
+    Returns the channel that the value came from along with the value, or
+    nothing if all of the channels are closed and empty, or the timeout passed.
+*/
+pub(crate) async fn channel_select(
+    _: Lua,
+    (channels, timeout): (LuaTable, Option<f64>),
+) -> LuaResult<(LuaValue, LuaValue)> {
+    let mut entries = Vec::new();
+    for (index, value) in channels.sequence_values::<LuaValue>().enumerate() {
+        match value? {
+            LuaValue::UserData(ud) if ud.is::<Channel>() => {
+                let receiver = ud.borrow::<Channel>()?.receiver().clone();
+                entries.push((ud, receiver));
+            }
+            other => {
+                return Err(LuaError::runtime(format!(
+                    "Expected a channel at index {}, got '{}'",
+                    index + 1,
+                    other.type_name()
+                )));
+            }
+        }
+    }
+    if entries.is_empty() {
+        return Err(LuaError::runtime(
+            "Expected at least one channel to select from",
+        ));
+    }
+    let timeout = timeout.map(to_duration).transpose()?;
+
+    let count = entries.len();
+    let start = NEXT_START.fetch_add(1, Ordering::Relaxed) % count;
+    let mut receives = entries
+        .iter()
+        .map(|(_, receiver)| Some(Box::pin(receiver.recv())))
+        .collect::<Vec<_>>();
+
+    let received = future::poll_fn(|cx| {
+        let mut any_open = false;
+        for offset in 0..count {
+            let index = (start + offset) % count;
+            let Some(receive) = receives[index].as_mut() else {
+                continue;
+            };
+            match receive.as_mut().poll(cx) {
+                Poll::Ready(Ok(value)) => return Poll::Ready(Some((index, value))),
+                // NOTE: Closed and empty channels can never be selected again, so stop polling them
+                Poll::Ready(Err(_)) => receives[index] = None,
+                Poll::Pending => any_open = true,
+            }
+        }
+        if any_open {
+            Poll::Pending
+        } else {
+            Poll::Ready(None)
+        }
+    });
+
+    let received = match timeout {
+        Some(timeout) => {
+            future::or(received, async {
+                Timer::after(timeout).await;
+                None
+            })
+            .await
+        }
+        None => received.await,
+    };
+
+    Ok(match received {
+        Some((index, value)) => (LuaValue::UserData(entries[index].0.clone()), value),
+        None => (LuaValue::Nil, LuaValue::Nil),
+    })
+}
@@ -0,0 +1,61 @@
+use std::{collections::HashSet, ffi::c_void};
+
+use mlua::prelude::*;
+
+/**
+    Checks that a value may be sent through a channel.
+
+    Channels only carry plain data - booleans, numbers, vectors, strings,
+    buffers, and tables containing only those. Functions, threads and
+    userdata are rejected, since they are tied to the task that made them.
+
+    `nil` is also rejected, since receiving `nil` means the channel is closed.
+*/
+pub(crate) fn check_sendable(value: &LuaValue) -> LuaResult<()> {
+    if value.is_nil() {
+        return Err(LuaError::runtime(
+            "Cannot send nil through a channel, since receiving nil means that the channel is closed",
+        ));
+    }
+    match find_unsendable(value, &mut HashSet::new())? {
+        None => Ok(()),
+        Some(type_name) if value.is_table() => Err(LuaError::runtime(format!(
+            "Cannot send a table containing a {type_name} through a channel"
+        ))),
+        Some(type_name) => Err(LuaError::runtime(format!(
+            "Cannot send a {type_name} through a channel"
+        ))),
+    }
+}
+
+/// Returns the type name of the first value found that can not be sent, looking inside of tables
+fn find_unsendable(
+    value: &LuaValue,
+    visited: &mut HashSet<*const c_void>,
+) -> LuaResult<Option<&'static str>> {
+    match value {
+        LuaValue::Nil
+        | LuaValue::Boolean(_)
+        | LuaValue::Integer(_)
+        | LuaValue::Number(_)
+        | LuaValue::Vector(_)
+        | LuaValue::String(_)
+        | LuaValue::Buffer(_) => Ok(None),
+        LuaValue::Table(table) => {
+            // NOTE: Tables may refer to themselves, and each only needs to be checked once
+            if !visited.insert(table.to_pointer()) {
+                return Ok(None);
+            }
+            for pair in table.pairs::<LuaValue, LuaValue>() {
+                let (key, value) = pair?;
+                for inner in [&key, &value] {
+                    if let Some(type_name) = find_unsendable(inner, visited)? {
+                        return Ok(Some(type_name));
+                    }
+                }
+            }
+            Ok(None)
+        }
+        other => Ok(Some(other.type_name())),
+    }
+}
@@ -1,10 +1,20 @@
 #![allow(clippy::cargo_common_metadata)]
 
-//! Color3 type for Lux - RGB, HSV, Hex support
+//! Color3 type for Lux - RGB, HSV, Hex support, along with
+//! the `ColorSequence` and `NumberSequence` types for gradients
 
 use lux_utils::TableBuilder;
+use lux_utils::packed::{read_f64s, write_f64s};
 use mlua::prelude::*;
 
+mod sequence;
+
+pub use self::sequence::{
+    ColorSequence, ColorSequenceKeypoint, NumberSequence, NumberSequenceKeypoint,
+    create_color_sequence, create_color_sequence_keypoint, create_number_sequence,
+    create_number_sequence_keypoint,
+};
+
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
 #[must_use]
@@ -133,6 +143,9 @@ impl LuaUserData for Color3 {
             Ok((h, s, v))
         });
         m.add_method("ToHex", |_, t, ()| Ok(t.to_hex()));
+        m.add_method("WriteTo", |_, t, (b, o): (mlua::Buffer, usize)| {
+            write_f64s(&b, o, &[t.r, t.g, t.b])
+        });
         m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
             Ok(*t == *o)
         });
@@ -142,6 +155,19 @@ impl LuaUserData for Color3 {
     }
 }
 
+impl FromLua for Color3 {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: "Color3".to_string(),
+                message: Some("expected a Color3".into()),
+            }),
+        }
+    }
+}
+
 pub fn create(lua: Lua) -> LuaResult<LuaValue> {
     TableBuilder::new(lua)?
         .with_function("new", |lua, (r, g, b): (f64, f64, f64)| {
@@ -153,6 +179,10 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
         .with_function("fromHSV", |lua, (h, s, v): (f64, f64, f64)| {
             lua.create_userdata(Color3::from_hsv(h, s, v))
         })?
+        .with_function("ReadFrom", |lua, (buf, o): (mlua::Buffer, usize)| {
+            let [r, g, b] = read_f64s(&buf, o)?;
+            lua.create_userdata(Color3::new(r, g, b))
+        })?
         .with_function("fromHex", |lua, hex: String| {
             Color3::from_hex(&hex)
                 .map(|c| lua.create_userdata(c))
@@ -0,0 +1,448 @@
+//! `ColorSequence` and `NumberSequence` - values that change over a 0-1 range,
+//! such as gradients, described by keypoints that are interpolated between
+
+use std::fmt::Write;
+
+use lux_utils::{LuxError, TableBuilder};
+use mlua::prelude::*;
+
+use crate::Color3;
+
+/**
+    Checks that keypoint times start at 0, end at 1 and never go backwards.
+*/
+fn validate_times(times: &[f64]) -> Result<(), String> {
+    if times.len() < 2 {
+        return Err(format!(
+            "sequences must have at least 2 keypoints, got {}",
+            times.len()
+        ));
+    }
+    let (first, last) = (times[0], times[times.len() - 1]);
+    if let Some(time) = times.iter().find(|t| !(0.0..=1.0).contains(*t)) {
+        return Err(format!(
+            "keypoint times must be between 0 and 1, got {time}"
+        ));
+    }
+    // NOTE: Times are known to be within 0-1 here, so these are exact checks for 0 and 1
+    if first > 0.0 {
+        return Err(format!("the first keypoint must be at time 0, got {first}"));
+    }
+    if last < 1.0 {
+        return Err(format!("the last keypoint must be at time 1, got {last}"));
+    }
+    if let Some([before, after]) = times
+        .array_windows::<2>()
+        .find(|[before, after]| after < before)
+    {
+        return Err(format!(
+            "keypoints must be sorted by time, but {after} comes after {before}"
+        ));
+    }
+    Ok(())
+}
+
+/**
+    Finds the keypoints surrounding `alpha`, and how far along between them it is.
+
+    Keypoints sharing a time make a hard edge, where the later of them is used.
+*/
+fn segment<K>(keypoints: &[K], time: impl Fn(&K) -> f64, alpha: f64) -> (&K, &K, f64) {
+    let alpha = if alpha.is_nan() {
+        0.0
+    } else {
+        alpha.clamp(0.0, 1.0)
+    };
+    // NOTE: The first keypoint is always at 0, so at least one keypoint is before alpha
+    let after = keypoints.partition_point(|k| time(k) <= alpha).max(1);
+    let Some(next) = keypoints.get(after) else {
+        let last = &keypoints[keypoints.len() - 1];
+        return (last, last, 0.0);
+    };
+    let prev = &keypoints[after - 1];
+    let span = time(next) - time(prev);
+    let t = if span > 0.0 {
+        (alpha - time(prev)) / span
+    } else {
+        0.0
+    };
+    (prev, next, t)
+}
+
+fn keypoints_from_table<K: FromLua>(lua: &Lua, table: &LuaTable) -> LuaResult<Vec<K>> {
+    table
+        .sequence_values::<LuaValue>()
+        .map(|value| K::from_lua(value?, lua))
+        .collect()
+}
+
+fn keypoints_to_table<K: LuaUserData + Copy + 'static>(
+    lua: &Lua,
+    keypoints: &[K],
+) -> LuaResult<LuaTable> {
+    let table = lua.create_table_with_capacity(keypoints.len(), 0)?;
+    for keypoint in keypoints {
+        table.push(lua.create_userdata(*keypoint)?)?;
+    }
+    Ok(table)
+}
+
+// ============================================================================
+// ColorSequenceKeypoint
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ColorSequenceKeypoint {
+    pub time: f64,
+    pub value: Color3,
+}
+
+impl ColorSequenceKeypoint {
+    #[inline]
+    #[must_use]
+    pub fn new(time: f64, value: Color3) -> Self {
+        Self { time, value }
+    }
+}
+
+impl LuaUserData for ColorSequenceKeypoint {
+    fn add_fields<F: LuaUserDataFields<Self>>(f: &mut F) {
+        f.add_field_method_get("Time", |_, t| Ok(t.time));
+        f.add_field_method_get("Value", |_, t| Ok(t.value));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
+        m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
+            Ok(*t == *o)
+        });
+        m.add_meta_method(LuaMetaMethod::ToString, |_, t, ()| {
+            Ok(format!(
+                "ColorSequenceKeypoint({}, {}, {}, {})",
+                t.time, t.value.r, t.value.g, t.value.b
+            ))
+        });
+    }
+}
+
+impl FromLua for ColorSequenceKeypoint {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: "ColorSequenceKeypoint".to_string(),
+                message: Some("expected a ColorSequenceKeypoint".into()),
+            }),
+        }
+    }
+}
+
+// ============================================================================
+// ColorSequence
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorSequence {
+    keypoints: Vec<ColorSequenceKeypoint>,
+}
+
+impl ColorSequence {
+    /**
+        Creates a sequence from keypoints, which must start at time 0,
+        end at time 1, and be sorted by time.
+
+        # Errors
+
+        Errors if the keypoints are not valid for a sequence.
+    */
+    pub fn new(keypoints: Vec<ColorSequenceKeypoint>) -> Result<Self, String> {
+        let times = keypoints.iter().map(|k| k.time).collect::<Vec<_>>();
+        validate_times(&times)?;
+        Ok(Self { keypoints })
+    }
+
+    /// A sequence going from one color to another
+    #[must_use]
+    pub fn between(start: Color3, end: Color3) -> Self {
+        Self {
+            keypoints: vec![
+                ColorSequenceKeypoint::new(0.0, start),
+                ColorSequenceKeypoint::new(1.0, end),
+            ],
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn keypoints(&self) -> &[ColorSequenceKeypoint] {
+        &self.keypoints
+    }
+
+    /// The color at `alpha`, interpolated between the surrounding keypoints
+    #[must_use]
+    pub fn evaluate(&self, alpha: f64) -> Color3 {
+        let (prev, next, t) = segment(&self.keypoints, |k| k.time, alpha);
+        prev.value.lerp(&next.value, t)
+    }
+}
+
+impl LuaUserData for ColorSequence {
+    fn add_fields<F: LuaUserDataFields<Self>>(f: &mut F) {
+        f.add_field_method_get("Keypoints", |lua, t| keypoints_to_table(lua, &t.keypoints));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
+        m.add_method("Evaluate", |_, t, alpha: f64| Ok(t.evaluate(alpha)));
+        m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
+            Ok(*t == *o)
+        });
+        m.add_meta_method(LuaMetaMethod::ToString, |_, t, ()| {
+            let mut s = String::from("ColorSequence(");
+            for (i, k) in t.keypoints.iter().enumerate() {
+                let sep = if i == 0 { "" } else { "; " };
+                let _ = write!(
+                    s,
+                    "{sep}{}: {}, {}, {}",
+                    k.time, k.value.r, k.value.g, k.value.b
+                );
+            }
+            s.push(')');
+            Ok(s)
+        });
+    }
+}
+
+// ============================================================================
+// NumberSequenceKeypoint
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NumberSequenceKeypoint {
+    pub time: f64,
+    pub value: f64,
+    pub envelope: f64,
+}
+
+impl NumberSequenceKeypoint {
+    #[inline]
+    #[must_use]
+    pub fn new(time: f64, value: f64, envelope: f64) -> Self {
+        Self {
+            time,
+            value,
+            envelope: envelope.max(0.0),
+        }
+    }
+}
+
+impl LuaUserData for NumberSequenceKeypoint {
+    fn add_fields<F: LuaUserDataFields<Self>>(f: &mut F) {
+        f.add_field_method_get("Time", |_, t| Ok(t.time));
+        f.add_field_method_get("Value", |_, t| Ok(t.value));
+        f.add_field_method_get("Envelope", |_, t| Ok(t.envelope));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
+        m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
+            Ok(*t == *o)
+        });
+        m.add_meta_method(LuaMetaMethod::ToString, |_, t, ()| {
+            Ok(format!(
+                "NumberSequenceKeypoint({}, {}, {})",
+                t.time, t.value, t.envelope
+            ))
+        });
+    }
+}
+
+impl FromLua for NumberSequenceKeypoint {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: "NumberSequenceKeypoint".to_string(),
+                message: Some("expected a NumberSequenceKeypoint".into()),
+            }),
+        }
+    }
+}
+
+// ============================================================================
+// NumberSequence
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberSequence {
+    keypoints: Vec<NumberSequenceKeypoint>,
+}
+
+impl NumberSequence {
+    /**
+        Creates a sequence from keypoints, which must start at time 0,
+        end at time 1, and be sorted by time.
+
+        # Errors
+
+        Errors if the keypoints are not valid for a sequence.
+    */
+    pub fn new(keypoints: Vec<NumberSequenceKeypoint>) -> Result<Self, String> {
+        let times = keypoints.iter().map(|k| k.time).collect::<Vec<_>>();
+        validate_times(&times)?;
+        Ok(Self { keypoints })
+    }
+
+    /// A sequence going from one number to another
+    #[must_use]
+    pub fn between(start: f64, end: f64) -> Self {
+        Self {
+            keypoints: vec![
+                NumberSequenceKeypoint::new(0.0, start, 0.0),
+                NumberSequenceKeypoint::new(1.0, end, 0.0),
+            ],
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn keypoints(&self) -> &[NumberSequenceKeypoint] {
+        &self.keypoints
+    }
+
+    /// The value at `alpha`, interpolated between the surrounding keypoints
+    #[must_use]
+    pub fn evaluate(&self, alpha: f64) -> f64 {
+        let (prev, next, t) = segment(&self.keypoints, |k| k.time, alpha);
+        prev.value + (next.value - prev.value) * t
+    }
+}
+
+impl LuaUserData for NumberSequence {
+    fn add_fields<F: LuaUserDataFields<Self>>(f: &mut F) {
+        f.add_field_method_get("Keypoints", |lua, t| keypoints_to_table(lua, &t.keypoints));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
+        m.add_method("Evaluate", |_, t, alpha: f64| Ok(t.evaluate(alpha)));
+        m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
+            Ok(*t == *o)
+        });
+        m.add_meta_method(LuaMetaMethod::ToString, |_, t, ()| {
+            let mut s = String::from("NumberSequence(");
+            for (i, k) in t.keypoints.iter().enumerate() {
+                let sep = if i == 0 { "" } else { "; " };
+                let _ = write!(s, "{sep}{}: {}", k.time, k.value);
+            }
+            s.push(')');
+            Ok(s)
+        });
+    }
+}
+
+// ============================================================================
+// Globals
+// ============================================================================
+
+/**
+    Creates the `ColorSequence` global.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn create_color_sequence(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua)?
+        .with_function("new", |lua, args: LuaMultiValue| {
+            let mut args = args.into_iter();
+            let sequence = match (args.next(), args.next()) {
+                (Some(LuaValue::Table(t)), None) => {
+                    ColorSequence::new(keypoints_from_table(lua, &t)?).map_err(LuxError::Value)?
+                }
+                (Some(start), None) => {
+                    let color = Color3::from_lua(start, lua)?;
+                    ColorSequence::between(color, color)
+                }
+                (Some(start), Some(end)) => ColorSequence::between(
+                    Color3::from_lua(start, lua)?,
+                    Color3::from_lua(end, lua)?,
+                ),
+                (None, _) => {
+                    return Err(LuaError::runtime(
+                        "ColorSequence.new expects a Color3, two Color3s or a table of keypoints",
+                    ));
+                }
+            };
+            lua.create_userdata(sequence)
+        })?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+/**
+    Creates the `ColorSequenceKeypoint` global.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn create_color_sequence_keypoint(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua)?
+        .with_function("new", |lua, (time, value): (f64, Color3)| {
+            lua.create_userdata(ColorSequenceKeypoint::new(time, value))
+        })?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+/**
+    Creates the `NumberSequence` global.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn create_number_sequence(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua)?
+        .with_function("new", |lua, args: LuaMultiValue| {
+            let mut args = args.into_iter();
+            let sequence = match (args.next(), args.next()) {
+                (Some(LuaValue::Table(t)), None) => {
+                    NumberSequence::new(keypoints_from_table(lua, &t)?).map_err(LuxError::Value)?
+                }
+                (Some(start), None) => {
+                    let value = f64::from_lua(start, lua)?;
+                    NumberSequence::between(value, value)
+                }
+                (Some(start), Some(end)) => {
+                    NumberSequence::between(f64::from_lua(start, lua)?, f64::from_lua(end, lua)?)
+                }
+                (None, _) => {
+                    return Err(LuaError::runtime(
+                        "NumberSequence.new expects a number, two numbers or a table of keypoints",
+                    ));
+                }
+            };
+            lua.create_userdata(sequence)
+        })?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+/**
+    Creates the `NumberSequenceKeypoint` global.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn create_number_sequence_keypoint(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua)?
+        .with_function(
+            "new",
+            |lua, (time, value, envelope): (f64, f64, Option<f64>)| {
+                lua.create_userdata(NumberSequenceKeypoint::new(
+                    time,
+                    value,
+                    envelope.unwrap_or_default(),
+                ))
+            },
+        )?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
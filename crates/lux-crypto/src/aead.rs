@@ -0,0 +1,129 @@
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use bstr::BString;
+use chacha20poly1305::{
+    ChaCha20Poly1305,
+    aead::{Aead, KeyInit, Payload},
+};
+use mlua::prelude::*;
+
+/**
+    An authenticated encryption cipher supported by the `crypto` standard library.
+
+    All ciphers use a 12 byte nonce and append a 16 byte authentication tag.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    Aes128Gcm,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl Cipher {
+    const ALL: [Self; 3] = [Self::Aes128Gcm, Self::Aes256Gcm, Self::ChaCha20Poly1305];
+
+    const NONCE_LEN: usize = 12;
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Aes128Gcm => "aes-128-gcm",
+            Self::Aes256Gcm => "aes-256-gcm",
+            Self::ChaCha20Poly1305 => "chacha20-poly1305",
+        }
+    }
+
+    const fn key_len(self) -> usize {
+        match self {
+            Self::Aes128Gcm => 16,
+            Self::Aes256Gcm | Self::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    fn apply(
+        self,
+        encrypt: bool,
+        key: &[u8],
+        nonce: &[u8],
+        payload: Payload,
+    ) -> LuaResult<Vec<u8>> {
+        if key.len() != self.key_len() {
+            return Err(LuaError::runtime(format!(
+                "{} expects a {} byte key, got {} bytes",
+                self.name(),
+                self.key_len(),
+                key.len()
+            )));
+        }
+        if nonce.len() != Self::NONCE_LEN {
+            return Err(LuaError::runtime(format!(
+                "{} expects a {} byte nonce, got {} bytes",
+                self.name(),
+                Self::NONCE_LEN,
+                nonce.len()
+            )));
+        }
+
+        macro_rules! run {
+            ($Cipher:ty) => {{
+                let cipher = <$Cipher>::new_from_slice(key).into_lua_err()?;
+                if encrypt {
+                    cipher.encrypt(nonce.into(), payload)
+                } else {
+                    cipher.decrypt(nonce.into(), payload)
+                }
+            }};
+        }
+
+        let result = match self {
+            Self::Aes128Gcm => run!(Aes128Gcm),
+            Self::Aes256Gcm => run!(Aes256Gcm),
+            Self::ChaCha20Poly1305 => run!(ChaCha20Poly1305),
+        };
+
+        // NOTE: The aead error is deliberately opaque, so we add some context
+        result.map_err(|_| {
+            if encrypt {
+                LuaError::runtime("Encryption failed")
+            } else {
+                LuaError::runtime("Decryption failed - wrong key, nonce or tampered data")
+            }
+        })
+    }
+}
+
+impl FromLua for Cipher {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        if let LuaValue::String(s) = &value {
+            let s = s.to_str()?.to_ascii_lowercase();
+            if let Some(cipher) = Self::ALL.into_iter().find(|c| c.name() == s) {
+                return Ok(cipher);
+            }
+            let expected = Self::ALL.map(|c| format!("'{}'", c.name())).join(", ");
+            return Err(LuaError::RuntimeError(format!(
+                "Invalid cipher '{s}', valid kinds are:\n{expected}"
+            )));
+        }
+        Err(LuaError::FromLuaConversionError {
+            from: value.type_name(),
+            to: "Cipher".to_string(),
+            message: None,
+        })
+    }
+}
+
+type AeadArgs = (Cipher, BString, BString, BString, Option<BString>);
+
+pub fn encrypt(lua: &Lua, (cipher, key, nonce, data, aad): AeadArgs) -> LuaResult<mlua::Buffer> {
+    let payload = Payload {
+        msg: &data,
+        aad: aad.as_deref().map_or(&[], |a| a.as_ref()),
+    };
+    lua.create_buffer(cipher.apply(true, &key, &nonce, payload)?)
+}
+
+pub fn decrypt(lua: &Lua, (cipher, key, nonce, data, aad): AeadArgs) -> LuaResult<mlua::Buffer> {
+    let payload = Payload {
+        msg: &data,
+        aad: aad.as_deref().map_or(&[], |a| a.as_ref()),
+    };
+    lua.create_buffer(cipher.apply(false, &key, &nonce, payload)?)
+}
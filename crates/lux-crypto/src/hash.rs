@@ -0,0 +1,102 @@
+use blake3::Hasher as Blake3;
+use md5::Md5;
+use mlua::prelude::*;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+/**
+    A hashing algorithm supported by the `crypto` standard library.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [Self; 5] = [
+        Self::Md5,
+        Self::Sha1,
+        Self::Sha256,
+        Self::Sha512,
+        Self::Blake3,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+            Self::Blake3 => "blake3",
+        }
+    }
+
+    /**
+        Computes the raw digest of `data`.
+    */
+    #[must_use]
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        use digest::Digest;
+
+        match self {
+            Self::Md5 => Md5::digest(data).to_vec(),
+            Self::Sha1 => Sha1::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+            Self::Blake3 => Blake3::digest(data).to_vec(),
+        }
+    }
+
+    /**
+        Computes the raw HMAC of `data` using `key`.
+
+        # Errors
+
+        If the `key` is invalid for the algorithm.
+    */
+    pub fn hmac(self, key: &[u8], data: &[u8]) -> LuaResult<Vec<u8>> {
+        use hmac::{Hmac, Mac, SimpleHmac};
+
+        // NOTE: blake3 is not a block-based hasher, so it needs `SimpleHmac`
+        macro_rules! hmac {
+            ($Mac:ident, $Type:ty) => {{
+                let mut mac: $Mac<$Type> = $Mac::new_from_slice(key).into_lua_err()?;
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }};
+        }
+
+        Ok(match self {
+            Self::Md5 => hmac!(Hmac, Md5),
+            Self::Sha1 => hmac!(Hmac, Sha1),
+            Self::Sha256 => hmac!(Hmac, Sha256),
+            Self::Sha512 => hmac!(Hmac, Sha512),
+            Self::Blake3 => hmac!(SimpleHmac, Blake3),
+        })
+    }
+}
+
+impl FromLua for HashAlgorithm {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        if let LuaValue::String(s) = &value {
+            let s = s.to_str()?.to_ascii_lowercase();
+            if let Some(algorithm) = Self::ALL.into_iter().find(|a| a.name() == s) {
+                return Ok(algorithm);
+            }
+            let expected = Self::ALL.map(|a| format!("'{}'", a.name())).join(", ");
+            return Err(LuaError::RuntimeError(format!(
+                "Invalid hashing algorithm '{s}', valid kinds are:\n{expected}"
+            )));
+        }
+        Err(LuaError::FromLuaConversionError {
+            from: value.type_name(),
+            to: "HashAlgorithm".to_string(),
+            message: None,
+        })
+    }
+}
@@ -0,0 +1,97 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use bstr::BString;
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+#[cfg(feature = "aead")]
+mod aead;
+mod hash;
+
+pub use self::hash::HashAlgorithm;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `crypto` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `crypto` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let builder = TableBuilder::new(lua)?
+        .with_function("sha1", |lua, data: BString| {
+            digest_buffer(lua, HashAlgorithm::Sha1, &data)
+        })?
+        .with_function("sha256", |lua, data: BString| {
+            digest_buffer(lua, HashAlgorithm::Sha256, &data)
+        })?
+        .with_function("sha512", |lua, data: BString| {
+            digest_buffer(lua, HashAlgorithm::Sha512, &data)
+        })?
+        .with_function("md5", |lua, data: BString| {
+            digest_buffer(lua, HashAlgorithm::Md5, &data)
+        })?
+        .with_function("blake3", |lua, data: BString| {
+            digest_buffer(lua, HashAlgorithm::Blake3, &data)
+        })?
+        .with_function("hash", crypto_hash)?
+        .with_function("hmac", crypto_hmac)?
+        .with_function("equals", crypto_equals)?
+        .with_function("randomBytes", crypto_random_bytes)?
+        .with_function("toHex", crypto_to_hex)?;
+
+    #[cfg(feature = "aead")]
+    let builder = builder
+        .with_function("encrypt", aead::encrypt)?
+        .with_function("decrypt", aead::decrypt)?;
+
+    builder.build_readonly()
+}
+
+fn digest_buffer(lua: &Lua, algorithm: HashAlgorithm, data: &[u8]) -> LuaResult<mlua::Buffer> {
+    lua.create_buffer(algorithm.digest(data))
+}
+
+fn crypto_hash(lua: &Lua, (algorithm, data): (HashAlgorithm, BString)) -> LuaResult<mlua::Buffer> {
+    digest_buffer(lua, algorithm, &data)
+}
+
+fn crypto_hmac(
+    lua: &Lua,
+    (algorithm, key, data): (HashAlgorithm, BString, BString),
+) -> LuaResult<mlua::Buffer> {
+    lua.create_buffer(algorithm.hmac(&key, &data)?)
+}
+
+fn crypto_equals(_: &Lua, (a, b): (BString, BString)) -> LuaResult<bool> {
+    // NOTE: Only the length is leaked through timing, never the contents
+    Ok(constant_time_eq::constant_time_eq(&a, &b))
+}
+
+fn crypto_random_bytes(lua: &Lua, len: usize) -> LuaResult<mlua::Buffer> {
+    let mut bytes = vec![0u8; len];
+    getrandom::fill(&mut bytes).map_err(|e| LuaError::external(e.to_string()))?;
+    lua.create_buffer(bytes)
+}
+
+fn crypto_to_hex(_: &Lua, data: BString) -> LuaResult<String> {
+    use std::fmt::Write;
+
+    Ok(data
+        .iter()
+        .fold(String::with_capacity(data.len() * 2), |mut output, b| {
+            let _ = write!(output, "{b:02x}");
+            output
+        }))
+}
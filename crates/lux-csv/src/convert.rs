@@ -0,0 +1,120 @@
+use mlua::prelude::*;
+
+/**
+    Converts a single field into a Lua value, optionally coercing
+    numbers and booleans, and converting empty fields into nil.
+*/
+fn field_to_value(lua: &Lua, field: &[u8], coerce: bool) -> LuaResult<LuaValue> {
+    if coerce {
+        match field {
+            b"" => return Ok(LuaValue::Nil),
+            b"true" => return Ok(LuaValue::Boolean(true)),
+            b"false" => return Ok(LuaValue::Boolean(false)),
+            _ => {
+                if let Some(number) = parse_number(field) {
+                    return Ok(number);
+                }
+            }
+        }
+    }
+    lua.create_string(field).map(LuaValue::String)
+}
+
+fn parse_number(field: &[u8]) -> Option<LuaValue> {
+    // NOTE: Rust also parses words such as "inf" and "NaN" as floats,
+    // these are almost always meant to be strings in a CSV file
+    let looks_numeric = field
+        .iter()
+        .all(|b| b.is_ascii_digit() || matches!(b, b'-' | b'+' | b'.' | b'e' | b'E'))
+        && field.iter().any(u8::is_ascii_digit);
+    if !looks_numeric {
+        return None;
+    }
+    let text = std::str::from_utf8(field).ok()?;
+    if let Ok(integer) = text.parse::<i64>() {
+        // Integers that can't be represented exactly as a double are kept as-is
+        if integer.unsigned_abs() <= 1 << 53 {
+            return Some(LuaValue::Number(integer as f64));
+        }
+        return None;
+    }
+    text.parse::<f64>().ok().map(LuaValue::Number)
+}
+
+/**
+    Converts the fields of a record into a row table.
+
+    When header names are given, the row is keyed by them, otherwise it is an array.
+*/
+pub(crate) fn record_to_row(
+    lua: &Lua,
+    fields: &[Vec<u8>],
+    header: Option<&[String]>,
+    coerce: bool,
+) -> LuaResult<LuaTable> {
+    match header {
+        None => {
+            let row = lua.create_table_with_capacity(fields.len(), 0)?;
+            for (index, field) in fields.iter().enumerate() {
+                row.raw_set(index + 1, field_to_value(lua, field, coerce)?)?;
+            }
+            Ok(row)
+        }
+        Some(names) => {
+            if fields.len() > names.len() {
+                return Err(LuaError::runtime(format!(
+                    "record has {} fields, but the header only has {}",
+                    fields.len(),
+                    names.len()
+                )));
+            }
+            let row = lua.create_table_with_capacity(0, names.len())?;
+            for (name, field) in names.iter().zip(fields) {
+                row.raw_set(name.as_str(), field_to_value(lua, field, coerce)?)?;
+            }
+            Ok(row)
+        }
+    }
+}
+
+/**
+    Checks if a record is an empty line, which are skipped when reading.
+*/
+pub(crate) fn is_blank_record(fields: &[Vec<u8>]) -> bool {
+    matches!(fields, [field] if field.is_empty())
+}
+
+fn value_to_field(value: LuaValue) -> LuaResult<Vec<u8>> {
+    match value {
+        LuaValue::Nil => Ok(Vec::new()),
+        LuaValue::String(s) => Ok(s.as_bytes().to_vec()),
+        LuaValue::Boolean(_) | LuaValue::Integer(_) | LuaValue::Number(_) => {
+            Ok(value.to_string()?.into_bytes())
+        }
+        _ => Err(LuaError::runtime(format!(
+            "can not write a {} as a CSV field",
+            value.type_name()
+        ))),
+    }
+}
+
+/**
+    Converts a row table into the fields of a record.
+
+    Rows with array items are written in order, other rows are written
+    in the order of the header names, and must have a header to write.
+*/
+pub(crate) fn row_to_record(row: &LuaTable, header: Option<&[String]>) -> LuaResult<Vec<Vec<u8>>> {
+    if row.raw_len() > 0 {
+        return row.sequence_values().map(|v| value_to_field(v?)).collect();
+    }
+    match header {
+        Some(names) => names
+            .iter()
+            .map(|name| value_to_field(row.raw_get(name.as_str())?))
+            .collect(),
+        None => Err(LuaError::runtime(
+            "can not write a row keyed by names without a header, pass header names in the options",
+        )),
+    }
+}
@@ -0,0 +1,122 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod convert;
+mod options;
+mod reader;
+mod record;
+mod writer;
+
+use self::convert::{is_blank_record, record_to_row, row_to_record};
+use self::options::{CsvHeader, CsvOptions, NdjsonOptions};
+use self::reader::{CsvReader, NdjsonReader};
+use self::record::{parse_record, write_record};
+use self::writer::{CsvWriter, NdjsonWriter};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `csv` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `csv` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let ndjson = TableBuilder::new(lua.clone())?
+        .with_async_function("reader", ndjson_reader)?
+        .with_async_function("writer", ndjson_writer)?
+        .build_readonly()?;
+    TableBuilder::new(lua)?
+        .with_value("ndjson", ndjson)?
+        .with_function("parse", csv_parse)?
+        .with_function("stringify", csv_stringify)?
+        .with_async_function("reader", csv_reader)?
+        .with_async_function("writer", csv_writer)?
+        .build_readonly()
+}
+
+fn csv_parse(lua: &Lua, (text, options): (LuaString, CsvOptions)) -> LuaResult<LuaTable> {
+    let bytes = text.as_bytes();
+    let mut input = &bytes[..];
+    let mut read_header = matches!(options.header, CsvHeader::FirstRecord);
+    let mut header = match options.header {
+        CsvHeader::FirstRecord | CsvHeader::None => None,
+        CsvHeader::Names(names) => Some(names),
+    };
+
+    let rows = lua.create_table()?;
+    let mut records = 0;
+    loop {
+        let parsed = parse_record(input, options.delimiter, true).map_err(|e| {
+            LuaError::runtime(format!("Invalid CSV at record {} - {e}", records + 1))
+        })?;
+        let Some((fields, used)) = parsed else {
+            break;
+        };
+        input = &input[used..];
+        records += 1;
+        if is_blank_record(&fields) {
+            continue;
+        }
+        if read_header {
+            let names = fields
+                .into_iter()
+                .map(|name| String::from_utf8(name).into_lua_err())
+                .collect::<LuaResult<Vec<_>>>()?;
+            header = Some(names);
+            read_header = false;
+            continue;
+        }
+        let row = record_to_row(lua, &fields, header.as_deref(), options.coerce)
+            .map_err(|e| LuaError::runtime(format!("Invalid CSV at record {records} - {e}")))?;
+        rows.raw_push(row)?;
+    }
+    Ok(rows)
+}
+
+fn csv_stringify(lua: &Lua, (rows, options): (LuaTable, CsvOptions)) -> LuaResult<LuaString> {
+    let header = match options.header {
+        CsvHeader::Names(names) => Some(names),
+        CsvHeader::FirstRecord | CsvHeader::None => None,
+    };
+    let mut out = Vec::new();
+    if let Some(names) = &header {
+        write_record(&mut out, names, options.delimiter);
+    }
+    for row in rows.sequence_values::<LuaTable>() {
+        let record = row_to_record(&row?, header.as_deref())?;
+        write_record(&mut out, &record, options.delimiter);
+    }
+    lua.create_string(out)
+}
+
+async fn csv_reader(_: Lua, (path, options): (String, CsvOptions)) -> LuaResult<CsvReader> {
+    CsvReader::open(path, options).await
+}
+
+async fn csv_writer(_: Lua, (path, options): (String, CsvOptions)) -> LuaResult<CsvWriter> {
+    CsvWriter::open(path, options).await
+}
+
+async fn ndjson_reader(
+    _: Lua,
+    (path, options): (String, NdjsonOptions),
+) -> LuaResult<NdjsonReader> {
+    NdjsonReader::open(path, options).await
+}
+
+async fn ndjson_writer(_: Lua, path: String) -> LuaResult<NdjsonWriter> {
+    NdjsonWriter::open(path).await
+}
@@ -0,0 +1,147 @@
+use mlua::prelude::*;
+
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/**
+    How the header of a CSV file is handled.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) enum CsvHeader {
+    /// The first record is the header, and rows are keyed by its names
+    #[default]
+    FirstRecord,
+    /// There is no header, and rows are arrays of fields
+    None,
+    /// Rows are keyed by these names, and the file has no header of its own
+    Names(Vec<String>),
+}
+
+impl FromLua for CsvHeader {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil | LuaValue::Boolean(true) => Ok(Self::FirstRecord),
+            LuaValue::Boolean(false) => Ok(Self::None),
+            LuaValue::Table(t) => Ok(Self::Names(
+                t.sequence_values().collect::<LuaResult<Vec<String>>>()?,
+            )),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "CsvHeader".to_string(),
+                message: Some(format!(
+                    "Invalid header - expected boolean or array of names, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    Options for reading and writing CSV.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct CsvOptions {
+    pub delimiter: u8,
+    pub header: CsvHeader,
+    /// Whether fields that look like numbers or booleans are converted, and empty fields become nil
+    pub coerce: bool,
+    /// Number of bytes to read from the file at once
+    pub chunk_size: usize,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: CsvHeader::default(),
+            coerce: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
+
+impl FromLua for CsvOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let defaults = Self::default();
+        match value {
+            LuaValue::Nil => Ok(defaults),
+            LuaValue::Table(t) => {
+                let delimiter = match t.get::<Option<LuaString>>("delimiter")? {
+                    None => defaults.delimiter,
+                    Some(s) => match s.as_bytes()[..] {
+                        [b] if b != b'"' && b != b'\n' && b != b'\r' => b,
+                        _ => {
+                            return Err(LuaError::runtime(format!(
+                                "Invalid CSV delimiter '{}' - expected a single character other than a quote or line break",
+                                s.to_string_lossy()
+                            )));
+                        }
+                    },
+                };
+                let chunk_size = t
+                    .get::<Option<usize>>("chunkSize")?
+                    .unwrap_or(defaults.chunk_size);
+                if chunk_size == 0 {
+                    return Err(LuaError::runtime("CSV chunk size must be at least 1"));
+                }
+                Ok(Self {
+                    delimiter,
+                    header: t.get("header")?,
+                    coerce: t.get::<Option<bool>>("coerce")?.unwrap_or(defaults.coerce),
+                    chunk_size,
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "CsvOptions".to_string(),
+                message: Some(format!(
+                    "Invalid CSV options - expected table or nil, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    Options for reading NDJSON.
+*/
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct NdjsonOptions {
+    /// Number of bytes to read from the file at once
+    pub chunk_size: usize,
+}
+
+impl Default for NdjsonOptions {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
+
+impl FromLua for NdjsonOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let defaults = Self::default();
+        match value {
+            LuaValue::Nil => Ok(defaults),
+            LuaValue::Table(t) => {
+                let chunk_size = t
+                    .get::<Option<usize>>("chunkSize")?
+                    .unwrap_or(defaults.chunk_size);
+                if chunk_size == 0 {
+                    return Err(LuaError::runtime("NDJSON chunk size must be at least 1"));
+                }
+                Ok(Self { chunk_size })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "NdjsonOptions".to_string(),
+                message: Some(format!(
+                    "Invalid NDJSON options - expected table or nil, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::sync::Arc;
+
+use async_fs::File;
+use async_lock::Mutex as AsyncMutex;
+use futures_lite::prelude::*;
+use mlua::prelude::*;
+
+use lux_serde::{EncodeDecodeFormat, decode};
+
+use crate::convert::{is_blank_record, record_to_row};
+use crate::options::{CsvHeader, CsvOptions, NdjsonOptions};
+use crate::record::{parse_record, split_line};
+
+fn closed_error(path: &str) -> LuaError {
+    LuaError::runtime(format!("Reader for file '{path}' is closed"))
+}
+
+/**
+    A file that is read in chunks into a buffer, as records are consumed from it.
+*/
+#[derive(Debug)]
+struct ChunkedFile {
+    file: Option<File>,
+    buf: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    chunk_size: usize,
+}
+
+impl ChunkedFile {
+    async fn open(path: &str, chunk_size: usize) -> LuaResult<Self> {
+        let file = File::open(path).await.into_lua_err()?;
+        Ok(Self {
+            file: Some(file),
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+            chunk_size,
+        })
+    }
+
+    fn pending(&self) -> &[u8] {
+        &self.buf[self.pos..]
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.pos += len;
+    }
+
+    /**
+        Reads the next chunk of the file into the buffer, dropping
+        consumed data first so that the buffer does not keep growing.
+    */
+    async fn fill(&mut self, path: &str) -> LuaResult<()> {
+        let file = self.file.as_mut().ok_or_else(|| closed_error(path))?;
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        let start = self.buf.len();
+        self.buf.resize(start + self.chunk_size, 0);
+        let read = file.read(&mut self.buf[start..]).await.into_lua_err()?;
+        self.buf.truncate(start + read);
+        if read == 0 {
+            self.eof = true;
+            self.file.take();
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct CsvReaderState {
+    file: ChunkedFile,
+    header: Option<Vec<String>>,
+    records: usize,
+    closed: bool,
+}
+
+/**
+    A CSV file opened for reading row by row, without loading it into memory at once.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct CsvReader {
+    path: String,
+    delimiter: u8,
+    coerce: bool,
+    read_header: bool,
+    state: Arc<AsyncMutex<CsvReaderState>>,
+}
+
+impl CsvReader {
+    pub(crate) async fn open(path: String, options: CsvOptions) -> LuaResult<Self> {
+        let file = ChunkedFile::open(&path, options.chunk_size).await?;
+        let (read_header, header) = match options.header {
+            CsvHeader::FirstRecord => (true, None),
+            CsvHeader::None => (false, None),
+            CsvHeader::Names(names) => (false, Some(names)),
+        };
+        Ok(Self {
+            path,
+            delimiter: options.delimiter,
+            coerce: options.coerce,
+            read_header,
+            state: Arc::new(AsyncMutex::new(CsvReaderState {
+                file,
+                header,
+                records: 0,
+                closed: false,
+            })),
+        })
+    }
+
+    async fn next_record(&self, state: &mut CsvReaderState) -> LuaResult<Option<Vec<Vec<u8>>>> {
+        loop {
+            if state.closed {
+                return Err(closed_error(&self.path));
+            }
+            let parsed = parse_record(state.file.pending(), self.delimiter, state.file.eof)
+                .map_err(|e| {
+                    LuaError::runtime(format!(
+                        "Invalid CSV in '{}' at record {} - {e}",
+                        self.path,
+                        state.records + 1
+                    ))
+                })?;
+            match parsed {
+                Some((fields, used)) => {
+                    state.file.consume(used);
+                    state.records += 1;
+                    if !is_blank_record(&fields) {
+                        return Ok(Some(fields));
+                    }
+                }
+                None if state.file.eof => return Ok(None),
+                None => state.file.fill(&self.path).await?,
+            }
+        }
+    }
+
+    /**
+        Reads the header from the first record, if the file has
+        one and it has not already been read.
+    */
+    async fn read_header(&self, state: &mut CsvReaderState) -> LuaResult<()> {
+        if self.read_header
+            && state.header.is_none()
+            && let Some(fields) = self.next_record(state).await?
+        {
+            let names = fields
+                .into_iter()
+                .map(|name| String::from_utf8(name).into_lua_err())
+                .collect::<LuaResult<Vec<_>>>()?;
+            state.header = Some(names);
+        }
+        Ok(())
+    }
+
+    async fn read_row(&self, lua: &Lua) -> LuaResult<LuaValue> {
+        let mut state = self.state.lock().await;
+        self.read_header(&mut state).await?;
+        let Some(fields) = self.next_record(&mut state).await? else {
+            return Ok(LuaValue::Nil);
+        };
+        record_to_row(lua, &fields, state.header.as_deref(), self.coerce)
+            .map(LuaValue::Table)
+            .map_err(|e| {
+                LuaError::runtime(format!(
+                    "Invalid CSV in '{}' at record {} - {e}",
+                    self.path, state.records
+                ))
+            })
+    }
+}
+
+impl LuaUserData for CsvReader {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "CsvReader");
+        fields.add_field_method_get("path", |_, this| Ok(this.path.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("read", |lua, this, (): ()| {
+            let this = this.clone();
+            async move { this.read_row(&lua).await }
+        });
+        methods.add_async_method("header", |lua, this, (): ()| {
+            let this = this.clone();
+            async move {
+                let mut state = this.state.lock().await;
+                this.read_header(&mut state).await?;
+                match &state.header {
+                    Some(names) => lua
+                        .create_sequence_from(names.iter().map(String::as_str))
+                        .map(LuaValue::Table),
+                    None => Ok(LuaValue::Nil),
+                }
+            }
+        });
+        methods.add_async_method("close", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                let mut state = this.state.lock().await;
+                state.closed = true;
+                state.file.file.take();
+                Ok(())
+            }
+        });
+    }
+}
+
+#[derive(Debug)]
+struct NdjsonReaderState {
+    file: ChunkedFile,
+    lines: usize,
+    closed: bool,
+}
+
+/**
+    A newline-delimited JSON file opened for reading value
+    by value, without loading it into memory at once.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct NdjsonReader {
+    path: String,
+    state: Arc<AsyncMutex<NdjsonReaderState>>,
+}
+
+impl NdjsonReader {
+    pub(crate) async fn open(path: String, options: NdjsonOptions) -> LuaResult<Self> {
+        let file = ChunkedFile::open(&path, options.chunk_size).await?;
+        Ok(Self {
+            path,
+            state: Arc::new(AsyncMutex::new(NdjsonReaderState {
+                file,
+                lines: 0,
+                closed: false,
+            })),
+        })
+    }
+
+    async fn read_value(&self, lua: &Lua) -> LuaResult<LuaValue> {
+        let mut state = self.state.lock().await;
+        loop {
+            if state.closed {
+                return Err(closed_error(&self.path));
+            }
+            let eof = state.file.eof;
+            match split_line(state.file.pending(), eof) {
+                Some((line, used)) => {
+                    let value = if line.trim_ascii().is_empty() {
+                        None
+                    } else {
+                        let config = EncodeDecodeFormat::Json.into();
+                        Some(decode(line, lua, config).map_err(|e| {
+                            LuaError::runtime(format!(
+                                "Invalid JSON in '{}' at line {} - {e}",
+                                self.path,
+                                state.lines + 1
+                            ))
+                        })?)
+                    };
+                    state.file.consume(used);
+                    state.lines += 1;
+                    if let Some(value) = value {
+                        return Ok(value);
+                    }
+                }
+                None if eof => return Ok(LuaValue::Nil),
+                None => state.file.fill(&self.path).await?,
+            }
+        }
+    }
+}
+
+impl LuaUserData for NdjsonReader {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "NdjsonReader");
+        fields.add_field_method_get("path", |_, this| Ok(this.path.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("read", |lua, this, (): ()| {
+            let this = this.clone();
+            async move { this.read_value(&lua).await }
+        });
+        methods.add_async_method("close", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                let mut state = this.state.lock().await;
+                state.closed = true;
+                state.file.file.take();
+                Ok(())
+            }
+        });
+    }
+}
@@ -0,0 +1,131 @@
+/// The fields of a parsed record, and the number of bytes it used
+pub(crate) type ParsedRecord = (Vec<Vec<u8>>, usize);
+
+/**
+    Parses a single CSV record from the start of `input`.
+
+    Returns the fields of the record and the number of bytes it used, including
+    its line ending, or `None` if the record may continue past the end of `input`
+    and more data is needed. When `eof` is set, the end of `input` also ends the record.
+
+    Fields may be quoted with `"`, in which case they can contain delimiters,
+    line breaks, and quotes escaped by doubling them as `""`.
+*/
+pub(crate) fn parse_record(
+    input: &[u8],
+    delimiter: u8,
+    eof: bool,
+) -> Result<Option<ParsedRecord>, String> {
+    let mut fields = Vec::new();
+    let mut field = Vec::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut i = 0;
+
+    while i < input.len() {
+        let byte = input[i];
+        if in_quotes {
+            if byte == b'"' {
+                match input.get(i + 1) {
+                    Some(b'"') => {
+                        field.push(b'"');
+                        i += 1;
+                    }
+                    // NOTE: We can't know if this quote is escaped until we see the next byte
+                    None if !eof => return Ok(None),
+                    _ => in_quotes = false,
+                }
+            } else {
+                field.push(byte);
+            }
+        } else if byte == b'"' && field.is_empty() && !quoted {
+            quoted = true;
+            in_quotes = true;
+        } else if byte == delimiter {
+            fields.push(std::mem::take(&mut field));
+            quoted = false;
+        } else if byte == b'\n' || byte == b'\r' {
+            let mut end = i + 1;
+            if byte == b'\r' {
+                match input.get(end) {
+                    Some(b'\n') => end += 1,
+                    None if !eof => return Ok(None),
+                    _ => {}
+                }
+            }
+            fields.push(field);
+            return Ok(Some((fields, end)));
+        } else if quoted {
+            return Err(format!(
+                "unexpected '{}' after closing quote",
+                char::from(byte).escape_default()
+            ));
+        } else {
+            field.push(byte);
+        }
+        i += 1;
+    }
+
+    if !eof {
+        return Ok(None);
+    }
+    if in_quotes {
+        return Err("unterminated quoted field".to_string());
+    }
+    if input.is_empty() {
+        return Ok(None);
+    }
+    fields.push(field);
+    Ok(Some((fields, input.len())))
+}
+
+/**
+    Writes a single CSV record to `out`, followed by a line ending.
+
+    Fields are only quoted when necessary - when they contain the delimiter,
+    a quote, a line break, or start or end with whitespace.
+*/
+pub(crate) fn write_record<F: AsRef<[u8]>>(out: &mut Vec<u8>, fields: &[F], delimiter: u8) {
+    for (index, field) in fields.iter().enumerate() {
+        if index > 0 {
+            out.push(delimiter);
+        }
+        let field = field.as_ref();
+        let needs_quotes = field
+            .iter()
+            .any(|&b| b == delimiter || b == b'"' || b == b'\n' || b == b'\r')
+            || field.first().is_some_and(u8::is_ascii_whitespace)
+            || field.last().is_some_and(u8::is_ascii_whitespace);
+        if needs_quotes {
+            out.push(b'"');
+            for &byte in field {
+                if byte == b'"' {
+                    out.push(b'"');
+                }
+                out.push(byte);
+            }
+            out.push(b'"');
+        } else {
+            out.extend_from_slice(field);
+        }
+    }
+    out.push(b'\n');
+}
+
+/**
+    Splits a single line from the start of `input`, for line-delimited formats.
+
+    Returns the line without its line ending and the number of bytes it used,
+    or `None` if the line may continue past the end of `input`.
+*/
+pub(crate) fn split_line(input: &[u8], eof: bool) -> Option<(&[u8], usize)> {
+    if let Some(pos) = input.iter().position(|&b| b == b'\n') {
+        let line = &input[..pos];
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        Some((line, pos + 1))
+    } else if eof && !input.is_empty() {
+        Some((input, input.len()))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use async_fs::{File, OpenOptions};
+use async_lock::Mutex as AsyncMutex;
+use futures_lite::prelude::*;
+use mlua::prelude::*;
+
+use lux_serde::{EncodeDecodeFormat, encode};
+
+use crate::convert::row_to_record;
+use crate::options::{CsvHeader, CsvOptions};
+use crate::record::write_record;
+
+fn closed_error(path: &str) -> LuaError {
+    LuaError::runtime(format!("Writer for file '{path}' is closed"))
+}
+
+async fn create_file(path: &str) -> LuaResult<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .await
+        .into_lua_err()
+}
+
+/**
+    A CSV file opened for writing row by row.
+
+    When header names are given, they are written as the first record
+    and rows may be keyed by them, otherwise rows must be arrays.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct CsvWriter {
+    path: String,
+    delimiter: u8,
+    header: Option<Arc<[String]>>,
+    file: Arc<AsyncMutex<Option<File>>>,
+}
+
+impl CsvWriter {
+    pub(crate) async fn open(path: String, options: CsvOptions) -> LuaResult<Self> {
+        let mut file = create_file(&path).await?;
+        let header = match options.header {
+            CsvHeader::Names(names) => {
+                let mut out = Vec::new();
+                write_record(&mut out, &names, options.delimiter);
+                file.write_all(&out).await.into_lua_err()?;
+                Some(names.into())
+            }
+            CsvHeader::FirstRecord | CsvHeader::None => None,
+        };
+        Ok(Self {
+            path,
+            delimiter: options.delimiter,
+            header,
+            file: Arc::new(AsyncMutex::new(Some(file))),
+        })
+    }
+
+    /**
+        Encodes rows into CSV records, to be written all at once.
+    */
+    fn encode_rows(&self, rows: LuaMultiValue) -> LuaResult<Vec<u8>> {
+        let mut out = Vec::new();
+        for row in rows {
+            let LuaValue::Table(row) = row else {
+                return Err(LuaError::runtime(format!(
+                    "Expected row to be a table, got {}",
+                    row.type_name()
+                )));
+            };
+            let record = row_to_record(&row, self.header.as_deref())?;
+            write_record(&mut out, &record, self.delimiter);
+        }
+        Ok(out)
+    }
+}
+
+impl LuaUserData for CsvWriter {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "CsvWriter");
+        fields.add_field_method_get("path", |_, this| Ok(this.path.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("write", |_, this, rows: LuaMultiValue| {
+            let this = this.clone();
+            let encoded = this.encode_rows(rows);
+            async move {
+                let encoded = encoded?;
+                let mut file = this.file.lock().await;
+                let file = file.as_mut().ok_or_else(|| closed_error(&this.path))?;
+                file.write_all(&encoded).await.into_lua_err()
+            }
+        });
+        methods.add_async_method("close", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                if let Some(mut file) = this.file.lock().await.take() {
+                    file.flush().await.into_lua_err()?;
+                }
+                Ok(())
+            }
+        });
+    }
+}
+
+/**
+    A newline-delimited JSON file opened for writing value by value.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct NdjsonWriter {
+    path: String,
+    file: Arc<AsyncMutex<Option<File>>>,
+}
+
+impl NdjsonWriter {
+    pub(crate) async fn open(path: String) -> LuaResult<Self> {
+        let file = create_file(&path).await?;
+        Ok(Self {
+            path,
+            file: Arc::new(AsyncMutex::new(Some(file))),
+        })
+    }
+}
+
+impl LuaUserData for NdjsonWriter {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "NdjsonWriter");
+        fields.add_field_method_get("path", |_, this| Ok(this.path.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("write", |lua, this, values: LuaMultiValue| {
+            let this = this.clone();
+            let encoded = values.into_iter().try_fold(Vec::new(), |mut out, value| {
+                let line = encode(value, &lua, EncodeDecodeFormat::Json.into())?;
+                out.extend_from_slice(&line.as_bytes());
+                out.push(b'\n');
+                Ok::<_, LuaError>(out)
+            });
+            async move {
+                let encoded = encoded?;
+                let mut file = this.file.lock().await;
+                let file = file.as_mut().ok_or_else(|| closed_error(&this.path))?;
+                file.write_all(&encoded).await.into_lua_err()
+            }
+        });
+        methods.add_async_method("close", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                if let Some(mut file) = this.file.lock().await.take() {
+                    file.flush().await.into_lua_err()?;
+                }
+                Ok(())
+            }
+        });
+    }
+}
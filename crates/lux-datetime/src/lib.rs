@@ -15,13 +15,19 @@
 //! - :ToUniversalTime(), :ToLocalTime(), :ToIsoDate()
 //! - :FormatUniversalTime(format, locale)
 //! - :FormatLocalTime(format, locale)
+//! - :IsLeapYear(zone), :DaysInMonth(zone)
+//! - :StartOfDay(zone), :StartOfWeek(zone), :StartOfMonth(zone)
+//! - :AddBusinessDays(days, holidays, zone)
+//!
+//! Calendar methods work on the date in UTC unless given the "Local" zone.
 
 use chrono::{
-    DateTime as ChronoDateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+    DateTime as ChronoDateTime, Datelike, Local, LocalResult, Months, NaiveDate, NaiveDateTime,
+    NaiveTime, TimeDelta, TimeZone, Utc, Weekday,
 };
-use lux_utils::TableBuilder;
+use lux_utils::{LuxError, TableBuilder};
 use mlua::prelude::*;
-use std::cmp::Ordering;
+use std::{cmp::Ordering, collections::HashSet};
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
@@ -143,6 +149,152 @@ impl DateTime {
             .format(fmt.unwrap_or("%Y-%m-%d %H:%M:%S"))
             .to_string()
     }
+
+    /// Date and wall clock time in the given zone
+    fn naive(&self, zone: Zone) -> NaiveDateTime {
+        match zone {
+            Zone::Universal => self.inner.naive_utc(),
+            Zone::Local => self.inner.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// From a date and wall clock time in the given zone
+    fn from_naive(naive: NaiveDateTime, zone: Zone) -> Option<Self> {
+        let inner = match zone {
+            Zone::Universal => Utc.from_utc_datetime(&naive),
+            Zone::Local => resolve_local(naive)?,
+        };
+        Some(Self { inner })
+    }
+
+    /// Whether the year is a leap year
+    #[must_use]
+    pub fn is_leap_year(&self, zone: Zone) -> bool {
+        NaiveDate::from_ymd_opt(self.naive(zone).year(), 2, 29).is_some()
+    }
+
+    /// Number of days in the month
+    #[must_use]
+    pub fn days_in_month(&self, zone: Zone) -> u32 {
+        let first = self.naive(zone).date().with_day(1).unwrap_or_default();
+        first
+            .checked_add_months(Months::new(1))
+            .map_or(31, |next| (next - first).num_days() as u32)
+    }
+
+    /// Midnight at the start of the day
+    #[must_use]
+    pub fn start_of_day(&self, zone: Zone) -> Option<Self> {
+        Self::from_naive(self.naive(zone).date().and_time(NaiveTime::MIN), zone)
+    }
+
+    /// Midnight at the start of the ISO week, which starts on Monday
+    #[must_use]
+    pub fn start_of_week(&self, zone: Zone) -> Option<Self> {
+        let date = self.naive(zone).date();
+        let monday = date.checked_sub_signed(TimeDelta::days(i64::from(
+            date.weekday().num_days_from_monday(),
+        )))?;
+        Self::from_naive(monday.and_time(NaiveTime::MIN), zone)
+    }
+
+    /// Midnight at the start of the month
+    #[must_use]
+    pub fn start_of_month(&self, zone: Zone) -> Option<Self> {
+        let first = self.naive(zone).date().with_day(1)?;
+        Self::from_naive(first.and_time(NaiveTime::MIN), zone)
+    }
+
+    /// Moves by a number of business days, skipping weekends and holidays
+    /// and keeping the wall clock time. Negative amounts move backwards.
+    #[must_use]
+    pub fn add_business_days(
+        &self,
+        days: i64,
+        holidays: &HashSet<NaiveDate>,
+        zone: Zone,
+    ) -> Option<Self> {
+        let naive = self.naive(zone);
+        let step = TimeDelta::days(days.signum());
+        let mut date = naive.date();
+        let mut remaining = days.unsigned_abs();
+        while remaining > 0 {
+            date = date.checked_add_signed(step)?;
+            let weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+            if !weekend && !holidays.contains(&date) {
+                remaining -= 1;
+            }
+        }
+        Self::from_naive(date.and_time(naive.time()), zone)
+    }
+}
+
+/// Resolves a local date and time to an instant, taking the earlier of repeated
+/// times and the first time after a gap, for when daylight saving time changes
+fn resolve_local(naive: NaiveDateTime) -> Option<ChronoDateTime<Utc>> {
+    (0..=96).find_map(|quarters| {
+        let naive = naive.checked_add_signed(TimeDelta::minutes(15 * quarters))?;
+        match Local.from_local_datetime(&naive) {
+            LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => Some(dt.with_timezone(&Utc)),
+            LocalResult::None => None,
+        }
+    })
+}
+
+/// The zone that calendar methods look at the date in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Zone {
+    #[default]
+    Universal,
+    Local,
+}
+
+impl FromLua for Zone {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match &value {
+            LuaValue::Nil => Ok(Self::Universal),
+            LuaValue::String(s) => match &*s.to_str()? {
+                "Universal" | "UTC" => Ok(Self::Universal),
+                "Local" => Ok(Self::Local),
+                other => Err(LuxError::Value(format!(
+                    "invalid zone '{other}', expected 'Universal' or 'Local'"
+                ))
+                .into()),
+            },
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Zone".to_string(),
+                message: Some("expected 'Universal' or 'Local'".into()),
+            }),
+        }
+    }
+}
+
+/// Reads holidays as the dates of DateTimes in the given zone, or "YYYY-MM-DD" strings
+fn holiday_dates(holidays: Option<LuaTable>, zone: Zone) -> LuaResult<HashSet<NaiveDate>> {
+    let mut dates = HashSet::new();
+    let Some(holidays) = holidays else {
+        return Ok(dates);
+    };
+    for value in holidays.sequence_values::<LuaValue>() {
+        let date = match value? {
+            LuaValue::UserData(ud) => ud.borrow::<DateTime>()?.naive(zone).date(),
+            LuaValue::String(s) => {
+                let s = s.to_str()?;
+                NaiveDate::parse_from_str(&s, "%Y-%m-%d").map_err(|_| {
+                    LuxError::Parse(format!("invalid holiday '{s}', expected YYYY-MM-DD"))
+                })?
+            }
+            other => {
+                return Err(LuaError::runtime(format!(
+                    "holidays must be DateTimes or date strings, got {}",
+                    other.type_name()
+                )));
+            }
+        };
+        dates.insert(date);
+    }
+    Ok(dates)
 }
 
 /// DateTimeValues table
@@ -155,12 +307,16 @@ pub struct DateTimeValues {
     pub minute: u32,
     pub second: u32,
     pub millisecond: u32,
+    pub week_of_year: u32,
+    pub week_year: i32,
+    pub day_of_year: u32,
+    pub day_of_week: u32,
 }
 
 impl DateTimeValues {
     fn from_chrono<T: TimeZone>(dt: &ChronoDateTime<T>) -> Self {
-        use chrono::Datelike;
         use chrono::Timelike;
+        let week = dt.iso_week();
         Self {
             year: dt.year(),
             month: dt.month(),
@@ -169,6 +325,10 @@ impl DateTimeValues {
             minute: dt.minute(),
             second: dt.second(),
             millisecond: dt.timestamp_subsec_millis(),
+            week_of_year: week.week(),
+            week_year: week.year(),
+            day_of_year: dt.ordinal(),
+            day_of_week: dt.weekday().number_from_monday(),
         }
     }
 }
@@ -183,6 +343,10 @@ impl IntoLua for DateTimeValues {
         t.set("Minute", self.minute)?;
         t.set("Second", self.second)?;
         t.set("Millisecond", self.millisecond)?;
+        t.set("WeekOfYear", self.week_of_year)?;
+        t.set("WeekYear", self.week_year)?;
+        t.set("DayOfYear", self.day_of_year)?;
+        t.set("DayOfWeek", self.day_of_week)?;
         Ok(LuaValue::Table(t))
     }
 }
@@ -228,6 +392,36 @@ impl LuaUserData for DateTime {
                 Ok(this.format_local_time(fmt.as_deref(), locale.as_deref()))
             },
         );
+
+        methods.add_method("IsLeapYear", |_, this, zone: Zone| {
+            Ok(this.is_leap_year(zone))
+        });
+        methods.add_method("DaysInMonth", |_, this, zone: Zone| {
+            Ok(this.days_in_month(zone))
+        });
+        methods.add_method("StartOfDay", |_, this, zone: Zone| {
+            this.start_of_day(zone)
+                .ok_or_else(|| LuxError::Value("start of day is out of range".to_string()).into())
+        });
+        methods.add_method("StartOfWeek", |_, this, zone: Zone| {
+            this.start_of_week(zone)
+                .ok_or_else(|| LuxError::Value("start of week is out of range".to_string()).into())
+        });
+        methods.add_method("StartOfMonth", |_, this, zone: Zone| {
+            this.start_of_month(zone)
+                .ok_or_else(|| LuxError::Value("start of month is out of range".to_string()).into())
+        });
+        methods.add_method(
+            "AddBusinessDays",
+            |_, this, (days, holidays, zone): (i64, Option<LuaTable>, Zone)| {
+                let holidays = holiday_dates(holidays, zone)?;
+                this.add_business_days(days, &holidays, zone)
+                    .ok_or_else(|| {
+                        LuxError::Value(format!("adding {days} business days is out of range"))
+                            .into()
+                    })
+            },
+        );
     }
 }
 
@@ -239,13 +433,13 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
             DateTime::from_unix_timestamp(secs)
                 .map(|dt| lua.create_userdata(dt))
                 .transpose()?
-                .ok_or_else(|| LuaError::external("invalid timestamp"))
+                .ok_or_else(|| LuxError::Value(format!("invalid timestamp {secs}")).into())
         })?
         .with_function("fromUnixTimestampMillis", |lua, millis: f64| {
             DateTime::from_unix_timestamp_millis(millis.round() as i64)
                 .map(|dt| lua.create_userdata(dt))
                 .transpose()?
-                .ok_or_else(|| LuaError::external("invalid timestamp"))
+                .ok_or_else(|| LuxError::Value(format!("invalid timestamp {millis}ms")).into())
         })?
         .with_function("fromUniversalTime", |lua, args: LuaMultiValue| {
             let a: Vec<LuaValue> = args.into_vec();
@@ -259,7 +453,7 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
             DateTime::from_universal_time(year, month, day, hour, min, sec, ms)
                 .map(|dt| lua.create_userdata(dt))
                 .transpose()?
-                .ok_or_else(|| LuaError::external("invalid date/time"))
+                .ok_or_else(|| LuxError::Value("invalid date/time".to_string()).into())
         })?
         .with_function("fromLocalTime", |lua, args: LuaMultiValue| {
             let a: Vec<LuaValue> = args.into_vec();
@@ -273,13 +467,13 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
             DateTime::from_local_time(year, month, day, hour, min, sec, ms)
                 .map(|dt| lua.create_userdata(dt))
                 .transpose()?
-                .ok_or_else(|| LuaError::external("invalid date/time"))
+                .ok_or_else(|| LuxError::Value("invalid date/time".to_string()).into())
         })?
         .with_function("fromIsoDate", |lua, s: String| {
             DateTime::from_iso_date(&s)
                 .map(|dt| lua.create_userdata(dt))
                 .transpose()?
-                .ok_or_else(|| LuaError::external("invalid ISO date"))
+                .ok_or_else(|| LuxError::Parse(format!("invalid ISO date '{s}'")).into())
         })?
         .build_readonly()
         .map(LuaValue::Table)
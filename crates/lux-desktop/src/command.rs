@@ -0,0 +1,135 @@
+use std::{
+    io::ErrorKind,
+    process::{Output, Stdio},
+};
+
+use async_process::Command;
+use futures_lite::prelude::*;
+use mlua::prelude::*;
+
+/**
+    An external program that provides some desktop integration, such as `xclip` or `pbcopy`.
+
+    Anything passed to scripts, such as notification text, should be given as
+    arguments or environment variables instead of being formatted into the script.
+*/
+pub struct Tool {
+    program: &'static str,
+    args: Vec<String>,
+    envs: Vec<(&'static str, String)>,
+}
+
+impl Tool {
+    pub fn new(program: &'static str, args: &[&str]) -> Self {
+        Self {
+            program,
+            args: args.iter().map(ToString::to_string).collect(),
+            envs: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    // NOTE: None of the tools used on Linux need environment variables
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    #[must_use]
+    pub fn env(mut self, key: &'static str, value: impl Into<String>) -> Self {
+        self.envs.push((key, value.into()));
+        self
+    }
+
+    /**
+        Runs the program to completion, writing `input` to its stdin.
+
+        If `capture` is `false`, the output of the program is discarded instead,
+        which is required for clipboard tools that fork to keep serving the
+        clipboard, since they would otherwise keep our pipes open forever.
+    */
+    async fn spawn(self, input: Option<Vec<u8>>, capture: bool) -> LuaResult<Output> {
+        let (stdout, stderr) = if capture {
+            (Stdio::piped(), Stdio::piped())
+        } else {
+            (Stdio::null(), Stdio::null())
+        };
+        let mut child = Command::new(self.program)
+            .args(&self.args)
+            .envs(self.envs)
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(stdout)
+            .stderr(stderr)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                ErrorKind::NotFound => LuaError::runtime(format!(
+                    "'{}' was not found, make sure that it is installed",
+                    self.program
+                )),
+                _ => LuaError::runtime(format!("Failed to run '{}' - {e}", self.program)),
+            })?;
+
+        if let Some(input) = input {
+            let mut stdin = child.stdin.take().expect("stdin is piped when given input");
+            stdin.write_all(&input).await?;
+            stdin.close().await?;
+        }
+
+        Ok(child.output().await?)
+    }
+
+    /**
+        Runs the program and returns its full output, even if it failed.
+    */
+    pub async fn output(self) -> LuaResult<Output> {
+        self.spawn(None, true).await
+    }
+
+    /**
+        Runs the program and returns its stdout, erroring if it failed.
+    */
+    pub async fn read(self) -> LuaResult<Vec<u8>> {
+        let program = self.program;
+        let output = self.spawn(None, true).await?;
+        if output.status.success() {
+            Ok(output.stdout)
+        } else {
+            Err(failed(program, &output.stderr))
+        }
+    }
+
+    /**
+        Runs the program with the given stdin, discarding its output and erroring if it failed.
+    */
+    pub async fn write(self, input: Vec<u8>) -> LuaResult<()> {
+        let program = self.program;
+        let output = self.spawn(Some(input), false).await?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(failed(program, &[]))
+        }
+    }
+
+    /**
+        Runs the program, erroring with its error output if it failed.
+    */
+    pub async fn run(self) -> LuaResult<()> {
+        self.read().await.map(|_| ())
+    }
+}
+
+fn failed(program: &str, stderr: &[u8]) -> LuaError {
+    let stderr = String::from_utf8_lossy(stderr);
+    let stderr = stderr.trim();
+    if stderr.is_empty() {
+        LuaError::runtime(format!("'{program}' failed"))
+    } else {
+        LuaError::runtime(format!("'{program}' failed - {stderr}"))
+    }
+}
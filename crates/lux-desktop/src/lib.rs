@@ -0,0 +1,112 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::time::Duration;
+
+use mlua::prelude::*;
+
+use lux_image::Image;
+use lux_utils::TableBuilder;
+
+mod command;
+mod platform;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `desktop` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `desktop` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let clipboard = TableBuilder::new(lua.clone())?
+        .with_async_function("getText", clipboard_get_text)?
+        .with_async_function("setText", clipboard_set_text)?
+        .with_async_function("getImage", clipboard_get_image)?
+        .with_async_function("setImage", clipboard_set_image)?
+        .build_readonly()?;
+    TableBuilder::new(lua)?
+        .with_value("clipboard", clipboard)?
+        .with_async_function("notify", desktop_notify)?
+        .with_async_function("open", desktop_open)?
+        .build_readonly()
+}
+
+/**
+    Options for `desktop.notify`.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+struct NotifyOptions {
+    timeout: Option<Duration>,
+}
+
+impl FromLua for NotifyOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let timeout = match t.get::<Option<f64>>("timeout")? {
+                    None => None,
+                    Some(secs) if secs.is_finite() && secs > 0.0 => {
+                        Some(Duration::from_secs_f64(secs))
+                    }
+                    Some(secs) => {
+                        return Err(LuaError::runtime(format!(
+                            "Expected timeout to be a positive number, got {secs}"
+                        )));
+                    }
+                };
+                Ok(Self { timeout })
+            }
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("NotifyOptions"),
+                message: Some(String::from("Expected options to be a table")),
+            }),
+        }
+    }
+}
+
+async fn clipboard_get_text(_: Lua, (): ()) -> LuaResult<String> {
+    platform::get_text().await
+}
+
+async fn clipboard_set_text(_: Lua, text: String) -> LuaResult<()> {
+    platform::set_text(text).await
+}
+
+async fn clipboard_get_image(_: Lua, (): ()) -> LuaResult<Option<Image>> {
+    platform::get_image()
+        .await?
+        .map(|png| lux_image::decode(&png))
+        .transpose()
+}
+
+async fn clipboard_set_image(_: Lua, image: LuaUserDataRef<Image>) -> LuaResult<()> {
+    let png = lux_image::encode_png(&image)?;
+    drop(image);
+    platform::set_image(png).await
+}
+
+async fn desktop_notify(
+    _: Lua,
+    (title, body, options): (String, Option<String>, NotifyOptions),
+) -> LuaResult<()> {
+    platform::notify(title, body.unwrap_or_default(), options.timeout).await
+}
+
+async fn desktop_open(_: Lua, target: String) -> LuaResult<()> {
+    if target.trim().is_empty() {
+        return Err(LuaError::runtime("Expected a path or URL to open"));
+    }
+    platform::open(target).await
+}
@@ -0,0 +1,317 @@
+//! Platform-specific implementations, each using the tools that ship with
+//! (or are commonly installed on) that platform:
+//! - Windows: PowerShell, with .NET for the clipboard and WinRT for notifications
+//! - Linux: wl-clipboard on Wayland or xclip on X11, notify-send and xdg-open
+//! - macOS: pbcopy / pbpaste, osascript and open
+
+use std::process::Output;
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+use mlua::prelude::*;
+
+/**
+    Returns the text a clipboard tool printed, treating failures as an empty
+    clipboard since most tools fail when there is no text to paste.
+*/
+fn text_or_empty(output: &Output) -> String {
+    if output.status.success() {
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    } else {
+        String::new()
+    }
+}
+
+/**
+    Returns a unique path in the temporary directory, for tools that can only read or write files.
+*/
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn temp_path(extension: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "lux-desktop-{}-{n}.{extension}",
+        std::process::id()
+    ))
+}
+
+/**
+    Reads and removes a temporary file, returning `None` if it was never created.
+*/
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn take_temp_file(path: &Path) -> LuaResult<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(bytes) => {
+            let _ = std::fs::remove_file(path);
+            Ok(Some(bytes))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use std::time::Duration;
+
+    use mlua::prelude::*;
+
+    use super::{take_temp_file, temp_path, text_or_empty};
+    use crate::command::Tool;
+
+    const GET_TEXT_PS: &str = r"
+Add-Type -AssemblyName System.Windows.Forms
+[Console]::OutputEncoding = [Text.Encoding]::UTF8
+[Console]::Out.Write([Windows.Forms.Clipboard]::GetText())
+";
+
+    const SET_TEXT_PS: &str = r"
+Add-Type -AssemblyName System.Windows.Forms
+[Console]::InputEncoding = [Text.Encoding]::UTF8
+$text = [Console]::In.ReadToEnd()
+if ($text) { [Windows.Forms.Clipboard]::SetText($text) } else { [Windows.Forms.Clipboard]::Clear() }
+";
+
+    const GET_IMAGE_PS: &str = r"
+Add-Type -AssemblyName System.Windows.Forms, System.Drawing
+$image = [Windows.Forms.Clipboard]::GetImage()
+if ($image) { $image.Save($env:LUX_DESKTOP_PATH, [Drawing.Imaging.ImageFormat]::Png) }
+";
+
+    const SET_IMAGE_PS: &str = r"
+Add-Type -AssemblyName System.Windows.Forms, System.Drawing
+$image = [Drawing.Image]::FromFile($env:LUX_DESKTOP_PATH)
+[Windows.Forms.Clipboard]::SetImage($image)
+$image.Dispose()
+";
+
+    const NOTIFY_PS: &str = r"
+$null = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]
+$template = [Windows.UI.Notifications.ToastTemplateType]::ToastText02
+$xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent($template)
+$texts = $xml.GetElementsByTagName('text')
+$null = $texts.Item(0).AppendChild($xml.CreateTextNode($env:LUX_DESKTOP_TITLE))
+$null = $texts.Item(1).AppendChild($xml.CreateTextNode($env:LUX_DESKTOP_BODY))
+$toast = [Windows.UI.Notifications.ToastNotification]::new($xml)
+if ($env:LUX_DESKTOP_TIMEOUT) {
+    $toast.ExpirationTime = [DateTimeOffset]::Now.AddSeconds([double]$env:LUX_DESKTOP_TIMEOUT)
+}
+# NOTE: Toasts need a registered app id, so borrow the one that PowerShell itself uses
+$app = '{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe'
+[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier($app).Show($toast)
+";
+
+    fn powershell(script: &str) -> Tool {
+        Tool::new(
+            "powershell",
+            &["-NoProfile", "-NonInteractive", "-Sta", "-Command", script],
+        )
+    }
+
+    pub async fn get_text() -> LuaResult<String> {
+        Ok(text_or_empty(&powershell(GET_TEXT_PS).output().await?))
+    }
+
+    pub async fn set_text(text: String) -> LuaResult<()> {
+        powershell(SET_TEXT_PS).write(text.into_bytes()).await
+    }
+
+    pub async fn get_image() -> LuaResult<Option<Vec<u8>>> {
+        let path = temp_path("png");
+        powershell(GET_IMAGE_PS)
+            .env("LUX_DESKTOP_PATH", path.to_string_lossy())
+            .run()
+            .await?;
+        take_temp_file(&path)
+    }
+
+    pub async fn set_image(png: Vec<u8>) -> LuaResult<()> {
+        let path = temp_path("png");
+        std::fs::write(&path, png)?;
+        let result = powershell(SET_IMAGE_PS)
+            .env("LUX_DESKTOP_PATH", path.to_string_lossy())
+            .run()
+            .await;
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    pub async fn notify(title: String, body: String, timeout: Option<Duration>) -> LuaResult<()> {
+        let mut tool = powershell(NOTIFY_PS)
+            .env("LUX_DESKTOP_TITLE", title)
+            .env("LUX_DESKTOP_BODY", body);
+        if let Some(timeout) = timeout {
+            tool = tool.env("LUX_DESKTOP_TIMEOUT", timeout.as_secs_f64().to_string());
+        }
+        tool.run().await
+    }
+
+    pub async fn open(target: String) -> LuaResult<()> {
+        Tool::new("rundll32", &["url.dll,FileProtocolHandler"])
+            .arg(target)
+            .run()
+            .await
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::time::Duration;
+
+    use mlua::prelude::*;
+
+    use super::text_or_empty;
+    use crate::command::Tool;
+
+    fn is_wayland() -> bool {
+        std::env::var_os("WAYLAND_DISPLAY").is_some()
+    }
+
+    fn paste(mime: Option<&str>) -> Tool {
+        let tool = if is_wayland() {
+            Tool::new("wl-paste", &["--no-newline"])
+        } else {
+            Tool::new("xclip", &["-selection", "clipboard", "-out"])
+        };
+        match mime {
+            Some(mime) if is_wayland() => tool.arg("--type").arg(mime),
+            Some(mime) => tool.arg("-target").arg(mime),
+            None => tool,
+        }
+    }
+
+    fn copy(mime: Option<&str>) -> Tool {
+        let tool = if is_wayland() {
+            Tool::new("wl-copy", &[])
+        } else {
+            Tool::new("xclip", &["-selection", "clipboard", "-in"])
+        };
+        match mime {
+            Some(mime) if is_wayland() => tool.arg("--type").arg(mime),
+            Some(mime) => tool.arg("-target").arg(mime),
+            None => tool,
+        }
+    }
+
+    pub async fn get_text() -> LuaResult<String> {
+        Ok(text_or_empty(&paste(None).output().await?))
+    }
+
+    pub async fn set_text(text: String) -> LuaResult<()> {
+        copy(None).write(text.into_bytes()).await
+    }
+
+    pub async fn get_image() -> LuaResult<Option<Vec<u8>>> {
+        // NOTE: Both tools fail when the clipboard has no image in it
+        let output = paste(Some("image/png")).output().await?;
+        Ok((output.status.success() && !output.stdout.is_empty()).then_some(output.stdout))
+    }
+
+    pub async fn set_image(png: Vec<u8>) -> LuaResult<()> {
+        copy(Some("image/png")).write(png).await
+    }
+
+    pub async fn notify(title: String, body: String, timeout: Option<Duration>) -> LuaResult<()> {
+        let mut tool = Tool::new("notify-send", &[]);
+        if let Some(timeout) = timeout {
+            tool = tool.arg(format!("--expire-time={}", timeout.as_millis()));
+        }
+        tool.arg("--").arg(title).arg(body).run().await
+    }
+
+    pub async fn open(target: String) -> LuaResult<()> {
+        Tool::new("xdg-open", &[]).arg(target).run().await
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use std::time::Duration;
+
+    use mlua::prelude::*;
+
+    use super::{take_temp_file, temp_path, text_or_empty};
+    use crate::command::Tool;
+
+    const GET_IMAGE_APPLESCRIPT: &str = r#"
+on run argv
+    try
+        set png to the clipboard as «class PNGf»
+    on error
+        return
+    end try
+    set f to open for access (POSIX file (item 1 of argv)) with write permission
+    write png to f
+    close access f
+end run
+"#;
+
+    const SET_IMAGE_APPLESCRIPT: &str = r#"
+on run argv
+    set the clipboard to (read (POSIX file (item 1 of argv)) as «class PNGf»)
+end run
+"#;
+
+    const NOTIFY_APPLESCRIPT: &str = r#"
+on run argv
+    display notification (item 2 of argv) with title (item 1 of argv)
+end run
+"#;
+
+    fn osascript(script: &str) -> Tool {
+        Tool::new("osascript", &["-e", script])
+    }
+
+    // NOTE: pbcopy and pbpaste use the locale to pick an encoding, which may not be UTF-8
+    fn pasteboard(program: &'static str) -> Tool {
+        Tool::new(program, &[]).env("LANG", "en_US.UTF-8")
+    }
+
+    pub async fn get_text() -> LuaResult<String> {
+        Ok(text_or_empty(&pasteboard("pbpaste").output().await?))
+    }
+
+    pub async fn set_text(text: String) -> LuaResult<()> {
+        pasteboard("pbcopy").write(text.into_bytes()).await
+    }
+
+    pub async fn get_image() -> LuaResult<Option<Vec<u8>>> {
+        let path = temp_path("png");
+        osascript(GET_IMAGE_APPLESCRIPT)
+            .arg(path.to_string_lossy())
+            .run()
+            .await?;
+        take_temp_file(&path)
+    }
+
+    pub async fn set_image(png: Vec<u8>) -> LuaResult<()> {
+        let path = temp_path("png");
+        std::fs::write(&path, png)?;
+        let result = osascript(SET_IMAGE_APPLESCRIPT)
+            .arg(path.to_string_lossy())
+            .run()
+            .await;
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    pub async fn notify(title: String, body: String, _timeout: Option<Duration>) -> LuaResult<()> {
+        // NOTE: Notification Center decides how long notifications are shown for
+        osascript(NOTIFY_APPLESCRIPT)
+            .arg(title)
+            .arg(body)
+            .run()
+            .await
+    }
+
+    pub async fn open(target: String) -> LuaResult<()> {
+        Tool::new("open", &[]).arg(target).run().await
+    }
+}
+
+pub use imp::*;
@@ -0,0 +1,238 @@
+use std::f64::consts::PI;
+
+use lux_enum::EnumItem;
+use mlua::prelude::*;
+
+/// Overshoot of the `Back` style
+const BACK_OVERSHOOT: f64 = 1.701_58;
+/// Period of the `Elastic` style, as a fraction of the whole curve
+const ELASTIC_PERIOD: f64 = 0.3;
+
+/**
+    The shape of an easing curve, matching `Enum.EasingStyle`.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EasingStyle {
+    Linear,
+    #[default]
+    Quad,
+    Cubic,
+    Quart,
+    Quint,
+    Sine,
+    Expo,
+    Circ,
+    Elastic,
+    Back,
+    Bounce,
+}
+
+impl EasingStyle {
+    pub const ALL: &'static [Self] = &[
+        Self::Linear,
+        Self::Quad,
+        Self::Cubic,
+        Self::Quart,
+        Self::Quint,
+        Self::Sine,
+        Self::Expo,
+        Self::Circ,
+        Self::Elastic,
+        Self::Back,
+        Self::Bounce,
+    ];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Linear => "Linear",
+            Self::Quad => "Quad",
+            Self::Cubic => "Cubic",
+            Self::Quart => "Quart",
+            Self::Quint => "Quint",
+            Self::Sine => "Sine",
+            Self::Expo => "Expo",
+            Self::Circ => "Circ",
+            Self::Elastic => "Elastic",
+            Self::Back => "Back",
+            Self::Bounce => "Bounce",
+        }
+    }
+
+    /**
+        Returns the style with the given `Enum.EasingStyle` value.
+    */
+    #[must_use]
+    pub fn from_value(value: i32) -> Option<Self> {
+        usize::try_from(value)
+            .ok()
+            .and_then(|index| Self::ALL.get(index).copied())
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|style| style.name() == name)
+    }
+
+    /**
+        Evaluates the `In` curve of this style, which starts slow and ends fast.
+    */
+    #[must_use]
+    pub fn ease_in(self, t: f64) -> f64 {
+        match self {
+            Self::Linear => t,
+            Self::Quad => t.powi(2),
+            Self::Cubic => t.powi(3),
+            Self::Quart => t.powi(4),
+            Self::Quint => t.powi(5),
+            Self::Sine => 1.0 - (t * PI / 2.0).cos(),
+            Self::Expo => {
+                if t <= 0.0 {
+                    0.0
+                } else {
+                    2f64.powf(10.0 * (t - 1.0))
+                }
+            }
+            Self::Circ => 1.0 - (1.0 - t * t).max(0.0).sqrt(),
+            Self::Elastic => {
+                if t <= 0.0 || t >= 1.0 {
+                    return t;
+                }
+                let shift = ELASTIC_PERIOD / 4.0;
+                -(2f64.powf(10.0 * (t - 1.0)))
+                    * ((t - 1.0 - shift) * 2.0 * PI / ELASTIC_PERIOD).sin()
+            }
+            Self::Back => t * t * ((BACK_OVERSHOOT + 1.0) * t - BACK_OVERSHOOT),
+            Self::Bounce => 1.0 - bounce_out(1.0 - t),
+        }
+    }
+}
+
+/**
+    Which end of the curve is eased, matching `Enum.EasingDirection`.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum EasingDirection {
+    In,
+    #[default]
+    Out,
+    InOut,
+}
+
+impl EasingDirection {
+    pub const ALL: &'static [Self] = &[Self::In, Self::Out, Self::InOut];
+
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::In => "In",
+            Self::Out => "Out",
+            Self::InOut => "InOut",
+        }
+    }
+
+    /**
+        Returns the direction with the given `Enum.EasingDirection` value.
+    */
+    #[must_use]
+    pub fn from_value(value: i32) -> Option<Self> {
+        usize::try_from(value)
+            .ok()
+            .and_then(|index| Self::ALL.get(index).copied())
+    }
+
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|dir| dir.name() == name)
+    }
+}
+
+/**
+    Converts an `Enum.EasingStyle` or `Enum.EasingDirection` item into one of the given
+    values, accepting plain number items, `EnumItem` userdata, and item names as strings.
+*/
+fn enum_from_lua<T: Copy>(
+    value: &LuaValue,
+    enum_type: &'static str,
+    from_value: fn(i32) -> Option<T>,
+    from_name: fn(&str) -> Option<T>,
+) -> LuaResult<T> {
+    let item = match value {
+        LuaValue::Integer(i) => i32::try_from(*i).ok().and_then(from_value),
+        LuaValue::Number(n) if n.fract() == 0.0 => from_value(*n as i32),
+        LuaValue::String(s) => s.to_str().ok().and_then(|s| from_name(&s)),
+        LuaValue::UserData(ud) => match ud.borrow::<EnumItem>() {
+            Ok(item) if item.enum_type() == enum_type => from_value(item.value()),
+            _ => None,
+        },
+        _ => None,
+    };
+    item.ok_or_else(|| LuaError::FromLuaConversionError {
+        from: value.type_name(),
+        to: enum_type.to_string(),
+        message: Some(format!(
+            "expected an Enum.{enum_type} item or name, got {}",
+            value
+                .to_string()
+                .unwrap_or_else(|_| value.type_name().to_string())
+        )),
+    })
+}
+
+impl FromLua for EasingStyle {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        enum_from_lua(&value, "EasingStyle", Self::from_value, Self::from_name)
+    }
+}
+
+impl FromLua for EasingDirection {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        enum_from_lua(&value, "EasingDirection", Self::from_value, Self::from_name)
+    }
+}
+
+/**
+    Evaluates an easing curve at `alpha`, which is clamped to be between `0` and `1`.
+
+    Curves always start at `0` and end at `1`, but `Back` and `Elastic` overshoot in between.
+
+    - `In` curves start slow and end fast
+    - `Out` curves are `In` curves mirrored, starting fast and ending slow
+    - `InOut` curves run the `In` curve over the first half, and the `Out` curve over the second
+*/
+#[must_use]
+pub fn ease(style: EasingStyle, direction: EasingDirection, alpha: f64) -> f64 {
+    let t = if alpha.is_nan() {
+        0.0
+    } else {
+        alpha.clamp(0.0, 1.0)
+    };
+    match direction {
+        EasingDirection::In => style.ease_in(t),
+        EasingDirection::Out => 1.0 - style.ease_in(1.0 - t),
+        EasingDirection::InOut => {
+            if t < 0.5 {
+                style.ease_in(t * 2.0) / 2.0
+            } else {
+                1.0 - style.ease_in((1.0 - t) * 2.0) / 2.0
+            }
+        }
+    }
+}
+
+fn bounce_out(t: f64) -> f64 {
+    const SCALE: f64 = 7.5625;
+    const WIDTH: f64 = 2.75;
+    if t < 1.0 / WIDTH {
+        SCALE * t * t
+    } else if t < 2.0 / WIDTH {
+        let t = t - 1.5 / WIDTH;
+        SCALE * t * t + 0.75
+    } else if t < 2.5 / WIDTH {
+        let t = t - 2.25 / WIDTH;
+        SCALE * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / WIDTH;
+        SCALE * t * t + 0.984_375
+    }
+}
@@ -0,0 +1,80 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::{Buffer, prelude::*};
+
+use lux_utils::TableBuilder;
+
+mod curve;
+
+pub use self::curve::{EasingDirection, EasingStyle, ease};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+const F64_SIZE: usize = size_of::<f64>();
+
+/**
+    Returns a string containing type definitions for the `easing` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `easing` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("ease", easing_ease)?
+        .with_function("get", easing_get)?
+        .with_function("easeBuffer", easing_ease_buffer)?
+        .build_readonly()
+}
+
+fn easing_ease(
+    _: &Lua,
+    (style, direction, alpha): (EasingStyle, EasingDirection, f64),
+) -> LuaResult<f64> {
+    Ok(ease(style, direction, alpha))
+}
+
+fn easing_get(
+    lua: &Lua,
+    (style, direction): (EasingStyle, EasingDirection),
+) -> LuaResult<LuaFunction> {
+    lua.create_function(move |_, alpha: f64| Ok(ease(style, direction, alpha)))
+}
+
+fn easing_ease_buffer(
+    _: &Lua,
+    (style, direction, input, output): (EasingStyle, EasingDirection, Buffer, Option<Buffer>),
+) -> LuaResult<Buffer> {
+    let len = input.len();
+    if len % F64_SIZE != 0 {
+        return Err(LuaError::runtime(format!(
+            "Input buffer length must be a multiple of {F64_SIZE} bytes, got {len}"
+        )));
+    }
+
+    let output = output.unwrap_or_else(|| input.clone());
+    if output.len() < len {
+        return Err(LuaError::runtime(format!(
+            "Output buffer is too small ({} bytes, expected at least {len})",
+            output.len()
+        )));
+    }
+
+    let bytes = input.to_vec();
+    for (index, chunk) in bytes.chunks_exact(F64_SIZE).enumerate() {
+        let mut alpha = [0; F64_SIZE];
+        alpha.copy_from_slice(chunk);
+        let value = ease(style, direction, f64::from_le_bytes(alpha));
+        output.write_bytes(index * F64_SIZE, &value.to_le_bytes());
+    }
+
+    Ok(output)
+}
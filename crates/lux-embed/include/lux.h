@@ -0,0 +1,35 @@
+/*
+    Lux - C interface for scripts built using `lux build --crate-type cdylib`
+
+    Every function must be called from a thread that called `lux_init` first,
+    and only ever affects the Lux runtime belonging to that thread.
+*/
+
+#ifndef LUX_H
+#define LUX_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Runs the embedded script, returns 0 on success and -1 on failure */
+int lux_init(void);
+
+/*
+    Calls a function exported by the embedded script, with a JSON array of arguments
+    or NULL for none. Returns the results as JSON, to be freed with `lux_free`,
+    or NULL on failure.
+*/
+char *lux_call(const char *name, const char *json_args);
+
+/* Frees a result returned by `lux_call` */
+void lux_free(char *result);
+
+/* Describes why the last call failed, or NULL if it succeeded */
+const char *lux_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* LUX_H */
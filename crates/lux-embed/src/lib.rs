@@ -0,0 +1,286 @@
+#![allow(clippy::cargo_common_metadata)]
+//! A shared library that runs a script embedded into it by `lux build --crate-type cdylib`,
+//! so that C and C++ applications can call into Lux scripts without linking to Rust.
+//!
+//! See `include/lux.h` for the exported functions. The script runs once per thread
+//! that calls `lux_init`, and its exported functions may then be called from that thread.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use lux::Runtime;
+use mlua::prelude::*;
+
+mod payload;
+
+use self::payload::Payload;
+
+thread_local! {
+    static RUNTIME: RefCell<Option<Runtime>> = const { RefCell::new(None) };
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/**
+    Initializes Lux on the calling thread, running the embedded script.
+
+    Does nothing if Lux was already initialized on this thread.
+
+    Returns `0` on success, or `-1` if the script failed to load or run,
+    in which case `lux_last_error` describes why.
+*/
+#[unsafe(no_mangle)]
+pub extern "C" fn lux_init() -> c_int {
+    guard(-1, || {
+        if RUNTIME.with_borrow(Option::is_some) {
+            return Ok(0);
+        }
+
+        let payload = payload::read_payload().map_err(|e| e.to_string())?;
+        start(payload)?;
+        Ok(0)
+    })
+}
+
+/**
+    Runs the script from the given payload, keeping its runtime for the calling thread.
+*/
+fn start(payload: Payload) -> Result<(), String> {
+    let rt = match &payload.snapshot {
+        Some(snapshot) => Runtime::from_snapshot(snapshot),
+        None => Runtime::new(),
+    };
+    let mut rt = rt.map_err(|e| e.to_string())?;
+    let values = if payload.snapshot.is_some() {
+        async_io::block_on(rt.run_snapshot())
+    } else {
+        async_io::block_on(rt.run_custom("EMBEDDED", payload.bytecode))
+    }
+    .map_err(|e| e.to_string())?;
+    if !values.success() {
+        return Err(format!(
+            "The embedded script exited with status {}",
+            values.status()
+        ));
+    }
+
+    RUNTIME.with_borrow_mut(|runtime| *runtime = Some(rt));
+    Ok(())
+}
+
+/**
+    Calls a function exported by the embedded script, waiting for it to complete.
+
+    The arguments are a JSON array, or any other JSON value for a single argument,
+    and `NULL` for no arguments. Returns the return values as JSON - `null` for
+    none, and an array for several - which must be freed using `lux_free`.
+
+    Returns `NULL` if the call failed, in which case `lux_last_error` describes why.
+
+    # Safety
+
+    `name` must be a NUL-terminated string, and `json_args` must be one or `NULL`.
+*/
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lux_call(name: *const c_char, json_args: *const c_char) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        if name.is_null() {
+            return Err("The function name must not be NULL".to_string());
+        }
+        let name = unsafe { CStr::from_ptr(name) }
+            .to_str()
+            .map_err(|e| format!("The function name is not valid UTF-8: {e}"))?;
+
+        let args = if json_args.is_null() {
+            Vec::new()
+        } else {
+            let json = unsafe { CStr::from_ptr(json_args) }.to_bytes();
+            match serde_json::from_slice(json) {
+                Ok(serde_json::Value::Array(args)) => args,
+                Ok(arg) => vec![arg],
+                Err(e) => return Err(format!("The arguments are not valid JSON: {e}")),
+            }
+        };
+
+        let json = RUNTIME.with_borrow_mut(|runtime| {
+            let rt = runtime
+                .as_mut()
+                .ok_or("lux_init must be called on this thread before lux_call")?;
+            let values: LuaMultiValue = async_io::block_on(rt.call_function(name, JsonArgs(args)))
+                .map_err(|e| e.to_string())?;
+            encode(&values)
+        })?;
+
+        let json = CString::new(json).map_err(|e| e.to_string())?;
+        Ok(json.into_raw())
+    })
+}
+
+/**
+    Frees a result returned by `lux_call`. Does nothing for `NULL`.
+
+    # Safety
+
+    `result` must have been returned by `lux_call`, and not been freed before.
+*/
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn lux_free(result: *mut c_char) {
+    if !result.is_null() {
+        drop(unsafe { CString::from_raw(result) });
+    }
+}
+
+/**
+    Describes why the last call into Lux on this thread failed, or returns `NULL` if it succeeded.
+
+    The string belongs to Lux, and stays valid until the next call into Lux on this thread.
+*/
+#[unsafe(no_mangle)]
+pub extern "C" fn lux_last_error() -> *const c_char {
+    LAST_ERROR.with_borrow(|error| error.as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Arguments decoded from JSON, which become Lua values once passed to the runtime
+struct JsonArgs(Vec<serde_json::Value>);
+
+impl IntoLuaMulti for JsonArgs {
+    fn into_lua_multi(self, lua: &Lua) -> LuaResult<LuaMultiValue> {
+        self.0.iter().map(|arg| lua.to_value(arg)).collect()
+    }
+}
+
+fn encode(values: &LuaMultiValue) -> Result<String, String> {
+    let json = match values.len() {
+        0 => Ok("null".to_string()),
+        1 => serde_json::to_string(&values[0]),
+        _ => serde_json::to_string(&values.iter().collect::<Vec<_>>()),
+    };
+    json.map_err(|e| format!("The return values can not be encoded as JSON: {e}"))
+}
+
+/**
+    Runs a call into Lux, keeping track of its error for `lux_last_error`,
+    and making sure that panics never unwind into the calling C code.
+*/
+fn guard<T>(failed: T, f: impl FnOnce() -> Result<T, String>) -> T {
+    let (value, error) = match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => (value, None),
+        Ok(Err(error)) => (failed, Some(error)),
+        Err(_) => (failed, Some("Lux panicked during the call".to_string())),
+    };
+    // NOTE: C strings end at the first NUL, so any inside of the error are replaced
+    let error = error.map(|e| CString::new(e.replace('\0', " ")).unwrap_or_default());
+    LAST_ERROR.with_borrow_mut(|last| *last = error);
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = r#"
+        return {
+            echo = function(...)
+                return ...
+            end,
+            sum = function(numbers)
+                local total = 0
+                for _, n in numbers do
+                    total += n
+                end
+                return total
+            end,
+            fail = function()
+                error("boom")
+            end,
+        }
+    "#;
+
+    fn init() {
+        let payload = Payload {
+            bytecode: SCRIPT.as_bytes().to_vec(),
+            snapshot: None,
+        };
+        assert_eq!(guard(-1, || start(payload).map(|()| 0)), 0);
+        assert!(lux_last_error().is_null());
+    }
+
+    /// Calls a function, returning its result as a string and freeing it
+    fn call(name: &str, json_args: Option<&str>) -> Option<String> {
+        let name = CString::new(name).unwrap();
+        let json_args = json_args.map(|args| CString::new(args).unwrap());
+        let result = unsafe {
+            lux_call(
+                name.as_ptr(),
+                json_args.as_ref().map_or(ptr::null(), |args| args.as_ptr()),
+            )
+        };
+        if result.is_null() {
+            return None;
+        }
+        let json = unsafe { CStr::from_ptr(result) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { lux_free(result) };
+        Some(json)
+    }
+
+    fn last_error() -> String {
+        let error = lux_last_error();
+        assert!(!error.is_null());
+        unsafe { CStr::from_ptr(error) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn init_without_payload() {
+        assert_eq!(lux_init(), -1);
+        assert!(last_error().contains("no script is embedded"));
+    }
+
+    #[test]
+    fn call_before_init() {
+        assert_eq!(call("echo", None), None);
+        assert!(last_error().contains("lux_init must be called"));
+    }
+
+    #[test]
+    fn call_round_trips_json() {
+        init();
+        assert_eq!(call("echo", None).as_deref(), Some("null"));
+        assert_eq!(call("echo", Some("\"lux\"")).as_deref(), Some("\"lux\""));
+        assert_eq!(
+            call("echo", Some(r#"[1, "two", {"three": true}]"#)).as_deref(),
+            Some(r#"[1,"two",{"three":true}]"#)
+        );
+        assert_eq!(call("sum", Some("[[1, 2, 3.5]]")).as_deref(), Some("6.5"));
+        assert!(lux_last_error().is_null());
+    }
+
+    #[test]
+    fn call_errors_are_reported() {
+        init();
+        assert_eq!(call("fail", None), None);
+        assert!(last_error().contains("boom"));
+
+        assert_eq!(call("missing", None), None);
+        assert!(!last_error().is_empty());
+
+        assert_eq!(call("echo", Some("[1,")), None);
+        assert!(last_error().contains("not valid JSON"));
+
+        // A successful call clears the error again
+        assert!(call("echo", None).is_some());
+        assert!(lux_last_error().is_null());
+    }
+
+    #[test]
+    fn free_null_is_a_no_op() {
+        unsafe { lux_free(ptr::null_mut()) };
+    }
+}
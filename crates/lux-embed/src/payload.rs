@@ -0,0 +1,100 @@
+use std::{ffi::c_void, fs, io, path::PathBuf};
+
+/// Marks the end of an embedded script, the same as for standalone executables
+const MAGIC: &[u8; 8] = b"cr3sc3nt";
+/// Marks the end of a module snapshot, which comes right before the script
+const SNAPSHOT_MAGIC: &[u8; 8] = b"cr3snap1";
+
+/// What `lux build --crate-type cdylib` appended to this library
+pub(crate) struct Payload {
+    pub bytecode: Vec<u8>,
+    pub snapshot: Option<Vec<u8>>,
+}
+
+/**
+    Reads the script, and the module snapshot if there is one, appended to this library.
+
+    Both are followed by their length as a big-endian `u64` and then their magic bytes,
+    the same layout that standalone executables built by `lux build` use.
+*/
+pub(crate) fn read_payload() -> io::Result<Payload> {
+    let path = library_path()?;
+    let bytes = fs::read(&path)?;
+    let Some((bytecode, rest)) = split_section(&bytes, MAGIC) else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "no script is embedded in '{}', it must be built using `lux build --crate-type cdylib`",
+                path.display()
+            ),
+        ));
+    };
+    let snapshot = split_section(rest, SNAPSHOT_MAGIC).map(|(snapshot, _)| snapshot.to_vec());
+    Ok(Payload {
+        bytecode: bytecode.to_vec(),
+        snapshot,
+    })
+}
+
+/// Splits a section marked by `magic` off of the end of `bytes`, returning it and what comes before
+fn split_section<'a>(bytes: &'a [u8], magic: &[u8; 8]) -> Option<(&'a [u8], &'a [u8])> {
+    if !bytes.ends_with(magic) {
+        return None;
+    }
+    let trailer = bytes.len().checked_sub(16)?;
+    let size = u64::from_be_bytes(bytes[trailer..trailer + 8].try_into().ok()?);
+    let start = trailer.checked_sub(usize::try_from(size).ok()?)?;
+    Some((&bytes[start..trailer], &bytes[..start]))
+}
+
+/**
+    Finds the path of this library, which is not the path of the
+    executable since the library is loaded by some other program.
+*/
+#[cfg(unix)]
+fn library_path() -> io::Result<PathBuf> {
+    use std::{
+        ffi::{CStr, OsStr},
+        os::unix::ffi::OsStrExt,
+    };
+
+    let mut info: libc::Dl_info = unsafe { std::mem::zeroed() };
+    // NOTE: Any address inside of this library can be used to find it
+    let found = unsafe { libc::dladdr(library_path as *const c_void, &raw mut info) };
+    if found == 0 || info.dli_fname.is_null() {
+        return Err(io::Error::other(
+            "failed to find the path of the Lux library",
+        ));
+    }
+    let name = unsafe { CStr::from_ptr(info.dli_fname) };
+    Ok(PathBuf::from(OsStr::from_bytes(name.to_bytes())))
+}
+
+#[cfg(windows)]
+fn library_path() -> io::Result<PathBuf> {
+    use std::{ffi::OsString, os::windows::ffi::OsStringExt, ptr};
+    use windows_sys::Win32::System::LibraryLoader::{
+        GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS, GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+        GetModuleFileNameW, GetModuleHandleExW,
+    };
+
+    let mut module = ptr::null_mut();
+    // NOTE: Any address inside of this library can be used to find it
+    let found = unsafe {
+        GetModuleHandleExW(
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            library_path as *const c_void as *const u16,
+            &raw mut module,
+        )
+    };
+    if found == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut buffer = vec![0u16; 32 * 1024];
+    let len = unsafe { GetModuleFileNameW(module, buffer.as_mut_ptr(), buffer.len() as u32) };
+    if len == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(PathBuf::from(OsString::from_wide(&buffer[..len as usize])))
+}
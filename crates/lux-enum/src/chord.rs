@@ -0,0 +1,261 @@
+use std::{fmt, str::FromStr};
+
+use crate::{KEY_CODES, keycodes, modifiers};
+
+/**
+    A modifier key that can be a part of a [`KeyChord`].
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Meta,
+}
+
+impl Modifier {
+    /// Every modifier, in the order they are written in chords
+    pub const ALL: [Self; 4] = [Self::Ctrl, Self::Alt, Self::Shift, Self::Meta];
+
+    /**
+        Returns the `Enum.ModifierKey` mask of the modifier.
+    */
+    #[must_use]
+    pub const fn mask(self) -> i32 {
+        match self {
+            Self::Shift => modifiers::SHIFT,
+            Self::Ctrl => modifiers::CTRL,
+            Self::Alt => modifiers::ALT,
+            Self::Meta => modifiers::META,
+        }
+    }
+
+    /**
+        Returns the `Enum.KeyCode` of the left-hand key for the modifier.
+    */
+    #[must_use]
+    pub const fn key_code(self) -> i32 {
+        match self {
+            Self::Shift => keycodes::LEFT_SHIFT,
+            Self::Ctrl => keycodes::LEFT_CONTROL,
+            Self::Alt => keycodes::LEFT_ALT,
+            Self::Meta => keycodes::LEFT_SUPER,
+        }
+    }
+
+    /**
+        Returns the modifier that either the left-hand or right-hand key with the given `Enum.KeyCode` is for.
+    */
+    #[must_use]
+    pub const fn from_key_code(code: i32) -> Option<Self> {
+        Some(match code {
+            keycodes::LEFT_SHIFT | keycodes::RIGHT_SHIFT => Self::Shift,
+            keycodes::LEFT_CONTROL | keycodes::RIGHT_CONTROL => Self::Ctrl,
+            keycodes::LEFT_ALT | keycodes::RIGHT_ALT => Self::Alt,
+            keycodes::LEFT_SUPER | keycodes::RIGHT_SUPER => Self::Meta,
+            _ => return None,
+        })
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "shift" => Self::Shift,
+            "ctrl" | "control" => Self::Ctrl,
+            "alt" | "option" | "opt" => Self::Alt,
+            "meta" | "super" | "win" | "cmd" | "command" => Self::Meta,
+            _ => return None,
+        })
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Shift => "Shift",
+            Self::Ctrl => "Ctrl",
+            Self::Alt => "Alt",
+            Self::Meta => "Meta",
+        }
+    }
+}
+
+/**
+    A combination of modifiers and a single key, such as `Ctrl+Shift+P`.
+
+    Chords are parsed from their names separated by `+`, ignoring case and whitespace.
+    Modifiers are `Shift`, `Ctrl`, `Alt` and `Meta` along with common aliases such as
+    `Cmd` and `Win`, and the key is the name of any `Enum.KeyCode` item, a single letter,
+    digit, or punctuation character, or one of `Enter`, `Esc` and `Del`.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub modifiers: Vec<Modifier>,
+    pub key: i32,
+}
+
+impl KeyChord {
+    /**
+        Returns the combined `Enum.ModifierKey` mask of all modifiers in the chord.
+    */
+    #[must_use]
+    pub fn modifier_mask(&self) -> i32 {
+        self.modifiers.iter().fold(0, |mask, m| mask | m.mask())
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rest, last) = match s.trim().rsplit_once('+') {
+            Some((rest, last)) => (rest, last.trim()),
+            None => ("", s.trim()),
+        };
+
+        let mut modifiers = Vec::new();
+        if !rest.is_empty() {
+            for part in rest.split('+').map(str::trim) {
+                let modifier = Modifier::from_name(part)
+                    .ok_or_else(|| format!("'{part}' is not a modifier key in chord '{s}'"))?;
+                if modifiers.contains(&modifier) {
+                    return Err(format!("Modifier '{part}' is repeated in chord '{s}'"));
+                }
+                modifiers.push(modifier);
+            }
+        }
+
+        let key = key_from_name(last).ok_or_else(|| {
+            if last.is_empty() {
+                format!("Chord '{s}' is missing a key")
+            } else {
+                format!("'{last}' is not a key in chord '{s}'")
+            }
+        })?;
+
+        Ok(Self { modifiers, key })
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in Modifier::ALL {
+            if self.modifiers.contains(&modifier) {
+                write!(f, "{}+", modifier.name())?;
+            }
+        }
+        match KEY_CODES.iter().find(|&&(_, code)| code == self.key) {
+            Some((name, _)) => f.write_str(name),
+            None => write!(f, "{}", self.key),
+        }
+    }
+}
+
+/**
+    Finds the `Enum.KeyCode` for the name of a key, as written in a chord.
+*/
+fn key_from_name(name: &str) -> Option<i32> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next())
+        && let Some(code) = key_from_char(c)
+    {
+        return Some(code);
+    }
+    let name = match name.to_ascii_lowercase().as_str() {
+        "enter" => "Return",
+        "esc" => "Escape",
+        "del" => "Delete",
+        "ins" => "Insert",
+        "pgup" => "PageUp",
+        "pgdn" => "PageDown",
+        _ => name,
+    };
+    KEY_CODES
+        .iter()
+        .find(|&&(item, _)| item.eq_ignore_ascii_case(name))
+        .map(|&(_, code)| code)
+}
+
+/**
+    Finds the `Enum.KeyCode` for the key that types a single character on a US keyboard layout.
+
+    Letters of either case give the key for the letter, so typing an uppercase letter
+    also needs Shift to be held. Returns `None` for characters that need Shift otherwise.
+*/
+#[must_use]
+pub fn key_from_char(c: char) -> Option<i32> {
+    Some(match c.to_ascii_uppercase() {
+        '0' => keycodes::ZERO,
+        '1' => keycodes::ONE,
+        '2' => keycodes::TWO,
+        '3' => keycodes::THREE,
+        '4' => keycodes::FOUR,
+        '5' => keycodes::FIVE,
+        '6' => keycodes::SIX,
+        '7' => keycodes::SEVEN,
+        '8' => keycodes::EIGHT,
+        '9' => keycodes::NINE,
+        ' ' => keycodes::SPACE,
+        '\t' => keycodes::TAB,
+        '\n' => keycodes::RETURN,
+        ';' => keycodes::SEMICOLON,
+        '=' => keycodes::EQUALS,
+        ',' => keycodes::COMMA,
+        '-' => keycodes::MINUS,
+        '.' => keycodes::PERIOD,
+        '/' => keycodes::SLASH,
+        '`' => keycodes::GRAVE,
+        '[' => keycodes::LEFT_BRACKET,
+        '\\' => keycodes::BACKSLASH,
+        ']' => keycodes::RIGHT_BRACKET,
+        '\'' => keycodes::APOSTROPHE,
+        upper @ 'A'..='Z' => {
+            let mut buf = [0; 4];
+            let name = &*upper.encode_utf8(&mut buf);
+            return KEY_CODES
+                .iter()
+                .find(|&&(item, _)| item == name)
+                .map(|&(_, code)| code);
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modifiers_and_key() {
+        let chord: KeyChord = "Ctrl+Shift+P".parse().unwrap();
+        assert_eq!(chord.modifiers, vec![Modifier::Ctrl, Modifier::Shift]);
+        assert_eq!(chord.key, keycodes::P);
+        assert_eq!(chord.modifier_mask(), modifiers::CTRL | modifiers::SHIFT);
+    }
+
+    #[test]
+    fn parses_aliases_and_whitespace() {
+        let chord: KeyChord = " cmd + option + enter ".parse().unwrap();
+        assert_eq!(chord.modifiers, vec![Modifier::Meta, Modifier::Alt]);
+        assert_eq!(chord.key, keycodes::RETURN);
+
+        let chord: KeyChord = "F5".parse().unwrap();
+        assert!(chord.modifiers.is_empty());
+        assert_eq!(chord.key, keycodes::F5);
+
+        let chord: KeyChord = "Alt+/".parse().unwrap();
+        assert_eq!(chord.key, keycodes::SLASH);
+    }
+
+    #[test]
+    fn rejects_invalid_chords() {
+        assert!("".parse::<KeyChord>().is_err());
+        assert!("Ctrl+".parse::<KeyChord>().is_err());
+        assert!("Ctrl+Ctrl+A".parse::<KeyChord>().is_err());
+        assert!("Hyper+A".parse::<KeyChord>().is_err());
+        assert!("Ctrl+NotAKey".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn displays_in_canonical_order() {
+        let chord: KeyChord = "shift+ctrl+p".parse().unwrap();
+        assert_eq!(chord.to_string(), "Ctrl+Shift+P");
+    }
+}
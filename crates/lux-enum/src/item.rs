@@ -0,0 +1,212 @@
+use lux_utils::{
+    TableBuilder,
+    flags::{FeatureFlag, FeatureFlags},
+};
+use mlua::prelude::*;
+
+const DEPRECATION_MESSAGE: &str =
+    "Enum items will become EnumItem userdata instead of plain numbers in a future release";
+
+/**
+    A single item of an enum, such as `Enum.KeyCode.A`.
+
+    Only used when the `new-enum-items` feature flag is enabled.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumItem {
+    enum_type: String,
+    name: String,
+    value: i32,
+}
+
+impl EnumItem {
+    #[must_use]
+    pub fn enum_type(&self) -> &str {
+        &self.enum_type
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[must_use]
+    pub fn value(&self) -> i32 {
+        self.value
+    }
+}
+
+impl LuaUserData for EnumItem {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Name", |_, this| Ok(this.name.clone()));
+        fields.add_field_method_get("Value", |_, this| Ok(this.value));
+        fields.add_field_method_get("EnumType", |lua, this| {
+            let enums = lua.globals().get::<LuaTable>("Enum")?;
+            enums.get::<LuaValue>(this.enum_type.as_str())
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("IsA", |_, this, enum_type: String| {
+            Ok(this.enum_type == enum_type)
+        });
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaUserDataRef<Self>| {
+            Ok(*this == *other)
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(format!("Enum.{}.{}", this.enum_type, this.name))
+        });
+    }
+}
+
+/**
+    Converts an item of the given enum into its value, accepting plain
+    number items, [`EnumItem`] userdata, and item names as strings.
+
+    # Errors
+
+    Errors if the value is not one of the given items of the enum.
+*/
+pub fn item_value_from_lua(
+    value: &LuaValue,
+    enum_type: &'static str,
+    items: &[(&str, i32)],
+) -> LuaResult<i32> {
+    let is_item = |v: i32| items.iter().any(|&(_, item)| item == v);
+    let item = match value {
+        LuaValue::Integer(i) => i32::try_from(*i).ok().filter(|&v| is_item(v)),
+        LuaValue::Number(n) if n.fract() == 0.0 => Some(*n as i32).filter(|&v| is_item(v)),
+        LuaValue::String(s) => s.to_str().ok().and_then(|s| {
+            items
+                .iter()
+                .find(|&&(name, _)| name == &*s)
+                .map(|&(_, item)| item)
+        }),
+        LuaValue::UserData(ud) => match ud.borrow::<EnumItem>() {
+            Ok(item) if item.enum_type == enum_type => Some(item.value),
+            _ => None,
+        },
+        _ => None,
+    };
+    item.ok_or_else(|| LuaError::FromLuaConversionError {
+        from: value.type_name(),
+        to: enum_type.to_string(),
+        message: Some(format!(
+            "expected an Enum.{enum_type} item or name, got {}",
+            value
+                .to_string()
+                .unwrap_or_else(|_| value.type_name().to_string())
+        )),
+    })
+}
+
+/**
+    Converts the value of an item of the given enum into the item scripts would get
+    from the `Enum` global - [`EnumItem`] userdata if the `new-enum-items` flag is
+    enabled, and a plain number otherwise, or if the value is not a known item.
+*/
+pub fn item_into_lua(
+    lua: &Lua,
+    enum_type: &str,
+    items: &[(&str, i32)],
+    value: i32,
+) -> LuaResult<LuaValue> {
+    let name = items
+        .iter()
+        .find(|&&(_, item)| item == value)
+        .map(|&(name, _)| name);
+    match name {
+        Some(name) if FeatureFlags::is_enabled_in(lua, FeatureFlag::NewEnumItems) => EnumItem {
+            enum_type: enum_type.to_string(),
+            name: name.to_string(),
+            value,
+        }
+        .into_lua(lua),
+        _ => Ok(LuaValue::Integer(value.into())),
+    }
+}
+
+/**
+    Converts a table of enums with plain number items into one with [`EnumItem`] items.
+
+    Anything in the table other than an enum, such as a function, is kept as it is.
+*/
+pub(crate) fn convert_enums(lua: &Lua, enums: &LuaTable) -> LuaResult<LuaTable> {
+    let mut builder = TableBuilder::new(lua.clone())?;
+    for pair in enums.pairs::<String, LuaValue>() {
+        let (enum_type, value) = pair?;
+        let LuaValue::Table(items) = value else {
+            builder = builder.with_value(enum_type, value)?;
+            continue;
+        };
+        let mut item_builder = TableBuilder::new(lua.clone())?;
+        for pair in items.pairs::<String, i32>() {
+            let (name, value) = pair?;
+            let item = EnumItem {
+                enum_type: enum_type.clone(),
+                name: name.clone(),
+                value,
+            };
+            item_builder = item_builder.with_value(name, item)?;
+        }
+        builder = builder.with_value(enum_type, item_builder.build_readonly()?)?;
+    }
+    builder.build_readonly()
+}
+
+/**
+    Wraps a table of enums with plain number items so that using them
+    warns about the upcoming change to [`EnumItem`] items, once.
+
+    Once the `new-enum-items` flag gets set, lookups are forwarded to the current
+    `Enum` global instead, which also covers scripts holding on to this table.
+*/
+pub(crate) fn create_deprecated_enums(lua: &Lua, enums: LuaTable) -> LuaResult<LuaTable> {
+    let index_enums = enums.clone();
+    let index = lua.create_function(move |lua, (this, key): (LuaTable, LuaValue)| {
+        // NOTE: Luau resolves imports such as `Enum.KeyCode.A` when a chunk
+        // is loaded, from native code - resolving those to nil makes them get
+        // looked up when the script actually runs, which is when we warn
+        let called_from_luau = lua
+            .inspect_stack(1, |debug| debug.source().what != "C")
+            .unwrap_or(false);
+        if !called_from_luau {
+            return Ok(LuaValue::Nil);
+        }
+        let current = current_enums(lua, &this, &index_enums)?;
+        let value = current.raw_get::<LuaValue>(key)?;
+        // Functions such as `Enum.scancodeFromKeyCode` work the same either way
+        if current == index_enums && value.is_table() {
+            FeatureFlags::warn_deprecated(lua, FeatureFlag::NewEnumItems, DEPRECATION_MESSAGE);
+        }
+        Ok(value)
+    })?;
+
+    let iter_enums = enums.clone();
+    let iter = lua.create_function(move |lua, this: LuaTable| {
+        let current = current_enums(lua, &this, &iter_enums)?;
+        let next = lua.globals().get::<LuaFunction>("next")?;
+        Ok((next, current))
+    })?;
+
+    let meta = TableBuilder::new(lua.clone())?
+        .with_value(LuaMetaMethod::Index.name(), index)?
+        .with_value(LuaMetaMethod::Iter.name(), iter)?
+        .with_value("__metatable", "The metatable is locked")?
+        .build_readonly()?;
+
+    let proxy = lua.create_table()?;
+    proxy.set_metatable(Some(meta))?;
+    proxy.set_readonly(true);
+    Ok(proxy)
+}
+
+fn current_enums(lua: &Lua, this: &LuaTable, enums: &LuaTable) -> LuaResult<LuaTable> {
+    if FeatureFlags::get_in(lua, FeatureFlag::NewEnumItems).is_some()
+        && let Ok(global) = lua.globals().get::<LuaTable>("Enum")
+        && global != *this
+    {
+        return Ok(global);
+    }
+    Ok(enums.clone())
+}
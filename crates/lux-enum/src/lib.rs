@@ -1,14 +1,28 @@
 #![allow(clippy::cargo_common_metadata)]
 
-//! Enum types for Lux: KeyCode (cross-platform), MouseButton, EasingStyle, EasingDirection
-//! KeyCode values are platform-specific:
+//! Enum types for Lux: KeyCode (cross-platform), MouseButton, GamepadButton, UserInputType,
+//! ModifierKey, EasingStyle, EasingDirection
+//! KeyCode values are platform-specific (as are GamepadButton and ModifierKey, see below):
 //! - Windows: VK_* codes (user32.dll)
 //! - Linux: evdev KEY_* codes
 //! - macOS: Carbon kVK_* codes
+//!
+//! Scancode values identify physical keys regardless of the keyboard layout, see `scancode.rs`
 
-use lux_utils::TableBuilder;
+use lux_utils::{
+    TableBuilder,
+    flags::{FeatureFlag, FeatureFlags},
+};
 use mlua::prelude::*;
 
+mod chord;
+mod item;
+mod scancode;
+
+pub use self::chord::{KeyChord, Modifier, key_from_char};
+pub use self::item::{EnumItem, item_into_lua, item_value_from_lua};
+pub use self::scancode::{SCANCODES, key_code_from_scancode, scancode_from_key_code};
+
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
 #[must_use]
@@ -79,6 +93,9 @@ mod keycodes {
     pub const LEFT_SUPER: i32 = 0x5B;
     pub const RIGHT_SUPER: i32 = 0x5C;
     pub const MENU: i32 = 0x5D;
+    pub const PRINT_SCREEN: i32 = 0x2C;
+    pub const SCROLL_LOCK: i32 = 0x91;
+    pub const PAUSE: i32 = 0x13;
     pub const SPACE: i32 = 0x20;
     pub const RETURN: i32 = 0x0D;
     pub const BACKSPACE: i32 = 0x08;
@@ -103,6 +120,13 @@ mod keycodes {
     pub const NUMPAD8: i32 = 0x68;
     pub const NUMPAD9: i32 = 0x69;
     pub const NUM_LOCK: i32 = 0x90;
+    pub const NUMPAD_ADD: i32 = 0x6B;
+    pub const NUMPAD_SUBTRACT: i32 = 0x6D;
+    pub const NUMPAD_MULTIPLY: i32 = 0x6A;
+    pub const NUMPAD_DIVIDE: i32 = 0x6F;
+    pub const NUMPAD_DECIMAL: i32 = 0x6E;
+    // Shares its virtual key with Return, see `Enum.Scancode` to tell them apart
+    pub const NUMPAD_ENTER: i32 = 0x0D;
     pub const SEMICOLON: i32 = 0xBA;
     pub const EQUALS: i32 = 0xBB;
     pub const COMMA: i32 = 0xBC;
@@ -114,6 +138,16 @@ mod keycodes {
     pub const BACKSLASH: i32 = 0xDC;
     pub const RIGHT_BRACKET: i32 = 0xDD;
     pub const APOSTROPHE: i32 = 0xDE;
+    pub const INTL_BACKSLASH: i32 = 0xE2;
+    // Shares its virtual key with Backslash, see `Enum.Scancode` to tell them apart
+    pub const YEN: i32 = 0xDC;
+    pub const VOLUME_MUTE: i32 = 0xAD;
+    pub const VOLUME_DOWN: i32 = 0xAE;
+    pub const VOLUME_UP: i32 = 0xAF;
+    pub const MEDIA_PLAY_PAUSE: i32 = 0xB3;
+    pub const MEDIA_STOP: i32 = 0xB2;
+    pub const MEDIA_NEXT: i32 = 0xB0;
+    pub const MEDIA_PREVIOUS: i32 = 0xB1;
 }
 
 #[cfg(target_os = "linux")]
@@ -178,6 +212,9 @@ mod keycodes {
     pub const LEFT_SUPER: i32 = 125;
     pub const RIGHT_SUPER: i32 = 126;
     pub const MENU: i32 = 127;
+    pub const PRINT_SCREEN: i32 = 99;
+    pub const SCROLL_LOCK: i32 = 70;
+    pub const PAUSE: i32 = 119;
     pub const SPACE: i32 = 57;
     pub const RETURN: i32 = 28;
     pub const BACKSPACE: i32 = 14;
@@ -202,6 +239,12 @@ mod keycodes {
     pub const NUMPAD8: i32 = 72;
     pub const NUMPAD9: i32 = 73;
     pub const NUM_LOCK: i32 = 69;
+    pub const NUMPAD_ADD: i32 = 78;
+    pub const NUMPAD_SUBTRACT: i32 = 74;
+    pub const NUMPAD_MULTIPLY: i32 = 55;
+    pub const NUMPAD_DIVIDE: i32 = 98;
+    pub const NUMPAD_DECIMAL: i32 = 83;
+    pub const NUMPAD_ENTER: i32 = 96;
     pub const SEMICOLON: i32 = 39;
     pub const EQUALS: i32 = 13;
     pub const COMMA: i32 = 51;
@@ -213,6 +256,15 @@ mod keycodes {
     pub const BACKSLASH: i32 = 43;
     pub const RIGHT_BRACKET: i32 = 27;
     pub const APOSTROPHE: i32 = 40;
+    pub const INTL_BACKSLASH: i32 = 86;
+    pub const YEN: i32 = 124;
+    pub const VOLUME_MUTE: i32 = 113;
+    pub const VOLUME_DOWN: i32 = 114;
+    pub const VOLUME_UP: i32 = 115;
+    pub const MEDIA_PLAY_PAUSE: i32 = 164;
+    pub const MEDIA_STOP: i32 = 166;
+    pub const MEDIA_NEXT: i32 = 163;
+    pub const MEDIA_PREVIOUS: i32 = 165;
 }
 
 #[cfg(target_os = "macos")]
@@ -277,6 +329,10 @@ mod keycodes {
     pub const LEFT_SUPER: i32 = 0x37;
     pub const RIGHT_SUPER: i32 = 0x36;
     pub const MENU: i32 = 0x6E;
+    // F13 to F15, which keyboards for PCs send for these keys on macOS
+    pub const PRINT_SCREEN: i32 = 0x69;
+    pub const SCROLL_LOCK: i32 = 0x6B;
+    pub const PAUSE: i32 = 0x71;
     pub const SPACE: i32 = 0x31;
     pub const RETURN: i32 = 0x24;
     pub const BACKSPACE: i32 = 0x33;
@@ -301,6 +357,12 @@ mod keycodes {
     pub const NUMPAD8: i32 = 0x5B;
     pub const NUMPAD9: i32 = 0x5C;
     pub const NUM_LOCK: i32 = 0x47;
+    pub const NUMPAD_ADD: i32 = 0x45;
+    pub const NUMPAD_SUBTRACT: i32 = 0x4E;
+    pub const NUMPAD_MULTIPLY: i32 = 0x43;
+    pub const NUMPAD_DIVIDE: i32 = 0x4B;
+    pub const NUMPAD_DECIMAL: i32 = 0x41;
+    pub const NUMPAD_ENTER: i32 = 0x4C;
     pub const SEMICOLON: i32 = 0x29;
     pub const EQUALS: i32 = 0x18;
     pub const COMMA: i32 = 0x2B;
@@ -312,120 +374,332 @@ mod keycodes {
     pub const BACKSLASH: i32 = 0x2A;
     pub const RIGHT_BRACKET: i32 = 0x1E;
     pub const APOSTROPHE: i32 = 0x27;
+    pub const INTL_BACKSLASH: i32 = 0x0A;
+    pub const YEN: i32 = 0x5D;
+    // Other media keys are system events rather than key codes on macOS
+    pub const VOLUME_MUTE: i32 = 0x4A;
+    pub const VOLUME_DOWN: i32 = 0x49;
+    pub const VOLUME_UP: i32 = 0x48;
+}
+
+// Platform-specific gamepad buttons
+// - Windows: XInput XINPUT_GAMEPAD_* button masks
+// - Linux: evdev BTN_* codes, as reported by the xpad driver
+// - macOS: SDL game controller button indices
+#[cfg(target_os = "windows")]
+mod gamepad {
+    pub const A: i32 = 0x1000;
+    pub const B: i32 = 0x2000;
+    pub const X: i32 = 0x4000;
+    pub const Y: i32 = 0x8000;
+    pub const LEFT_BUMPER: i32 = 0x0100;
+    pub const RIGHT_BUMPER: i32 = 0x0200;
+    pub const LEFT_STICK: i32 = 0x0040;
+    pub const RIGHT_STICK: i32 = 0x0080;
+    pub const DPAD_UP: i32 = 0x0001;
+    pub const DPAD_DOWN: i32 = 0x0002;
+    pub const DPAD_LEFT: i32 = 0x0004;
+    pub const DPAD_RIGHT: i32 = 0x0008;
+    pub const START: i32 = 0x0010;
+    pub const BACK: i32 = 0x0020;
+    pub const GUIDE: i32 = 0x0400;
+}
+
+#[cfg(target_os = "linux")]
+mod gamepad {
+    pub const A: i32 = 0x130;
+    pub const B: i32 = 0x131;
+    pub const X: i32 = 0x133;
+    pub const Y: i32 = 0x134;
+    pub const LEFT_BUMPER: i32 = 0x136;
+    pub const RIGHT_BUMPER: i32 = 0x137;
+    pub const LEFT_STICK: i32 = 0x13D;
+    pub const RIGHT_STICK: i32 = 0x13E;
+    pub const DPAD_UP: i32 = 0x220;
+    pub const DPAD_DOWN: i32 = 0x221;
+    pub const DPAD_LEFT: i32 = 0x222;
+    pub const DPAD_RIGHT: i32 = 0x223;
+    pub const START: i32 = 0x13B;
+    pub const BACK: i32 = 0x13A;
+    pub const GUIDE: i32 = 0x13C;
+}
+
+#[cfg(target_os = "macos")]
+mod gamepad {
+    pub const A: i32 = 0;
+    pub const B: i32 = 1;
+    pub const X: i32 = 2;
+    pub const Y: i32 = 3;
+    pub const BACK: i32 = 4;
+    pub const GUIDE: i32 = 5;
+    pub const START: i32 = 6;
+    pub const LEFT_STICK: i32 = 7;
+    pub const RIGHT_STICK: i32 = 8;
+    pub const LEFT_BUMPER: i32 = 9;
+    pub const RIGHT_BUMPER: i32 = 10;
+    pub const DPAD_UP: i32 = 11;
+    pub const DPAD_DOWN: i32 = 12;
+    pub const DPAD_LEFT: i32 = 13;
+    pub const DPAD_RIGHT: i32 = 14;
+}
+
+// Platform-specific modifier key masks
+// - Windows: MOD_* flags (RegisterHotKey)
+// - Linux: X11 *Mask modifier state bits
+// - macOS: NSEventModifierFlags
+#[cfg(target_os = "windows")]
+mod modifiers {
+    pub const SHIFT: i32 = 0x4;
+    pub const CTRL: i32 = 0x2;
+    pub const ALT: i32 = 0x1;
+    pub const META: i32 = 0x8;
+}
+
+#[cfg(target_os = "linux")]
+mod modifiers {
+    pub const SHIFT: i32 = 0x1;
+    pub const CTRL: i32 = 0x4;
+    pub const ALT: i32 = 0x8;
+    pub const META: i32 = 0x40;
+}
+
+#[cfg(target_os = "macos")]
+mod modifiers {
+    pub const SHIFT: i32 = 1 << 17;
+    pub const CTRL: i32 = 1 << 18;
+    pub const ALT: i32 = 1 << 19;
+    pub const META: i32 = 1 << 20;
 }
 
 use keycodes::*;
 
+/// Every `Enum.KeyCode` item, by name, along with its platform-specific key code
+pub const KEY_CODES: &[(&str, i32)] = &[
+    ("A", A),
+    ("B", B),
+    ("C", C),
+    ("D", D),
+    ("E", E),
+    ("F", F),
+    ("G", G),
+    ("H", H),
+    ("I", I),
+    ("J", J),
+    ("K", K),
+    ("L", L),
+    ("M", M),
+    ("N", N),
+    ("O", O),
+    ("P", P),
+    ("Q", Q),
+    ("R", R),
+    ("S", S),
+    ("T", T),
+    ("U", U),
+    ("V", V),
+    ("W", W),
+    ("X", X),
+    ("Y", Y),
+    ("Z", Z),
+    ("Zero", ZERO),
+    ("One", ONE),
+    ("Two", TWO),
+    ("Three", THREE),
+    ("Four", FOUR),
+    ("Five", FIVE),
+    ("Six", SIX),
+    ("Seven", SEVEN),
+    ("Eight", EIGHT),
+    ("Nine", NINE),
+    ("F1", F1),
+    ("F2", F2),
+    ("F3", F3),
+    ("F4", F4),
+    ("F5", F5),
+    ("F6", F6),
+    ("F7", F7),
+    ("F8", F8),
+    ("F9", F9),
+    ("F10", F10),
+    ("F11", F11),
+    ("F12", F12),
+    ("Escape", ESCAPE),
+    ("Tab", TAB),
+    ("CapsLock", CAPS_LOCK),
+    ("LeftShift", LEFT_SHIFT),
+    ("RightShift", RIGHT_SHIFT),
+    ("LeftControl", LEFT_CONTROL),
+    ("RightControl", RIGHT_CONTROL),
+    ("LeftAlt", LEFT_ALT),
+    ("RightAlt", RIGHT_ALT),
+    ("LeftSuper", LEFT_SUPER),
+    ("RightSuper", RIGHT_SUPER),
+    ("Menu", MENU),
+    ("PrintScreen", PRINT_SCREEN),
+    ("ScrollLock", SCROLL_LOCK),
+    ("Pause", PAUSE),
+    ("Space", SPACE),
+    ("Return", RETURN),
+    ("Backspace", BACKSPACE),
+    ("Delete", DELETE),
+    ("Insert", INSERT),
+    ("Home", HOME),
+    ("End", END),
+    ("PageUp", PAGE_UP),
+    ("PageDown", PAGE_DOWN),
+    ("Up", UP),
+    ("Down", DOWN),
+    ("Left", LEFT),
+    ("Right", RIGHT),
+    ("Numpad0", NUMPAD0),
+    ("Numpad1", NUMPAD1),
+    ("Numpad2", NUMPAD2),
+    ("Numpad3", NUMPAD3),
+    ("Numpad4", NUMPAD4),
+    ("Numpad5", NUMPAD5),
+    ("Numpad6", NUMPAD6),
+    ("Numpad7", NUMPAD7),
+    ("Numpad8", NUMPAD8),
+    ("Numpad9", NUMPAD9),
+    ("NumLock", NUM_LOCK),
+    ("NumpadAdd", NUMPAD_ADD),
+    ("NumpadSubtract", NUMPAD_SUBTRACT),
+    ("NumpadMultiply", NUMPAD_MULTIPLY),
+    ("NumpadDivide", NUMPAD_DIVIDE),
+    ("NumpadDecimal", NUMPAD_DECIMAL),
+    ("NumpadEnter", NUMPAD_ENTER),
+    ("Semicolon", SEMICOLON),
+    ("Equals", EQUALS),
+    ("Comma", COMMA),
+    ("Minus", MINUS),
+    ("Period", PERIOD),
+    ("Slash", SLASH),
+    ("Grave", GRAVE),
+    ("LeftBracket", LEFT_BRACKET),
+    ("Backslash", BACKSLASH),
+    ("RightBracket", RIGHT_BRACKET),
+    ("Apostrophe", APOSTROPHE),
+    ("IntlBackslash", INTL_BACKSLASH),
+    ("Yen", YEN),
+    ("VolumeMute", VOLUME_MUTE),
+    ("VolumeDown", VOLUME_DOWN),
+    ("VolumeUp", VOLUME_UP),
+    #[cfg(not(target_os = "macos"))]
+    ("MediaPlayPause", MEDIA_PLAY_PAUSE),
+    #[cfg(not(target_os = "macos"))]
+    ("MediaStop", MEDIA_STOP),
+    #[cfg(not(target_os = "macos"))]
+    ("MediaNext", MEDIA_NEXT),
+    #[cfg(not(target_os = "macos"))]
+    ("MediaPrevious", MEDIA_PREVIOUS),
+];
+
 /// Creates Enum.KeyCode - Platform-specific key codes for FFI
 pub fn create_keycode(lua: Lua) -> LuaResult<LuaValue> {
+    let mut builder = TableBuilder::new(lua)?;
+    for &(name, code) in KEY_CODES {
+        builder = builder.with_value(name, code)?;
+    }
+    builder.build_readonly().map(LuaValue::Table)
+}
+
+/// Creates Enum.Scancode - Platform-specific scancodes of physical keys, for layout-independent input
+pub fn create_scancode(lua: Lua) -> LuaResult<LuaValue> {
+    let mut builder = TableBuilder::new(lua)?;
+    for &(name, scancode) in SCANCODES {
+        builder = builder.with_value(name, scancode)?;
+    }
+    builder.build_readonly().map(LuaValue::Table)
+}
+
+fn enum_scancode_from_key_code(lua: &Lua, key: &LuaValue) -> LuaResult<LuaValue> {
+    let code = item_value_from_lua(key, "KeyCode", KEY_CODES)?;
+    match scancode_from_key_code(code) {
+        Some(scancode) => item_into_lua(lua, "Scancode", SCANCODES, scancode),
+        None => Ok(LuaValue::Nil),
+    }
+}
+
+fn enum_key_code_from_scancode(lua: &Lua, scancode: &LuaValue) -> LuaResult<LuaValue> {
+    let scancode = item_value_from_lua(scancode, "Scancode", SCANCODES)?;
+    match key_code_from_scancode(scancode) {
+        Some(code) => item_into_lua(lua, "KeyCode", KEY_CODES, code),
+        None => Ok(LuaValue::Nil),
+    }
+}
+
+/// Every `Enum.MouseButton` item, by name, along with its value
+pub const MOUSE_BUTTONS: &[(&str, i32)] = &[
+    ("Left", 0),
+    ("Right", 1),
+    ("Middle", 2),
+    ("Button4", 3),
+    ("Button5", 4),
+];
+
+/// Creates Enum.MouseButton
+pub fn create_mouse_button(lua: Lua) -> LuaResult<LuaValue> {
+    let mut builder = TableBuilder::new(lua)?;
+    for &(name, value) in MOUSE_BUTTONS {
+        builder = builder.with_value(name, value)?;
+    }
+    builder.build_readonly().map(LuaValue::Table)
+}
+
+/// Creates Enum.GamepadButton - Platform-specific gamepad buttons for FFI
+pub fn create_gamepad_button(lua: Lua) -> LuaResult<LuaValue> {
     TableBuilder::new(lua)?
-        .with_value("A", A)?
-        .with_value("B", B)?
-        .with_value("C", C)?
-        .with_value("D", D)?
-        .with_value("E", E)?
-        .with_value("F", F)?
-        .with_value("G", G)?
-        .with_value("H", H)?
-        .with_value("I", I)?
-        .with_value("J", J)?
-        .with_value("K", K)?
-        .with_value("L", L)?
-        .with_value("M", M)?
-        .with_value("N", N)?
-        .with_value("O", O)?
-        .with_value("P", P)?
-        .with_value("Q", Q)?
-        .with_value("R", R)?
-        .with_value("S", S)?
-        .with_value("T", T)?
-        .with_value("U", U)?
-        .with_value("V", V)?
-        .with_value("W", W)?
-        .with_value("X", X)?
-        .with_value("Y", Y)?
-        .with_value("Z", Z)?
-        .with_value("Zero", ZERO)?
-        .with_value("One", ONE)?
-        .with_value("Two", TWO)?
-        .with_value("Three", THREE)?
-        .with_value("Four", FOUR)?
-        .with_value("Five", FIVE)?
-        .with_value("Six", SIX)?
-        .with_value("Seven", SEVEN)?
-        .with_value("Eight", EIGHT)?
-        .with_value("Nine", NINE)?
-        .with_value("F1", F1)?
-        .with_value("F2", F2)?
-        .with_value("F3", F3)?
-        .with_value("F4", F4)?
-        .with_value("F5", F5)?
-        .with_value("F6", F6)?
-        .with_value("F7", F7)?
-        .with_value("F8", F8)?
-        .with_value("F9", F9)?
-        .with_value("F10", F10)?
-        .with_value("F11", F11)?
-        .with_value("F12", F12)?
-        .with_value("Escape", ESCAPE)?
-        .with_value("Tab", TAB)?
-        .with_value("CapsLock", CAPS_LOCK)?
-        .with_value("LeftShift", LEFT_SHIFT)?
-        .with_value("RightShift", RIGHT_SHIFT)?
-        .with_value("LeftControl", LEFT_CONTROL)?
-        .with_value("RightControl", RIGHT_CONTROL)?
-        .with_value("LeftAlt", LEFT_ALT)?
-        .with_value("RightAlt", RIGHT_ALT)?
-        .with_value("LeftSuper", LEFT_SUPER)?
-        .with_value("RightSuper", RIGHT_SUPER)?
-        .with_value("Menu", MENU)?
-        .with_value("Space", SPACE)?
-        .with_value("Return", RETURN)?
-        .with_value("Backspace", BACKSPACE)?
-        .with_value("Delete", DELETE)?
-        .with_value("Insert", INSERT)?
-        .with_value("Home", HOME)?
-        .with_value("End", END)?
-        .with_value("PageUp", PAGE_UP)?
-        .with_value("PageDown", PAGE_DOWN)?
-        .with_value("Up", UP)?
-        .with_value("Down", DOWN)?
-        .with_value("Left", LEFT)?
-        .with_value("Right", RIGHT)?
-        .with_value("Numpad0", NUMPAD0)?
-        .with_value("Numpad1", NUMPAD1)?
-        .with_value("Numpad2", NUMPAD2)?
-        .with_value("Numpad3", NUMPAD3)?
-        .with_value("Numpad4", NUMPAD4)?
-        .with_value("Numpad5", NUMPAD5)?
-        .with_value("Numpad6", NUMPAD6)?
-        .with_value("Numpad7", NUMPAD7)?
-        .with_value("Numpad8", NUMPAD8)?
-        .with_value("Numpad9", NUMPAD9)?
-        .with_value("NumLock", NUM_LOCK)?
-        .with_value("Semicolon", SEMICOLON)?
-        .with_value("Equals", EQUALS)?
-        .with_value("Comma", COMMA)?
-        .with_value("Minus", MINUS)?
-        .with_value("Period", PERIOD)?
-        .with_value("Slash", SLASH)?
-        .with_value("Grave", GRAVE)?
-        .with_value("LeftBracket", LEFT_BRACKET)?
-        .with_value("Backslash", BACKSLASH)?
-        .with_value("RightBracket", RIGHT_BRACKET)?
-        .with_value("Apostrophe", APOSTROPHE)?
+        .with_value("ButtonA", gamepad::A)?
+        .with_value("ButtonB", gamepad::B)?
+        .with_value("ButtonX", gamepad::X)?
+        .with_value("ButtonY", gamepad::Y)?
+        .with_value("LeftBumper", gamepad::LEFT_BUMPER)?
+        .with_value("RightBumper", gamepad::RIGHT_BUMPER)?
+        .with_value("LeftStick", gamepad::LEFT_STICK)?
+        .with_value("RightStick", gamepad::RIGHT_STICK)?
+        .with_value("DPadUp", gamepad::DPAD_UP)?
+        .with_value("DPadDown", gamepad::DPAD_DOWN)?
+        .with_value("DPadLeft", gamepad::DPAD_LEFT)?
+        .with_value("DPadRight", gamepad::DPAD_RIGHT)?
+        .with_value("Start", gamepad::START)?
+        .with_value("Back", gamepad::BACK)?
+        .with_value("Guide", gamepad::GUIDE)?
         .build_readonly()
         .map(LuaValue::Table)
 }
 
-/// Creates Enum.MouseButton
-pub fn create_mouse_button(lua: Lua) -> LuaResult<LuaValue> {
+/// Creates Enum.UserInputType
+pub fn create_user_input_type(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua)?
+        .with_value("MouseButton1", 0)?
+        .with_value("MouseButton2", 1)?
+        .with_value("MouseButton3", 2)?
+        .with_value("MouseWheel", 3)?
+        .with_value("MouseMovement", 4)?
+        .with_value("Touch", 7)?
+        .with_value("Keyboard", 8)?
+        .with_value("Focus", 9)?
+        .with_value("Gamepad1", 12)?
+        .with_value("Gamepad2", 13)?
+        .with_value("Gamepad3", 14)?
+        .with_value("Gamepad4", 15)?
+        .with_value("Gamepad5", 16)?
+        .with_value("Gamepad6", 17)?
+        .with_value("Gamepad7", 18)?
+        .with_value("Gamepad8", 19)?
+        .with_value("TextInput", 20)?
+        .with_value("None", 22)?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+/// Creates Enum.ModifierKey - Platform-specific modifier masks, which can be combined
+pub fn create_modifier_key(lua: Lua) -> LuaResult<LuaValue> {
     TableBuilder::new(lua)?
-        .with_value("Left", 0)?
-        .with_value("Right", 1)?
-        .with_value("Middle", 2)?
-        .with_value("Button4", 3)?
-        .with_value("Button5", 4)?
+        .with_value("Shift", modifiers::SHIFT)?
+        .with_value("Ctrl", modifiers::CTRL)?
+        .with_value("Alt", modifiers::ALT)?
+        .with_value("Meta", modifiers::META)?
         .build_readonly()
         .map(LuaValue::Table)
 }
@@ -467,14 +741,55 @@ pub fn create_sort_order(lua: Lua) -> LuaResult<LuaValue> {
         .map(LuaValue::Table)
 }
 
-/// Creates the main Enum global
-pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+/// Creates Enum.FillDirection
+pub fn create_fill_direction(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua)?
+        .with_value("Horizontal", 0)?
+        .with_value("Vertical", 1)?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+/// Creates the table of all enums, with plain number items, along with the functions for converting between them
+fn create_enums(lua: &Lua) -> LuaResult<LuaTable> {
     TableBuilder::new(lua.clone())?
         .with_value("KeyCode", create_keycode(lua.clone())?)?
+        .with_value("Scancode", create_scancode(lua.clone())?)?
         .with_value("MouseButton", create_mouse_button(lua.clone())?)?
+        .with_value("GamepadButton", create_gamepad_button(lua.clone())?)?
+        .with_value("UserInputType", create_user_input_type(lua.clone())?)?
+        .with_value("ModifierKey", create_modifier_key(lua.clone())?)?
         .with_value("EasingStyle", create_easing_style(lua.clone())?)?
         .with_value("EasingDirection", create_easing_direction(lua.clone())?)?
-        .with_value("SortOrder", create_sort_order(lua)?)?
+        .with_value("SortOrder", create_sort_order(lua.clone())?)?
+        .with_value("FillDirection", create_fill_direction(lua.clone())?)?
+        .with_function("scancodeFromKeyCode", |lua, key: LuaValue| {
+            enum_scancode_from_key_code(lua, &key)
+        })?
+        .with_function("keyCodeFromScancode", |lua, scancode: LuaValue| {
+            enum_key_code_from_scancode(lua, &scancode)
+        })?
         .build_readonly()
-        .map(LuaValue::Table)
+}
+
+/**
+    Creates the main Enum global
+
+    Which kind of items the enums contain depends on the `new-enum-items` feature flag:
+
+    - Enabled - items are [`EnumItem`] userdata
+    - Disabled - items are plain numbers
+    - Not set - items are plain numbers, and scripts using them get a deprecation warning
+*/
+pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+    let enums = create_enums(&lua)?;
+    let flag = FeatureFlag::NewEnumItems;
+    let table = if FeatureFlags::is_enabled_in(&lua, flag) {
+        item::convert_enums(&lua, &enums)?
+    } else if FeatureFlags::get_in(&lua, flag).is_some() {
+        enums
+    } else {
+        item::create_deprecated_enums(&lua, enums)?
+    };
+    Ok(LuaValue::Table(table))
 }
@@ -0,0 +1,235 @@
+//! Scancodes, which identify physical keys regardless of the keyboard layout:
+//! - Windows: PS/2 set 1 scancodes, with `0xE000` added for extended keys
+//! - Linux: evdev KEY_* codes, the same as `Enum.KeyCode`
+//! - macOS: Carbon kVK_* codes, the same as `Enum.KeyCode`
+//!
+//! Every `Enum.Scancode` item is named after the `Enum.KeyCode`
+//! item for the key in the same position on a US keyboard layout.
+
+use crate::KEY_CODES;
+
+/// Every `Enum.Scancode` item, by name, along with its platform-specific scancode
+#[cfg(target_os = "windows")]
+pub const SCANCODES: &[(&str, i32)] = &[
+    ("A", 0x1E),
+    ("B", 0x30),
+    ("C", 0x2E),
+    ("D", 0x20),
+    ("E", 0x12),
+    ("F", 0x21),
+    ("G", 0x22),
+    ("H", 0x23),
+    ("I", 0x17),
+    ("J", 0x24),
+    ("K", 0x25),
+    ("L", 0x26),
+    ("M", 0x32),
+    ("N", 0x31),
+    ("O", 0x18),
+    ("P", 0x19),
+    ("Q", 0x10),
+    ("R", 0x13),
+    ("S", 0x1F),
+    ("T", 0x14),
+    ("U", 0x16),
+    ("V", 0x2F),
+    ("W", 0x11),
+    ("X", 0x2D),
+    ("Y", 0x15),
+    ("Z", 0x2C),
+    ("Zero", 0x0B),
+    ("One", 0x02),
+    ("Two", 0x03),
+    ("Three", 0x04),
+    ("Four", 0x05),
+    ("Five", 0x06),
+    ("Six", 0x07),
+    ("Seven", 0x08),
+    ("Eight", 0x09),
+    ("Nine", 0x0A),
+    ("F1", 0x3B),
+    ("F2", 0x3C),
+    ("F3", 0x3D),
+    ("F4", 0x3E),
+    ("F5", 0x3F),
+    ("F6", 0x40),
+    ("F7", 0x41),
+    ("F8", 0x42),
+    ("F9", 0x43),
+    ("F10", 0x44),
+    ("F11", 0x57),
+    ("F12", 0x58),
+    ("Escape", 0x01),
+    ("Tab", 0x0F),
+    ("CapsLock", 0x3A),
+    ("LeftShift", 0x2A),
+    ("RightShift", 0x36),
+    ("LeftControl", 0x1D),
+    ("RightControl", 0xE01D),
+    ("LeftAlt", 0x38),
+    ("RightAlt", 0xE038),
+    ("LeftSuper", 0xE05B),
+    ("RightSuper", 0xE05C),
+    ("Menu", 0xE05D),
+    ("PrintScreen", 0xE037),
+    ("ScrollLock", 0x46),
+    ("Pause", 0x45),
+    ("Space", 0x39),
+    ("Return", 0x1C),
+    ("Backspace", 0x0E),
+    ("Delete", 0xE053),
+    ("Insert", 0xE052),
+    ("Home", 0xE047),
+    ("End", 0xE04F),
+    ("PageUp", 0xE049),
+    ("PageDown", 0xE051),
+    ("Up", 0xE048),
+    ("Down", 0xE050),
+    ("Left", 0xE04B),
+    ("Right", 0xE04D),
+    ("Numpad0", 0x52),
+    ("Numpad1", 0x4F),
+    ("Numpad2", 0x50),
+    ("Numpad3", 0x51),
+    ("Numpad4", 0x4B),
+    ("Numpad5", 0x4C),
+    ("Numpad6", 0x4D),
+    ("Numpad7", 0x47),
+    ("Numpad8", 0x48),
+    ("Numpad9", 0x49),
+    ("NumLock", 0xE045),
+    ("NumpadAdd", 0x4E),
+    ("NumpadSubtract", 0x4A),
+    ("NumpadMultiply", 0x37),
+    ("NumpadDivide", 0xE035),
+    ("NumpadDecimal", 0x53),
+    ("NumpadEnter", 0xE01C),
+    ("Semicolon", 0x27),
+    ("Equals", 0x0D),
+    ("Comma", 0x33),
+    ("Minus", 0x0C),
+    ("Period", 0x34),
+    ("Slash", 0x35),
+    ("Grave", 0x29),
+    ("LeftBracket", 0x1A),
+    ("Backslash", 0x2B),
+    ("RightBracket", 0x1B),
+    ("Apostrophe", 0x28),
+    ("IntlBackslash", 0x56),
+    ("Yen", 0x7D),
+    ("VolumeMute", 0xE020),
+    ("VolumeDown", 0xE02E),
+    ("VolumeUp", 0xE030),
+    ("MediaPlayPause", 0xE022),
+    ("MediaStop", 0xE024),
+    ("MediaNext", 0xE019),
+    ("MediaPrevious", 0xE010),
+];
+
+/// Every `Enum.Scancode` item, by name, along with its platform-specific scancode
+#[cfg(not(target_os = "windows"))]
+pub const SCANCODES: &[(&str, i32)] = KEY_CODES;
+
+/**
+    Finds the scancode of the physical key that types the given `Enum.KeyCode`
+    with the current keyboard layout, or `None` if no key does.
+*/
+#[must_use]
+pub fn scancode_from_key_code(code: i32) -> Option<i32> {
+    #[cfg(target_os = "windows")]
+    if let Some(scancode) = os::scancode_from_key_code(code) {
+        return Some(scancode);
+    }
+    let (name, _) = KEY_CODES.iter().find(|&&(_, item)| item == code)?;
+    SCANCODES
+        .iter()
+        .find(|&&(item, _)| item == *name)
+        .map(|&(_, scancode)| scancode)
+}
+
+/**
+    Finds the `Enum.KeyCode` that the physical key with the given scancode
+    types with the current keyboard layout, or `None` if it is not a known key.
+*/
+#[must_use]
+pub fn key_code_from_scancode(scancode: i32) -> Option<i32> {
+    #[cfg(target_os = "windows")]
+    if let Some(code) = os::key_code_from_scancode(scancode) {
+        return Some(code);
+    }
+    let (name, _) = SCANCODES.iter().find(|&&(_, item)| item == scancode)?;
+    KEY_CODES
+        .iter()
+        .find(|&&(item, _)| item == *name)
+        .map(|&(_, code)| code)
+}
+
+/*
+    Virtual keys on Windows depend on the keyboard layout, but only for
+    the keys that type characters - the rest are the same for every layout,
+    and are better looked up from the tables above, since the layout gives
+    the numpad keys the virtual keys they have while num lock is off
+*/
+#[cfg(target_os = "windows")]
+mod os {
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+        MAPVK_VK_TO_VSC, MAPVK_VSC_TO_VK, MapVirtualKeyW,
+    };
+
+    /**
+        Returns whether a scancode is for one of the keys that type characters,
+        which are the ones that keyboard layouts give different virtual keys.
+    */
+    fn is_character_key(scancode: i32) -> bool {
+        matches!(
+            scancode,
+            0x02..=0x0D // Number row
+                | 0x10..=0x1B // Top letter row and brackets
+                | 0x1E..=0x29 // Middle letter row, semicolon, apostrophe and grave
+                | 0x2B..=0x35 // Backslash, bottom letter row, comma, period and slash
+                | 0x56 // Key between left shift and Z on ISO keyboards
+                | 0x7D // Yen
+        )
+    }
+
+    pub(super) fn scancode_from_key_code(code: i32) -> Option<i32> {
+        let vk = u32::try_from(code).ok()?;
+        let scancode = unsafe { MapVirtualKeyW(vk, MAPVK_VK_TO_VSC) } as i32;
+        is_character_key(scancode).then_some(scancode)
+    }
+
+    pub(super) fn key_code_from_scancode(scancode: i32) -> Option<i32> {
+        if !is_character_key(scancode) {
+            return None;
+        }
+        let vk = unsafe { MapVirtualKeyW(scancode as u32, MAPVK_VSC_TO_VK) } as i32;
+        (vk != 0).then_some(vk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keycodes;
+
+    #[test]
+    fn every_key_code_has_a_scancode() {
+        for &(name, _) in KEY_CODES {
+            assert!(
+                SCANCODES.iter().any(|&(item, _)| item == name),
+                "KeyCode.{name} is missing from Scancode"
+            );
+        }
+        assert_eq!(SCANCODES.len(), KEY_CODES.len());
+    }
+
+    #[test]
+    fn converts_between_key_codes_and_scancodes() {
+        for code in [keycodes::ESCAPE, keycodes::NUMPAD7, keycodes::NUMPAD_DIVIDE] {
+            let scancode = scancode_from_key_code(code).unwrap();
+            assert_eq!(key_code_from_scancode(scancode), Some(code));
+        }
+        assert_eq!(scancode_from_key_code(-1), None);
+        assert_eq!(key_code_from_scancode(-1), None);
+    }
+}
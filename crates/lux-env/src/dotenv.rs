@@ -0,0 +1,211 @@
+use std::{fmt, iter::Peekable, str::Chars};
+
+/**
+    A single `KEY=value` pair parsed from a `.env` file.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub key: String,
+    pub value: String,
+    /// Whether `${VAR}` references in the value should be expanded - false for single quoted values
+    pub expand: bool,
+}
+
+/**
+    An error parsing a `.env` file, with the line it happened on.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Cursor<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_inline_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\r')) {
+            self.bump();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+}
+
+/**
+    Parses the contents of a `.env` file.
+
+    Supports `#` comments, an optional `export` prefix, unquoted values with trailing
+    ` # comments`, single quoted literal values, and double quoted values with escapes
+    that may span multiple lines. Later entries for the same key are kept as-is.
+
+    # Errors
+
+    Errors if a line is not a valid `KEY=value` pair, or a quoted value is not terminated.
+*/
+pub fn parse(contents: &str) -> Result<Vec<Entry>, ParseError> {
+    let mut cursor = Cursor {
+        chars: contents.chars().peekable(),
+        line: 1,
+    };
+    let mut entries = Vec::new();
+
+    loop {
+        cursor.skip_inline_whitespace();
+        match cursor.peek() {
+            None => break,
+            Some('\n') => {
+                cursor.bump();
+                continue;
+            }
+            Some('#') => {
+                cursor.skip_line();
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        let mut key = parse_key(&mut cursor)?;
+        if key == "export" && matches!(cursor.peek(), Some(' ' | '\t')) {
+            cursor.skip_inline_whitespace();
+            key = parse_key(&mut cursor)?;
+        }
+
+        cursor.skip_inline_whitespace();
+        if cursor.bump() != Some('=') {
+            return Err(cursor.error(format!("expected '=' after '{key}'")));
+        }
+        cursor.skip_inline_whitespace();
+
+        let (value, expand) = match cursor.peek() {
+            Some('"') => {
+                cursor.bump();
+                (parse_double_quoted(&mut cursor)?, true)
+            }
+            Some('\'') => {
+                cursor.bump();
+                (parse_single_quoted(&mut cursor)?, false)
+            }
+            _ => (parse_unquoted(&mut cursor), true),
+        };
+
+        // Only a comment may follow a quoted value on the same line
+        cursor.skip_inline_whitespace();
+        match cursor.peek() {
+            None | Some('\n') => {}
+            Some('#') => cursor.skip_line(),
+            Some(c) => {
+                return Err(cursor.error(format!("unexpected '{c}' after value of '{key}'")));
+            }
+        }
+
+        entries.push(Entry { key, value, expand });
+    }
+
+    Ok(entries)
+}
+
+fn parse_key(cursor: &mut Cursor) -> Result<String, ParseError> {
+    let mut key = String::new();
+    while let Some(c) = cursor.peek() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            key.push(c);
+            cursor.bump();
+        } else {
+            break;
+        }
+    }
+    if key.is_empty() || key.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(cursor.error("expected a variable name"));
+    }
+    Ok(key)
+}
+
+fn parse_double_quoted(cursor: &mut Cursor) -> Result<String, ParseError> {
+    let mut value = String::new();
+    loop {
+        match cursor.bump() {
+            None => return Err(cursor.error("unterminated double quoted value")),
+            Some('"') => return Ok(value),
+            Some('\\') => match cursor.bump() {
+                Some('n') => value.push('\n'),
+                Some('r') => value.push('\r'),
+                Some('t') => value.push('\t'),
+                Some(c @ ('"' | '\\' | '$')) => {
+                    // Keep escaped dollars escaped, so that expansion leaves them alone
+                    if c == '$' {
+                        value.push('$');
+                    }
+                    value.push(c);
+                }
+                Some(c) => {
+                    value.push('\\');
+                    value.push(c);
+                }
+                None => return Err(cursor.error("unterminated double quoted value")),
+            },
+            Some(c) => value.push(c),
+        }
+    }
+}
+
+fn parse_single_quoted(cursor: &mut Cursor) -> Result<String, ParseError> {
+    let mut value = String::new();
+    loop {
+        match cursor.bump() {
+            None => return Err(cursor.error("unterminated single quoted value")),
+            Some('\'') => return Ok(value),
+            Some(c) => value.push(c),
+        }
+    }
+}
+
+fn parse_unquoted(cursor: &mut Cursor) -> String {
+    let mut value = String::new();
+    while let Some(c) = cursor.peek() {
+        if c == '\n' {
+            break;
+        }
+        // A comment must be separated from the value by whitespace
+        if c == '#' && (value.is_empty() || value.ends_with([' ', '\t'])) {
+            break;
+        }
+        value.push(c);
+        cursor.bump();
+    }
+    value.trim().to_string()
+}
@@ -0,0 +1,92 @@
+/**
+    Expands environment variable references in a string.
+
+    Supports `$NAME`, `${NAME}`, `${NAME:-default}` - used when `NAME` is unset or empty -
+    and `$$` for a literal dollar sign. Variables that are not set expand to an empty string.
+
+    # Errors
+
+    Errors if a `${` reference is not terminated, or contains an invalid variable name.
+*/
+pub fn expand(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    expand_with(input, &lookup)
+}
+
+fn expand_with(input: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.char_indices().peekable();
+
+    while let Some((_, c)) = chars.next() {
+        if c != '$' {
+            output.push(c);
+            continue;
+        }
+        match chars.peek().copied() {
+            Some((_, '$')) => {
+                chars.next();
+                output.push('$');
+            }
+            Some((start, '{')) => {
+                chars.next();
+                let Some(len) = closing_brace(&input[start + 1..]) else {
+                    return Err(format!(
+                        "unterminated variable reference '{}'",
+                        &input[start - 1..]
+                    ));
+                };
+                let inner = &input[start + 1..start + 1 + len];
+                let (name, default) = match inner.split_once(":-") {
+                    Some((name, default)) => (name, Some(default)),
+                    None => (inner, None),
+                };
+                if !is_valid_name(name) {
+                    return Err(format!("invalid variable name '{name}' in '${{{inner}}}'"));
+                }
+                match (lookup(name).filter(|v| !v.is_empty()), default) {
+                    (Some(value), _) => output.push_str(&value),
+                    (None, Some(default)) => output.push_str(&expand_with(default, lookup)?),
+                    (None, None) => {}
+                }
+                // Skip past the closing brace
+                while chars.next_if(|&(i, _)| i <= start + 1 + len).is_some() {}
+            }
+            Some((start, c)) if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if c.is_ascii_alphanumeric() || c == '_' {
+                        end = i + c.len_utf8();
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if let Some(value) = lookup(&input[start..end]) {
+                    output.push_str(&value);
+                }
+            }
+            _ => output.push('$'),
+        }
+    }
+
+    Ok(output)
+}
+
+/// Finds the brace closing a `${`, skipping over any nested `${...}` in a default value
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
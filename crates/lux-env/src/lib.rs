@@ -0,0 +1,191 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Environment variable backed configuration for Lux
+//!
+//! Variables are read from and written to the environment stored in app data,
+//! the same one used by `process.env`, `os.getenv` and child processes.
+
+use std::{collections::HashSet, path::PathBuf};
+
+use lux_utils::{TableBuilder, process::ProcessEnv};
+use mlua::prelude::*;
+
+mod dotenv;
+mod expand;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+fn process_env(lua: &Lua) -> LuaResult<ProcessEnv> {
+    lua.app_data_ref::<ProcessEnv>()
+        .map(|env| env.clone())
+        .ok_or_else(|| LuaError::runtime("Missing process env in Lua app data"))
+}
+
+fn get_var(lua: &Lua, name: &str) -> LuaResult<Option<String>> {
+    Ok(process_env(lua)?
+        .get_value_bytes(name)
+        .map(|value| String::from_utf8_lossy(&value).into_owned()))
+}
+
+fn env_get(lua: &Lua, (name, default): (String, Option<String>)) -> LuaResult<Option<String>> {
+    Ok(get_var(lua, &name)?.or(default))
+}
+
+fn env_require(lua: &Lua, name: String) -> LuaResult<String> {
+    get_var(lua, &name)?
+        .ok_or_else(|| LuaError::runtime(format!("Missing required environment variable '{name}'")))
+}
+
+fn env_get_number(lua: &Lua, (name, default): (String, Option<f64>)) -> LuaResult<Option<f64>> {
+    let Some(value) = get_var(lua, &name)? else {
+        return Ok(default);
+    };
+    value.trim().parse::<f64>().map(Some).map_err(|_| {
+        LuaError::runtime(format!(
+            "Environment variable '{name}' is not a number, got '{value}'"
+        ))
+    })
+}
+
+fn env_get_bool(lua: &Lua, (name, default): (String, Option<bool>)) -> LuaResult<Option<bool>> {
+    let Some(value) = get_var(lua, &name)? else {
+        return Ok(default);
+    };
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(Some(true)),
+        "0" | "false" | "no" | "off" | "" => Ok(Some(false)),
+        _ => Err(LuaError::runtime(format!(
+            "Environment variable '{name}' is not a boolean, got '{value}'"
+        ))),
+    }
+}
+
+fn env_expand(lua: &Lua, input: String) -> LuaResult<String> {
+    let env = process_env(lua)?;
+    expand::expand(&input, |name| {
+        env.get_value_bytes(name)
+            .map(|value| String::from_utf8_lossy(&value).into_owned())
+    })
+    .map_err(LuaError::runtime)
+}
+
+/**
+    Options for `env.load`.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+struct LoadOptions {
+    /// Replace variables that are already set, instead of keeping them
+    override_existing: bool,
+    /// Return an empty table instead of erroring when the file does not exist
+    optional: bool,
+}
+
+impl FromLua for LoadOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                override_existing: t.get::<Option<bool>>("override")?.unwrap_or_default(),
+                optional: t.get::<Option<bool>>("optional")?.unwrap_or_default(),
+            }),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("LoadOptions"),
+                message: Some(String::from("Expected load options to be a table")),
+            }),
+        }
+    }
+}
+
+/**
+    Applies parsed entries to the environment, in order, returning the values that were set.
+
+    Values are expanded against the environment as it is after applying the previous
+    entries, so later entries may refer to earlier ones. Unless `override_existing` is
+    set, variables that were already set before loading keep their current value.
+*/
+fn apply(lua: &Lua, entries: Vec<dotenv::Entry>, override_existing: bool) -> LuaResult<LuaTable> {
+    let env = process_env(lua)?;
+    let existing = entries
+        .iter()
+        .filter(|entry| !override_existing && env.get_value_bytes(&entry.key).is_some())
+        .map(|entry| entry.key.clone())
+        .collect::<HashSet<_>>();
+
+    let loaded = lua.create_table()?;
+    for entry in entries {
+        if existing.contains(&entry.key) {
+            continue;
+        }
+        let value = if entry.expand {
+            expand::expand(&entry.value, |name| {
+                env.get_value_bytes(name)
+                    .map(|value| String::from_utf8_lossy(&value).into_owned())
+            })
+            .map_err(|e| LuaError::runtime(format!("Failed to expand '{}': {e}", entry.key)))?
+        } else {
+            entry.value
+        };
+        env.set_value_bytes(&entry.key, value.as_bytes().to_vec());
+        loaded.set(entry.key, value)?;
+    }
+    Ok(loaded)
+}
+
+fn env_load(lua: &Lua, (path, options): (Option<String>, LoadOptions)) -> LuaResult<LuaTable> {
+    let path = PathBuf::from(path.unwrap_or_else(|| String::from(".env")));
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if options.optional && e.kind() == std::io::ErrorKind::NotFound => {
+            return lua.create_table();
+        }
+        Err(e) => {
+            return Err(LuaError::runtime(format!(
+                "Failed to read '{}': {e}",
+                path.display()
+            )));
+        }
+    };
+    let entries = dotenv::parse(&contents)
+        .map_err(|e| LuaError::runtime(format!("Failed to parse '{}', {e}", path.display())))?;
+    apply(lua, entries, options.override_existing)
+}
+
+fn env_parse(lua: &Lua, contents: String) -> LuaResult<LuaTable> {
+    let entries = dotenv::parse(&contents).map_err(|e| LuaError::runtime(e.to_string()))?;
+    let parsed = lua.create_table()?;
+    for entry in entries {
+        // Escaped dollar signs are kept doubled for expansion, which is not done here
+        let value = if entry.expand {
+            entry.value.replace("$$", "$")
+        } else {
+            entry.value
+        };
+        parsed.set(entry.key, value)?;
+    }
+    Ok(parsed)
+}
+
+/**
+    Creates the `env` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("get", env_get)?
+        .with_function("require", env_require)?
+        .with_function("getNumber", env_get_number)?
+        .with_function("getBool", env_get_bool)?
+        .with_function("expand", env_expand)?
+        .with_function("load", env_load)?
+        .with_function("parse", env_parse)?
+        .build_readonly()
+}
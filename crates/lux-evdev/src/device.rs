@@ -0,0 +1,314 @@
+use std::{
+    path::Path,
+    rc::Rc,
+    sync::{
+        Arc, Weak,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use async_io::Timer;
+use futures_lite::future;
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+use parking_lot::Mutex;
+
+use lux_enum::{KEY_CODES, MOUSE_BUTTONS, item_into_lua, item_value_from_lua};
+use lux_signal::{ConnectHook, Signal};
+
+use crate::os::{
+    BTN_EXTRA, BTN_LEFT, DeviceInfo, EV_KEY, EV_REL, EV_SYN, REL_HWHEEL, REL_WHEEL, REL_X, REL_Y,
+    RawEvent, Reader, SYN_REPORT,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/**
+    Options for `evdev.open`.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct OpenOptions {
+    grab: bool,
+}
+
+impl FromLua for OpenOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                grab: t.get::<Option<bool>>("grab")?.unwrap_or_default(),
+            }),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("OpenOptions"),
+                message: Some(String::from("Expected options to be a table")),
+            }),
+        }
+    }
+}
+
+/**
+    Converts a key code into the mouse button it is for, if it is one.
+*/
+fn mouse_button_from_code(code: u16) -> Option<i32> {
+    (BTN_LEFT..=BTN_EXTRA)
+        .contains(&code)
+        .then(|| i32::from(code - BTN_LEFT))
+}
+
+/**
+    Converts an `Enum.MouseButton` item into its value.
+*/
+pub(crate) fn mouse_button(value: &LuaValue) -> LuaResult<u16> {
+    let button = item_value_from_lua(value, "MouseButton", MOUSE_BUTTONS)?;
+    Ok(button as u16)
+}
+
+/**
+    Converts an `Enum.KeyCode` item into its key code.
+*/
+pub(crate) fn key_code(value: &LuaValue) -> LuaResult<u16> {
+    let key = item_value_from_lua(value, "KeyCode", KEY_CODES)?;
+    Ok(key as u16)
+}
+
+/**
+    Signals for the events of a device, all of which share a single reader.
+*/
+struct DeviceSignals {
+    key_down: Signal,
+    key_up: Signal,
+    mouse_button_down: Signal,
+    mouse_button_up: Signal,
+    mouse_moved: Signal,
+    mouse_wheel: Signal,
+}
+
+impl DeviceSignals {
+    fn count(&self) -> usize {
+        [
+            &self.key_down,
+            &self.key_up,
+            &self.mouse_button_down,
+            &self.mouse_button_up,
+            &self.mouse_moved,
+            &self.mouse_wheel,
+        ]
+        .iter()
+        .map(|signal| signal.count())
+        .sum()
+    }
+}
+
+/**
+    Motion that is accumulated until the device reports that a set of events is complete.
+*/
+#[derive(Debug, Default)]
+struct Motion {
+    dx: i32,
+    dy: i32,
+    wheel: i32,
+    hwheel: i32,
+}
+
+/**
+    An input device opened for reading its events.
+
+    Events are only read while a handler is connected to any of the signals
+    of the device, so that an unused device never keeps the scheduler alive.
+*/
+#[derive(Clone)]
+pub(crate) struct InputDevice {
+    info: Arc<DeviceInfo>,
+    reader: Arc<Mutex<Option<Reader>>>,
+    closed: Arc<AtomicBool>,
+    signals: Arc<DeviceSignals>,
+}
+
+impl InputDevice {
+    pub(crate) fn open(path: &str, options: OpenOptions) -> LuaResult<Self> {
+        let (reader, info) = Reader::open(Path::new(path), options.grab)
+            .map_err(|e| LuaError::runtime(format!("Failed to open input device '{path}': {e}")))?;
+
+        let reader = Arc::new(Mutex::new(Some(reader)));
+        let closed = Arc::new(AtomicBool::new(false));
+        let reading = Arc::new(AtomicBool::new(false));
+
+        // Every signal starts the same reader, which stops once all of them are unused,
+        // and the signals are only weakly referenced by their own hooks to not leak
+        let signals = Arc::new_cyclic(|weak: &Weak<DeviceSignals>| {
+            let weak = weak.clone();
+            let reader = Arc::clone(&reader);
+            let closed = Arc::clone(&closed);
+            let hook: ConnectHook = Rc::new(move |lua, _| {
+                let Some(signals) = weak.upgrade() else {
+                    return;
+                };
+                if !reading.swap(true, Ordering::SeqCst) {
+                    lua.spawn_local(read_events(
+                        lua.clone(),
+                        signals,
+                        Arc::clone(&reader),
+                        Arc::clone(&closed),
+                        Arc::clone(&reading),
+                    ));
+                }
+            });
+            let new_signal = || Signal::new().with_connect_hook(Rc::clone(&hook));
+            DeviceSignals {
+                key_down: new_signal(),
+                key_up: new_signal(),
+                mouse_button_down: new_signal(),
+                mouse_button_up: new_signal(),
+                mouse_moved: new_signal(),
+                mouse_wheel: new_signal(),
+            }
+        });
+
+        Ok(Self {
+            info: Arc::new(info),
+            reader,
+            closed,
+            signals,
+        })
+    }
+}
+
+async fn read_events(
+    lua: Lua,
+    signals: Arc<DeviceSignals>,
+    reader: Arc<Mutex<Option<Reader>>>,
+    closed: Arc<AtomicBool>,
+    reading: Arc<AtomicBool>,
+) {
+    // NOTE: The reader is taken out while reading so that the lock
+    // is never held across an await, and put back once done
+    let Some(mut owned) = reader.lock().take() else {
+        reading.store(false, Ordering::SeqCst);
+        return;
+    };
+
+    let mut motion = Motion::default();
+    while signals.count() > 0 && !closed.load(Ordering::SeqCst) {
+        let event = future::or(async { Some(owned.read().await) }, async {
+            Timer::after(POLL_INTERVAL).await;
+            None
+        })
+        .await;
+        match event {
+            None => {}
+            Some(Ok(event)) => dispatch(&lua, &signals, &mut motion, event),
+            // NOTE: Devices that get unplugged error, and there is nothing more to read
+            Some(Err(_)) => break,
+        }
+    }
+
+    if !closed.load(Ordering::SeqCst) {
+        *reader.lock() = Some(owned);
+    }
+    reading.store(false, Ordering::SeqCst);
+}
+
+fn dispatch(lua: &Lua, signals: &DeviceSignals, motion: &mut Motion, event: RawEvent) {
+    // NOTE: Handler errors are reported by the signals themselves
+    let fire = |signal: &Signal, args: LuaResult<LuaMultiValue>| {
+        if let Ok(args) = args {
+            signal.fire_native(lua, args);
+        }
+    };
+
+    match (event.kind, event.code) {
+        (EV_KEY, code) => {
+            if let Some(button) = mouse_button_from_code(code) {
+                let Ok(button) = item_into_lua(lua, "MouseButton", MOUSE_BUTTONS, button) else {
+                    return;
+                };
+                match event.value {
+                    1 => fire(&signals.mouse_button_down, button.into_lua_multi(lua)),
+                    0 => fire(&signals.mouse_button_up, button.into_lua_multi(lua)),
+                    _ => {}
+                }
+            } else {
+                let Ok(key) = item_into_lua(lua, "KeyCode", KEY_CODES, code.into()) else {
+                    return;
+                };
+                match event.value {
+                    0 => fire(&signals.key_up, key.into_lua_multi(lua)),
+                    // Values of 2 are repeats of a key being held down
+                    value => fire(&signals.key_down, (key, value == 2).into_lua_multi(lua)),
+                }
+            }
+        }
+        (EV_REL, REL_X) => motion.dx += event.value,
+        (EV_REL, REL_Y) => motion.dy += event.value,
+        (EV_REL, REL_WHEEL) => motion.wheel += event.value,
+        (EV_REL, REL_HWHEEL) => motion.hwheel += event.value,
+        (EV_SYN, SYN_REPORT) => {
+            let Motion {
+                dx,
+                dy,
+                wheel,
+                hwheel,
+            } = std::mem::take(motion);
+            if dx != 0 || dy != 0 {
+                fire(&signals.mouse_moved, (dx, dy).into_lua_multi(lua));
+            }
+            if wheel != 0 || hwheel != 0 {
+                fire(&signals.mouse_wheel, (wheel, hwheel).into_lua_multi(lua));
+            }
+        }
+        _ => {}
+    }
+}
+
+impl LuaUserData for InputDevice {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "InputDevice");
+        fields.add_field_method_get("path", |_, this| Ok(this.info.path.clone()));
+        fields.add_field_method_get("name", |_, this| Ok(this.info.name.clone()));
+        fields.add_field_method_get("vendorId", |_, this| Ok(this.info.vendor));
+        fields.add_field_method_get("productId", |_, this| Ok(this.info.product));
+        fields.add_field_method_get("isKeyboard", |_, this| Ok(this.info.is_keyboard()));
+        fields.add_field_method_get("isMouse", |_, this| Ok(this.info.is_mouse()));
+
+        fields.add_field_method_get("KeyDown", |_, this| Ok(this.signals.key_down.clone()));
+        fields.add_field_method_get("KeyUp", |_, this| Ok(this.signals.key_up.clone()));
+        fields.add_field_method_get("MouseButtonDown", |_, this| {
+            Ok(this.signals.mouse_button_down.clone())
+        });
+        fields.add_field_method_get("MouseButtonUp", |_, this| {
+            Ok(this.signals.mouse_button_up.clone())
+        });
+        fields.add_field_method_get("MouseMoved", |_, this| Ok(this.signals.mouse_moved.clone()));
+        fields.add_field_method_get("MouseWheel", |_, this| Ok(this.signals.mouse_wheel.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("hasKey", |_, this, key: LuaValue| {
+            Ok(this.info.has_key(key_code(&key)?))
+        });
+        methods.add_method("hasButton", |_, this, button: LuaValue| {
+            Ok(this.info.has_key(BTN_LEFT + mouse_button(&button)?))
+        });
+        methods.add_method("close", |_, this, (): ()| {
+            this.closed.store(true, Ordering::SeqCst);
+            this.reader.lock().take();
+            Ok(())
+        });
+    }
+}
+
+/**
+    Converts information about a device into a table, as returned by `evdev.devices`.
+*/
+pub(crate) fn device_info_to_table(lua: &Lua, info: &DeviceInfo) -> LuaResult<LuaTable> {
+    let t = lua.create_table_with_capacity(0, 6)?;
+    t.set("path", info.path.as_str())?;
+    t.set("name", info.name.as_str())?;
+    t.set("vendorId", info.vendor)?;
+    t.set("productId", info.product)?;
+    t.set("isKeyboard", info.is_keyboard())?;
+    t.set("isMouse", info.is_mouse())?;
+    Ok(t)
+}
@@ -0,0 +1,108 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod device;
+mod os;
+mod synth;
+
+use self::device::{InputDevice, OpenOptions, device_info_to_table, key_code, mouse_button};
+
+pub use self::synth::{send_key, send_motion, send_mouse_button, send_scroll};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `evdev` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `evdev` standard library module.
+
+    The module exists on every platform, but using
+    it errors on anything other than Linux.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("devices", evdev_devices)?
+        .with_function("open", evdev_open)?
+        .with_async_function("keyDown", evdev_key_down)?
+        .with_async_function("keyUp", evdev_key_up)?
+        .with_async_function("pressKey", evdev_press_key)?
+        .with_async_function("mouseDown", evdev_mouse_down)?
+        .with_async_function("mouseUp", evdev_mouse_up)?
+        .with_async_function("click", evdev_click)?
+        .with_async_function("moveMouse", evdev_move_mouse)?
+        .with_async_function("scroll", evdev_scroll)?
+        .build_readonly()
+}
+
+fn evdev_devices(lua: &Lua, (): ()) -> LuaResult<LuaTable> {
+    let devices = os::list_devices()
+        .map_err(|e| LuaError::runtime(format!("Failed to list input devices: {e}")))?;
+    let t = lua.create_table_with_capacity(devices.len(), 0)?;
+    for info in &devices {
+        t.push(device_info_to_table(lua, info)?)?;
+    }
+    Ok(t)
+}
+
+fn evdev_open(_: &Lua, (path, options): (String, OpenOptions)) -> LuaResult<InputDevice> {
+    InputDevice::open(&path, options)
+}
+
+/**
+    Converts an optional `Enum.MouseButton` item into its value, defaulting to the left button.
+*/
+fn button_or_left(button: &LuaValue) -> LuaResult<u16> {
+    match button {
+        LuaValue::Nil => Ok(0),
+        button => mouse_button(button),
+    }
+}
+
+async fn evdev_key_down(lua: Lua, key: LuaValue) -> LuaResult<()> {
+    send_key(&lua, key_code(&key)?, true).await
+}
+
+async fn evdev_key_up(lua: Lua, key: LuaValue) -> LuaResult<()> {
+    send_key(&lua, key_code(&key)?, false).await
+}
+
+async fn evdev_press_key(lua: Lua, key: LuaValue) -> LuaResult<()> {
+    let code = key_code(&key)?;
+    send_key(&lua, code, true).await?;
+    send_key(&lua, code, false).await
+}
+
+async fn evdev_mouse_down(lua: Lua, button: LuaValue) -> LuaResult<()> {
+    send_mouse_button(&lua, button_or_left(&button)?, true).await
+}
+
+async fn evdev_mouse_up(lua: Lua, button: LuaValue) -> LuaResult<()> {
+    send_mouse_button(&lua, button_or_left(&button)?, false).await
+}
+
+async fn evdev_click(lua: Lua, button: LuaValue) -> LuaResult<()> {
+    let button = button_or_left(&button)?;
+    send_mouse_button(&lua, button, true).await?;
+    send_mouse_button(&lua, button, false).await
+}
+
+async fn evdev_move_mouse(lua: Lua, (dx, dy): (i32, i32)) -> LuaResult<()> {
+    send_motion(&lua, dx, dy).await
+}
+
+async fn evdev_scroll(lua: Lua, (vertical, horizontal): (i32, Option<i32>)) -> LuaResult<()> {
+    send_scroll(&lua, vertical, horizontal.unwrap_or_default()).await
+}
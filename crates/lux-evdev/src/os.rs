@@ -0,0 +1,382 @@
+pub(crate) use self::imp::{Reader, VirtualDevice, list_devices};
+
+// Event types and codes, from linux/input-event-codes.h
+pub(crate) const EV_SYN: u16 = 0x00;
+pub(crate) const EV_KEY: u16 = 0x01;
+pub(crate) const EV_REL: u16 = 0x02;
+pub(crate) const SYN_REPORT: u16 = 0x00;
+pub(crate) const REL_X: u16 = 0x00;
+pub(crate) const REL_Y: u16 = 0x01;
+pub(crate) const REL_HWHEEL: u16 = 0x06;
+pub(crate) const REL_WHEEL: u16 = 0x08;
+pub(crate) const KEY_A: u16 = 30;
+pub(crate) const BTN_LEFT: u16 = 0x110;
+pub(crate) const BTN_EXTRA: u16 = 0x114;
+
+/**
+    A single input event, as read from or written to a device.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RawEvent {
+    pub(crate) kind: u16,
+    pub(crate) code: u16,
+    pub(crate) value: i32,
+}
+
+impl RawEvent {
+    pub(crate) const fn new(kind: u16, code: u16, value: i32) -> Self {
+        Self { kind, code, value }
+    }
+}
+
+/**
+    Information about an input device, and which events it is able to send.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct DeviceInfo {
+    pub(crate) path: String,
+    pub(crate) name: String,
+    pub(crate) vendor: u16,
+    pub(crate) product: u16,
+    pub(crate) event_types: u32,
+    pub(crate) keys: Vec<u8>,
+    pub(crate) relative: u16,
+}
+
+impl DeviceInfo {
+    pub(crate) fn has_event_type(&self, kind: u16) -> bool {
+        self.event_types & (1 << kind) != 0
+    }
+
+    pub(crate) fn has_key(&self, code: u16) -> bool {
+        self.keys
+            .get(usize::from(code / 8))
+            .is_some_and(|byte| byte & (1 << (code % 8)) != 0)
+    }
+
+    pub(crate) fn has_relative(&self, code: u16) -> bool {
+        self.relative & (1 << code) != 0
+    }
+
+    /**
+        Returns `true` if the device has letter keys, which
+        excludes devices such as power buttons and headsets.
+    */
+    pub(crate) fn is_keyboard(&self) -> bool {
+        self.has_event_type(EV_KEY) && self.has_key(KEY_A)
+    }
+
+    pub(crate) fn is_mouse(&self) -> bool {
+        self.has_event_type(EV_REL)
+            && self.has_relative(REL_X)
+            && self.has_relative(REL_Y)
+            && self.has_key(BTN_LEFT)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, Write};
+    use std::mem;
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::Path;
+
+    use async_io::Async;
+    use futures_lite::prelude::*;
+
+    use super::{BTN_EXTRA, BTN_LEFT, DeviceInfo, EV_KEY, EV_REL, EV_SYN, RawEvent};
+    use super::{REL_HWHEEL, REL_WHEEL, REL_X, REL_Y, SYN_REPORT};
+
+    const IOC_NONE: u64 = 0;
+    const IOC_WRITE: u64 = 1;
+    const IOC_READ: u64 = 2;
+
+    /// Builds an ioctl request number, the same way as the `_IOC` macro does
+    const fn ioc(dir: u64, kind: u8, nr: u8, size: usize) -> libc::Ioctl {
+        ((dir << 30) | ((size as u64) << 16) | ((kind as u64) << 8) | nr as u64) as libc::Ioctl
+    }
+
+    const fn eviocgname(len: usize) -> libc::Ioctl {
+        ioc(IOC_READ, b'E', 0x06, len)
+    }
+
+    const fn eviocgbit(kind: u16, len: usize) -> libc::Ioctl {
+        ioc(IOC_READ, b'E', 0x20 + kind as u8, len)
+    }
+
+    const EVIOCGID: libc::Ioctl = ioc(IOC_READ, b'E', 0x02, mem::size_of::<InputId>());
+    const EVIOCGRAB: libc::Ioctl = ioc(IOC_WRITE, b'E', 0x90, mem::size_of::<libc::c_int>());
+    const UI_DEV_CREATE: libc::Ioctl = ioc(IOC_NONE, b'U', 1, 0);
+    const UI_DEV_DESTROY: libc::Ioctl = ioc(IOC_NONE, b'U', 2, 0);
+    const UI_DEV_SETUP: libc::Ioctl = ioc(IOC_WRITE, b'U', 3, mem::size_of::<UinputSetup>());
+    const UI_SET_EVBIT: libc::Ioctl = ioc(IOC_WRITE, b'U', 100, mem::size_of::<libc::c_int>());
+    const UI_SET_KEYBIT: libc::Ioctl = ioc(IOC_WRITE, b'U', 101, mem::size_of::<libc::c_int>());
+    const UI_SET_RELBIT: libc::Ioctl = ioc(IOC_WRITE, b'U', 102, mem::size_of::<libc::c_int>());
+
+    const KEY_MAX: u16 = 0x2FF;
+    const BUS_VIRTUAL: u16 = 0x06;
+    const VIRTUAL_DEVICE_NAME: &[u8] = b"Lux Virtual Input";
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct InputId {
+        bustype: u16,
+        vendor: u16,
+        product: u16,
+        version: u16,
+    }
+
+    #[repr(C)]
+    struct UinputSetup {
+        id: InputId,
+        name: [u8; 80],
+        ff_effects_max: u32,
+    }
+
+    #[repr(C)]
+    struct InputEvent {
+        time: libc::timeval,
+        kind: u16,
+        code: u16,
+        value: i32,
+    }
+
+    const EVENT_SIZE: usize = mem::size_of::<InputEvent>();
+
+    fn ioctl<T>(file: &File, request: libc::Ioctl, arg: *mut T) -> io::Result<libc::c_int> {
+        let result = unsafe { libc::ioctl(file.as_raw_fd(), request, arg) };
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+
+    fn ioctl_int(file: &File, request: libc::Ioctl, value: libc::c_int) -> io::Result<()> {
+        let result = unsafe { libc::ioctl(file.as_raw_fd(), request, value) };
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn open_nonblocking(path: &Path, write: bool) -> io::Result<File> {
+        OpenOptions::new()
+            .read(!write)
+            .write(write)
+            .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+            .open(path)
+    }
+
+    fn query(file: &File, path: &Path) -> io::Result<DeviceInfo> {
+        let mut name = [0u8; 256];
+        let len = ioctl(file, eviocgname(name.len()), name.as_mut_ptr())?;
+        let name = &name[..(len as usize).min(name.len())];
+        let name = name.split(|&b| b == 0).next().unwrap_or_default();
+
+        let mut id = InputId::default();
+        ioctl(file, EVIOCGID, &raw mut id)?;
+
+        let mut event_types = 0u32;
+        ioctl(
+            file,
+            eviocgbit(0, mem::size_of::<u32>()),
+            &raw mut event_types,
+        )?;
+
+        let mut keys = vec![0u8; usize::from(KEY_MAX / 8 + 1)];
+        if event_types & (1 << EV_KEY) != 0 {
+            ioctl(file, eviocgbit(EV_KEY, keys.len()), keys.as_mut_ptr())?;
+        }
+
+        let mut relative = 0u16;
+        if event_types & (1 << EV_REL) != 0 {
+            ioctl(
+                file,
+                eviocgbit(EV_REL, mem::size_of::<u16>()),
+                &raw mut relative,
+            )?;
+        }
+
+        Ok(DeviceInfo {
+            path: path.to_string_lossy().into_owned(),
+            name: String::from_utf8_lossy(name).into_owned(),
+            vendor: id.vendor,
+            product: id.product,
+            event_types,
+            keys,
+            relative,
+        })
+    }
+
+    fn query_device(path: &Path) -> io::Result<DeviceInfo> {
+        let file = open_nonblocking(path, false)?;
+        query(&file, path)
+    }
+
+    /**
+        Lists the event devices in `/dev/input` that the current user can open, in order.
+    */
+    pub fn list_devices() -> io::Result<Vec<DeviceInfo>> {
+        let mut paths = fs::read_dir("/dev/input")?
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let number = name.to_str()?.strip_prefix("event")?.parse::<u32>().ok()?;
+                Some((number, entry.path()))
+            })
+            .collect::<Vec<_>>();
+        paths.sort_by_key(|(number, _)| *number);
+        Ok(paths
+            .into_iter()
+            .filter_map(|(_, path)| query_device(&path).ok())
+            .collect())
+    }
+
+    pub struct Reader {
+        file: Async<File>,
+    }
+
+    impl Reader {
+        pub fn open(path: &Path, grab: bool) -> io::Result<(Self, DeviceInfo)> {
+            let file = open_nonblocking(path, false)?;
+            let info = query(&file, path)?;
+            if grab {
+                ioctl_int(&file, EVIOCGRAB, 1)?;
+            }
+            Ok((
+                Self {
+                    file: Async::new(file)?,
+                },
+                info,
+            ))
+        }
+
+        pub async fn read(&mut self) -> io::Result<RawEvent> {
+            let mut buf = [0u8; EVENT_SIZE];
+            self.file.read_exact(&mut buf).await?;
+            let event = unsafe { buf.as_ptr().cast::<InputEvent>().read_unaligned() };
+            Ok(RawEvent::new(event.kind, event.code, event.value))
+        }
+    }
+
+    pub struct VirtualDevice {
+        file: File,
+    }
+
+    impl VirtualDevice {
+        /**
+            Creates a virtual device able to send every key, the common mouse
+            buttons, mouse movement and scrolling, through `/dev/uinput`.
+        */
+        pub fn create() -> io::Result<Self> {
+            let file = open_nonblocking(Path::new("/dev/uinput"), true)?;
+
+            ioctl_int(&file, UI_SET_EVBIT, EV_KEY.into())?;
+            // NOTE: Codes below BTN_MISC are keyboard keys, and enabling them all
+            // is what makes the device get recognized as a keyboard at all
+            for code in 1..BTN_LEFT {
+                ioctl_int(&file, UI_SET_KEYBIT, code.into())?;
+            }
+            for code in BTN_LEFT..=BTN_EXTRA {
+                ioctl_int(&file, UI_SET_KEYBIT, code.into())?;
+            }
+            ioctl_int(&file, UI_SET_EVBIT, EV_REL.into())?;
+            for code in [REL_X, REL_Y, REL_HWHEEL, REL_WHEEL] {
+                ioctl_int(&file, UI_SET_RELBIT, code.into())?;
+            }
+
+            let mut setup = UinputSetup {
+                id: InputId {
+                    bustype: BUS_VIRTUAL,
+                    vendor: 0x4C55,
+                    product: 0x0001,
+                    version: 1,
+                },
+                name: [0; 80],
+                ff_effects_max: 0,
+            };
+            setup.name[..VIRTUAL_DEVICE_NAME.len()].copy_from_slice(VIRTUAL_DEVICE_NAME);
+            ioctl(&file, UI_DEV_SETUP, &raw mut setup)?;
+            ioctl(&file, UI_DEV_CREATE, std::ptr::null_mut::<()>())?;
+
+            Ok(Self { file })
+        }
+
+        /**
+            Sends a batch of events, followed by a report that marks them as happening at once.
+        */
+        pub fn send(&self, events: &[RawEvent]) -> io::Result<()> {
+            let report = RawEvent::new(EV_SYN, SYN_REPORT, 0);
+            let mut buf = Vec::with_capacity((events.len() + 1) * EVENT_SIZE);
+            for event in events.iter().chain([&report]) {
+                let event = InputEvent {
+                    time: libc::timeval {
+                        tv_sec: 0,
+                        tv_usec: 0,
+                    },
+                    kind: event.kind,
+                    code: event.code,
+                    value: event.value,
+                };
+                let bytes = unsafe {
+                    std::slice::from_raw_parts((&raw const event).cast::<u8>(), EVENT_SIZE)
+                };
+                buf.extend_from_slice(bytes);
+            }
+            (&self.file).write_all(&buf)
+        }
+    }
+
+    impl Drop for VirtualDevice {
+        fn drop(&mut self) {
+            let _ = ioctl(&self.file, UI_DEV_DESTROY, std::ptr::null_mut::<()>());
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use std::io;
+    use std::path::Path;
+
+    use super::{DeviceInfo, RawEvent};
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "evdev input devices are only available on Linux",
+        )
+    }
+
+    pub fn list_devices() -> io::Result<Vec<DeviceInfo>> {
+        Err(unsupported())
+    }
+
+    pub enum Reader {}
+
+    impl Reader {
+        pub fn open(_path: &Path, _grab: bool) -> io::Result<(Self, DeviceInfo)> {
+            Err(unsupported())
+        }
+
+        pub async fn read(&mut self) -> io::Result<RawEvent> {
+            match *self {}
+        }
+    }
+
+    pub enum VirtualDevice {}
+
+    impl VirtualDevice {
+        pub fn create() -> io::Result<Self> {
+            Err(unsupported())
+        }
+
+        pub fn send(&self, _events: &[RawEvent]) -> io::Result<()> {
+            match *self {}
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::{sync::Arc, time::Duration};
+
+use async_io::Timer;
+use mlua::prelude::*;
+
+use crate::os::{
+    BTN_LEFT, EV_KEY, EV_REL, REL_HWHEEL, REL_WHEEL, REL_X, REL_Y, RawEvent, VirtualDevice,
+};
+
+/**
+    How long to wait after creating the virtual device before sending anything.
+
+    Until the desktop has picked up the new device, events sent through it are dropped.
+*/
+const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+/**
+    The virtual device used to synthesize input, created on first use
+    and destroyed along with the Luau VM that it was created for.
+*/
+struct VirtualInput(Arc<VirtualDevice>);
+
+async fn virtual_device(lua: &Lua) -> LuaResult<Arc<VirtualDevice>> {
+    if let Some(device) = lua.app_data_ref::<VirtualInput>() {
+        return Ok(Arc::clone(&device.0));
+    }
+    let device = VirtualDevice::create()
+        .map_err(|e| LuaError::runtime(format!("Failed to create virtual input device: {e}")))?;
+    let device = Arc::new(device);
+    lua.set_app_data(VirtualInput(Arc::clone(&device)));
+    Timer::after(SETTLE_DELAY).await;
+    Ok(device)
+}
+
+/**
+    Sends a batch of events through the virtual device, creating it first if needed.
+*/
+async fn send(lua: &Lua, events: &[RawEvent]) -> LuaResult<()> {
+    let device = virtual_device(lua).await?;
+    device
+        .send(events)
+        .map_err(|e| LuaError::runtime(format!("Failed to send input events: {e}")))
+}
+
+/**
+    Presses or releases a key, given its `Enum.KeyCode`.
+
+    # Errors
+
+    Errors if the virtual device could not be created, or the event could not be sent.
+*/
+pub async fn send_key(lua: &Lua, code: u16, down: bool) -> LuaResult<()> {
+    send(lua, &[key_event(code, down)]).await
+}
+
+/**
+    Presses or releases a mouse button, given its `Enum.MouseButton`.
+
+    # Errors
+
+    Errors if the virtual device could not be created, or the event could not be sent.
+*/
+pub async fn send_mouse_button(lua: &Lua, button: u16, down: bool) -> LuaResult<()> {
+    send(lua, &[key_event(BTN_LEFT + button, down)]).await
+}
+
+/**
+    Moves the mouse relative to where it currently is.
+
+    # Errors
+
+    Errors if the virtual device could not be created, or the events could not be sent.
+*/
+pub async fn send_motion(lua: &Lua, dx: i32, dy: i32) -> LuaResult<()> {
+    send(lua, &move_events(dx, dy)).await
+}
+
+/**
+    Scrolls the mouse wheel vertically and horizontally.
+
+    # Errors
+
+    Errors if the virtual device could not be created, or the events could not be sent.
+*/
+pub async fn send_scroll(lua: &Lua, vertical: i32, horizontal: i32) -> LuaResult<()> {
+    send(lua, &scroll_events(vertical, horizontal)).await
+}
+
+fn key_event(code: u16, down: bool) -> RawEvent {
+    RawEvent::new(EV_KEY, code, i32::from(down))
+}
+
+fn move_events(dx: i32, dy: i32) -> Vec<RawEvent> {
+    let mut events = Vec::with_capacity(2);
+    if dx != 0 {
+        events.push(RawEvent::new(EV_REL, REL_X, dx));
+    }
+    if dy != 0 {
+        events.push(RawEvent::new(EV_REL, REL_Y, dy));
+    }
+    events
+}
+
+fn scroll_events(vertical: i32, horizontal: i32) -> Vec<RawEvent> {
+    let mut events = Vec::with_capacity(2);
+    if vertical != 0 {
+        events.push(RawEvent::new(EV_REL, REL_WHEEL, vertical));
+    }
+    if horizontal != 0 {
+        events.push(RawEvent::new(EV_REL, REL_HWHEEL, horizontal));
+    }
+    events
+}
@@ -0,0 +1,68 @@
+//! Struct field access benchmark
+//!
+//! Times reading and writing struct fields through cdata, next to the same
+//! accesses on a plain Lua table, so regressions in field lookup stand out.
+//!
+//! Run with `cargo bench -p lux-ffi --bench struct_access`.
+
+use std::time::Instant;
+
+use mlua::prelude::*;
+
+const ITERATIONS: u32 = 1_000_000;
+
+const SETUP: &str = r"
+    ffi.cdef([[
+        typedef struct {
+            int id;
+            double x;
+            double y;
+            double z;
+            float weight;
+            unsigned char flags;
+        } BenchEntity;
+    ]])
+    entity = ffi.new('BenchEntity')
+    tbl = { id = 0, x = 0, y = 0, z = 0, weight = 0, flags = 0 }
+";
+
+const CASES: &[(&str, &str)] = &[
+    (
+        "table read",
+        "local t = tbl local s = 0 for i = 1, N do s += t.x + t.z end",
+    ),
+    (
+        "cdata read",
+        "local e = entity local s = 0 for i = 1, N do s += e.x + e.z end",
+    ),
+    (
+        "table write",
+        "local t = tbl for i = 1, N do t.y = i t.flags = 1 end",
+    ),
+    (
+        "cdata write",
+        "local e = entity for i = 1, N do e.y = i e.flags = 1 end",
+    ),
+];
+
+fn main() -> LuaResult<()> {
+    let lua = Lua::new();
+    lua.globals().set("ffi", lux_ffi::module(lua.clone())?)?;
+    lua.globals().set("N", ITERATIONS)?;
+    lua.load(SETUP).exec()?;
+
+    println!("{ITERATIONS} iterations of two accesses each");
+    for (name, source) in CASES {
+        let chunk = lua.load(*source).into_function()?;
+        // Warm up, so the first case does not pay for anything lazily initialized
+        chunk.call::<()>(())?;
+
+        let start = Instant::now();
+        chunk.call::<()>(())?;
+        let elapsed = start.elapsed();
+
+        let per_access = elapsed.as_secs_f64() * 1e9 / f64::from(ITERATIONS * 2);
+        println!("{name:<12} {elapsed:>10.2?} {per_access:>8.1} ns/access");
+    }
+    Ok(())
+}
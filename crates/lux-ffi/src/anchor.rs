@@ -0,0 +1,73 @@
+//! FFI String Anchors
+//!
+//! Lua strings stored in C memory, like a `const char*` field of a struct, are copied
+//! into owned null-terminated buffers. Lua may collect or move the string itself at
+//! any time, while the copy stays alive until something else is stored at the same
+//! address, or the memory it was stored in is freed.
+//!
+//! Only memory allocated by Lux, like that of `ffi.new` and arenas, frees its copies by
+//! itself. Memory allocated by C code may be freed without Lux knowing, so the copies
+//! stored in it are kept until they are released with `ffi.releaseStrings`.
+
+use std::collections::BTreeMap;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Copies of strings stored in C memory, by the address they were stored at
+static STORED: Mutex<BTreeMap<usize, Box<[u8]>>> = Mutex::new(BTreeMap::new());
+
+/// Number of strings in `STORED`, to skip locking it when freeing memory if there are none
+static STORED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn stored() -> MutexGuard<'static, BTreeMap<usize, Box<[u8]>>> {
+    STORED.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Stores a null-terminated copy of a string for the pointer at `slot`, returning the pointer to the copy
+pub(crate) fn store(slot: *mut c_void, bytes: &[u8]) -> *mut c_void {
+    let mut copy = Vec::with_capacity(bytes.len() + 1);
+    copy.extend_from_slice(bytes);
+    copy.push(0);
+    let copy = copy.into_boxed_slice();
+    let ptr = copy.as_ptr().cast_mut().cast();
+    let mut stored = stored();
+    if stored.insert(slot as usize, copy).is_none() {
+        STORED_COUNT.fetch_add(1, Ordering::Relaxed);
+    }
+    ptr
+}
+
+/// Returns the number of copies of strings stored in C memory, for finding copies that leak
+pub(crate) fn count() -> usize {
+    STORED_COUNT.load(Ordering::Relaxed)
+}
+
+/// Frees the string stored for the pointer at `slot`, if any, since something else replaced it
+pub(crate) fn release(slot: *mut c_void) {
+    if STORED_COUNT.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    if stored().remove(&(slot as usize)).is_some() {
+        STORED_COUNT.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Frees every string stored for pointers within memory that is being freed,
+/// returning the number of strings that were freed
+pub(crate) fn release_range(start: *mut c_void, size: usize) -> usize {
+    if STORED_COUNT.load(Ordering::Relaxed) == 0 {
+        return 0;
+    }
+    let start = start as usize;
+    let mut stored = stored();
+    let slots = stored
+        .range(start..start.saturating_add(size))
+        .map(|(&slot, _)| slot)
+        .collect::<Vec<_>>();
+    for slot in &slots {
+        stored.remove(slot);
+        STORED_COUNT.fetch_sub(1, Ordering::Relaxed);
+    }
+    slots.len()
+}
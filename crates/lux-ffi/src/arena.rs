@@ -0,0 +1,170 @@
+//! FFI Arenas
+//!
+//! Bump allocation for cdata, so scripts creating many small values per frame
+//! pay for one allocation per chunk instead of one per value.
+
+use crate::anchor;
+use crate::memory::{self, CBox};
+use crate::safety::Region;
+use crate::types::CType;
+use mlua::prelude::*;
+use std::alloc::{Layout, alloc, dealloc};
+use std::ffi::c_void;
+use std::ptr;
+use std::rc::Rc;
+
+/// Alignment of every chunk, values that need more are aligned within it
+const CHUNK_ALIGN: usize = 16;
+
+/// A block of memory that values are placed in, freed once nothing points into it
+struct Chunk {
+    ptr: *mut u8,
+    size: usize,
+}
+
+impl Chunk {
+    fn new(size: usize) -> LuaResult<Self> {
+        let layout = Layout::from_size_align(size, CHUNK_ALIGN)
+            .map_err(|_| LuaError::external(format!("ffi.arena: invalid size {size}")))?;
+        let ptr = unsafe { alloc(layout) };
+        if ptr.is_null() {
+            return Err(LuaError::external(format!(
+                "ffi.arena: failed to allocate {size} bytes"
+            )));
+        }
+        memory::track_allocation(size);
+        Ok(Self { ptr, size })
+    }
+}
+
+impl Drop for Chunk {
+    fn drop(&mut self) {
+        anchor::release_range(self.ptr.cast(), self.size);
+        unsafe {
+            dealloc(
+                self.ptr,
+                Layout::from_size_align_unchecked(self.size, CHUNK_ALIGN),
+            );
+        }
+        memory::track_deallocation(self.size);
+    }
+}
+
+/// Keeps a chunk alive for as long as a cdata placed in it exists
+struct ChunkGuard(#[allow(dead_code)] Rc<Chunk>);
+
+impl LuaUserData for ChunkGuard {}
+
+/// An arena that cdata is placed in (`ffi.arena`)
+pub struct Arena {
+    /// The chunk that values are currently placed in
+    chunk: Rc<Chunk>,
+    /// Offset of the first free byte in the current chunk
+    offset: usize,
+    /// Chunks that filled up since the last reset
+    full: Vec<Rc<Chunk>>,
+    /// Bytes used by values since the last reset, including padding
+    used: usize,
+}
+
+impl Arena {
+    fn new(size: usize) -> LuaResult<Self> {
+        Ok(Self {
+            chunk: Rc::new(Chunk::new(size)?),
+            offset: 0,
+            full: Vec::new(),
+            used: 0,
+        })
+    }
+
+    /// Total size of all chunks in use
+    fn capacity(&self) -> usize {
+        self.chunk.size + self.full.iter().map(|chunk| chunk.size).sum::<usize>()
+    }
+
+    /// Place a zeroed value of the given type in the arena, growing it when it is full
+    pub(crate) fn place(&mut self, lua: &Lua, ctype: CType) -> LuaResult<LuaAnyUserData> {
+        let size = ctype.size().max(1);
+        let align = ctype.align().max(1);
+
+        let start = if let Some(start) = self.fit(size, align) {
+            start
+        } else {
+            // Grow geometrically, but always by enough for this value
+            let grown = (self.chunk.size * 2).max(size + align);
+            let full = std::mem::replace(&mut self.chunk, Rc::new(Chunk::new(grown)?));
+            self.full.push(full);
+            self.offset = 0;
+            self.fit(size, align)
+                .ok_or_else(|| LuaError::external("ffi.arena: value does not fit"))?
+        };
+        self.used += start + size - self.offset;
+        self.offset = start + size;
+
+        let ptr = unsafe { self.chunk.ptr.add(start) }.cast::<c_void>();
+        unsafe { ptr::write_bytes(ptr, 0, size) };
+
+        let cbox = CBox::from_raw(ptr, ctype, false).with_region(Some(Region::new(ptr, size)));
+        let cdata = lua.create_userdata(cbox)?;
+        cdata.set_user_value(ChunkGuard(Rc::clone(&self.chunk)))?;
+        Ok(cdata)
+    }
+
+    /// Offset in the current chunk that a value would be placed at, if it fits
+    fn fit(&self, size: usize, align: usize) -> Option<usize> {
+        let base = self.chunk.ptr as usize;
+        let start = (base + self.offset).checked_next_multiple_of(align)? - base;
+        (start.checked_add(size)? <= self.chunk.size).then_some(start)
+    }
+
+    /// Start placing values from the beginning again, in a single chunk large
+    /// enough for everything that was placed before if the arena had to grow
+    fn reset(&mut self) -> LuaResult<()> {
+        // Values placed in the current chunk are overwritten from now on
+        anchor::release_range(self.chunk.ptr.cast(), self.chunk.size);
+        if !self.full.is_empty() {
+            let capacity = self.capacity();
+            self.full.clear();
+            self.chunk = Rc::new(Chunk::new(capacity)?);
+        }
+        self.offset = 0;
+        self.used = 0;
+        Ok(())
+    }
+}
+
+impl LuaUserData for Arena {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("size", |_, this| Ok(this.capacity()));
+        fields.add_field_method_get("used", |_, this| Ok(this.used));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // arena:new(type, init?) - Same as ffi.new, but placed in the arena
+        methods.add_method_mut(
+            "new",
+            |lua, this, (type_name, init): (String, Option<LuaValue>)| {
+                memory::new_cdata(lua, &type_name, init, Some(this))
+            },
+        );
+
+        // arena:reset() - Reuse the arena, values placed before must no longer be used
+        methods.add_method_mut("reset", |_, this, ()| this.reset());
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(format!(
+                "ffi.arena<{} of {} bytes used>",
+                this.used,
+                this.capacity()
+            ))
+        });
+    }
+}
+
+/// ffi.arena(size) - Create an arena, starting with a chunk of `size` bytes
+pub(crate) fn ffi_arena(_: &Lua, size: usize) -> LuaResult<Arena> {
+    if size == 0 {
+        return Err(LuaError::external("ffi.arena: size must be greater than 0"));
+    }
+    Arena::new(size)
+}
@@ -1,7 +1,8 @@
 //! FFI Batch Processing
 //!
 //! Provides batch processing of arrays through FFI functions
-//! for zero per-element Lua allocation overhead.
+//! for zero per-element Lua allocation overhead, as well as
+//! built-in vectorized kernels implemented in simd.rs.
 //!
 //! Note: This module uses raw pointers passed from Lua.
 //! The caller is responsible for ensuring buffer validity.
@@ -9,6 +10,8 @@
 #![allow(dead_code)]
 
 use crate::call::CachedFunction;
+use crate::memory::CBox;
+use crate::simd;
 use mlua::prelude::*;
 
 /// Batch call a function on an array of doubles using raw pointers
@@ -118,3 +121,208 @@ pub fn ffi_batch2(
 
     Ok(count)
 }
+
+// ============== VECTORIZED KERNELS ==============
+
+/// An array of doubles passed to a kernel
+enum Operand {
+    /// Memory accessed in place, with its length in doubles if it is known
+    Memory { ptr: *mut f64, len: Option<usize> },
+    /// A Luau buffer, which is copied in and - for outputs - written back afterwards
+    Buffer {
+        buffer: mlua::Buffer,
+        staged: Vec<f64>,
+    },
+}
+
+impl Operand {
+    fn from_value(value: LuaValue, name: &str) -> LuaResult<Self> {
+        let (ptr, len) = match value {
+            LuaValue::Buffer(buffer) => {
+                let staged = buffer
+                    .to_vec()
+                    .chunks_exact(8)
+                    .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect();
+                return Ok(Self::Buffer { buffer, staged });
+            }
+            LuaValue::UserData(ud) => {
+                let cbox = ud.borrow::<CBox>().map_err(|_| {
+                    LuaError::external(format!("batch: {name} must be a buffer, cdata or pointer"))
+                })?;
+                let ptr = cbox.as_ptr().cast::<f64>();
+                let len = cbox
+                    .region()
+                    .map(|r| (r.base + r.len).saturating_sub(ptr as usize) / size_of::<f64>());
+                (ptr, len)
+            }
+            LuaValue::LightUserData(ud) => (ud.0.cast::<f64>(), None),
+            LuaValue::Integer(i) => (i as *mut f64, None),
+            _ => {
+                return Err(LuaError::external(format!(
+                    "batch: {name} must be a buffer, cdata or pointer"
+                )));
+            }
+        };
+        if ptr.is_null() {
+            return Err(LuaError::external(format!(
+                "batch: {name} is a NULL pointer"
+            )));
+        }
+        Ok(Self::Memory { ptr, len })
+    }
+
+    fn len(&self) -> Option<usize> {
+        match self {
+            Self::Memory { len, .. } => *len,
+            Self::Buffer { staged, .. } => Some(staged.len()),
+        }
+    }
+
+    fn ptr(&mut self) -> *mut f64 {
+        match self {
+            Self::Memory { ptr, .. } => *ptr,
+            Self::Buffer { staged, .. } => staged.as_mut_ptr(),
+        }
+    }
+
+    /// Write the first `count` doubles back, if this is a staged output
+    fn write_back(&self, count: usize) {
+        if let Self::Buffer { buffer, staged } = self {
+            let bytes = staged[..count]
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<_>>();
+            buffer.write_bytes(0, &bytes);
+        }
+    }
+}
+
+/// Resolve how many elements of `width` doubles to process, checking it against every known length
+fn element_count(count: Option<usize>, operands: &[&Operand], width: usize) -> LuaResult<usize> {
+    let known = operands
+        .iter()
+        .map(|operand| operand.len())
+        .collect::<Option<Vec<_>>>();
+    let available = operands
+        .iter()
+        .filter_map(|operand| operand.len())
+        .min()
+        .map(|len| len / width);
+    match (count, available) {
+        (Some(count), Some(available)) if count > available => Err(LuaError::external(format!(
+            "batch: count {count} exceeds the {available} elements available"
+        ))),
+        (Some(count), _) => Ok(count),
+        (None, Some(available)) if known.is_some() => Ok(available),
+        (None, _) => Err(LuaError::external(
+            "batch: count is required when passing raw pointers",
+        )),
+    }
+}
+
+type BinaryKernel = unsafe fn(*const f64, *const f64, *mut f64, usize);
+
+fn batch_binary(
+    kernel: BinaryKernel,
+    (a, b, out, count): (LuaValue, LuaValue, LuaValue, Option<usize>),
+) -> LuaResult<usize> {
+    let mut a = Operand::from_value(a, "a")?;
+    let mut b = Operand::from_value(b, "b")?;
+    let mut out = Operand::from_value(out, "out")?;
+    let count = element_count(count, &[&a, &b, &out], 1)?;
+    unsafe { kernel(a.ptr(), b.ptr(), out.ptr(), count) };
+    out.write_back(count);
+    Ok(count)
+}
+
+/// batch.addf64(a, b, out, count?) - `out[i] = a[i] + b[i]`
+fn batch_addf64(_: &Lua, args: (LuaValue, LuaValue, LuaValue, Option<usize>)) -> LuaResult<usize> {
+    batch_binary(simd::add, args)
+}
+
+/// batch.mulf64(a, b, out, count?) - `out[i] = a[i] * b[i]`
+fn batch_mulf64(_: &Lua, args: (LuaValue, LuaValue, LuaValue, Option<usize>)) -> LuaResult<usize> {
+    batch_binary(simd::mul, args)
+}
+
+/// batch.fma(a, b, c, out, count?) - `out[i] = a[i] * b[i] + c[i]`
+fn batch_fma(
+    _: &Lua,
+    (a, b, c, out, count): (LuaValue, LuaValue, LuaValue, LuaValue, Option<usize>),
+) -> LuaResult<usize> {
+    let mut a = Operand::from_value(a, "a")?;
+    let mut b = Operand::from_value(b, "b")?;
+    let mut c = Operand::from_value(c, "c")?;
+    let mut out = Operand::from_value(out, "out")?;
+    let count = element_count(count, &[&a, &b, &c, &out], 1)?;
+    unsafe { simd::fma(a.ptr(), b.ptr(), c.ptr(), out.ptr(), count) };
+    out.write_back(count);
+    Ok(count)
+}
+
+/// batch.minmax(a, count?) - Smallest and largest values, nil for empty arrays
+fn batch_minmax(
+    _: &Lua,
+    (a, count): (LuaValue, Option<usize>),
+) -> LuaResult<(Option<f64>, Option<f64>)> {
+    let mut a = Operand::from_value(a, "a")?;
+    let count = element_count(count, &[&a], 1)?;
+    if count == 0 {
+        return Ok((None, None));
+    }
+    let (min, max) = unsafe { simd::minmax(a.ptr(), count) };
+    Ok((Some(min), Some(max)))
+}
+
+/// batch.sumKahan(a, count?) - Compensated sum of all values
+fn batch_sum_kahan(_: &Lua, (a, count): (LuaValue, Option<usize>)) -> LuaResult<f64> {
+    let mut a = Operand::from_value(a, "a")?;
+    let count = element_count(count, &[&a], 1)?;
+    Ok(unsafe { simd::sum_kahan(a.ptr(), count) })
+}
+
+/// batch.transform(points, cframe, out?, count?) - Transform points of three doubles, in place by default
+fn batch_transform(
+    _: &Lua,
+    (points, cframe, out, count): (LuaValue, Vec<f64>, Option<LuaValue>, Option<usize>),
+) -> LuaResult<usize> {
+    let cframe: [f64; 12] = cframe.try_into().map_err(|_| {
+        LuaError::external("batch: cframe must have 12 components, x, y, z and a 3x3 rotation")
+    })?;
+    let mut points = Operand::from_value(points, "points")?;
+    let mut out = out.map(|out| Operand::from_value(out, "out")).transpose()?;
+    let count = match &out {
+        Some(out) => element_count(count, &[&points, out], 3)?,
+        None => element_count(count, &[&points], 3)?,
+    };
+    let input = points.ptr();
+    let output = out.as_mut().unwrap_or(&mut points);
+    unsafe { simd::transform(input, &cframe, output.ptr(), count) };
+    output.write_back(count * 3);
+    Ok(count)
+}
+
+/// `ffi.batch` - Callable as `ffi.batch(func, ...)`, with vectorized kernels as fields
+pub(crate) fn create_batch_table(lua: &Lua) -> LuaResult<LuaTable> {
+    let batch = lua.create_table()?;
+    batch.set("addf64", lua.create_function(batch_addf64)?)?;
+    batch.set("mulf64", lua.create_function(batch_mulf64)?)?;
+    batch.set("fma", lua.create_function(batch_fma)?)?;
+    batch.set("minmax", lua.create_function(batch_minmax)?)?;
+    batch.set("sumKahan", lua.create_function(batch_sum_kahan)?)?;
+    batch.set("transform", lua.create_function(batch_transform)?)?;
+    batch.set("backend", simd::backend())?;
+
+    let meta = lua.create_table()?;
+    meta.set(
+        "__call",
+        lua.create_function(
+            |lua, (_, func, input, output, count): (LuaValue, LuaValue, LuaValue, LuaValue, usize)| {
+                ffi_batch(lua, (func, input, output, count))
+            },
+        )?,
+    )?;
+    batch.set_metatable(Some(meta))?;
+    Ok(batch)
+}
@@ -0,0 +1,338 @@
+//! FFI Struct Bindings
+//!
+//! Structs annotated with `// @bind` in a cdef get a Lua constructor and
+//! type-checked field access, so they can be used like regular Lua objects.
+
+use crate::callback::FfiCallback;
+use crate::debug;
+use crate::memory::{CBox, CData, c_to_lua_at_ptr, lua_to_c_at_ptr};
+use crate::registry::Registry;
+use crate::types::{CType, StructDef};
+use mlua::prelude::*;
+use std::ffi::c_void;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Returns the definition of a bound struct, for struct values and pointers to them
+pub(crate) fn bound_def(ctype: &CType) -> Option<Arc<StructDef>> {
+    let name = match ctype {
+        CType::Struct(name) | CType::Union(name) => name,
+        CType::Pointer(Some(inner)) => match inner.as_ref() {
+            CType::Struct(name) | CType::Union(name) => name,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let reg = Registry::get();
+    if reg.is_bound(name) {
+        reg.get_struct_shared(name)
+    } else {
+        None
+    }
+}
+
+/// Inclusive range of values an integer type can hold, if it fits a Lua number exactly
+fn integer_range(ctype: &CType) -> Option<(f64, f64)> {
+    match ctype {
+        CType::Char | CType::Int8 => Some((f64::from(i8::MIN), f64::from(i8::MAX))),
+        CType::UChar | CType::UInt8 => Some((0.0, f64::from(u8::MAX))),
+        CType::Short | CType::Int16 => Some((f64::from(i16::MIN), f64::from(i16::MAX))),
+        CType::UShort | CType::UInt16 | CType::WChar => Some((0.0, f64::from(u16::MAX))),
+        CType::Int | CType::Int32 | CType::Enum(_) | CType::HRESULT => {
+            Some((f64::from(i32::MIN), f64::from(i32::MAX)))
+        }
+        CType::UInt | CType::UInt32 => Some((0.0, f64::from(u32::MAX))),
+        _ => None,
+    }
+}
+
+fn is_integer_type(ctype: &CType) -> bool {
+    integer_range(ctype).is_some()
+        || matches!(
+            ctype,
+            CType::Long
+                | CType::LongLong
+                | CType::Int64
+                | CType::ULong
+                | CType::ULongLong
+                | CType::UInt64
+        )
+}
+
+/// Short C-like name for a type, used in error messages
+fn type_label(ctype: &CType) -> String {
+    match ctype {
+        CType::Struct(name) | CType::Union(name) | CType::Enum(name) => name.clone(),
+        CType::Pointer(Some(inner)) => format!("{}*", type_label(inner)),
+        CType::Pointer(None) => "void*".to_string(),
+        CType::Array(elem, count) => format!("{}[{count}]", type_label(elem)),
+        other => format!("{other:?}").to_lowercase(),
+    }
+}
+
+fn cdata_type(value: &LuaValue) -> Option<CType> {
+    match value {
+        LuaValue::UserData(ud) => ud.borrow::<CBox>().ok().map(|b| b.ctype.clone()),
+        _ => None,
+    }
+}
+
+/// Checks that a Lua value can be stored in a field of the given type
+fn check_value(ctype: &CType, value: &LuaValue) -> Result<(), String> {
+    let expected = |what: &str| {
+        Err(format!(
+            "expected {what} for '{}', got {}",
+            type_label(ctype),
+            value.type_name()
+        ))
+    };
+
+    match ctype {
+        CType::Void => Ok(()),
+        CType::Bool => match value {
+            LuaValue::Boolean(_) => Ok(()),
+            _ => expected("boolean"),
+        },
+        CType::Half | CType::Float | CType::Double | CType::LongDouble => match value {
+            LuaValue::Number(_) | LuaValue::Integer(_) => Ok(()),
+            _ => expected("number"),
+        },
+        t if is_integer_type(t) => {
+            let n = match value {
+                LuaValue::Integer(i) => *i as f64,
+                LuaValue::Number(n) if n.fract() == 0.0 => *n,
+                LuaValue::Number(_) => return expected("integer"),
+                // NOTE: C `bool` and Win32 `BOOL` are declared as int
+                LuaValue::Boolean(_) => return Ok(()),
+                _ => return expected("number"),
+            };
+            match integer_range(t) {
+                Some((min, max)) if n < min || n > max => Err(format!(
+                    "value {n} is out of range for '{}'",
+                    type_label(ctype)
+                )),
+                _ => Ok(()),
+            }
+        }
+        CType::Pointer(_) | CType::Function(_) => match value {
+            LuaValue::Nil | LuaValue::LightUserData(_) => Ok(()),
+            LuaValue::UserData(ud) if ud.is::<CBox>() || ud.is::<FfiCallback>() => Ok(()),
+            // NOTE: A Lua string may be collected while C still holds the pointer
+            LuaValue::String(_) => Err(format!(
+                "cannot store a Lua string in '{}', copy it into memory from ffi.new first",
+                type_label(ctype)
+            )),
+            _ => expected("cdata or nil"),
+        },
+        CType::Array(..) | CType::Struct(_) | CType::Union(_) | CType::GUID => match value {
+            LuaValue::Table(_) if *ctype != CType::GUID => Ok(()),
+            LuaValue::UserData(_) => match cdata_type(value) {
+                Some(t) if t == *ctype => Ok(()),
+                Some(t) => Err(format!(
+                    "expected '{}', got cdata '{}'",
+                    type_label(ctype),
+                    type_label(&t)
+                )),
+                None => expected("cdata"),
+            },
+            _ => expected("table or cdata"),
+        },
+        _ => expected("a supported value"),
+    }
+}
+
+/// Writes a Lua value into a field, with type checking
+fn write_checked(ctype: &CType, ptr: *mut c_void, value: LuaValue) -> LuaResult<()> {
+    check_value(ctype, &value).map_err(LuaError::external)?;
+
+    // Nested bound structs initialized from tables are checked field by field
+    if let (LuaValue::Table(t), Some(def)) = (&value, bound_def(ctype))
+        && !matches!(ctype, CType::Pointer(_))
+    {
+        return write_table(&def, ptr, t);
+    }
+
+    let value = match value {
+        LuaValue::Boolean(b) if is_integer_type(ctype) => LuaValue::Integer(i64::from(b)),
+        other => other,
+    };
+    unsafe { lua_to_c_at_ptr(ctype, ptr, value) }.map_err(LuaError::external)
+}
+
+/// Sets a field of a bound struct, erroring on unknown fields and mismatched types
+pub(crate) fn set_field(
+    def: &StructDef,
+    ptr: *mut c_void,
+    key: &str,
+    value: LuaValue,
+) -> LuaResult<()> {
+    let field = def
+        .field(key)
+        .ok_or_else(|| LuaError::external(format!("'{}' has no field '{key}'", def.name)))?;
+    let field_ptr = unsafe { ptr.cast::<u8>().add(field.offset).cast() };
+    write_checked(&field.ctype, field_ptr, value)
+        .map_err(|e| LuaError::external(format!("{}.{key}: {e}", def.name)))
+}
+
+/// Reads a field of a bound struct, erroring on unknown fields
+pub(crate) fn get_field(
+    lua: &Lua,
+    def: &StructDef,
+    ptr: *mut c_void,
+    key: &str,
+) -> LuaResult<LuaValue> {
+    let field = def
+        .field(key)
+        .ok_or_else(|| LuaError::external(format!("'{}' has no field '{key}'", def.name)))?;
+    unsafe { c_to_lua_at_ptr(lua, &field.ctype, ptr.cast::<u8>().add(field.offset).cast()) }
+}
+
+fn write_table(def: &StructDef, ptr: *mut c_void, table: &LuaTable) -> LuaResult<()> {
+    for pair in table.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        let LuaValue::String(key) = key else {
+            return Err(LuaError::external(format!(
+                "'{}' initializer keys must be field names, got {}",
+                def.name,
+                key.type_name()
+            )));
+        };
+        set_field(def, ptr, &key.to_str()?, value)?;
+    }
+    Ok(())
+}
+
+/// Formats a bound struct value as `Name(field = value, ...)`
+pub(crate) fn format_struct(lua: &Lua, def: &StructDef, ptr: *mut c_void) -> LuaResult<String> {
+    let mut out = format!("{}(", def.name);
+    for (i, field) in def.fields.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        let field_ptr: *mut c_void = unsafe { ptr.cast::<u8>().add(field.offset).cast() };
+        let value = match (&field.ctype, bound_def(&field.ctype)) {
+            (CType::Struct(_) | CType::Union(_), Some(inner)) => {
+                format_struct(lua, &inner, field_ptr)?
+            }
+            _ => unsafe { c_to_lua_at_ptr(lua, &field.ctype, field_ptr) }?.to_string()?,
+        };
+        let _ = write!(out, "{} = {value}", field.name);
+    }
+    out.push(')');
+    Ok(out)
+}
+
+/// Compares two bound struct values by their bytes
+pub(crate) fn struct_eq(def: &StructDef, a: *mut c_void, b: *mut c_void) -> bool {
+    let (a, b) = unsafe {
+        (
+            std::slice::from_raw_parts(a.cast::<u8>(), def.size),
+            std::slice::from_raw_parts(b.cast::<u8>(), def.size),
+        )
+    };
+    a == b
+}
+
+/// Constructor and metadata for a bound struct, returned by cdef and `ffi.bind`
+pub struct StructBinding {
+    name: String,
+}
+
+impl StructBinding {
+    fn def(&self) -> LuaResult<Arc<StructDef>> {
+        Registry::get()
+            .get_struct_shared(&self.name)
+            .ok_or_else(|| LuaError::external(format!("Unknown struct: {}", self.name)))
+    }
+
+    /// Creates a zeroed struct, initialized from a table or positional field values
+    fn construct(&self, lua: &Lua, args: LuaMultiValue) -> LuaResult<LuaAnyUserData> {
+        let def = self.def()?;
+        let mut cbox = CBox::new(CType::Struct(self.name.clone()));
+        cbox.set_origin(debug::origin(lua, || format!("{}.new()", self.name), None));
+        let ptr = cbox.ptr();
+
+        match args.len() {
+            1 if matches!(args.front(), Some(LuaValue::Table(_))) => {
+                let Some(LuaValue::Table(t)) = args.front() else {
+                    unreachable!()
+                };
+                write_table(&def, ptr, t)?;
+            }
+            n if n > def.fields.len() => {
+                return Err(LuaError::external(format!(
+                    "'{}' has {} fields, got {n} values",
+                    def.name,
+                    def.fields.len()
+                )));
+            }
+            _ => {
+                for (field, value) in def.fields.iter().zip(args) {
+                    set_field(&def, ptr, &field.name, value)?;
+                }
+            }
+        }
+
+        lua.create_userdata(cbox)
+    }
+}
+
+impl LuaUserData for StructBinding {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("name", |_, this| Ok(this.name.clone()));
+        fields.add_field_method_get("size", |_, this| Ok(this.def()?.size));
+        fields.add_field_method_get("fields", |_, this| {
+            Ok(this
+                .def()?
+                .fields
+                .iter()
+                .map(|f| f.name.clone())
+                .collect::<Vec<_>>())
+        });
+
+        // Point.new(1, 2) / Point.new({ x = 1, y = 2 })
+        fields.add_field_method_get("new", |lua, this| {
+            let binding = Self {
+                name: this.name.clone(),
+            };
+            lua.create_function(move |lua, args: LuaMultiValue| binding.construct(lua, args))
+        });
+
+        // Point.is(value) - check if a value is an instance of this struct
+        fields.add_field_method_get("is", |lua, this| {
+            let ctype = CType::Struct(this.name.clone());
+            lua.create_function(move |_, value: LuaValue| {
+                Ok(cdata_type(&value) == Some(ctype.clone()))
+            })
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::Call, |lua, this, args: LuaMultiValue| {
+            this.construct(lua, args)
+        });
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(format!("binding<{}>", this.name))
+        });
+    }
+}
+
+/// ffi.bind(name) - bind an already declared struct
+pub(crate) fn ffi_bind(_lua: &Lua, name: String) -> LuaResult<StructBinding> {
+    let mut reg = Registry::get();
+    if !reg.has_struct(&name) {
+        return Err(LuaError::external(format!("Unknown struct: {name}")));
+    }
+    reg.bind_struct(&name);
+    Ok(StructBinding { name })
+}
+
+/// Creates the table of bindings returned by cdef
+pub(crate) fn create_bindings(lua: &Lua, names: Vec<String>) -> LuaResult<LuaTable> {
+    let bindings = lua.create_table()?;
+    for name in names {
+        bindings.set(name.clone(), StructBinding { name })?;
+    }
+    Ok(bindings)
+}
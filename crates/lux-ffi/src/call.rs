@@ -2,14 +2,20 @@
 //!
 //! Provides dynamic function calling using libffi low-level API.
 
-use crate::callback::FfiCallback;
-use crate::memory::{CBox, CData};
+use crate::callback::{FfiCallback, SyncCallGuard};
+use crate::errno;
+use crate::memory::{CBox, CData, mark_const_target};
+use crate::out::{call_results, out_param_ptr};
+use crate::pool;
 use crate::types::*;
 use libffi::low::{ffi_cif, ffi_type, prep_cif, prep_cif_var};
 use libffi::raw::ffi_call;
+use libloading::Library;
+use lux_utils::profiler::{self, ProfileTrack};
 use mlua::prelude::*;
 use std::ffi::{CString, c_void};
 use std::ptr;
+use std::sync::Arc;
 
 // Platform-specific ABI
 #[cfg(all(target_os = "windows", target_arch = "x86"))]
@@ -37,6 +43,14 @@ pub struct CachedFunction {
     arg_types: Vec<*mut ffi_type>,
     /// Return type pointer
     ret_type: *mut ffi_type,
+    /// Raise a Lua error when the function returns NULL or -1
+    checked: bool,
+    /// Direct call shape, detected once instead of on every call
+    fast_path: FastPathType,
+    /// Converter for each fixed argument, so calls skip matching on its CType
+    plan: Vec<ArgConverter>,
+    /// The library the function was resolved from, kept loaded for as long as the function lives
+    library: Option<Arc<Library>>,
 }
 
 // SAFETY: CachedFunction contains raw pointers but they point to static libffi data
@@ -47,6 +61,7 @@ impl CachedFunction {
     /// Create a new cached function with pre-prepared CIF
     pub fn new(fn_ptr: usize, sig: FuncSig) -> Result<Self, String> {
         let arg_ctypes: Vec<CType> = sig.args.iter().map(|(_, t)| t.clone()).collect();
+        crate::float::check_by_value(std::iter::once(&sig.ret).chain(&arg_ctypes))?;
 
         // Build ffi_type pointers
         let mut arg_types: Vec<*mut ffi_type> = arg_ctypes.iter().map(ctype_to_ffi_type).collect();
@@ -84,19 +99,50 @@ impl CachedFunction {
             return Err("Failed to prepare CIF".to_string());
         }
 
+        let fast_path = Self::detect_fast_path(&sig);
+        let plan = arg_ctypes.iter().map(converter).collect();
+
         Ok(Self {
             fn_ptr,
             sig,
             cif: Box::new(cif),
             arg_types,
             ret_type,
+            checked: false,
+            fast_path,
+            plan,
+            library: None,
         })
     }
 
+    /// Keep the library that the function pointer was resolved from loaded
+    #[must_use]
+    pub fn with_library(mut self, library: Arc<Library>) -> Self {
+        self.library = Some(library);
+        self
+    }
+
     /// Get function name
     pub fn name(&self) -> &str {
         &self.sig.name
     }
+
+    /// Whether a return value signals failure - NULL for pointers, -1 for signed integers
+    fn is_sentinel(&self, ret: &LuaValue) -> bool {
+        match self.sig.ret {
+            CType::Pointer(_) => ret.is_nil(),
+            CType::Char
+            | CType::Short
+            | CType::Int
+            | CType::Long
+            | CType::LongLong
+            | CType::Int8
+            | CType::Int16
+            | CType::Int32
+            | CType::Int64 => ret.as_i64() == Some(-1),
+            _ => false,
+        }
+    }
 }
 
 /// Fast path signature types for direct calls without libffi
@@ -105,24 +151,42 @@ pub enum FastPathType {
     None,
     DoubleDouble,       // double func(double)
     DoubleDoubleDouble, // double func(double, double)
+    DoubleDouble3,      // double func(double, double, double)
+    DoubleDouble4,      // double func(double, double, double, double)
+    FloatFloat,         // float func(float)
+    FloatFloat2,        // float func(float, float)
     IntInt,             // int func(int)
+    IntInt2,            // int func(int, int)
+    IntInt3,            // int func(int, int, int)
+    IntPtrInt,          // int func(void*, int)
+    VoidPtr,            // void func(void*)
     VoidVoid,           // void func(void)
 }
 
 impl CachedFunction {
     /// Detect if this function can use a fast path
     pub fn detect_fast_path(sig: &FuncSig) -> FastPathType {
-        let args = &sig.args;
-        let ret = &sig.ret;
+        // Variadic functions may use a different calling convention than a direct call
+        if sig.variadic {
+            return FastPathType::None;
+        }
 
-        match (ret, args.len()) {
-            (CType::Double, 1) if matches!(args[0].1, CType::Double) => FastPathType::DoubleDouble,
-            (CType::Double, 2)
-                if matches!(args[0].1, CType::Double) && matches!(args[1].1, CType::Double) =>
-            {
-                FastPathType::DoubleDoubleDouble
-            }
-            (CType::Int, 1) if matches!(args[0].1, CType::Int) => FastPathType::IntInt,
+        let args = &sig.args;
+        let all = |ctype: &CType| args.iter().all(|(_, t)| t == ctype);
+        let is_pointer = |i: usize| matches!(args[i].1, CType::Pointer(_));
+
+        match (&sig.ret, args.len()) {
+            (CType::Double, 1) if all(&CType::Double) => FastPathType::DoubleDouble,
+            (CType::Double, 2) if all(&CType::Double) => FastPathType::DoubleDoubleDouble,
+            (CType::Double, 3) if all(&CType::Double) => FastPathType::DoubleDouble3,
+            (CType::Double, 4) if all(&CType::Double) => FastPathType::DoubleDouble4,
+            (CType::Float, 1) if all(&CType::Float) => FastPathType::FloatFloat,
+            (CType::Float, 2) if all(&CType::Float) => FastPathType::FloatFloat2,
+            (CType::Int, 1) if all(&CType::Int) => FastPathType::IntInt,
+            (CType::Int, 2) if all(&CType::Int) => FastPathType::IntInt2,
+            (CType::Int, 3) if all(&CType::Int) => FastPathType::IntInt3,
+            (CType::Int, 2) if is_pointer(0) && args[1].1 == CType::Int => FastPathType::IntPtrInt,
+            (CType::Void, 1) if is_pointer(0) => FastPathType::VoidPtr,
             (CType::Void, 0) => FastPathType::VoidVoid,
             _ => FastPathType::None,
         }
@@ -131,60 +195,35 @@ impl CachedFunction {
 
 impl LuaUserData for CachedFunction {
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // func:checked() - Copy of the function that raises errors with errno on NULL/-1 returns
+        methods.add_method("checked", |_, this, ()| {
+            let mut checked =
+                CachedFunction::new(this.fn_ptr, this.sig.clone()).map_err(LuaError::external)?;
+            checked.checked = true;
+            checked.library.clone_from(&this.library);
+            Ok(checked)
+        });
+
         // __call metamethod for direct invocation: func(args...)
         methods.add_meta_method(LuaMetaMethod::Call, |lua, this, args: LuaMultiValue| {
-            // Helper to extract f64 from either Number or Integer
-            fn get_f64(v: &LuaValue) -> Option<f64> {
-                match v {
-                    LuaValue::Number(n) => Some(*n),
-                    LuaValue::Integer(i) => Some(*i as f64),
-                    _ => None,
-                }
-            }
+            let _scope = profiler::scope(ProfileTrack::Ffi, this.name());
 
-            // Try fast path first
-            let fast_path = CachedFunction::detect_fast_path(&this.sig);
-
-            match fast_path {
-                FastPathType::DoubleDouble => {
-                    if args.len() == 1 {
-                        if let Some(arg) = args.get(0).and_then(get_f64) {
-                            let result = unsafe { invoke_double_double(this.fn_ptr, arg) };
-                            return Ok(LuaValue::Number(result));
-                        }
-                    }
-                }
-                FastPathType::DoubleDoubleDouble => {
-                    if args.len() == 2 {
-                        if let (Some(a), Some(b)) =
-                            (args.get(0).and_then(get_f64), args.get(1).and_then(get_f64))
-                        {
-                            let result = unsafe { invoke_double_double_double(this.fn_ptr, a, b) };
-                            return Ok(LuaValue::Number(result));
-                        }
-                    }
-                }
-                FastPathType::IntInt => {
-                    if args.len() == 1 {
-                        if let Some(arg) = args.get(0).and_then(|v| v.as_i32()) {
-                            let result = unsafe { invoke_int_int(this.fn_ptr, arg) };
-                            return Ok(LuaValue::Integer(result as i64));
-                        }
-                    }
-                }
-                FastPathType::VoidVoid => {
-                    if args.is_empty() {
-                        unsafe { invoke_void_void(this.fn_ptr) };
-                        return Ok(LuaValue::Nil);
-                    }
-                }
-                FastPathType::None => {}
+            // Try fast path first, checked functions always need the generic path
+            if !this.checked
+                && let Some(ret) = invoke_fast(this, &args)
+            {
+                return Ok(ret);
             }
 
             // Fallback to generic path
             unsafe { invoke_cached(lua, this, args) }
         });
 
+        // func:callAsync(args...) - Call on the call pool without blocking other coroutines
+        methods.add_async_method("callAsync", |lua, this, args: LuaMultiValue| {
+            invoke_cached_async(lua, this, args)
+        });
+
         methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
             Ok(format!("ffi.func<{}>", this.name()))
         });
@@ -194,32 +233,113 @@ impl LuaUserData for CachedFunction {
 // ============== FAST PATH FUNCTIONS ==============
 // Direct function calls without libffi overhead
 
-/// Fast path: double func(double)
-#[inline(always)]
-pub unsafe fn invoke_double_double(fn_ptr: usize, arg: f64) -> f64 {
-    let func: extern "C" fn(f64) -> f64 = std::mem::transmute(fn_ptr);
-    func(arg)
+/// Call a function directly if it has a fast path and the arguments fit it,
+/// returning `None` when the generic path is needed instead
+fn invoke_fast(cached: &CachedFunction, args: &LuaMultiValue) -> Option<LuaMultiValue> {
+    let fn_ptr = cached.fn_ptr;
+    let f = |i: usize| args.get(i).and_then(fast_f64);
+    let n = |i: usize| args.get(i).and_then(LuaValue::as_i32);
+    let p = |i: usize| args.get(i).and_then(fast_pointer);
+
+    let call = SyncCallGuard::enter();
+    let ret = unsafe {
+        match (cached.fast_path, args.len()) {
+            (FastPathType::DoubleDouble, 1) => {
+                f(0).map(|a| LuaValue::Number(fast_fn::<extern "C" fn(f64) -> f64>(fn_ptr)(a)))
+            }
+            (FastPathType::DoubleDoubleDouble, 2) => f(0).zip(f(1)).map(|(a, b)| {
+                LuaValue::Number(fast_fn::<extern "C" fn(f64, f64) -> f64>(fn_ptr)(a, b))
+            }),
+            (FastPathType::DoubleDouble3, 3) => f(0).zip(f(1)).zip(f(2)).map(|((a, b), c)| {
+                LuaValue::Number(fast_fn::<extern "C" fn(f64, f64, f64) -> f64>(fn_ptr)(
+                    a, b, c,
+                ))
+            }),
+            (FastPathType::DoubleDouble4, 4) => {
+                f(0).zip(f(1)).zip(f(2)).zip(f(3)).map(|(((a, b), c), d)| {
+                    LuaValue::Number(fast_fn::<extern "C" fn(f64, f64, f64, f64) -> f64>(fn_ptr)(
+                        a, b, c, d,
+                    ))
+                })
+            }
+            (FastPathType::FloatFloat, 1) => f(0).map(|a| {
+                LuaValue::Number(f64::from(fast_fn::<extern "C" fn(f32) -> f32>(fn_ptr)(
+                    a as f32,
+                )))
+            }),
+            (FastPathType::FloatFloat2, 2) => f(0).zip(f(1)).map(|(a, b)| {
+                LuaValue::Number(f64::from(
+                    fast_fn::<extern "C" fn(f32, f32) -> f32>(fn_ptr)(a as f32, b as f32),
+                ))
+            }),
+            (FastPathType::IntInt, 1) => n(0).map(|a| {
+                LuaValue::Integer(i64::from(fast_fn::<extern "C" fn(i32) -> i32>(fn_ptr)(a)))
+            }),
+            (FastPathType::IntInt2, 2) => n(0).zip(n(1)).map(|(a, b)| {
+                LuaValue::Integer(i64::from(
+                    fast_fn::<extern "C" fn(i32, i32) -> i32>(fn_ptr)(a, b),
+                ))
+            }),
+            (FastPathType::IntInt3, 3) => n(0).zip(n(1)).zip(n(2)).map(|((a, b), c)| {
+                LuaValue::Integer(i64::from(fast_fn::<extern "C" fn(i32, i32, i32) -> i32>(
+                    fn_ptr,
+                )(a, b, c)))
+            }),
+            (FastPathType::IntPtrInt, 2) => p(0).zip(n(1)).map(|(a, b)| {
+                LuaValue::Integer(i64::from(
+                    fast_fn::<extern "C" fn(*mut c_void, i32) -> i32>(fn_ptr)(a, b),
+                ))
+            }),
+            (FastPathType::VoidPtr, 1) => p(0).map(|a| {
+                fast_fn::<extern "C" fn(*mut c_void)>(fn_ptr)(a);
+                LuaValue::Nil
+            }),
+            (FastPathType::VoidVoid, 0) => {
+                fast_fn::<extern "C" fn()>(fn_ptr)();
+                Some(LuaValue::Nil)
+            }
+            _ => None,
+        }
+    }?;
+    errno::capture();
+    drop(call);
+
+    if cached.sig.ret == CType::Void {
+        Some(LuaMultiValue::new())
+    } else {
+        Some(LuaMultiValue::from_vec(vec![ret]))
+    }
 }
 
-/// Fast path: double func(double, double)
-#[inline(always)]
-pub unsafe fn invoke_double_double_double(fn_ptr: usize, a: f64, b: f64) -> f64 {
-    let func: extern "C" fn(f64, f64) -> f64 = std::mem::transmute(fn_ptr);
-    func(a, b)
+/// Extract a number for a floating point fast path, from either a Number or an Integer
+fn fast_f64(v: &LuaValue) -> Option<f64> {
+    match v {
+        LuaValue::Number(n) => Some(*n),
+        LuaValue::Integer(i) => Some(*i as f64),
+        _ => None,
+    }
 }
 
-/// Fast path: int func(int)
-#[inline(always)]
-pub unsafe fn invoke_int_int(fn_ptr: usize, arg: i32) -> i32 {
-    let func: extern "C" fn(i32) -> i32 = std::mem::transmute(fn_ptr);
-    func(arg)
+/// Extract a pointer for a fast path - strings, callbacks, objects and
+/// out-parameters all need the generic path, which knows how to pass them
+fn fast_pointer(v: &LuaValue) -> Option<*mut c_void> {
+    match v {
+        LuaValue::Nil => Some(ptr::null_mut()),
+        LuaValue::LightUserData(ud) => Some(ud.0),
+        LuaValue::UserData(ud) => ud.borrow::<CBox>().ok().map(|cbox| cbox.ptr().cast()),
+        _ => None,
+    }
 }
 
-/// Fast path: void func(void)
-#[inline(always)]
-pub unsafe fn invoke_void_void(fn_ptr: usize) {
-    let func: extern "C" fn() = std::mem::transmute(fn_ptr);
-    func()
+/// Reinterpret the address of a C function as a function pointer, for the fast paths
+///
+/// # Safety
+///
+/// `fn_ptr` must be the address of a function with exactly the signature of `F`,
+/// which must itself be an `extern "C" fn` type.
+unsafe fn fast_fn<F: Copy>(fn_ptr: usize) -> F {
+    debug_assert_eq!(std::mem::size_of::<F>(), std::mem::size_of::<usize>());
+    unsafe { std::mem::transmute_copy(&fn_ptr) }
 }
 
 // ============== GENERIC PATH ==============
@@ -229,46 +349,133 @@ pub unsafe fn invoke_cached(
     lua: &Lua,
     cached: &CachedFunction,
     args: LuaMultiValue,
-) -> LuaResult<LuaValue> {
-    let arg_types: Vec<CType> = cached.sig.args.iter().map(|(_, t)| t.clone()).collect();
+) -> LuaResult<LuaMultiValue> {
+    // The cached CIF only covers the fixed arguments, variadic ones need a CIF per call
+    if cached.sig.variadic && args.len() != cached.sig.args.len() {
+        let ret = invoke(lua, cached.fn_ptr, &cached.sig, args)?;
+        if cached.checked && ret.front().is_some_and(|ret| cached.is_sentinel(ret)) {
+            return Err(errno::last_call_error(cached.name()));
+        }
+        return Ok(ret);
+    }
 
-    let mut values: Vec<u64> = Vec::with_capacity(arg_types.len());
-    let mut cstrings: Vec<CString> = Vec::new();
-    let mut refs: Vec<usize> = Vec::new();
-    let mut arg_values: Vec<*mut c_void> = Vec::with_capacity(arg_types.len());
+    let mut prepared = cached.prepare_args(&args)?;
+    let call = SyncCallGuard::enter();
+    let result = prepared.call(cached.cif_ptr(), cached.fn_ptr);
+    errno::capture();
+    drop(call);
+    cached.finish(lua, &result, &args)
+}
 
-    // Validate argument count
-    let provided = args.len();
-    let expected = arg_types.len();
+/// Arguments converted for a call through a cached CIF, which must outlive the call
+struct PreparedArgs {
+    // NOTE: Slots and strings are pointed to by `arg_values`, and are only kept alive here
+    _values: Vec<ArgSlot>,
+    _cstrings: Vec<CString>,
+    arg_values: Vec<*mut c_void>,
+}
 
-    if !cached.sig.variadic && provided != expected {
-        return Err(LuaError::external(format!(
-            "Bad argument count: expected {}, got {}",
-            expected, provided
-        )));
+// SAFETY: The pointers are either owned by the prepared arguments themselves, or point
+// to memory that the caller keeps alive - and does not touch - until the call returns
+unsafe impl Send for PreparedArgs {}
+
+impl PreparedArgs {
+    /// Call a function through a prepared CIF, errno must be captured right after
+    unsafe fn call(&mut self, cif: *mut ffi_cif, fn_ptr: usize) -> ArgSlot {
+        let mut result = ArgSlot::default();
+        ffi_call(
+            cif,
+            Some(std::mem::transmute::<usize, unsafe extern "C" fn()>(fn_ptr)),
+            &mut result as *mut ArgSlot as *mut c_void,
+            self.arg_values.as_mut_ptr(),
+        );
+        result
     }
+}
 
-    // Prepare arguments
-    for (i, arg_val) in args.iter().enumerate() {
-        if i < expected {
-            let ctype = &arg_types[i];
-            let ptr = prepare_arg(arg_val, ctype, &mut values, &mut cstrings, &mut refs)?;
-            arg_values.push(ptr);
+impl CachedFunction {
+    fn cif_ptr(&self) -> *mut ffi_cif {
+        self.cif.as_ref() as *const ffi_cif as *mut ffi_cif
+    }
+
+    /// Convert arguments for the cached CIF, which only covers the fixed arguments
+    fn prepare_args(&self, args: &LuaMultiValue) -> LuaResult<PreparedArgs> {
+        let provided = args.len();
+        let expected = self.sig.args.len();
+        if provided != expected {
+            return Err(LuaError::external(format!(
+                "Bad argument count: expected {}, got {}",
+                expected, provided
+            )));
         }
+
+        let mut values = vec![ArgSlot::default(); expected];
+        let mut cstrings: Vec<CString> = Vec::new();
+        for ((arg_val, convert), slot) in args.iter().zip(&self.plan).zip(&mut values) {
+            let (arg_val, _) = untag(arg_val);
+            convert(&arg_val, slot, &mut cstrings)?;
+        }
+        // NOTE: Taken once every slot is written, the buffer never moves after this
+        let arg_values = values
+            .iter_mut()
+            .map(|slot| slot as *mut ArgSlot as *mut c_void)
+            .collect();
+
+        Ok(PreparedArgs {
+            _values: values,
+            _cstrings: cstrings,
+            arg_values,
+        })
     }
 
-    // Execute call with pre-prepared CIF
-    let mut result: u64 = 0;
+    /// Convert the result of a call, followed by any out-parameters
+    fn finish(
+        &self,
+        lua: &Lua,
+        result: &ArgSlot,
+        args: &LuaMultiValue,
+    ) -> LuaResult<LuaMultiValue> {
+        let ret = result_to_lua(lua, &self.sig.ret, result)?;
+        mark_const_target(&ret, self.sig.ret_qualifiers.is_const_target(&self.sig.ret));
+        if self.checked && self.is_sentinel(&ret) {
+            return Err(errno::last_call_error(self.name()));
+        }
+        call_results(lua, &self.sig.ret, ret, args)
+    }
+}
 
-    ffi_call(
-        cached.cif.as_ref() as *const ffi_cif as *mut ffi_cif,
-        Some(std::mem::transmute(cached.fn_ptr)),
-        &mut result as *mut u64 as *mut c_void,
-        arg_values.as_mut_ptr(),
-    );
+/**
+    Invoke a cached function on the call pool, suspending the calling coroutine until it returns.
+
+    The arguments are converted and the results read back on the Lua thread, only the C call
+    itself runs on the pool - which means that callbacks must not be called from within it.
+*/
+async fn invoke_cached_async(
+    lua: Lua,
+    cached: LuaUserDataRef<CachedFunction>,
+    args: LuaMultiValue,
+) -> LuaResult<LuaMultiValue> {
+    if cached.sig.variadic && args.len() != cached.sig.args.len() {
+        return Err(LuaError::external(
+            "callAsync does not support extra variadic arguments",
+        ));
+    }
 
-    // Convert result
-    c_to_lua(lua, &cached.sig.ret, result)
+    let _scope = profiler::scope(ProfileTrack::Ffi, cached.name());
+
+    let mut prepared = cached.prepare_args(&args)?;
+    // The function keeps its CIF alive, and is held by this call until the pool is done with it
+    let cif = cached.cif_ptr() as usize;
+    let fn_ptr = cached.fn_ptr;
+    let (result, last_errors) = pool::run(&lua, move || {
+        let result = unsafe { prepared.call(cif as *mut ffi_cif, fn_ptr) };
+        errno::capture();
+        (result, errno::last())
+    })
+    .await?;
+    errno::restore(last_errors);
+
+    cached.finish(&lua, &result, &args)
 }
 
 fn get_abi(conv: CallConv) -> libffi::raw::ffi_abi {
@@ -298,7 +505,8 @@ fn ctype_to_ffi_type(ctype: &CType) -> *mut ffi_type {
         CType::Bool | CType::Char | CType::Int8 => ptr::addr_of_mut!(libffi::low::types::sint8),
         CType::UChar | CType::UInt8 => ptr::addr_of_mut!(libffi::low::types::uint8),
         CType::Short | CType::Int16 => ptr::addr_of_mut!(libffi::low::types::sint16),
-        CType::UShort | CType::UInt16 | CType::WChar => {
+        // NOTE: libffi has no half-float type, calls reject it before getting here
+        CType::UShort | CType::UInt16 | CType::WChar | CType::Half => {
             ptr::addr_of_mut!(libffi::low::types::uint16)
         }
         CType::Int | CType::Int32 | CType::Enum(_) | CType::HRESULT => {
@@ -313,6 +521,7 @@ fn ctype_to_ffi_type(ctype: &CType) -> *mut ffi_type {
         }
         CType::Float => ptr::addr_of_mut!(libffi::low::types::float),
         CType::Double => ptr::addr_of_mut!(libffi::low::types::double),
+        CType::LongDouble => ptr::addr_of_mut!(libffi::low::types::longdouble),
         CType::Pointer(_)
         | CType::Struct(_)
         | CType::Union(_)
@@ -323,10 +532,15 @@ fn ctype_to_ffi_type(ctype: &CType) -> *mut ffi_type {
 }
 
 /// Invoke a function dynamically
-pub fn invoke(lua: &Lua, fn_ptr: usize, sig: &FuncSig, args: LuaMultiValue) -> LuaResult<LuaValue> {
+pub fn invoke(
+    lua: &Lua,
+    fn_ptr: usize,
+    sig: &FuncSig,
+    args: LuaMultiValue,
+) -> LuaResult<LuaMultiValue> {
     // Extract types from FuncSig (discarding names)
     let arg_types: Vec<CType> = sig.args.iter().map(|(_, t)| t.clone()).collect();
-    unsafe {
+    let results = unsafe {
         invoke_impl(
             lua.clone(),
             fn_ptr,
@@ -336,7 +550,11 @@ pub fn invoke(lua: &Lua, fn_ptr: usize, sig: &FuncSig, args: LuaMultiValue) -> L
             sig.conv,
             args,
         )
+    }?;
+    if let Some(ret) = results.front() {
+        mark_const_target(ret, sig.ret_qualifiers.is_const_target(&sig.ret));
     }
+    Ok(results)
 }
 
 // New helper for calling a function pointer directly with arguments
@@ -345,7 +563,7 @@ pub unsafe fn ffi_call_ptr(
     fn_ptr: usize,
     ctype: &CType,
     args: LuaMultiValue,
-) -> LuaResult<LuaValue> {
+) -> LuaResult<LuaMultiValue> {
     // Extract signature from ctype
     let sig = match ctype {
         CType::Function(sig) => sig,
@@ -367,6 +585,8 @@ pub unsafe fn ffi_call_ptr(
         _ => return Err(LuaError::external("ffi.call: not a function type")),
     };
 
+    let _scope = profiler::scope(ProfileTrack::Ffi, format_args!("<function {fn_ptr:#x}>"));
+
     // Reuse existing call logic?
     // call::ffi_call takes lua, args. It expects to find the function... wait.
     // The existing ffi_call in module exports finds the function by name from the library.
@@ -393,6 +613,84 @@ pub unsafe fn ffi_call_ptr(
     }
 }
 
+/// Storage for a single argument or return value, large enough for any type passed by value
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Default)]
+struct ArgSlot([u8; 16]);
+
+/// A value tagged with an explicit C type, created by `ffi.arg(type, value)`
+///
+/// Mostly useful for variadic arguments, which otherwise have their type inferred.
+pub struct TypedArg {
+    pub ctype: CType,
+    pub value: LuaValue,
+}
+
+impl LuaUserData for TypedArg {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(format!("ffi.arg<{:?}>", this.ctype))
+        });
+    }
+}
+
+/// ffi.arg(type, value) - Tag a value with a C type for variadic calls
+///
+/// # Errors
+///
+/// Errors if the type is unknown, or cannot be passed by value.
+pub fn ffi_arg(_: &Lua, (type_name, value): (String, LuaValue)) -> LuaResult<TypedArg> {
+    let ctype = CType::parse(&type_name)
+        .ok_or_else(|| LuaError::external(format!("Unknown type: {type_name}")))?;
+    if matches!(
+        ctype,
+        CType::Void | CType::Struct(_) | CType::Union(_) | CType::Array(_, _)
+    ) {
+        return Err(LuaError::external(format!(
+            "ffi.arg: cannot pass '{type_name}' as an argument"
+        )));
+    }
+    Ok(TypedArg { ctype, value })
+}
+
+/// Unwrap an `ffi.arg` value, returning the type it was tagged with
+fn untag(val: &LuaValue) -> (LuaValue, Option<CType>) {
+    if let LuaValue::UserData(ud) = val
+        && let Ok(arg) = ud.borrow::<TypedArg>()
+    {
+        return (arg.value.clone(), Some(arg.ctype.clone()));
+    }
+    (val.clone(), None)
+}
+
+/// Apply the C default argument promotions, which variadic arguments are subject to
+fn promote(ctype: CType) -> CType {
+    match ctype {
+        CType::Bool
+        | CType::Char
+        | CType::UChar
+        | CType::Short
+        | CType::UShort
+        | CType::Int8
+        | CType::UInt8
+        | CType::Int16
+        | CType::UInt16
+        | CType::WChar => CType::Int,
+        CType::Half | CType::Float => CType::Double,
+        other => other,
+    }
+}
+
+fn result_to_lua(lua: &Lua, ctype: &CType, result: &ArgSlot) -> LuaResult<LuaValue> {
+    if *ctype == CType::LongDouble {
+        let value = unsafe { crate::float::read_long_double(result.0.as_ptr().cast()) };
+        return Ok(LuaValue::Number(value));
+    }
+    let mut raw = [0; 8];
+    raw.copy_from_slice(&result.0[..8]);
+    c_to_lua(lua, ctype, u64::from_ne_bytes(raw))
+}
+
 unsafe fn invoke_impl(
     lua: Lua,
     fn_ptr: usize,
@@ -401,10 +699,13 @@ unsafe fn invoke_impl(
     variadic: bool,
     conv: crate::types::CallConv,
     args: LuaMultiValue,
-) -> LuaResult<LuaValue> {
-    let mut values: Vec<u64> = Vec::new();
+) -> LuaResult<LuaMultiValue> {
+    crate::float::check_by_value(std::iter::once(ret_type).chain(arg_types))
+        .map_err(LuaError::external)?;
+
+    // NOTE: Slots are pointed to while more are added, so they must never reallocate
+    let mut values: Vec<ArgSlot> = Vec::with_capacity(args.len());
     let mut cstrings: Vec<CString> = Vec::new();
-    let mut refs: Vec<usize> = Vec::new();
     let mut ffi_arg_types: Vec<*mut ffi_type> = Vec::new();
     let mut arg_values: Vec<*mut c_void> = Vec::new();
 
@@ -428,39 +729,33 @@ unsafe fn invoke_impl(
 
     // Process fixed arguments
     for (i, arg_val) in args.iter().enumerate() {
-        if i < expected {
-            let ctype = &arg_types[i];
-            ffi_arg_types.push(ctype_to_ffi_type(ctype));
-            let ptr = prepare_arg(arg_val, ctype, &mut values, &mut cstrings, &mut refs)?;
-            arg_values.push(ptr);
+        let (arg_val, tagged) = untag(arg_val);
+        let ctype = if i < expected {
+            arg_types[i].clone()
+        } else if let Some(ctype) = tagged {
+            // Variadic arguments tagged with ffi.arg
+            promote(ctype)
         } else {
-            // Variadic arguments
-            // We need to guess the type or convert based on Lua type?
-            // LuaJIT allows passing cdata types to varargs.
-            // For now, let's assume primitives like int/double based on Lua value.
-            // Simplification: treat integer as Int, number as Double, string as Char*.
-            // This is tricky without type info.
-            // A better approach for variadic:
-            // Let user cast? or just support basic types.
-
-            // Infer type from value
-            let ctype = match arg_val {
-                LuaValue::Integer(_) => CType::Int, // or Long? Default C behavior often Promotes to Int/Double
+            // Variadic arguments without a type are inferred from the Lua value,
+            // use ffi.arg for anything else (int64_t, unsigned, ...)
+            match arg_val {
+                LuaValue::Integer(i) if i32::try_from(i).is_ok() => CType::Int,
+                LuaValue::Integer(_) => CType::Long,
                 LuaValue::Number(_) => CType::Double,
                 LuaValue::String(_) => CType::Pointer(Some(Box::new(CType::Char))),
                 LuaValue::Boolean(_) => CType::Int,
                 LuaValue::LightUserData(_) | LuaValue::UserData(_) => CType::Pointer(None),
                 _ => CType::Void, // Skip or error
-            };
-
-            if ctype == CType::Void {
-                continue; // Skip nil/tables
             }
+        };
 
-            ffi_arg_types.push(ctype_to_ffi_type(&ctype));
-            let ptr = prepare_arg(arg_val, &ctype, &mut values, &mut cstrings, &mut refs)?;
-            arg_values.push(ptr);
+        if ctype == CType::Void {
+            continue; // Skip nil/tables
         }
+
+        ffi_arg_types.push(ctype_to_ffi_type(&ctype));
+        let ptr = prepare_arg(&arg_val, &ctype, &mut values, &mut cstrings)?;
+        arg_values.push(ptr);
     }
 
     // Call
@@ -490,128 +785,195 @@ unsafe fn invoke_impl(
         }
 
         // Return value storage
-        let mut result: u64 = 0;
+        let mut result = ArgSlot::default();
 
+        let call = SyncCallGuard::enter();
         ffi_call(
             &mut cif,
-            Some(std::mem::transmute(fn_ptr)),
-            &mut result as *mut u64 as *mut c_void,
+            Some(std::mem::transmute::<usize, unsafe extern "C" fn()>(fn_ptr)),
+            &mut result as *mut ArgSlot as *mut c_void,
             arg_values.as_mut_ptr(),
         );
-        // Convert result to Lua
-        c_to_lua(&lua, ret_type, result)
+        errno::capture();
+        drop(call);
+
+        // Convert result to Lua, followed by any out-parameters
+        let ret = result_to_lua(&lua, ret_type, &result)?;
+        call_results(&lua, ret_type, ret, &args)
     }
 }
 
 fn prepare_arg(
     val: &LuaValue,
     ctype: &CType,
-    values: &mut Vec<u64>,
+    values: &mut Vec<ArgSlot>,
     cstrings: &mut Vec<CString>,
-    _refs: &mut Vec<usize>,
 ) -> LuaResult<*mut c_void> {
-    let slot_idx = values.len();
-    values.push(0); // Reserve slot
-    let slot_ptr = &mut values[slot_idx] as *mut u64;
+    values.push(ArgSlot::default());
+    let slot = values.last_mut().expect("slot was just pushed");
+    converter(ctype)(val, slot, cstrings)?;
+    Ok(slot as *mut ArgSlot as *mut c_void)
+}
+
+// ============== ARGUMENT CONVERSION ==============
+// Picked once per argument type, so cached functions skip matching on it for every call
+
+/// Writes a Lua value into an argument slot, keeping any strings it needs alive in `cstrings`
+type ArgConverter = fn(&LuaValue, &mut ArgSlot, &mut Vec<CString>) -> LuaResult<()>;
+
+/// Pick the converter for an argument type
+fn converter(ctype: &CType) -> ArgConverter {
+    match ctype {
+        CType::Int | CType::Enum(_) | CType::HRESULT => convert_int,
+        CType::UInt => convert_uint,
+        CType::Long => convert_long,
+        CType::ULong => convert_ulong,
+        CType::Short => convert_short,
+        CType::UShort => convert_ushort,
+        CType::Char => convert_char,
+        CType::UChar => convert_uchar,
+        CType::Bool => convert_bool,
+        CType::Float => convert_float,
+        CType::Double => convert_double,
+        CType::LongDouble => convert_long_double,
+        CType::Pointer(Some(inner)) if **inner == CType::Char => convert_string_pointer,
+        CType::Pointer(_) => convert_pointer,
+        // Structs/Arrays by value not fully supported here, passing as pointer/sized?
+        // Fallback to usize 0
+        _ => convert_zero,
+    }
+}
+
+/// Write a value to the start of a slot
+fn store<T: Copy>(slot: &mut ArgSlot, value: T) {
+    const { assert!(size_of::<T>() <= size_of::<ArgSlot>()) };
+    // SAFETY: The slot is large enough and aligned for any argument type, as checked above
+    unsafe { *(slot as *mut ArgSlot as *mut T) = value };
+}
+
+fn convert_int(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, val.as_i32().unwrap_or(0));
+    Ok(())
+}
+
+fn convert_uint(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, val.as_u32().unwrap_or(0));
+    Ok(())
+}
+
+fn convert_long(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    let v = match val {
+        LuaValue::LightUserData(ud) => ud.0 as i64,
+        LuaValue::UserData(_) => crate::memory::get_ptr_from_value(val)
+            .map(|p| p as i64)
+            .unwrap_or(0),
+        _ => val.as_i64().unwrap_or(0),
+    };
+    store(slot, v);
+    Ok(())
+}
+
+fn convert_ulong(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    let v = match val {
+        LuaValue::LightUserData(ud) => ud.0 as u64,
+        LuaValue::UserData(_) => crate::memory::get_ptr_from_value(val)
+            .map(|p| p as u64)
+            .unwrap_or(0),
+        _ => val.as_u64().unwrap_or(0),
+    };
+    store(slot, v);
+    Ok(())
+}
+
+fn convert_short(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, val.as_i32().unwrap_or(0) as i16);
+    Ok(())
+}
+
+fn convert_ushort(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, val.as_u32().unwrap_or(0) as u16);
+    Ok(())
+}
+
+fn convert_char(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, val.as_i32().unwrap_or(0) as i8);
+    Ok(())
+}
+
+fn convert_uchar(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, val.as_u32().unwrap_or(0) as u8);
+    Ok(())
+}
+
+fn convert_bool(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, i8::from(matches!(val, LuaValue::Boolean(true))));
+    Ok(())
+}
+
+fn convert_float(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, crate::memory::number_value(val) as f32);
+    Ok(())
+}
+
+fn convert_double(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, crate::memory::number_value(val));
+    Ok(())
+}
 
+fn convert_long_double(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
     unsafe {
-        match ctype {
-            CType::Int | CType::Enum(_) => {
-                *(slot_ptr as *mut i32) = val.as_i32().unwrap_or(0);
-            }
-            CType::UInt => {
-                *(slot_ptr as *mut u32) = val.as_u32().unwrap_or(0);
-            }
-            CType::Long => {
-                let v = if let LuaValue::LightUserData(ud) = val {
-                    ud.0 as i64
-                } else if let LuaValue::UserData(_ud) = val {
-                    crate::memory::get_ptr_from_value(val)
-                        .map(|p| p as i64)
-                        .unwrap_or(0)
-                } else {
-                    val.as_i64().unwrap_or(0)
-                };
-                *(slot_ptr as *mut i64) = v;
-            }
-            CType::ULong => {
-                let v = if let LuaValue::LightUserData(ud) = val {
-                    ud.0 as u64
-                } else if let LuaValue::UserData(_ud) = val {
-                    crate::memory::get_ptr_from_value(val)
-                        .map(|p| p as u64)
-                        .unwrap_or(0)
-                } else {
-                    val.as_u64().unwrap_or(0)
-                };
-                *(slot_ptr as *mut u64) = v;
-            }
-            CType::Short => {
-                *(slot_ptr as *mut i16) = val.as_i32().unwrap_or(0) as i16;
-            }
-            CType::UShort => {
-                *(slot_ptr as *mut u16) = val.as_u32().unwrap_or(0) as u16;
-            }
-            CType::Char => {
-                *(slot_ptr as *mut i8) = val.as_i32().unwrap_or(0) as i8;
-            }
-            CType::UChar => {
-                *(slot_ptr as *mut u8) = val.as_u32().unwrap_or(0) as u8;
-            }
-            CType::Bool => {
-                *(slot_ptr as *mut i8) = if matches!(val, LuaValue::Boolean(true)) {
-                    1
-                } else {
-                    0
-                };
-            }
-            CType::Float => {
-                *(slot_ptr as *mut f32) = val.as_f64().unwrap_or(0.0) as f32;
-            }
-            CType::Double => {
-                *(slot_ptr as *mut f64) = val.as_f64().unwrap_or(0.0);
-            }
-            CType::Pointer(inner) => {
-                // Handle strings specifically if inner is char
-                let is_string = matches!(inner.as_ref(), Some(b) if **b == CType::Char);
-
-                let ptr_val = if is_string {
-                    if let LuaValue::String(s) = val {
-                        let cstr = CString::new(s.as_bytes().to_vec())
-                            .map_err(|_| LuaError::external("Null byte in string"))?;
-                        let p = cstr.as_ptr() as usize;
-                        cstrings.push(cstr);
-                        p
-                    } else {
-                        0
-                    }
-                } else {
-                    match val {
-                        LuaValue::LightUserData(ud) => ud.0 as usize,
-                        LuaValue::Integer(i) => *i as usize,
-                        LuaValue::UserData(ud) => {
-                            if let Ok(cbox) = ud.borrow::<CBox>() {
-                                cbox.ptr() as usize
-                            } else if let Ok(cb) = ud.borrow::<FfiCallback>() {
-                                cb.as_ptr() as usize
-                            } else {
-                                0
-                            }
-                        }
-                        _ => 0,
-                    }
-                };
-                *(slot_ptr as *mut usize) = ptr_val;
-            }
-            _ => {
-                // Structs/Arrays by value not fully supported here, passing as pointer/sized?
-                // Fallback to usize 0
-                *(slot_ptr as *mut usize) = 0;
+        crate::float::write_long_double(
+            (slot as *mut ArgSlot).cast(),
+            crate::memory::number_value(val),
+        );
+    }
+    Ok(())
+}
+
+fn convert_string_pointer(
+    val: &LuaValue,
+    slot: &mut ArgSlot,
+    cstrings: &mut Vec<CString>,
+) -> LuaResult<()> {
+    let LuaValue::String(s) = val else {
+        // Buffers and other cdata can be passed as char* too
+        return convert_pointer(val, slot, cstrings);
+    };
+    let cstr = CString::new(s.as_bytes().to_vec())
+        .map_err(|_| LuaError::external("Null byte in string"))?;
+    store(slot, cstr.as_ptr() as usize);
+    cstrings.push(cstr);
+    Ok(())
+}
+
+fn convert_pointer(val: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    let ptr_val = match val {
+        LuaValue::LightUserData(ud) => ud.0 as usize,
+        LuaValue::Integer(i) => *i as usize,
+        LuaValue::UserData(ud) => {
+            if let Ok(cbox) = ud.borrow::<CBox>() {
+                cbox.ptr() as usize
+            } else if let Ok(cb) = ud.borrow::<FfiCallback>() {
+                cb.as_ptr() as usize
+            } else if let Ok(obj) = ud.borrow::<crate::com::ComObject>() {
+                obj.as_ptr()? as usize
+            } else if let Ok(obj) = ud.borrow::<crate::objc::ObjcObject>() {
+                obj.as_ptr() as usize
+            } else if let Some(out) = out_param_ptr(val) {
+                out as usize
+            } else {
+                0
             }
         }
-    }
+        _ => 0,
+    };
+    store(slot, ptr_val);
+    Ok(())
+}
 
-    Ok(slot_ptr as *mut c_void)
+fn convert_zero(_: &LuaValue, slot: &mut ArgSlot, _: &mut Vec<CString>) -> LuaResult<()> {
+    store(slot, 0usize);
+    Ok(())
 }
 
 fn c_to_lua(lua: &Lua, ctype: &CType, raw: u64) -> LuaResult<LuaValue> {
@@ -623,7 +985,7 @@ fn c_to_lua(lua: &Lua, ctype: &CType, raw: u64) -> LuaResult<LuaValue> {
         CType::UChar => Ok(LuaValue::Integer(raw as u8 as i64)),
         CType::Short => Ok(LuaValue::Integer(raw as i16 as i64)),
         CType::UShort => Ok(LuaValue::Integer(raw as u16 as i64)),
-        CType::Int | CType::Enum(_) => Ok(LuaValue::Integer(raw as i32 as i64)),
+        CType::Int | CType::Enum(_) | CType::HRESULT => Ok(LuaValue::Integer(raw as i32 as i64)),
         CType::UInt => Ok(LuaValue::Integer(raw as u32 as i64)),
         CType::Long => Ok(LuaValue::Integer(raw as i64)),
         CType::ULong => Ok(LuaValue::Integer(raw as i64)),
@@ -652,35 +1014,3 @@ fn c_to_lua(lua: &Lua, ctype: &CType, raw: u64) -> LuaResult<LuaValue> {
         _ => Ok(LuaValue::Integer(raw as i64)),
     }
 }
-
-/// Get/set C errno
-pub fn ffi_errno(new_errno: Option<i32>) -> LuaResult<i32> {
-    #[cfg(windows)]
-    {
-        unsafe extern "C" {
-            fn _errno() -> *mut i32;
-        }
-        unsafe {
-            let ptr = _errno();
-            let old = *ptr;
-            if let Some(new) = new_errno {
-                *ptr = new;
-            }
-            Ok(old)
-        }
-    }
-    #[cfg(not(windows))]
-    {
-        unsafe extern "C" {
-            fn __errno_location() -> *mut i32;
-        }
-        unsafe {
-            let ptr = __errno_location();
-            let old = *ptr;
-            if let Some(new) = new_errno {
-                *ptr = new;
-            }
-            Ok(old)
-        }
-    }
-}
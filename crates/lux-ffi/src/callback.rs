@@ -1,7 +1,12 @@
 //! FFI Callbacks with libffi closures
 //!
 //! Uses libffi::low API for dynamic closures with proper type conversions.
+//!
+//! Callbacks may only run Lua code on the thread that created them. Calls from
+//! other threads are either refused, or sent to the scheduler through the queue
+//! in callback_queue.rs, depending on the [`CallbackMode`] of the callback.
 
+use crate::callback_queue::{CallbackQueue, Invocation, InvocationSender};
 use crate::memory::CBox;
 use crate::types::{CType, CallConv};
 use libffi::low::{
@@ -9,8 +14,16 @@ use libffi::low::{
     prep_closure_mut,
 };
 use mlua::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::c_void;
+use std::mem::ManuallyDrop;
 use std::ptr::{self, addr_of_mut};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::{self, ThreadId};
+use std::time::Duration;
 
 // ABI Handling
 #[cfg(not(target_os = "windows"))]
@@ -50,7 +63,10 @@ fn ctype_to_ffi_type(ctype: &CType) -> *mut ffi_type {
         CType::Bool | CType::Char | CType::Int8 => addr_of_mut!(libffi::low::types::sint8),
         CType::UChar | CType::UInt8 => addr_of_mut!(libffi::low::types::uint8),
         CType::Short | CType::Int16 => addr_of_mut!(libffi::low::types::sint16),
-        CType::UShort | CType::UInt16 | CType::WChar => addr_of_mut!(libffi::low::types::uint16),
+        // NOTE: libffi has no half-float type, callbacks reject it before getting here
+        CType::UShort | CType::UInt16 | CType::WChar | CType::Half => {
+            addr_of_mut!(libffi::low::types::uint16)
+        }
         CType::Int | CType::Int32 | CType::Enum(_) | CType::HRESULT => {
             addr_of_mut!(libffi::low::types::sint32)
         }
@@ -59,6 +75,7 @@ fn ctype_to_ffi_type(ctype: &CType) -> *mut ffi_type {
         CType::ULong | CType::ULongLong | CType::UInt64 => addr_of_mut!(libffi::low::types::uint64),
         CType::Float => addr_of_mut!(libffi::low::types::float),
         CType::Double => addr_of_mut!(libffi::low::types::double),
+        CType::LongDouble => addr_of_mut!(libffi::low::types::longdouble),
         // All pointer types use pointer
         CType::Pointer(_)
         | CType::Struct(_)
@@ -73,12 +90,137 @@ fn ctype_to_ffi_type(ctype: &CType) -> *mut ffi_type {
 // Callback Data - Stored with each callback
 // ============================================================================
 
+/// Unique id for each callback, used to find it when called from another thread
+static NEXT_CALLBACK_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Number of callbacks that have been created, and not yet freed
+static LIVE_CALLBACKS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the number of callbacks that are alive, for finding callbacks that leak
+pub fn callback_count() -> usize {
+    LIVE_CALLBACKS.load(Ordering::SeqCst)
+}
+
+/// How a callback behaves when C code calls it from a thread other than the one that created it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CallbackMode {
+    /// Calls from other threads are refused, returning a zeroed value
+    #[default]
+    Direct,
+    /// Calls from other threads run on the scheduler, blocking the calling thread until they finish
+    ///
+    /// The scheduler can not run anything while the creating thread is blocked in a synchronous
+    /// C call, so a call made while that C call waits for the calling thread would deadlock.
+    /// Calls that have not run after a second, while the creating thread is still in the
+    /// same C call, report an error and return a zeroed value instead.
+    Blocking,
+    /// Calls from other threads are queued to run on the scheduler, returning a zeroed value immediately
+    Queued,
+}
+
+impl CallbackMode {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::Blocking => "blocking",
+            Self::Queued => "queued",
+        }
+    }
+}
+
+/// How long a blocking call waits while the creating thread is stuck in the same C call
+const DEADLOCK_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The synchronous C calls a thread is in, which calls to blocking callbacks can not run during
+#[derive(Debug, Default)]
+struct SyncCalls {
+    /// How many synchronous C calls the thread is inside of
+    depth: AtomicUsize,
+    /// How many synchronous C calls the thread has entered in total, to tell them apart
+    entered: AtomicU64,
+}
+
+impl SyncCalls {
+    /// The synchronous C call the thread is currently in, if any
+    fn current(&self) -> Option<u64> {
+        (self.depth.load(Ordering::SeqCst) > 0).then(|| self.entered.load(Ordering::SeqCst))
+    }
+}
+
+thread_local! {
+    static SYNC_CALLS: Arc<SyncCalls> = Arc::default();
+}
+
+/// Marks the current thread as blocked in a synchronous C call, until dropped
+pub(crate) struct SyncCallGuard(());
+
+impl SyncCallGuard {
+    pub(crate) fn enter() -> Self {
+        SYNC_CALLS.with(|calls| {
+            calls.entered.fetch_add(1, Ordering::SeqCst);
+            calls.depth.fetch_add(1, Ordering::SeqCst);
+        });
+        Self(())
+    }
+}
+
+impl Drop for SyncCallGuard {
+    fn drop(&mut self) {
+        SYNC_CALLS.with(|calls| calls.depth.fetch_sub(1, Ordering::SeqCst));
+    }
+}
+
+/// Options for `ffi.callback`, either nil or a table such as `{ mode = "blocking" }`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallbackOptions {
+    pub mode: CallbackMode,
+}
+
+impl FromLua for CallbackOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let options = match value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::Table(t) => t,
+            other => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: other.type_name(),
+                    to: String::from("CallbackOptions"),
+                    message: Some(String::from("expected a table of callback options")),
+                });
+            }
+        };
+        let mode = match options.get::<Option<String>>("mode")?.as_deref() {
+            None | Some("direct") => CallbackMode::Direct,
+            Some("blocking") => CallbackMode::Blocking,
+            Some("queued") => CallbackMode::Queued,
+            Some(other) => {
+                return Err(LuaError::runtime(format!(
+                    "Invalid callback mode '{other}', expected 'direct', 'blocking' or 'queued'"
+                )));
+            }
+        };
+        Ok(Self { mode })
+    }
+}
+
 /// Userdata stored with each callback
 struct CallbackData {
-    func_key: LuaRegistryKey,
-    lua: Lua,
+    /// Cleared once the callback is freed, releasing the Lua function
+    func_key: RefCell<Option<LuaRegistryKey>>,
+    /// Set once the callback is freed, after which calls return a zeroed value
+    freed: AtomicBool,
+    /// Weak, so that callbacks kept until they are freed do not keep the Lua state open
+    lua: WeakLua,
     arg_types: Vec<CType>,
     ret_type: CType,
+    id: u64,
+    mode: CallbackMode,
+    /// The thread that created the callback, the only one allowed to call into Lua
+    owner: ThreadId,
+    /// The synchronous C calls of the thread that created the callback
+    owner_calls: Arc<SyncCalls>,
+    /// Sends calls from other threads to the scheduler, unless the mode is direct
+    queue: Option<InvocationSender>,
 }
 
 // ============================================================================
@@ -93,42 +235,206 @@ unsafe extern "C" fn callback_trampoline(
     userdata: &mut c_void,
 ) {
     // All operations here are inside an unsafe block since this is an unsafe fn
-    let data = unsafe { &*(userdata as *const c_void as *const CallbackData) };
-    let lua = &data.lua;
+    let data = unsafe { &*ptr::from_mut(userdata).cast::<CallbackData>() };
+    let ret_ptr = ptr::from_mut(result);
 
-    // println!("[FFI] In trampoline, calling Lua...");
+    // NOTE: The Lua state must never be touched from another thread, not even to look up
+    // the function, so calls from other threads only read the arguments and hand them off
+    if thread::current().id() != data.owner {
+        unsafe { call_from_other_thread(data, args, ret_ptr) };
+        return;
+    }
 
-    // Get Lua function from registry
-    let func: LuaFunction = match lua.registry_value(&data.func_key) {
-        Ok(f) => f,
-        Err(e) => {
+    // C code may keep calling a callback after it was freed, the closure
+    // stays allocated until it is garbage collected so that this is safe
+    let lua = match data.lua.try_upgrade() {
+        Some(lua) if !data.freed.load(Ordering::SeqCst) => lua,
+        _ => {
+            unsafe { lua_to_c_result(&data.ret_type, &CValue::Nil, ret_ptr) };
+            return;
+        }
+    };
+    let lua = &lua;
+
+    // Get Lua function from registry - the borrow must end here, since the function may free itself
+    let func = data
+        .func_key
+        .borrow()
+        .as_ref()
+        .map(|key| lua.registry_value::<LuaFunction>(key));
+    let func = match func {
+        Some(Ok(f)) => f,
+        Some(Err(e)) => {
             eprintln!("[FFI CALLBACK ERROR] Failed to get Lua function: {}", e);
+            unsafe { lua_to_c_result(&data.ret_type, &CValue::Nil, ret_ptr) };
+            return;
+        }
+        None => {
+            unsafe { lua_to_c_result(&data.ret_type, &CValue::Nil, ret_ptr) };
             return;
         }
     };
 
     // Convert C args to Lua values
-    let mut lua_args = Vec::with_capacity(data.arg_types.len());
-    for (i, arg_type) in data.arg_types.iter().enumerate() {
-        let arg_ptr = unsafe { *args.add(i) };
-        let lua_val = unsafe { c_arg_to_lua(lua, arg_type, arg_ptr) };
-        lua_args.push(lua_val);
-    }
+    let c_args = unsafe { read_c_args(&data.arg_types, args) };
+    let lua_args = c_args.into_iter().map(|arg| arg.into_lua(lua));
 
     // Call Lua function
     let call_result = func.call::<LuaMultiValue>(LuaMultiValue::from_iter(lua_args));
 
     // Convert result back to C
-    let ret_ptr = result as *mut c_void;
-    match call_result {
-        Ok(values) => {
-            let first = values.into_iter().next().unwrap_or(LuaValue::Nil);
-            unsafe { lua_to_c_result(&data.ret_type, &first, ret_ptr) };
+    unsafe {
+        lua_to_c_result(
+            &data.ret_type,
+            &CValue::from_call_result(call_result),
+            ret_ptr,
+        )
+    };
+}
+
+/**
+    Waits for a blocking call to run on the scheduler, giving up if the thread that created
+    the callback stays blocked in the same C call, since that call may be waiting for this one.
+*/
+fn wait_for_reply(data: &CallbackData, rx: &mpsc::Receiver<CValue>) -> CValue {
+    let mut blocked_in = data.owner_calls.current();
+    loop {
+        match rx.recv_timeout(DEADLOCK_TIMEOUT) {
+            Ok(value) => return value,
+            // The callback was dropped, or the scheduler stopped, before the call ran
+            Err(RecvTimeoutError::Disconnected) => return CValue::Nil,
+            Err(RecvTimeoutError::Timeout) => {
+                let current = data.owner_calls.current();
+                if current.is_some() && current == blocked_in {
+                    eprintln!(
+                        "[FFI CALLBACK ERROR] Blocking callback was called while the thread that created it \
+                        is blocked in a C call, use callAsync to call C functions that wait for other threads"
+                    );
+                    return CValue::Nil;
+                }
+                blocked_in = current;
+            }
+        }
+    }
+}
+
+/// Handles a call from a thread other than the one that created the callback
+unsafe fn call_from_other_thread(
+    data: &CallbackData,
+    args: *const *const c_void,
+    ret_ptr: *mut c_void,
+) {
+    if data.freed.load(Ordering::SeqCst) {
+        lua_to_c_result(&data.ret_type, &CValue::Nil, ret_ptr);
+        return;
+    }
+
+    let Some(queue) = &data.queue else {
+        eprintln!(
+            "[FFI CALLBACK ERROR] Callback was called from another thread, \
+            create it with {{ mode = \"blocking\" }} or {{ mode = \"queued\" }} to allow this"
+        );
+        lua_to_c_result(&data.ret_type, &CValue::Nil, ret_ptr);
+        return;
+    };
+
+    let c_args = read_c_args(&data.arg_types, args);
+    let value = match data.mode {
+        CallbackMode::Blocking => {
+            let (tx, rx) = mpsc::sync_channel(1);
+            let invocation = Invocation::new(data.id, c_args, Some(tx));
+            if queue.try_send(Some(invocation)).is_ok() {
+                wait_for_reply(data, &rx)
+            } else {
+                CValue::Nil
+            }
+        }
+        CallbackMode::Queued | CallbackMode::Direct => {
+            let _ = queue.try_send(Some(Invocation::new(data.id, c_args, None)));
+            CValue::Nil
         }
-        Err(e) => {
-            eprintln!("[FFI CALLBACK ERROR] Lua function error: {}", e);
-            // Set default return value on error
-            unsafe { lua_to_c_result(&data.ret_type, &LuaValue::Integer(0), ret_ptr) };
+    };
+    lua_to_c_result(&data.ret_type, &value, ret_ptr);
+}
+
+/// Copies the arguments of a call out of C memory, so that they can outlive it
+unsafe fn read_c_args(arg_types: &[CType], args: *const *const c_void) -> Vec<CValue> {
+    arg_types
+        .iter()
+        .enumerate()
+        .map(|(i, arg_type)| c_arg_to_value(arg_type, *args.add(i)))
+        .collect()
+}
+
+// ============================================================================
+// CValue - C values that can be sent between threads
+// ============================================================================
+
+/// An argument or return value of a callback, copied out of (or to be written into)
+/// C memory, which can be sent between threads unlike [`LuaValue`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CValue {
+    Nil,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    /// A `char*` argument, copied into a Lua string
+    String(String),
+    Pointer(usize),
+    /// Any other Lua value, which is truthy but converts to zero or NULL
+    Other,
+}
+
+impl CValue {
+    pub(crate) fn into_lua(self, lua: &Lua) -> LuaValue {
+        match self {
+            Self::Nil | Self::Other => LuaValue::Nil,
+            Self::Boolean(b) => LuaValue::Boolean(b),
+            Self::Integer(i) => LuaValue::Integer(i),
+            Self::Number(n) => LuaValue::Number(n),
+            Self::String(s) => lua
+                .create_string(s)
+                .map(LuaValue::String)
+                .unwrap_or(LuaValue::Nil),
+            Self::Pointer(p) => LuaValue::LightUserData(LuaLightUserData(p as *mut c_void)),
+        }
+    }
+
+    pub(crate) fn from_lua(val: &LuaValue) -> Self {
+        match val {
+            LuaValue::Nil => Self::Nil,
+            LuaValue::Boolean(b) => Self::Boolean(*b),
+            LuaValue::Integer(i) => Self::Integer(*i),
+            LuaValue::Number(n) => Self::Number(*n),
+            LuaValue::LightUserData(ud) => Self::Pointer(ud.0 as usize),
+            LuaValue::UserData(ud) => {
+                if let Ok(cbox) = ud.borrow::<CBox>() {
+                    Self::Pointer(cbox.as_ptr() as usize)
+                } else if let Ok(cb) = ud.borrow::<FfiCallback>() {
+                    Self::Pointer(cb.ptr())
+                } else {
+                    Self::Pointer(0)
+                }
+            }
+            // String -> char* (dangerous, string must stay alive!)
+            LuaValue::String(s) => Self::Pointer(s.as_bytes().as_ptr() as usize),
+            _ => Self::Other,
+        }
+    }
+
+    /// Converts the first value returned by a callback
+    pub(crate) fn from_values(values: &LuaMultiValue) -> Self {
+        values.iter().next().map_or(Self::Nil, Self::from_lua)
+    }
+
+    /// Converts the first value returned by a callback, or a zeroed value if it errored
+    pub(crate) fn from_call_result(result: LuaResult<LuaMultiValue>) -> Self {
+        match result {
+            Ok(values) => Self::from_values(&values),
+            Err(e) => {
+                eprintln!("[FFI CALLBACK ERROR] Lua function error: {}", e);
+                Self::Nil
+            }
         }
     }
 }
@@ -137,64 +443,59 @@ unsafe extern "C" fn callback_trampoline(
 // C -> Lua Conversion
 // ============================================================================
 
-unsafe fn c_arg_to_lua(lua: &Lua, ctype: &CType, ptr: *const c_void) -> LuaValue {
+unsafe fn c_arg_to_value(ctype: &CType, ptr: *const c_void) -> CValue {
     if ptr.is_null() {
-        return LuaValue::Nil;
+        return CValue::Nil;
     }
 
     match ctype {
-        CType::Void => LuaValue::Nil,
+        CType::Void => CValue::Nil,
 
-        CType::Bool => LuaValue::Boolean(unsafe { *(ptr as *const i8) } != 0),
+        CType::Bool => CValue::Boolean(unsafe { *ptr.cast::<i8>() } != 0),
 
-        CType::Char | CType::Int8 => LuaValue::Integer(i64::from(unsafe { *(ptr as *const i8) })),
-        CType::UChar | CType::UInt8 => LuaValue::Integer(i64::from(unsafe { *(ptr as *const u8) })),
+        CType::Char | CType::Int8 => CValue::Integer(i64::from(unsafe { *ptr.cast::<i8>() })),
+        CType::UChar | CType::UInt8 => CValue::Integer(i64::from(unsafe { *ptr.cast::<u8>() })),
 
-        CType::Short | CType::Int16 => {
-            LuaValue::Integer(i64::from(unsafe { *(ptr as *const i16) }))
-        }
+        CType::Short | CType::Int16 => CValue::Integer(i64::from(unsafe { *ptr.cast::<i16>() })),
         CType::UShort | CType::UInt16 | CType::WChar => {
-            LuaValue::Integer(i64::from(unsafe { *(ptr as *const u16) }))
+            CValue::Integer(i64::from(unsafe { *ptr.cast::<u16>() }))
         }
 
         CType::Int | CType::Int32 | CType::Enum(_) | CType::HRESULT => {
-            LuaValue::Integer(i64::from(unsafe { *(ptr as *const i32) }))
-        }
-        CType::UInt | CType::UInt32 => {
-            LuaValue::Integer(i64::from(unsafe { *(ptr as *const u32) }))
+            CValue::Integer(i64::from(unsafe { *ptr.cast::<i32>() }))
         }
+        CType::UInt | CType::UInt32 => CValue::Integer(i64::from(unsafe { *ptr.cast::<u32>() })),
 
         CType::Long | CType::LongLong | CType::Int64 => {
-            LuaValue::Integer(unsafe { *(ptr as *const i64) })
+            CValue::Integer(unsafe { *ptr.cast::<i64>() })
         }
         CType::ULong | CType::ULongLong | CType::UInt64 => {
-            LuaValue::Number(unsafe { *(ptr as *const u64) } as f64)
+            CValue::Number(unsafe { *ptr.cast::<u64>() } as f64)
         }
 
-        CType::Float => LuaValue::Number(f64::from(unsafe { *(ptr as *const f32) })),
-        CType::Double => LuaValue::Number(unsafe { *(ptr as *const f64) }),
+        CType::Float => CValue::Number(f64::from(unsafe { *ptr.cast::<f32>() })),
+        CType::Double => CValue::Number(unsafe { *ptr.cast::<f64>() }),
+        CType::LongDouble => CValue::Number(unsafe { crate::float::read_long_double(ptr) }),
+        CType::Half => CValue::Number(f64::from(crate::float::f16_to_f32(unsafe {
+            *ptr.cast::<u16>()
+        }))),
 
         CType::Pointer(inner) => {
             // Special handling for char* (strings)
             if let Some(inner_type) = inner {
                 if **inner_type == CType::Char {
-                    let cptr = unsafe { *(ptr as *const *const std::ffi::c_char) };
+                    let cptr = unsafe { *ptr.cast::<*const std::ffi::c_char>() };
                     if cptr.is_null() {
-                        return LuaValue::Nil;
-                    }
-                    match unsafe { std::ffi::CStr::from_ptr(cptr) }.to_str() {
-                        Ok(s) => {
-                            return lua
-                                .create_string(s)
-                                .map(LuaValue::String)
-                                .unwrap_or(LuaValue::Nil);
-                        }
-                        Err(_) => return LuaValue::Nil,
+                        return CValue::Nil;
                     }
+                    return match unsafe { std::ffi::CStr::from_ptr(cptr) }.to_str() {
+                        Ok(s) => CValue::String(s.to_string()),
+                        Err(_) => CValue::Nil,
+                    };
                 }
             }
             // Generic pointer - return as LightUserData
-            LuaValue::LightUserData(LuaLightUserData(unsafe { *(ptr as *const *mut c_void) }))
+            CValue::Pointer(unsafe { *ptr.cast::<usize>() })
         }
 
         // Struct/Union/Array/Function/GUID pointers
@@ -202,9 +503,7 @@ unsafe fn c_arg_to_lua(lua: &Lua, ctype: &CType, ptr: *const c_void) -> LuaValue
         | CType::Union(_)
         | CType::Array(_, _)
         | CType::Function(_)
-        | CType::GUID => {
-            LuaValue::LightUserData(LuaLightUserData(unsafe { *(ptr as *const *mut c_void) }))
-        }
+        | CType::GUID => CValue::Pointer(unsafe { *ptr.cast::<usize>() }),
     }
 }
 
@@ -212,7 +511,7 @@ unsafe fn c_arg_to_lua(lua: &Lua, ctype: &CType, ptr: *const c_void) -> LuaValue
 // Lua -> C Conversion
 // ============================================================================
 
-unsafe fn lua_to_c_result(ctype: &CType, val: &LuaValue, ret_ptr: *mut c_void) {
+unsafe fn lua_to_c_result(ctype: &CType, val: &CValue, ret_ptr: *mut c_void) {
     if ret_ptr.is_null() {
         return;
     }
@@ -222,45 +521,51 @@ unsafe fn lua_to_c_result(ctype: &CType, val: &LuaValue, ret_ptr: *mut c_void) {
 
         CType::Bool => {
             let b = match val {
-                LuaValue::Boolean(b) => *b,
-                LuaValue::Nil => false,
-                LuaValue::Integer(i) => *i != 0,
-                LuaValue::Number(n) => *n != 0.0,
+                CValue::Boolean(b) => *b,
+                CValue::Nil => false,
+                CValue::Integer(i) => *i != 0,
+                CValue::Number(n) => *n != 0.0,
                 _ => true, // Any other value is truthy
             };
-            *(ret_ptr as *mut i8) = if b { 1 } else { 0 };
+            *ret_ptr.cast::<i8>() = if b { 1 } else { 0 };
         }
 
         CType::Char | CType::Int8 => {
-            *(ret_ptr as *mut i8) = lua_to_i64(val) as i8;
+            *ret_ptr.cast::<i8>() = value_to_i64(val) as i8;
         }
         CType::UChar | CType::UInt8 => {
-            *(ret_ptr as *mut u8) = lua_to_i64(val) as u8;
+            *ret_ptr.cast::<u8>() = value_to_i64(val) as u8;
         }
         CType::Short | CType::Int16 => {
-            *(ret_ptr as *mut i16) = lua_to_i64(val) as i16;
+            *ret_ptr.cast::<i16>() = value_to_i64(val) as i16;
         }
         CType::UShort | CType::UInt16 | CType::WChar => {
-            *(ret_ptr as *mut u16) = lua_to_i64(val) as u16;
+            *ret_ptr.cast::<u16>() = value_to_i64(val) as u16;
         }
         CType::Int | CType::Int32 | CType::Enum(_) | CType::HRESULT => {
-            *(ret_ptr as *mut i32) = lua_to_i64(val) as i32;
+            *ret_ptr.cast::<i32>() = value_to_i64(val) as i32;
         }
         CType::UInt | CType::UInt32 => {
-            *(ret_ptr as *mut u32) = lua_to_i64(val) as u32;
+            *ret_ptr.cast::<u32>() = value_to_i64(val) as u32;
         }
         CType::Long | CType::LongLong | CType::Int64 => {
             // LRESULT is typically long (i64 on 64-bit)
-            *(ret_ptr as *mut i64) = lua_to_i64(val);
+            *ret_ptr.cast::<i64>() = value_to_i64(val);
         }
         CType::ULong | CType::ULongLong | CType::UInt64 => {
-            *(ret_ptr as *mut u64) = lua_to_u64(val);
+            *ret_ptr.cast::<u64>() = value_to_u64(val);
         }
         CType::Float => {
-            *(ret_ptr as *mut f32) = lua_to_f64(val) as f32;
+            *ret_ptr.cast::<f32>() = value_to_f64(val) as f32;
         }
         CType::Double => {
-            *(ret_ptr as *mut f64) = lua_to_f64(val);
+            *ret_ptr.cast::<f64>() = value_to_f64(val);
+        }
+        CType::LongDouble => {
+            crate::float::write_long_double(ret_ptr, value_to_f64(val));
+        }
+        CType::Half => {
+            *ret_ptr.cast::<u16>() = crate::float::f32_to_f16(value_to_f64(val) as f32);
         }
 
         CType::Pointer(_)
@@ -271,76 +576,66 @@ unsafe fn lua_to_c_result(ctype: &CType, val: &LuaValue, ret_ptr: *mut c_void) {
         | CType::GUID => {
             // For pointers, we need to handle various Lua types robustly
             let ptr_val: *mut c_void = match val {
-                LuaValue::LightUserData(ud) => ud.0,
-                LuaValue::Integer(i) => *i as *mut c_void,
-                LuaValue::Number(n) => *n as i64 as *mut c_void,
-                LuaValue::Nil => ptr::null_mut(),
-                LuaValue::Boolean(false) => ptr::null_mut(),
-                LuaValue::Boolean(true) => 1 as *mut c_void,
-                LuaValue::UserData(ud) => {
-                    if let Ok(cbox) = ud.borrow::<CBox>() {
-                        cbox.as_ptr()
-                    } else if let Ok(cb) = ud.borrow::<crate::callback::FfiCallback>() {
-                        cb.ptr() as *mut c_void
-                    } else {
-                        ptr::null_mut()
-                    }
+                CValue::Pointer(p) => *p as *mut c_void,
+                CValue::Integer(i) => *i as *mut c_void,
+                CValue::Number(n) if *n >= 0.0 && n.fract() == 0.0 => *n as usize as *mut c_void,
+                CValue::Number(n) => {
+                    eprintln!(
+                        "[FFI CALLBACK ERROR] Cannot return {n} as a pointer, expected an integer address"
+                    );
+                    ptr::null_mut()
                 }
-                LuaValue::String(s) => {
-                    // String -> char* (dangerous, string must stay alive!)
-                    s.as_bytes().as_ptr() as *mut c_void
+                CValue::Boolean(true) => ptr::dangling_mut(),
+                CValue::Nil | CValue::Boolean(false) | CValue::String(_) | CValue::Other => {
+                    ptr::null_mut()
                 }
-                _ => ptr::null_mut(),
             };
-            *(ret_ptr as *mut *mut c_void) = ptr_val;
+            *ret_ptr.cast::<*mut c_void>() = ptr_val;
         }
     }
 }
 
-fn lua_to_i64(val: &LuaValue) -> i64 {
+fn value_to_i64(val: &CValue) -> i64 {
     match val {
-        LuaValue::Integer(i) => *i,
-        LuaValue::Number(n) => *n as i64,
-        LuaValue::Boolean(b) => {
+        CValue::Integer(i) => *i,
+        CValue::Number(n) => *n as i64,
+        CValue::Boolean(b) => {
             if *b {
                 1
             } else {
                 0
             }
         }
-        LuaValue::Nil => 0,
         _ => 0,
     }
 }
 
-fn lua_to_u64(val: &LuaValue) -> u64 {
+fn value_to_u64(val: &CValue) -> u64 {
     match val {
-        LuaValue::Integer(i) => *i as u64,
-        LuaValue::Number(n) => *n as u64,
-        LuaValue::Boolean(b) => {
+        CValue::Integer(i) => *i as u64,
+        CValue::Number(n) => *n as u64,
+        CValue::Boolean(b) => {
             if *b {
                 1
             } else {
                 0
             }
         }
-        LuaValue::Nil => 0,
         _ => 0,
     }
 }
 
-fn lua_to_f64(val: &LuaValue) -> f64 {
+fn value_to_f64(val: &CValue) -> f64 {
     match val {
-        LuaValue::Number(n) => *n,
-        LuaValue::Integer(i) => *i as f64,
-        LuaValue::Boolean(b) => {
+        CValue::Number(n) => *n,
+        CValue::Integer(i) => *i as f64,
+        CValue::Boolean(b) => {
             if *b {
                 1.0
             } else {
                 0.0
             }
         }
-        LuaValue::Nil => 0.0,
         _ => 0.0,
     }
 }
@@ -350,14 +645,21 @@ fn lua_to_f64(val: &LuaValue) -> f64 {
 // ============================================================================
 
 /// A callback that can be passed to C functions.
+///
+/// The closure and everything it points to are only released when a callback that
+/// was freed is dropped. Callbacks that were never freed are leaked instead, see [`Drop`].
 pub struct FfiCallback {
     closure: *mut ffi_closure,
     code_ptr: CodePtr,
-    _cif: Box<ffi_cif>,
-    _arg_types_ffi: Vec<*mut ffi_type>,
-    _data: Box<CallbackData>,
+    cif: ManuallyDrop<Box<ffi_cif>>,
+    arg_types_ffi: ManuallyDrop<Vec<*mut ffi_type>>,
+    data: ManuallyDrop<Box<CallbackData>>,
     ret_type: CType,
     arg_count: usize,
+    id: u64,
+    mode: CallbackMode,
+    /// The queue this callback receives calls from other threads through, unless the mode is direct
+    queue: Option<CallbackQueue>,
 }
 
 unsafe impl Send for FfiCallback {}
@@ -371,8 +673,16 @@ impl FfiCallback {
         ret_type: CType,
         arg_types: Vec<CType>,
         conv: CallConv,
+        mode: CallbackMode,
     ) -> LuaResult<Self> {
-        let func_key = lua.create_registry_value(func)?;
+        crate::float::check_by_value(std::iter::once(&ret_type).chain(&arg_types))
+            .map_err(LuaError::external)?;
+        let id = NEXT_CALLBACK_ID.fetch_add(1, Ordering::Relaxed);
+        let queue = match mode {
+            CallbackMode::Direct => None,
+            CallbackMode::Blocking | CallbackMode::Queued => Some(CallbackQueue::get(lua)),
+        };
+        let func_key = lua.create_registry_value(func.clone())?;
 
         let arg_types_ffi: Vec<*mut ffi_type> =
             arg_types.iter().map(|t| ctype_to_ffi_type(t)).collect();
@@ -394,7 +704,7 @@ impl FfiCallback {
                 if arg_types_ffi.is_empty() {
                     ptr::null_mut()
                 } else {
-                    arg_types_ffi.as_ptr() as *mut _
+                    arg_types_ffi.as_ptr().cast_mut()
                 },
             )
         };
@@ -412,10 +722,16 @@ impl FfiCallback {
         }
 
         let data = Box::new(CallbackData {
-            func_key,
-            lua: lua.clone(),
+            func_key: RefCell::new(Some(func_key)),
+            freed: AtomicBool::new(false),
+            lua: lua.weak(),
             arg_types: arg_types.clone(),
             ret_type: ret_type_for_data,
+            id,
+            mode,
+            owner: thread::current().id(),
+            owner_calls: SYNC_CALLS.with(Arc::clone),
+            queue: queue.as_ref().map(CallbackQueue::sender),
         });
 
         let arg_count = arg_types.len();
@@ -436,30 +752,74 @@ impl FfiCallback {
             return Err(LuaError::external("Failed to prepare closure"));
         }
 
+        if let Some(queue) = &queue {
+            queue.register(lua, id, func);
+        }
+        LIVE_CALLBACKS.fetch_add(1, Ordering::SeqCst);
+
         Ok(Self {
             closure,
             code_ptr,
-            _cif: cif,
-            _arg_types_ffi: arg_types_ffi,
-            _data: data,
+            cif: ManuallyDrop::new(cif),
+            arg_types_ffi: ManuallyDrop::new(arg_types_ffi),
+            data: ManuallyDrop::new(data),
             ret_type,
             arg_count,
+            id,
+            mode,
+            queue,
         })
     }
 
     pub fn as_ptr(&self) -> *mut c_void {
-        self.code_ptr.as_ptr() as *mut c_void
+        self.code_ptr.as_ptr().cast::<c_void>().cast_mut()
     }
 
     pub fn ptr(&self) -> usize {
         self.code_ptr.as_ptr() as usize
     }
+
+    /// Returns `true` if the callback has not been freed
+    pub fn is_valid(&self) -> bool {
+        !self.closure.is_null() && !self.data.freed.load(Ordering::SeqCst)
+    }
+
+    /// Releases the Lua function of the callback, after which any calls to it
+    /// from C return a zeroed value. Freeing a callback more than once does nothing.
+    pub fn free(&self) {
+        if self.data.freed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // NOTE: Dropping the key lets mlua clean up the registry slot, the closure itself
+        // can not be freed yet, since C code may still hold a pointer to it and call it
+        drop(self.data.func_key.borrow_mut().take());
+        if let Some(queue) = &self.queue {
+            queue.unregister(self.id);
+        }
+        LIVE_CALLBACKS.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
+/**
+    Callbacks made by `ffi.callback` are kept from being garbage collected until they are
+    freed, so one that was never freed is only dropped when the Lua state closes. C code
+    may still hold a pointer to it then, so its closure is leaked, and any calls to it
+    return a zeroed value. Freed callbacks release their closure, and must not be called
+    by C code after they are garbage collected.
+*/
 impl Drop for FfiCallback {
     fn drop(&mut self) {
-        if !self.closure.is_null() {
-            unsafe { closure_free(self.closure) };
+        if !self.data.freed.load(Ordering::SeqCst) {
+            self.free();
+            return;
+        }
+        unsafe {
+            if !self.closure.is_null() {
+                closure_free(self.closure);
+            }
+            ManuallyDrop::drop(&mut self.data);
+            ManuallyDrop::drop(&mut self.arg_types_ffi);
+            ManuallyDrop::drop(&mut self.cif);
         }
     }
 }
@@ -471,13 +831,20 @@ impl LuaUserData for FfiCallback {
         fields.add_field_method_get("ptr", |_, this| Ok(LuaLightUserData(this.as_ptr())));
         fields.add_field_method_get("retType", |lua, this| this.ret_type.clone().into_lua(lua));
         fields.add_field_method_get("argCount", |_, this| Ok(this.arg_count));
-        fields.add_field_method_get("isValid", |_, this| Ok(!this.closure.is_null()));
+        fields.add_field_method_get("isValid", |_, this| Ok(this.is_valid()));
+        fields.add_field_method_get("mode", |_, this| Ok(this.mode.name()));
     }
 
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
         methods.add_method("getPtr", |_, this, ()| Ok(LuaLightUserData(this.as_ptr())));
-        methods.add_method("isValid", |_, this, ()| Ok(!this.closure.is_null()));
-        methods.add_method("free", |_, _, ()| Ok(()));
+        methods.add_method("isValid", |_, this, ()| Ok(this.is_valid()));
+        methods.add_method("free", |lua, this, ()| {
+            this.free();
+            if let Some(mut unfreed) = lua.app_data_mut::<UnfreedCallbacks>() {
+                unfreed.0.remove(&this.id);
+            }
+            Ok(())
+        });
     }
 }
 
@@ -486,10 +853,88 @@ impl LuaUserData for FfiCallback {
 // ============================================================================
 
 /// Create a callback from a Lua function with signature string
-pub fn create_callback(lua: &Lua, sig_str: &str, func: LuaFunction) -> LuaResult<LuaAnyUserData> {
+pub fn create_callback(
+    lua: &Lua,
+    sig_str: &str,
+    func: LuaFunction,
+    options: CallbackOptions,
+) -> LuaResult<LuaAnyUserData> {
     let (ret_type, arg_types, conv) = parse_callback_signature(sig_str)?;
-    let cb = FfiCallback::new(lua, func, ret_type, arg_types, conv)?;
-    lua.create_userdata(cb)
+    let cb = FfiCallback::new(lua, func, ret_type, arg_types, conv, options.mode)?;
+    let id = cb.id;
+    let cb = lua.create_userdata(cb)?;
+    if lua.app_data_ref::<UnfreedCallbacks>().is_none() {
+        lua.set_app_data(UnfreedCallbacks::default());
+    }
+    if let Some(mut unfreed) = lua.app_data_mut::<UnfreedCallbacks>() {
+        unfreed.0.insert(id, cb.clone());
+    }
+    Ok(cb)
+}
+
+/// Callbacks made by `ffi.callback` that have not been freed yet, by their id
+///
+/// C code may call a callback at any time until it is freed, so these are kept from
+/// being garbage collected, even when Lua no longer holds any reference to them.
+#[derive(Default)]
+struct UnfreedCallbacks(HashMap<u64, LuaAnyUserData>);
+
+/// Callbacks created for Lua functions stored in C memory, by the address they were stored at
+#[derive(Default)]
+struct StoredCallbacks(HashMap<usize, LuaAnyUserData>);
+
+impl StoredCallbacks {
+    /// Stores a callback at `slot`, freeing the one stored there before so that it is released
+    fn replace(&mut self, slot: *mut c_void, cb: Option<LuaAnyUserData>) {
+        let previous = match cb {
+            Some(cb) => self.0.insert(slot as usize, cb),
+            None => self.0.remove(&(slot as usize)),
+        };
+        if let Some(Ok(previous)) = previous.as_ref().map(LuaAnyUserData::borrow::<FfiCallback>) {
+            previous.free();
+        }
+    }
+}
+
+/**
+    Prepares a value to be stored in C memory of the given type at `slot`.
+
+    Lua functions stored in function pointers, like the fields of a vtable, become callbacks
+    with the signature of the pointer. Those are kept alive until something else is stored
+    at the same address, since C code may call them at any time until then.
+*/
+pub(crate) fn store_in_slot(
+    lua: &Lua,
+    ctype: &CType,
+    slot: *mut c_void,
+    value: LuaValue,
+) -> LuaResult<LuaValue> {
+    let Some(func_type) = ctype.function_type() else {
+        return Ok(value);
+    };
+    if lua.app_data_ref::<StoredCallbacks>().is_none() {
+        lua.set_app_data(StoredCallbacks::default());
+    }
+    let LuaValue::Function(func) = value else {
+        if let Some(mut stored) = lua.app_data_mut::<StoredCallbacks>() {
+            stored.replace(slot, None);
+        }
+        return Ok(value);
+    };
+
+    let cb = FfiCallback::new(
+        lua,
+        func,
+        func_type.ret.clone(),
+        func_type.args.clone(),
+        func_type.conv,
+        CallbackMode::Direct,
+    )?;
+    let cb = lua.create_userdata(cb)?;
+    if let Some(mut stored) = lua.app_data_mut::<StoredCallbacks>() {
+        stored.replace(slot, Some(cb.clone()));
+    }
+    Ok(LuaValue::UserData(cb))
 }
 
 /// Parse callback signature: "int(int, int)" -> (CType, Vec<CType>, CallConv)
@@ -526,9 +971,23 @@ fn parse_callback_signature(sig: &str) -> LuaResult<(CType, Vec<CType>, CallConv
 
     let before_paren = sig[..paren_start].trim();
 
-    let (conv, ret_str) = if before_paren.contains("__stdcall") {
+    // Function pointer types have a declarator like "(*)" or "(__stdcall *)" before the arguments
+    let (declarator, args_start) = match sig[paren_start..].find(')') {
+        Some(close) if sig[paren_start..paren_start + close].contains('*') => {
+            let close = paren_start + close;
+            let args_start = sig[close..]
+                .find('(')
+                .map(|open| close + open)
+                .ok_or_else(|| LuaError::external(format!("Invalid signature: {}", sig)))?;
+            (&sig[paren_start + 1..close], args_start)
+        }
+        _ => ("", paren_start),
+    };
+
+    let (conv, ret_str) = if before_paren.contains("__stdcall") || declarator.contains("__stdcall")
+    {
         (CallConv::Stdcall, before_paren.replace("__stdcall", ""))
-    } else if before_paren.contains("WINAPI") {
+    } else if before_paren.contains("WINAPI") || declarator.contains("WINAPI") {
         (CallConv::Stdcall, before_paren.replace("WINAPI", ""))
     } else {
         (CallConv::C, before_paren.to_string())
@@ -536,7 +995,7 @@ fn parse_callback_signature(sig: &str) -> LuaResult<(CType, Vec<CType>, CallConv
 
     let ret = CType::parse(&ret_str).unwrap_or(CType::Int);
 
-    let args_str = &sig[paren_start + 1..paren_end];
+    let args_str = sig[args_start + 1..paren_end].trim();
     let mut args = Vec::new();
 
     if !args_str.is_empty() && args_str != "void" {
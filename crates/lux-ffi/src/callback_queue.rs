@@ -0,0 +1,137 @@
+//! Callback Queue - Calls to callbacks from other threads
+//!
+//! Callbacks created with the `blocking` or `queued` mode register their Lua function
+//! here. Calls made to them from other threads are sent through a channel, and run as
+//! new threads on the scheduler by a dispatcher, which keeps the scheduler alive for
+//! as long as any such callback exists - the same way connected signal handlers do.
+
+use crate::callback::CValue;
+use async_channel::{Receiver, Sender, unbounded};
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::mpsc::SyncSender;
+
+/// Sends calls from other threads to the dispatcher, `None` wakes it up to check if it should stop
+pub(crate) type InvocationSender = Sender<Option<Invocation>>;
+
+/// A call to a callback made from another thread
+pub(crate) struct Invocation {
+    id: u64,
+    args: Vec<CValue>,
+    /// Receives the return value, for calls that block until the callback has run
+    reply: Option<SyncSender<CValue>>,
+}
+
+impl Invocation {
+    pub(crate) fn new(id: u64, args: Vec<CValue>, reply: Option<SyncSender<CValue>>) -> Self {
+        Self { id, args, reply }
+    }
+}
+
+struct QueueInner {
+    callbacks: RefCell<HashMap<u64, LuaFunction>>,
+    sender: InvocationSender,
+    receiver: Receiver<Option<Invocation>>,
+    dispatching: Cell<bool>,
+}
+
+/// The callbacks of a Lua state that may be called from other threads
+#[derive(Clone)]
+pub(crate) struct CallbackQueue(Rc<QueueInner>);
+
+impl CallbackQueue {
+    /// Returns the queue of the given Lua state, creating it on first use
+    pub(crate) fn get(lua: &Lua) -> Self {
+        if let Some(queue) = lua.app_data_ref::<Self>() {
+            return queue.clone();
+        }
+        let (sender, receiver) = unbounded();
+        let queue = Self(Rc::new(QueueInner {
+            callbacks: RefCell::new(HashMap::new()),
+            sender,
+            receiver,
+            dispatching: Cell::new(false),
+        }));
+        lua.set_app_data(queue.clone());
+        queue
+    }
+
+    pub(crate) fn sender(&self) -> InvocationSender {
+        self.0.sender.clone()
+    }
+
+    /// Registers a callback, starting the dispatcher if it is not already running
+    pub(crate) fn register(&self, lua: &Lua, id: u64, func: LuaFunction) {
+        self.0.callbacks.borrow_mut().insert(id, func);
+
+        if !self.0.dispatching.replace(true) {
+            let this = self.clone();
+            let inner = lua.clone();
+            lua.spawn_local(async move {
+                this.dispatch(&inner).await;
+                this.0.dispatching.set(false);
+            });
+        }
+    }
+
+    /// Unregisters a callback, letting the scheduler finish once none are left
+    pub(crate) fn unregister(&self, id: u64) {
+        let mut callbacks = self.0.callbacks.borrow_mut();
+        if callbacks.remove(&id).is_some() && callbacks.is_empty() {
+            let _ = self.0.sender.try_send(None);
+        }
+    }
+
+    async fn dispatch(&self, lua: &Lua) {
+        while let Ok(message) = self.0.receiver.recv().await {
+            let Some(invocation) = message else {
+                if self.0.callbacks.borrow().is_empty() {
+                    break;
+                }
+                continue;
+            };
+            let func = self.0.callbacks.borrow().get(&invocation.id).cloned();
+            if let Some(func) = func {
+                run(lua, func, invocation);
+            }
+        }
+
+        // Calls that arrived after the last callback was dropped will never run,
+        // dropping them lets any thread blocked on their result return a zeroed value
+        while self.0.receiver.try_recv().is_ok() {}
+    }
+}
+
+/// Runs a call on its own thread, so that the callback may yield, and replies with its result
+fn run(lua: &Lua, func: LuaFunction, invocation: Invocation) {
+    let args = invocation
+        .args
+        .into_iter()
+        .map(|arg| arg.into_lua(lua))
+        .collect::<LuaMultiValue>();
+
+    let thread_id = match lua.push_thread_back(func, args) {
+        Ok(thread_id) => thread_id,
+        Err(e) => {
+            eprintln!("[FFI CALLBACK ERROR] Failed to run callback: {e}");
+            return;
+        }
+    };
+    lua.track_thread(thread_id);
+
+    let inner = lua.clone();
+    lua.spawn_local(async move {
+        inner.wait_for_thread(thread_id).await;
+        // NOTE: Errors are already reported by the scheduler, like for any other thread
+        let value = match inner.get_thread_result(thread_id) {
+            Some(Ok(values)) => CValue::from_values(&values),
+            _ => CValue::Nil,
+        };
+        if let Some(reply) = invocation.reply {
+            let _ = reply.try_send(value);
+        }
+    });
+}
@@ -0,0 +1,455 @@
+//! FFI COM - Component Object Model interfaces
+//!
+//! Interfaces are declared with `ffi.com.define(name, iid)`, and their methods
+//! are resolved through a struct of function pointers declared with `ffi.cdef`,
+//! named `<name>Vtbl` as in C headers. Objects hold a single reference to an
+//! interface, which is released when they are collected.
+
+use crate::call::ffi_call_ptr;
+use crate::memory::{CBox, get_ptr_from_value};
+use crate::registry::Registry;
+use crate::types::CType;
+use mlua::prelude::*;
+use std::ffi::c_void;
+use std::fmt::{self, Write as _};
+use std::ptr;
+
+type QueryInterfaceFn =
+    unsafe extern "system" fn(*mut c_void, *const Guid, *mut *mut c_void) -> i32;
+type ReleaseFn = unsafe extern "system" fn(*mut c_void) -> u32;
+
+/// Vtable slots of the `IUnknown` methods, which every interface starts with
+const SLOT_QUERY_INTERFACE: usize = 0;
+const SLOT_RELEASE: usize = 2;
+
+const IID_IUNKNOWN: Guid = Guid {
+    data1: 0,
+    data2: 0,
+    data3: 0,
+    data4: [0xC0, 0, 0, 0, 0, 0, 0, 0x46],
+};
+
+/// Names of common failure codes, for error messages
+const KNOWN_HRESULTS: &[(u32, &str)] = &[
+    (0x8000_4001, "E_NOTIMPL"),
+    (0x8000_4002, "E_NOINTERFACE"),
+    (0x8000_4003, "E_POINTER"),
+    (0x8000_4004, "E_ABORT"),
+    (0x8000_4005, "E_FAIL"),
+    (0x8000_FFFF, "E_UNEXPECTED"),
+    (0x8001_0106, "RPC_E_CHANGED_MODE"),
+    (0x8004_0110, "CLASS_E_NOAGGREGATION"),
+    (0x8004_0154, "REGDB_E_CLASSNOTREG"),
+    (0x8004_01F0, "CO_E_NOTINITIALIZED"),
+    (0x8007_0005, "E_ACCESSDENIED"),
+    (0x8007_0006, "E_HANDLE"),
+    (0x8007_000E, "E_OUTOFMEMORY"),
+    (0x8007_0057, "E_INVALIDARG"),
+];
+
+/// A 128-bit interface or class identifier, laid out as the C `GUID` struct
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Guid {
+    pub data1: u32,
+    pub data2: u16,
+    pub data3: u16,
+    pub data4: [u8; 8],
+}
+
+impl Guid {
+    /// Parses a GUID written as `{xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx}`, braces optional
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let s = s
+            .strip_prefix('{')
+            .and_then(|s| s.strip_suffix('}'))
+            .unwrap_or(s);
+        let groups: Vec<&str> = s.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] || !s.chars().all(|c| c == '-' || c.is_ascii_hexdigit()) {
+            return None;
+        }
+        Some(Self {
+            data1: u32::from_str_radix(groups[0], 16).ok()?,
+            data2: u16::from_str_radix(groups[1], 16).ok()?,
+            data3: u16::from_str_radix(groups[2], 16).ok()?,
+            data4: u64::from_str_radix(&groups[3..].concat(), 16)
+                .ok()?
+                .to_be_bytes(),
+        })
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-",
+            self.data1, self.data2, self.data3
+        )?;
+        for (index, byte) in self.data4.iter().enumerate() {
+            if index == 2 {
+                write!(f, "-")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// An interface declared with `ffi.com.define`
+#[derive(Debug, Clone)]
+pub struct ComInterface {
+    pub name: String,
+    pub iid: Guid,
+    /// The struct of function pointers that methods are resolved through
+    pub vtbl: String,
+}
+
+/// Describes an HRESULT, such as `HRESULT 0x80004002 (E_NOINTERFACE)`
+#[must_use]
+pub fn describe_hresult(hr: i32) -> String {
+    let code = hr.cast_unsigned();
+    let mut description = format!("HRESULT {code:#010X}");
+    if let Some((_, name)) = KNOWN_HRESULTS.iter().find(|(known, _)| *known == code) {
+        let _ = write!(description, " ({name})");
+    }
+    // Win32 errors wrapped as HRESULTs have a system message for their error code
+    #[cfg(windows)]
+    if code & 0xFFFF_0000 == 0x8007_0000 {
+        let err = std::io::Error::from_raw_os_error((code & 0xFFFF).cast_signed());
+        let _ = write!(description, ": {err}");
+    }
+    description
+}
+
+/**
+    Returns the HRESULT if it signals success.
+
+    # Errors
+
+    Errors with a description of the HRESULT if it signals failure.
+*/
+pub fn check_hresult(hr: i32, what: &str) -> LuaResult<i32> {
+    if hr >= 0 {
+        Ok(hr)
+    } else {
+        Err(LuaError::external(format!(
+            "{what} failed with {}",
+            describe_hresult(hr)
+        )))
+    }
+}
+
+/// Resolves an interface by name, `IID_` name or GUID string
+fn resolve_interface(spec: &str) -> LuaResult<ComInterface> {
+    let reg = Registry::get();
+    let name = spec.strip_prefix("IID_").unwrap_or(spec);
+    if let Some(interface) = reg.get_interface(name) {
+        return Ok(interface);
+    }
+    if let Some(interface) = Guid::parse(spec).and_then(|iid| reg.find_interface(iid)) {
+        return Ok(interface);
+    }
+    Err(LuaError::external(format!(
+        "Unknown COM interface '{spec}', declare it with ffi.com.define"
+    )))
+}
+
+/// Reads the function pointer at `offset` bytes into the vtable of an interface pointer
+unsafe fn vtable_entry(ptr: *mut c_void, offset: usize) -> usize {
+    let vtbl = *ptr.cast::<*const u8>();
+    vtbl.add(offset).cast::<usize>().read_unaligned()
+}
+
+/// Queries an interface pointer for another interface, returning a new reference to it
+unsafe fn query_raw(ptr: *mut c_void, interface: ComInterface) -> LuaResult<ComObject> {
+    if ptr.is_null() {
+        return Err(LuaError::external(
+            "ffi.com.query: interface pointer is NULL",
+        ));
+    }
+    let query: QueryInterfaceFn =
+        std::mem::transmute(vtable_entry(ptr, SLOT_QUERY_INTERFACE * size_of::<usize>()));
+    let mut out = ptr::null_mut();
+    let hr = query(ptr, &raw const interface.iid, &raw mut out);
+    check_hresult(hr, &format!("QueryInterface for {}", interface.name))?;
+    ComObject::from_raw(out, interface)
+}
+
+/// A reference to a COM interface, released when collected
+pub struct ComObject {
+    ptr: *mut c_void,
+    interface: ComInterface,
+}
+
+impl ComObject {
+    /**
+        Takes ownership of a reference to an interface.
+
+        # Errors
+
+        Errors if the pointer is NULL.
+    */
+    pub fn from_raw(ptr: *mut c_void, interface: ComInterface) -> LuaResult<Self> {
+        if ptr.is_null() {
+            return Err(LuaError::external(format!(
+                "Attempt to wrap a NULL {} pointer",
+                interface.name
+            )));
+        }
+        Ok(Self { ptr, interface })
+    }
+
+    /**
+        Returns the interface pointer.
+
+        # Errors
+
+        Errors if the reference has been released.
+    */
+    pub fn as_ptr(&self) -> LuaResult<*mut c_void> {
+        if self.ptr.is_null() {
+            Err(LuaError::external(format!(
+                "Attempt to use a released {}",
+                self.interface.name
+            )))
+        } else {
+            Ok(self.ptr)
+        }
+    }
+
+    /// Releases the reference, if it has not been released yet
+    pub fn release(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe {
+                let release: ReleaseFn =
+                    std::mem::transmute(vtable_entry(self.ptr, SLOT_RELEASE * size_of::<usize>()));
+                release(self.ptr);
+            }
+            self.ptr = ptr::null_mut();
+        }
+    }
+
+    /// Creates a Lua function calling the method with the given name through the vtable
+    fn method(&self, lua: &Lua, name: &str) -> LuaResult<LuaFunction> {
+        let (offset, ctype) = {
+            let reg = Registry::get();
+            let vtbl = reg.get_struct(&self.interface.vtbl).ok_or_else(|| {
+                LuaError::external(format!(
+                    "No vtable declared for {}, declare a struct named '{}' with ffi.cdef",
+                    self.interface.name, self.interface.vtbl
+                ))
+            })?;
+            let field = vtbl.field(name).ok_or_else(|| {
+                LuaError::external(format!(
+                    "{} has no method named '{name}'",
+                    self.interface.name
+                ))
+            })?;
+            (field.offset, field.ctype.clone())
+        };
+        let CType::Pointer(Some(func)) = &ctype else {
+            return Err(LuaError::external(format!(
+                "{}.{name} is not a function pointer",
+                self.interface.vtbl
+            )));
+        };
+        let returns_hresult =
+            matches!(func.as_ref(), CType::Function(f) if f.ret == CType::HRESULT);
+        let label = format!("{}::{name}", self.interface.name);
+
+        lua.create_function(
+            move |lua, (this, args): (LuaUserDataRef<ComObject>, LuaMultiValue)| {
+                let ptr = this.as_ptr()?;
+                let fn_ptr = unsafe { vtable_entry(ptr, offset) };
+                let mut args = args;
+                args.push_front(LuaValue::LightUserData(LuaLightUserData(ptr)));
+                let ret = unsafe { ffi_call_ptr(lua.clone(), fn_ptr, &ctype, args)? };
+                if returns_hresult && let Some(hr) = ret.front().and_then(LuaValue::as_i32) {
+                    check_hresult(hr, &label)?;
+                }
+                Ok(ret)
+            },
+        )
+    }
+}
+
+impl Drop for ComObject {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+impl LuaUserData for ComObject {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("ptr", |_, this| Ok(LuaLightUserData(this.as_ptr()?)));
+        fields.add_field_method_get("interface", |_, this| Ok(this.interface.name.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // obj:query(iid) - Another interface of the same object
+        methods.add_method("query", |_, this, iid: String| {
+            let interface = resolve_interface(&iid)?;
+            unsafe { query_raw(this.as_ptr()?, interface) }
+        });
+
+        // obj:release() - Release the reference now instead of when collected
+        methods.add_method_mut("release", |_, this, ()| {
+            this.release();
+            Ok(())
+        });
+
+        methods.add_meta_method(LuaMetaMethod::Index, |lua, this, name: String| {
+            this.as_ptr()?;
+            this.method(lua, &name)
+        });
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(if this.ptr.is_null() {
+                format!("com<{}: released>", this.interface.name)
+            } else {
+                format!("com<{}: {:p}>", this.interface.name, this.ptr)
+            })
+        });
+    }
+}
+
+/// ffi.com.define(name, iid, vtbl?) - declare an interface, with methods from `<name>Vtbl`
+fn com_define(_: &Lua, (name, iid, vtbl): (String, String, Option<String>)) -> LuaResult<()> {
+    let iid = Guid::parse(&iid)
+        .ok_or_else(|| LuaError::external(format!("ffi.com.define: invalid IID '{iid}'")))?;
+    let vtbl = vtbl.unwrap_or_else(|| format!("{name}Vtbl"));
+    Registry::get().add_interface(ComInterface { name, iid, vtbl });
+    Ok(())
+}
+
+/// ffi.com.query(ptr, iid) - query any interface pointer for another interface
+fn com_query(_: &Lua, (ptr, iid): (LuaValue, String)) -> LuaResult<ComObject> {
+    let interface = resolve_interface(&iid)?;
+    unsafe { query_raw(get_ptr_from_value(&ptr)?, interface) }
+}
+
+/// ffi.com.wrap(ptr, iid) - take ownership of a reference, such as one returned through an out-parameter
+fn com_wrap(_: &Lua, (ptr, iid): (LuaValue, String)) -> LuaResult<ComObject> {
+    let interface = resolve_interface(&iid)?;
+    ComObject::from_raw(get_ptr_from_value(&ptr)?, interface)
+}
+
+/// ffi.com.guid(str) - a GUID as cdata, to pass to C functions
+fn com_guid(_: &Lua, s: String) -> LuaResult<CBox> {
+    let guid = Guid::parse(&s)
+        .ok_or_else(|| LuaError::external(format!("ffi.com.guid: invalid GUID '{s}'")))?;
+    let cbox = CBox::new(CType::GUID);
+    unsafe { cbox.as_ptr().cast::<Guid>().write_unaligned(guid) };
+    Ok(cbox)
+}
+
+/// ffi.com.check(hr, what?) - raise an error for failed HRESULTs, returning successful ones
+fn com_check(_: &Lua, (hr, what): (i64, Option<String>)) -> LuaResult<i32> {
+    // HRESULTs are 32 bits, but may have been read as unsigned
+    let hr = (hr as u32).cast_signed();
+    check_hresult(hr, what.as_deref().unwrap_or("COM call"))
+}
+
+#[cfg(windows)]
+mod os {
+    use super::{ComObject, Guid, check_hresult, resolve_interface};
+    use mlua::prelude::*;
+    use std::ptr;
+    use windows_sys::Win32::System::Com::{
+        CLSCTX_ALL, COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED, CoCreateInstance,
+        CoInitializeEx, CoUninitialize,
+    };
+
+    /// ffi.com.initialize(multithreaded?) - initialize COM for the current thread
+    pub fn initialize(_: &Lua, multithreaded: Option<bool>) -> LuaResult<i32> {
+        let model = if multithreaded.unwrap_or(false) {
+            COINIT_MULTITHREADED
+        } else {
+            COINIT_APARTMENTTHREADED
+        };
+        let hr = unsafe { CoInitializeEx(ptr::null(), model as _) };
+        check_hresult(hr, "CoInitializeEx")
+    }
+
+    /// ffi.com.uninitialize() - balance a successful call to ffi.com.initialize
+    pub fn uninitialize(_: &Lua, (): ()) -> LuaResult<()> {
+        unsafe { CoUninitialize() };
+        Ok(())
+    }
+
+    /// ffi.com.create(clsid, iid, context?) - create an object of a registered class
+    pub fn create(
+        _: &Lua,
+        (clsid, iid, context): (String, String, Option<u32>),
+    ) -> LuaResult<ComObject> {
+        let clsid = Guid::parse(&clsid).ok_or_else(|| {
+            LuaError::external(format!("ffi.com.create: invalid CLSID '{clsid}'"))
+        })?;
+        let interface = resolve_interface(&iid)?;
+        let mut out = ptr::null_mut();
+        let hr = unsafe {
+            CoCreateInstance(
+                (&raw const clsid).cast(),
+                ptr::null_mut(),
+                context.unwrap_or(CLSCTX_ALL as u32) as _,
+                (&raw const interface.iid).cast(),
+                &raw mut out,
+            )
+        };
+        check_hresult(hr, &format!("CoCreateInstance for {}", interface.name))?;
+        ComObject::from_raw(out, interface)
+    }
+}
+
+#[cfg(not(windows))]
+mod os {
+    use super::ComObject;
+    use mlua::prelude::*;
+
+    fn unsupported<T>(name: &str) -> LuaResult<T> {
+        Err(LuaError::external(format!(
+            "ffi.com.{name} is only available on Windows"
+        )))
+    }
+
+    pub fn initialize(_: &Lua, _: Option<bool>) -> LuaResult<i32> {
+        unsupported("initialize")
+    }
+
+    pub fn uninitialize(_: &Lua, (): ()) -> LuaResult<()> {
+        unsupported("uninitialize")
+    }
+
+    pub fn create(_: &Lua, _: (String, String, Option<u32>)) -> LuaResult<ComObject> {
+        unsupported("create")
+    }
+}
+
+/// Creates the ffi.com table
+pub(crate) fn create_com_table(lua: &Lua) -> LuaResult<LuaTable> {
+    {
+        let mut reg = Registry::get();
+        if reg.get_interface("IUnknown").is_none() {
+            reg.add_interface(ComInterface {
+                name: "IUnknown".to_string(),
+                iid: IID_IUNKNOWN,
+                vtbl: "IUnknownVtbl".to_string(),
+            });
+        }
+    }
+
+    let com = lua.create_table()?;
+    com.set("initialize", lua.create_function(os::initialize)?)?;
+    com.set("uninitialize", lua.create_function(os::uninitialize)?)?;
+    com.set("create", lua.create_function(os::create)?)?;
+    com.set("define", lua.create_function(com_define)?)?;
+    com.set("query", lua.create_function(com_query)?)?;
+    com.set("wrap", lua.create_function(com_wrap)?)?;
+    com.set("guid", lua.create_function(com_guid)?)?;
+    com.set("check", lua.create_function(com_check)?)?;
+    Ok(com)
+}
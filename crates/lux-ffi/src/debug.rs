@@ -0,0 +1,422 @@
+//! FFI Debugging
+//!
+//! Shows the memory behind cdata - as annotated hexdumps, and as field values in
+//! `tostring` of structs when enabled. In debug mode, cdata also remembers where
+//! it came from, and casts between incompatible pointer types can be logged.
+
+use crate::memory::{CBox, c_to_lua_at_ptr, get_ptr_from_value};
+use crate::registry::Registry;
+use crate::safety::{self, Access};
+use crate::types::{CType, StructDef};
+use lux_utils::fmt::Label;
+use lux_utils::process::ProcessFfiDebug;
+use mlua::prelude::*;
+use std::ffi::c_void;
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// Bytes shown on each line of a hexdump
+const BYTES_PER_LINE: usize = 16;
+
+/// Bytes dumped from pointers to memory of an unknown size
+const DEFAULT_DUMP_BYTES: usize = 64;
+
+/// How many levels of nested structs verbose `tostring` lists the fields of
+const VERBOSE_DEPTH: usize = 1;
+
+/// Whether `tostring` of struct cdata lists its fields, set by `ffi.setVerboseTostring`
+#[derive(Clone, Copy)]
+struct VerboseTostring(bool);
+
+/// Returns whether `tostring` of struct cdata lists its fields for the given Lua state
+#[must_use]
+pub fn is_verbose_tostring(lua: &Lua) -> bool {
+    lua.app_data_ref::<VerboseTostring>()
+        .is_some_and(|verbose| verbose.0)
+}
+
+/// `ffi.setVerboseTostring(enabled)`
+pub(crate) fn ffi_set_verbose_tostring(lua: &Lua, enabled: bool) -> LuaResult<()> {
+    lua.set_app_data(VerboseTostring(enabled));
+    Ok(())
+}
+
+/// Where a cdata value came from, recorded while debug mode is enabled
+#[derive(Debug)]
+pub struct Origin {
+    /// How the value was created, such as `ffi.new("Point")` or `Node*.next`
+    pub source: String,
+    /// The traceback of the script when the value was created
+    pub traceback: Option<String>,
+    /// Where the value that this one was cast or loaded from came from
+    pub from: Option<Arc<Origin>>,
+}
+
+fn debug_options(lua: &Lua) -> ProcessFfiDebug {
+    lua.app_data_ref::<ProcessFfiDebug>()
+        .map(|options| *options)
+        .unwrap_or_default()
+}
+
+/// Returns whether cdata remembers where it was created for the given Lua state
+#[must_use]
+pub fn is_debug_mode(lua: &Lua) -> bool {
+    debug_options(lua).track_origins()
+}
+
+/// Returns whether casts between incompatible pointer types are logged for the given Lua state
+#[must_use]
+pub fn is_logging_casts(lua: &Lua) -> bool {
+    debug_options(lua).log_casts()
+}
+
+/// `ffi.setDebugMode(enabled)`
+pub(crate) fn ffi_set_debug_mode(lua: &Lua, enabled: bool) -> LuaResult<()> {
+    let mut options = debug_options(lua);
+    options.set_track_origins(enabled);
+    lua.set_app_data(options);
+    Ok(())
+}
+
+/// `ffi.isDebugMode()`
+pub(crate) fn ffi_is_debug_mode(lua: &Lua, (): ()) -> LuaResult<bool> {
+    Ok(is_debug_mode(lua))
+}
+
+/// `ffi.setCastLogging(enabled)`
+pub(crate) fn ffi_set_cast_logging(lua: &Lua, enabled: bool) -> LuaResult<()> {
+    let mut options = debug_options(lua);
+    options.set_log_casts(enabled);
+    lua.set_app_data(options);
+    Ok(())
+}
+
+/**
+    Creates the origin of a new cdata value in debug mode, along with the
+    traceback of the script, returning `None` when debug mode is disabled.
+*/
+pub(crate) fn origin(
+    lua: &Lua,
+    source: impl FnOnce() -> String,
+    from: Option<Arc<Origin>>,
+) -> Option<Arc<Origin>> {
+    if !is_debug_mode(lua) {
+        return None;
+    }
+    Some(Arc::new(Origin {
+        source: source(),
+        traceback: lua.traceback(None, 1).ok().map(|t| t.to_string_lossy()),
+        from,
+    }))
+}
+
+/// Records the origin of a value in debug mode, if it is cdata
+pub(crate) fn record_origin(
+    lua: &Lua,
+    value: &LuaValue,
+    source: impl FnOnce() -> String,
+    from: Option<Arc<Origin>>,
+) {
+    if let LuaValue::UserData(ud) = value
+        && let Some(origin) = origin(lua, source, from)
+        && let Ok(mut cbox) = ud.borrow_mut::<CBox>()
+    {
+        cbox.set_origin(Some(origin));
+    }
+}
+
+/// Returns the origin of a value, if it is cdata that was created in debug mode
+#[must_use]
+pub fn origin_of(value: &LuaValue) -> Option<Arc<Origin>> {
+    match value {
+        LuaValue::UserData(ud) => ud.borrow::<CBox>().ok()?.origin().cloned(),
+        _ => None,
+    }
+}
+
+/// Bytes can be read through any pointer, the same as in C
+fn is_byte(ctype: &CType) -> bool {
+    matches!(
+        ctype,
+        CType::Char | CType::UChar | CType::Int8 | CType::UInt8
+    )
+}
+
+/**
+    Logs a cast of cdata to a pointer type that points to a different type
+    than the cdata did, along with the line of the script that made it.
+
+    Casts from and to `void*` and byte pointers are never logged.
+*/
+pub(crate) fn log_cast(lua: &Lua, value: &LuaValue, target: &CType) {
+    if !is_logging_casts(lua) {
+        return;
+    }
+    let (CType::Pointer(Some(to)), LuaValue::UserData(ud)) = (target, value) else {
+        return;
+    };
+    let Ok(cbox) = ud.borrow::<CBox>() else {
+        return;
+    };
+    // Values other than pointers are cast from their address, and arrays decay to pointers
+    let from = match &cbox.ctype {
+        CType::Pointer(None) => return,
+        CType::Pointer(Some(inner)) | CType::Array(inner, _) => inner.resolve(),
+        ctype => ctype.resolve(),
+    };
+    let to = to.resolve();
+    if from == to || is_byte(&from) || is_byte(&to) || to == CType::Void {
+        return;
+    }
+
+    let location = script_location(lua);
+    eprintln!(
+        "{} ffi.cast from '{}*' to incompatible pointer type '{}'{location}",
+        Label::Warn,
+        from.c_name(),
+        target.c_name()
+    );
+}
+
+/// The line of the script that called into the FFI, as ` at source:line`, if there is one
+pub(crate) fn script_location(lua: &Lua) -> String {
+    lua.inspect_stack(1, |debug| {
+        let line = debug.current_line()?;
+        let source = debug.source().short_src?.to_string();
+        Some(format!(" at {source}:{line}"))
+    })
+    .flatten()
+    .unwrap_or_default()
+}
+
+fn origin_table(lua: &Lua, origin: &Origin) -> LuaResult<LuaTable> {
+    let info = lua.create_table()?;
+    info.set("source", origin.source.as_str())?;
+    info.set("traceback", origin.traceback.as_deref())?;
+    if let Some(from) = &origin.from {
+        info.set("from", origin_table(lua, from)?)?;
+    }
+    Ok(info)
+}
+
+/**
+    `ffi.debuginfo(cdata)` - Describes a cdata value, and where it came from if it
+    was created in debug mode, following casts and loads back to the original allocation.
+*/
+pub(crate) fn ffi_debuginfo(lua: &Lua, value: LuaValue) -> LuaResult<LuaTable> {
+    let LuaValue::UserData(ud) = &value else {
+        return Err(LuaError::external(format!(
+            "ffi.debuginfo: expected cdata, got {}",
+            value.type_name()
+        )));
+    };
+    let cbox = ud
+        .borrow::<CBox>()
+        .map_err(|_| LuaError::external("ffi.debuginfo: expected cdata"))?;
+
+    let info = lua.create_table()?;
+    info.set("type", cbox.ctype.c_name())?;
+    info.set("address", cbox.as_ptr() as usize)?;
+    info.set("size", cbox.ctype.size())?;
+    info.set("owned", cbox.is_owned())?;
+    if let Some(region) = cbox.region() {
+        let allocation = lua.create_table()?;
+        allocation.set("size", region.len)?;
+        allocation.set(
+            "offset",
+            (cbox.as_ptr() as usize).wrapping_sub(region.base) as isize,
+        )?;
+        info.set("allocation", allocation)?;
+    }
+    if let Some(origin) = cbox.origin() {
+        info.set("origin", origin_table(lua, origin)?)?;
+    }
+    Ok(info)
+}
+
+/// The definition of a struct or union type, following typedefs
+fn struct_def(ctype: &CType) -> Option<Arc<StructDef>> {
+    // NOTE: Resolving locks the registry, so it must happen before getting the definition
+    match ctype.resolve() {
+        CType::Struct(name) | CType::Union(name) => Registry::get().get_struct_shared(&name),
+        _ => None,
+    }
+}
+
+/**
+    Formats the fields of a struct or union as `{ name = value, ... }`, listing the
+    fields of nested structs up to `depth` levels deep and eliding deeper ones.
+*/
+fn format_fields(lua: &Lua, def: &StructDef, ptr: *mut c_void, depth: usize) -> LuaResult<String> {
+    let mut out = String::from("{");
+    for (i, field) in def.fields.iter().enumerate() {
+        let _ = write!(out, "{} {} = ", if i > 0 { "," } else { "" }, field.name);
+        let field_ptr: *mut c_void = unsafe { ptr.cast::<u8>().add(field.offset).cast() };
+        match struct_def(&field.ctype) {
+            Some(_) if depth == 0 => out.push_str("{...}"),
+            Some(nested) => out.push_str(&format_fields(lua, &nested, field_ptr, depth - 1)?),
+            None => {
+                let value = unsafe { c_to_lua_at_ptr(lua, &field.ctype, field_ptr) }?;
+                out.push_str(&value.to_string()?);
+            }
+        }
+    }
+    out.push_str(if def.fields.is_empty() { "}" } else { " }" });
+    Ok(out)
+}
+
+/**
+    Formats the field values of struct cdata for verbose `tostring`,
+    returning `None` for any other cdata or when verbose `tostring` is disabled.
+*/
+pub(crate) fn verbose_fields(lua: &Lua, cbox: &CBox) -> LuaResult<Option<String>> {
+    if !is_verbose_tostring(lua) || cbox.as_ptr().is_null() {
+        return Ok(None);
+    }
+    let Some(def) = struct_def(&cbox.ctype) else {
+        return Ok(None);
+    };
+    if safety::is_enabled(lua) {
+        safety::check(
+            cbox.as_ptr(),
+            def.size,
+            def.align,
+            cbox.region(),
+            Access::Read,
+        )?;
+    }
+    format_fields(lua, &def, cbox.as_ptr(), VERBOSE_DEPTH).map(Some)
+}
+
+/// Options for `ffi.dump`
+#[derive(Default)]
+pub(crate) struct DumpOptions {
+    bytes: Option<usize>,
+}
+
+impl FromLua for DumpOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                bytes: t.get("bytes")?,
+            }),
+            other => Err(LuaError::external(format!(
+                "ffi.dump: options must be a table, got {}",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+/// Describes what starts within `range` of a value of type `ctype`, such as the struct fields
+fn annotate(ctype: Option<&CType>, range: std::ops::Range<usize>) -> String {
+    let Some(ctype) = ctype else {
+        return String::new();
+    };
+    if let Some(def) = struct_def(ctype) {
+        return def
+            .fields
+            .iter()
+            .filter(|field| range.contains(&field.offset))
+            .map(|field| format!("{}: {}", field.name, field.ctype.c_name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+    if let CType::Array(elem, count) = ctype
+        && elem.size() > 0
+    {
+        let size = elem.size();
+        let first = range.start.div_ceil(size);
+        let last = ((range.end - 1) / size).min(count.saturating_sub(1));
+        return match last.checked_sub(first) {
+            Some(0) => format!("[{first}]"),
+            Some(_) => format!("[{first}..{last}]"),
+            None => String::new(),
+        };
+    }
+    String::new()
+}
+
+/**
+    `ffi.dump(cdata, options?)` - Formats the memory of cdata as a hexdump, annotated
+    with the fields or elements that start on each line.
+
+    Dumps the whole value by default, or what a pointer points to when its size is known.
+*/
+pub(crate) fn ffi_dump(lua: &Lua, (value, options): (LuaValue, DumpOptions)) -> LuaResult<String> {
+    let (ptr, ctype, region) = match &value {
+        LuaValue::UserData(ud) if ud.is::<CBox>() => {
+            let cbox = ud.borrow::<CBox>()?;
+            // Pointers are dumped as what they point to, the same way they are indexed
+            let ctype = match &cbox.ctype {
+                CType::Pointer(inner) => inner.as_deref().cloned(),
+                ctype => Some(ctype.clone()),
+            };
+            (cbox.as_ptr(), ctype, cbox.region())
+        }
+        _ => (get_ptr_from_value(&value)?, None, None),
+    };
+    if ptr.is_null() {
+        return Err(LuaError::external("ffi.dump: null pointer"));
+    }
+
+    let len = match (options.bytes, &ctype) {
+        (Some(bytes), _) => bytes,
+        (None, Some(ctype)) if ctype.size() > 0 => ctype.size(),
+        (None, _) => DEFAULT_DUMP_BYTES,
+    };
+    if safety::is_enabled(lua) {
+        safety::check(ptr, len, 1, region, Access::Read)?;
+    }
+
+    let mut out = match &ctype {
+        Some(ctype) => format!("cdata<{}>: {ptr:p} ({len} bytes)", ctype.c_name()),
+        None => format!("{ptr:p} ({len} bytes)"),
+    };
+    let bytes = unsafe { std::slice::from_raw_parts(ptr.cast::<u8>(), len) };
+    for (line, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let offset = line * BYTES_PER_LINE;
+        let _ = write!(out, "\n{offset:04x} ");
+        for i in 0..BYTES_PER_LINE {
+            if i % 8 == 0 {
+                out.push(' ');
+            }
+            match chunk.get(i) {
+                Some(byte) => {
+                    let _ = write!(out, "{byte:02x} ");
+                }
+                None => out.push_str("   "),
+            }
+        }
+        out.push_str(" |");
+        out.extend(chunk.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                char::from(byte)
+            } else {
+                '.'
+            }
+        }));
+        out.push('|');
+        let annotation = annotate(ctype.as_ref(), offset..offset + chunk.len());
+        if !annotation.is_empty() {
+            let padding = BYTES_PER_LINE - chunk.len();
+            let _ = write!(out, "{:padding$}  {annotation}", "");
+        }
+    }
+    Ok(out)
+}
+
+/**
+    `ffi.sizeofValue(cdata)` - The size of a cdata value in bytes, which unlike
+    `ffi.sizeof` includes the length of arrays that was only known at runtime.
+*/
+pub(crate) fn ffi_sizeof_value(_: &Lua, value: LuaValue) -> LuaResult<usize> {
+    match &value {
+        LuaValue::UserData(ud) if ud.is::<CBox>() => Ok(ud.borrow::<CBox>()?.ctype.size()),
+        other => Err(LuaError::external(format!(
+            "ffi.sizeofValue: expected cdata, got {}",
+            other.type_name()
+        ))),
+    }
+}
@@ -0,0 +1,104 @@
+//! FFI Errors - `errno` and `GetLastError`
+//!
+//! Both are captured right after every C call, before any other Rust code
+//! gets a chance to clobber them, and are kept per thread until the next call.
+
+use mlua::prelude::*;
+use std::cell::Cell;
+
+/// Error codes captured after the last C call on this thread
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct LastErrors {
+    errno: i32,
+    lasterror: u32,
+}
+
+thread_local! {
+    static LAST_ERRORS: Cell<LastErrors> = Cell::new(LastErrors::default());
+}
+
+fn errno_location() -> *mut i32 {
+    #[cfg(windows)]
+    unsafe extern "C" {
+        #[link_name = "_errno"]
+        fn location() -> *mut i32;
+    }
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    unsafe extern "C" {
+        #[link_name = "__error"]
+        fn location() -> *mut i32;
+    }
+    #[cfg(not(any(windows, target_os = "macos", target_os = "ios", target_os = "freebsd")))]
+    unsafe extern "C" {
+        #[link_name = "__errno_location"]
+        fn location() -> *mut i32;
+    }
+    unsafe { location() }
+}
+
+#[cfg(windows)]
+fn os_last_error() -> u32 {
+    unsafe { windows_sys::Win32::Foundation::GetLastError() }
+}
+
+#[cfg(not(windows))]
+fn os_last_error() -> u32 {
+    0
+}
+
+/// Capture `errno` and `GetLastError`, must be called right after a C call returns
+#[inline]
+pub(crate) fn capture() {
+    // NOTE: `GetLastError` goes first, reading errno may call into the CRT
+    let lasterror = os_last_error();
+    let errno = unsafe { *errno_location() };
+    LAST_ERRORS.with(|last| last.set(LastErrors { errno, lasterror }));
+}
+
+/// Error codes captured after the last C call on this thread
+pub(crate) fn last() -> LastErrors {
+    LAST_ERRORS.with(Cell::get)
+}
+
+/// Make codes captured on another thread, such as the call pool, the last ones on this thread
+pub(crate) fn restore(last: LastErrors) {
+    LAST_ERRORS.with(|cell| cell.set(last));
+}
+
+/// Build the error raised by checked functions, from the codes of the last call
+pub(crate) fn last_call_error(func_name: &str) -> LuaError {
+    let last = LAST_ERRORS.with(Cell::get);
+    #[allow(clippy::cast_possible_wrap)]
+    let code = if last.lasterror != 0 {
+        last.lasterror as i32
+    } else {
+        last.errno
+    };
+    LuaError::external(format!(
+        "{func_name} failed: {}",
+        std::io::Error::from_raw_os_error(code)
+    ))
+}
+
+/// ffi.errno(new?) - Get the `errno` of the last call, optionally setting `errno` for the next one
+pub fn ffi_errno(new_errno: Option<i32>) -> i32 {
+    let last = LAST_ERRORS.with(Cell::get);
+    if let Some(errno) = new_errno {
+        unsafe { *errno_location() = errno };
+        LAST_ERRORS.with(|cell| cell.set(LastErrors { errno, ..last }));
+    }
+    last.errno
+}
+
+/// ffi.lasterror(new?) - Get the `GetLastError` code of the last call, always 0 outside of Windows
+pub fn ffi_lasterror(new_error: Option<u32>) -> u32 {
+    let last = LAST_ERRORS.with(Cell::get);
+    #[cfg(windows)]
+    if let Some(lasterror) = new_error {
+        unsafe { windows_sys::Win32::Foundation::SetLastError(lasterror) };
+        LAST_ERRORS.with(|cell| cell.set(LastErrors { lasterror, ..last }));
+    }
+    #[cfg(not(windows))]
+    let _ = new_error;
+    last.lasterror
+}
@@ -0,0 +1,262 @@
+//! Floating Point Conversions
+//!
+//! Conversions between Lua numbers and the C floating point types
+//! that have no Rust equivalent - `_Float16` and `long double`.
+
+use std::ffi::c_void;
+
+use crate::types::CType;
+
+/// How `long double` is represented on the target platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)] // Only one format is used on any given target
+pub(crate) enum LongDoubleFormat {
+    /// Same as `double`, such as with MSVC and on Apple Silicon
+    Double,
+    /// 80-bit x87 extended precision, padded to 12 or 16 bytes
+    X87,
+    /// IEEE 754 quadruple precision
+    Quad,
+}
+
+#[cfg(all(
+    any(target_arch = "x86", target_arch = "x86_64"),
+    not(target_env = "msvc")
+))]
+pub(crate) const LONG_DOUBLE_FORMAT: LongDoubleFormat = LongDoubleFormat::X87;
+
+#[cfg(all(
+    any(target_arch = "aarch64", target_arch = "riscv64"),
+    not(target_vendor = "apple"),
+    not(target_os = "windows")
+))]
+pub(crate) const LONG_DOUBLE_FORMAT: LongDoubleFormat = LongDoubleFormat::Quad;
+
+#[cfg(not(any(
+    all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        not(target_env = "msvc")
+    ),
+    all(
+        any(target_arch = "aarch64", target_arch = "riscv64"),
+        not(target_vendor = "apple"),
+        not(target_os = "windows")
+    )
+)))]
+pub(crate) const LONG_DOUBLE_FORMAT: LongDoubleFormat = LongDoubleFormat::Double;
+
+/// Size of `long double` in bytes, including any padding
+pub(crate) const fn long_double_size() -> usize {
+    match LONG_DOUBLE_FORMAT {
+        LongDoubleFormat::Double => 8,
+        LongDoubleFormat::X87 if cfg!(target_arch = "x86") => 12,
+        LongDoubleFormat::X87 | LongDoubleFormat::Quad => 16,
+    }
+}
+
+/// Alignment of `long double` in bytes
+pub(crate) const fn long_double_align() -> usize {
+    match LONG_DOUBLE_FORMAT {
+        LongDoubleFormat::Double => 8,
+        LongDoubleFormat::X87 if cfg!(target_arch = "x86") => 4,
+        LongDoubleFormat::X87 | LongDoubleFormat::Quad => 16,
+    }
+}
+
+/// Errors if any of the given types is `_Float16`, which libffi can not pass by value
+pub(crate) fn check_by_value<'a>(types: impl IntoIterator<Item = &'a CType>) -> Result<(), String> {
+    if types.into_iter().any(|t| *t == CType::Half) {
+        Err("_Float16 can not be passed to or returned from C functions by value, use a pointer instead".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// Multiplies by a power of two, without the power itself over- or underflowing
+fn ldexp(mut value: f64, mut exp: i32) -> f64 {
+    while exp > 1023 && value.is_finite() {
+        value *= 2f64.powi(1023);
+        exp -= 1023;
+    }
+    while exp < -1022 && value != 0.0 {
+        value *= 2f64.powi(-1022);
+        exp += 1022;
+    }
+    value * 2f64.powi(exp)
+}
+
+/// Converts the bits of a `_Float16` to a float, which is always exact
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exp = (bits >> 10) & 0x1f;
+    let frac = u32::from(bits & 0x3ff);
+    match exp {
+        0 => sign * frac as f32 * 2f32.powi(-24),
+        0x1f => f32::from_bits((u32::from(bits & 0x8000) << 16) | 0x7f80_0000 | (frac << 13)),
+        _ => f32::from_bits(
+            (u32::from(bits & 0x8000) << 16) | ((u32::from(exp) + 112) << 23) | (frac << 13),
+        ),
+    }
+}
+
+/// Converts a float to the bits of a `_Float16`, rounding to nearest even
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let frac = bits & 0x007f_ffff;
+
+    if exp == 0xff {
+        // Infinity stays infinity, NaN stays NaN - but always quiet
+        let nan = if frac == 0 {
+            0
+        } else {
+            0x200 | (frac >> 13) as u16
+        };
+        return sign | 0x7c00 | nan;
+    }
+
+    let half_exp = exp - 127 + 15;
+    if half_exp >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    let round = |mantissa: u32, shift: u32| -> u32 {
+        let halfway = 1 << (shift - 1);
+        let rest = mantissa & ((1 << shift) - 1);
+        let truncated = mantissa >> shift;
+        if rest > halfway || (rest == halfway && truncated & 1 == 1) {
+            truncated + 1
+        } else {
+            truncated
+        }
+    };
+
+    if half_exp <= 0 {
+        // Too small for a normal half, becomes subnormal or zero
+        if half_exp < -10 {
+            return sign;
+        }
+        let shift = (14 - half_exp) as u32;
+        return sign | round(frac | 0x0080_0000, shift) as u16;
+    }
+
+    // NOTE: Rounding up may carry into the exponent, which is still correct
+    sign | round(((half_exp as u32) << 23) | frac, 13) as u16
+}
+
+fn x87_to_f64(bytes: [u8; 10]) -> f64 {
+    let mut mantissa_bytes = [0; 8];
+    mantissa_bytes.copy_from_slice(&bytes[..8]);
+    let mantissa = u64::from_le_bytes(mantissa_bytes);
+    let sign_exp = u16::from_le_bytes([bytes[8], bytes[9]]);
+    let negative = sign_exp & 0x8000 != 0;
+    let exp = i32::from(sign_exp & 0x7fff);
+
+    let magnitude = if exp == 0x7fff {
+        if mantissa << 1 == 0 {
+            f64::INFINITY
+        } else {
+            f64::NAN
+        }
+    } else {
+        // Denormals use the same exponent as the smallest normal numbers
+        ldexp(mantissa as f64, exp.max(1) - 16383 - 63)
+    };
+    if negative { -magnitude } else { magnitude }
+}
+
+fn f64_to_x87(value: f64) -> [u8; 10] {
+    let bits = value.to_bits();
+    let sign = ((bits >> 48) & 0x8000) as u16;
+    let exp = ((bits >> 52) & 0x7ff) as u16;
+    let frac = bits & 0x000f_ffff_ffff_ffff;
+
+    let (exp, mantissa) = match exp {
+        0 if frac == 0 => (0, 0),
+        0 => {
+            // Subnormal doubles are normal in the wider exponent range
+            let zeros = frac.leading_zeros();
+            (15372 - zeros as u16, frac << zeros)
+        }
+        0x7ff => (0x7fff, (1 << 63) | (frac << 11)),
+        _ => (exp + (16383 - 1023), (1 << 63) | (frac << 11)),
+    };
+
+    let mut bytes = [0; 10];
+    bytes[..8].copy_from_slice(&mantissa.to_le_bytes());
+    bytes[8..].copy_from_slice(&(sign | exp).to_le_bytes());
+    bytes
+}
+
+fn quad_to_f64(bits: u128) -> f64 {
+    let negative = bits >> 127 != 0;
+    let exp = ((bits >> 112) & 0x7fff) as i32;
+    let frac = bits & ((1 << 112) - 1);
+
+    let magnitude = match exp {
+        0x7fff if frac == 0 => f64::INFINITY,
+        0x7fff => f64::NAN,
+        0 => ldexp(frac as f64, 1 - 16383 - 112),
+        _ => ldexp(((1 << 112) | frac) as f64, exp - 16383 - 112),
+    };
+    if negative { -magnitude } else { magnitude }
+}
+
+fn f64_to_quad(value: f64) -> u128 {
+    let bits = value.to_bits();
+    let sign = u128::from(bits >> 63) << 127;
+    let exp = (bits >> 52) & 0x7ff;
+    let frac = bits & 0x000f_ffff_ffff_ffff;
+
+    let (exp, frac) = match exp {
+        0 if frac == 0 => (0, 0),
+        0 => {
+            // Drop the leading bit, which becomes the implicit one
+            let zeros = frac.leading_zeros();
+            let exp = 16383 - 1074 + 63 - u64::from(zeros);
+            (exp, (u128::from(frac) << (zeros + 49)) & ((1 << 112) - 1))
+        }
+        0x7ff => (0x7fff, u128::from(frac) << 60),
+        _ => (exp + (16383 - 1023), u128::from(frac) << 60),
+    };
+    sign | (u128::from(exp) << 112) | frac
+}
+
+/// Reads a `long double` from memory, rounding it to the nearest double
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads of [`long_double_size`] bytes.
+pub(crate) unsafe fn read_long_double(ptr: *const c_void) -> f64 {
+    unsafe {
+        match LONG_DOUBLE_FORMAT {
+            LongDoubleFormat::Double => ptr.cast::<f64>().read_unaligned(),
+            LongDoubleFormat::X87 => x87_to_f64(ptr.cast::<[u8; 10]>().read_unaligned()),
+            LongDoubleFormat::Quad => {
+                quad_to_f64(u128::from_le(ptr.cast::<u128>().read_unaligned()))
+            }
+        }
+    }
+}
+
+/// Writes a double to memory as a `long double`, which is always exact
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes of [`long_double_size`] bytes.
+pub(crate) unsafe fn write_long_double(ptr: *mut c_void, value: f64) {
+    unsafe {
+        match LONG_DOUBLE_FORMAT {
+            LongDoubleFormat::Double => ptr.cast::<f64>().write_unaligned(value),
+            LongDoubleFormat::X87 => {
+                // Zero the padding too, so that equal values have equal bytes
+                ptr.cast::<u8>().write_bytes(0, long_double_size());
+                ptr.cast::<[u8; 10]>().write_unaligned(f64_to_x87(value));
+            }
+            LongDoubleFormat::Quad => ptr
+                .cast::<u128>()
+                .write_unaligned(f64_to_quad(value).to_le()),
+        }
+    }
+}
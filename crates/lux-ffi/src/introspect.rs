@@ -0,0 +1,195 @@
+//! FFI Introspection
+//!
+//! Describes the declarations in the registry as plain Lua tables,
+//! for tools such as binding generators written in Luau, and checks
+//! the layouts computed for them against the ones the real ABI uses.
+
+use crate::registry::Registry;
+use crate::types::*;
+use mlua::prelude::*;
+
+/// Describe every field of a struct or union, for `ffi.structinfo` and `ctype:fields()`
+///
+/// # Errors
+///
+/// Errors when out of memory.
+pub(crate) fn fields_table(lua: &Lua, def: &StructDef) -> LuaResult<LuaTable> {
+    let fields = lua.create_table_with_capacity(def.fields.len(), 0)?;
+    for field in &def.fields {
+        let info = lua.create_table()?;
+        info.set("name", field.name.as_str())?;
+        info.set("type", field.ctype.c_name())?;
+        info.set("offset", field.offset)?;
+        info.set("size", field.ctype.size())?;
+        if let Some((offset, width)) = field.bits {
+            info.set("bitOffset", offset)?;
+            info.set("bitWidth", width)?;
+        }
+        fields.push(info)?;
+    }
+    Ok(fields)
+}
+
+fn struct_table(lua: &Lua, def: &StructDef) -> LuaResult<LuaTable> {
+    let fields = fields_table(lua, def)?;
+    let info = lua.create_table()?;
+    info.set("name", def.name.as_str())?;
+    info.set("size", def.size)?;
+    info.set("align", def.align)?;
+    info.set("isUnion", def.is_union)?;
+    info.set("isPacked", def.is_packed)?;
+    info.set("fields", fields)?;
+    Ok(info)
+}
+
+fn func_table(lua: &Lua, sig: &FuncSig) -> LuaResult<LuaTable> {
+    let args = lua.create_table_with_capacity(sig.args.len(), 0)?;
+    for (name, ctype) in &sig.args {
+        let arg = lua.create_table()?;
+        arg.set("name", name.as_str())?;
+        arg.set("type", ctype.c_name())?;
+        args.push(arg)?;
+    }
+
+    let info = lua.create_table()?;
+    info.set("name", sig.name.as_str())?;
+    info.set("ret", sig.ret.c_name())?;
+    info.set("args", args)?;
+    info.set("variadic", sig.variadic)?;
+    info.set(
+        "conv",
+        match sig.conv {
+            CallConv::C => "cdecl",
+            CallConv::Stdcall => "stdcall",
+            CallConv::Fastcall => "fastcall",
+            CallConv::Win64 => "win64",
+        },
+    )?;
+    Ok(info)
+}
+
+/// `ffi.types()` - Describe everything declared with `ffi.cdef`
+///
+/// # Errors
+///
+/// Errors when out of memory.
+pub fn ffi_types(lua: &Lua, (): ()) -> LuaResult<LuaTable> {
+    // NOTE: Everything is cloned out first, sizes of fields lock the registry again
+    let (structs, funcs, enums, typedefs) = {
+        let reg = Registry::get();
+        (
+            reg.all_structs(),
+            reg.all_funcs(),
+            reg.all_enums(),
+            reg.all_typedefs(),
+        )
+    };
+
+    let struct_infos = lua.create_table()?;
+    for (name, def) in &structs {
+        struct_infos.set(name.as_str(), struct_table(lua, def)?)?;
+    }
+
+    let func_infos = lua.create_table()?;
+    for (name, sig) in &funcs {
+        func_infos.set(name.as_str(), func_table(lua, sig)?)?;
+    }
+
+    let enum_infos = lua.create_table()?;
+    for (name, values) in enums {
+        enum_infos.set(name, lua.create_table_from(values)?)?;
+    }
+
+    let typedef_infos = lua.create_table()?;
+    for (name, ctype) in typedefs {
+        typedef_infos.set(name, ctype.c_name())?;
+    }
+
+    let types = lua.create_table()?;
+    types.set("structs", struct_infos)?;
+    types.set("functions", func_infos)?;
+    types.set("enums", enum_infos)?;
+    types.set("typedefs", typedef_infos)?;
+    Ok(types)
+}
+
+/// `ffi.structinfo(name)` - Describe the layout of a struct or union
+///
+/// # Errors
+///
+/// Errors when out of memory.
+pub fn ffi_structinfo(lua: &Lua, name: String) -> LuaResult<Option<LuaTable>> {
+    let name = name
+        .strip_prefix("struct ")
+        .or_else(|| name.strip_prefix("union "))
+        .unwrap_or(&name)
+        .trim();
+    let def = {
+        let reg = Registry::get();
+        match reg.get_typedef(name) {
+            Some(CType::Struct(target) | CType::Union(target)) => reg.get_struct(&target).cloned(),
+            _ => reg.get_struct(name).cloned(),
+        }
+    };
+    def.map(|def| struct_table(lua, &def)).transpose()
+}
+
+/// Resolve a type name for a layout assertion, erroring for structs that were never declared
+fn layout_type(type_name: &str) -> LuaResult<CType> {
+    let ctype = CType::parse(type_name)
+        .ok_or_else(|| LuaError::external(format!("Unknown type: {type_name}")))?;
+    if let CType::Struct(name) | CType::Union(name) = &ctype
+        && !Registry::get().has_struct(name)
+    {
+        return Err(LuaError::external(format!("Struct '{name}' not found")));
+    }
+    Ok(ctype)
+}
+
+/// `ffi.assertsize(type, size)` - Error unless the computed size of a type is `size` bytes
+///
+/// # Errors
+///
+/// Errors if the type is unknown, or its size is not the one expected.
+pub fn ffi_assertsize(_: &Lua, (type_name, expected): (String, usize)) -> LuaResult<()> {
+    let actual = layout_type(&type_name)?.size();
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(LuaError::external(format!(
+            "Size of '{type_name}' is {actual} bytes, but {expected} were expected - \
+             its declaration does not match the real layout"
+        )))
+    }
+}
+
+/// `ffi.assertoffset(type, field, offset)` - Error unless a field is at `offset` bytes
+///
+/// # Errors
+///
+/// Errors if the type is not a declared struct or union, the field is
+/// missing, or its offset is not the one expected.
+pub fn ffi_assertoffset(
+    _: &Lua,
+    (type_name, field, expected): (String, String, usize),
+) -> LuaResult<()> {
+    let (CType::Struct(name) | CType::Union(name)) = layout_type(&type_name)? else {
+        return Err(LuaError::external(format!(
+            "'{type_name}' is not a struct or union"
+        )));
+    };
+    let actual = Registry::get()
+        .get_struct(&name)
+        .and_then(|def| def.field_offset(&field))
+        .ok_or_else(|| {
+            LuaError::external(format!("Field '{field}' not found in struct '{name}'"))
+        })?;
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(LuaError::external(format!(
+            "Offset of '{field}' in '{type_name}' is {actual} bytes, but {expected} were expected - \
+             its declaration does not match the real layout"
+        )))
+    }
+}
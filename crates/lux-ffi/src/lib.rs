@@ -5,35 +5,80 @@
 //! Re-implements LuaJIT FFI for Lux using modular architecture.
 #[allow(dead_code)]
 use libloading::Library;
+use lux_utils::LuxError;
 use mlua::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+mod anchor;
+pub mod arena;
 pub mod batch;
+pub mod bind;
 pub mod call;
 pub mod callback;
+mod callback_queue;
+pub mod com;
+pub mod debug;
+pub mod errno;
+mod float;
+pub mod introspect;
+pub mod loader;
 pub mod memory;
+pub mod mmap;
+pub mod objc;
+pub mod out;
 pub mod parser;
+mod pool;
+pub mod process_memory;
 pub mod registry;
+pub mod safety;
+pub mod shm;
+mod simd;
+pub mod template;
 pub mod types;
 
 use types::CType;
 
 /// The FFI Module Entry Point
+#[allow(clippy::too_many_lines)]
 pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     let exports = lua.create_table()?;
 
     // ffi.cdef(decl)
     exports.set(
         "cdef",
-        lua.create_function(|_, decl: String| {
-            parser::parse_cdef(&decl).map_err(LuaError::external)?;
-            Ok(())
+        lua.create_function(|lua, decl: String| {
+            let bound = parser::parse_cdef(&decl).map_err(LuxError::Parse)?;
+            bind::create_bindings(lua, bound)
         })?,
     )?;
 
+    // ffi.bind(name) - Constructor and checked field access for a declared struct
+    exports.set("bind", lua.create_function(bind::ffi_bind)?)?;
+
     // ffi.new(type, init...) - Implemented in memory.rs
     exports.set("new", lua.create_function(memory::ffi_new)?)?;
 
+    // ffi.arena(size) - Bump allocation for many small values, implemented in arena.rs
+    exports.set("arena", lua.create_function(arena::ffi_arena)?)?;
+
+    // ffi.out(type, init?) - Out-parameter slots, implemented in out.rs
+    exports.set("out", lua.create_function(out::ffi_out)?)?;
+
+    // ffi.arg(type, value) - Explicitly typed (variadic) arguments, implemented in call.rs
+    exports.set("arg", lua.create_function(call::ffi_arg)?)?;
+
+    // ffi.errno(new?) / ffi.lasterror(new?) - Captured after every call, implemented in errno.rs
+    exports.set(
+        "errno",
+        lua.create_function(|_, new: Option<i32>| Ok(errno::ffi_errno(new)))?,
+    )?;
+    exports.set(
+        "lasterror",
+        lua.create_function(|_, new: Option<u32>| Ok(errno::ffi_lasterror(new)))?,
+    )?;
+
     // ffi.cast(type, val) - Implemented in memory.rs
     exports.set(
         "cast",
@@ -42,9 +87,16 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         })?,
     )?;
 
-    // ffi.typeof(type) - Implemented in memory.rs
+    // ffi.typeof(type, params...) - Implemented in memory.rs, with `$` parameters in template.rs
     exports.set("typeof", lua.create_function(memory::ffi_typeof)?)?;
 
+    // ffi.types() / ffi.structinfo(name) - Registry introspection, implemented in introspect.rs
+    exports.set("types", lua.create_function(introspect::ffi_types)?)?;
+    exports.set(
+        "structinfo",
+        lua.create_function(introspect::ffi_structinfo)?,
+    )?;
+
     // ffi.sizeof(type)
     exports.set(
         "sizeof",
@@ -59,6 +111,9 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         })?,
     )?;
 
+    // ffi.sizeofValue(cdata) - Includes the length of variable-length arrays, in debug.rs
+    exports.set("sizeofValue", lua.create_function(debug::ffi_sizeof_value)?)?;
+
     // ffi.alignof(type)
     exports.set(
         "alignof",
@@ -81,12 +136,37 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         })?,
     )?;
 
+    // ffi.assertsize(type, size) / ffi.assertoffset(type, field, offset) - Layout checks, in introspect.rs
+    exports.set(
+        "assertsize",
+        lua.create_function(introspect::ffi_assertsize)?,
+    )?;
+    exports.set(
+        "assertoffset",
+        lua.create_function(introspect::ffi_assertoffset)?,
+    )?;
+
     // ffi.addressof(cdata, field) - Special ext
     exports.set("addressof", lua.create_function(memory::ffi_addressof)?)?;
 
     // ffi.string(ptr, len)
     exports.set("string", lua.create_function(memory::ffi_string)?)?;
 
+    // ffi.newstring(str) - Null-terminated copy owned by the cdata, implemented in memory.rs
+    exports.set("newstring", lua.create_function(memory::ffi_newstring)?)?;
+
+    // ffi.releaseStrings(ptr, size?) - Frees copies of strings assigned to pointers in C memory
+    exports.set(
+        "releaseStrings",
+        lua.create_function(memory::ffi_release_strings)?,
+    )?;
+
+    // ffi.stringCount() - Number of copies of strings kept for C memory, for finding leaks
+    exports.set(
+        "stringCount",
+        lua.create_function(|_, ()| Ok(anchor::count()))?,
+    )?;
+
     // ffi.copy(dst, src, len)
     exports.set("copy", lua.create_function(memory::ffi_copy)?)?;
 
@@ -96,8 +176,8 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     // ffi.istype(type, val)
     exports.set(
         "istype",
-        lua.create_function(|_, (type_name, val): (String, LuaValue)| {
-            memory::ffi_istype(&type_name, val)
+        lua.create_function(|_, (ctype, val): (LuaValue, LuaValue)| {
+            memory::ffi_istype(ctype, val)
         })?,
     )?;
 
@@ -112,45 +192,35 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     // ffi.gc(cdata, finalizer)
     exports.set("gc", lua.create_function(memory::ffi_gc)?)?;
 
-    // ffi.load(name)
+    // ffi.load(name, options?) - Searches for the library the way loader.rs describes
     exports.set(
         "load",
-        lua.create_function(move |_lua, name: String| {
-            let load_name = if cfg!(windows) && !name.ends_with(".dll") {
-                format!("{}.dll", name)
-            } else if cfg!(target_os = "linux") && !name.contains(".so") {
-                format!("lib{}.so", name)
-            } else {
-                name.clone()
-            };
-
-            let lib = unsafe { Library::new(&load_name) }.map_err(|e| {
-                LuaError::external(format!("Failed to load library '{}': {}", load_name, e))
-            })?;
-
-            Ok(SmartLibrary {
-                lib: Arc::new(lib),
-                name,
-            })
+        lua.create_function(|lua, (name, options): (String, loader::LoadOptions)| {
+            let (lib, path) = loader::load(&name, &options).map_err(LuxError::Load)?;
+            SmartLibrary::new(lua, lib, name, path.display().to_string())
         })?,
     )?;
 
-    // ffi.callback(sig, func)
+    // ffi.callback(sig, func, options?) - Options pick how calls from other threads are handled
     exports.set(
         "callback",
-        lua.create_function(|lua, (sig, func): (String, LuaFunction)| {
-            callback::create_callback(lua, &sig, func)
-        })?,
+        lua.create_function(
+            |lua, (sig, func, options): (String, LuaFunction, callback::CallbackOptions)| {
+                callback::create_callback(lua, &sig, func, options)
+            },
+        )?,
     )?;
 
-    // ffi.batch(func, input_ptr, output_ptr, count) - Batch processing
+    // ffi.callbackCount() - Number of callbacks not yet freed, for finding leaks
     exports.set(
-        "batch",
-        lua.create_function(|lua, args: (LuaValue, LuaValue, LuaValue, usize)| {
-            batch::ffi_batch(&lua, args)
-        })?,
+        "callbackCount",
+        lua.create_function(|_, ()| Ok(callback::callback_count()))?,
     )?;
 
+    // ffi.batch(func, input_ptr, output_ptr, count) - Batch processing, with vectorized
+    // kernels such as ffi.batch.addf64(a, b, out) as fields
+    exports.set("batch", batch::create_batch_table(&lua)?)?;
+
     // ffi.batch2(func, input1_ptr, input2_ptr, output_ptr, count) - Two-arg batch
     exports.set(
         "batch2",
@@ -161,19 +231,63 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         )?,
     )?;
 
+    // ffi.shm.create(name, size) / ffi.shm.open(name) - Named shared memory
+    exports.set("shm", shm::create_shm_table(&lua)?)?;
+
+    // ffi.mmap(path?, size?, protection?) - Mapped files and virtual memory, implemented in mmap.rs
+    exports.set("mmap", lua.create_function(mmap::ffi_mmap)?)?;
+
+    // ffi.setSafeMode(enabled) / ffi.isSafeMode() - Checked memory access, implemented in safety.rs
+    exports.set(
+        "setSafeMode",
+        lua.create_function(safety::ffi_set_safe_mode)?,
+    )?;
+    exports.set("isSafeMode", lua.create_function(safety::ffi_is_safe_mode)?)?;
+
+    // ffi.dump(cdata, options?) / ffi.setVerboseTostring(enabled) - Implemented in debug.rs
+    exports.set("dump", lua.create_function(debug::ffi_dump)?)?;
+    exports.set(
+        "setVerboseTostring",
+        lua.create_function(debug::ffi_set_verbose_tostring)?,
+    )?;
+
+    // ffi.debuginfo(cdata) / ffi.setDebugMode(enabled) / ffi.isDebugMode() /
+    // ffi.setCastLogging(enabled) - Where cdata came from, implemented in debug.rs
+    exports.set("debuginfo", lua.create_function(debug::ffi_debuginfo)?)?;
+    exports.set(
+        "setDebugMode",
+        lua.create_function(debug::ffi_set_debug_mode)?,
+    )?;
+    exports.set(
+        "isDebugMode",
+        lua.create_function(debug::ffi_is_debug_mode)?,
+    )?;
+    exports.set(
+        "setCastLogging",
+        lua.create_function(debug::ffi_set_cast_logging)?,
+    )?;
+
+    // ffi.process.open(pid) - Other processes' memory, needs --allow-process-memory
+    exports.set("process", process_memory::create_process_table(&lua)?)?;
+
+    // ffi.com.define(name, iid) / ffi.com.query(ptr, iid) - COM interfaces, implemented in com.rs
+    exports.set("com", com::create_com_table(&lua)?)?;
+
+    // ffi.objc.class(name) / obj:msgSend(selector, ...) - Objective-C messaging, implemented in objc.rs
+    exports.set("objc", objc::create_objc_table(&lua)?)?;
+
     // ffi.C
     let default_lib_name = if cfg!(windows) {
         "msvcrt.dll"
+    } else if cfg!(target_os = "macos") {
+        "/usr/lib/libSystem.B.dylib"
     } else {
         "libc.so.6"
     };
     if let Ok(lib) = unsafe { Library::new(default_lib_name) } {
         exports.set(
             "C",
-            SmartLibrary {
-                lib: Arc::new(lib),
-                name: "C".to_string(),
-            },
+            SmartLibrary::new(&lua, lib, "C".to_string(), default_lib_name.to_string())?,
         )?;
     }
 
@@ -187,29 +301,127 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
 /// Helper for Smart Library wrapper
 #[derive(Clone)]
 pub struct SmartLibrary {
-    lib: Arc<Library>,
+    /// The loaded library, `None` once unloaded
+    lib: Option<Arc<Library>>,
     name: String,
+    path: String,
+    /// Functions resolved so far, by name
+    functions: LuaTable,
+    /// The declaration each function in `functions` was resolved from, by name
+    versions: RefCell<HashMap<String, u64>>,
+}
+
+impl SmartLibrary {
+    fn new(lua: &Lua, lib: Library, name: String, path: String) -> LuaResult<Self> {
+        Ok(Self {
+            lib: Some(Arc::new(lib)),
+            name,
+            path,
+            functions: lua.create_table()?,
+            versions: RefCell::default(),
+        })
+    }
+
+    fn library(&self) -> LuaResult<&Arc<Library>> {
+        self.lib
+            .as_ref()
+            .ok_or_else(|| LuaError::external(format!("Library '{}' has been unloaded", self.name)))
+    }
+
+    /// Resolve a declared function, creating a CachedFunction with a pre-prepared CIF
+    fn resolve(&self, lua: &Lua, func_name: &str) -> LuaResult<Option<LuaAnyUserData>> {
+        let lib = self.library()?;
+
+        // Functions declared again by cdef may have a new signature, so resolve them again
+        let Some(version) = registry::Registry::get().func_version(func_name) else {
+            return Ok(None);
+        };
+        if self.versions.borrow().get(func_name) == Some(&version)
+            && let Some(func) = self
+                .functions
+                .raw_get::<Option<LuaAnyUserData>>(func_name)?
+        {
+            return Ok(Some(func));
+        }
+
+        let (sig, version) = {
+            let registry = registry::Registry::get();
+            match (
+                registry.get_func(func_name),
+                registry.func_version(func_name),
+            ) {
+                (Some(sig), Some(version)) => (sig, version),
+                _ => return Ok(None),
+            }
+        };
+
+        // Resolve symbol once
+        let func_ptr = unsafe {
+            let sym: libloading::Symbol<*const std::ffi::c_void> =
+                lib.get(func_name.as_bytes()).map_err(|e| {
+                    LuaError::external(format!("Symbol '{}' not found: {}", func_name, e))
+                })?;
+            *sym as usize
+        };
+
+        let cached = call::CachedFunction::new(func_ptr, sig)
+            .map_err(LuaError::external)?
+            .with_library(Arc::clone(lib));
+        let func = lua.create_userdata(cached)?;
+        self.functions.raw_set(func_name, &func)?;
+        self.versions
+            .borrow_mut()
+            .insert(func_name.to_string(), version);
+        Ok(Some(func))
+    }
 }
 
 impl LuaUserData for SmartLibrary {
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // lib:hasSymbol(name) - Whether the library exports a symbol, declared or not
+        methods.add_method("hasSymbol", |_, this, name: String| {
+            let lib = this.library()?;
+            let sym = unsafe { lib.get::<*const std::ffi::c_void>(name.as_bytes()) };
+            Ok(sym.is_ok())
+        });
+
+        // lib:path() - The path the library was loaded from
+        methods.add_method("path", |_, this, ()| Ok(this.path.clone()));
+
+        // lib:unload() - Unload the library, once none of its functions are in use
+        methods.add_method_mut("unload", |_, this, ()| {
+            if this.name == "C" {
+                return Err(LuaError::external("The C library cannot be unloaded"));
+            }
+            let Some(lib) = this.lib.take() else {
+                return Ok(());
+            };
+
+            // Functions keep the library loaded, so stop referencing them here - any that
+            // are no longer referenced elsewhere release the library once they are collected
+            this.functions.clear()?;
+            this.versions.borrow_mut().clear();
+
+            match Arc::try_unwrap(lib) {
+                Ok(lib) => lib.close().map_err(|e| {
+                    LuaError::external(format!("Failed to unload library '{}': {e}", this.name))
+                }),
+                Err(lib) => {
+                    let in_use = Arc::strong_count(&lib) - 1;
+                    this.lib = Some(lib);
+                    Err(LuaError::external(format!(
+                        "Cannot unload library '{}', {in_use} reference(s) to it are still in use \
+                        - functions that are no longer referenced release it once they are garbage collected",
+                        this.name
+                    )))
+                }
+            }
+        });
+
         methods.add_meta_method(LuaMetaMethod::Index, |lua, this, func_name: String| {
             // 1. Check if function declared in registry
-            if let Some(sig) = registry::Registry::get().get_func(&func_name) {
-                // Resolve symbol once
-                let func_ptr = unsafe {
-                    let sym: libloading::Symbol<*const std::ffi::c_void> =
-                        this.lib.get(func_name.as_bytes()).map_err(|e| {
-                            LuaError::external(format!("Symbol '{}' not found: {}", func_name, e))
-                        })?;
-                    *sym as usize
-                };
-
-                // Create CachedFunction with pre-prepared CIF
-                let cached =
-                    call::CachedFunction::new(func_ptr, sig).map_err(LuaError::external)?;
-
-                return Ok(LuaValue::UserData(lua.create_userdata(cached)?));
+            if let Some(func) = this.resolve(lua, &func_name)? {
+                return Ok(LuaValue::UserData(func));
             }
 
             // 2. Constants / Global Variables? (TODO)
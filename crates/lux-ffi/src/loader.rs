@@ -0,0 +1,297 @@
+//! FFI Library Loading
+//!
+//! Finds dynamic libraries for `ffi.load`, spelling bare names the way each platform
+//! does - `libfoo.so.1`, `libfoo.dylib`, `Foo.framework/Foo`, `foo.dll` - and searching
+//! the paths given to it and in `LUX_FFI_PATH` before the system search path.
+
+use libloading::Library;
+use mlua::prelude::*;
+use std::path::{Path, PathBuf};
+
+/// Environment variable with more directories to search, separated the same as `PATH`
+pub const SEARCH_PATH_VAR: &str = "LUX_FFI_PATH";
+
+/// Directories that macOS keeps frameworks in, searched after any others
+const FRAMEWORK_DIRS: [&str; 2] = ["/Library/Frameworks", "/System/Library/Frameworks"];
+
+/// Options for `ffi.load(name, options)`
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Directories to search before those in `LUX_FFI_PATH`
+    pub paths: Vec<PathBuf>,
+    /// Whether the symbols of the library are available to libraries loaded after it
+    pub global: bool,
+    /// The version to load, such as `1` for `libfoo.so.1`
+    pub version: Option<String>,
+}
+
+impl FromLua for LoadOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let options = match value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::Table(t) => t,
+            other => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: other.type_name(),
+                    to: String::from("LoadOptions"),
+                    message: Some(String::from("expected a table of load options")),
+                });
+            }
+        };
+        let paths = options.get::<Option<Vec<String>>>("paths")?;
+        Ok(Self {
+            paths: paths.into_iter().flatten().map(PathBuf::from).collect(),
+            global: options.get::<Option<bool>>("global")?.unwrap_or(false),
+            version: options.get("version")?,
+        })
+    }
+}
+
+/// A path to try loading a library from
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Candidate {
+    path: PathBuf,
+    /// Whether the path was made by joining a search directory, and is only worth trying if it exists
+    searched: bool,
+}
+
+/// The file names a library may have on the given OS, in the order they are tried
+fn file_names(name: &str, version: Option<&str>, os: &str) -> Vec<String> {
+    let lib = if name.starts_with("lib") {
+        name.to_string()
+    } else {
+        format!("lib{name}")
+    };
+    match os {
+        "windows" => {
+            if has_extension(name, "dll") {
+                return vec![name.to_string()];
+            }
+            match version {
+                Some(version) => vec![format!("{name}-{version}.dll")],
+                None if name.starts_with("lib") => vec![format!("{name}.dll")],
+                // Libraries built with MinGW keep the prefix
+                None => vec![format!("{name}.dll"), format!("{lib}.dll")],
+            }
+        }
+        "macos" | "ios" => {
+            if has_extension(name, "dylib") {
+                return vec![name.to_string()];
+            }
+            match version {
+                Some(version) => vec![format!("{lib}.{version}.dylib")],
+                None => vec![format!("{lib}.dylib")],
+            }
+        }
+        _ => {
+            if name.contains(".so") {
+                return vec![name.to_string()];
+            }
+            match version {
+                Some(version) => vec![format!("{lib}.so.{version}")],
+                None => vec![format!("{lib}.so")],
+            }
+        }
+    }
+}
+
+fn has_extension(name: &str, extension: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Every path to try loading a library from, in order
+///
+/// Names with a directory in them are only tried as they are, and with the extension
+/// of the platform added. Bare names are looked for in every search directory first,
+/// then left to the system loader, and on macOS are also looked for as frameworks.
+fn candidates(name: &str, options: &LoadOptions, env_dirs: &[PathBuf], os: &str) -> Vec<Candidate> {
+    let version = options.version.as_deref();
+    let is_apple = matches!(os, "macos" | "ios");
+    let unsearched = |path: PathBuf| Candidate {
+        path,
+        searched: false,
+    };
+
+    let path = Path::new(name);
+    if path
+        .parent()
+        .is_some_and(|parent| !parent.as_os_str().is_empty())
+    {
+        let extension = match (os, version) {
+            ("windows", _) => "dll".to_string(),
+            ("macos" | "ios", Some(version)) => format!("{version}.dylib"),
+            ("macos" | "ios", None) => "dylib".to_string(),
+            (_, Some(version)) => format!("so.{version}"),
+            (_, None) => "so".to_string(),
+        };
+        let mut paths = vec![unsearched(path.to_path_buf())];
+        if path.extension().is_none() {
+            paths.push(unsearched(PathBuf::from(format!("{name}.{extension}"))));
+        }
+        return paths;
+    }
+
+    let dirs: Vec<&PathBuf> = options.paths.iter().chain(env_dirs).collect();
+    let framework = name.strip_suffix(".framework");
+    let mut paths = Vec::new();
+
+    if framework.is_none() {
+        let names = file_names(name, version, os);
+        for dir in &dirs {
+            paths.extend(names.iter().map(|file| Candidate {
+                path: dir.join(file),
+                searched: true,
+            }));
+        }
+        paths.extend(
+            names
+                .into_iter()
+                .map(|file| unsearched(PathBuf::from(file))),
+        );
+    }
+
+    if is_apple {
+        let framework = framework.unwrap_or(name);
+        let binary = match version {
+            Some(version) => format!("{framework}.framework/Versions/{version}/{framework}"),
+            None => format!("{framework}.framework/{framework}"),
+        };
+        for dir in dirs {
+            paths.push(Candidate {
+                path: dir.join(&binary),
+                searched: true,
+            });
+        }
+        // System frameworks may only exist in the shared cache of dyld, so are always tried
+        for dir in FRAMEWORK_DIRS {
+            paths.push(unsearched(Path::new(dir).join(&binary)));
+        }
+    }
+
+    paths
+}
+
+#[cfg(unix)]
+unsafe fn open(path: &Path, global: bool) -> Result<Library, libloading::Error> {
+    use libloading::os::unix::{self, RTLD_GLOBAL, RTLD_LAZY, RTLD_LOCAL};
+
+    let scope = if global { RTLD_GLOBAL } else { RTLD_LOCAL };
+    unix::Library::open(Some(path), RTLD_LAZY | scope).map(Library::from)
+}
+
+#[cfg(not(unix))]
+unsafe fn open(path: &Path, _global: bool) -> Result<Library, libloading::Error> {
+    // Symbols of every loaded library are always available to others on Windows
+    Library::new(path)
+}
+
+/// Load a library for `ffi.load`, returning it along with the path it was loaded from
+///
+/// Loading a library runs its initializers, which may do anything.
+///
+/// # Errors
+///
+/// Errors with every path that was tried, and why it failed, if none could be loaded.
+pub fn load(name: &str, options: &LoadOptions) -> Result<(Library, PathBuf), String> {
+    let env_dirs: Vec<PathBuf> = std::env::var_os(SEARCH_PATH_VAR)
+        .map(|paths| std::env::split_paths(&paths).collect())
+        .unwrap_or_default();
+
+    let mut tried = Vec::new();
+    for candidate in candidates(name, options, &env_dirs, std::env::consts::OS) {
+        if candidate.searched && !candidate.path.exists() {
+            tried.push(format!("{}: not found", candidate.path.display()));
+            continue;
+        }
+        match unsafe { open(&candidate.path, options.global) } {
+            Ok(lib) => return Ok((lib, candidate.path)),
+            Err(e) => tried.push(format!("{}: {e}", candidate.path.display())),
+        }
+    }
+
+    Err(format!(
+        "Failed to load library '{name}', tried:\n  {}",
+        tried.join("\n  ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(name: &str, options: &LoadOptions, os: &str) -> Vec<String> {
+        let env_dirs = [PathBuf::from("/env")];
+        candidates(name, options, &env_dirs, os)
+            .into_iter()
+            .map(|candidate| candidate.path.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn spells_bare_names_per_platform() {
+        let options = LoadOptions {
+            paths: vec![PathBuf::from("/opt/lib")],
+            ..LoadOptions::default()
+        };
+        assert_eq!(
+            paths("foo", &options, "linux"),
+            ["/opt/lib/libfoo.so", "/env/libfoo.so", "libfoo.so"]
+        );
+        assert_eq!(
+            paths("libm.so.6", &LoadOptions::default(), "linux"),
+            ["/env/libm.so.6", "libm.so.6"]
+        );
+        assert_eq!(
+            paths("foo", &LoadOptions::default(), "windows"),
+            ["/env/foo.dll", "/env/libfoo.dll", "foo.dll", "libfoo.dll"]
+        );
+        assert_eq!(
+            paths("Cocoa.framework", &LoadOptions::default(), "macos"),
+            [
+                "/env/Cocoa.framework/Cocoa",
+                "/Library/Frameworks/Cocoa.framework/Cocoa",
+                "/System/Library/Frameworks/Cocoa.framework/Cocoa"
+            ]
+        );
+        assert_eq!(
+            paths("foo", &LoadOptions::default(), "macos")[..2],
+            ["/env/libfoo.dylib", "libfoo.dylib"]
+        );
+    }
+
+    #[test]
+    fn spells_versions_per_platform() {
+        let options = LoadOptions {
+            version: Some("1".to_string()),
+            ..LoadOptions::default()
+        };
+        assert_eq!(
+            paths("foo", &options, "linux"),
+            ["/env/libfoo.so.1", "libfoo.so.1"]
+        );
+        assert_eq!(
+            paths("foo", &options, "windows"),
+            ["/env/foo-1.dll", "foo-1.dll"]
+        );
+        assert_eq!(paths("foo", &options, "macos")[1], "libfoo.1.dylib");
+        assert_eq!(
+            paths("foo", &options, "macos")[2],
+            "/env/foo.framework/Versions/1/foo"
+        );
+    }
+
+    #[test]
+    fn tries_paths_as_given() {
+        let options = LoadOptions::default();
+        assert_eq!(
+            paths("./build/foo", &options, "linux"),
+            ["./build/foo", "./build/foo.so"]
+        );
+        assert_eq!(
+            paths("C:/libs/foo.dll", &options, "windows"),
+            ["C:/libs/foo.dll"]
+        );
+    }
+}
@@ -2,13 +2,40 @@
 //!
 //! Handles allocation, pointers, and C data types.
 
-use crate::callback::FfiCallback;
+use crate::anchor;
+use crate::arena::Arena;
+use crate::bind;
+use crate::callback::{self, FfiCallback};
+use crate::debug::{self, Origin};
+use crate::mmap::{self, MemoryMap};
 use crate::registry::Registry;
-use crate::types::CType;
+use crate::safety::{self, Access, Region};
+use crate::template;
+use crate::types::{CType, Qualifiers};
 use mlua::prelude::*;
 use std::alloc::{Layout, alloc, dealloc};
 use std::ffi::{CStr, c_void};
 use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Total size of all memory currently owned by `CBox`es
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the total size of C memory currently allocated through `ffi.new` and friends
+pub fn allocated_bytes() -> usize {
+    ALLOCATED_BYTES.load(Ordering::Relaxed)
+}
+
+/// Counts C memory allocated outside of `CBox`, such as arena chunks
+pub(crate) fn track_allocation(size: usize) {
+    ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
+}
+
+/// Counts C memory freed outside of `CBox`, such as arena chunks
+pub(crate) fn track_deallocation(size: usize) {
+    ALLOCATED_BYTES.fetch_sub(size, Ordering::Relaxed);
+}
 
 /// Common trait for C data wrappers
 pub trait CData {
@@ -21,7 +48,10 @@ pub struct CBox {
     ptr: *mut c_void,
     size: usize,
     pub ctype: CType,
-    owned: bool, // If true, we free on drop
+    owned: bool,                 // If true, we free on drop
+    region: Option<Region>,      // The allocation this points into, for safe mode
+    origin: Option<Arc<Origin>>, // Where this was created, in debug mode
+    const_target: bool,          // If true, what this refers to was declared const
 }
 
 impl CBox {
@@ -33,12 +63,16 @@ impl CBox {
             let layout = Layout::from_size_align(size, align).unwrap();
             let ptr = alloc(layout).cast();
             ptr::write_bytes(ptr, 0, size); // Zero initialize by default
+            ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
 
             Self {
                 ptr,
                 size,
                 ctype,
                 owned: true,
+                region: Some(Region::new(ptr, size)),
+                origin: None,
+                const_target: false,
             }
         }
     }
@@ -49,9 +83,54 @@ impl CBox {
             size: ctype.size(),
             ctype,
             owned,
+            region: None,
+            origin: None,
+            const_target: false,
         }
     }
 
+    /// Marks this cdata as pointing into the given allocation
+    #[must_use]
+    pub fn with_region(mut self, region: Option<Region>) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// The allocation this cdata points into, if it is known
+    #[must_use]
+    pub fn region(&self) -> Option<Region> {
+        self.region
+    }
+
+    /// Marks this cdata as referring to memory that was declared `const`
+    #[must_use]
+    pub fn with_const_target(mut self, const_target: bool) -> Self {
+        self.const_target = const_target;
+        self
+    }
+
+    /// Whether this cdata refers to memory that was declared `const`, like a `const char*`
+    #[must_use]
+    pub fn is_const_target(&self) -> bool {
+        self.const_target
+    }
+
+    /// Where this cdata was created, if it was created in debug mode
+    #[must_use]
+    pub fn origin(&self) -> Option<&Arc<Origin>> {
+        self.origin.as_ref()
+    }
+
+    pub(crate) fn set_origin(&mut self, origin: Option<Arc<Origin>>) {
+        self.origin = origin;
+    }
+
+    /// Whether this cdata frees its memory when it is dropped
+    #[must_use]
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
     pub fn as_ptr(&self) -> *mut c_void {
         self.ptr
     }
@@ -62,10 +141,12 @@ impl Drop for CBox {
         if self.owned && !self.ptr.is_null() {
             let size = self.size.max(1);
             let align = self.ctype.align().max(1);
+            anchor::release_range(self.ptr, size);
             unsafe {
                 let layout = Layout::from_size_align(size, align).unwrap();
                 dealloc(self.ptr.cast(), layout);
             }
+            ALLOCATED_BYTES.fetch_sub(size, Ordering::Relaxed);
         }
     }
 }
@@ -84,6 +165,7 @@ impl LuaUserData for CBox {
         fields.add_field_method_get("ptr", |_, this| Ok(LuaLightUserData(this.ptr)));
     }
 
+    #[allow(clippy::too_many_lines)]
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
         // Pointer arithmetic and dereference
         methods.add_meta_method(LuaMetaMethod::Index, |lua, this, key: LuaValue| {
@@ -113,10 +195,17 @@ impl LuaUserData for CBox {
                 };
 
                 let offset = (idx as isize) * (stride as isize);
-                let ptr = unsafe { (this.ptr as *mut u8).offset(offset) as *mut c_void };
+                let ptr = (this.ptr as *mut u8).wrapping_offset(offset) as *mut c_void;
+                if safety::is_enabled(lua) {
+                    safety::check(ptr, stride, target_type.align(), this.region, Access::Read)?;
+                }
 
                 // Return reference/value depending on type
-                return unsafe { c_to_lua_at_ptr(lua, target_type, ptr) };
+                let value = unsafe { c_to_lua_at_ptr(lua, target_type, ptr) }?;
+                let source = || format!("{}[{idx}]", this.ctype.c_name());
+                debug::record_origin(lua, &value, source, this.origin.clone());
+                let value = inherit_const(value, target_type, Qualifiers::default(), this);
+                return Ok(inherit_region(value, target_type, this.region));
             } else if let LuaValue::String(s) = key {
                 // Handle struct field access
                 let field_name = s.to_str().map_err(LuaError::external)?;
@@ -129,27 +218,34 @@ impl LuaUserData for CBox {
                     Some(&this.ctype)
                 };
 
-                if let Some(match_type) = target_type {
-                    if let CType::Struct(struct_name) = match_type {
-                        let reg = Registry::get();
-                        if let Some(def) = reg.get_struct(struct_name) {
-                            if let Some(field) = def.field(&field_name) {
-                                let offset = field.offset as isize;
-                                let ptr = unsafe { this.ptr.offset(offset) };
-                                return unsafe { c_to_lua_at_ptr(lua, &field.ctype, ptr) };
-                            }
-                        }
-                    } else if let CType::Union(union_name) = match_type {
-                        // Union logic (same as struct but all offsets 0 usually, or defined in def)
-                        // Our StructDef handles unions too with offsets
-                        let reg = Registry::get();
-                        if let Some(def) = reg.get_struct(union_name) {
-                            if let Some(field) = def.field(&field_name) {
-                                let offset = field.offset as isize;
-                                let ptr = unsafe { this.ptr.offset(offset) };
-                                return unsafe { c_to_lua_at_ptr(lua, &field.ctype, ptr) };
-                            }
-                        }
+                if let Some(target_type) = target_type
+                    && safety::is_enabled(lua)
+                {
+                    let (size, align) = (target_type.size(), target_type.align());
+                    safety::check(this.ptr, size, align, this.region, Access::Read)?;
+                }
+
+                let source = || format!("{}.{field_name}", this.ctype.c_name());
+
+                // Bound structs error on unknown fields
+                if let Some(def) = bind::bound_def(&this.ctype) {
+                    let value = bind::get_field(lua, &def, this.ptr, &field_name)?;
+                    debug::record_origin(lua, &value, source, this.origin.clone());
+                    mark_const_target(&value, this.const_target);
+                    return Ok(inherit_nested_region(value, this.region));
+                }
+
+                if let Some(CType::Struct(struct_name) | CType::Union(struct_name)) = target_type {
+                    // Our StructDef handles unions too, with all offsets 0
+                    // NOTE: The definition is shared so that the registry is not locked while
+                    // converting, which looks up the sizes of nested structs
+                    let def = Registry::get().get_struct_shared(struct_name);
+                    if let Some(field) = def.as_deref().and_then(|def| def.field(&field_name)) {
+                        let ptr = unsafe { this.ptr.add(field.offset) };
+                        let value = unsafe { c_to_lua_at_ptr(lua, &field.ctype, ptr) }?;
+                        debug::record_origin(lua, &value, source, this.origin.clone());
+                        let value = inherit_const(value, &field.ctype, field.qualifiers, this);
+                        return Ok(inherit_region(value, &field.ctype, this.region));
                     }
                 }
             }
@@ -159,7 +255,7 @@ impl LuaUserData for CBox {
 
         methods.add_meta_method(
             LuaMetaMethod::NewIndex,
-            |_, this, (key, value): (LuaValue, LuaValue)| {
+            |lua, this, (key, value): (LuaValue, LuaValue)| {
                 let idx = if let LuaValue::Integer(i) = key {
                     Some(i as isize)
                 } else if let LuaValue::Number(n) = key {
@@ -182,8 +278,22 @@ impl LuaUserData for CBox {
                     };
 
                     let offset = (idx as isize) * (stride as isize);
-                    let ptr = unsafe { (this.ptr as *mut u8).offset(offset) as *mut c_void };
+                    let ptr = (this.ptr as *mut u8).wrapping_offset(offset) as *mut c_void;
+                    if safety::is_enabled(lua) {
+                        let align = target_type.align();
+                        safety::check(ptr, stride, align, this.region, Access::Write)?;
+                        if this.const_target {
+                            safety::warn_const_write(lua, || {
+                                format!(
+                                    "'{}' through '{}'",
+                                    target_type.c_name(),
+                                    this.ctype.c_name()
+                                )
+                            });
+                        }
+                    }
                     // Set value
+                    let value = callback::store_in_slot(lua, target_type, ptr, value)?;
                     return unsafe { lua_to_c_at_ptr(target_type, ptr, value) }
                         .map_err(LuaError::external);
                 } else if let LuaValue::String(s) = key {
@@ -196,24 +306,44 @@ impl LuaUserData for CBox {
                         Some(&this.ctype)
                     };
 
-                    if let Some(match_type) = target_type {
-                        let reg = Registry::get();
-                        // Handle Struct and Union
-                        let struct_name = match match_type {
-                            CType::Struct(n) => Some(n),
-                            CType::Union(n) => Some(n),
-                            _ => None,
-                        };
+                    let safe_mode = safety::is_enabled(lua);
+                    if let Some(target_type) = target_type
+                        && safe_mode
+                    {
+                        let (size, align) = (target_type.size(), target_type.align());
+                        safety::check(this.ptr, size, align, this.region, Access::Write)?;
+                    }
+                    let warn_const = |is_const: bool| {
+                        if safe_mode && is_const {
+                            let owner = target_type.unwrap_or(&this.ctype).c_name();
+                            safety::warn_const_write(lua, || {
+                                format!("field '{field_name}' of '{owner}'")
+                            });
+                        }
+                    };
 
-                        if let Some(name) = struct_name {
-                            if let Some(def) = reg.get_struct(name) {
-                                if let Some(field) = def.field(&field_name) {
-                                    let offset = field.offset as isize;
-                                    let ptr = unsafe { this.ptr.offset(offset) };
-                                    return unsafe { lua_to_c_at_ptr(&field.ctype, ptr, value) }
-                                        .map_err(LuaError::external);
-                                }
+                    // Bound structs type-check the assigned value
+                    if let Some(def) = bind::bound_def(&this.ctype) {
+                        warn_const(this.const_target);
+                        let value = match def.field(&field_name) {
+                            Some(field) => {
+                                let ptr = unsafe { this.ptr.add(field.offset) };
+                                callback::store_in_slot(lua, &field.ctype, ptr, value)?
                             }
+                            None => value,
+                        };
+                        return bind::set_field(&def, this.ptr, &field_name, value);
+                    }
+
+                    // Handle Struct and Union
+                    if let Some(CType::Struct(name) | CType::Union(name)) = target_type {
+                        let def = Registry::get().get_struct_shared(name);
+                        if let Some(field) = def.as_deref().and_then(|def| def.field(&field_name)) {
+                            warn_const(this.const_target || field.qualifiers.is_const);
+                            let ptr = unsafe { this.ptr.add(field.offset) };
+                            let value = callback::store_in_slot(lua, &field.ctype, ptr, value)?;
+                            return unsafe { lua_to_c_at_ptr(&field.ctype, ptr, value) }
+                                .map_err(LuaError::external);
                         }
                     }
                 }
@@ -221,10 +351,34 @@ impl LuaUserData for CBox {
             },
         );
 
-        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+        methods.add_meta_method(LuaMetaMethod::ToString, |lua, this, ()| {
+            if let (CType::Struct(_) | CType::Union(_), Some(def)) =
+                (&this.ctype, bind::bound_def(&this.ctype))
+            {
+                return bind::format_struct(lua, &def, this.ptr);
+            }
+            if let Some(fields) = debug::verbose_fields(lua, this)? {
+                return Ok(format!("cdata<{:?}>: {:p} {fields}", this.ctype, this.ptr));
+            }
             Ok(format!("cdata<{:?}>: {:p}", this.ctype, this.ptr))
         });
 
+        // Bound structs compare by value, other cdata by address and type
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaAnyUserData| {
+            let Ok(other) = other.borrow::<CBox>() else {
+                return Ok(false);
+            };
+            if this.ctype != other.ctype {
+                return Ok(false);
+            }
+            if let (CType::Struct(_) | CType::Union(_), Some(def)) =
+                (&this.ctype, bind::bound_def(&this.ctype))
+            {
+                return Ok(bind::struct_eq(&def, this.ptr, other.ptr));
+            }
+            Ok(this.ptr == other.ptr)
+        });
+
         methods.add_meta_method(LuaMetaMethod::Call, |lua, this, args: LuaMultiValue| {
             // Check if it is a function pointer or function
 
@@ -273,9 +427,55 @@ impl LuaUserData for CBox {
 
 // Helpers for reading/writing memory at ptr based on type
 
+/// Cdata referencing memory inside of an allocation points into the same allocation,
+/// unlike pointers loaded from it, which may point anywhere
+fn inherit_region(value: LuaValue, ctype: &CType, region: Option<Region>) -> LuaValue {
+    if matches!(ctype, CType::Pointer(_) | CType::Function(_)) {
+        return value;
+    }
+    inherit_nested_region(value, region)
+}
+
+/// Cdata referencing memory inside of `const` memory can not be written through either,
+/// unlike pointers loaded from it, unless they were declared as pointers to `const`
+fn inherit_const(
+    value: LuaValue,
+    ctype: &CType,
+    qualifiers: Qualifiers,
+    parent: &CBox,
+) -> LuaValue {
+    let inherited = parent.const_target && !matches!(ctype, CType::Pointer(_) | CType::Function(_));
+    mark_const_target(&value, inherited || qualifiers.is_const_target(ctype));
+    value
+}
+
+/// Marks cdata as referring to `const` memory, so that writing through it warns in safe mode
+pub(crate) fn mark_const_target(value: &LuaValue, is_const: bool) {
+    if is_const
+        && let LuaValue::UserData(ud) = value
+        && let Ok(mut cbox) = ud.borrow_mut::<CBox>()
+    {
+        cbox.const_target = true;
+    }
+}
+
+fn inherit_nested_region(value: LuaValue, region: Option<Region>) -> LuaValue {
+    if let (Some(region), LuaValue::UserData(ud)) = (region, &value)
+        && let Ok(mut cbox) = ud.borrow_mut::<CBox>()
+        && !matches!(cbox.ctype, CType::Pointer(_))
+    {
+        cbox.region = Some(region);
+    }
+    value
+}
+
 /// Convert C value at pointer to Lua value
 /// Creates proper CBox userdata for aggregate types and pointers
-unsafe fn c_to_lua_at_ptr(lua: &Lua, ctype: &CType, ptr: *mut c_void) -> LuaResult<LuaValue> {
+pub(crate) unsafe fn c_to_lua_at_ptr(
+    lua: &Lua,
+    ctype: &CType,
+    ptr: *mut c_void,
+) -> LuaResult<LuaValue> {
     if ptr.is_null() {
         return Ok(LuaValue::Nil);
     }
@@ -311,6 +511,25 @@ unsafe fn c_to_lua_at_ptr(lua: &Lua, ctype: &CType, ptr: *mut c_void) -> LuaResu
 
         CType::Float => Ok(LuaValue::Number(*(ptr as *const f32) as f64)),
         CType::Double => Ok(LuaValue::Number(*(ptr as *const f64))),
+        CType::LongDouble => Ok(LuaValue::Number(crate::float::read_long_double(ptr))),
+        CType::Half => Ok(LuaValue::Number(f64::from(crate::float::f16_to_f32(
+            *(ptr as *const u16),
+        )))),
+
+        CType::Pointer(Some(inner)) if matches!(inner.as_ref(), CType::Function(_)) => {
+            c_to_lua_at_ptr(lua, inner, ptr)
+        }
+
+        CType::Function(_) => {
+            let val = *(ptr as *const *mut c_void);
+            if val.is_null() {
+                return Ok(LuaValue::Nil);
+            }
+
+            // Function pointers become cdata of the function itself, which can be called
+            let cbox = CBox::from_raw(val, ctype.clone(), false);
+            lua.create_userdata(cbox).map(LuaValue::UserData)
+        }
 
         CType::Pointer(_) => {
             let val = *(ptr as *const *mut c_void);
@@ -336,12 +555,6 @@ unsafe fn c_to_lua_at_ptr(lua: &Lua, ctype: &CType, ptr: *mut c_void) -> LuaResu
             lua.create_userdata(cbox).map(LuaValue::UserData)
         }
 
-        CType::Function(_) => {
-            // Function type - return as callable CBox
-            let cbox = CBox::from_raw(ptr, ctype.clone(), false);
-            lua.create_userdata(cbox).map(LuaValue::UserData)
-        }
-
         CType::GUID => {
             // GUID is a 16-byte struct, return as CBox
             let cbox = CBox::from_raw(ptr, ctype.clone(), false);
@@ -350,8 +563,30 @@ unsafe fn c_to_lua_at_ptr(lua: &Lua, ctype: &CType, ptr: *mut c_void) -> LuaResu
     }
 }
 
+/// Lua numbers may arrive as either integers or floats
+pub(crate) fn number_value(value: &LuaValue) -> f64 {
+    match value {
+        LuaValue::Integer(i) => *i as f64,
+        LuaValue::Number(n) => *n,
+        _ => 0.0,
+    }
+}
+
+/// Whether values of a type are aggregates, which are zeroed instead of assigned when not given
+fn is_aggregate(ctype: &CType) -> bool {
+    matches!(
+        ctype.resolve(),
+        CType::Struct(_) | CType::Union(_) | CType::Array(_, _) | CType::GUID
+    )
+}
+
 /// Write Lua value to C memory at pointer
-unsafe fn lua_to_c_at_ptr(ctype: &CType, ptr: *mut c_void, value: LuaValue) -> Result<(), String> {
+#[allow(clippy::too_many_lines)]
+pub(crate) unsafe fn lua_to_c_at_ptr(
+    ctype: &CType,
+    ptr: *mut c_void,
+    value: LuaValue,
+) -> Result<(), String> {
     if ptr.is_null() {
         return Err("Cannot write to null pointer".to_string());
     }
@@ -438,15 +673,27 @@ unsafe fn lua_to_c_at_ptr(ctype: &CType, ptr: *mut c_void, value: LuaValue) -> R
         }
 
         CType::Float => {
-            *(ptr as *mut f32) = value.as_f64().unwrap_or(0.0) as f32;
+            *ptr.cast::<f32>() = number_value(&value) as f32;
             Ok(())
         }
         CType::Double => {
-            *(ptr as *mut f64) = value.as_f64().unwrap_or(0.0);
+            *ptr.cast::<f64>() = number_value(&value);
+            Ok(())
+        }
+        CType::LongDouble => {
+            crate::float::write_long_double(ptr, number_value(&value));
+            Ok(())
+        }
+        CType::Half => {
+            *ptr.cast::<u16>() = crate::float::f32_to_f16(number_value(&value) as f32);
             Ok(())
         }
 
         CType::Pointer(_) | CType::Function(_) => {
+            // Anything replacing a string stored here frees the copy of it
+            if !value.is_string() {
+                anchor::release(ptr);
+            }
             let p = match &value {
                 LuaValue::Nil => ptr::null_mut(),
                 LuaValue::LightUserData(ud) => ud.0,
@@ -460,8 +707,8 @@ unsafe fn lua_to_c_at_ptr(ctype: &CType, ptr: *mut c_void, value: LuaValue) -> R
                         ptr::null_mut()
                     }
                 }
-                // String -> char* requires special handling (caller must keep string alive)
-                LuaValue::String(s) => s.as_bytes().as_ptr() as *mut c_void,
+                // Lua may collect the string at any time, so the pointer is to a copy of it
+                LuaValue::String(s) => anchor::store(ptr, &s.as_bytes()),
                 _ => ptr::null_mut(),
             };
             *(ptr as *mut *mut c_void) = p;
@@ -475,6 +722,11 @@ unsafe fn lua_to_c_at_ptr(ctype: &CType, ptr: *mut c_void, value: LuaValue) -> R
                     if let Ok(v) = t.get::<LuaValue>(i as i64 + 1) {
                         let offset = (i * elem_type.size()) as isize;
                         let elem_ptr = ptr.offset(offset);
+                        // Missing aggregate elements are zeroed, the same as missing scalars
+                        if v.is_nil() && is_aggregate(elem_type) {
+                            ptr::write_bytes(elem_ptr.cast::<u8>(), 0, elem_type.size());
+                            continue;
+                        }
                         lua_to_c_at_ptr(elem_type, elem_ptr, v)?;
                     }
                 }
@@ -494,11 +746,21 @@ unsafe fn lua_to_c_at_ptr(ctype: &CType, ptr: *mut c_void, value: LuaValue) -> R
         CType::Struct(_name) | CType::Union(_name) => {
             // Struct/union assignment from table or cdata
             if let LuaValue::Table(t) = &value {
-                let reg = Registry::get();
-                if let Some(def) = reg.get_struct(_name) {
-                    for field in &def.fields {
-                        if let Ok(v) = t.get::<LuaValue>(field.name.as_str()) {
+                let def = Registry::get().get_struct(_name).cloned();
+                if let Some(def) = def {
+                    for (i, field) in def.fields.iter().enumerate() {
+                        if let Ok(mut v) = t.get::<LuaValue>(field.name.as_str()) {
+                            // Fields may also be given in order, like `{ 5, 6 }`,
+                            // of which unions only take the first one
+                            if v.is_nil() && (i == 0 || !def.is_union) {
+                                v = t.get::<LuaValue>(i as i64 + 1).unwrap_or(LuaValue::Nil);
+                            }
                             let field_ptr = ptr.offset(field.offset as isize);
+                            // Missing aggregate fields are zeroed, the same as missing scalars
+                            if v.is_nil() && is_aggregate(&field.ctype) {
+                                ptr::write_bytes(field_ptr.cast::<u8>(), 0, field.ctype.size());
+                                continue;
+                            }
                             lua_to_c_at_ptr(&field.ctype, field_ptr, v)?;
                         }
                     }
@@ -531,7 +793,7 @@ unsafe fn lua_to_c_at_ptr(ctype: &CType, ptr: *mut c_void, value: LuaValue) -> R
 // Module Functions
 
 pub fn ffi_new(lua: &Lua, args: LuaMultiValue) -> LuaResult<LuaValue> {
-    let args_vec: Vec<LuaValue> = args.into_iter().collect();
+    let mut args_vec: Vec<LuaValue> = args.into_iter().collect();
     if args_vec.is_empty() {
         return Err(LuaError::external("ffi.new expects at least 1 argument"));
     }
@@ -541,8 +803,43 @@ pub fn ffi_new(lua: &Lua, args: LuaMultiValue) -> LuaResult<LuaValue> {
         _ => return Err(LuaError::external("ffi.new: type must be a string")),
     };
 
-    let ctype = CType::parse(&type_name)
-        .ok_or_else(|| LuaError::external(format!("Unknown type: {}", type_name)))?;
+    // ffi.new(type, init, arena) - Place the value in an arena instead of allocating it
+    let arena = match args_vec.last() {
+        Some(LuaValue::UserData(ud)) if args_vec.len() > 1 && ud.is::<Arena>() => Some(ud.clone()),
+        _ => None,
+    };
+    if arena.is_some() {
+        args_vec.pop();
+    }
+    let init = args_vec.into_iter().nth(1);
+
+    match arena {
+        Some(arena) => {
+            let mut arena = arena.borrow_mut::<Arena>()?;
+            new_cdata(lua, &type_name, init, Some(&mut *arena))
+        }
+        None => new_cdata(lua, &type_name, init, None),
+    }
+}
+
+/// Create a cdata value, allocated on its own or placed in an arena
+pub(crate) fn new_cdata(
+    lua: &Lua,
+    type_name: &str,
+    mut init: Option<LuaValue>,
+    arena: Option<&mut Arena>,
+) -> LuaResult<LuaValue> {
+    let ctype = match type_name.trim().strip_suffix("[?]") {
+        // Variable-length arrays take their length in place of an initializer
+        Some(elem) => {
+            let elem = CType::parse(elem)
+                .ok_or_else(|| LuaError::external(format!("Unknown type: {}", type_name)))?;
+            let count = vla_length(type_name, init.take())?;
+            CType::Array(Box::new(elem), count)
+        }
+        None => CType::parse(type_name)
+            .ok_or_else(|| LuaError::external(format!("Unknown type: {}", type_name)))?,
+    };
 
     // Allocate
     if ctype.size() == 0 {
@@ -551,30 +848,129 @@ pub fn ffi_new(lua: &Lua, args: LuaMultiValue) -> LuaResult<LuaValue> {
             type_name
         )));
     }
-    let cbox = CBox::new(ctype.clone());
+    let creator = if arena.is_some() {
+        "arena:new"
+    } else {
+        "ffi.new"
+    };
+    let source = || format!("{creator}(\"{type_name}\")");
+    let cdata = match arena {
+        Some(arena) => arena.place(lua, ctype.clone())?,
+        None => lua.create_userdata(CBox::new(ctype.clone()))?,
+    };
+    cdata
+        .borrow_mut::<CBox>()?
+        .set_origin(debug::origin(lua, source, None));
 
-    // Initialize if init value provided
-    if args_vec.len() > 1 {
-        // TODO: array initialization, struct initialization
-        // For now, simple primitive init
-        unsafe {
-            let _ = lua_to_c_at_ptr(&ctype, cbox.ptr, args_vec[1].clone());
-        }
+    let ptr = cdata.borrow::<CBox>()?.ptr;
+    unsafe { init_cdata(&ctype, ptr, init.into_iter().collect()) }.map_err(LuaError::external)?;
+
+    Ok(LuaValue::UserData(cdata))
+}
+
+/// The length given for a variable-length array such as `int[?]`
+fn vla_length(type_name: &str, length: Option<LuaValue>) -> LuaResult<usize> {
+    match length {
+        Some(LuaValue::Integer(n)) if n >= 0 => Ok(n as usize),
+        Some(LuaValue::Number(n)) if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+        _ => Err(LuaError::external(format!(
+            "'{type_name}' needs a length that is a non-negative integer"
+        ))),
     }
+}
 
-    lua.create_userdata(cbox).map(LuaValue::UserData)
+/// Initialize zeroed memory from the values given to a ctype constructor, following LuaJIT
+///
+/// A single table or cdata initializes the whole value, and a single number every element
+/// of an array. Several values initialize the elements of an array or the fields of a
+/// struct in order, and only the first field of a union. Anything not given stays zeroed.
+pub(crate) unsafe fn init_cdata(
+    ctype: &CType,
+    ptr: *mut c_void,
+    inits: Vec<LuaValue>,
+) -> Result<(), String> {
+    let too_many = |count: usize| {
+        format!(
+            "Too many initializers for '{}', got {} but it takes {count}",
+            ctype.c_name(),
+            inits.len()
+        )
+    };
+    let resolved = ctype.resolve();
+    match (&resolved, inits.as_slice()) {
+        (_, []) => Ok(()),
+        (_, [LuaValue::Table(_) | LuaValue::UserData(_)]) => {
+            lua_to_c_at_ptr(ctype, ptr, inits[0].clone())
+        }
+        // Byte arrays take the bytes of a string, and its terminator if there is room for it
+        (CType::Array(elem, count), [LuaValue::String(s)]) if elem.size() == 1 => {
+            let bytes = s.as_bytes();
+            if bytes.len() > *count {
+                return Err(format!(
+                    "String of {} bytes does not fit in '{}'",
+                    bytes.len(),
+                    ctype.c_name()
+                ));
+            }
+            ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.cast::<u8>(), bytes.len());
+            Ok(())
+        }
+        (CType::Array(elem, count), [value]) => {
+            for i in 0..*count {
+                lua_to_c_at_ptr(elem, ptr.add(i * elem.size()), value.clone())?;
+            }
+            Ok(())
+        }
+        (CType::Array(elem, count), values) => {
+            if values.len() > *count {
+                return Err(too_many(*count));
+            }
+            for (i, value) in values.iter().enumerate() {
+                lua_to_c_at_ptr(elem, ptr.add(i * elem.size()), value.clone())?;
+            }
+            Ok(())
+        }
+        (CType::Struct(name) | CType::Union(name), values) => {
+            let def = Registry::get()
+                .get_struct(name)
+                .cloned()
+                .ok_or_else(|| format!("Struct '{name}' not found"))?;
+            let count = if def.is_union {
+                def.fields.len().min(1)
+            } else {
+                def.fields.len()
+            };
+            if values.len() > count {
+                return Err(too_many(count));
+            }
+            for (field, value) in def.fields.iter().zip(values) {
+                lua_to_c_at_ptr(&field.ctype, ptr.add(field.offset), value.clone())?;
+            }
+            Ok(())
+        }
+        (_, [value]) => lua_to_c_at_ptr(ctype, ptr, value.clone()),
+        (_, _) => Err(too_many(1)),
+    }
 }
 
 pub fn ffi_cast(lua: &Lua, ctype_str: String, value: LuaValue) -> LuaResult<LuaValue> {
-    let ctype = CType::parse(&ctype_str)
+    let (ctype, qualifiers) = CType::parse_qualified(&ctype_str)
         .ok_or_else(|| LuaError::external(format!("Unknown type: {}", ctype_str)))?;
+    // Function pointers become cdata of the function itself, the same as when read from memory,
+    // so that casting an address to a function pointer type calls the code at that address
+    let ctype = match ctype {
+        CType::Pointer(Some(inner)) if matches!(inner.as_ref(), CType::Function(_)) => *inner,
+        ctype => ctype,
+    };
 
-    let ptr = match value {
+    let ptr = match &value {
         LuaValue::LightUserData(ud) => ud.0,
-        LuaValue::Integer(i) => i as *mut c_void,
+        LuaValue::Integer(i) => *i as *mut c_void,
         LuaValue::UserData(ud) => {
             if let Ok(b) = ud.borrow::<CBox>() {
                 b.ptr
+            } else if let Ok(map) = ud.borrow::<MemoryMap>() {
+                map.ptr()?
             } else {
                 ptr::null_mut()
             }
@@ -582,9 +978,18 @@ pub fn ffi_cast(lua: &Lua, ctype_str: String, value: LuaValue) -> LuaResult<LuaV
         _ => ptr::null_mut(),
     };
 
-    // Create a non-owned CBox (reference)
-    let cbox = CBox::from_raw(ptr, ctype, false);
-    lua.create_userdata(cbox).map(LuaValue::UserData)
+    debug::log_cast(lua, &value, &ctype);
+
+    // Create a non-owned CBox (reference), which still points into the same allocation
+    let const_target = qualifiers.is_const_target(&ctype);
+    let mut cbox = CBox::from_raw(ptr, ctype, false)
+        .with_region(safety::region_of(&value))
+        .with_const_target(const_target);
+    let source = || format!("ffi.cast(\"{ctype_str}\")");
+    cbox.set_origin(debug::origin(lua, source, debug::origin_of(&value)));
+    let cdata = lua.create_userdata(cbox)?;
+    mmap::keep_alive(&value, &cdata)?;
+    Ok(LuaValue::UserData(cdata))
 }
 
 pub fn ffi_string(lua: &Lua, args: LuaMultiValue) -> LuaResult<LuaValue> {
@@ -619,6 +1024,17 @@ pub fn ffi_string(lua: &Lua, args: LuaMultiValue) -> LuaResult<LuaValue> {
         None
     };
 
+    let len = if safety::is_enabled(lua) {
+        let region = safety::region_of(&args_vec[0]);
+        match len {
+            Some(len) => safety::check(ptr, len, 1, region, Access::Read).map(|()| len),
+            None => safety::c_string_len(ptr, region),
+        }
+        .map(Some)?
+    } else {
+        len
+    };
+
     unsafe {
         if let Some(l) = len {
             let slice = std::slice::from_raw_parts(ptr as *const u8, l);
@@ -630,11 +1046,62 @@ pub fn ffi_string(lua: &Lua, args: LuaMultiValue) -> LuaResult<LuaValue> {
     }
 }
 
-pub fn ffi_copy(_lua: &Lua, (dst, src, len): (LuaValue, LuaValue, Option<usize>)) -> LuaResult<()> {
+/// ffi.newstring(str) - A null-terminated copy of a string, as a `char[?]` owned by the cdata
+pub fn ffi_newstring(lua: &Lua, s: LuaString) -> LuaResult<LuaValue> {
+    let bytes = s.as_bytes();
+    let mut cbox = CBox::new(CType::Array(Box::new(CType::Char), bytes.len() + 1));
+    unsafe {
+        ptr::copy_nonoverlapping(bytes.as_ptr(), cbox.ptr.cast::<u8>(), bytes.len());
+    }
+    let source = || "ffi.newstring()".to_string();
+    cbox.set_origin(debug::origin(lua, source, None));
+    lua.create_userdata(cbox).map(LuaValue::UserData)
+}
+
+/**
+    Frees the copies of strings that were assigned to pointers within the memory at `target`,
+    for `ffi.releaseStrings(target, size)`.
+
+    The memory spans `size` bytes, or when `target` is a cdata without a size, what it
+    points to, or the cdata itself if it is not a pointer. Copies in memory allocated by
+    Lux are freed along with it, this is only needed for memory allocated by C code.
+
+    # Errors
+
+    Errors if `target` is not a pointer, or has no size and none is given.
+*/
+pub fn ffi_release_strings(
+    _lua: &Lua,
+    (target, size): (LuaValue, Option<usize>),
+) -> LuaResult<usize> {
+    let ptr = get_ptr_from_value(&target)?;
+    if ptr.is_null() {
+        return Err(LuaError::external("ffi.releaseStrings: null pointer"));
+    }
+    let size = match (size, &target) {
+        (Some(size), _) => size,
+        (None, LuaValue::UserData(ud)) if ud.is::<CBox>() => {
+            let cbox = ud.borrow::<CBox>()?;
+            match &cbox.ctype {
+                CType::Pointer(Some(inner)) => inner.size(),
+                _ => cbox.size,
+            }
+        }
+        _ => {
+            return Err(LuaError::external(
+                "ffi.releaseStrings: a size is needed for anything but CData",
+            ));
+        }
+    };
+    Ok(anchor::release_range(ptr, size))
+}
+
+pub fn ffi_copy(lua: &Lua, (dst, src, len): (LuaValue, LuaValue, Option<usize>)) -> LuaResult<()> {
     let dst_ptr = get_ptr_from_value(&dst)?;
     let src_ptr = get_ptr_from_value(&src)?;
 
-    if dst_ptr.is_null() || src_ptr.is_null() {
+    // Strings are copied from directly, and have no pointer
+    if dst_ptr.is_null() || (src_ptr.is_null() && !src.is_string()) {
         return Err(LuaError::external("ffi.copy: null pointer"));
     }
 
@@ -644,11 +1111,23 @@ pub fn ffi_copy(_lua: &Lua, (dst, src, len): (LuaValue, LuaValue, Option<usize>)
     // We strictly take len for now or default?
     let count = len.unwrap_or(0);
 
+    let safe = safety::is_enabled(lua);
+
     // Check if src is a lua string
     if let LuaValue::String(s) = src {
         unsafe {
             let bytes = s.as_bytes();
             let copy_len = len.unwrap_or(bytes.len());
+            if safe {
+                if copy_len > bytes.len() {
+                    return Err(LuaError::external(format!(
+                        "ffi.copy: cannot copy {copy_len} bytes from a string of {} bytes",
+                        bytes.len()
+                    )));
+                }
+                let region = safety::region_of(&dst);
+                safety::check(dst_ptr, copy_len, 1, region, Access::Write)?;
+            }
             ptr::copy_nonoverlapping(bytes.as_ptr(), dst_ptr as *mut u8, copy_len);
         }
         return Ok(());
@@ -658,17 +1137,25 @@ pub fn ffi_copy(_lua: &Lua, (dst, src, len): (LuaValue, LuaValue, Option<usize>)
         return Ok(());
     }
 
+    if safe {
+        safety::check(src_ptr, count, 1, safety::region_of(&src), Access::Read)?;
+        safety::check(dst_ptr, count, 1, safety::region_of(&dst), Access::Write)?;
+    }
+
     unsafe {
         ptr::copy_nonoverlapping(src_ptr as *const u8, dst_ptr as *mut u8, count);
     }
     Ok(())
 }
 
-pub fn ffi_fill(_lua: &Lua, (dst, len, byte): (LuaValue, usize, Option<u8>)) -> LuaResult<()> {
+pub fn ffi_fill(lua: &Lua, (dst, len, byte): (LuaValue, usize, Option<u8>)) -> LuaResult<()> {
     let dst_ptr = get_ptr_from_value(&dst)?;
     if dst_ptr.is_null() {
         return Err(LuaError::external("ffi.fill: null pointer"));
     }
+    if safety::is_enabled(lua) {
+        safety::check(dst_ptr, len, 1, safety::region_of(&dst), Access::Write)?;
+    }
     let val = byte.unwrap_or(0);
     unsafe {
         ptr::write_bytes(dst_ptr as *mut u8, val, len);
@@ -685,6 +1172,14 @@ pub fn get_ptr_from_value(val: &LuaValue) -> LuaResult<*mut c_void> {
                 Ok(b.ptr)
             } else if let Ok(cb) = ud.borrow::<FfiCallback>() {
                 Ok(cb.as_ptr())
+            } else if let Ok(obj) = ud.borrow::<crate::com::ComObject>() {
+                obj.as_ptr()
+            } else if let Ok(obj) = ud.borrow::<crate::objc::ObjcObject>() {
+                Ok(obj.as_ptr())
+            } else if let Ok(map) = ud.borrow::<MemoryMap>() {
+                map.ptr()
+            } else if let Some(out) = crate::out::out_param_ptr(val) {
+                Ok(out)
             } else {
                 Ok(ptr::null_mut())
             }
@@ -714,60 +1209,134 @@ pub fn ffi_metatype(_ctype: &str, _mt: LuaTable) -> LuaResult<()> {
     Ok(())
 }
 
-pub fn ffi_istype(ctype_str: &str, value: LuaValue) -> LuaResult<bool> {
-    if let LuaValue::UserData(ud) = value {
-        if let Ok(cbox) = ud.borrow::<CBox>() {
-            if let Some(expected) = CType::parse(ctype_str) {
-                return Ok(cbox.ctype == expected);
-            }
+pub fn ffi_istype(ctype: LuaValue, value: LuaValue) -> LuaResult<bool> {
+    let expected = match &ctype {
+        LuaValue::String(s) => match CType::parse(&s.to_str()?) {
+            Some(expected) => expected,
+            None => return Ok(false),
+        },
+        LuaValue::UserData(ud) if ud.is::<CTypeWrapper>() => {
+            ud.borrow::<CTypeWrapper>()?.ctype.clone()
+        }
+        other => {
+            return Err(LuaError::external(format!(
+                "ffi.istype: expected a type name or ctype, got {}",
+                other.type_name()
+            )));
         }
+    };
+
+    let LuaValue::UserData(ud) = value else {
+        return Ok(false);
+    };
+    let actual = if let Ok(cbox) = ud.borrow::<CBox>() {
+        cbox.ctype.clone()
+    } else if let Ok(wrapper) = ud.borrow::<CTypeWrapper>() {
+        wrapper.ctype.clone()
+    } else {
+        return Ok(false);
+    };
+
+    Ok(is_compatible(&expected.resolve(), &actual.resolve()))
+}
+
+/// Whether a value of one resolved type is an instance of another, following LuaJIT -
+/// `void*` is compatible with every pointer, and a struct with pointers to it
+fn is_compatible(expected: &CType, actual: &CType) -> bool {
+    match (expected, actual) {
+        _ if expected == actual => true,
+        (CType::Pointer(None), CType::Pointer(_)) | (CType::Pointer(_), CType::Pointer(None)) => {
+            true
+        }
+        (CType::Struct(_) | CType::Union(_), CType::Pointer(Some(inner))) => **inner == *expected,
+        _ => false,
     }
-    Ok(false)
 }
 
 /// CType wrapper for ffi.typeof - allows using ctype as constructor
 pub struct CTypeWrapper {
+    /// The type, or the element type of a variable-length array
     pub ctype: CType,
     pub name: String,
+    /// Whether this is a variable-length array such as `int[?]`, which is given its length when called
+    pub vla: bool,
 }
 
 impl LuaUserData for CTypeWrapper {
     fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
-        fields.add_field_method_get("size", |_, this| Ok(this.ctype.size()));
+        // Variable-length arrays have no size until they are given a length
+        fields.add_field_method_get("size", |_, this| Ok((!this.vla).then(|| this.ctype.size())));
         fields.add_field_method_get("align", |_, this| Ok(this.ctype.align()));
         fields.add_field_method_get("name", |_, this| Ok(this.name.clone()));
     }
 
     fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
-        // Call metamethod: allows ctype(init) syntax
+        // Call metamethod: allows ctype(init...) syntax, or ctype(length, init...) for variable-length arrays
         methods.add_meta_method(LuaMetaMethod::Call, |lua, this, args: LuaMultiValue| {
+            let mut args = args.into_iter();
+            let ctype = if this.vla {
+                let count = vla_length(&this.name, args.next())?;
+                CType::Array(Box::new(this.ctype.clone()), count)
+            } else {
+                this.ctype.clone()
+            };
+            if ctype.size() == 0 && !this.vla {
+                return Err(LuaError::external(format!(
+                    "cannot allocate incomplete type '{}'",
+                    this.name
+                )));
+            }
+
             // Create a new CBox with this type
-            let cbox = CBox::new(this.ctype.clone());
+            let mut cbox = CBox::new(ctype.clone());
+            cbox.set_origin(debug::origin(
+                lua,
+                || format!("ctype<{}>()", this.name),
+                None,
+            ));
 
-            // Initialize if init value provided
-            let args_vec: Vec<LuaValue> = args.into_iter().collect();
-            if !args_vec.is_empty() {
-                unsafe {
-                    let _ = lua_to_c_at_ptr(&this.ctype, cbox.ptr, args_vec[0].clone());
-                }
-            }
+            unsafe { init_cdata(&ctype, cbox.ptr, args.collect()) }.map_err(LuaError::external)?;
 
             lua.create_userdata(cbox).map(LuaValue::UserData)
         });
 
+        // ctype:fields() - The layout of every field of a struct or union, as in ffi.structinfo
+        methods.add_method("fields", |lua, this, ()| {
+            let resolved = this.ctype.resolve();
+            let def = match &resolved {
+                CType::Struct(name) | CType::Union(name) if !this.vla => {
+                    Registry::get().get_struct(name).cloned()
+                }
+                _ => None,
+            };
+            match def {
+                Some(def) => crate::introspect::fields_table(lua, &def),
+                None => Err(LuaError::external(format!(
+                    "'{}' is not a struct or union",
+                    this.name
+                ))),
+            }
+        });
+
         methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
             Ok(format!("ctype<{}>", this.name))
         });
     }
 }
 
-pub fn ffi_typeof(lua: &Lua, ctype_str: String) -> LuaResult<LuaValue> {
-    let ctype = CType::parse(&ctype_str)
-        .ok_or_else(|| LuaError::external(format!("Unknown type: {}", ctype_str)))?;
+/// `ffi.typeof(type, params...)` - A ctype, with every `$` in the type replaced by the next parameter
+pub fn ffi_typeof(lua: &Lua, (ctype_str, params): (String, LuaMultiValue)) -> LuaResult<LuaValue> {
+    let parameterized = !params.is_empty();
+    let declared = template::parse(&ctype_str, params)?;
 
     let wrapper = CTypeWrapper {
-        ctype,
-        name: ctype_str,
+        name: if parameterized {
+            declared.c_name()
+        } else {
+            ctype_str
+        },
+        ctype: declared.ctype,
+        vla: declared.vla,
     };
 
     lua.create_userdata(wrapper).map(LuaValue::UserData)
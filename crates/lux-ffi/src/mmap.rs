@@ -0,0 +1,574 @@
+//! FFI Memory Mapping
+//!
+//! Files and anonymous memory mapped into the process (`mmap` / `VirtualAlloc`),
+//! with control over page protection - including executable pages for generated code.
+
+use crate::memory::CBox;
+use crate::safety::Region;
+use crate::types::CType;
+use mlua::prelude::*;
+use std::ffi::c_void;
+use std::fs::File;
+use std::sync::Arc;
+
+/// Which accesses pages allow, from a table such as `{ read = true, exec = true }`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protection {
+    pub read: bool,
+    pub write: bool,
+    pub exec: bool,
+}
+
+impl Default for Protection {
+    fn default() -> Self {
+        Self {
+            read: true,
+            write: true,
+            exec: false,
+        }
+    }
+}
+
+impl FromLua for Protection {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                read: t.get::<Option<bool>>("read")?.unwrap_or(false),
+                write: t.get::<Option<bool>>("write")?.unwrap_or(false),
+                exec: t.get::<Option<bool>>("exec")?.unwrap_or(false),
+            }),
+            other => Err(LuaError::external(format!(
+                "ffi.mmap: protection must be a table such as {{ read = true, write = true }}, got {}",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+impl IntoLua for Protection {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let t = lua.create_table()?;
+        t.set("read", self.read)?;
+        t.set("write", self.write)?;
+        t.set("exec", self.exec)?;
+        Ok(LuaValue::Table(t))
+    }
+}
+
+impl std::fmt::Display for Protection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flag = |set: bool, c: char| if set { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}",
+            flag(self.read, 'r'),
+            flag(self.write, 'w'),
+            flag(self.exec, 'x')
+        )
+    }
+}
+
+/// A mapped region of memory, unmapped when the last reference is dropped
+struct Mapping {
+    ptr: *mut c_void,
+    size: usize,
+    /// The mapped file, `None` for anonymous memory
+    file: Option<File>,
+    #[cfg(windows)]
+    handle: windows_sys::Win32::Foundation::HANDLE,
+}
+
+// The mapping is plain process-wide memory, access is synchronized by the user
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+#[cfg(unix)]
+impl Mapping {
+    fn prot(protection: Protection) -> i32 {
+        let mut prot = libc::PROT_NONE;
+        if protection.read {
+            prot |= libc::PROT_READ;
+        }
+        if protection.write {
+            prot |= libc::PROT_WRITE;
+        }
+        if protection.exec {
+            prot |= libc::PROT_EXEC;
+        }
+        prot
+    }
+
+    fn anonymous(size: usize, protection: Protection) -> LuaResult<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                Self::prot(protection),
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(last_error("map anonymous memory"));
+        }
+        Ok(Self {
+            ptr,
+            size,
+            file: None,
+        })
+    }
+
+    fn file(file: File, size: usize, protection: Protection) -> LuaResult<Self> {
+        use std::os::fd::AsRawFd;
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                Self::prot(protection),
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(last_error("map file"));
+        }
+        Ok(Self {
+            ptr,
+            size,
+            file: Some(file),
+        })
+    }
+
+    fn protect(&self, protection: Protection) -> LuaResult<()> {
+        if unsafe { libc::mprotect(self.ptr, self.size, Self::prot(protection)) } != 0 {
+            return Err(last_error("change protection"));
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> LuaResult<()> {
+        if self.file.is_some() && unsafe { libc::msync(self.ptr, self.size, libc::MS_SYNC) } != 0 {
+            return Err(last_error("flush"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.size);
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Mapping {
+    fn page_protection(protection: Protection) -> u32 {
+        use windows_sys::Win32::System::Memory::{
+            PAGE_EXECUTE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_NOACCESS, PAGE_READONLY,
+            PAGE_READWRITE,
+        };
+
+        // NOTE: Windows has no write-only pages, writable pages are always readable too
+        match (protection.read, protection.write, protection.exec) {
+            (_, true, true) => PAGE_EXECUTE_READWRITE,
+            (true, false, true) => PAGE_EXECUTE_READ,
+            (false, false, true) => PAGE_EXECUTE,
+            (_, true, false) => PAGE_READWRITE,
+            (true, false, false) => PAGE_READONLY,
+            (false, false, false) => PAGE_NOACCESS,
+        }
+    }
+
+    fn anonymous(size: usize, protection: Protection) -> LuaResult<Self> {
+        use windows_sys::Win32::System::Memory::{MEM_COMMIT, MEM_RESERVE, VirtualAlloc};
+
+        let ptr = unsafe {
+            VirtualAlloc(
+                std::ptr::null(),
+                size,
+                MEM_COMMIT | MEM_RESERVE,
+                Self::page_protection(protection),
+            )
+        };
+        if ptr.is_null() {
+            return Err(last_error("allocate virtual memory"));
+        }
+        Ok(Self {
+            ptr,
+            size,
+            file: None,
+            handle: std::ptr::null_mut(),
+        })
+    }
+
+    fn file(file: File, size: usize, protection: Protection) -> LuaResult<Self> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Memory::{
+            CreateFileMappingW, FILE_MAP_EXECUTE, FILE_MAP_READ, FILE_MAP_WRITE, MapViewOfFile,
+        };
+
+        let size64 = size as u64;
+        let mut access = 0;
+        if protection.read {
+            access |= FILE_MAP_READ;
+        }
+        if protection.write {
+            access |= FILE_MAP_WRITE;
+        }
+        if protection.exec {
+            access |= FILE_MAP_EXECUTE;
+        }
+        unsafe {
+            let handle = CreateFileMappingW(
+                file.as_raw_handle() as _,
+                std::ptr::null(),
+                Self::page_protection(protection),
+                (size64 >> 32) as u32,
+                size64 as u32,
+                std::ptr::null(),
+            );
+            if handle.is_null() {
+                return Err(last_error("map file"));
+            }
+            let view = MapViewOfFile(handle, access, 0, 0, size);
+            if view.Value.is_null() {
+                let err = last_error("map file");
+                CloseHandle(handle);
+                return Err(err);
+            }
+            Ok(Self {
+                ptr: view.Value,
+                size,
+                file: Some(file),
+                handle,
+            })
+        }
+    }
+
+    fn protect(&self, protection: Protection) -> LuaResult<()> {
+        use windows_sys::Win32::System::Memory::VirtualProtect;
+
+        let mut old = 0;
+        let ok = unsafe {
+            VirtualProtect(
+                self.ptr,
+                self.size,
+                Self::page_protection(protection),
+                &raw mut old,
+            )
+        };
+        if ok == 0 {
+            return Err(last_error("change protection"));
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> LuaResult<()> {
+        use windows_sys::Win32::System::Memory::FlushViewOfFile;
+
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if unsafe { FlushViewOfFile(self.ptr, self.size) } == 0 {
+            return Err(last_error("flush"));
+        }
+        // Flushing the view only hands the pages to the system, this writes them to disk
+        file.sync_data()
+            .map_err(|e| LuaError::external(format!("ffi.mmap: failed to flush: {e}")))
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Memory::{
+            MEM_RELEASE, MEMORY_MAPPED_VIEW_ADDRESS, UnmapViewOfFile, VirtualFree,
+        };
+        unsafe {
+            if self.file.is_some() {
+                UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS { Value: self.ptr });
+                CloseHandle(self.handle);
+            } else {
+                VirtualFree(self.ptr, 0, MEM_RELEASE);
+            }
+        }
+    }
+}
+
+impl Mapping {
+    /**
+        Makes code written into mapped memory visible to instruction fetches, which
+        is needed on architectures where the instruction cache is not kept coherent.
+    */
+    #[cfg_attr(
+        not(any(
+            windows,
+            target_vendor = "apple",
+            target_arch = "aarch64",
+            target_arch = "arm"
+        )),
+        allow(unused_variables)
+    )]
+    fn flush_instruction_cache(ptr: *mut c_void, size: usize) {
+        #[cfg(windows)]
+        unsafe {
+            use windows_sys::Win32::System::Diagnostics::Debug::FlushInstructionCache;
+            use windows_sys::Win32::System::Threading::GetCurrentProcess;
+            FlushInstructionCache(GetCurrentProcess(), ptr, size);
+        }
+        #[cfg(target_vendor = "apple")]
+        unsafe {
+            unsafe extern "C" {
+                fn sys_icache_invalidate(start: *mut c_void, len: usize);
+            }
+            sys_icache_invalidate(ptr, size);
+        }
+        #[cfg(all(
+            unix,
+            not(target_vendor = "apple"),
+            any(target_arch = "aarch64", target_arch = "arm")
+        ))]
+        unsafe {
+            unsafe extern "C" {
+                fn __clear_cache(start: *mut std::ffi::c_char, end: *mut std::ffi::c_char);
+            }
+            let start = ptr.cast::<std::ffi::c_char>();
+            __clear_cache(start, start.add(size));
+        }
+    }
+}
+
+fn last_error(action: &str) -> LuaError {
+    LuaError::external(format!(
+        "ffi.mmap: failed to {action}: {}",
+        std::io::Error::last_os_error()
+    ))
+}
+
+/// Keeps a mapping alive for as long as a cdata view of it exists
+struct MappingGuard(#[allow(dead_code)] Arc<Mapping>);
+
+impl LuaUserData for MappingGuard {}
+
+/// A mapped file or anonymous memory region (`ffi.mmap`)
+pub struct MemoryMap {
+    path: Option<String>,
+    protection: Protection,
+    mapping: Option<Arc<Mapping>>,
+}
+
+impl MemoryMap {
+    fn mapping(&self) -> LuaResult<&Arc<Mapping>> {
+        self.mapping
+            .as_ref()
+            .ok_or_else(|| LuaError::external("ffi.mmap: the mapping is closed"))
+    }
+
+    /// The base address of the mapping
+    pub(crate) fn ptr(&self) -> LuaResult<*mut c_void> {
+        Ok(self.mapping()?.ptr)
+    }
+
+    /// The mapped memory, for bounds checks in safe mode
+    pub(crate) fn region(&self) -> Option<Region> {
+        self.mapping.as_ref().map(|m| Region::new(m.ptr, m.size))
+    }
+
+    fn range(&self, offset: usize, len: usize, access: &str, allowed: bool) -> LuaResult<*mut u8> {
+        let mapping = self.mapping()?;
+        if !allowed {
+            return Err(LuaError::external(format!(
+                "ffi.mmap: cannot {access} a mapping with protection '{}'",
+                self.protection
+            )));
+        }
+        match offset.checked_add(len) {
+            Some(end) if end <= mapping.size => Ok(unsafe { mapping.ptr.cast::<u8>().add(offset) }),
+            _ => Err(LuaError::external(format!(
+                "ffi.mmap: range {offset}..{} is out of bounds for mapping of {} bytes",
+                offset.saturating_add(len),
+                mapping.size
+            ))),
+        }
+    }
+}
+
+/**
+    Keeps the mapping alive for as long as `cdata` exists, if `source` is a mapping.
+
+    Used by `ffi.cast`, since cdata cast from a mapping points into it without owning it.
+*/
+pub(crate) fn keep_alive(source: &LuaValue, cdata: &LuaAnyUserData) -> LuaResult<()> {
+    if let LuaValue::UserData(ud) = source
+        && let Ok(map) = ud.borrow::<MemoryMap>()
+        && let Some(mapping) = &map.mapping
+    {
+        cdata.set_user_value(MappingGuard(Arc::clone(mapping)))?;
+    }
+    Ok(())
+}
+
+impl LuaUserData for MemoryMap {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("path", |_, this| Ok(this.path.clone()));
+        fields.add_field_method_get("size", |_, this| Ok(this.mapping()?.size));
+        fields.add_field_method_get("ptr", |_, this| Ok(LuaLightUserData(this.ptr()?)));
+        fields.add_field_method_get("protection", |_, this| Ok(this.protection));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // map:view(type) - cdata over the mapping, "uint8_t*" by default
+        methods.add_method("view", |lua, this, type_name: Option<String>| {
+            let type_name = type_name.unwrap_or_else(|| "uint8_t*".to_string());
+            let ctype = CType::parse(&type_name)
+                .ok_or_else(|| LuaError::external(format!("Unknown type: {type_name}")))?;
+            let mapping = this.mapping()?;
+
+            let cbox = CBox::from_raw(mapping.ptr, ctype, false).with_region(this.region());
+            let view = lua.create_userdata(cbox)?;
+            view.set_user_value(MappingGuard(Arc::clone(mapping)))?;
+            Ok(view)
+        });
+
+        // map:read(offset, len) - copy bytes out as a string
+        methods.add_method(
+            "read",
+            |lua, this, (offset, len): (usize, Option<usize>)| {
+                let len = len.unwrap_or(this.mapping()?.size.saturating_sub(offset));
+                let ptr = this.range(offset, len, "read", this.protection.read)?;
+                lua.create_string(unsafe { std::slice::from_raw_parts(ptr, len) })
+            },
+        );
+
+        // map:write(offset, data) - copy a string or buffer in
+        methods.add_method("write", |_, this, (offset, data): (usize, LuaValue)| {
+            let bytes = match &data {
+                LuaValue::String(s) => s.as_bytes().to_vec(),
+                LuaValue::Buffer(b) => b.to_vec(),
+                _ => {
+                    return Err(LuaError::external(
+                        "ffi.mmap: data must be a string or buffer",
+                    ));
+                }
+            };
+            let ptr = this.range(offset, bytes.len(), "write", this.protection.write)?;
+            unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len()) };
+            Ok(())
+        });
+
+        // map:flush() - write changes to a mapped file back to disk
+        methods.add_method("flush", |_, this, ()| this.mapping()?.flush());
+
+        // map:protect(protection) - change which accesses the pages allow
+        methods.add_method_mut("protect", |_, this, protection: Protection| {
+            let mapping = this.mapping()?;
+            mapping.protect(protection)?;
+            if protection.exec {
+                Mapping::flush_instruction_cache(mapping.ptr, mapping.size);
+            }
+            this.protection = protection;
+            Ok(())
+        });
+
+        // map:close() - release this handle, views keep the mapping alive until collected
+        methods.add_method_mut("close", |_, this, ()| {
+            this.mapping = None;
+            Ok(())
+        });
+
+        methods.add_meta_method(LuaMetaMethod::Len, |_, this, ()| Ok(this.mapping()?.size));
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            let source = this.path.as_deref().unwrap_or("anonymous");
+            Ok(match &this.mapping {
+                Some(m) => format!("mmap<{source}: {} bytes, {}>", m.size, this.protection),
+                None => format!("mmap<{source}: closed>"),
+            })
+        });
+    }
+}
+
+/// Opens a file to map, growing it to `size` when mapped writable
+fn open_file(path: &str, size: Option<usize>, protection: Protection) -> LuaResult<(File, usize)> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true).write(protection.write);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        use windows_sys::Win32::Foundation::{GENERIC_EXECUTE, GENERIC_READ, GENERIC_WRITE};
+
+        // Executable views need the file to be opened for execution as well
+        let mut access = GENERIC_READ;
+        if protection.write {
+            access |= GENERIC_WRITE;
+        }
+        if protection.exec {
+            access |= GENERIC_EXECUTE;
+        }
+        options.access_mode(access);
+    }
+
+    let file = options
+        .open(path)
+        .map_err(|e| LuaError::external(format!("ffi.mmap: failed to open '{path}': {e}")))?;
+    let len = file
+        .metadata()
+        .map_err(|e| LuaError::external(format!("ffi.mmap: failed to open '{path}': {e}")))?
+        .len() as usize;
+
+    let size = size.unwrap_or(len);
+    if size == 0 {
+        return Err(LuaError::external(format!(
+            "ffi.mmap: '{path}' is empty, a size must be given to map it"
+        )));
+    }
+    if size > len {
+        // Pages past the end of the file can not be accessed, so the file has to grow first
+        if !protection.write {
+            return Err(LuaError::external(format!(
+                "ffi.mmap: cannot map {size} bytes of '{path}', which is only {len} bytes long"
+            )));
+        }
+        file.set_len(size as u64)
+            .map_err(|e| LuaError::external(format!("ffi.mmap: failed to grow '{path}': {e}")))?;
+    }
+    Ok((file, size))
+}
+
+/// ffi.mmap(path?, size?, protection?) - map a file, or anonymous memory when path is nil
+pub(crate) fn ffi_mmap(
+    _lua: &Lua,
+    (path, size, protection): (Option<String>, Option<usize>, Protection),
+) -> LuaResult<MemoryMap> {
+    let mapping = match &path {
+        Some(path) => {
+            let (file, size) = open_file(path, size, protection)?;
+            Mapping::file(file, size, protection)?
+        }
+        None => match size {
+            Some(size) if size > 0 => Mapping::anonymous(size, protection)?,
+            _ => {
+                return Err(LuaError::external(
+                    "ffi.mmap: size must be greater than 0 for anonymous memory",
+                ));
+            }
+        },
+    };
+    if protection.exec {
+        Mapping::flush_instruction_cache(mapping.ptr, mapping.size);
+    }
+    Ok(MemoryMap {
+        path,
+        protection,
+        mapping: Some(Arc::new(mapping)),
+    })
+}
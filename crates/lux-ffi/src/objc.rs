@@ -0,0 +1,345 @@
+//! FFI Objective-C - Messaging the Objective-C runtime
+//!
+//! Messages are sent with `objc_msgSend`, using a signature built from the type
+//! encoding of the method that the receiver resolves the selector to, so that
+//! no signatures have to be declared by hand.
+
+use crate::call::invoke;
+use crate::memory::get_ptr_from_value;
+use crate::types::{CType, CallConv, FuncSig, Qualifiers};
+use libloading::Library;
+use mlua::prelude::*;
+use std::ffi::{CStr, CString, c_char, c_void};
+use std::sync::OnceLock;
+
+#[cfg(target_os = "macos")]
+const LIBRARY_NAME: &str = "/usr/lib/libobjc.A.dylib";
+#[cfg(not(target_os = "macos"))]
+const LIBRARY_NAME: &str = "libobjc.so.4";
+
+type Id = *mut c_void;
+
+/// The functions of the Objective-C runtime, loaded on first use
+struct Runtime {
+    _lib: Library,
+    get_class: unsafe extern "C" fn(*const c_char) -> Id,
+    register_selector: unsafe extern "C" fn(*const c_char) -> Id,
+    object_get_class: unsafe extern "C" fn(Id) -> Id,
+    class_get_name: unsafe extern "C" fn(Id) -> *const c_char,
+    class_get_instance_method: unsafe extern "C" fn(Id, Id) -> Id,
+    method_get_type_encoding: unsafe extern "C" fn(Id) -> *const c_char,
+    msg_send: usize,
+}
+
+impl Runtime {
+    fn load() -> Result<Self, String> {
+        unsafe {
+            let lib = Library::new(LIBRARY_NAME).map_err(|e| {
+                format!("The Objective-C runtime is not available ({LIBRARY_NAME}): {e}")
+            })?;
+            macro_rules! symbol {
+                ($name:literal) => {
+                    *lib.get(concat!($name, "\0").as_bytes())
+                        .map_err(|e| format!("Objective-C runtime is missing {}: {e}", $name))?
+                };
+            }
+            let msg_send: *const c_void = symbol!("objc_msgSend");
+            Ok(Self {
+                get_class: symbol!("objc_getClass"),
+                register_selector: symbol!("sel_registerName"),
+                object_get_class: symbol!("object_getClass"),
+                class_get_name: symbol!("class_getName"),
+                class_get_instance_method: symbol!("class_getInstanceMethod"),
+                method_get_type_encoding: symbol!("method_getTypeEncoding"),
+                msg_send: msg_send as usize,
+                _lib: lib,
+            })
+        }
+    }
+
+    fn get() -> LuaResult<&'static Self> {
+        static RUNTIME: OnceLock<Result<Runtime, String>> = OnceLock::new();
+        RUNTIME
+            .get_or_init(Self::load)
+            .as_ref()
+            .map_err(|e| LuaError::external(e.clone()))
+    }
+
+    fn selector(&self, name: &str) -> LuaResult<Id> {
+        let name = CString::new(name)
+            .map_err(|_| LuaError::external("Selector must not contain null bytes"))?;
+        Ok(unsafe { (self.register_selector)(name.as_ptr()) })
+    }
+
+    fn class_name(&self, class: Id) -> String {
+        unsafe { CStr::from_ptr((self.class_get_name)(class)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+/// Skips a struct, union or array encoding, including any nested in it
+fn skip_aggregate(encoding: &mut &[u8], open: u8) {
+    let close = match open {
+        b'{' => b'}',
+        b'(' => b')',
+        _ => b']',
+    };
+    let mut depth = 0usize;
+    while let Some((&c, rest)) = encoding.split_first() {
+        *encoding = rest;
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+}
+
+/// Reads a single type from an Objective-C type encoding, such as `@`, `^v` or `{CGPoint=dd}`
+fn parse_encoding_type(encoding: &mut &[u8]) -> Result<CType, String> {
+    // Method qualifiers such as const, in, out and oneway do not change the type
+    while let [b'r' | b'n' | b'N' | b'o' | b'O' | b'R' | b'V', rest @ ..] = encoding {
+        *encoding = rest;
+    }
+    let Some((&code, rest)) = encoding.split_first() else {
+        return Err("unexpected end of type encoding".to_string());
+    };
+    *encoding = rest;
+    let ctype = match code {
+        b'c' => CType::Char,
+        b'C' => CType::UChar,
+        b's' => CType::Short,
+        b'S' => CType::UShort,
+        b'i' | b'l' => CType::Int,
+        b'I' | b'L' => CType::UInt,
+        b'q' => CType::Long,
+        b'Q' => CType::ULong,
+        b'f' => CType::Float,
+        b'd' => CType::Double,
+        b'B' => CType::Bool,
+        b'v' => CType::Void,
+        b'*' => CType::Pointer(Some(Box::new(CType::Char))),
+        b'@' | b'#' | b':' | b'?' => {
+            // Blocks are encoded as @?
+            if code == b'@' && encoding.first() == Some(&b'?') {
+                *encoding = &encoding[1..];
+            }
+            CType::Pointer(None)
+        }
+        b'^' => {
+            // Pointers to aggregates are fine, only the pointee needs skipping
+            if let Some(&open @ (b'{' | b'(' | b'[')) = encoding.first() {
+                skip_aggregate(encoding, open);
+            } else {
+                parse_encoding_type(encoding)?;
+            }
+            CType::Pointer(None)
+        }
+        b'{' | b'(' | b'[' => {
+            let name = String::from_utf8_lossy(&rest[..rest.len().min(32)]).into_owned();
+            return Err(format!(
+                "structs, unions and arrays passed by value are not supported ({}{name})",
+                code as char
+            ));
+        }
+        other => return Err(format!("unknown type encoding '{}'", other as char)),
+    };
+    // Skip the stack offset that follows each type in method encodings
+    while let [b'0'..=b'9', rest @ ..] = encoding {
+        *encoding = rest;
+    }
+    Ok(ctype)
+}
+
+/// Builds the signature of a method from its type encoding, such as `@24@0:8*16`
+fn parse_method_encoding(selector: &str, encoding: &str) -> Result<FuncSig, String> {
+    let mut bytes = encoding.as_bytes();
+    let ret = parse_encoding_type(&mut bytes)?;
+    let mut args = Vec::new();
+    while !bytes.is_empty() {
+        let index = args.len();
+        args.push((format!("arg{index}"), parse_encoding_type(&mut bytes)?));
+    }
+    if args.len() < 2 {
+        return Err(format!(
+            "method encoding '{encoding}' is missing self and _cmd"
+        ));
+    }
+    Ok(FuncSig {
+        name: selector.to_string(),
+        ret,
+        ret_qualifiers: Qualifiers::default(),
+        args,
+        variadic: false,
+        conv: CallConv::C,
+    })
+}
+
+/// Sends a message, resolving its signature through the class of the receiver
+fn send(lua: &Lua, receiver: Id, selector: &str, args: LuaMultiValue) -> LuaResult<LuaMultiValue> {
+    let runtime = Runtime::get()?;
+    if receiver.is_null() {
+        return Err(LuaError::external(format!(
+            "Attempt to send '{selector}' to nil"
+        )));
+    }
+    let expected = selector.matches(':').count();
+    if args.len() != expected {
+        return Err(LuaError::external(format!(
+            "'{selector}' takes {expected} arguments, got {}",
+            args.len()
+        )));
+    }
+
+    // Class objects resolve to their metaclass, whose instance methods are the class methods
+    let sel = runtime.selector(selector)?;
+    let class = unsafe { (runtime.object_get_class)(receiver) };
+    let method = unsafe { (runtime.class_get_instance_method)(class, sel) };
+    if method.is_null() {
+        return Err(LuaError::external(format!(
+            "{} does not respond to '{selector}'",
+            runtime.class_name(class)
+        )));
+    }
+    let encoding = unsafe { CStr::from_ptr((runtime.method_get_type_encoding)(method)) };
+    let sig = parse_method_encoding(selector, &encoding.to_string_lossy())
+        .map_err(|e| LuaError::external(format!("Can not send '{selector}': {e}")))?;
+
+    let mut call_args = LuaMultiValue::with_capacity(args.len() + 2);
+    call_args.push_back(LuaValue::LightUserData(LuaLightUserData(receiver)));
+    call_args.push_back(LuaValue::LightUserData(LuaLightUserData(sel)));
+    call_args.extend(args);
+    let mut ret = invoke(lua, runtime.msg_send, &sig, call_args)?;
+
+    // Returned objects are wrapped so that messages can be sent to them as well
+    if sig.ret == CType::Pointer(None)
+        && encoding
+            .to_bytes()
+            .first()
+            .is_some_and(|&c| c == b'@' || c == b'#')
+        && let Some(LuaValue::LightUserData(ptr)) = ret.front()
+    {
+        let object = ObjcObject { ptr: ptr.0 };
+        ret[0] = LuaValue::UserData(lua.create_userdata(object)?);
+    }
+    Ok(ret)
+}
+
+/// An Objective-C object or class, which is not retained or released
+pub struct ObjcObject {
+    ptr: Id,
+}
+
+impl ObjcObject {
+    #[must_use]
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.ptr
+    }
+}
+
+impl LuaUserData for ObjcObject {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("ptr", |_, this| Ok(LuaLightUserData(this.ptr)));
+        fields.add_field_method_get("class", |_, this| {
+            let runtime = Runtime::get()?;
+            Ok(runtime.class_name(unsafe { (runtime.object_get_class)(this.ptr) }))
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // obj:msgSend(selector, ...) - Send a message, with a signature from the method's type encoding
+        methods.add_method(
+            "msgSend",
+            |lua, this, (selector, args): (String, LuaMultiValue)| {
+                send(lua, this.ptr, &selector, args)
+            },
+        );
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            let runtime = Runtime::get()?;
+            let class = unsafe { (runtime.object_get_class)(this.ptr) };
+            Ok(format!(
+                "objc<{}: {:p}>",
+                runtime.class_name(class),
+                this.ptr
+            ))
+        });
+
+        methods.add_meta_method(
+            LuaMetaMethod::Eq,
+            |_, this, other: LuaUserDataRef<ObjcObject>| Ok(this.ptr == other.ptr),
+        );
+    }
+}
+
+/// ffi.objc.class(name) - look up a class, erroring if it is not loaded
+fn objc_class(_: &Lua, name: String) -> LuaResult<ObjcObject> {
+    let runtime = Runtime::get()?;
+    let c_name = CString::new(name.as_str())
+        .map_err(|_| LuaError::external("Class name must not contain null bytes"))?;
+    let class = unsafe { (runtime.get_class)(c_name.as_ptr()) };
+    if class.is_null() {
+        return Err(LuaError::external(format!(
+            "Objective-C class '{name}' is not loaded"
+        )));
+    }
+    Ok(ObjcObject { ptr: class })
+}
+
+/// ffi.objc.selector(name) - register a selector, for passing SEL arguments
+fn objc_selector(_: &Lua, name: String) -> LuaResult<LuaLightUserData> {
+    Ok(LuaLightUserData(Runtime::get()?.selector(&name)?))
+}
+
+/// ffi.objc.wrap(ptr) - an object from a pointer, such as one returned by a C function
+fn objc_wrap(_: &Lua, ptr: LuaValue) -> LuaResult<Option<ObjcObject>> {
+    let ptr = get_ptr_from_value(&ptr)?;
+    Ok((!ptr.is_null()).then_some(ObjcObject { ptr }))
+}
+
+/// ffi.objc.msgSend(receiver, selector, ...) - send a message to any object pointer
+fn objc_msg_send(
+    lua: &Lua,
+    (receiver, selector, args): (LuaValue, String, LuaMultiValue),
+) -> LuaResult<LuaMultiValue> {
+    send(lua, get_ptr_from_value(&receiver)?, &selector, args)
+}
+
+/// `ffi.objc.available()` - whether the Objective-C runtime could be loaded
+fn objc_available(_: &Lua, (): ()) -> LuaResult<bool> {
+    Ok(Runtime::get().is_ok())
+}
+
+/// Creates the ffi.objc table
+pub(crate) fn create_objc_table(lua: &Lua) -> LuaResult<LuaTable> {
+    let objc = lua.create_table()?;
+    objc.set("class", lua.create_function(objc_class)?)?;
+    objc.set("selector", lua.create_function(objc_selector)?)?;
+    objc.set("wrap", lua.create_function(objc_wrap)?)?;
+    objc.set("msgSend", lua.create_function(objc_msg_send)?)?;
+    objc.set("available", lua.create_function(objc_available)?)?;
+    Ok(objc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_method_encodings() {
+        let sig = parse_method_encoding("stringWithUTF8String:", "@24@0:8r*16").unwrap();
+        assert_eq!(sig.ret, CType::Pointer(None));
+        assert_eq!(sig.args.len(), 3);
+        assert_eq!(sig.args[2].1, CType::Pointer(Some(Box::new(CType::Char))));
+
+        let sig = parse_method_encoding("setValue:forKey:", "v32@0:8@16^{__CFString=}24").unwrap();
+        assert_eq!(sig.ret, CType::Void);
+        assert_eq!(sig.args[3].1, CType::Pointer(None));
+
+        assert!(parse_method_encoding("frame", "{CGRect={CGPoint=dd}{CGSize=dd}}16@0:8").is_err());
+    }
+}
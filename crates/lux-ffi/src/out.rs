@@ -0,0 +1,137 @@
+//! FFI Output Parameters
+//!
+//! Temporary slots for C out-parameters, created by `ffi.out(type)`.
+//! Passing a slot where a pointer is expected passes the address of the slot,
+//! and calls given any slots also return their values after the call.
+
+use crate::memory::{CBox, CData, c_to_lua_at_ptr, lua_to_c_at_ptr};
+use crate::types::CType;
+use mlua::prelude::*;
+use std::ffi::c_void;
+use std::ptr;
+
+/// A zero-initialized slot for a single value written to by a C function
+pub struct OutParam {
+    slot: CBox,
+}
+
+impl OutParam {
+    #[must_use]
+    pub fn new(ctype: CType) -> Self {
+        Self {
+            slot: CBox::new(ctype),
+        }
+    }
+
+    #[must_use]
+    pub fn ctype(&self) -> &CType {
+        &self.slot.ctype
+    }
+
+    #[must_use]
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.slot.ptr()
+    }
+
+    /// Read the current value of the slot
+    ///
+    /// Aggregates are copied into new cdata, so that they outlive the slot.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be converted to Lua.
+    pub fn get(&self, lua: &Lua) -> LuaResult<LuaValue> {
+        let ctype = self.ctype();
+        if matches!(
+            ctype,
+            CType::Struct(_) | CType::Union(_) | CType::Array(_, _) | CType::GUID
+        ) {
+            let copy = CBox::new(ctype.clone());
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    self.as_ptr().cast::<u8>(),
+                    copy.ptr().cast::<u8>(),
+                    ctype.size(),
+                );
+            }
+            return lua.create_userdata(copy).map(LuaValue::UserData);
+        }
+        unsafe { c_to_lua_at_ptr(lua, ctype, self.as_ptr()) }
+    }
+}
+
+impl LuaUserData for OutParam {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("ptr", |_, this| Ok(LuaLightUserData(this.as_ptr())));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("get", |lua, this, ()| this.get(lua));
+
+        // Sets the value before a call, for in/out parameters
+        methods.add_method("set", |_, this, value: LuaValue| unsafe {
+            lua_to_c_at_ptr(this.ctype(), this.as_ptr(), value).map_err(LuaError::external)
+        });
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(format!("ffi.out<{:?}>: {:p}", this.ctype(), this.as_ptr()))
+        });
+    }
+}
+
+/// ffi.out(type, init?) - Create a slot for an out-parameter
+///
+/// # Errors
+///
+/// Errors if the type is unknown or incomplete, or the initial value does not fit it.
+pub fn ffi_out(_: &Lua, (type_name, init): (String, Option<LuaValue>)) -> LuaResult<OutParam> {
+    let ctype = CType::parse(&type_name)
+        .ok_or_else(|| LuaError::external(format!("Unknown type: {type_name}")))?;
+    if ctype.size() == 0 {
+        return Err(LuaError::external(format!(
+            "ffi.out: cannot create a slot for incomplete type '{type_name}'"
+        )));
+    }
+
+    let out = OutParam::new(ctype);
+    if let Some(init) = init {
+        unsafe { lua_to_c_at_ptr(out.ctype(), out.as_ptr(), init) }.map_err(LuaError::external)?;
+    }
+    Ok(out)
+}
+
+/// Returns the address of the slot if the value is an out-parameter
+pub(crate) fn out_param_ptr(value: &LuaValue) -> Option<*mut c_void> {
+    match value {
+        LuaValue::UserData(ud) => ud.borrow::<OutParam>().ok().map(|out| out.as_ptr()),
+        _ => None,
+    }
+}
+
+/// Build the values returned from a call
+///
+/// Without any out-parameters this is just the result. Otherwise, the values of the
+/// out-parameters follow the result in argument order - and for functions returning
+/// `void`, the values of the out-parameters are all that is returned.
+pub(crate) fn call_results(
+    lua: &Lua,
+    ret_type: &CType,
+    result: LuaValue,
+    args: &LuaMultiValue,
+) -> LuaResult<LuaMultiValue> {
+    let mut results = LuaMultiValue::new();
+    let mut has_out = false;
+    for arg in args {
+        if let LuaValue::UserData(ud) = arg
+            && let Ok(out) = ud.borrow::<OutParam>()
+        {
+            has_out = true;
+            results.push_back(out.get(lua)?);
+        }
+    }
+
+    if !has_out || *ret_type != CType::Void {
+        results.push_front(result);
+    }
+    Ok(results)
+}
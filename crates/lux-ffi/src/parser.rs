@@ -7,11 +7,23 @@ use crate::types::*;
 use std::collections::HashMap;
 
 /// Parse C declarations and register them
-pub fn parse_cdef(cdef: &str) -> Result<(), String> {
+///
+/// Returns the names of structs annotated with `// @bind`, which are
+/// registered as bound structs with checked field access.
+pub fn parse_cdef(cdef: &str) -> Result<Vec<String>, String> {
     let lines: Vec<&str> = cdef.lines().collect();
+    let mut bound = Vec::new();
+    let mut pending_bind = false;
     let mut i = 0;
 
     while i < lines.len() {
+        // Annotation comment for the next struct
+        if is_bind_annotation(lines[i]) {
+            pending_bind = true;
+            i += 1;
+            continue;
+        }
+
         let line = lines[i].split("//").next().unwrap_or("").trim();
 
         // Skip empty lines
@@ -20,6 +32,8 @@ pub fn parse_cdef(cdef: &str) -> Result<(), String> {
             continue;
         }
 
+        let bind = std::mem::take(&mut pending_bind);
+
         // Skip preprocessor directives
         if line.starts_with('#') {
             i += 1;
@@ -34,17 +48,20 @@ pub fn parse_cdef(cdef: &str) -> Result<(), String> {
             let is_union = line.starts_with("typedef union");
             let (def, consumed) = parse_typedef_struct(&lines, i, is_union)?;
             if let Some(d) = def {
-                Registry::get().add_struct(d);
+                register_struct(d, bind, &mut bound);
             }
             i += consumed;
             continue;
         }
 
-        // Typedef enum
-        if line.starts_with("typedef enum") {
-            let (name, values, consumed) = parse_typedef_enum(&lines, i)?;
-            if let (Some(n), Some(v)) = (name, values) {
-                Registry::get().add_enum(&n, v);
+        // Enum, with or without typedef
+        if line.starts_with("typedef enum") || (line.starts_with("enum") && line.contains('{')) {
+            let (parsed, consumed) = parse_enum(&lines, i)?;
+            if let Some(parsed) = parsed {
+                let mut reg = Registry::get();
+                for name in parsed.names {
+                    reg.add_enum(&name, parsed.values.clone());
+                }
             }
             i += consumed;
             continue;
@@ -109,7 +126,7 @@ pub fn parse_cdef(cdef: &str) -> Result<(), String> {
             let is_union = line.starts_with("union ");
             let (def, consumed) = parse_struct(&lines, i, is_union)?;
             if let Some(d) = def {
-                Registry::get().add_struct(d);
+                register_struct(d, bind, &mut bound);
             }
             i += consumed;
             continue;
@@ -118,7 +135,23 @@ pub fn parse_cdef(cdef: &str) -> Result<(), String> {
         i += 1;
     }
 
-    Ok(())
+    Ok(bound)
+}
+
+/// Checks for a `// @bind` annotation line
+fn is_bind_annotation(line: &str) -> bool {
+    line.trim()
+        .strip_prefix("//")
+        .is_some_and(|comment| comment.trim() == "@bind")
+}
+
+fn register_struct(def: StructDef, bind: bool, bound: &mut Vec<String>) {
+    let mut reg = Registry::get();
+    if bind && !def.name.is_empty() {
+        reg.bind_struct(&def.name);
+        bound.push(def.name.clone());
+    }
+    reg.add_struct(def);
 }
 
 fn parse_typedef_struct(
@@ -349,63 +382,247 @@ fn expand_compact_fields(body: &str) -> String {
     result.join("; ")
 }
 
-fn parse_struct_body(name: &str, body: &str, is_union: bool) -> Result<StructDef, String> {
-    let mut fields = Vec::new();
-    let mut offset = 0usize;
-    let mut max_align = 1usize;
+/// Field placement while laying out a struct or union
+struct Layout {
+    fields: Vec<Field>,
+    offset: usize,
+    size: usize,
+    align: usize,
+    is_union: bool,
+}
 
-    // Pre-process: expand compact field declarations
-    // "long left, top, right, bottom" -> "long left; long top; long right; long bottom"
-    let expanded = expand_compact_fields(body);
+impl Layout {
+    fn new(is_union: bool) -> Self {
+        Self {
+            fields: Vec::new(),
+            offset: 0,
+            size: 0,
+            align: 1,
+            is_union,
+        }
+    }
 
-    for line in expanded.split(';') {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
+    /// Reserve space for a member, returning its offset
+    fn place(&mut self, size: usize, align: usize) -> usize {
+        let align = align.max(1);
+        self.align = self.align.max(align);
+        if self.is_union {
+            self.size = self.size.max(size);
+            0
+        } else {
+            self.offset = self.offset.next_multiple_of(align);
+            let offset = self.offset;
+            self.offset += size;
+            offset
         }
+    }
 
-        if let Some((field_name, ctype)) = parse_field_decl(line) {
-            let size = ctype.size();
-            let align = ctype.align();
+    fn push_field(&mut self, name: String, ctype: CType, qualifiers: Qualifiers) {
+        self.push_aligned_field(name, ctype, qualifiers, None);
+    }
 
-            // Align offset
-            if !is_union && align > 0 {
-                offset = (offset + align - 1) / align * align;
+    /// Place a field, aligned to at least `align` bytes when it has an alignment specifier
+    fn push_aligned_field(
+        &mut self,
+        name: String,
+        ctype: CType,
+        qualifiers: Qualifiers,
+        align: Option<usize>,
+    ) {
+        let align = align.map_or(ctype.align(), |align| align.max(ctype.align()));
+        let offset = self.place(ctype.size(), align);
+        self.fields.push(Field {
+            name,
+            ctype,
+            offset,
+            bits: None,
+            qualifiers,
+        });
+    }
+
+    /// Flatten the fields of an anonymous struct or union into this one
+    fn push_anonymous(&mut self, def: StructDef) {
+        let base = self.place(def.size, def.align);
+        self.fields
+            .extend(def.fields.into_iter().map(|field| Field {
+                offset: base + field.offset,
+                ..field
+            }));
+    }
+
+    fn finish(self, name: &str) -> StructDef {
+        let size = if self.is_union {
+            self.size
+        } else {
+            self.offset
+        };
+        // Final size with alignment padding
+        StructDef::new(
+            name.to_string(),
+            self.fields,
+            size.next_multiple_of(self.align),
+            self.align,
+            self.is_union,
+        )
+    }
+}
+
+/// Split a struct body into member declarations, keeping nested bodies together
+fn split_members(body: &str) -> Vec<&str> {
+    let mut members = Vec::new();
+    let mut depth = 0i32;
+    let mut start = 0;
+    for (i, c) in body.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ';' if depth == 0 => {
+                members.push(body[start..i].trim());
+                start = i + 1;
             }
+            _ => {}
+        }
+    }
+    members.push(body[start..].trim());
+    members.retain(|m| !m.is_empty());
+    members
+}
+
+fn parse_struct_body(name: &str, body: &str, is_union: bool) -> Result<StructDef, String> {
+    let mut layout = Layout::new(is_union);
 
-            fields.push(Field {
-                name: field_name,
-                ctype,
-                offset: if is_union { 0 } else { offset },
-                bits: None,
-            });
+    for member in split_members(body) {
+        if member.contains('{') {
+            parse_nested_member(name, member, &mut layout)?;
+            continue;
+        }
 
-            if !is_union {
-                offset += size;
+        // Alignment specifiers apply to every field the member declares: "alignas(16) float a, b"
+        let (member, align) = strip_alignas(member)?;
+        let member = member.trim();
+
+        // Function pointer fields, such as the methods of a vtable: "HRESULT (*Method)(void* This)"
+        if member.contains('(') {
+            if let Some((field_name, ctype)) = parse_func_ptr_typedef(&format!("typedef {member}"))
+            {
+                layout.push_aligned_field(field_name, ctype, Qualifiers::default(), align);
             }
+            continue;
+        }
 
-            max_align = max_align.max(align);
+        // Pre-process: expand compact field declarations
+        // "long left, top, right, bottom" -> "long left; long top; long right; long bottom"
+        for line in expand_compact_fields(member).split(';') {
+            if let Some((field_name, ctype, qualifiers)) = parse_field_decl(line) {
+                layout.push_aligned_field(field_name, ctype, qualifiers, align);
+            }
         }
     }
 
-    // Final size with alignment padding
-    let size = if is_union {
-        fields.iter().map(|f| f.ctype.size()).max().unwrap_or(0)
-    } else {
-        (offset + max_align - 1) / max_align * max_align
+    Ok(layout.finish(name))
+}
+
+/// Remove the `alignas(...)` and `_Alignas(...)` specifiers from a member declaration
+///
+/// Returns the declaration without them, and the strictest alignment they asked for.
+/// Both an alignment in bytes and a type to take the alignment of are supported.
+fn strip_alignas(member: &str) -> Result<(String, Option<usize>), String> {
+    let mut rest = member.to_string();
+    let mut strictest: Option<usize> = None;
+
+    while let Some((start, open)) = find_alignas(&rest) {
+        let close = rest[open..]
+            .find(')')
+            .map(|i| open + i)
+            .ok_or_else(|| format!("Unclosed alignment specifier: {member}"))?;
+        let arg = rest[open + 1..close].trim();
+
+        let align = if let Ok(bytes) = arg.parse::<usize>() {
+            // An alignment of zero has no effect
+            if bytes != 0 && !bytes.is_power_of_two() {
+                return Err(format!(
+                    "Alignment must be a power of two, got {bytes}: {member}"
+                ));
+            }
+            bytes.max(1)
+        } else {
+            CType::parse(arg)
+                .map(|ctype| ctype.align())
+                .ok_or_else(|| format!("Unknown type '{arg}' in alignment specifier: {member}"))?
+        };
+        strictest = Some(strictest.map_or(align, |strictest| strictest.max(align)));
+        rest.replace_range(start..=close, " ");
+    }
+
+    Ok((rest, strictest))
+}
+
+/// Find the next alignment specifier, returning where it starts and where its `(` is
+fn find_alignas(decl: &str) -> Option<(usize, usize)> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    ["alignas", "_Alignas"]
+        .iter()
+        .flat_map(|keyword| {
+            decl.match_indices(keyword).filter_map(move |(start, _)| {
+                let after = &decl[start + keyword.len()..];
+                let before_ok = !decl[..start].ends_with(is_ident);
+                let open = after.len() - after.trim_start().len();
+                (before_ok && after[open..].starts_with('('))
+                    .then_some((start, start + keyword.len() + open))
+            })
+        })
+        .min()
+}
+
+/// Parse a struct or union defined inside another one
+///
+/// Anonymous members (`union { int i; float f; };`) have their fields flattened into
+/// the parent. Named members (`struct { int x, y; } pos;`) register the nested type,
+/// under its tag or as `Parent.member` when it has none, and use it as the field type.
+fn parse_nested_member(parent: &str, member: &str, layout: &mut Layout) -> Result<(), String> {
+    let (Some(open), Some(close)) = (member.find('{'), member.rfind('}')) else {
+        return Err(format!("Unbalanced braces in struct '{parent}': {member}"));
     };
+    let head = member[..open].trim();
+    let declarators = member[close + 1..].trim();
+    let is_union = head.starts_with("union");
+    let tag = head
+        .strip_prefix("struct")
+        .or_else(|| head.strip_prefix("union"))
+        .ok_or_else(|| format!("Expected a nested struct or union in '{parent}': {member}"))?
+        .trim();
 
-    Ok(StructDef {
-        name: name.to_string(),
-        fields,
-        size,
-        align: max_align,
-        is_union,
-        is_packed: false,
-    })
+    if declarators.is_empty() && tag.is_empty() {
+        // Named types nested in anonymous members are named after the parent
+        let def = parse_struct_body(parent, &member[open + 1..close], is_union)?;
+        layout.push_anonymous(def);
+        return Ok(());
+    }
+
+    let type_name = if tag.is_empty() {
+        let first = declarators.split(',').next().unwrap_or_default();
+        let field = first.trim().trim_start_matches('*');
+        let field = field.split('[').next().unwrap_or_default().trim();
+        format!("{parent}.{field}")
+    } else {
+        tag.to_string()
+    };
+    let def = parse_struct_body(&type_name, &member[open + 1..close], is_union)?;
+    Registry::get().add_struct(def);
+
+    if !declarators.is_empty() {
+        let keyword = if is_union { "union" } else { "struct" };
+        let fields = expand_compact_fields(&format!("{keyword} {type_name} {declarators}"));
+        for line in fields.split(';') {
+            if let Some((field_name, ctype, qualifiers)) = parse_field_decl(line) {
+                layout.push_field(field_name, ctype, qualifiers);
+            }
+        }
+    }
+    Ok(())
 }
 
-fn parse_field_decl(line: &str) -> Option<(String, CType)> {
+fn parse_field_decl(line: &str) -> Option<(String, CType, Qualifiers)> {
     let line = line.trim();
     if line.is_empty() {
         return None;
@@ -438,7 +655,7 @@ fn parse_field_decl(line: &str) -> Option<(String, CType)> {
         let (name, type_str) = split_type_and_name(before_bracket)?;
 
         // Build nested array type: for [4][4], inner is CType::Array(base, 4), outer is CType::Array(inner, 4)
-        let base_type = CType::parse(&type_str)?;
+        let (base_type, qualifiers) = CType::parse_qualified(&type_str)?;
 
         // Build from innermost to outermost
         let mut result_type = base_type;
@@ -446,13 +663,13 @@ fn parse_field_decl(line: &str) -> Option<(String, CType)> {
             result_type = CType::Array(Box::new(result_type), dim);
         }
 
-        return Some((name, result_type));
+        return Some((name, result_type, qualifiers));
     }
 
     // Handle regular declarations with complex pointers
     let (name, type_str) = split_type_and_name(line)?;
-    let ctype = CType::parse(&type_str)?;
-    Some((name, ctype))
+    let (ctype, qualifiers) = CType::parse_qualified(&type_str)?;
+    Some((name, ctype, qualifiers))
 }
 
 /// Split a declaration into name and type, handling complex pointer syntax
@@ -546,6 +763,11 @@ fn parse_simple_typedef(line: &str) -> Option<(String, CType)> {
     Some((name_clean.to_string(), ctype))
 }
 
+/// Parse an unnamed function pointer type, like `int (*)(int, int)`
+pub(crate) fn parse_func_ptr_type(s: &str) -> Option<CType> {
+    parse_func_ptr_typedef(&format!("typedef {s}")).map(|(_, ctype)| ctype)
+}
+
 fn parse_func_ptr_typedef(line: &str) -> Option<(String, CType)> {
     // Format: typedef RetType (CallConv *Name)(Args);
     let content = line.strip_prefix("typedef ")?.trim_end_matches(';').trim();
@@ -582,30 +804,14 @@ fn parse_func_ptr_typedef(line: &str) -> Option<(String, CType)> {
                 continue;
             }
 
-            // Hack: Try to parse the whole string. If fails, try removing last word.
-            let full = arg.to_string();
-            if let Some(t) = CType::parse(&full) {
-                args.push(t);
-            } else {
-                // pop last word
-                if let Some(idx) = full.rfind(' ') {
-                    let sub = full[..idx].trim();
-                    if let Some(t) = CType::parse(sub) {
-                        args.push(t);
-                    } else {
-                        // Default to void*? No, fallback to Int?
-                        // Error safest.
-                        // But let's act like parse_func_decl.
-                        args.push(CType::Void); // Placeholder for failure
-                    }
-                }
-            }
+            args.push(parse_param_type(arg).unwrap_or(CType::Void)); // Void is a placeholder for failure
         }
     }
 
     // Check inner for stdcall/winapi
     let conv = if inner.to_uppercase().contains("WINAPI")
         || inner.to_uppercase().contains("STDCALL")
+        || inner.to_uppercase().contains("STDMETHODCALLTYPE")
         || inner.to_uppercase().contains("CALLBACK")
         || inner.to_uppercase().contains("APIENTRY")
         || inner.to_uppercase().contains("PASCAL")
@@ -630,10 +836,39 @@ fn parse_func_ptr_typedef(line: &str) -> Option<(String, CType)> {
     ))
 }
 
-fn parse_typedef_enum(
-    lines: &[&str],
-    start: usize,
-) -> Result<(Option<String>, Option<HashMap<String, i64>>, usize), String> {
+/// Parse the type of a parameter, which may or may not be named
+///
+/// "void** ppv" -> void**, "const GUID* riid" -> GUID*, "unsigned int" -> unsigned int
+fn parse_param_type(param: &str) -> Option<CType> {
+    if let Some((name, type_str)) = split_type_and_name(param)
+        && param.split_whitespace().count() > 1
+        && !param.ends_with('*')
+        && !matches!(type_str.as_str(), "struct" | "union" | "enum" | "const")
+    {
+        // The last word is a name if it is not a type of its own, unknown words parse as structs
+        let is_name = match CType::parse(&name) {
+            Some(CType::Struct(tag)) => !Registry::get().has_struct(&tag),
+            Some(_) => false,
+            None => true,
+        };
+        if is_name {
+            return CType::parse(&type_str);
+        }
+    }
+    CType::parse(param)
+}
+
+/// An enum declaration with a body
+struct ParsedEnum {
+    /// Names to register the enum under - the alias and the tag, if present
+    names: Vec<String>,
+    values: HashMap<String, i64>,
+}
+
+/// Parse `enum Tag { ... };` or `typedef enum Tag { ... } Alias;`
+///
+/// Returns the enum, if it has a body, along with the number of lines consumed.
+fn parse_enum(lines: &[&str], start: usize) -> Result<(Option<ParsedEnum>, usize), String> {
     let mut i = start;
     let mut body = String::new();
     let mut brace_count = 0;
@@ -669,12 +904,24 @@ fn parse_typedef_enum(
 
     // Parse enum values
     if let (Some(start_idx), Some(end_idx)) = (body.find('{'), body.rfind('}')) {
+        let head = body[..start_idx].trim();
+        let head = head.strip_prefix("typedef").unwrap_or(head).trim();
+        let tag = head.strip_prefix("enum").unwrap_or(head).trim();
+
+        let mut names = Vec::new();
+        if !name.is_empty() {
+            names.push(name);
+        }
+        if !tag.is_empty() && !names.iter().any(|n| n == tag) {
+            names.push(tag.to_string());
+        }
+
         let inner = &body[start_idx + 1..end_idx];
         let values = parse_enum_values(inner);
-        return Ok((Some(name), Some(values), i - start + 1));
+        return Ok((Some(ParsedEnum { names, values }), i - start + 1));
     }
 
-    Ok((None, None, i - start + 1))
+    Ok((None, i - start + 1))
 }
 
 fn parse_enum_values(body: &str) -> HashMap<String, i64> {
@@ -746,7 +993,8 @@ fn parse_func_decl(line: &str) -> Option<FuncSig> {
         ret_str.push('*');
     }
 
-    let ret = CType::parse(&ret_str).unwrap_or(CType::Int);
+    let (ret, ret_qualifiers) =
+        CType::parse_qualified(&ret_str).unwrap_or((CType::Int, Qualifiers::default()));
 
     // Parse arguments
     let args_str = &line[paren_start + 1..paren_end];
@@ -817,6 +1065,7 @@ fn parse_func_decl(line: &str) -> Option<FuncSig> {
     Some(FuncSig {
         name,
         ret,
+        ret_qualifiers,
         args,
         variadic,
         conv,
@@ -0,0 +1,75 @@
+//! FFI Call Pool - Threads for blocking C calls
+//!
+//! Calls made with `func:callAsync(...)` run on a small pool of threads owned
+//! by the Lua state, so that a blocking C function only suspends the calling
+//! coroutine instead of the whole scheduler.
+
+use lux_utils::process::ProcessFfiPoolSize;
+use mlua::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Threads that run blocking calls, stopping once the pool is dropped
+pub struct CallPool {
+    jobs: async_channel::Sender<Job>,
+}
+
+impl CallPool {
+    fn new(size: usize) -> LuaResult<Self> {
+        let (jobs, queue) = async_channel::unbounded::<Job>();
+        for index in 0..size.max(1) {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name(format!("lux-ffi-{index}"))
+                .spawn(move || {
+                    while let Ok(job) = queue.recv_blocking() {
+                        // A panicking call drops its result sender, which the caller sees as an error
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    }
+                })
+                .map_err(LuaError::external)?;
+        }
+        Ok(Self { jobs })
+    }
+
+    /// Returns the pool of the given Lua state, starting it on first use
+    fn get(lua: &Lua) -> LuaResult<async_channel::Sender<Job>> {
+        if let Some(pool) = lua.app_data_ref::<Self>() {
+            return Ok(pool.jobs.clone());
+        }
+        let size = lua
+            .app_data_ref::<ProcessFfiPoolSize>()
+            .map_or_else(|| ProcessFfiPoolSize::default().get(), |size| size.get());
+        let pool = Self::new(size)?;
+        let jobs = pool.jobs.clone();
+        lua.set_app_data(pool);
+        Ok(jobs)
+    }
+}
+
+impl Drop for CallPool {
+    fn drop(&mut self) {
+        // Workers exit once the queue is drained, any call still running finishes first
+        self.jobs.close();
+    }
+}
+
+/// Runs `f` on the call pool of the given Lua state, waiting for its result without blocking
+pub(crate) async fn run<F, T>(lua: &Lua, f: F) -> LuaResult<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = async_channel::bounded(1);
+    CallPool::get(lua)?
+        .send(Box::new(move || {
+            let _ = tx.send_blocking(f());
+        }))
+        .await
+        .map_err(|_| LuaError::external("FFI call pool has shut down"))?;
+    rx.recv()
+        .await
+        .map_err(|_| LuaError::external("FFI call panicked on the call pool"))
+}
@@ -0,0 +1,452 @@
+//! FFI Process Memory
+//!
+//! Reading and writing the memory of other processes, for debugging and
+//! analysis tooling. Gated behind the `process-memory` permission.
+
+use crate::memory::get_ptr_from_value;
+use lux_utils::process::{Permission, ProcessPermissions};
+use mlua::prelude::*;
+
+/// A loaded module (shared library or executable) in another process
+pub struct ModuleInfo {
+    pub name: String,
+    pub path: String,
+    pub base: usize,
+    pub size: usize,
+}
+
+/// A mapped memory region in another process
+pub struct RegionInfo {
+    pub base: usize,
+    pub size: usize,
+    pub protection: String,
+    pub path: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    use super::{ModuleInfo, RegionInfo};
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::unix::fs::FileExt;
+
+    pub struct Handle {
+        pid: u32,
+        mem: File,
+    }
+
+    impl Handle {
+        pub fn open(pid: u32) -> io::Result<Self> {
+            let path = format!("/proc/{pid}/mem");
+            // Fall back to read-only access if writing is not allowed
+            let mem = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&path)
+                .or_else(|_| File::open(&path))?;
+            Ok(Self { pid, mem })
+        }
+
+        pub fn read(&self, address: usize, buf: &mut [u8]) -> io::Result<()> {
+            self.mem.read_exact_at(buf, address as u64)
+        }
+
+        pub fn write(&self, address: usize, data: &[u8]) -> io::Result<()> {
+            self.mem.write_all_at(data, address as u64)
+        }
+
+        pub fn regions(&self) -> io::Result<Vec<RegionInfo>> {
+            let maps = std::fs::read_to_string(format!("/proc/{}/maps", self.pid))?;
+            Ok(maps.lines().filter_map(parse_maps_line).collect())
+        }
+
+        pub fn modules(&self) -> io::Result<Vec<ModuleInfo>> {
+            let mut modules: Vec<ModuleInfo> = Vec::new();
+            for region in self.regions()? {
+                let Some(path) = region.path.filter(|p| p.starts_with('/')) else {
+                    continue;
+                };
+                // Modules are mapped as several consecutive regions, merge them
+                if let Some(module) = modules.iter_mut().find(|m| m.path == path) {
+                    let end = (module.base + module.size).max(region.base + region.size);
+                    module.base = module.base.min(region.base);
+                    module.size = end - module.base;
+                } else {
+                    let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+                    modules.push(ModuleInfo {
+                        name,
+                        path,
+                        base: region.base,
+                        size: region.size,
+                    });
+                }
+            }
+            Ok(modules)
+        }
+    }
+
+    fn parse_maps_line(line: &str) -> Option<RegionInfo> {
+        let mut parts = line.split_whitespace();
+        let (start, end) = parts.next()?.split_once('-')?;
+        let start = usize::from_str_radix(start, 16).ok()?;
+        let end = usize::from_str_radix(end, 16).ok()?;
+        let protection = parts.next()?.to_string();
+        // Skip offset, device and inode - the rest of the line is the path
+        let path = parts.nth(3).map(|first| {
+            std::iter::once(first)
+                .chain(parts)
+                .collect::<Vec<_>>()
+                .join(" ")
+        });
+        Some(RegionInfo {
+            base: start,
+            size: end - start,
+            protection,
+            path,
+        })
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use super::{ModuleInfo, RegionInfo};
+    use std::ffi::c_void;
+    use std::io;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, HMODULE};
+    use windows_sys::Win32::System::Diagnostics::Debug::{ReadProcessMemory, WriteProcessMemory};
+    use windows_sys::Win32::System::Memory::{
+        MEM_COMMIT, MEMORY_BASIC_INFORMATION, PAGE_EXECUTE, PAGE_EXECUTE_READ,
+        PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY, PAGE_READONLY, PAGE_READWRITE,
+        PAGE_WRITECOPY, VirtualQueryEx,
+    };
+    use windows_sys::Win32::System::ProcessStatus::{
+        EnumProcessModulesEx, GetModuleFileNameExW, GetModuleInformation, LIST_MODULES_ALL,
+        MODULEINFO,
+    };
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_OPERATION, PROCESS_VM_READ,
+        PROCESS_VM_WRITE,
+    };
+
+    pub struct Handle {
+        handle: HANDLE,
+    }
+
+    impl Handle {
+        pub fn open(pid: u32) -> io::Result<Self> {
+            let access = PROCESS_QUERY_INFORMATION
+                | PROCESS_VM_READ
+                | PROCESS_VM_WRITE
+                | PROCESS_VM_OPERATION;
+            let handle = unsafe { OpenProcess(access, 0, pid) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { handle })
+        }
+
+        pub fn read(&self, address: usize, buf: &mut [u8]) -> io::Result<()> {
+            let mut read = 0;
+            let ok = unsafe {
+                ReadProcessMemory(
+                    self.handle,
+                    address as *const c_void,
+                    buf.as_mut_ptr().cast(),
+                    buf.len(),
+                    &raw mut read,
+                )
+            };
+            if ok == 0 || read != buf.len() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        pub fn write(&self, address: usize, data: &[u8]) -> io::Result<()> {
+            let mut written = 0;
+            let ok = unsafe {
+                WriteProcessMemory(
+                    self.handle,
+                    address as *const c_void,
+                    data.as_ptr().cast(),
+                    data.len(),
+                    &raw mut written,
+                )
+            };
+            if ok == 0 || written != data.len() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        pub fn regions(&self) -> io::Result<Vec<RegionInfo>> {
+            let mut regions = Vec::new();
+            let mut address = 0usize;
+            loop {
+                let mut info: MEMORY_BASIC_INFORMATION = unsafe { std::mem::zeroed() };
+                let len = unsafe {
+                    VirtualQueryEx(
+                        self.handle,
+                        address as *const c_void,
+                        &raw mut info,
+                        std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+                    )
+                };
+                if len == 0 {
+                    break;
+                }
+                if info.State == MEM_COMMIT {
+                    regions.push(RegionInfo {
+                        base: info.BaseAddress as usize,
+                        size: info.RegionSize,
+                        protection: protection_string(info.Protect),
+                        path: None,
+                    });
+                }
+                match (info.BaseAddress as usize).checked_add(info.RegionSize) {
+                    Some(next) if next > address => address = next,
+                    _ => break,
+                }
+            }
+            Ok(regions)
+        }
+
+        pub fn modules(&self) -> io::Result<Vec<ModuleInfo>> {
+            let mut needed = 0u32;
+            let mut handles: Vec<HMODULE> = vec![std::ptr::null_mut(); 1024];
+            loop {
+                let size = (handles.len() * std::mem::size_of::<HMODULE>()) as u32;
+                let ok = unsafe {
+                    EnumProcessModulesEx(
+                        self.handle,
+                        handles.as_mut_ptr(),
+                        size,
+                        &raw mut needed,
+                        LIST_MODULES_ALL,
+                    )
+                };
+                if ok == 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if needed <= size {
+                    break;
+                }
+                handles.resize(
+                    needed as usize / std::mem::size_of::<HMODULE>(),
+                    std::ptr::null_mut(),
+                );
+            }
+            handles.truncate(needed as usize / std::mem::size_of::<HMODULE>());
+
+            let mut modules = Vec::with_capacity(handles.len());
+            for module in handles {
+                let mut info: MODULEINFO = unsafe { std::mem::zeroed() };
+                let ok = unsafe {
+                    GetModuleInformation(
+                        self.handle,
+                        module,
+                        &raw mut info,
+                        std::mem::size_of::<MODULEINFO>() as u32,
+                    )
+                };
+                if ok == 0 {
+                    continue;
+                }
+                let mut name = [0u16; 1024];
+                let len = unsafe {
+                    GetModuleFileNameExW(self.handle, module, name.as_mut_ptr(), name.len() as u32)
+                };
+                let path = String::from_utf16_lossy(&name[..len as usize]);
+                modules.push(ModuleInfo {
+                    name: path.rsplit('\\').next().unwrap_or(&path).to_string(),
+                    path,
+                    base: info.lpBaseOfDll as usize,
+                    size: info.SizeOfImage as usize,
+                });
+            }
+            Ok(modules)
+        }
+    }
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.handle) };
+        }
+    }
+
+    fn protection_string(protect: u32) -> String {
+        let (r, w, x) = match protect & 0xFF {
+            PAGE_READONLY => (true, false, false),
+            PAGE_READWRITE | PAGE_WRITECOPY => (true, true, false),
+            PAGE_EXECUTE => (false, false, true),
+            PAGE_EXECUTE_READ => (true, false, true),
+            PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY => (true, true, true),
+            _ => (false, false, false),
+        };
+        format!(
+            "{}{}{}p",
+            if r { 'r' } else { '-' },
+            if w { 'w' } else { '-' },
+            if x { 'x' } else { '-' }
+        )
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod os {
+    use super::{ModuleInfo, RegionInfo};
+    use std::io;
+
+    pub struct Handle;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "process memory access is not supported on this platform",
+        )
+    }
+
+    impl Handle {
+        pub fn open(_pid: u32) -> io::Result<Self> {
+            Err(unsupported())
+        }
+        pub fn read(&self, _address: usize, _buf: &mut [u8]) -> io::Result<()> {
+            Err(unsupported())
+        }
+        pub fn write(&self, _address: usize, _data: &[u8]) -> io::Result<()> {
+            Err(unsupported())
+        }
+        pub fn regions(&self) -> io::Result<Vec<RegionInfo>> {
+            Err(unsupported())
+        }
+        pub fn modules(&self) -> io::Result<Vec<ModuleInfo>> {
+            Err(unsupported())
+        }
+    }
+}
+
+/// Converts an address given as a number, pointer or cdata
+fn to_address(value: &LuaValue) -> LuaResult<usize> {
+    match value {
+        LuaValue::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Ok(*n as usize),
+        LuaValue::Integer(i) if *i >= 0 => Ok(*i as usize),
+        LuaValue::LightUserData(_) | LuaValue::UserData(_) => {
+            Ok(get_ptr_from_value(value)? as usize)
+        }
+        _ => Err(LuaError::external(format!(
+            "ffi.process: expected an address, got {}",
+            value.type_name()
+        ))),
+    }
+}
+
+/// An opened process (ffi.process.open)
+pub struct ProcessHandle {
+    pid: u32,
+    handle: Option<os::Handle>,
+}
+
+impl ProcessHandle {
+    fn handle(&self) -> LuaResult<&os::Handle> {
+        self.handle.as_ref().ok_or_else(|| {
+            LuaError::external(format!("ffi.process: process {} is closed", self.pid))
+        })
+    }
+
+    fn io_error(&self, action: &str, address: usize, err: std::io::Error) -> LuaError {
+        LuaError::external(format!(
+            "ffi.process: failed to {action} memory at {address:#x} in process {}: {err}",
+            self.pid
+        ))
+    }
+}
+
+impl LuaUserData for ProcessHandle {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("pid", |_, this| Ok(this.pid));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // proc:read(address, size) - copy memory out into a buffer
+        methods.add_method("read", |lua, this, (address, size): (LuaValue, usize)| {
+            let address = to_address(&address)?;
+            let mut data = vec![0u8; size];
+            this.handle()?
+                .read(address, &mut data)
+                .map_err(|e| this.io_error("read", address, e))?;
+            lua.create_buffer(data)
+        });
+
+        // proc:write(address, data) - copy a string or buffer in
+        methods.add_method("write", |_, this, (address, data): (LuaValue, LuaValue)| {
+            let address = to_address(&address)?;
+            let bytes = match &data {
+                LuaValue::String(s) => s.as_bytes().to_vec(),
+                LuaValue::Buffer(b) => b.to_vec(),
+                _ => {
+                    return Err(LuaError::external(
+                        "ffi.process: data must be a string or buffer",
+                    ));
+                }
+            };
+            this.handle()?
+                .write(address, &bytes)
+                .map_err(|e| this.io_error("write", address, e))
+        });
+
+        // proc:modules() - loaded executables and shared libraries
+        methods.add_method("modules", |lua, this, ()| {
+            let modules = this.handle()?.modules().map_err(LuaError::external)?;
+            let list = lua.create_table_with_capacity(modules.len(), 0)?;
+            for module in modules {
+                let t = lua.create_table_with_capacity(0, 4)?;
+                t.set("name", module.name)?;
+                t.set("path", module.path)?;
+                t.set("base", module.base)?;
+                t.set("size", module.size)?;
+                list.push(t)?;
+            }
+            Ok(list)
+        });
+
+        // proc:regions() - mapped memory regions and their protection
+        methods.add_method("regions", |lua, this, ()| {
+            let regions = this.handle()?.regions().map_err(LuaError::external)?;
+            let list = lua.create_table_with_capacity(regions.len(), 0)?;
+            for region in regions {
+                let t = lua.create_table_with_capacity(0, 4)?;
+                t.set("base", region.base)?;
+                t.set("size", region.size)?;
+                t.set("protection", region.protection)?;
+                t.set("path", region.path)?;
+                list.push(t)?;
+            }
+            Ok(list)
+        });
+
+        methods.add_method_mut("close", |_, this, ()| {
+            this.handle = None;
+            Ok(())
+        });
+    }
+}
+
+/// ffi.process.open(pid) - requires the process-memory permission
+fn process_open(lua: &Lua, pid: u32) -> LuaResult<ProcessHandle> {
+    ProcessPermissions::check(lua, Permission::ProcessMemory, "ffi.process.open")?;
+    let handle = os::Handle::open(pid).map_err(|e| {
+        LuaError::external(format!("ffi.process: failed to open process {pid}: {e}"))
+    })?;
+    Ok(ProcessHandle {
+        pid,
+        handle: Some(handle),
+    })
+}
+
+/// Creates the ffi.process table
+pub(crate) fn create_process_table(lua: &Lua) -> LuaResult<LuaTable> {
+    let process = lua.create_table()?;
+    process.set("open", lua.create_function(process_open)?)?;
+    process.set("pid", std::process::id())?;
+    Ok(process)
+}
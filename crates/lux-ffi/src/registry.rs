@@ -2,16 +2,24 @@
 //!
 //! Stores registered C types, structs, and functions.
 
+use crate::com::{ComInterface, Guid};
 use crate::types::*;
-use std::collections::HashMap;
-use std::sync::{Mutex, OnceLock};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
 
 /// Global registry
 pub struct Registry {
-    structs: HashMap<String, StructDef>,
+    /// Shared, so field access can use a definition without holding the registry lock
+    structs: HashMap<String, Arc<StructDef>>,
     enums: HashMap<String, HashMap<String, i64>>,
     typedefs: HashMap<String, CType>,
     funcs: HashMap<String, FuncSig>,
+    bound: HashSet<String>,
+    interfaces: HashMap<String, ComInterface>,
+    /// The declaration each function currently comes from, so resolved functions know to re-resolve
+    func_versions: HashMap<String, u64>,
+    /// How many functions have been declared so far, used to number declarations
+    declarations: u64,
 }
 
 impl Registry {
@@ -23,13 +31,17 @@ impl Registry {
                 enums: HashMap::new(),
                 typedefs: HashMap::new(),
                 funcs: HashMap::new(),
+                bound: HashSet::new(),
+                interfaces: HashMap::new(),
+                func_versions: HashMap::new(),
+                declarations: 0,
             })
         });
         instance.lock().unwrap()
     }
 
     pub fn add_struct(&mut self, def: StructDef) {
-        self.structs.insert(def.name.clone(), def);
+        self.structs.insert(def.name.clone(), Arc::new(def));
     }
 
     pub fn add_enum(&mut self, name: &str, values: HashMap<String, i64>) {
@@ -41,11 +53,33 @@ impl Registry {
     }
 
     pub fn add_func(&mut self, sig: FuncSig) {
+        self.declarations += 1;
+        self.func_versions
+            .insert(sig.name.clone(), self.declarations);
         self.funcs.insert(sig.name.clone(), sig);
     }
 
+    pub fn add_interface(&mut self, interface: ComInterface) {
+        self.interfaces.insert(interface.name.clone(), interface);
+    }
+
+    /// Marks a struct as bound, enabling checked field access (`// @bind`)
+    pub fn bind_struct(&mut self, name: &str) {
+        self.bound.insert(name.to_string());
+    }
+
+    #[must_use]
+    pub fn is_bound(&self, name: &str) -> bool {
+        self.bound.contains(name)
+    }
+
+    /// A struct definition that can be used after the registry is unlocked
+    pub fn get_struct_shared(&self, name: &str) -> Option<Arc<StructDef>> {
+        self.structs.get(name).cloned()
+    }
+
     pub fn get_struct(&self, name: &str) -> Option<&StructDef> {
-        self.structs.get(name)
+        self.structs.get(name).map(Arc::as_ref)
     }
 
     pub fn has_struct(&self, name: &str) -> bool {
@@ -64,10 +98,26 @@ impl Registry {
         self.typedefs.get(name).cloned()
     }
 
+    /// Which declaration of a function is current, which changes every time `cdef` declares it
+    #[must_use]
+    pub fn func_version(&self, name: &str) -> Option<u64> {
+        self.func_versions.get(name).copied()
+    }
+
     pub fn get_func(&self, name: &str) -> Option<FuncSig> {
         self.funcs.get(name).cloned()
     }
 
+    #[must_use]
+    pub fn get_interface(&self, name: &str) -> Option<ComInterface> {
+        self.interfaces.get(name).cloned()
+    }
+
+    #[must_use]
+    pub fn find_interface(&self, iid: Guid) -> Option<ComInterface> {
+        self.interfaces.values().find(|i| i.iid == iid).cloned()
+    }
+
     pub fn struct_size(&self, name: &str) -> Option<usize> {
         self.structs.get(name).map(|s| s.size)
     }
@@ -82,7 +132,10 @@ impl Registry {
     }
 
     pub fn all_structs(&self) -> HashMap<String, StructDef> {
-        self.structs.clone()
+        self.structs
+            .iter()
+            .map(|(name, def)| (name.clone(), StructDef::clone(def)))
+            .collect()
     }
 
     pub fn all_enums(&self) -> HashMap<String, HashMap<String, i64>> {
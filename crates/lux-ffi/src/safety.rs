@@ -0,0 +1,262 @@
+//! FFI Safe Mode
+//!
+//! Opt-in checks on memory accessed through cdata, turning reads and writes
+//! that would crash the process - out of bounds, NULL or misaligned - into Lua errors.
+
+use crate::debug;
+use crate::memory::CBox;
+use crate::mmap::MemoryMap;
+use lux_utils::fmt::Label;
+use lux_utils::process::ProcessFfiSafeMode;
+use mlua::prelude::*;
+use std::collections::HashSet;
+use std::ffi::c_void;
+
+/// Smallest page size of any supported platform, probes touch every page at least once
+const PROBE_PAGE_SIZE: usize = 4096;
+
+/// The allocation that a cdata value points into, if it is known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub base: usize,
+    pub len: usize,
+}
+
+impl Region {
+    #[must_use]
+    pub fn new(base: *const c_void, len: usize) -> Self {
+        Self {
+            base: base as usize,
+            len,
+        }
+    }
+
+    fn contains(self, address: usize, size: usize) -> bool {
+        address >= self.base
+            && address
+                .checked_add(size)
+                .is_some_and(|end| end <= self.base + self.len)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl Access {
+    fn verb(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+}
+
+/// Returns whether safe mode is enabled for the given Lua state
+#[must_use]
+pub fn is_enabled(lua: &Lua) -> bool {
+    lua.app_data_ref::<ProcessFfiSafeMode>()
+        .is_some_and(|mode| mode.enabled())
+}
+
+/// `ffi.setSafeMode(enabled)`
+pub(crate) fn ffi_set_safe_mode(lua: &Lua, enabled: bool) -> LuaResult<()> {
+    lua.set_app_data(ProcessFfiSafeMode::new(enabled));
+    Ok(())
+}
+
+/// `ffi.isSafeMode()`
+pub(crate) fn ffi_is_safe_mode(lua: &Lua, (): ()) -> LuaResult<bool> {
+    Ok(is_enabled(lua))
+}
+
+/// Returns the allocation that a value points into, if it is cdata with a known allocation
+#[must_use]
+pub fn region_of(value: &LuaValue) -> Option<Region> {
+    match value {
+        LuaValue::UserData(ud) => match ud.borrow::<CBox>() {
+            Ok(b) => b.region(),
+            Err(_) => ud.borrow::<MemoryMap>().ok().and_then(|map| map.region()),
+        },
+        _ => None,
+    }
+}
+
+/**
+    Checks an access of `size` bytes at `ptr`, which must be aligned to `align`.
+
+    Accesses within a known allocation are bounds-checked against it, while reads
+    through foreign pointers are probed first where the platform allows it.
+
+    # Errors
+
+    Errors if the access is through a NULL pointer, misaligned,
+    out of bounds of its allocation, or into unreadable memory.
+*/
+pub fn check(
+    ptr: *const c_void,
+    size: usize,
+    align: usize,
+    region: Option<Region>,
+    access: Access,
+) -> LuaResult<()> {
+    let verb = access.verb();
+    let address = ptr as usize;
+    if ptr.is_null() {
+        return Err(LuaError::external(format!(
+            "Attempt to {verb} through a NULL pointer"
+        )));
+    }
+    if align > 1 && !address.is_multiple_of(align) {
+        return Err(LuaError::external(format!(
+            "Attempt to {verb} misaligned address {address:#x}, which must be aligned to {align} bytes"
+        )));
+    }
+    match region {
+        Some(region) if !region.contains(address, size) => Err(LuaError::external(format!(
+            "Attempt to {verb} {size} bytes at offset {} of a {} byte allocation",
+            address.wrapping_sub(region.base) as isize,
+            region.len
+        ))),
+        None if access == Access::Read && probe_readable(address, size) == Some(false) => {
+            Err(LuaError::external(format!(
+                "Attempt to {verb} unreadable memory at {address:#x}"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Writes to `const` memory that have been warned about, so that each is only warned about once
+#[derive(Default)]
+struct ConstWrites(HashSet<String>);
+
+/**
+    Warns about a write to memory that was declared `const`, such as through a `const char*`,
+    once for every line of the script that does so.
+
+    The write still happens, since C code commonly leaves out `const` where it could be used.
+*/
+pub(crate) fn warn_const_write(lua: &Lua, what: impl FnOnce() -> String) {
+    let message = format!("Write to const {}{}", what(), debug::script_location(lua));
+    if lua.app_data_ref::<ConstWrites>().is_none() {
+        lua.set_app_data(ConstWrites::default());
+    }
+    let first = lua
+        .app_data_mut::<ConstWrites>()
+        .is_some_and(|mut warned| warned.0.insert(message.clone()));
+    if first {
+        eprintln!("{} {message}", Label::Warn);
+    }
+}
+
+/**
+    Returns the length of the NUL-terminated string at `ptr`,
+    without reading past its allocation or into unreadable memory.
+
+    # Errors
+
+    Errors if `ptr` is NULL or unreadable, or if no terminator is found
+    before the end of its allocation or of readable memory.
+*/
+pub fn c_string_len(ptr: *const c_void, region: Option<Region>) -> LuaResult<usize> {
+    check(ptr, 1, 1, region, Access::Read)?;
+    let start = ptr as usize;
+    let limit = region.map_or(usize::MAX, |r| r.base + r.len);
+    let mut address = start;
+    while address < limit {
+        // Scan up to the end of the page, which is readable if its first byte is
+        let page_end = (address / PROBE_PAGE_SIZE + 1) * PROBE_PAGE_SIZE;
+        let chunk_end = page_end.min(limit);
+        if region.is_none() && probe_readable(address, 1) == Some(false) {
+            break;
+        }
+        let chunk =
+            unsafe { std::slice::from_raw_parts(address as *const u8, chunk_end - address) };
+        if let Some(nul) = chunk.iter().position(|&b| b == 0) {
+            return Ok(address + nul - start);
+        }
+        address = chunk_end;
+    }
+    Err(LuaError::external(format!(
+        "String at {start:#x} is not terminated within readable memory"
+    )))
+}
+
+/**
+    Returns whether every byte of the range can be read,
+    or `None` if memory can not be probed on this platform.
+*/
+fn probe_readable(address: usize, size: usize) -> Option<bool> {
+    let last = address.checked_add(size.max(1) - 1)?;
+    let mut probe = address;
+    loop {
+        if !os::probe_byte(probe)? {
+            return Some(false);
+        }
+        let next_page = (probe / PROBE_PAGE_SIZE + 1) * PROBE_PAGE_SIZE;
+        if next_page > last {
+            return Some(true);
+        }
+        probe = next_page;
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod os {
+    /// Reads a byte of our own memory through the kernel, which fails instead of faulting
+    pub fn probe_byte(address: usize) -> Option<bool> {
+        let mut byte = 0u8;
+        let local = libc::iovec {
+            iov_base: (&raw mut byte).cast(),
+            iov_len: 1,
+        };
+        let remote = libc::iovec {
+            iov_base: address as *mut libc::c_void,
+            iov_len: 1,
+        };
+        let read = unsafe {
+            libc::process_vm_readv(libc::getpid(), &raw const local, 1, &raw const remote, 1, 0)
+        };
+        if read == 1 {
+            Some(true)
+        } else if std::io::Error::last_os_error().raw_os_error() == Some(libc::EFAULT) {
+            Some(false)
+        } else {
+            // Blocked by a sandbox or similar, so the result says nothing about the address
+            None
+        }
+    }
+}
+
+#[cfg(windows)]
+mod os {
+    use std::ffi::c_void;
+    use windows_sys::Win32::System::Diagnostics::Debug::ReadProcessMemory;
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    /// Reads a byte of our own memory through the kernel, which fails instead of faulting
+    pub fn probe_byte(address: usize) -> Option<bool> {
+        let mut byte = 0u8;
+        let mut read = 0;
+        let ok = unsafe {
+            ReadProcessMemory(
+                GetCurrentProcess(),
+                address as *const c_void,
+                (&raw mut byte).cast(),
+                1,
+                &raw mut read,
+            )
+        };
+        Some(ok != 0 && read == 1)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod os {
+    pub fn probe_byte(_address: usize) -> Option<bool> {
+        None
+    }
+}
@@ -0,0 +1,347 @@
+//! FFI Shared Memory
+//!
+//! Named shared memory segments (`shm_open` / `CreateFileMapping`) that can be
+//! viewed as cdata, for exchanging data with other native processes.
+
+use crate::memory::CBox;
+use crate::types::CType;
+use mlua::prelude::*;
+use std::ffi::c_void;
+use std::sync::Arc;
+
+/// A mapped shared memory segment, unmapped when the last reference is dropped
+struct Mapping {
+    ptr: *mut c_void,
+    size: usize,
+    #[cfg(unix)]
+    name: std::ffi::CString,
+    #[cfg(unix)]
+    owner: bool,
+    #[cfg(windows)]
+    handle: windows_sys::Win32::Foundation::HANDLE,
+}
+
+// The mapping is plain process-wide memory, access is synchronized by the user
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+#[cfg(unix)]
+impl Mapping {
+    fn os_name(name: &str) -> LuaResult<std::ffi::CString> {
+        let name = if name.starts_with('/') {
+            name.to_string()
+        } else {
+            format!("/{name}")
+        };
+        std::ffi::CString::new(name)
+            .map_err(|_| LuaError::external("ffi.shm: name must not contain null bytes"))
+    }
+
+    fn create(name: &str, size: usize) -> LuaResult<Self> {
+        let os_name = Self::os_name(name)?;
+        unsafe {
+            let fd = libc::shm_open(
+                os_name.as_ptr(),
+                libc::O_CREAT | libc::O_EXCL | libc::O_RDWR,
+                0o600,
+            );
+            if fd < 0 {
+                return Err(last_error("create", name));
+            }
+            if libc::ftruncate(fd, size as libc::off_t) != 0 {
+                let err = last_error("resize", name);
+                libc::close(fd);
+                libc::shm_unlink(os_name.as_ptr());
+                return Err(err);
+            }
+            let mapping = Self::map(fd, size, os_name, true);
+            if mapping.is_err() {
+                libc::shm_unlink(Self::os_name(name)?.as_ptr());
+            }
+            mapping
+        }
+    }
+
+    fn open(name: &str) -> LuaResult<Self> {
+        let os_name = Self::os_name(name)?;
+        unsafe {
+            let fd = libc::shm_open(os_name.as_ptr(), libc::O_RDWR, 0);
+            if fd < 0 {
+                return Err(last_error("open", name));
+            }
+            let mut stat: libc::stat = std::mem::zeroed();
+            if libc::fstat(fd, &raw mut stat) != 0 {
+                let err = last_error("open", name);
+                libc::close(fd);
+                return Err(err);
+            }
+            Self::map(fd, stat.st_size as usize, os_name, false)
+        }
+    }
+
+    unsafe fn map(fd: i32, size: usize, name: std::ffi::CString, owner: bool) -> LuaResult<Self> {
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            size.max(1),
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        );
+        // The mapping stays valid after the descriptor is closed
+        libc::close(fd);
+        if ptr == libc::MAP_FAILED {
+            return Err(last_error("map", &name.to_string_lossy()));
+        }
+        Ok(Self {
+            ptr,
+            size,
+            name,
+            owner,
+        })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr, self.size.max(1));
+            if self.owner {
+                libc::shm_unlink(self.name.as_ptr());
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Mapping {
+    fn os_name(name: &str) -> Vec<u16> {
+        name.trim_start_matches('/')
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect()
+    }
+
+    fn create(name: &str, size: usize) -> LuaResult<Self> {
+        use windows_sys::Win32::Foundation::{
+            CloseHandle, ERROR_ALREADY_EXISTS, GetLastError, INVALID_HANDLE_VALUE,
+        };
+        use windows_sys::Win32::System::Memory::{CreateFileMappingW, PAGE_READWRITE};
+
+        let os_name = Self::os_name(name);
+        let size64 = size as u64;
+        unsafe {
+            let handle = CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                std::ptr::null(),
+                PAGE_READWRITE,
+                (size64 >> 32) as u32,
+                size64 as u32,
+                os_name.as_ptr(),
+            );
+            if handle.is_null() {
+                return Err(last_error("create", name));
+            }
+            if GetLastError() == ERROR_ALREADY_EXISTS {
+                CloseHandle(handle);
+                return Err(LuaError::external(format!(
+                    "ffi.shm: failed to create '{name}': segment already exists"
+                )));
+            }
+            Self::map(handle, name, size)
+        }
+    }
+
+    fn open(name: &str) -> LuaResult<Self> {
+        use windows_sys::Win32::System::Memory::{FILE_MAP_ALL_ACCESS, OpenFileMappingW};
+
+        let os_name = Self::os_name(name);
+        unsafe {
+            let handle = OpenFileMappingW(FILE_MAP_ALL_ACCESS, 0, os_name.as_ptr());
+            if handle.is_null() {
+                return Err(last_error("open", name));
+            }
+            Self::map(handle, name, 0)
+        }
+    }
+
+    unsafe fn map(
+        handle: windows_sys::Win32::Foundation::HANDLE,
+        name: &str,
+        size: usize,
+    ) -> LuaResult<Self> {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Memory::{
+            FILE_MAP_ALL_ACCESS, MEMORY_BASIC_INFORMATION, MapViewOfFile, VirtualQuery,
+        };
+
+        let view = MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, size);
+        if view.Value.is_null() {
+            let err = last_error("map", name);
+            CloseHandle(handle);
+            return Err(err);
+        }
+        let size = if size == 0 {
+            // Opened segments report their size through the mapped region
+            let mut info: MEMORY_BASIC_INFORMATION = std::mem::zeroed();
+            VirtualQuery(
+                view.Value,
+                &raw mut info,
+                std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+            );
+            info.RegionSize
+        } else {
+            size
+        };
+        Ok(Self {
+            ptr: view.Value,
+            size,
+            handle,
+        })
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::Memory::{MEMORY_MAPPED_VIEW_ADDRESS, UnmapViewOfFile};
+        unsafe {
+            UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS { Value: self.ptr });
+            CloseHandle(self.handle);
+        }
+    }
+}
+
+fn last_error(action: &str, name: &str) -> LuaError {
+    LuaError::external(format!(
+        "ffi.shm: failed to {action} '{name}': {}",
+        std::io::Error::last_os_error()
+    ))
+}
+
+/// Keeps a mapping alive for as long as a cdata view of it exists
+struct MappingGuard(#[allow(dead_code)] Arc<Mapping>);
+
+impl LuaUserData for MappingGuard {}
+
+/// A named shared memory segment (`ffi.shm.create` / `ffi.shm.open`)
+pub struct SharedMemory {
+    name: String,
+    mapping: Option<Arc<Mapping>>,
+}
+
+impl SharedMemory {
+    fn mapping(&self) -> LuaResult<&Arc<Mapping>> {
+        self.mapping
+            .as_ref()
+            .ok_or_else(|| LuaError::external(format!("ffi.shm: '{}' is closed", self.name)))
+    }
+
+    fn range(&self, offset: usize, len: usize) -> LuaResult<*mut u8> {
+        let mapping = self.mapping()?;
+        match offset.checked_add(len) {
+            Some(end) if end <= mapping.size => Ok(unsafe { mapping.ptr.cast::<u8>().add(offset) }),
+            _ => Err(LuaError::external(format!(
+                "ffi.shm: range {offset}..{} is out of bounds for segment of {} bytes",
+                offset.saturating_add(len),
+                mapping.size
+            ))),
+        }
+    }
+}
+
+impl LuaUserData for SharedMemory {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("name", |_, this| Ok(this.name.clone()));
+        fields.add_field_method_get("size", |_, this| Ok(this.mapping()?.size));
+        fields.add_field_method_get("ptr", |_, this| Ok(LuaLightUserData(this.mapping()?.ptr)));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        // shm:view(type) - cdata over the segment, "uint8_t*" by default
+        methods.add_method("view", |lua, this, type_name: Option<String>| {
+            let type_name = type_name.unwrap_or_else(|| "uint8_t*".to_string());
+            let ctype = CType::parse(&type_name)
+                .ok_or_else(|| LuaError::external(format!("Unknown type: {type_name}")))?;
+            let mapping = this.mapping()?;
+
+            let view = lua.create_userdata(CBox::from_raw(mapping.ptr, ctype, false))?;
+            view.set_user_value(MappingGuard(Arc::clone(mapping)))?;
+            Ok(view)
+        });
+
+        // shm:read(offset, len) - copy bytes out as a string
+        methods.add_method(
+            "read",
+            |lua, this, (offset, len): (usize, Option<usize>)| {
+                let len = len.unwrap_or(this.mapping()?.size.saturating_sub(offset));
+                let ptr = this.range(offset, len)?;
+                lua.create_string(unsafe { std::slice::from_raw_parts(ptr, len) })
+            },
+        );
+
+        // shm:write(offset, data) - copy a string or buffer in
+        methods.add_method("write", |_, this, (offset, data): (usize, LuaValue)| {
+            let bytes = match &data {
+                LuaValue::String(s) => s.as_bytes().to_vec(),
+                LuaValue::Buffer(b) => b.to_vec(),
+                _ => {
+                    return Err(LuaError::external(
+                        "ffi.shm: data must be a string or buffer",
+                    ));
+                }
+            };
+            let ptr = this.range(offset, bytes.len())?;
+            unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len()) };
+            Ok(())
+        });
+
+        // shm:close() - release this handle, views keep the mapping alive until collected
+        methods.add_method_mut("close", |_, this, ()| {
+            this.mapping = None;
+            Ok(())
+        });
+
+        methods.add_meta_method(LuaMetaMethod::Len, |_, this, ()| Ok(this.mapping()?.size));
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(match &this.mapping {
+                Some(m) => format!("shm<{}: {} bytes>", this.name, m.size),
+                None => format!("shm<{}: closed>", this.name),
+            })
+        });
+    }
+}
+
+/// ffi.shm.create(name, size) - create a new segment, removed when the creator releases it
+fn shm_create(_lua: &Lua, (name, size): (String, usize)) -> LuaResult<SharedMemory> {
+    if size == 0 {
+        return Err(LuaError::external(
+            "ffi.shm.create: size must be greater than 0",
+        ));
+    }
+    let mapping = Mapping::create(&name, size)?;
+    Ok(SharedMemory {
+        name,
+        mapping: Some(Arc::new(mapping)),
+    })
+}
+
+/// ffi.shm.open(name) - open an existing segment created by another process
+fn shm_open(_lua: &Lua, name: String) -> LuaResult<SharedMemory> {
+    let mapping = Mapping::open(&name)?;
+    Ok(SharedMemory {
+        name,
+        mapping: Some(Arc::new(mapping)),
+    })
+}
+
+/// Creates the ffi.shm table
+pub(crate) fn create_shm_table(lua: &Lua) -> LuaResult<LuaTable> {
+    let shm = lua.create_table()?;
+    shm.set("create", lua.create_function(shm_create)?)?;
+    shm.set("open", lua.create_function(shm_open)?)?;
+    Ok(shm)
+}
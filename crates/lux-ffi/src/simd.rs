@@ -0,0 +1,207 @@
+//! FFI SIMD - Vectorized numeric kernels
+//!
+//! Kernels are written over fixed-size lanes that the compiler turns into vector
+//! instructions, and are compiled twice on x86-64 - once for the baseline target
+//! and once with AVX2 and FMA - picking between the two at runtime.
+//!
+//! All pointers may be unaligned, and outputs may alias inputs at the same index.
+
+// NOTE: Kernel bodies must be inlined into each version to get compiled with its target features
+#![allow(clippy::inline_always)]
+
+const LANES: usize = 4;
+
+/// Compile a kernel for the baseline target, and for AVX2 + FMA where it is detected at runtime
+macro_rules! multiversion {
+    ($(#[$meta:meta])* $vis:vis unsafe fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)? => $body:ident;) => {
+        $(#[$meta])*
+        $vis unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+            #[cfg(target_arch = "x86_64")]
+            {
+                #[target_feature(enable = "avx2,fma")]
+                unsafe fn avx2($($arg: $ty),*) $(-> $ret)? {
+                    $body($($arg),*)
+                }
+                if has_avx2() {
+                    return avx2($($arg),*);
+                }
+            }
+            $body($($arg),*)
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+fn has_avx2() -> bool {
+    std::is_x86_feature_detected!("avx2") && std::is_x86_feature_detected!("fma")
+}
+
+/// The instruction set that kernels run with on this machine
+pub fn backend() -> &'static str {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        return "avx2";
+    }
+    if cfg!(target_arch = "x86_64") {
+        "sse2"
+    } else if cfg!(target_arch = "aarch64") {
+        "neon"
+    } else {
+        "scalar"
+    }
+}
+
+#[inline(always)]
+unsafe fn load(ptr: *const f64, index: usize) -> [f64; LANES] {
+    std::array::from_fn(|lane| ptr.add(index + lane).read_unaligned())
+}
+
+#[inline(always)]
+unsafe fn store(ptr: *mut f64, index: usize, values: [f64; LANES]) {
+    for (lane, value) in values.into_iter().enumerate() {
+        ptr.add(index + lane).write_unaligned(value);
+    }
+}
+
+/// Apply `op` to every index, a full lane at a time and then the remainder
+#[inline(always)]
+unsafe fn zip_map<const N: usize>(
+    inputs: [*const f64; N],
+    out: *mut f64,
+    count: usize,
+    op: impl Fn([f64; N]) -> f64,
+) {
+    let whole = count - count % LANES;
+    let mut index = 0;
+    while index < whole {
+        let lanes = inputs.map(|input| load(input, index));
+        store(
+            out,
+            index,
+            std::array::from_fn(|lane| op(std::array::from_fn(|i| lanes[i][lane]))),
+        );
+        index += LANES;
+    }
+    for index in whole..count {
+        out.add(index)
+            .write_unaligned(op(inputs.map(|input| input.add(index).read_unaligned())));
+    }
+}
+
+#[inline(always)]
+unsafe fn add_impl(a: *const f64, b: *const f64, out: *mut f64, count: usize) {
+    zip_map([a, b], out, count, |[a, b]| a + b);
+}
+
+#[inline(always)]
+unsafe fn mul_impl(a: *const f64, b: *const f64, out: *mut f64, count: usize) {
+    zip_map([a, b], out, count, |[a, b]| a * b);
+}
+
+#[inline(always)]
+unsafe fn fma_impl(a: *const f64, b: *const f64, c: *const f64, out: *mut f64, count: usize) {
+    zip_map([a, b, c], out, count, |[a, b, c]| a.mul_add(b, c));
+}
+
+#[inline(always)]
+unsafe fn minmax_impl(a: *const f64, count: usize) -> (f64, f64) {
+    let mut min = [f64::INFINITY; LANES];
+    let mut max = [f64::NEG_INFINITY; LANES];
+    let whole = count - count % LANES;
+    let mut index = 0;
+    while index < whole {
+        let values = load(a, index);
+        for lane in 0..LANES {
+            min[lane] = min[lane].min(values[lane]);
+            max[lane] = max[lane].max(values[lane]);
+        }
+        index += LANES;
+    }
+    let mut min = min.into_iter().fold(f64::INFINITY, f64::min);
+    let mut max = max.into_iter().fold(f64::NEG_INFINITY, f64::max);
+    for index in whole..count {
+        let value = a.add(index).read_unaligned();
+        min = min.min(value);
+        max = max.max(value);
+    }
+    (min, max)
+}
+
+/// Kahan summation, with one running sum and compensation per lane
+#[inline(always)]
+unsafe fn sum_kahan_impl(a: *const f64, count: usize) -> f64 {
+    let mut sums = [0.0; LANES];
+    let mut compensations = [0.0; LANES];
+    let whole = count - count % LANES;
+    let mut index = 0;
+    while index < whole {
+        let values = load(a, index);
+        for lane in 0..LANES {
+            let y = values[lane] - compensations[lane];
+            let t = sums[lane] + y;
+            compensations[lane] = (t - sums[lane]) - y;
+            sums[lane] = t;
+        }
+        index += LANES;
+    }
+
+    // Fold the lanes and the remainder into a single compensated sum
+    let mut sum = 0.0;
+    let mut compensation = 0.0;
+    let lanes = sums.into_iter().chain(compensations.map(|c| -c));
+    let rest = (whole..count).map(|index| a.add(index).read_unaligned());
+    for value in lanes.chain(rest) {
+        let y = value - compensation;
+        let t = sum + y;
+        compensation = (t - sum) - y;
+        sum = t;
+    }
+    sum
+}
+
+/// Transform points by a rotation matrix and translation, given as `[x, y, z, r00, r01, ..., r22]`
+#[inline(always)]
+unsafe fn transform_impl(points: *const f64, cframe: &[f64; 12], out: *mut f64, count: usize) {
+    let [x, y, z, r00, r01, r02, r10, r11, r12, r20, r21, r22] = *cframe;
+    for point in 0..count {
+        let [px, py, pz] = std::array::from_fn(|i| points.add(point * 3 + i).read_unaligned());
+        let transformed = [
+            r00.mul_add(px, r01.mul_add(py, r02 * pz)) + x,
+            r10.mul_add(px, r11.mul_add(py, r12 * pz)) + y,
+            r20.mul_add(px, r21.mul_add(py, r22 * pz)) + z,
+        ];
+        for (i, value) in transformed.into_iter().enumerate() {
+            out.add(point * 3 + i).write_unaligned(value);
+        }
+    }
+}
+
+multiversion! {
+    /// `out[i] = a[i] + b[i]`
+    pub unsafe fn add(a: *const f64, b: *const f64, out: *mut f64, count: usize) => add_impl;
+}
+
+multiversion! {
+    /// `out[i] = a[i] * b[i]`
+    pub unsafe fn mul(a: *const f64, b: *const f64, out: *mut f64, count: usize) => mul_impl;
+}
+
+multiversion! {
+    /// `out[i] = a[i] * b[i] + c[i]`, rounded once
+    pub unsafe fn fma(a: *const f64, b: *const f64, c: *const f64, out: *mut f64, count: usize) => fma_impl;
+}
+
+multiversion! {
+    /// The smallest and largest values, ignoring NaN
+    pub unsafe fn minmax(a: *const f64, count: usize) -> (f64, f64) => minmax_impl;
+}
+
+multiversion! {
+    /// The sum of all values, with compensation for rounding errors
+    pub unsafe fn sum_kahan(a: *const f64, count: usize) -> f64 => sum_kahan_impl;
+}
+
+multiversion! {
+    /// Transform `count` points of three doubles each
+    pub unsafe fn transform(points: *const f64, cframe: &[f64; 12], out: *mut f64, count: usize) => transform_impl;
+}
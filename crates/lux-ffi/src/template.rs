@@ -0,0 +1,153 @@
+//! FFI Type Templates
+//!
+//! Parameterized types for `ffi.typeof`, following LuaJIT - every `$` in a template
+//! is replaced by the next parameter, so that `ffi.typeof("$[10]", point)` is an
+//! array of 10 of whatever type the ctype `point` is.
+
+use crate::memory::CTypeWrapper;
+use crate::types::{CType, strip_qualifiers};
+use mlua::prelude::*;
+
+/// A type declared by a template, which may be a variable-length array
+#[derive(Debug, Clone)]
+pub struct Declared {
+    /// The type itself, or the element type of a variable-length array
+    pub ctype: CType,
+    /// Whether the outermost dimension is `[?]`, with the length given when constructing values
+    pub vla: bool,
+}
+
+impl Declared {
+    /// The C spelling of the type, such as `int[?][4]`
+    #[must_use]
+    pub fn c_name(&self) -> String {
+        let name = self.ctype.c_name();
+        if !self.vla {
+            return name;
+        }
+        match name.find('[') {
+            Some(dims) => format!("{}[?]{}", &name[..dims], &name[dims..]),
+            None => format!("{name}[?]"),
+        }
+    }
+}
+
+/// Parse a type, substituting parameters for each `$` in it
+///
+/// Numbers and strings are pasted into the template as they are, such as lengths
+/// in `int[$]` and names in `struct $`. A ctype can only be given as the base type,
+/// which the pointers and array dimensions in the template are then applied to.
+///
+/// # Errors
+///
+/// Errors if the parameters do not match the template, or the type is unknown.
+pub fn parse(template: &str, params: LuaMultiValue) -> LuaResult<Declared> {
+    let mut params = params.into_iter();
+    let mut text = String::with_capacity(template.len());
+    let mut base = None;
+
+    for c in template.chars() {
+        if c != '$' {
+            text.push(c);
+            continue;
+        }
+        match params.next() {
+            Some(LuaValue::Integer(n)) => text.push_str(&n.to_string()),
+            Some(LuaValue::Number(n)) if n.fract() == 0.0 => {
+                text.push_str(&(n as i64).to_string());
+            }
+            Some(LuaValue::String(s)) => text.push_str(&s.to_str()?),
+            Some(LuaValue::UserData(ud)) if ud.is::<CTypeWrapper>() => {
+                let wrapper = ud.borrow::<CTypeWrapper>()?;
+                if wrapper.vla {
+                    return Err(LuaError::external(format!(
+                        "Variable-length type '{}' can not be used in '{template}'",
+                        wrapper.name
+                    )));
+                }
+                if base.replace(wrapper.ctype.clone()).is_some() {
+                    return Err(LuaError::external(format!(
+                        "Only one ctype can be used in '{template}', as its base type"
+                    )));
+                }
+                text.push('$');
+            }
+            Some(other) => {
+                return Err(LuaError::external(format!(
+                    "Expected a ctype, number or string for '$' in '{template}', got {}",
+                    other.type_name()
+                )));
+            }
+            None => {
+                return Err(LuaError::external(format!(
+                    "Missing parameter for '$' in '{template}'"
+                )));
+            }
+        }
+    }
+    if params.next().is_some() {
+        return Err(LuaError::external(format!(
+            "Too many parameters for '{template}'"
+        )));
+    }
+
+    // Pointers and dimensions follow the base type: "struct Foo*[4]"
+    let text = strip_qualifiers(&text);
+    let (base_text, suffix) = text.split_at(text.find(['*', '[']).unwrap_or(text.len()));
+    let unknown = || LuaError::external(format!("Unknown type: {text}"));
+    let (base, has_param) = match base {
+        Some(ctype) if base_text.trim() == "$" => (Some(ctype), true),
+        Some(_) => {
+            return Err(LuaError::external(format!(
+                "A ctype can only be the base type in '{template}', like '$*' or '$[4]'"
+            )));
+        }
+        None => (CType::parse(base_text), false),
+    };
+
+    match base.and_then(|base| apply_suffix(base, suffix)) {
+        Some((ctype, vla)) => Ok(Declared { ctype, vla }),
+        // Leave anything the simple grammar does not cover, like function pointers, to `CType::parse`
+        None if !has_param => CType::parse(&text)
+            .map(|ctype| Declared { ctype, vla: false })
+            .ok_or_else(unknown),
+        None => Err(unknown()),
+    }
+}
+
+/// Apply the pointers and array dimensions following a base type, like `*[2][3]`
+///
+/// Returns the type and whether its outermost dimension is `[?]`,
+/// or `None` if the suffix is not made of pointers and dimensions only.
+fn apply_suffix(base: CType, suffix: &str) -> Option<(CType, bool)> {
+    let mut ctype = base;
+    let mut rest = suffix.trim();
+
+    while let Some(after) = rest.strip_prefix('*') {
+        ctype = match ctype {
+            CType::Void => CType::Pointer(None),
+            ctype => CType::Pointer(Some(Box::new(ctype))),
+        };
+        rest = after.trim_start();
+    }
+
+    let mut dims = Vec::new();
+    while let Some(after) = rest.strip_prefix('[') {
+        let (len, after) = after.split_once(']')?;
+        dims.push(match len.trim() {
+            "?" => None,
+            len => Some(len.parse::<usize>().ok()?),
+        });
+        rest = after.trim_start();
+    }
+    if !rest.is_empty() {
+        return None;
+    }
+
+    // Dimensions are written outermost first, and only the outermost one may be "[?]"
+    let vla = dims.first() == Some(&None);
+    for len in dims.iter().skip(usize::from(vla)).rev() {
+        ctype = CType::Array(Box::new(ctype), (*len)?);
+    }
+    Some((ctype, vla))
+}
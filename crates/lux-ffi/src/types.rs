@@ -2,6 +2,8 @@
 //!
 //! Defines all C type representations and conversions.
 
+use std::collections::HashMap;
+
 /// C type representation
 #[derive(Debug, Clone, PartialEq)]
 pub enum CType {
@@ -25,9 +27,11 @@ pub enum CType {
     UInt32,
     Int64,
     UInt64,
+    Half, // _Float16, converted to and from f32
     Float,
     Double,
-    WChar, // Windows wide char (usually 16-bit)
+    LongDouble, // Platform-specific, see float.rs
+    WChar,      // Windows wide char (usually 16-bit)
     Pointer(Option<Box<CType>>),
     Array(Box<CType>, usize),
     Struct(String),
@@ -44,7 +48,12 @@ impl CType {
         match self {
             CType::Void => 0,
             CType::Bool | CType::Char | CType::UChar | CType::Int8 | CType::UInt8 => 1,
-            CType::Short | CType::UShort | CType::Int16 | CType::UInt16 | CType::WChar => 2,
+            CType::Short
+            | CType::UShort
+            | CType::Int16
+            | CType::UInt16
+            | CType::WChar
+            | CType::Half => 2,
             CType::Int
             | CType::UInt
             | CType::Int32
@@ -58,6 +67,7 @@ impl CType {
             | CType::Int64
             | CType::UInt64
             | CType::Double => 8,
+            CType::LongDouble => crate::float::long_double_size(),
             CType::GUID => 16,
             CType::Pointer(_) | CType::Function(_) => std::mem::size_of::<usize>(),
             CType::Array(elem, count) => elem.size() * count,
@@ -73,7 +83,12 @@ impl CType {
         match self {
             CType::Void => 1,
             CType::Bool | CType::Char | CType::UChar | CType::Int8 | CType::UInt8 => 1,
-            CType::Short | CType::UShort | CType::Int16 | CType::UInt16 | CType::WChar => 2,
+            CType::Short
+            | CType::UShort
+            | CType::Int16
+            | CType::UInt16
+            | CType::WChar
+            | CType::Half => 2,
             CType::Int
             | CType::UInt
             | CType::Int32
@@ -88,6 +103,7 @@ impl CType {
             | CType::Int64
             | CType::UInt64
             | CType::Double => 8,
+            CType::LongDouble => crate::float::long_double_align(),
             CType::GUID => 4, // GUID is typically 4-byte aligned
             CType::Pointer(_) | CType::Function(_) => std::mem::size_of::<usize>(),
             CType::Array(elem, _) => elem.align(),
@@ -98,6 +114,7 @@ impl CType {
     }
 
     /// Parse a C type string
+    #[allow(clippy::too_many_lines)]
     pub fn parse(s: &str) -> Option<Self> {
         // Clean the string of variable names if mixed in, but this is type parsing.
         // We assume 's' is just the type part.
@@ -105,6 +122,16 @@ impl CType {
         // but might be passed in.
         let mut s = s.trim();
 
+        // Qualifiers may also follow what they qualify, like `char const*` or `int* const`,
+        // and are kept track of separately by `Qualifiers::of` since they do not change layout
+        let unqualified;
+        if s.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .any(|word| QUALIFIERS.contains(&word))
+        {
+            unqualified = strip_qualifiers(s);
+            s = &unqualified;
+        }
+
         // Strip common modifiers
         s = s.strip_prefix("const ").unwrap_or(s).trim();
         s = s.strip_prefix("volatile ").unwrap_or(s).trim();
@@ -117,6 +144,16 @@ impl CType {
             return None;
         }
 
+        // Function pointers: "int (*)(int, int)"
+        if s.contains('(') {
+            return crate::parser::parse_func_ptr_type(s);
+        }
+
+        // Integer types may be spelled with their words in any order, like `long unsigned int`
+        if let Some(spelling) = canonical_integer(s) {
+            s = spelling;
+        }
+
         // Handle arrays: "int[4]" or "int [4]"
         if let Some(bracket) = s.find('[') {
             let base = s[..bracket].trim();
@@ -156,7 +193,8 @@ impl CType {
             "unsigned char" | "uint8_t" | "BYTE" | "byte" => Some(CType::UChar),
             "short" | "int16_t" | "SHORT" => Some(CType::Short),
             "unsigned short" | "uint16_t" | "USHORT" | "WORD" => Some(CType::UShort),
-            "int" | "int32_t" | "signed" | "INT" | "HRESULT" => Some(CType::Int),
+            "int" | "int32_t" | "signed" | "INT" => Some(CType::Int),
+            "HRESULT" => Some(CType::HRESULT),
             "unsigned" | "unsigned int" | "uint32_t" | "UINT" | "DWORD" => Some(CType::UInt),
             "long" | "long int" => {
                 // In C, 'long' depends on platform.
@@ -208,6 +246,8 @@ impl CType {
             }
             "float" | "FLOAT" => Some(CType::Float),
             "double" | "DOUBLE" => Some(CType::Double),
+            "long double" => Some(CType::LongDouble),
+            "_Float16" | "__fp16" | "float16_t" | "half" => Some(CType::Half),
 
             // String types -> char pointer
             "char*" | "const char*" | "LPCSTR" | "LPSTR" | "PCSTR" | "PSTR" => {
@@ -279,6 +319,104 @@ impl CType {
         }
     }
 
+    /// Parse a C type string, along with the qualifiers that `CType::parse` drops
+    pub fn parse_qualified(s: &str) -> Option<(Self, Qualifiers)> {
+        Some((Self::parse(s)?, Qualifiers::of(s)))
+    }
+
+    /// Follow typedefs that were used before they were declared, which parse as lazy
+    /// structs, to the type they name - including those pointed to or in arrays
+    #[must_use]
+    pub fn resolve(&self) -> CType {
+        self.resolve_within(MAX_TYPEDEF_DEPTH)
+    }
+
+    fn resolve_within(&self, depth: usize) -> CType {
+        match self {
+            CType::Struct(name) | CType::Union(name) if depth > 0 => {
+                let alias = {
+                    let reg = crate::registry::Registry::get();
+                    if reg.has_struct(name) {
+                        None
+                    } else {
+                        reg.get_typedef(name)
+                    }
+                };
+                match alias {
+                    Some(alias) if alias != *self => alias.resolve_within(depth - 1),
+                    _ => self.clone(),
+                }
+            }
+            CType::Pointer(Some(inner)) => {
+                CType::Pointer(Some(Box::new(inner.resolve_within(depth))))
+            }
+            CType::Array(elem, len) => CType::Array(Box::new(elem.resolve_within(depth)), *len),
+            other => other.clone(),
+        }
+    }
+
+    /// The signature of a function type, or of a pointer to one
+    #[must_use]
+    pub fn function_type(&self) -> Option<&FuncType> {
+        match self {
+            CType::Function(func) => Some(func),
+            CType::Pointer(Some(inner)) => match inner.as_ref() {
+                CType::Function(func) => Some(func),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// C spelling of the type, which parses back to the same type
+    #[must_use]
+    pub fn c_name(&self) -> String {
+        match self {
+            CType::Void => "void".to_string(),
+            CType::Bool => "bool".to_string(),
+            CType::Char => "char".to_string(),
+            CType::UChar => "unsigned char".to_string(),
+            CType::Short => "short".to_string(),
+            CType::UShort => "unsigned short".to_string(),
+            CType::Int => "int".to_string(),
+            CType::UInt => "unsigned int".to_string(),
+            CType::Long | CType::LongLong | CType::Int64 => "int64_t".to_string(),
+            CType::ULong | CType::ULongLong | CType::UInt64 => "uint64_t".to_string(),
+            CType::Int8 => "int8_t".to_string(),
+            CType::UInt8 => "uint8_t".to_string(),
+            CType::Int16 => "int16_t".to_string(),
+            CType::UInt16 => "uint16_t".to_string(),
+            CType::Int32 => "int32_t".to_string(),
+            CType::UInt32 => "uint32_t".to_string(),
+            CType::Half => "_Float16".to_string(),
+            CType::Float => "float".to_string(),
+            CType::Double => "double".to_string(),
+            CType::LongDouble => "long double".to_string(),
+            CType::WChar => "wchar_t".to_string(),
+            CType::Pointer(None) => "void*".to_string(),
+            CType::Pointer(Some(inner)) => match inner.as_ref() {
+                CType::Function(func) => func.c_name("(*)"),
+                inner => format!("{}*", inner.c_name()),
+            },
+            CType::Array(_, _) => {
+                use std::fmt::Write;
+
+                // Dimensions are written outermost first: int[2][3] is 2 arrays of 3 ints
+                let mut dims = String::new();
+                let mut elem = self;
+                while let CType::Array(inner, count) = elem {
+                    let _ = write!(dims, "[{count}]");
+                    elem = inner;
+                }
+                format!("{}{dims}", elem.c_name())
+            }
+            CType::Struct(name) | CType::Union(name) | CType::Enum(name) => name.clone(),
+            CType::Function(func) => func.c_name(""),
+            CType::GUID => "GUID".to_string(),
+            CType::HRESULT => "HRESULT".to_string(),
+        }
+    }
+
     /// Convert to libffi type
     pub fn to_ffi_type(&self) -> libffi::middle::Type {
         use libffi::middle::Type;
@@ -287,13 +425,15 @@ impl CType {
             CType::Bool | CType::Char | CType::Int8 => Type::i8(),
             CType::UChar | CType::UInt8 => Type::u8(),
             CType::Short | CType::Int16 => Type::i16(),
-            CType::UShort | CType::UInt16 | CType::WChar => Type::u16(),
+            // NOTE: libffi has no half-float type, calls reject it before getting here
+            CType::UShort | CType::UInt16 | CType::WChar | CType::Half => Type::u16(),
             CType::Int | CType::Int32 | CType::Enum(_) | CType::HRESULT => Type::i32(),
             CType::UInt | CType::UInt32 => Type::u32(),
             CType::Long | CType::LongLong | CType::Int64 => Type::i64(),
             CType::ULong | CType::ULongLong | CType::UInt64 => Type::u64(),
             CType::Float => Type::f32(),
             CType::Double => Type::f64(),
+            CType::LongDouble => Type::longdouble(),
             CType::Pointer(_) | CType::Function(_) => Type::pointer(),
             CType::Array(_, _) => Type::pointer(),
             CType::Struct(_) | CType::Union(_) => Type::pointer(),
@@ -302,6 +442,132 @@ impl CType {
     }
 }
 
+/// How many typedefs `CType::resolve` follows, in case they refer to each other
+const MAX_TYPEDEF_DEPTH: usize = 16;
+
+/// Type qualifiers, including the spellings of `restrict` used by compilers before C99
+const QUALIFIERS: [&str; 5] = [
+    "const",
+    "volatile",
+    "restrict",
+    "__restrict",
+    "__restrict__",
+];
+
+/// Remove qualifiers from a type, wherever they appear
+pub(crate) fn strip_qualifiers(s: &str) -> String {
+    s.replace('*', " * ")
+        .split_whitespace()
+        .filter(|word| !QUALIFIERS.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(" *", "*")
+}
+
+/**
+    Spells a multi-word integer type the one way that `CType::parse` matches,
+    such as `unsigned long long` for `long long unsigned int`.
+
+    Returns `None` for anything that is not made of integer type words only,
+    or that combines them in a way C does not allow, like `short long`.
+*/
+fn canonical_integer(s: &str) -> Option<&'static str> {
+    let words = s.split_whitespace().collect::<Vec<_>>();
+    if words.len() < 2 {
+        return None;
+    }
+    let count = |word: &str| words.iter().filter(|w| **w == word).count();
+    let (signed, unsigned) = (count("signed"), count("unsigned"));
+    let (char, short, int, long, double) = (
+        count("char"),
+        count("short"),
+        count("int"),
+        count("long"),
+        count("double"),
+    );
+    if signed + unsigned + char + short + int + long + double != words.len()
+        || signed + unsigned > 1
+        || int > 1
+    {
+        return None;
+    }
+    let unsigned = unsigned == 1;
+    Some(match (char, short, long, double) {
+        (0, 0, 1, 1) if signed == 0 && !unsigned && int == 0 => "long double",
+        (1, 0, 0, 0) if int == 0 => match (signed, unsigned) {
+            (1, _) => "signed char",
+            (_, true) => "unsigned char",
+            _ => "char",
+        },
+        (0, 1, 0, 0) if unsigned => "unsigned short",
+        (0, 1, 0, 0) => "short",
+        (0, 0, 0, 0) if unsigned => "unsigned int",
+        (0, 0, 0, 0) => "int",
+        (0, 0, 1, 0) if unsigned => "unsigned long",
+        (0, 0, 1, 0) => "long",
+        (0, 0, 2, 0) if unsigned => "unsigned long long",
+        (0, 0, 2, 0) => "long long",
+        _ => return None,
+    })
+}
+
+/**
+    The qualifiers of a declared type, which `CType` leaves out since they do not change its layout.
+
+    Qualifiers apply to what is on their left, or to what is on their right when nothing is,
+    so `const char*` and `char const*` both point to `const` chars, while `char* const`
+    is a `const` pointer to chars that may be written to.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Qualifiers {
+    /// The declared value itself is `const`, like `const int x` or `char* const p`
+    pub is_const: bool,
+    /// The value is a pointer to `const`, like `const char* s`
+    pub points_to_const: bool,
+    /// The type is `volatile` anywhere, like `volatile int* p`
+    pub is_volatile: bool,
+    /// The pointer is `restrict`, like `int* restrict p`
+    pub is_restrict: bool,
+}
+
+impl Qualifiers {
+    /// Finds the qualifiers of a C type string, such as `const unsigned char* const`
+    #[must_use]
+    pub fn of(s: &str) -> Self {
+        let spaced = s.replace('*', " * ");
+        let words = spaced.split_whitespace().collect::<Vec<_>>();
+        let last_star = words.iter().rposition(|w| *w == "*");
+        // Qualifiers after the last `*` apply to the pointer, those before it to what it points to
+        let (pointee, value) = match last_star {
+            Some(i) => {
+                let start = words[..i]
+                    .iter()
+                    .rposition(|w| *w == "*")
+                    .map_or(0, |j| j + 1);
+                (&words[start..i], &words[i + 1..])
+            }
+            None => (&words[..0], &words[..]),
+        };
+        Self {
+            is_const: value.contains(&"const"),
+            points_to_const: pointee.contains(&"const"),
+            is_volatile: words.contains(&"volatile"),
+            is_restrict: value
+                .iter()
+                .any(|w| matches!(*w, "restrict" | "__restrict" | "__restrict__")),
+        }
+    }
+
+    /// Whether cdata of the given type, declared with these qualifiers, refers to `const` memory
+    #[must_use]
+    pub fn is_const_target(self, ctype: &CType) -> bool {
+        match ctype {
+            CType::Pointer(_) => self.points_to_const,
+            _ => self.is_const,
+        }
+    }
+}
+
 /// Function type
 #[derive(Debug, Clone, PartialEq)]
 pub struct FuncType {
@@ -311,6 +577,23 @@ pub struct FuncType {
     pub conv: CallConv,
 }
 
+impl FuncType {
+    /// C spelling of the function type, with the given declarator such as `(*)`
+    #[must_use]
+    pub fn c_name(&self, declarator: &str) -> String {
+        let mut args: Vec<String> = self.args.iter().map(CType::c_name).collect();
+        if self.variadic {
+            args.push("...".to_string());
+        }
+        let args = if args.is_empty() {
+            "void".to_string()
+        } else {
+            args.join(", ")
+        };
+        format!("{} {declarator}({args})", self.ret.c_name())
+    }
+}
+
 /// Calling convention
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum CallConv {
@@ -328,6 +611,7 @@ pub struct Field {
     pub ctype: CType,
     pub offset: usize,
     pub bits: Option<(usize, usize)>,
+    pub qualifiers: Qualifiers,
 }
 
 /// Struct/Union definition
@@ -339,11 +623,36 @@ pub struct StructDef {
     pub align: usize,
     pub is_union: bool,
     pub is_packed: bool,
+    /// Index of every field by name, so field access does not scan all of them
+    index: HashMap<String, usize>,
 }
 
 impl StructDef {
+    pub fn new(
+        name: String,
+        fields: Vec<Field>,
+        size: usize,
+        align: usize,
+        is_union: bool,
+    ) -> Self {
+        // NOTE: Fields flattened from anonymous members may repeat a name, the first one wins
+        let mut index = HashMap::with_capacity(fields.len());
+        for (i, field) in fields.iter().enumerate() {
+            index.entry(field.name.clone()).or_insert(i);
+        }
+        Self {
+            name,
+            fields,
+            size,
+            align,
+            is_union,
+            is_packed: false,
+            index,
+        }
+    }
+
     pub fn field(&self, name: &str) -> Option<&Field> {
-        self.fields.iter().find(|f| f.name == name)
+        self.index.get(name).map(|&i| &self.fields[i])
     }
 
     pub fn field_offset(&self, name: &str) -> Option<usize> {
@@ -356,6 +665,7 @@ impl StructDef {
 pub struct FuncSig {
     pub name: String,
     pub ret: CType,
+    pub ret_qualifiers: Qualifiers,
     pub args: Vec<(String, CType)>,
     pub variadic: bool,
     pub conv: CallConv,
@@ -394,7 +704,9 @@ impl IntoLua for CType {
             CType::Int64 => "int64",
             CType::UInt64 => "uint64",
             CType::Float => "float",
+            CType::Half => "half",
             CType::Double => "double",
+            CType::LongDouble => "longdouble",
             CType::WChar => "wchar",
             CType::Pointer(_) => "pointer",
             CType::Array(_, _) => "array",
@@ -0,0 +1,163 @@
+use std::ops::Range;
+
+use mlua::prelude::*;
+
+/**
+    The kind of indentation to use when formatting.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IndentType {
+    #[default]
+    Tabs,
+    Spaces,
+}
+
+/**
+    Configuration for formatting Luau source code.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatConfig {
+    pub(crate) indent_type: IndentType,
+    pub(crate) indent_width: usize,
+    pub(crate) column_width: usize,
+    pub(crate) range: Option<Range<usize>>,
+}
+
+impl FormatConfig {
+    /**
+        Creates a new config with default values.
+    */
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            indent_type: IndentType::Tabs,
+            indent_width: 4,
+            column_width: 120,
+            range: None,
+        }
+    }
+
+    /**
+        Sets the kind of indentation to use.
+
+        Tabs are used by default.
+    */
+    #[must_use]
+    pub const fn with_indent_type(self, indent_type: IndentType) -> Self {
+        Self {
+            indent_type,
+            ..self
+        }
+    }
+
+    /**
+        Sets the width of a single level of indentation.
+
+        This is the number of spaces to indent with when using spaces,
+        and the width a tab counts as when measuring lines otherwise.
+    */
+    #[must_use]
+    pub const fn with_indent_width(self, indent_width: usize) -> Self {
+        Self {
+            indent_width,
+            ..self
+        }
+    }
+
+    /**
+        Sets the width that lines should fit within, if possible.
+
+        Lines are `120` columns wide by default.
+    */
+    #[must_use]
+    pub const fn with_column_width(self, column_width: usize) -> Self {
+        Self {
+            column_width,
+            ..self
+        }
+    }
+
+    /**
+        Sets a range of bytes in the source to format.
+
+        Only lines overlapping this range are formatted,
+        everything else is left exactly as it was.
+    */
+    #[must_use]
+    pub fn with_range(self, range: Range<usize>) -> Self {
+        Self {
+            range: Some(range),
+            ..self
+        }
+    }
+
+    pub(crate) fn indent(&self, level: usize) -> String {
+        match self.indent_type {
+            IndentType::Tabs => "\t".repeat(level),
+            IndentType::Spaces => " ".repeat(level * self.indent_width),
+        }
+    }
+}
+
+impl Default for FormatConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FromLua for IndentType {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let s = match &value {
+            LuaValue::String(s) => s.to_string_lossy(),
+            _ => String::new(),
+        };
+        match s.to_ascii_lowercase().as_str() {
+            "tabs" => Ok(Self::Tabs),
+            "spaces" => Ok(Self::Spaces),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: String::from("IndentType"),
+                message: Some(String::from("expected \"Tabs\" or \"Spaces\"")),
+            }),
+        }
+    }
+}
+
+impl FromLua for FormatConfig {
+    fn from_lua(value: LuaValue, lua: &Lua) -> LuaResult<Self> {
+        let mut config = Self::new();
+        let options = match value {
+            LuaValue::Nil => return Ok(config),
+            LuaValue::Table(t) => t,
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: String::from("FormatOptions"),
+                    message: Some(String::from("expected a table of options")),
+                });
+            }
+        };
+
+        if let Some(indent_type) = options.get::<Option<LuaValue>>("indentType")? {
+            config.indent_type = IndentType::from_lua(indent_type, lua)?;
+        }
+        if let Some(indent_width) = options.get::<Option<usize>>("indentWidth")? {
+            config.indent_width = indent_width;
+        }
+        if let Some(column_width) = options.get::<Option<usize>>("columnWidth")? {
+            config.column_width = column_width;
+        }
+
+        // NOTE: Ranges are given as inclusive, 1-based byte
+        // positions in Lua, the same as `string.sub` takes
+        let range_start = options.get::<Option<usize>>("rangeStart")?;
+        let range_end = options.get::<Option<usize>>("rangeEnd")?;
+        if range_start.is_some() || range_end.is_some() {
+            let start = range_start.unwrap_or(1).saturating_sub(1);
+            let end = range_end.unwrap_or(usize::MAX);
+            config.range = Some(start..end);
+        }
+
+        Ok(config)
+    }
+}
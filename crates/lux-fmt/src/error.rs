@@ -0,0 +1,65 @@
+use std::{error::Error, fmt};
+
+/**
+    An error that prevented source code from being formatted,
+    such as an unfinished string or an unbalanced block.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl FormatError {
+    pub(crate) fn new(source: &str, offset: usize, message: impl Into<String>) -> Self {
+        let (line, column) = position(source, offset);
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /**
+        Returns the line the error occurred on, starting at `1`.
+    */
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /**
+        Returns the column the error occurred on, in bytes and starting at `1`.
+    */
+    #[must_use]
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /**
+        Returns the message describing the error, without its location.
+    */
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+/**
+    Returns the line and column of a byte offset in the source, both starting at `1`.
+*/
+pub(crate) fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+    (line, column)
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for FormatError {}
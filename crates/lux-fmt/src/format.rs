@@ -0,0 +1,619 @@
+use std::{collections::HashSet, ops::Range};
+
+use mlua::{Compiler, Error as LuaError};
+
+use crate::{
+    config::FormatConfig,
+    error::FormatError,
+    lexer::{Token, TokenKind, tokenize},
+};
+
+const BINARY_OPERATORS: &[&str] = &[
+    "=", "==", "~=", "<=", ">=", "+", "-", "*", "/", "//", "%", "^", "..", "+=", "-=", "*=", "/=",
+    "//=", "%=", "^=", "..=", "->", "::", "|", "&", "<", ">", "and", "or",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    /// The condition of an `if`, `elseif`, `while` or `for` statement,
+    /// until the `then` or `do` that starts its block.
+    Condition { closer: &'static str },
+    /// A block closed by `end`, remembering if it belongs to an `if` statement.
+    Block { if_block: bool },
+    /// The block of a `repeat` statement, closed by `until`.
+    Repeat,
+    /// An `if` expression, which ends at its `else` since it has no `end`.
+    IfExpression,
+    /// A pair of brackets, closed by the given bracket.
+    Bracket { closer: &'static str },
+}
+
+/**
+    An open block or pair of brackets, along with the indentation
+    level of the line it was opened on, and its opening token.
+*/
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    kind: FrameKind,
+    base: usize,
+    opener: usize,
+}
+
+impl Frame {
+    fn is_closed_by(&self, token: &Token) -> bool {
+        match self.kind {
+            FrameKind::Condition { closer } | FrameKind::Bracket { closer } => token.is(closer),
+            FrameKind::Block { .. } => token.is("end") || token.is("else") || token.is("elseif"),
+            FrameKind::Repeat => token.is("until"),
+            FrameKind::IfExpression => false,
+        }
+    }
+}
+
+/**
+    A line of tokens from the original source, and the indentation level it should have.
+*/
+#[derive(Debug, Clone)]
+struct Line {
+    tokens: Range<usize>,
+    indent: usize,
+}
+
+fn is_binary_operator(token: &Token) -> bool {
+    token.kind != TokenKind::Comment
+        && token.kind != TokenKind::String
+        && BINARY_OPERATORS.contains(&token.text)
+}
+
+/**
+    Returns `true` if the token may appear between the angle brackets of generic type parameters.
+*/
+fn is_generic_content(token: &Token) -> bool {
+    match token.kind {
+        TokenKind::Name => !token.is_keyword() || matches!(token.text, "nil" | "true" | "false"),
+        TokenKind::String | TokenKind::Comment => true,
+        TokenKind::Number => false,
+        TokenKind::Symbol => matches!(
+            token.text,
+            "," | "."
+                | "?"
+                | "..."
+                | "|"
+                | "&"
+                | "("
+                | ")"
+                | "{"
+                | "}"
+                | "["
+                | "]"
+                | ":"
+                | "->"
+                | "="
+        ),
+    }
+}
+
+/**
+    Finds the indices of all `<` and `>` tokens that are angle brackets around
+    generic type parameters, rather than comparisons.
+
+    Angle brackets are only considered generic when the `<` comes right after a name
+    and everything up to the matching `>` on the same line could be part of a type.
+*/
+fn find_generics(tokens: &[Token]) -> HashSet<usize> {
+    let mut generics = HashSet::new();
+    for open in 1..tokens.len() {
+        let before = &tokens[open - 1];
+        let after_name =
+            before.kind == TokenKind::Name && (!before.is_keyword() || before.is("function"));
+        if !tokens[open].is("<") || !after_name || generics.contains(&open) {
+            continue;
+        }
+        let mut depth = 0usize;
+        let mut brackets = Vec::new();
+        for (index, token) in tokens.iter().enumerate().skip(open) {
+            if index > open && token.newlines_before > 0 {
+                break;
+            } else if token.is("<") {
+                depth += 1;
+                brackets.push(index);
+            } else if token.is(">") {
+                depth -= 1;
+                brackets.push(index);
+                if depth == 0 {
+                    generics.extend(brackets);
+                    break;
+                }
+            } else if !is_generic_content(token) {
+                break;
+            }
+        }
+    }
+    generics
+}
+
+fn is_word(token: &Token) -> bool {
+    matches!(token.kind, TokenKind::Name | TokenKind::Number)
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    tokens: Vec<Token<'a>>,
+    generics: HashSet<usize>,
+    config: &'a FormatConfig,
+}
+
+impl Formatter<'_> {
+    fn error(&self, token: usize, message: impl Into<String>) -> FormatError {
+        FormatError::new(self.source, self.tokens[token].start, message)
+    }
+
+    /**
+        Returns the index of the last token before `index` that is not a comment.
+    */
+    fn previous_significant(&self, index: usize) -> Option<usize> {
+        (0..index)
+            .rev()
+            .find(|&i| self.tokens[i].kind != TokenKind::Comment)
+    }
+
+    fn is_operator(&self, index: usize) -> bool {
+        is_binary_operator(&self.tokens[index]) && !self.generics.contains(&index)
+    }
+
+    /**
+        Returns `true` if the `:` at the given index is part of a method
+        call such as `value:method()`, rather than a type annotation.
+    */
+    fn is_method_call(&self, index: usize) -> bool {
+        let name = self.tokens.get(index + 1);
+        let call = self.tokens.get(index + 2);
+        name.is_some_and(|name| name.kind == TokenKind::Name && !name.space_before)
+            && call
+                .is_some_and(|call| call.is("(") || call.is("{") || call.kind == TokenKind::String)
+    }
+
+    fn is_unary(&self, index: usize) -> bool {
+        let token = &self.tokens[index];
+        if token.is("not") || token.is("#") {
+            return true;
+        }
+        token.is("-")
+            && self
+                .previous_significant(index)
+                .is_none_or(|prev| !self.tokens[prev].ends_value())
+    }
+
+    /**
+        Returns `true` if an `if` at the given index is an `if` expression
+        rather than a statement, based on the token that comes before it.
+    */
+    fn is_expression_position(&self, index: usize, after_expression_keyword: bool) -> bool {
+        let Some(prev) = self.previous_significant(index) else {
+            return false;
+        };
+        let prev = &self.tokens[prev];
+        match prev.kind {
+            TokenKind::Symbol => !matches!(prev.text, ")" | "]" | "}" | "..." | ";"),
+            TokenKind::Name if prev.is("then") || prev.is("else") => after_expression_keyword,
+            TokenKind::Name => matches!(
+                prev.text,
+                "return" | "and" | "or" | "not" | "in" | "until" | "while" | "if" | "elseif"
+            ),
+            _ => false,
+        }
+    }
+
+    /**
+        Returns `true` if a line starting at the given token continues
+        an expression from the line before it, and should be indented.
+    */
+    fn is_continuation(&self, first: usize) -> bool {
+        let token = &self.tokens[first];
+        let starts_with_operator = if token.is("-") {
+            !self.is_unary(first)
+        } else {
+            token.is(".") || token.is(":") || self.is_operator(first)
+        };
+        starts_with_operator
+            || self
+                .previous_significant(first)
+                .is_some_and(|prev| self.is_operator(prev))
+    }
+
+    /**
+        Splits the tokens into lines as they were in the original source,
+        and figures out the indentation level for each one of them.
+
+        Lines are indented one level deeper than the line that opened the
+        innermost block or bracket around them, so opening several brackets
+        on the same line only ever indents the lines after it by one level.
+        Lines continuing an expression outside of brackets get one more level.
+    */
+    #[allow(clippy::too_many_lines)]
+    fn lines(&self) -> Result<Vec<Line>, FormatError> {
+        let mut lines = Vec::new();
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut after_expression_keyword = false;
+
+        let mut start = 0;
+        while start < self.tokens.len() {
+            let mut end = start + 1;
+            while end < self.tokens.len() && self.tokens[end].newlines_before == 0 {
+                end += 1;
+            }
+
+            let indent = match stack.last() {
+                Some(frame) if frame.is_closed_by(&self.tokens[start]) => frame.base,
+                Some(
+                    frame @ Frame {
+                        kind: FrameKind::Bracket { .. },
+                        ..
+                    },
+                ) => frame.base + 1,
+                Some(frame) if self.is_continuation(start) => frame.base + 2,
+                Some(frame) => frame.base + 1,
+                None if self.is_continuation(start) => 1,
+                None => 0,
+            };
+
+            for index in start..end {
+                let token = &self.tokens[index];
+                if !matches!(token.kind, TokenKind::Name | TokenKind::Symbol) {
+                    continue;
+                }
+                // NOTE: Blocks closed on a later line than they were opened on always
+                // get their `end` on a line of its own, so that `end end` is split up
+                if index > start
+                    && (token.is("end") || token.is("until"))
+                    && stack
+                        .last()
+                        .is_some_and(|frame| frame.is_closed_by(token) && frame.opener < start)
+                {
+                    end = index;
+                    break;
+                }
+                let push = |stack: &mut Vec<Frame>, kind| {
+                    stack.push(Frame {
+                        kind,
+                        base: indent,
+                        opener: index,
+                    });
+                };
+                let top = stack.last().map(|frame| frame.kind);
+                match token.text {
+                    "function" => push(&mut stack, FrameKind::Block { if_block: false }),
+                    "repeat" => push(&mut stack, FrameKind::Repeat),
+                    "while" | "for" => push(&mut stack, FrameKind::Condition { closer: "do" }),
+                    "if" if self.is_expression_position(index, after_expression_keyword) => {
+                        push(&mut stack, FrameKind::IfExpression);
+                    }
+                    "if" => push(&mut stack, FrameKind::Condition { closer: "then" }),
+                    "(" => push(&mut stack, FrameKind::Bracket { closer: ")" }),
+                    "[" => push(&mut stack, FrameKind::Bracket { closer: "]" }),
+                    "{" => push(&mut stack, FrameKind::Bracket { closer: "}" }),
+                    "do" => match top {
+                        Some(FrameKind::Condition { closer: "do" }) => {
+                            if let Some(frame) = stack.last_mut() {
+                                frame.kind = FrameKind::Block { if_block: false };
+                            }
+                        }
+                        _ => push(&mut stack, FrameKind::Block { if_block: false }),
+                    },
+                    "then" => match top {
+                        Some(FrameKind::IfExpression) => after_expression_keyword = true,
+                        Some(FrameKind::Condition { closer: "then" }) => {
+                            after_expression_keyword = false;
+                            if let Some(frame) = stack.last_mut() {
+                                frame.kind = FrameKind::Block { if_block: true };
+                            }
+                        }
+                        _ => return Err(self.error(index, "unexpected 'then'")),
+                    },
+                    "elseif" => match top {
+                        Some(FrameKind::IfExpression) => {}
+                        Some(FrameKind::Block { if_block: true }) => {
+                            if let Some(frame) = stack.last_mut() {
+                                frame.kind = FrameKind::Condition { closer: "then" };
+                            }
+                        }
+                        _ => return Err(self.error(index, "unexpected 'elseif'")),
+                    },
+                    "else" => match top {
+                        Some(FrameKind::IfExpression) => {
+                            after_expression_keyword = true;
+                            stack.pop();
+                        }
+                        Some(FrameKind::Block { if_block: true }) => {
+                            after_expression_keyword = false;
+                        }
+                        _ => return Err(self.error(index, "unexpected 'else'")),
+                    },
+                    "end" | "until" | ")" | "]" | "}" => match stack.last() {
+                        Some(frame) if frame.is_closed_by(token) => {
+                            stack.pop();
+                        }
+                        _ => {
+                            return Err(self.error(index, format!("unexpected '{}'", token.text)));
+                        }
+                    },
+                    _ => {}
+                }
+            }
+
+            lines.push(Line {
+                tokens: start..end,
+                indent,
+            });
+            start = end;
+        }
+
+        match stack.last() {
+            Some(frame) => {
+                let opener = self.tokens[frame.opener].text;
+                Err(self.error(frame.opener, format!("'{opener}' is never closed")))
+            }
+            None => Ok(lines),
+        }
+    }
+
+    /**
+        Returns `true` if there should be a space before the token at the given index,
+        which must not be the first token on its line.
+    */
+    fn needs_space(&self, index: usize) -> bool {
+        let prev = &self.tokens[index - 1];
+        let next = &self.tokens[index];
+        // NOTE: Spacing that is not covered by any rule below, such as in the
+        // uncommon `f{ ... }` call syntax, is kept as it was in the original
+        let original = next.space_before;
+
+        if prev.kind == TokenKind::Comment || next.kind == TokenKind::Comment {
+            return true;
+        }
+        if [",", ";", ")", "]", "?", ":", "."]
+            .iter()
+            .any(|s| next.is(s))
+            || self.generics.contains(&index)
+        {
+            return false;
+        }
+        if next.is("}") {
+            return !prev.is("{");
+        }
+        if prev.is("(") || prev.is("[") || prev.is(".") || prev.is("@") {
+            return false;
+        }
+        if prev.is("{") || prev.is(",") || prev.is(";") {
+            return true;
+        }
+        if prev.is(":") {
+            return !self.is_method_call(index - 1);
+        }
+        if self.generics.contains(&(index - 1)) && (prev.is("<") || next.is("(")) {
+            return false;
+        }
+        if self.is_unary(index - 1) {
+            return prev.is("not");
+        }
+        if self.is_operator(index - 1) || self.is_operator(index) {
+            return true;
+        }
+        if next.is("(") || next.is("[") {
+            return !(prev.ends_value() || prev.is("function"));
+        }
+        if next.is("{") || next.kind == TokenKind::String {
+            return !prev.ends_value() || original;
+        }
+        if is_word(prev) && is_word(next) {
+            return true;
+        }
+        original
+    }
+
+    fn render(&self, tokens: Range<usize>) -> String {
+        let mut text = String::new();
+        for index in tokens.clone() {
+            if index > tokens.start && self.needs_space(index) {
+                text.push(' ');
+            }
+            text.push_str(self.tokens[index].text);
+        }
+        text
+    }
+
+    fn width(&self, indent: usize, text: &str) -> usize {
+        indent * self.config.indent_width + text.chars().count()
+    }
+
+    /**
+        Finds the outermost pair of brackets within the given tokens
+        that spans the most source text, returning the indices of its
+        opening and closing brackets, if there are any with contents.
+    */
+    fn widest_group(&self, tokens: Range<usize>) -> Option<(usize, usize)> {
+        let mut open = Vec::new();
+        let mut widest: Option<(usize, usize)> = None;
+        for index in tokens {
+            let token = &self.tokens[index];
+            if token.is("(") || token.is("[") || token.is("{") {
+                open.push(index);
+            } else if token.is(")") || token.is("]") || token.is("}") {
+                let Some(opener) = open.pop() else {
+                    continue;
+                };
+                let span = |(a, b): (usize, usize)| self.tokens[b].start - self.tokens[a].start;
+                if open.is_empty()
+                    && index > opener + 1
+                    && widest.is_none_or(|w| span((opener, index)) > span(w))
+                {
+                    widest = Some((opener, index));
+                }
+            }
+        }
+        widest
+    }
+
+    /**
+        Splits the tokens between a pair of brackets into elements separated
+        by commas or semicolons, each including its separator, if it has one.
+    */
+    fn split_elements(&self, tokens: Range<usize>) -> Vec<Range<usize>> {
+        let mut elements = Vec::new();
+        let mut depth = 0usize;
+        let mut start = tokens.start;
+        for index in tokens.clone() {
+            let token = &self.tokens[index];
+            if token.is("(") || token.is("[") || token.is("{") {
+                depth += 1;
+            } else if token.is(")") || token.is("]") || token.is("}") {
+                depth = depth.saturating_sub(1);
+            } else if depth == 0 && (token.is(",") || token.is(";")) {
+                elements.push(start..index + 1);
+                start = index + 1;
+            }
+        }
+        if start < tokens.end {
+            elements.push(start..tokens.end);
+        }
+        elements
+    }
+
+    /**
+        Lays out a line of tokens, splitting it across several lines if it is too wide.
+
+        Lines are split at the outermost pair of brackets, putting each element
+        between the brackets on a line of its own, and then split again recursively
+        until everything fits. Tables split this way always get a trailing separator.
+    */
+    fn layout(
+        &self,
+        indent: usize,
+        tokens: Range<usize>,
+        trailing_comma: bool,
+    ) -> Vec<(usize, String)> {
+        let mut text = self.render(tokens.clone());
+        if trailing_comma {
+            text.push(',');
+        }
+        if self.width(indent, &text) <= self.config.column_width || text.contains('\n') {
+            return vec![(indent, text)];
+        }
+        let Some((open, close)) = self.widest_group(tokens.clone()) else {
+            return vec![(indent, text)];
+        };
+
+        let is_table = self.tokens[open].is("{");
+        let elements = self.split_elements(open + 1..close);
+        let count = elements.len();
+
+        let mut lines = self.layout(indent, tokens.start..open + 1, false);
+        for (n, element) in elements.into_iter().enumerate() {
+            let last = &self.tokens[element.end - 1];
+            let needs_separator = is_table && n + 1 == count && !(last.is(",") || last.is(";"));
+            lines.extend(self.layout(indent + 1, element, needs_separator));
+        }
+        lines.extend(self.layout(indent, close..tokens.end, trailing_comma));
+        lines
+    }
+
+    fn in_range(&self, line: &Line) -> bool {
+        self.config.range.as_ref().is_none_or(|range| {
+            let start = self.tokens[line.tokens.start].start;
+            let end = self.tokens[line.tokens.end - 1].end;
+            range.start <= end && start < range.end.max(range.start + 1)
+        })
+    }
+
+    fn format(&self) -> Result<String, FormatError> {
+        let lines = self.lines()?;
+        check_syntax(self.source)?;
+        let mut output = String::with_capacity(self.source.len());
+
+        let mut previous_end = 0;
+        let mut last_in_range = true;
+        for (n, line) in lines.iter().enumerate() {
+            let first = &self.tokens[line.tokens.start];
+            let end = self.tokens[line.tokens.end - 1].end;
+            last_in_range = self.in_range(line);
+            if last_in_range {
+                if n > 0 {
+                    output.push_str(if first.newlines_before > 1 {
+                        "\n\n"
+                    } else {
+                        "\n"
+                    });
+                }
+                let laid_out = self.layout(line.indent, line.tokens.clone(), false);
+                for (i, (indent, text)) in laid_out.iter().enumerate() {
+                    if i > 0 {
+                        output.push('\n');
+                    }
+                    output.push_str(&self.config.indent(*indent));
+                    output.push_str(text);
+                }
+            } else {
+                // Lines outside of the range are kept exactly as they were,
+                // including any whitespace and blank lines before them
+                output.push_str(&self.source[previous_end..end]);
+            }
+            previous_end = end;
+        }
+
+        if last_in_range && !lines.is_empty() {
+            output.push('\n');
+        } else if !last_in_range {
+            output.push_str(&self.source[previous_end..]);
+        }
+        Ok(output)
+    }
+}
+
+/**
+    Checks that the source is valid Luau by compiling it, since formatting
+    only looks at tokens and would otherwise happily format broken code.
+*/
+fn check_syntax(source: &str) -> Result<(), FormatError> {
+    let message = match Compiler::new().compile(source) {
+        Ok(_) => return Ok(()),
+        Err(LuaError::SyntaxError { message, .. }) => message,
+        Err(e) => e.to_string(),
+    };
+    // Syntax errors start with the line they occurred on, such as `2: Expected ...`
+    let (line, message) = message
+        .split_once(": ")
+        .and_then(|(line, rest)| Some((line.parse::<usize>().ok()?, rest)))
+        .unwrap_or((1, message.as_str()));
+    let offset = source
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    Err(FormatError::new(source, offset, message))
+}
+
+/**
+    Formats Luau source code using the given config.
+
+    Line breaks from the original source are kept as they are, except for lines
+    that are wider than the configured column width, which are split at their
+    outermost brackets, and for an `end` or `until` closing a block opened on an
+    earlier line, which is moved to a line of its own. Indentation, spacing between tokens and blank lines
+    are all normalized, and comments are kept exactly as they were written.
+
+    # Errors
+
+    Errors if the source can not be tokenized, if its blocks and brackets are not
+    balanced, or if it is not valid Luau - the formatter never changes code it does
+    not understand.
+*/
+pub fn format(source: &str, config: &FormatConfig) -> Result<String, FormatError> {
+    let tokens = tokenize(source)?;
+    let formatter = Formatter {
+        source,
+        generics: find_generics(&tokens),
+        tokens,
+        config,
+    };
+    formatter.format()
+}
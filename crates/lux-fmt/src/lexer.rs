@@ -0,0 +1,303 @@
+use crate::error::FormatError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    Name,
+    Number,
+    String,
+    Comment,
+    Symbol,
+}
+
+/**
+    A single token in a Luau source file, along with information
+    about the whitespace that came before it in the original source.
+*/
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Token<'a> {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: &'a str,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    /// Number of newlines in the whitespace directly before this token.
+    pub(crate) newlines_before: usize,
+    /// If there was any whitespace at all directly before this token.
+    pub(crate) space_before: bool,
+}
+
+impl Token<'_> {
+    pub(crate) fn is(&self, text: &str) -> bool {
+        matches!(self.kind, TokenKind::Name | TokenKind::Symbol) && self.text == text
+    }
+
+    pub(crate) fn is_keyword(&self) -> bool {
+        self.kind == TokenKind::Name && KEYWORDS.contains(&self.text)
+    }
+
+    /**
+        Returns `true` if this token can end an expression, meaning that
+        a following `-` is a binary operator and `(` or `[` is a call or index.
+    */
+    pub(crate) fn ends_value(&self) -> bool {
+        match self.kind {
+            TokenKind::Name => !self.is_keyword() || matches!(self.text, "true" | "false" | "nil"),
+            TokenKind::Number | TokenKind::String => true,
+            TokenKind::Comment => false,
+            TokenKind::Symbol => matches!(self.text, ")" | "]" | "}" | "..."),
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// NOTE: Sorted so that longer symbols are always matched first
+const SYMBOLS: &[&str] = &[
+    "...", "..=", "//=", "..", "==", "~=", "<=", ">=", "+=", "-=", "*=", "/=", "%=", "^=", "//",
+    "->", "::", "+", "-", "*", "/", "%", "^", "#", "&", "|", "<", ">", "=", "(", ")", "{", "}",
+    "[", "]", ";", ":", ",", ".", "?", "@",
+];
+
+struct Lexer<'a> {
+    source: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).copied()
+    }
+
+    fn error(&self, offset: usize, message: impl Into<String>) -> FormatError {
+        FormatError::new(self.source, offset, message)
+    }
+
+    /**
+        Skips whitespace, returning the number of newlines skipped
+        and whether any whitespace was skipped at all.
+    */
+    fn skip_whitespace(&mut self) -> (usize, bool) {
+        let start = self.pos;
+        let mut newlines = 0;
+        while let Some(b) = self.peek(0) {
+            match b {
+                b'\n' => newlines += 1,
+                b' ' | b'\t' | b'\r' | 0x0b | 0x0c => {}
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        (newlines, self.pos > start)
+    }
+
+    /**
+        Returns the level of a long bracket such as `[==[` at the current
+        position, or `None` if there is no long bracket opening here.
+    */
+    fn long_bracket_level(&self) -> Option<usize> {
+        if self.peek(0) != Some(b'[') {
+            return None;
+        }
+        let mut level = 0;
+        while self.peek(1 + level) == Some(b'=') {
+            level += 1;
+        }
+        (self.peek(1 + level) == Some(b'[')).then_some(level)
+    }
+
+    fn skip_long_bracket(&mut self, level: usize, what: &str) -> Result<(), FormatError> {
+        let start = self.pos;
+        let close = format!("]{}]", "=".repeat(level));
+        let body = self.pos + level + 2;
+        match self.source[body..].find(&close) {
+            Some(index) => {
+                self.pos = body + index + close.len();
+                Ok(())
+            }
+            None => Err(self.error(start, format!("unfinished long {what}"))),
+        }
+    }
+
+    fn skip_quoted_string(&mut self, quote: u8) -> Result<(), FormatError> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek(0) {
+                None | Some(b'\n') => return Err(self.error(start, "unfinished string")),
+                Some(b'\\') if self.peek(1) == Some(b'z') => {
+                    self.pos += 2;
+                    self.skip_whitespace();
+                }
+                Some(b'\\') => self.pos += 2,
+                Some(b) if b == quote => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /**
+        Skips an interpolated string, including any expressions
+        inside of it, which may contain strings of their own.
+    */
+    fn skip_interpolated_string(&mut self) -> Result<(), FormatError> {
+        let start = self.pos;
+        self.pos += 1;
+        loop {
+            match self.peek(0) {
+                None => return Err(self.error(start, "unfinished interpolated string")),
+                Some(b'\\') => self.pos += 2,
+                Some(b'`') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(b'{') => {
+                    self.pos += 1;
+                    let mut depth = 0usize;
+                    loop {
+                        self.skip_whitespace();
+                        let Some(token) = self.next_token()? else {
+                            return Err(self.error(start, "unfinished interpolated string"));
+                        };
+                        match token.text {
+                            "{" => depth += 1,
+                            "}" if depth == 0 => break,
+                            "}" => depth -= 1,
+                            _ => {}
+                        }
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    fn skip_number(&mut self) {
+        let hex = self.peek(0) == Some(b'0') && matches!(self.peek(1), Some(b'x' | b'X'));
+        while let Some(b) = self.peek(0) {
+            let exponent = if hex {
+                matches!(b, b'p' | b'P')
+            } else {
+                matches!(b, b'e' | b'E')
+            };
+            if exponent && matches!(self.peek(1), Some(b'+' | b'-')) {
+                self.pos += 2;
+            } else if b.is_ascii_alphanumeric() || b == b'_' || b == b'.' {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token<'a>>, FormatError> {
+        let start = self.pos;
+        let Some(b) = self.peek(0) else {
+            return Ok(None);
+        };
+
+        let kind = if b == b'-' && self.peek(1) == Some(b'-') {
+            self.pos += 2;
+            if let Some(level) = self.long_bracket_level() {
+                self.skip_long_bracket(level, "comment")?;
+            } else {
+                while self.peek(0).is_some_and(|b| b != b'\n') {
+                    self.pos += 1;
+                }
+            }
+            TokenKind::Comment
+        } else if let Some(level) = self.long_bracket_level() {
+            self.skip_long_bracket(level, "string")?;
+            TokenKind::String
+        } else if b == b'"' || b == b'\'' {
+            self.skip_quoted_string(b)?;
+            TokenKind::String
+        } else if b == b'`' {
+            self.skip_interpolated_string()?;
+            TokenKind::String
+        } else if b.is_ascii_digit()
+            || (b == b'.' && self.peek(1).is_some_and(|b| b.is_ascii_digit()))
+        {
+            self.skip_number();
+            TokenKind::Number
+        } else if b.is_ascii_alphabetic() || b == b'_' {
+            while self
+                .peek(0)
+                .is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_')
+            {
+                self.pos += 1;
+            }
+            TokenKind::Name
+        } else if let Some(symbol) = SYMBOLS
+            .iter()
+            .find(|s| self.bytes[self.pos..].starts_with(s.as_bytes()))
+        {
+            self.pos += symbol.len();
+            TokenKind::Symbol
+        } else {
+            let c = self.source[start..].chars().next().unwrap_or_default();
+            return Err(self.error(start, format!("unexpected character '{c}'")));
+        };
+
+        // NOTE: Escapes at the very end of a string may skip past the end of the source
+        self.pos = self.pos.min(self.source.len());
+        let mut end = self.pos;
+        if kind == TokenKind::Comment {
+            // Line comments keep neither their newline nor any trailing whitespace
+            end = start + self.source[start..end].trim_end().len();
+        }
+
+        Ok(Some(Token {
+            kind,
+            text: &self.source[start..end],
+            start,
+            end,
+            newlines_before: 0,
+            space_before: false,
+        }))
+    }
+}
+
+/**
+    Splits Luau source code into tokens, including comments.
+
+    # Errors
+
+    Errors if the source contains unfinished strings or comments, or unknown characters.
+*/
+pub(crate) fn tokenize(source: &str) -> Result<Vec<Token<'_>>, FormatError> {
+    let mut lexer = Lexer {
+        source,
+        bytes: source.as_bytes(),
+        pos: 0,
+    };
+    let mut tokens = Vec::new();
+    if source.starts_with("#!") {
+        // Shebang lines are kept as they are, the same as comments
+        lexer.pos = source.find('\n').unwrap_or(source.len());
+        let text = source[..lexer.pos].trim_end();
+        tokens.push(Token {
+            kind: TokenKind::Comment,
+            text,
+            start: 0,
+            end: text.len(),
+            newlines_before: 0,
+            space_before: false,
+        });
+    }
+    loop {
+        let (newlines_before, space_before) = lexer.skip_whitespace();
+        let Some(mut token) = lexer.next_token()? else {
+            break;
+        };
+        token.newlines_before = newlines_before;
+        token.space_before = space_before;
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
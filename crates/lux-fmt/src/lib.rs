@@ -0,0 +1,50 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod config;
+mod error;
+mod format;
+mod lexer;
+mod lint;
+
+pub use self::config::{FormatConfig, IndentType};
+pub use self::error::FormatError;
+pub use self::format::format;
+pub use self::lint::{Diagnostic, Severity, lint};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `fmt` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `fmt` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("format", fmt_format)?
+        .with_function("check", fmt_check)?
+        .build_readonly()
+}
+
+fn fmt_format(_: &Lua, (source, config): (LuaString, FormatConfig)) -> LuaResult<String> {
+    format(&source.to_str()?, &config).map_err(LuaError::runtime)
+}
+
+fn fmt_check(_: &Lua, (source, config): (LuaString, FormatConfig)) -> LuaResult<bool> {
+    let source = source.to_str()?;
+    let formatted = format(&source, &config).map_err(LuaError::runtime)?;
+    Ok(formatted == *source)
+}
@@ -0,0 +1,889 @@
+use std::{collections::HashMap, fmt, ops::Range};
+
+use crate::{
+    error::{FormatError, position},
+    lexer::{Token, TokenKind, tokenize},
+};
+
+/// Number of entries that a table constructor needs to have to count as huge
+const HUGE_TABLE_ENTRIES: usize = 1000;
+
+/**
+    How serious a problem found by a lint is.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Code that may be fine, but is worth a second look
+    Info,
+    /// Code that leaks memory or runs slower than it needs to
+    Warning,
+    /// Code that is almost certainly a bug
+    Error,
+}
+
+impl Severity {
+    /**
+        Returns the name of the severity, in lowercase.
+    */
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/**
+    A problem found by a lint, located at the start of the code it was found in.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    rule: &'static str,
+    severity: Severity,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl Diagnostic {
+    /**
+        Returns the name of the lint that found the problem, such as `ffi-cast-string`.
+    */
+    #[must_use]
+    pub fn rule(&self) -> &'static str {
+        self.rule
+    }
+
+    /**
+        Returns how serious the problem is.
+    */
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /**
+        Returns the line the problem is on, starting at `1`.
+    */
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /**
+        Returns the column the problem starts at, in bytes and starting at `1`.
+    */
+    #[must_use]
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /**
+        Returns the message describing the problem, without its location.
+    */
+    #[must_use]
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}: {} ({})",
+            self.line, self.column, self.severity, self.message, self.rule
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FrameKind {
+    /// The body of a function, remembering if it is given directly
+    /// as an argument to a call, which is the case for most callbacks.
+    Function { argument: bool },
+    /// The condition of a `while` or `for` loop, until the `do` that starts its body.
+    LoopCondition,
+    /// The body of a loop, closed by `end`, or by `until` for `repeat` loops.
+    Loop,
+    /// Any other block closed by `end`.
+    Block,
+    /// An `if` expression, which ends at its `else` since it has no `end`.
+    IfExpression,
+}
+
+/**
+    A block, along with the indices of the tokens that open and close it.
+
+    Blocks that are never closed end at the end of the source.
+*/
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    kind: FrameKind,
+    start: usize,
+    end: usize,
+}
+
+/**
+    A call to a function in a standard library, such as `ffi.cast(...)`.
+*/
+#[derive(Debug, Clone)]
+struct Call {
+    /// Index of the first token of the call, the name of the library
+    start: usize,
+    arguments: Vec<Range<usize>>,
+}
+
+/**
+    Returns the contents of a string literal without its quotes or brackets, or
+    `None` if the token is not a string or is an interpolated string.
+
+    Escape sequences are kept as they are in the source.
+*/
+fn string_contents<'a>(token: &Token<'a>) -> Option<&'a str> {
+    if token.kind != TokenKind::String {
+        return None;
+    }
+    let text = token.text;
+    if text.starts_with('"') || text.starts_with('\'') {
+        text.get(1..text.len() - 1)
+    } else if let Some(rest) = text.strip_prefix('[') {
+        let level = rest.find('[')?;
+        text.get(level + 2..text.len() - level - 2)
+    } else {
+        None
+    }
+}
+
+/**
+    Returns `true` if the given C type is a pointer that allows
+    writing to what it points to, such as `char*` but not `const char*`.
+*/
+fn is_mutable_pointer(type_name: &str) -> bool {
+    let Some(star) = type_name.find('*') else {
+        return false;
+    };
+    !type_name[..star]
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .any(|word| word == "const")
+}
+
+/**
+    Returns `true` if an `if` at the given index is an `if` expression
+    rather than a statement, based on the token that comes before it.
+*/
+fn is_if_expression(tokens: &[Token], index: usize, after_expression_keyword: bool) -> bool {
+    let Some(prev) = index.checked_sub(1).map(|prev| &tokens[prev]) else {
+        return false;
+    };
+    match prev.kind {
+        TokenKind::Symbol => !matches!(prev.text, ")" | "]" | "}" | "..." | ";"),
+        TokenKind::Name if prev.is("then") || prev.is("else") => after_expression_keyword,
+        TokenKind::Name => matches!(
+            prev.text,
+            "return" | "and" | "or" | "not" | "in" | "until" | "while" | "if" | "elseif"
+        ),
+        _ => false,
+    }
+}
+
+/**
+    Returns `true` if an expression right after the given token starts a new
+    statement, meaning that the result of a call there is thrown away.
+*/
+fn is_statement_start(prev: Option<&Token>) -> bool {
+    prev.is_none_or(|prev| {
+        prev.ends_value()
+            || [";", "do", "then", "else", "end", "repeat"]
+                .iter()
+                .any(|s| prev.is(s))
+    })
+}
+
+/**
+    Finds all blocks in the given tokens, in the order that they are opened.
+*/
+fn find_frames(tokens: &[Token]) -> Vec<Frame> {
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut open: Vec<usize> = Vec::new();
+    let mut after_expression_keyword = false;
+
+    for (index, token) in tokens.iter().enumerate() {
+        if !matches!(token.kind, TokenKind::Name | TokenKind::Symbol) {
+            continue;
+        }
+        let top = open.last().map(|&frame| frames[frame].kind);
+        let kind = match token.text {
+            "function" => {
+                let argument = index
+                    .checked_sub(1)
+                    .is_some_and(|prev| tokens[prev].is("(") || tokens[prev].is(","));
+                Some(FrameKind::Function { argument })
+            }
+            "repeat" => Some(FrameKind::Loop),
+            "while" | "for" => Some(FrameKind::LoopCondition),
+            "do" if top == Some(FrameKind::LoopCondition) => {
+                if let Some(&frame) = open.last() {
+                    frames[frame].kind = FrameKind::Loop;
+                    frames[frame].start = index;
+                }
+                None
+            }
+            "if" if is_if_expression(tokens, index, after_expression_keyword) => {
+                Some(FrameKind::IfExpression)
+            }
+            "do" | "if" => Some(FrameKind::Block),
+            "then" => {
+                after_expression_keyword = top == Some(FrameKind::IfExpression);
+                None
+            }
+            "else" => {
+                after_expression_keyword = top == Some(FrameKind::IfExpression);
+                if after_expression_keyword && let Some(frame) = open.pop() {
+                    frames[frame].end = index;
+                }
+                None
+            }
+            "end" | "until" => {
+                if let Some(frame) = open.pop() {
+                    frames[frame].end = index;
+                }
+                None
+            }
+            _ => None,
+        };
+        if let Some(kind) = kind {
+            open.push(frames.len());
+            frames.push(Frame {
+                kind,
+                start: index,
+                end: tokens.len(),
+            });
+        }
+    }
+
+    frames
+}
+
+/**
+    Finds the matching bracket for every bracket in the given tokens, in both directions.
+
+    Unbalanced brackets have no match.
+*/
+fn match_brackets(tokens: &[Token]) -> HashMap<usize, usize> {
+    let mut brackets = HashMap::new();
+    let mut open = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if token.is("(") || token.is("[") || token.is("{") {
+            open.push(index);
+        } else if (token.is(")") || token.is("]") || token.is("}"))
+            && let Some(start) = open.pop()
+        {
+            brackets.insert(start, index);
+            brackets.insert(index, start);
+        }
+    }
+    brackets
+}
+
+struct Linter<'a> {
+    source: &'a str,
+    tokens: Vec<Token<'a>>,
+    frames: Vec<Frame>,
+    brackets: HashMap<usize, usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Linter<'a> {
+    fn new(source: &'a str, tokens: Vec<Token<'a>>) -> Self {
+        let frames = find_frames(&tokens);
+        let brackets = match_brackets(&tokens);
+        Self {
+            source,
+            tokens,
+            frames,
+            brackets,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, index: usize, rule: &'static str, severity: Severity, message: String) {
+        let (line, column) = position(self.source, self.tokens[index].start);
+        self.diagnostics.push(Diagnostic {
+            rule,
+            severity,
+            line,
+            column,
+            message,
+        });
+    }
+
+    /**
+        Returns the blocks that the token at the given index is inside of, innermost first.
+    */
+    fn enclosing(&self, index: usize) -> impl Iterator<Item = &Frame> {
+        self.frames
+            .iter()
+            .rev()
+            .filter(move |frame| frame.start < index && index < frame.end)
+    }
+
+    /**
+        Returns the indices of the tokens within `range` that are not
+        inside of a pair of brackets or a function body in that range.
+    */
+    fn top_level(&self, range: Range<usize>) -> Vec<usize> {
+        let mut indices = Vec::new();
+        let mut index = range.start;
+        while index < range.end {
+            indices.push(index);
+            let token = &self.tokens[index];
+            index = if token.is("(") || token.is("[") || token.is("{") {
+                self.brackets
+                    .get(&index)
+                    .map_or(range.end, |&close| close + 1)
+            } else if token.is("function") {
+                self.frames
+                    .iter()
+                    .find(|frame| frame.start == index)
+                    .map_or(range.end, |frame| frame.end + 1)
+            } else {
+                index + 1
+            };
+        }
+        indices
+    }
+
+    /**
+        Returns the names that a standard library can be used through, which is
+        its own name, for globals like `task`, along with the names of any locals
+        that it was required into, such as `local ffi = require("@lux/ffi")`.
+    */
+    fn library_names<'s>(&'s self, library: &'s str) -> Vec<&'s str> {
+        let path = format!("@lux/{library}");
+        let mut names = vec![library];
+        for index in 0..self.tokens.len() {
+            let [local, name, equals, require, rest @ ..] = &self.tokens[index..] else {
+                break;
+            };
+            if !local.is("local")
+                || name.kind != TokenKind::Name
+                || !equals.is("=")
+                || !require.is("require")
+            {
+                continue;
+            }
+            let argument = match rest {
+                [open, argument, ..] if open.is("(") => argument,
+                [argument, ..] => argument,
+                [] => continue,
+            };
+            if string_contents(argument) == Some(path.as_str()) {
+                names.push(name.text);
+            }
+        }
+        names
+    }
+
+    /**
+        Finds every call to `function` in the given standard library, such as `ffi.cast(...)`,
+        including calls with a single string or table and no parentheses.
+    */
+    fn calls(&self, library: &str, function: &str) -> Vec<Call> {
+        let names = self.library_names(library);
+        let mut calls = Vec::new();
+        for index in 0..self.tokens.len() {
+            let [object, dot, name, next, ..] = &self.tokens[index..] else {
+                break;
+            };
+            let is_field = index
+                .checked_sub(1)
+                .is_some_and(|prev| self.tokens[prev].is(".") || self.tokens[prev].is(":"));
+            if is_field
+                || object.kind != TokenKind::Name
+                || !names.contains(&object.text)
+                || !dot.is(".")
+                || !name.is(function)
+            {
+                continue;
+            }
+            let open = index + 3;
+            let arguments = if next.is("(") || next.is("{") {
+                let Some(&close) = self.brackets.get(&open) else {
+                    continue;
+                };
+                if next.is("(") {
+                    self.split_arguments(open + 1..close)
+                } else {
+                    self.split_arguments(open..close + 1)
+                }
+            } else if next.kind == TokenKind::String {
+                self.split_arguments(open..open + 1)
+            } else {
+                continue;
+            };
+            calls.push(Call {
+                start: index,
+                arguments,
+            });
+        }
+        calls
+    }
+
+    fn split_arguments(&self, range: Range<usize>) -> Vec<Range<usize>> {
+        let mut arguments = Vec::new();
+        let mut start = range.start;
+        for index in self.top_level(range.clone()) {
+            if self.tokens[index].is(",") {
+                arguments.push(start..index);
+                start = index + 1;
+            }
+        }
+        if start < range.end {
+            arguments.push(start..range.end);
+        }
+        arguments
+    }
+
+    /**
+        Returns `true` if the token at the given index starts
+        a method call such as `:name(`, with parentheses.
+    */
+    fn is_method_call(&self, index: usize, method: &str) -> bool {
+        matches!(
+            &self.tokens[index..],
+            [colon, name, open, ..] if colon.is(":") && name.is(method) && open.is("(")
+        )
+    }
+
+    /**
+        Returns `true` if the given argument is an expression that results in a string,
+        as far as can be told from the source alone, such as a literal or a concatenation.
+    */
+    fn is_string_expression(&self, range: Range<usize>) -> bool {
+        match &self.tokens[range.clone()] {
+            [] => false,
+            [token] => token.kind == TokenKind::String,
+            [first, second, ..] => {
+                (first.is("tostring") && second.is("("))
+                    || (first.is("string") && second.is("."))
+                    || self
+                        .top_level(range)
+                        .into_iter()
+                        .any(|index| self.tokens[index].is(".."))
+            }
+        }
+    }
+
+    /**
+        Returns the index of the first token of the prefix expression
+        that ends at the given index, such as `a.b:c()` in `a.b:c():d()`.
+    */
+    fn expression_start(&self, mut index: usize) -> usize {
+        loop {
+            let token = &self.tokens[index];
+            if token.is(")") || token.is("]") {
+                let Some(&open) = self.brackets.get(&index) else {
+                    return index;
+                };
+                // Calls and indexing continue the expression that comes before them
+                match open.checked_sub(1).map(|prev| &self.tokens[prev]) {
+                    Some(prev)
+                        if prev.is(")")
+                            || prev.is("]")
+                            || (prev.kind == TokenKind::Name && !prev.is_keyword()) =>
+                    {
+                        index = open - 1;
+                    }
+                    _ => return open,
+                }
+            } else if index >= 2
+                && (self.tokens[index - 1].is(".") || self.tokens[index - 1].is(":"))
+            {
+                index -= 2;
+            } else {
+                return index;
+            }
+        }
+    }
+
+    /**
+        Finds every local that is given a huge table as its value, returning
+        the index of the name of the local and the number of entries in the table.
+    */
+    fn huge_tables(&self) -> Vec<(usize, usize)> {
+        let mut tables = Vec::new();
+        for index in 0..self.tokens.len() {
+            let [local, name, equals, value, rest @ ..] = &self.tokens[index..] else {
+                break;
+            };
+            if !local.is("local") || name.kind != TokenKind::Name || !equals.is("=") {
+                continue;
+            }
+            let entries = if value.is("{") {
+                self.count_entries(index + 3)
+            } else if let [dot, create, open, size, ..] = rest
+                && value.is("table")
+                && dot.is(".")
+                && create.is("create")
+                && open.is("(")
+            {
+                size.text.parse().unwrap_or(0)
+            } else {
+                continue;
+            };
+            if entries >= HUGE_TABLE_ENTRIES {
+                tables.push((index + 1, entries));
+            }
+        }
+        tables
+    }
+
+    /**
+        Counts the entries in the table constructor opened at the
+        given index, including the entries of any tables nested in it.
+    */
+    fn count_entries(&self, open: usize) -> usize {
+        let Some(&close) = self.brackets.get(&open) else {
+            return 0;
+        };
+        let mut entries = 0;
+        let mut stack = Vec::new();
+        for index in open..close {
+            let token = &self.tokens[index];
+            if token.is("(") || token.is("[") || token.is("{") {
+                stack.push(token.text);
+            } else if token.is(")") || token.is("]") || token.is("}") {
+                stack.pop();
+            }
+            // Entries start right after an opening brace, or a separator between entries
+            let starts_entry =
+                token.is("{") || (stack.last() == Some(&"{") && (token.is(",") || token.is(";")));
+            if starts_entry && !self.tokens[index + 1].is("}") {
+                entries += 1;
+            }
+        }
+        entries
+    }
+
+    /**
+        Casting a string to a pointer that C code can write through lets it modify
+        memory owned by Lua, which corrupts every other use of the same string.
+    */
+    fn check_string_casts(&mut self) {
+        for call in self.calls("ffi", "cast") {
+            let [target, value] = call.arguments.as_slice() else {
+                continue;
+            };
+            let Some(type_name) = (target.len() == 1)
+                .then(|| string_contents(&self.tokens[target.start]))
+                .flatten()
+            else {
+                continue;
+            };
+            if is_mutable_pointer(type_name) && self.is_string_expression(value.clone()) {
+                self.report(
+                    call.start,
+                    "ffi-cast-string",
+                    Severity::Error,
+                    format!(
+                        "casting a string to `{type_name}` lets C code write into memory owned by Lua - \
+                        cast it to a `const` pointer, or copy it with `ffi.newstring` instead"
+                    ),
+                );
+            }
+        }
+    }
+
+    /**
+        Callbacks are never freed while the library they were given to may still call them,
+        so creating one on every iteration of a loop leaks all of them, unless they are freed.
+    */
+    fn check_callbacks_in_loops(&mut self) {
+        for call in self.calls("ffi", "callback") {
+            let Some(&frame) = self
+                .enclosing(call.start)
+                .find(|frame| matches!(frame.kind, FrameKind::Loop | FrameKind::Function { .. }))
+            else {
+                continue;
+            };
+            let freed = (frame.start..frame.end).any(|index| self.is_method_call(index, "free"));
+            if frame.kind == FrameKind::Loop && !freed {
+                self.report(
+                    call.start,
+                    "callback-in-loop",
+                    Severity::Warning,
+                    "`ffi.callback` creates a new callback on every iteration of this loop, \
+                    and none of them are freed - call `:free()` on it once it is no longer \
+                    needed, or create it once outside of the loop"
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    /**
+        `ffi.cdef` parses its declarations every time it is called, which is
+        slow inside of loops, and inside of callbacks that are called often.
+    */
+    fn check_cdefs_in_hot_functions(&mut self) {
+        for call in self.calls("ffi", "cdef") {
+            let hot = self.enclosing(call.start).any(|frame| {
+                matches!(
+                    frame.kind,
+                    FrameKind::Loop | FrameKind::Function { argument: true }
+                )
+            });
+            if hot {
+                self.report(
+                    call.start,
+                    "cdef-in-hot-function",
+                    Severity::Warning,
+                    "`ffi.cdef` parses its declarations again every time it runs, which is slow \
+                    inside of loops and callbacks - declare them once at the top level instead"
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    /**
+        Connections that are thrown away, or stored but never used again, can never
+        be disconnected, which keeps their handlers alive for as long as the signal is.
+    */
+    fn check_connections(&mut self) {
+        for index in 1..self.tokens.len() {
+            if !self.is_method_call(index, "Connect") {
+                continue;
+            }
+            let Some(&close) = self.brackets.get(&(index + 2)) else {
+                continue;
+            };
+            let start = self.expression_start(index - 1);
+            let prev = start.checked_sub(1).map(|prev| &self.tokens[prev]);
+            if is_statement_start(prev) {
+                self.report(
+                    start,
+                    "connection-never-disconnected",
+                    Severity::Info,
+                    "the connection returned by `:Connect` is thrown away, so it can never be \
+                    disconnected - store it and call `:Disconnect()` once it is no longer needed"
+                        .to_string(),
+                );
+            } else if prev.is_some_and(|prev| prev.is("="))
+                && start >= 3
+                && self.tokens[start - 3].is("local")
+                && self.tokens[start - 2].kind == TokenKind::Name
+            {
+                let name = self.tokens[start - 2].text;
+                let used = self.tokens[close + 1..]
+                    .iter()
+                    .any(|token| token.kind == TokenKind::Name && token.text == name);
+                if !used {
+                    self.report(
+                        start - 2,
+                        "connection-never-disconnected",
+                        Severity::Info,
+                        format!(
+                            "the connection stored in `{name}` is never used again, so it is never \
+                            disconnected - call `{name}:Disconnect()` once it is no longer needed"
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /**
+        Functions given to `task` keep everything they capture alive for as long as
+        their thread runs, which for huge tables can be a lot of memory for a long time.
+    */
+    fn check_spawned_captures(&mut self) {
+        let tables = self.huge_tables();
+        if tables.is_empty() {
+            return;
+        }
+        for function in ["spawn", "defer", "delay"] {
+            for call in self.calls("task", function) {
+                for argument in &call.arguments {
+                    let Some(&body) = self
+                        .frames
+                        .iter()
+                        .find(|frame| frame.start == argument.start)
+                    else {
+                        continue;
+                    };
+                    for &(declaration, entries) in &tables {
+                        let table = self.tokens[declaration].text;
+                        // The local has to be declared before the call, in a block that the call is in
+                        let in_scope = declaration < call.start
+                            && self.enclosing(declaration).next().is_none_or(|frame| {
+                                frame.start < call.start && call.start < frame.end
+                            });
+                        let captured = (body.start + 1..body.end).any(|index| {
+                            let token = &self.tokens[index];
+                            token.kind == TokenKind::Name
+                                && token.text == table
+                                && !self.tokens[index - 1].is(".")
+                                && !self.tokens[index - 1].is(":")
+                        });
+                        if in_scope && captured {
+                            self.report(
+                                call.start,
+                                "spawn-captures-huge-table",
+                                Severity::Warning,
+                                format!(
+                                    "the function given to `task.{function}` captures `{table}`, \
+                                    a table with about {entries} entries, which is kept alive for \
+                                    as long as the thread runs - give it only the values it needs"
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/**
+    Checks Luau source code for common misuse of the `ffi`, `signal` and `task` libraries,
+    returning every problem found, sorted by where they are in the source.
+
+    Lints only look at the tokens of the source, so they can not know the types
+    of values, or how often a function is called, and may miss some problems.
+
+    # Errors
+
+    Errors if the source contains unfinished strings or comments, or unknown characters.
+*/
+pub fn lint(source: &str) -> Result<Vec<Diagnostic>, FormatError> {
+    let tokens = tokenize(source)?
+        .into_iter()
+        .filter(|token| token.kind != TokenKind::Comment)
+        .collect();
+
+    let mut linter = Linter::new(source, tokens);
+    linter.check_string_casts();
+    linter.check_callbacks_in_loops();
+    linter.check_cdefs_in_hot_functions();
+    linter.check_connections();
+    linter.check_spawned_captures();
+
+    let mut diagnostics = linter.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    Ok(diagnostics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(source: &str) -> Vec<(&'static str, usize)> {
+        lint(source)
+            .unwrap()
+            .iter()
+            .map(|diagnostic| (diagnostic.rule(), diagnostic.line()))
+            .collect()
+    }
+
+    #[test]
+    fn string_casts() {
+        let source = "local ffi = require(\"@lux/ffi\")\n\
+            local a = ffi.cast(\"char*\", \"hello\")\n\
+            local b = ffi.cast(\"const char*\", \"hello\")\n\
+            local c = ffi.cast(\"uint8_t*\", name .. \"!\")\n\
+            local d = ffi.cast(\"char*\", buffer)\n";
+        assert_eq!(
+            rules(source),
+            [("ffi-cast-string", 2), ("ffi-cast-string", 4)]
+        );
+    }
+
+    #[test]
+    fn callbacks_in_loops() {
+        let source = "for i = 1, 10 do\n\
+                local cb = ffi.callback(\"void(*)()\", function() end)\n\
+            end\n\
+            while true do\n\
+                local cb = ffi.callback(\"void(*)()\", function() end)\n\
+                cb:free()\n\
+            end\n\
+            for _, f in fs do\n\
+                local make = function()\n\
+                    return ffi.callback(\"void(*)()\", f)\n\
+                end\n\
+            end\n";
+        assert_eq!(rules(source), [("callback-in-loop", 2)]);
+    }
+
+    #[test]
+    fn cdefs_in_hot_functions() {
+        let source = "ffi.cdef[[ int a(); ]]\n\
+            local function init()\n\
+                ffi.cdef[[ int b(); ]]\n\
+            end\n\
+            signal:Connect(function()\n\
+                ffi.cdef(\"int c();\")\n\
+            end)\n\
+            repeat\n\
+                local x = if ready then ffi.cdef(\"int d();\") else nil\n\
+            until ready\n";
+        assert_eq!(
+            rules(source),
+            [
+                ("connection-never-disconnected", 5),
+                ("cdef-in-hot-function", 6),
+                ("cdef-in-hot-function", 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn connections() {
+        let source = "a.b.Changed:Connect(print)\n\
+            local kept = getSignal():Connect(print)\n\
+            local unused = signal:Connect(print)\n\
+            table.insert(connections, signal:Connect(print))\n\
+            kept:Disconnect()\n";
+        assert_eq!(
+            rules(source),
+            [
+                ("connection-never-disconnected", 1),
+                ("connection-never-disconnected", 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn spawned_captures() {
+        let entries = vec!["0"; HUGE_TABLE_ENTRIES].join(", ");
+        let source = format!(
+            "local small = {{ 1, 2, 3 }}\n\
+            local huge = {{ {entries} }}\n\
+            local created = table.create(100000)\n\
+            task.spawn(function() print(small, huge) end)\n\
+            task.delay(1, function() print(created[1], t.huge) end)\n\
+            task.defer(function() print(#small) end)\n"
+        );
+        let diagnostics = lint(&source).unwrap();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].line(), 4);
+        assert!(diagnostics[0].message().contains("`huge`"));
+        assert!(
+            diagnostics[0]
+                .message()
+                .contains(&HUGE_TABLE_ENTRIES.to_string())
+        );
+        assert_eq!(diagnostics[1].line(), 5);
+        assert!(diagnostics[1].message().contains("`created`"));
+    }
+}
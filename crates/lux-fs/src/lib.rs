@@ -13,10 +13,15 @@ use lux_utils::TableBuilder;
 mod copy;
 mod metadata;
 mod options;
+mod pattern;
+mod stream;
+mod walk;
 
 use self::copy::copy;
 use self::metadata::FsMetadata;
 use self::options::FsWriteOptions;
+use self::stream::{FsReadStream, FsWriteStream, FsWriteStreamOptions};
+use self::walk::{FsWalkOptions, walk};
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
@@ -39,6 +44,7 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     TableBuilder::new(lua)?
         .with_async_function("readFile", fs_read_file)?
         .with_async_function("readDir", fs_read_dir)?
+        .with_async_function("walkDir", fs_walk_dir)?
         .with_async_function("writeFile", fs_write_file)?
         .with_async_function("writeDir", fs_write_dir)?
         .with_async_function("removeFile", fs_remove_file)?
@@ -48,6 +54,8 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .with_async_function("isDir", fs_is_dir)?
         .with_async_function("move", fs_move)?
         .with_async_function("copy", fs_copy)?
+        .with_async_function("readStream", fs_read_stream)?
+        .with_async_function("writeStream", fs_write_stream)?
         .build_readonly()
 }
 
@@ -73,6 +81,10 @@ async fn fs_read_dir(_: Lua, path: String) -> LuaResult<Vec<String>> {
     Ok(dir_strings)
 }
 
+async fn fs_walk_dir(_: Lua, (path, options): (String, FsWalkOptions)) -> LuaResult<Vec<String>> {
+    walk(PathBuf::from(path), options).await
+}
+
 async fn fs_write_file(_: Lua, (path, contents): (String, BString)) -> LuaResult<()> {
     fs::write(&path, contents.as_bytes()).await.into_lua_err()
 }
@@ -135,3 +147,14 @@ async fn fs_move(_: Lua, (from, to, options): (String, String, FsWriteOptions))
 async fn fs_copy(_: Lua, (from, to, options): (String, String, FsWriteOptions)) -> LuaResult<()> {
     copy(from, to, options).await
 }
+
+async fn fs_read_stream(_: Lua, path: String) -> LuaResult<FsReadStream> {
+    FsReadStream::open(path).await
+}
+
+async fn fs_write_stream(
+    _: Lua,
+    (path, options): (String, FsWriteStreamOptions),
+) -> LuaResult<FsWriteStream> {
+    FsWriteStream::open(path, options).await
+}
@@ -0,0 +1,216 @@
+/**
+    A glob pattern, matched against paths relative to the directory being walked.
+
+    Supports `*` and `?` within a path segment, `**` across any number of segments,
+    character classes such as `[a-z]` and `[!0-9]`, and escaping with `\`.
+
+    Patterns without a `/` are matched against the name of each entry, at any depth,
+    while patterns with one are matched against the whole relative path.
+*/
+#[derive(Debug, Clone)]
+pub struct Glob {
+    chars: Vec<char>,
+    whole_path: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        let whole_path = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+        Self {
+            chars: pattern.chars().collect(),
+            whole_path,
+        }
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        let text = if self.whole_path {
+            path
+        } else {
+            path.rsplit('/').next().unwrap_or(path)
+        };
+        let text = text.chars().collect::<Vec<_>>();
+        glob_match(&self.chars, &text)
+    }
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // Zero or more whole segments
+            glob_match(rest, text)
+                || text
+                    .iter()
+                    .enumerate()
+                    .any(|(i, &c)| c == '/' && glob_match(rest, &text[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+        ['*', rest @ ..] => {
+            let segment = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_match(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => {
+            matches!(text.first(), Some(&c) if c != '/') && glob_match(rest, &text[1..])
+        }
+        ['[', class @ ..] => match parse_class(class) {
+            Some((matches, rest)) => match text.first() {
+                Some(&c) if c != '/' && matches(c) => glob_match(rest, &text[1..]),
+                _ => false,
+            },
+            // An unclosed class is just a bracket
+            None => text.first() == Some(&'[') && glob_match(class, &text[1..]),
+        },
+        ['\\', c, rest @ ..] | [c, rest @ ..] => {
+            text.first() == Some(c) && glob_match(rest, &text[1..])
+        }
+    }
+}
+
+/**
+    Parses a character class, following its opening `[`, returning
+    a function that checks if a character is in the class, and the
+    rest of the pattern after the closing `]`.
+*/
+fn parse_class(class: &[char]) -> Option<(impl Fn(char) -> bool + '_, &[char])> {
+    let (negated, body) = match class {
+        ['!' | '^', body @ ..] => (true, body),
+        body => (false, body),
+    };
+    // A `]` right at the start is part of the class, rather than closing it
+    let end = body.iter().skip(1).position(|&c| c == ']')? + 1;
+    let (items, rest) = (&body[..end], &body[end + 1..]);
+    let matches = move |c: char| {
+        let mut found = false;
+        let mut i = 0;
+        while i < items.len() {
+            if i + 2 < items.len() && items[i + 1] == '-' {
+                found |= (items[i]..=items[i + 2]).contains(&c);
+                i += 3;
+            } else {
+                found |= items[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matches, rest))
+}
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// The directory the rule was given for, relative to the walked directory, ending in `/`
+    base: String,
+    glob: Glob,
+    negated: bool,
+    dirs_only: bool,
+}
+
+/**
+    Rules for paths to skip, written the same way as in a `.gitignore` file.
+
+    The last rule that matches a path decides if it is ignored, with rules starting with `!`
+    including paths again. Rules ending with `/` only match directories, and nothing
+    inside of an ignored directory is walked, even if a later rule would include it.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    /**
+        Adds a rule for paths within `base`, a directory relative to the walked one.
+
+        Blank lines and comments starting with `#` are skipped.
+    */
+    pub fn add(&mut self, base: &str, line: &str) {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(line) => (true, line),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dirs_only, line) = match line.strip_suffix('/') {
+            Some(line) => (true, line),
+            None => (false, line),
+        };
+        if line.is_empty() {
+            return;
+        }
+        let base = if base.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", base.trim_end_matches('/'))
+        };
+        self.rules.push(IgnoreRule {
+            base,
+            glob: Glob::new(line),
+            negated,
+            dirs_only,
+        });
+    }
+
+    /**
+        Adds every rule in the contents of an ignore file in `base`.
+    */
+    pub fn add_file(&mut self, base: &str, contents: &str) {
+        for line in contents.lines() {
+            self.add(base, line);
+        }
+    }
+
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dirs_only)
+                    && path
+                        .strip_prefix(rule.base.as_str())
+                        .is_some_and(|relative| rule.glob.is_match(relative))
+            })
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_globs() {
+        assert!(Glob::new("*.luau").is_match("src/main.luau"));
+        assert!(!Glob::new("*.luau").is_match("src/main.lua"));
+        assert!(Glob::new("src/*.luau").is_match("src/main.luau"));
+        assert!(!Glob::new("src/*.luau").is_match("src/lib/main.luau"));
+        assert!(Glob::new("src/**/*.luau").is_match("src/main.luau"));
+        assert!(Glob::new("src/**/*.luau").is_match("src/lib/deep/main.luau"));
+        assert!(Glob::new("**/test?.luau").is_match("a/b/test1.luau"));
+        assert!(Glob::new("[a-c]*").is_match("beta"));
+        assert!(!Glob::new("[!a-c]*").is_match("beta"));
+        assert!(Glob::new("\\*").is_match("*"));
+        assert!(Glob::new("[").is_match("["));
+    }
+
+    #[test]
+    fn ignores_like_gitignore() {
+        let mut rules = IgnoreRules::default();
+        rules.add_file(
+            "",
+            "# Build output\n/target\nnode_modules/\n*.log\n!keep.log\n",
+        );
+        rules.add("packages/app", "dist");
+
+        assert!(rules.is_ignored("target", true));
+        assert!(!rules.is_ignored("crates/target", true));
+        assert!(rules.is_ignored("web/node_modules", true));
+        assert!(!rules.is_ignored("web/node_modules", false));
+        assert!(rules.is_ignored("logs/debug.log", false));
+        assert!(!rules.is_ignored("logs/keep.log", false));
+        assert!(rules.is_ignored("packages/app/dist", true));
+        assert!(!rules.is_ignored("packages/lib/dist", true));
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use async_fs::{File, OpenOptions};
+use async_lock::Mutex as AsyncMutex;
+use bstr::BString;
+use futures_lite::prelude::*;
+use mlua::prelude::*;
+
+const DEFAULT_READ_SIZE: usize = 64 * 1024;
+
+/**
+    Options for opening a file for streaming writes.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsWriteStreamOptions {
+    pub append: bool,
+}
+
+impl FromLua for FsWriteStreamOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                append: t.get::<Option<bool>>("append")?.unwrap_or_default(),
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "FsWriteStreamOptions".to_string(),
+                message: Some(format!(
+                    "Invalid write stream options - expected table or nil, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn closed_error(path: &str) -> LuaError {
+    LuaError::runtime(format!("Stream for file '{path}' is closed"))
+}
+
+/**
+    A file opened for reading in chunks, without loading it into memory at once.
+*/
+#[derive(Debug, Clone)]
+pub struct FsReadStream {
+    path: String,
+    file: Arc<AsyncMutex<Option<File>>>,
+}
+
+impl FsReadStream {
+    pub async fn open(path: String) -> LuaResult<Self> {
+        let file = File::open(&path).await.into_lua_err()?;
+        Ok(Self {
+            path,
+            file: Arc::new(AsyncMutex::new(Some(file))),
+        })
+    }
+}
+
+impl LuaUserData for FsReadStream {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "FsReadStream");
+        fields.add_field_method_get("path", |_, this| Ok(this.path.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("read", |lua, this, size: Option<usize>| {
+            let this = this.clone();
+            async move {
+                let mut file = this.file.lock().await;
+                let file = file.as_mut().ok_or_else(|| closed_error(&this.path))?;
+                let mut buf = vec![0; size.unwrap_or(DEFAULT_READ_SIZE).max(1)];
+                let read = file.read(&mut buf).await.into_lua_err()?;
+                if read == 0 {
+                    return Ok(LuaValue::Nil);
+                }
+                buf.truncate(read);
+                lua.create_buffer(buf).map(LuaValue::Buffer)
+            }
+        });
+        methods.add_async_method("close", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                this.file.lock().await.take();
+                Ok(())
+            }
+        });
+    }
+}
+
+/**
+    A file opened for writing in chunks, where each write
+    completes only once the data has been handed off to the OS.
+*/
+#[derive(Debug, Clone)]
+pub struct FsWriteStream {
+    path: String,
+    file: Arc<AsyncMutex<Option<File>>>,
+}
+
+impl FsWriteStream {
+    pub async fn open(path: String, options: FsWriteStreamOptions) -> LuaResult<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(options.append)
+            .truncate(!options.append)
+            .open(&path)
+            .await
+            .into_lua_err()?;
+        Ok(Self {
+            path,
+            file: Arc::new(AsyncMutex::new(Some(file))),
+        })
+    }
+}
+
+impl LuaUserData for FsWriteStream {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "FsWriteStream");
+        fields.add_field_method_get("path", |_, this| Ok(this.path.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("write", |_, this, data: BString| {
+            let this = this.clone();
+            async move {
+                let mut file = this.file.lock().await;
+                let file = file.as_mut().ok_or_else(|| closed_error(&this.path))?;
+                file.write_all(&data).await.into_lua_err()
+            }
+        });
+        methods.add_async_method("flush", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                let mut file = this.file.lock().await;
+                let file = file.as_mut().ok_or_else(|| closed_error(&this.path))?;
+                file.flush().await.into_lua_err()
+            }
+        });
+        methods.add_async_method("close", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                // NOTE: Closing twice is not an error, same as for other streams
+                if let Some(mut file) = this.file.lock().await.take() {
+                    file.flush().await.into_lua_err()?;
+                }
+                Ok(())
+            }
+        });
+    }
+}
@@ -0,0 +1,166 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use async_fs as fs;
+use futures_lite::{future, prelude::*};
+use mlua::prelude::*;
+
+use super::pattern::{Glob, IgnoreRules};
+
+/// How many entries to walk before letting other threads run
+const CHUNK_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Default)]
+pub struct FsWalkOptions {
+    pattern: Option<Glob>,
+    depth: Option<usize>,
+    ignore: IgnoreRules,
+    gitignore: bool,
+}
+
+impl FromLua for FsWalkOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let t = match value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::Table(t) => t,
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: "FsWalkOptions".to_string(),
+                    message: Some(format!(
+                        "Invalid walk options - expected table, got {}",
+                        value.type_name()
+                    )),
+                });
+            }
+        };
+
+        let pattern: Option<String> = t.get("pattern")?;
+        let depth = match t.get::<Option<f64>>("depth")? {
+            None => None,
+            Some(depth) if depth >= 1.0 && depth.fract() == 0.0 => Some(depth as usize),
+            Some(depth) => {
+                return Err(LuaError::RuntimeError(format!(
+                    "Invalid walk options - depth must be a positive integer, got {depth}"
+                )));
+            }
+        };
+        let mut ignore = IgnoreRules::default();
+        for rule in t.get::<Option<Vec<String>>>("ignore")?.unwrap_or_default() {
+            ignore.add("", &rule);
+        }
+        let gitignore: Option<bool> = t.get("gitignore")?;
+
+        Ok(Self {
+            pattern: pattern.as_deref().map(Glob::new),
+            depth,
+            ignore,
+            gitignore: gitignore.unwrap_or(false),
+        })
+    }
+}
+
+/**
+    Walks the directory at `root` and everything inside of it, returning the paths
+    of all entries relative to it, separated by `/`, with directories coming right
+    before their contents and entries within a directory in sorted order.
+
+    Symlinks are listed, but never followed, so that walking can not loop forever.
+*/
+pub async fn walk(root: PathBuf, options: FsWalkOptions) -> LuaResult<Vec<String>> {
+    let FsWalkOptions {
+        pattern,
+        depth: max_depth,
+        mut ignore,
+        gitignore,
+    } = options;
+
+    let mut paths = Vec::new();
+    let mut walked = 0;
+
+    // Entries left to walk, as (relative path, depth, is directory), with the next one last
+    let mut pending = Vec::new();
+    read_entries(&root, "", 1, gitignore, &mut ignore, &mut pending).await?;
+
+    while let Some((path, depth, is_dir)) = pending.pop() {
+        if ignore.is_ignored(&path, is_dir) {
+            continue;
+        }
+        if is_dir && max_depth.is_none_or(|max| depth < max) {
+            read_entries(
+                &root,
+                &path,
+                depth + 1,
+                gitignore,
+                &mut ignore,
+                &mut pending,
+            )
+            .await?;
+        }
+        if pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(&path))
+        {
+            paths.push(path);
+        }
+
+        // NOTE: Huge directories take a while to walk, and most
+        // of the time goes into these checks rather than awaiting
+        // the filesystem, so let other threads run once in a while
+        walked += 1;
+        if walked % CHUNK_SIZE == 0 {
+            future::yield_now().await;
+        }
+    }
+
+    Ok(paths)
+}
+
+/**
+    Reads the entries of the directory at `dir`, relative to `root`, adding them to `pending`
+    such that they get popped in sorted order, and reading its `.gitignore` if asked to.
+*/
+async fn read_entries(
+    root: &Path,
+    dir: &str,
+    depth: usize,
+    gitignore: bool,
+    ignore: &mut IgnoreRules,
+    pending: &mut Vec<(String, usize, bool)>,
+) -> LuaResult<()> {
+    let path = if dir.is_empty() {
+        root.to_path_buf()
+    } else {
+        root.join(dir)
+    };
+
+    if gitignore {
+        match fs::read_to_string(path.join(".gitignore")).await {
+            Ok(contents) => ignore.add_file(dir, &contents),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut reader = fs::read_dir(&path).await.into_lua_err()?;
+    while let Some(entry) = reader.try_next().await.into_lua_err()? {
+        let Some(name) = entry.file_name().to_str().map(ToOwned::to_owned) else {
+            return Err(LuaError::RuntimeError(format!(
+                "File name could not be converted into a string: '{}'",
+                entry.file_name().to_string_lossy()
+            )));
+        };
+        let is_dir = entry.file_type().await.into_lua_err()?.is_dir();
+        let relative = if dir.is_empty() {
+            name
+        } else {
+            format!("{dir}/{name}")
+        };
+        entries.push((relative, depth, is_dir));
+    }
+
+    entries.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+    pending.extend(entries);
+    Ok(())
+}
@@ -0,0 +1,63 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Memory usage and garbage collector controls for Lux
+
+use lux_utils::TableBuilder;
+use mlua::prelude::*;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+fn gc_count(lua: &Lua, (): ()) -> LuaResult<usize> {
+    Ok(lua.used_memory())
+}
+
+fn gc_collect(lua: &Lua, (): ()) -> LuaResult<()> {
+    lua.gc_collect()
+}
+
+/// Performs an incremental step, returning whether it finished a collection cycle
+fn gc_step(lua: &Lua, kilobytes: Option<i32>) -> LuaResult<bool> {
+    match kilobytes {
+        Some(kb) if kb < 0 => Err(LuaError::runtime(format!(
+            "Step size must not be negative, got {kb}"
+        ))),
+        Some(kb) => lua.gc_step_kbytes(kb),
+        None => lua.gc_step(),
+    }
+}
+
+fn gc_usage(lua: &Lua, (): ()) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua.clone())?
+        .with_value("luau", lua.used_memory())?
+        .with_value("ffi", lux_ffi::memory::allocated_bytes())?
+        .build()
+}
+
+fn gc_limit(lua: &Lua, (): ()) -> LuaResult<Option<usize>> {
+    // The limit can only be read by replacing it, so put it right back
+    let limit = lua.set_memory_limit(0)?;
+    lua.set_memory_limit(limit)?;
+    Ok((limit > 0).then_some(limit))
+}
+
+/**
+    Creates the `gc` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("count", gc_count)?
+        .with_function("collect", gc_collect)?
+        .with_function("step", gc_step)?
+        .with_function("usage", gc_usage)?
+        .with_function("limit", gc_limit)?
+        .build_readonly()
+}
@@ -0,0 +1,52 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_enum::KeyChord;
+use lux_signal::Connection;
+use lux_utils::TableBuilder;
+
+mod os;
+mod registry;
+
+use self::registry::Registry;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `hotkey` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `hotkey` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_async_function("register", hotkey_register)?
+        .with_function("isRegistered", hotkey_is_registered)?
+        .build_readonly()
+}
+
+fn parse_chord(chord: &str) -> LuaResult<KeyChord> {
+    chord.parse::<KeyChord>().map_err(LuaError::runtime)
+}
+
+async fn hotkey_register(
+    lua: Lua,
+    (chord, callback): (String, LuaFunction),
+) -> LuaResult<Connection> {
+    let chord = parse_chord(&chord)?;
+    Registry::get(&lua).register(&lua, &chord, callback).await
+}
+
+fn hotkey_is_registered(_: &Lua, chord: String) -> LuaResult<bool> {
+    Ok(os::is_registered(&parse_chord(&chord)?))
+}
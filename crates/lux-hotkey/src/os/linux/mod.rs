@@ -0,0 +1,25 @@
+use std::{env, io};
+
+mod portal;
+mod x11;
+
+/**
+    Registers a hotkey with the desktop portal on Wayland, where X11 programs only
+    see keys while one of them is focused, falling back to X11 without a portal.
+*/
+pub(super) async fn register(id: u32, modifiers: i32, key: i32) -> io::Result<()> {
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        match portal::register(id, modifiers, key).await {
+            Err(e)
+                if e.kind() == io::ErrorKind::Unsupported && env::var_os("DISPLAY").is_some() => {}
+            result => return result,
+        }
+    }
+    x11::register(id, modifiers, key).await
+}
+
+pub(super) fn unregister(id: u32) {
+    // NOTE: Unregistering a hotkey that a backend does not know about does nothing
+    portal::unregister(id);
+    x11::unregister(id);
+}
@@ -0,0 +1,183 @@
+//! Hotkeys through the `org.freedesktop.portal.GlobalShortcuts` desktop portal.
+//!
+//! Every hotkey gets a portal session of its own, listened to on a thread of its own,
+//! since sessions can only bind shortcuts once. The desktop may ask the user to confirm
+//! or change the shortcut, and the hotkey is only registered once the user has done so.
+
+use std::{collections::HashMap, io, pin::pin, sync::LazyLock, thread};
+
+use ashpd::desktop::global_shortcuts::{GlobalShortcuts, NewShortcut};
+use async_channel::{Receiver, Sender};
+use futures_lite::{StreamExt, future};
+use parking_lot::Mutex;
+
+use lux_enum::{KEY_CODES, Modifier};
+
+/// Closing the sender for a hotkey stops its thread, closing its session
+static SESSIONS: LazyLock<Mutex<HashMap<u32, Sender<()>>>> = LazyLock::new(Mutex::default);
+
+fn shortcut_id(id: u32) -> String {
+    format!("lux-hotkey-{id}")
+}
+
+/**
+    Converts a hotkey into a trigger as described by the XDG shortcuts specification,
+    which is a list of modifiers followed by the name of an XKB keysym, such as `CTRL+ALT+k`.
+*/
+fn trigger(modifiers: i32, key: i32) -> Option<String> {
+    let (name, _) = KEY_CODES.iter().find(|&&(_, code)| code == key)?;
+    let keysym = match *name {
+        letter if letter.len() == 1 => letter.to_ascii_lowercase(),
+        "Zero" => String::from("0"),
+        "One" => String::from("1"),
+        "Two" => String::from("2"),
+        "Three" => String::from("3"),
+        "Four" => String::from("4"),
+        "Five" => String::from("5"),
+        "Six" => String::from("6"),
+        "Seven" => String::from("7"),
+        "Eight" => String::from("8"),
+        "Nine" => String::from("9"),
+        numpad if numpad.starts_with("Numpad") => format!("KP_{}", &numpad[6..]),
+        function if function.starts_with('F') => String::from(function),
+        other => String::from(match other {
+            "CapsLock" => "Caps_Lock",
+            "LeftShift" => "Shift_L",
+            "RightShift" => "Shift_R",
+            "LeftControl" => "Control_L",
+            "RightControl" => "Control_R",
+            "LeftAlt" => "Alt_L",
+            "RightAlt" => "Alt_R",
+            "LeftSuper" => "Super_L",
+            "RightSuper" => "Super_R",
+            "Space" => "space",
+            "Backspace" => "BackSpace",
+            "PageUp" => "Page_Up",
+            "PageDown" => "Page_Down",
+            "NumLock" => "Num_Lock",
+            "Semicolon" => "semicolon",
+            "Equals" => "equal",
+            "Comma" => "comma",
+            "Minus" => "minus",
+            "Period" => "period",
+            "Slash" => "slash",
+            "Grave" => "grave",
+            "LeftBracket" => "bracketleft",
+            "Backslash" => "backslash",
+            "RightBracket" => "bracketright",
+            "Apostrophe" => "apostrophe",
+            // Escape, Tab, Menu, Return, Delete, Insert, Home, End, and arrows
+            same => same,
+        }),
+    };
+
+    let mut trigger = String::new();
+    for (modifier, name) in [
+        (Modifier::Ctrl, "CTRL"),
+        (Modifier::Alt, "ALT"),
+        (Modifier::Shift, "SHIFT"),
+        (Modifier::Meta, "LOGO"),
+    ] {
+        if modifiers & modifier.mask() != 0 {
+            trigger.push_str(name);
+            trigger.push('+');
+        }
+    }
+    trigger.push_str(&keysym);
+    Some(trigger)
+}
+
+fn unsupported(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("the global shortcuts portal is not available ({e})"),
+    )
+}
+
+async fn run(id: u32, trigger: String, reply: Sender<io::Result<()>>, closed: Receiver<()>) {
+    let fail = async |e: io::Error| {
+        let _ = reply.send(Err(e)).await;
+    };
+
+    let proxy = match GlobalShortcuts::new().await {
+        Ok(proxy) => proxy,
+        Err(e) => return fail(unsupported(e)).await,
+    };
+    let session = match proxy.create_session().await {
+        Ok(session) => session,
+        Err(e) => return fail(unsupported(e)).await,
+    };
+    let activated = match proxy.receive_activated().await {
+        Ok(activated) => activated,
+        Err(e) => return fail(io::Error::other(e.to_string())).await,
+    };
+
+    let shortcut_id = shortcut_id(id);
+    let shortcut = NewShortcut::new(shortcut_id.as_str(), format!("Lux hotkey {trigger}"))
+        .preferred_trigger(trigger.as_str());
+    let bound = match proxy.bind_shortcuts(&session, &[shortcut], None).await {
+        Ok(request) => request.response(),
+        Err(e) => Err(e),
+    };
+    match bound {
+        Ok(bound) if bound.shortcuts().iter().any(|s| s.id() == shortcut_id) => {
+            let _ = reply.send(Ok(())).await;
+        }
+        // NOTE: Desktops leave out shortcuts that they could not bind, such as when the
+        // trigger is taken, and the user may also have cancelled binding it altogether
+        Ok(_) => {
+            let _ = session.close().await;
+            return fail(io::Error::new(io::ErrorKind::AlreadyExists, "not bound")).await;
+        }
+        Err(e) => {
+            let _ = session.close().await;
+            return fail(io::Error::other(e.to_string())).await;
+        }
+    }
+
+    let mut activated = pin!(activated);
+    loop {
+        let next = future::or(activated.next(), async {
+            let _ = closed.recv().await;
+            None
+        })
+        .await;
+        match next {
+            Some(event) if event.shortcut_id() == shortcut_id => super::super::dispatch(id),
+            Some(_) => {}
+            None => break,
+        }
+    }
+    let _ = session.close().await;
+}
+
+pub(super) async fn register(id: u32, modifiers: i32, key: i32) -> io::Result<()> {
+    let trigger = trigger(modifiers, key)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unknown key"))?;
+    let (reply, result) = async_channel::bounded(1);
+    let (close, closed) = async_channel::bounded(1);
+    SESSIONS.lock().insert(id, close);
+
+    let spawned = thread::Builder::new()
+        .name(format!("lux-hotkey-{id}"))
+        .spawn(move || future::block_on(run(id, trigger, reply, closed)));
+    if let Err(e) = spawned {
+        SESSIONS.lock().remove(&id);
+        return Err(e);
+    }
+
+    let result = result
+        .recv()
+        .await
+        .unwrap_or_else(|_| Err(io::Error::other("hotkey thread has stopped")));
+    if result.is_err() {
+        SESSIONS.lock().remove(&id);
+    }
+    result
+}
+
+pub(super) fn unregister(id: u32) {
+    if let Some(close) = SESSIONS.lock().remove(&id) {
+        close.close();
+    }
+}
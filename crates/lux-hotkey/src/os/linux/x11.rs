@@ -0,0 +1,349 @@
+//! Hotkeys through X11, by grabbing keys on the root window, with `libX11` loaded at runtime
+//! so that Lux does not need it to start on machines without a display server.
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong, c_void},
+    io,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use async_channel::Sender;
+use libloading::Library;
+
+type Display = c_void;
+type Window = c_ulong;
+type ErrorHandler = unsafe extern "C" fn(*mut Display, *mut XErrorEvent) -> c_int;
+
+// From X.h
+const KEY_PRESS: c_int = 2;
+const KEY_RELEASE: c_int = 3;
+const GRAB_MODE_ASYNC: c_int = 1;
+const LOCK_MASK: c_uint = 1 << 1;
+const MOD2_MASK: c_uint = 1 << 4;
+const BAD_ACCESS: c_uchar = 10;
+
+/// Keys grabbed along with Caps Lock and Num Lock, which are modifiers to X11
+const IGNORED_MODIFIERS: [c_uint; 4] = [0, LOCK_MASK, MOD2_MASK, LOCK_MASK | MOD2_MASK];
+
+/// How long to wait for key events before checking for new commands
+const POLL_TIMEOUT: Duration = Duration::from_millis(50);
+
+// NOTE: Events are laid out to match Xlib, even where their fields are unused
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+struct XKeyEvent {
+    kind: c_int,
+    serial: c_ulong,
+    send_event: c_int,
+    display: *mut Display,
+    window: Window,
+    root: Window,
+    subwindow: Window,
+    time: c_ulong,
+    x: c_int,
+    y: c_int,
+    x_root: c_int,
+    y_root: c_int,
+    state: c_uint,
+    keycode: c_uint,
+    same_screen: c_int,
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+union XEvent {
+    kind: c_int,
+    key: XKeyEvent,
+    pad: [c_long; 24],
+}
+
+#[repr(C)]
+#[allow(dead_code)]
+struct XErrorEvent {
+    kind: c_int,
+    display: *mut Display,
+    resource_id: c_ulong,
+    serial: c_ulong,
+    error_code: c_uchar,
+    request_code: c_uchar,
+    minor_code: c_uchar,
+}
+
+/// The functions of `libX11` that are used, loaded on first use
+struct Xlib {
+    _lib: Library,
+    open_display: unsafe extern "C" fn(*const c_char) -> *mut Display,
+    default_root_window: unsafe extern "C" fn(*mut Display) -> Window,
+    connection_number: unsafe extern "C" fn(*mut Display) -> c_int,
+    grab_key:
+        unsafe extern "C" fn(*mut Display, c_int, c_uint, Window, c_int, c_int, c_int) -> c_int,
+    ungrab_key: unsafe extern "C" fn(*mut Display, c_int, c_uint, Window) -> c_int,
+    pending: unsafe extern "C" fn(*mut Display) -> c_int,
+    next_event: unsafe extern "C" fn(*mut Display, *mut XEvent) -> c_int,
+    sync: unsafe extern "C" fn(*mut Display, c_int) -> c_int,
+    set_error_handler: unsafe extern "C" fn(Option<ErrorHandler>) -> Option<ErrorHandler>,
+    set_detectable_auto_repeat: unsafe extern "C" fn(*mut Display, c_int, *mut c_int) -> c_int,
+}
+
+impl Xlib {
+    fn load() -> io::Result<Self> {
+        let unavailable = |e: libloading::Error| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("X11 is not available (libX11.so.6): {e}"),
+            )
+        };
+        unsafe {
+            let lib = Library::new("libX11.so.6").map_err(unavailable)?;
+            macro_rules! symbol {
+                ($name:literal) => {
+                    *lib.get(concat!($name, "\0").as_bytes())
+                        .map_err(unavailable)?
+                };
+            }
+            Ok(Self {
+                open_display: symbol!("XOpenDisplay"),
+                default_root_window: symbol!("XDefaultRootWindow"),
+                connection_number: symbol!("XConnectionNumber"),
+                grab_key: symbol!("XGrabKey"),
+                ungrab_key: symbol!("XUngrabKey"),
+                pending: symbol!("XPending"),
+                next_event: symbol!("XNextEvent"),
+                sync: symbol!("XSync"),
+                set_error_handler: symbol!("XSetErrorHandler"),
+                set_detectable_auto_repeat: symbol!("XkbSetDetectableAutoRepeat"),
+                _lib: lib,
+            })
+        }
+    }
+}
+
+/// Set by the error handler when a grab fails because another program has the key grabbed
+static GRAB_DENIED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn on_error(_display: *mut Display, event: *mut XErrorEvent) -> c_int {
+    if unsafe { (*event).error_code } == BAD_ACCESS {
+        GRAB_DENIED.store(true, Ordering::SeqCst);
+    }
+    0
+}
+
+enum Command {
+    Register {
+        id: u32,
+        modifiers: c_uint,
+        keycode: c_int,
+        reply: Sender<io::Result<()>>,
+    },
+    Unregister {
+        id: u32,
+    },
+}
+
+/// The connection to the X server, only ever used from the thread listening for key events
+struct Connection {
+    xlib: Xlib,
+    display: *mut Display,
+    root: Window,
+    grabs: HashMap<u32, (c_int, c_uint)>,
+    held: HashSet<u32>,
+}
+
+impl Connection {
+    fn open() -> io::Result<Self> {
+        let xlib = Xlib::load()?;
+        unsafe {
+            let display = (xlib.open_display)(std::ptr::null());
+            if display.is_null() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "could not connect to the X11 display",
+                ));
+            }
+            (xlib.set_error_handler)(Some(on_error));
+            // Without this, held keys repeat as pairs of releases and presses
+            (xlib.set_detectable_auto_repeat)(display, 1, std::ptr::null_mut());
+            let root = (xlib.default_root_window)(display);
+            Ok(Self {
+                xlib,
+                display,
+                root,
+                grabs: HashMap::new(),
+                held: HashSet::new(),
+            })
+        }
+    }
+
+    fn grab(&mut self, id: u32, modifiers: c_uint, keycode: c_int) -> io::Result<()> {
+        unsafe {
+            GRAB_DENIED.store(false, Ordering::SeqCst);
+            for ignored in IGNORED_MODIFIERS {
+                (self.xlib.grab_key)(
+                    self.display,
+                    keycode,
+                    modifiers | ignored,
+                    self.root,
+                    0,
+                    GRAB_MODE_ASYNC,
+                    GRAB_MODE_ASYNC,
+                );
+            }
+            // NOTE: Errors are reported asynchronously, so wait for the server to process the grabs
+            (self.xlib.sync)(self.display, 0);
+        }
+        if GRAB_DENIED.load(Ordering::SeqCst) {
+            self.ungrab(keycode, modifiers);
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                "key is grabbed by another program",
+            ));
+        }
+        self.grabs.insert(id, (keycode, modifiers));
+        Ok(())
+    }
+
+    fn ungrab(&self, keycode: c_int, modifiers: c_uint) {
+        unsafe {
+            for ignored in IGNORED_MODIFIERS {
+                (self.xlib.ungrab_key)(self.display, keycode, modifiers | ignored, self.root);
+            }
+            (self.xlib.sync)(self.display, 0);
+        }
+    }
+
+    fn handle(&mut self, command: Command) {
+        match command {
+            Command::Register {
+                id,
+                modifiers,
+                keycode,
+                reply,
+            } => {
+                let _ = reply.send_blocking(self.grab(id, modifiers, keycode));
+            }
+            Command::Unregister { id } => {
+                if let Some((keycode, modifiers)) = self.grabs.remove(&id) {
+                    self.ungrab(keycode, modifiers);
+                    self.held.remove(&id);
+                }
+            }
+        }
+    }
+
+    fn find(&self, keycode: c_uint, state: c_uint) -> Option<u32> {
+        let state = state & !(LOCK_MASK | MOD2_MASK);
+        self.grabs
+            .iter()
+            .find(|&(_, &(k, m))| k as c_uint == keycode && m == state)
+            .map(|(&id, _)| id)
+    }
+
+    fn process_events(&mut self) {
+        while unsafe { (self.xlib.pending)(self.display) } > 0 {
+            let mut event = XEvent { pad: [0; 24] };
+            let (kind, key) = unsafe {
+                (self.xlib.next_event)(self.display, &mut event);
+                (event.kind, event.key)
+            };
+            if kind != KEY_PRESS && kind != KEY_RELEASE {
+                continue;
+            }
+            let Some(id) = self.find(key.keycode, key.state) else {
+                continue;
+            };
+            match kind {
+                // Held keys keep sending presses, but hotkeys only fire once per press
+                KEY_PRESS if self.held.insert(id) => super::super::dispatch(id),
+                KEY_RELEASE => {
+                    self.held.remove(&id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn wait(&self) {
+        let mut fd = libc::pollfd {
+            fd: unsafe { (self.xlib.connection_number)(self.display) },
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut fd, 1, POLL_TIMEOUT.as_millis() as c_int) };
+    }
+}
+
+fn run(connection: &mut Connection, commands: &mpsc::Receiver<Command>) {
+    loop {
+        for command in commands.try_iter() {
+            connection.handle(command);
+        }
+        connection.process_events();
+        connection.wait();
+    }
+}
+
+/// The thread listening for key events, which is sent commands to grab and ungrab keys
+static WORKER: OnceLock<Result<mpsc::Sender<Command>, (io::ErrorKind, String)>> = OnceLock::new();
+
+fn worker() -> io::Result<&'static mpsc::Sender<Command>> {
+    WORKER
+        .get_or_init(|| {
+            let (commands, receiver) = mpsc::channel();
+            let (ready, opened) = mpsc::channel();
+            thread::Builder::new()
+                .name(String::from("lux-hotkey"))
+                .spawn(move || match Connection::open() {
+                    Ok(mut connection) => {
+                        let _ = ready.send(Ok(()));
+                        run(&mut connection, &receiver);
+                    }
+                    Err(e) => {
+                        let _ = ready.send(Err(e));
+                    }
+                })
+                .map_err(|e| (e.kind(), e.to_string()))?;
+            match opened.recv() {
+                Ok(Ok(())) => Ok(commands),
+                Ok(Err(e)) => Err((e.kind(), e.to_string())),
+                Err(_) => Err((
+                    io::ErrorKind::Other,
+                    String::from("hotkey thread failed to start"),
+                )),
+            }
+        })
+        .as_ref()
+        .map_err(|(kind, message)| io::Error::new(*kind, message.clone()))
+}
+
+pub(super) async fn register(id: u32, modifiers: i32, key: i32) -> io::Result<()> {
+    let (reply, result) = async_channel::bounded(1);
+    worker()?
+        .send(Command::Register {
+            id,
+            // Enum.ModifierKey already has the X11 modifier masks, and X11
+            // key codes are the Enum.KeyCode evdev codes offset by 8
+            modifiers: modifiers as c_uint,
+            keycode: key + 8,
+            reply,
+        })
+        .map_err(|_| io::Error::other("hotkey thread has stopped"))?;
+    result
+        .recv()
+        .await
+        .unwrap_or_else(|_| Err(io::Error::other("hotkey thread has stopped")))
+}
+
+pub(super) fn unregister(id: u32) {
+    if let Some(Ok(commands)) = WORKER.get() {
+        let _ = commands.send(Command::Unregister { id });
+    }
+}
@@ -0,0 +1,169 @@
+//! Carbon hotkeys and `NSEvent` monitors are both delivered through the run loop of the
+//! main thread, which a command line program never runs, so hotkeys are instead caught
+//! with a Quartz event tap that runs on a thread of its own - the same mechanism that
+//! global `NSEvent` monitors use. Unlike a monitor, the tap consumes the hotkeys it catches.
+
+use std::{
+    ffi::c_void,
+    io,
+    sync::{
+        OnceLock,
+        atomic::{AtomicPtr, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use lux_enum::Modifier;
+
+type CFMachPortRef = *mut c_void;
+type CFRunLoopSourceRef = *mut c_void;
+type CFRunLoopRef = *mut c_void;
+type CGEventRef = *mut c_void;
+type CGEventTapCallBack = unsafe extern "C" fn(
+    proxy: *mut c_void,
+    kind: u32,
+    event: CGEventRef,
+    info: *mut c_void,
+) -> CGEventRef;
+
+// From CGEventTypes.h
+const SESSION_EVENT_TAP: u32 = 1;
+const HEAD_INSERT_EVENT_TAP: u32 = 0;
+const EVENT_TAP_OPTION_DEFAULT: u32 = 0;
+const EVENT_KEY_DOWN: u32 = 10;
+const EVENT_TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFFFFFE;
+const EVENT_TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFFFFFF;
+const FIELD_KEYBOARD_EVENT_AUTOREPEAT: u32 = 8;
+const FIELD_KEYBOARD_EVENT_KEYCODE: u32 = 9;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventTapCreate(
+        tap: u32,
+        place: u32,
+        options: u32,
+        events_of_interest: u64,
+        callback: CGEventTapCallBack,
+        info: *mut c_void,
+    ) -> CFMachPortRef;
+    fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
+    fn CGEventGetIntegerValueField(event: CGEventRef, field: u32) -> i64;
+    fn CGEventGetFlags(event: CGEventRef) -> u64;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    #[allow(non_upper_case_globals)]
+    static kCFRunLoopCommonModes: *const c_void;
+    fn CFMachPortCreateRunLoopSource(
+        allocator: *const c_void,
+        port: CFMachPortRef,
+        order: isize,
+    ) -> CFRunLoopSourceRef;
+    fn CFRunLoopGetCurrent() -> CFRunLoopRef;
+    fn CFRunLoopAddSource(run_loop: CFRunLoopRef, source: CFRunLoopSourceRef, mode: *const c_void);
+    fn CFRunLoopRun();
+}
+
+/// The event tap, kept to enable it again whenever macOS disables it
+static TAP: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+unsafe extern "C" fn on_event(
+    _proxy: *mut c_void,
+    kind: u32,
+    event: CGEventRef,
+    _info: *mut c_void,
+) -> CGEventRef {
+    if kind == EVENT_TAP_DISABLED_BY_TIMEOUT || kind == EVENT_TAP_DISABLED_BY_USER_INPUT {
+        unsafe { CGEventTapEnable(TAP.load(Ordering::SeqCst), true) };
+        return event;
+    }
+    if kind != EVENT_KEY_DOWN {
+        return event;
+    }
+
+    let relevant = Modifier::ALL.iter().fold(0, |mask, m| mask | m.mask()) as u64;
+    let (key, repeat, modifiers) = unsafe {
+        (
+            CGEventGetIntegerValueField(event, FIELD_KEYBOARD_EVENT_KEYCODE),
+            CGEventGetIntegerValueField(event, FIELD_KEYBOARD_EVENT_AUTOREPEAT),
+            CGEventGetFlags(event) & relevant,
+        )
+    };
+    match super::find(modifiers as i32, key as i32) {
+        Some(id) => {
+            if repeat == 0 {
+                super::dispatch(id);
+            }
+            // Hotkeys are consumed, including any repeats while they are held
+            std::ptr::null_mut()
+        }
+        None => event,
+    }
+}
+
+fn run(ready: &mpsc::Sender<io::Result<()>>) {
+    unsafe {
+        let tap = CGEventTapCreate(
+            SESSION_EVENT_TAP,
+            HEAD_INSERT_EVENT_TAP,
+            EVENT_TAP_OPTION_DEFAULT,
+            1 << EVENT_KEY_DOWN,
+            on_event,
+            std::ptr::null_mut(),
+        );
+        if tap.is_null() {
+            let _ = ready.send(Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "global hotkeys require Accessibility access, which can be granted to \
+                the terminal running Lux in System Settings > Privacy & Security",
+            )));
+            return;
+        }
+        TAP.store(tap, Ordering::SeqCst);
+
+        let source = CFMachPortCreateRunLoopSource(std::ptr::null(), tap, 0);
+        CFRunLoopAddSource(CFRunLoopGetCurrent(), source, kCFRunLoopCommonModes);
+        CGEventTapEnable(tap, true);
+        let _ = ready.send(Ok(()));
+        CFRunLoopRun();
+    }
+}
+
+/**
+    Starts the event tap the first time that it is needed.
+
+    The tap keeps running for the rest of the process, since
+    hotkeys that are not registered pass straight through it.
+*/
+fn start() -> io::Result<()> {
+    static STARTED: OnceLock<Result<(), (io::ErrorKind, String)>> = OnceLock::new();
+    STARTED
+        .get_or_init(|| {
+            let (ready, started) = mpsc::channel();
+            thread::Builder::new()
+                .name(String::from("lux-hotkey"))
+                .spawn(move || run(&ready))
+                .map_err(|e| (e.kind(), e.to_string()))?;
+            match started.recv() {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(e)) => Err((e.kind(), e.to_string())),
+                Err(_) => Err((
+                    io::ErrorKind::Other,
+                    String::from("hotkey thread failed to start"),
+                )),
+            }
+        })
+        .clone()
+        .map_err(|(kind, message)| io::Error::new(kind, message))
+}
+
+#[allow(clippy::unused_async)] // Kept async to match the other platforms
+pub(super) async fn register(_id: u32, _modifiers: i32, _key: i32) -> io::Result<()> {
+    // NOTE: The tap looks up every key press in the registered hotkeys,
+    // so there is nothing more to do than to make sure that it is running
+    start()
+}
+
+pub(super) fn unregister(_id: u32) {}
@@ -0,0 +1,147 @@
+//! Platform-specific global hotkeys, each listening on a background thread of its own:
+//! - Windows: `RegisterHotKey`, with a message loop for `WM_HOTKEY`
+//! - Linux: the `GlobalShortcuts` desktop portal on Wayland, and `XGrabKey` on X11
+//! - macOS: a Quartz event tap, which needs Accessibility access
+//!
+//! Hotkeys are identified by ids that are unique to the process, and every time a hotkey
+//! is pressed its id is sent to the channel that was given when registering it.
+
+use std::{
+    collections::HashMap,
+    io,
+    sync::{LazyLock, atomic::AtomicU32},
+};
+
+use async_channel::Sender;
+use mlua::prelude::*;
+use parking_lot::Mutex;
+
+use lux_enum::KeyChord;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(target_os = "linux")]
+use self::linux as imp;
+#[cfg(target_os = "macos")]
+use self::macos as imp;
+#[cfg(windows)]
+use self::windows as imp;
+
+/// The next id to give a hotkey, starting at 1 since some platforms reserve 0
+pub(crate) static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+struct Registration {
+    modifiers: i32,
+    key: i32,
+    sender: Sender<u32>,
+}
+
+/// Every hotkey registered by any Luau VM in the process, by id
+static REGISTERED: LazyLock<Mutex<HashMap<u32, Registration>>> = LazyLock::new(Mutex::default);
+
+/**
+    Registers a global hotkey, sending its id to the given channel whenever it is pressed.
+
+    Errors if the chord is already registered, by this or another program.
+*/
+pub(crate) async fn register(id: u32, chord: &KeyChord, sender: Sender<u32>) -> LuaResult<()> {
+    let modifiers = chord.modifier_mask();
+    let key = chord.key;
+    {
+        // NOTE: The chord is claimed before waiting for the platform so that
+        // registering the same chord twice at once can not succeed for both
+        let mut registered = REGISTERED.lock();
+        if registered
+            .values()
+            .any(|r| r.modifiers == modifiers && r.key == key)
+        {
+            return Err(LuaError::runtime(format!(
+                "Hotkey '{chord}' is already registered"
+            )));
+        }
+        registered.insert(
+            id,
+            Registration {
+                modifiers,
+                key,
+                sender,
+            },
+        );
+    }
+
+    match imp::register(id, modifiers, key).await {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            REGISTERED.lock().remove(&id);
+            Err(if e.kind() == io::ErrorKind::AlreadyExists {
+                LuaError::runtime(format!(
+                    "Hotkey '{chord}' is already registered by another program"
+                ))
+            } else {
+                LuaError::runtime(format!("Failed to register hotkey '{chord}': {e}"))
+            })
+        }
+    }
+}
+
+/**
+    Unregisters a global hotkey, doing nothing if it was not registered.
+*/
+pub(crate) fn unregister(id: u32) {
+    if REGISTERED.lock().remove(&id).is_some() {
+        imp::unregister(id);
+    }
+}
+
+/**
+    Returns whether the given chord is registered by any Luau VM in the process.
+*/
+pub(crate) fn is_registered(chord: &KeyChord) -> bool {
+    find(chord.modifier_mask(), chord.key).is_some()
+}
+
+/**
+    Finds the id of the hotkey for a combination of `Enum.ModifierKey` and `Enum.KeyCode`.
+*/
+fn find(modifiers: i32, key: i32) -> Option<u32> {
+    REGISTERED
+        .lock()
+        .iter()
+        .find(|(_, r)| r.modifiers == modifiers && r.key == key)
+        .map(|(&id, _)| id)
+}
+
+/**
+    Sends a pressed hotkey to whoever registered it, from the thread listening for hotkeys.
+*/
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "macos", windows)),
+    allow(dead_code)
+)]
+fn dispatch(id: u32) {
+    if let Some(registration) = REGISTERED.lock().get(&id) {
+        // NOTE: A full channel means the hotkey is pressed faster than
+        // the scheduler gets to it, so those extra presses are dropped
+        let _ = registration.sender.try_send(id);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+#[allow(clippy::unused_async)]
+mod imp {
+    use std::io;
+
+    pub(super) async fn register(_id: u32, _modifiers: i32, _key: i32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "global hotkeys are not supported on this platform",
+        ))
+    }
+
+    pub(super) fn unregister(_id: u32) {}
+}
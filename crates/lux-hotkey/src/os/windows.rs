@@ -0,0 +1,157 @@
+use std::{
+    io,
+    sync::{OnceLock, mpsc},
+    thread,
+};
+
+use async_channel::Sender;
+use windows_sys::Win32::{
+    Foundation::ERROR_HOTKEY_ALREADY_REGISTERED,
+    System::Threading::GetCurrentThreadId,
+    UI::{
+        Input::KeyboardAndMouse::{MOD_NOREPEAT, RegisterHotKey, UnregisterHotKey},
+        WindowsAndMessaging::{
+            GetMessageW, MSG, PM_NOREMOVE, PeekMessageW, PostThreadMessageW, WM_APP, WM_HOTKEY,
+            WM_USER,
+        },
+    },
+};
+
+enum Command {
+    Register {
+        id: u32,
+        modifiers: u32,
+        key: u32,
+        reply: Sender<io::Result<()>>,
+    },
+    Unregister {
+        id: u32,
+    },
+}
+
+/**
+    The thread that hotkeys are registered on, which is also
+    the thread that Windows posts `WM_HOTKEY` messages to.
+*/
+struct Worker {
+    thread_id: u32,
+    commands: mpsc::Sender<Command>,
+}
+
+impl Worker {
+    fn get() -> io::Result<&'static Self> {
+        static WORKER: OnceLock<Result<Worker, String>> = OnceLock::new();
+        WORKER
+            .get_or_init(|| Self::spawn().map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(|e| io::Error::other(e.clone()))
+    }
+
+    fn spawn() -> io::Result<Self> {
+        let (commands, receiver) = mpsc::channel();
+        let (ready, thread_id) = mpsc::channel();
+        thread::Builder::new()
+            .name(String::from("lux-hotkey"))
+            .spawn(move || run(&ready, &receiver))?;
+        let thread_id = thread_id
+            .recv()
+            .map_err(|_| io::Error::other("hotkey thread failed to start"))?;
+        Ok(Self {
+            thread_id,
+            commands,
+        })
+    }
+
+    fn send(&self, command: Command) -> io::Result<()> {
+        self.commands
+            .send(command)
+            .map_err(|_| io::Error::other("hotkey thread has stopped"))?;
+        // NOTE: The thread only wakes up for messages, so commands are announced with one
+        if unsafe { PostThreadMessageW(self.thread_id, WM_APP, 0, 0) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+fn run(ready: &mpsc::Sender<u32>, commands: &mpsc::Receiver<Command>) {
+    let mut msg: MSG = unsafe { std::mem::zeroed() };
+    unsafe {
+        // Messages can only be posted to threads that have a message queue,
+        // which is created the first time that the thread looks for messages
+        PeekMessageW(
+            &mut msg,
+            std::ptr::null_mut(),
+            WM_USER,
+            WM_USER,
+            PM_NOREMOVE,
+        );
+        let _ = ready.send(GetCurrentThreadId());
+    }
+
+    while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+        match msg.message {
+            WM_HOTKEY => super::dispatch(msg.wParam as u32),
+            WM_APP => {
+                for command in commands.try_iter() {
+                    handle(command);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn handle(command: Command) {
+    match command {
+        Command::Register {
+            id,
+            modifiers,
+            key,
+            reply,
+        } => {
+            let registered = unsafe {
+                RegisterHotKey(
+                    std::ptr::null_mut(),
+                    id as i32,
+                    modifiers | MOD_NOREPEAT,
+                    key,
+                )
+            };
+            let result = if registered != 0 {
+                Ok(())
+            } else {
+                let e = io::Error::last_os_error();
+                if e.raw_os_error() == Some(ERROR_HOTKEY_ALREADY_REGISTERED as i32) {
+                    Err(io::Error::new(io::ErrorKind::AlreadyExists, e))
+                } else {
+                    Err(e)
+                }
+            };
+            let _ = reply.send_blocking(result);
+        }
+        Command::Unregister { id } => unsafe {
+            UnregisterHotKey(std::ptr::null_mut(), id as i32);
+        },
+    }
+}
+
+pub(super) async fn register(id: u32, modifiers: i32, key: i32) -> io::Result<()> {
+    let (reply, result) = async_channel::bounded(1);
+    Worker::get()?.send(Command::Register {
+        id,
+        modifiers: modifiers as u32,
+        key: key as u32,
+        reply,
+    })?;
+    result
+        .recv()
+        .await
+        .unwrap_or_else(|_| Err(io::Error::other("hotkey thread has stopped")))
+}
+
+pub(super) fn unregister(id: u32) {
+    if let Ok(worker) = Worker::get() {
+        let _ = worker.send(Command::Unregister { id });
+    }
+}
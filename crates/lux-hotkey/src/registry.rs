@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, atomic::Ordering},
+    time::Duration,
+};
+
+use async_channel::{Receiver, Sender};
+use async_io::Timer;
+use futures_lite::future;
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+use parking_lot::Mutex;
+
+use lux_enum::KeyChord;
+use lux_signal::{Connection, Signal};
+
+use crate::os;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How many presses of hotkeys can be waiting for the scheduler before more are dropped
+const PRESS_CAPACITY: usize = 16;
+
+#[derive(Default)]
+struct State {
+    hotkeys: HashMap<u32, Signal>,
+    listening: bool,
+}
+
+impl State {
+    /**
+        Unregisters all hotkeys that no longer have their handler connected.
+    */
+    fn prune(&mut self) {
+        self.hotkeys.retain(|&id, signal| {
+            let connected = signal.count() > 0;
+            if !connected {
+                os::unregister(id);
+            }
+            connected
+        });
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        // NOTE: This runs once the Luau VM has closed, and hotkeys are
+        // global to the system, so they must never outlive their VM
+        for &id in self.hotkeys.keys() {
+            os::unregister(id);
+        }
+    }
+}
+
+/**
+    The hotkeys registered by a Luau VM.
+
+    Pressed hotkeys are only listened for while any of them are registered, so
+    that the scheduler is kept alive for exactly as long as hotkeys can fire.
+    Hotkeys are unregistered shortly after their connection is disconnected.
+*/
+#[derive(Clone)]
+pub(crate) struct Registry {
+    state: Arc<Mutex<State>>,
+    sender: Sender<u32>,
+    receiver: Receiver<u32>,
+}
+
+impl Registry {
+    pub(crate) fn get(lua: &Lua) -> Self {
+        if let Some(registry) = lua.app_data_ref::<Self>() {
+            return registry.clone();
+        }
+        let (sender, receiver) = async_channel::bounded(PRESS_CAPACITY);
+        let registry = Self {
+            state: Arc::default(),
+            sender,
+            receiver,
+        };
+        lua.set_app_data(registry.clone());
+        registry
+    }
+
+    pub(crate) async fn register(
+        &self,
+        lua: &Lua,
+        chord: &KeyChord,
+        callback: LuaFunction,
+    ) -> LuaResult<Connection> {
+        // Disconnected hotkeys may not have been noticed yet, and
+        // should not count as conflicts when registered again
+        self.state.lock().prune();
+
+        let id = os::NEXT_ID.fetch_add(1, Ordering::SeqCst);
+        os::register(id, chord, self.sender.clone()).await?;
+
+        let signal = Signal::new();
+        let connection = signal.connect_handler(lua, callback);
+
+        let mut state = self.state.lock();
+        state.hotkeys.insert(id, signal);
+        if !state.listening {
+            state.listening = true;
+            lua.spawn_local(listen(lua.clone(), self.clone()));
+        }
+        Ok(connection)
+    }
+}
+
+async fn listen(lua: Lua, registry: Registry) {
+    loop {
+        {
+            let mut state = registry.state.lock();
+            state.prune();
+            if state.hotkeys.is_empty() {
+                state.listening = false;
+                break;
+            }
+        }
+
+        let pressed = future::or(async { registry.receiver.recv().await.ok() }, async {
+            Timer::after(POLL_INTERVAL).await;
+            None
+        })
+        .await;
+
+        let signal = pressed.and_then(|id| {
+            let state = registry.state.lock();
+            state.hotkeys.get(&id).cloned()
+        });
+        if let Some(signal) = signal {
+            signal.fire_native(&lua, LuaMultiValue::new());
+        }
+    }
+}
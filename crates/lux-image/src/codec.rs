@@ -0,0 +1,115 @@
+use std::io::Cursor;
+
+use ::image::{ImageFormat, RgbaImage, imageops::FilterType};
+use mlua::prelude::*;
+
+use super::image::Image;
+
+/**
+    Decodes a PNG, JPEG or BMP image, detecting the format from its contents.
+
+    # Errors
+
+    Errors if the format is not recognized, or if the data is not a valid image.
+*/
+pub fn decode(bytes: &[u8]) -> LuaResult<Image> {
+    let format = ::image::guess_format(bytes).map_err(|_| {
+        LuaError::runtime("Failed to decode image - expected PNG, JPEG or BMP data")
+    })?;
+    if !matches!(
+        format,
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Bmp
+    ) {
+        return Err(LuaError::runtime(format!(
+            "Failed to decode image - unsupported format '{}'",
+            format.extensions_str().first().unwrap_or(&"unknown")
+        )));
+    }
+    let decoded = ::image::load_from_memory_with_format(bytes, format)
+        .map_err(|e| LuaError::runtime(format!("Failed to decode image - {e}")))?
+        .into_rgba8();
+    Image::from_rgba(decoded.width(), decoded.height(), decoded.into_raw())
+}
+
+/**
+    Encodes an image as PNG, keeping the alpha channel.
+
+    # Errors
+
+    Errors if encoding fails, which should only happen when out of memory.
+*/
+pub fn encode_png(image: &Image) -> LuaResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    to_rgba_image(image)
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|e| LuaError::runtime(format!("Failed to encode image - {e}")))?;
+    Ok(bytes)
+}
+
+/**
+    The filter used to sample pixels when resizing an image.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizeFilter {
+    Nearest,
+    #[default]
+    Linear,
+    Cubic,
+    Lanczos,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> FilterType {
+        match self {
+            Self::Nearest => FilterType::Nearest,
+            Self::Linear => FilterType::Triangle,
+            Self::Cubic => FilterType::CatmullRom,
+            Self::Lanczos => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl FromLua for ResizeFilter {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::String(s) => match &*s.to_str()? {
+                "nearest" => Ok(Self::Nearest),
+                "linear" => Ok(Self::Linear),
+                "cubic" => Ok(Self::Cubic),
+                "lanczos" => Ok(Self::Lanczos),
+                other => Err(LuaError::runtime(format!(
+                    "Invalid resize filter '{other}' - expected one of 'nearest', 'linear', 'cubic', 'lanczos'"
+                ))),
+            },
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("ResizeFilter"),
+                message: Some(String::from("Expected filter to be a string")),
+            }),
+        }
+    }
+}
+
+/**
+    Resizes an image to exactly the given dimensions, ignoring its aspect ratio.
+
+    # Errors
+
+    Errors if either dimension is zero.
+*/
+pub fn resize(image: &Image, width: u32, height: u32, filter: ResizeFilter) -> LuaResult<Image> {
+    if width == 0 || height == 0 {
+        return Err(LuaError::runtime(format!(
+            "Image dimensions must be non-zero, got {width}x{height}"
+        )));
+    }
+    let resized =
+        ::image::imageops::resize(&to_rgba_image(image), width, height, filter.filter_type());
+    Image::from_rgba(width, height, resized.into_raw())
+}
+
+fn to_rgba_image(image: &Image) -> RgbaImage {
+    RgbaImage::from_raw(image.width(), image.height(), image.to_rgba())
+        .expect("image always has the correct amount of pixel data")
+}
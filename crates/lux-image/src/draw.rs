@@ -0,0 +1,209 @@
+use font8x8::{BASIC_FONTS, UnicodeFonts};
+
+use lux_udim::Rect;
+
+use super::image::{Image, Rgba};
+
+// NOTE: All glyphs in the bundled font are 8x8 pixels
+const GLYPH_SIZE: u32 = 8;
+
+/// Converts a Rect into inclusive-exclusive pixel bounds
+pub fn pixel_bounds(rect: &Rect) -> (i64, i64, i64, i64) {
+    (
+        rect.min_x.round() as i64,
+        rect.min_y.round() as i64,
+        rect.max_x.round() as i64,
+        rect.max_y.round() as i64,
+    )
+}
+
+/**
+    Draws a line between two points, inclusive, using Bresenham's algorithm.
+*/
+pub fn line(image: &mut Image, (x0, y0): (i64, i64), (x1, y1): (i64, i64), pixel: Rgba) {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+    let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+    loop {
+        image.blend(x, y, pixel);
+        if x == x1 && y == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+/**
+    Draws the 1 pixel wide outline of a Rect.
+*/
+pub fn rect(image: &mut Image, rect: &Rect, pixel: Rgba) {
+    let (x0, y0, x1, y1) = pixel_bounds(rect);
+    if x1 <= x0 || y1 <= y0 {
+        return;
+    }
+    for x in x0..x1 {
+        image.blend(x, y0, pixel);
+        if y1 - 1 != y0 {
+            image.blend(x, y1 - 1, pixel);
+        }
+    }
+    for y in (y0 + 1)..(y1 - 1) {
+        image.blend(x0, y, pixel);
+        if x1 - 1 != x0 {
+            image.blend(x1 - 1, y, pixel);
+        }
+    }
+}
+
+/**
+    Fills the area covered by a Rect.
+*/
+pub fn fill_rect(image: &mut Image, rect: &Rect, pixel: Rgba) {
+    let (x0, y0, x1, y1) = pixel_bounds(rect);
+    let (x0, y0) = (x0.max(0), y0.max(0));
+    let (x1, y1) = (
+        x1.min(i64::from(image.width())),
+        y1.min(i64::from(image.height())),
+    );
+    for y in y0..y1 {
+        for x in x0..x1 {
+            image.blend(x, y, pixel);
+        }
+    }
+}
+
+/**
+    Draws the 1 pixel wide outline of a circle, using the midpoint circle algorithm.
+*/
+pub fn circle(image: &mut Image, (cx, cy): (i64, i64), radius: i64, pixel: Rgba) {
+    if radius < 0 {
+        return;
+    }
+    let (mut x, mut y, mut err) = (radius, 0, 1 - radius);
+    // NOTE: Octants overlap on the diagonals and axes, so we collect
+    // points first - blending the same pixel twice would darken it
+    let mut points = Vec::new();
+    while x >= y {
+        for (px, py) in [
+            (x, y),
+            (y, x),
+            (-y, x),
+            (-x, y),
+            (-x, -y),
+            (-y, -x),
+            (y, -x),
+            (x, -y),
+        ] {
+            points.push((cx + px, cy + py));
+        }
+        y += 1;
+        if err < 0 {
+            err += 2 * y + 1;
+        } else {
+            x -= 1;
+            err += 2 * (y - x) + 1;
+        }
+    }
+    points.sort_unstable();
+    points.dedup();
+    for (px, py) in points {
+        image.blend(px, py, pixel);
+    }
+}
+
+/**
+    Fills the area covered by a circle.
+*/
+pub fn fill_circle(image: &mut Image, (cx, cy): (i64, i64), radius: i64, pixel: Rgba) {
+    if radius < 0 {
+        return;
+    }
+    let r2 = radius * radius + radius;
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if dx * dx + dy * dy <= r2 {
+                image.blend(cx + dx, cy + dy, pixel);
+            }
+        }
+    }
+}
+
+/**
+    Returns the size in pixels of the given text when drawn with `text`.
+*/
+#[must_use]
+pub fn measure_text(text: &str, scale: u32) -> (u32, u32) {
+    let lines = text.split('\n');
+    let (mut width, mut count) = (0, 0);
+    for line in lines {
+        width = width.max(line.chars().count() as u32);
+        count += 1;
+    }
+    (width * GLYPH_SIZE * scale, count * GLYPH_SIZE * scale)
+}
+
+/**
+    Draws text using the bundled 8x8 bitmap font, with the top-left corner at the given point.
+
+    Characters missing from the font are drawn as blank space.
+*/
+pub fn text(image: &mut Image, text: &str, (x, y): (i64, i64), scale: u32, pixel: Rgba) {
+    let step = i64::from(GLYPH_SIZE * scale);
+    let scale = i64::from(scale);
+    for (row, line) in text.split('\n').enumerate() {
+        let top = y + row as i64 * step;
+        for (col, ch) in line.chars().enumerate() {
+            let Some(glyph) = BASIC_FONTS.get(ch) else {
+                continue;
+            };
+            let left = x + col as i64 * step;
+            for (gy, bits) in glyph.iter().enumerate() {
+                for gx in 0..GLYPH_SIZE {
+                    // NOTE: The least significant bit is the leftmost pixel
+                    if bits & (1 << gx) == 0 {
+                        continue;
+                    }
+                    let (px, py) = (left + i64::from(gx) * scale, top + gy as i64 * scale);
+                    for sy in 0..scale {
+                        for sx in 0..scale {
+                            image.blend(px + sx, py + sy, pixel);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/**
+    Blends `source` over `image`, stretched to cover `rect` using nearest-neighbor sampling.
+
+    The alpha of every source pixel is multiplied by `alpha`.
+*/
+pub fn paste(image: &mut Image, source: &Image, rect: &Rect, alpha: f64) {
+    let (x0, y0, x1, y1) = pixel_bounds(rect);
+    let (w, h) = (x1 - x0, y1 - y0);
+    if w <= 0 || h <= 0 {
+        return;
+    }
+    let alpha = alpha.clamp(0.0, 1.0);
+    let (sw, sh) = (i64::from(source.width()), i64::from(source.height()));
+    for y in y0.max(0)..y1.min(i64::from(image.height())) {
+        let sy = (y - y0) * sh / h;
+        for x in x0.max(0)..x1.min(i64::from(image.width())) {
+            let sx = (x - x0) * sw / w;
+            if let Some(mut p) = source.get(sx, sy) {
+                p.a = (f64::from(p.a) * alpha).round() as u8;
+                image.blend(x, y, p);
+            }
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use std::path::Path;
+
+use mlua::prelude::*;
+
+use lux_color::Color3;
+use lux_udim::Rect;
+use lux_utils::process::ProcessPermissions;
+
+use super::codec::{self, ResizeFilter};
+use super::draw;
+
+/**
+    A single RGBA pixel, with 8 bits per channel.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const TRANSPARENT: Self = Self::new(0, 0, 0, 0);
+
+    #[inline]
+    #[must_use]
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /**
+        Creates a pixel from a `Color3` and an alpha value in the range `0..=1`.
+    */
+    #[inline]
+    #[must_use]
+    pub fn from_color3(color: &Color3, alpha: f64) -> Self {
+        Self::new(
+            unit_to_u8(color.r),
+            unit_to_u8(color.g),
+            unit_to_u8(color.b),
+            unit_to_u8(alpha),
+        )
+    }
+
+    /**
+        Converts the pixel back into a `Color3` and an alpha value in the range `0..=1`.
+    */
+    #[inline]
+    #[must_use]
+    pub fn to_color3(self) -> (Color3, f64) {
+        (
+            Color3::from_rgb(self.r, self.g, self.b),
+            f64::from(self.a) / 255.0,
+        )
+    }
+
+    /**
+        Composites `self` over `dst` using standard "source over" alpha blending.
+    */
+    #[must_use]
+    pub fn over(self, dst: Self) -> Self {
+        if self.a == u8::MAX || dst.a == 0 {
+            return self;
+        }
+        if self.a == 0 {
+            return dst;
+        }
+        let sa = f32::from(self.a) / 255.0;
+        let da = f32::from(dst.a) / 255.0 * (1.0 - sa);
+        let out_a = sa + da;
+        let channel = |s: u8, d: u8| {
+            let c = (f32::from(s) * sa + f32::from(d) * da) / out_a;
+            c.round().clamp(0.0, 255.0) as u8
+        };
+        Self::new(
+            channel(self.r, dst.r),
+            channel(self.g, dst.g),
+            channel(self.b, dst.b),
+            (out_a * 255.0).round().clamp(0.0, 255.0) as u8,
+        )
+    }
+}
+
+#[inline]
+fn unit_to_u8(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/**
+    An RGBA image, stored row by row with the top-left pixel first.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Rgba>,
+}
+
+impl Image {
+    /**
+        Creates a new image filled with the given pixel.
+
+        # Errors
+
+        Errors if either dimension is zero.
+    */
+    pub fn new(width: u32, height: u32, fill: Rgba) -> LuaResult<Self> {
+        if width == 0 || height == 0 {
+            return Err(LuaError::runtime(format!(
+                "Image dimensions must be non-zero, got {width}x{height}"
+            )));
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels: vec![fill; width as usize * height as usize],
+        })
+    }
+
+    /**
+        Creates a new image from raw RGBA bytes.
+
+        # Errors
+
+        Errors if either dimension is zero, or if the
+        amount of bytes does not match the dimensions.
+    */
+    pub fn from_rgba(width: u32, height: u32, bytes: Vec<u8>) -> LuaResult<Self> {
+        let mut image = Self::new(width, height, Rgba::TRANSPARENT)?;
+        if bytes.len() != image.pixels.len() * 4 {
+            return Err(LuaError::runtime(format!(
+                "Expected {} bytes of RGBA data for a {width}x{height} image, got {}",
+                image.pixels.len() * 4,
+                bytes.len()
+            )));
+        }
+        for (pixel, chunk) in image.pixels.iter_mut().zip(bytes.chunks_exact(4)) {
+            *pixel = Rgba::new(chunk[0], chunk[1], chunk[2], chunk[3]);
+        }
+        Ok(image)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /**
+        Returns the raw RGBA bytes of the image.
+    */
+    #[must_use]
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|p| [p.r, p.g, p.b, p.a])
+            .collect()
+    }
+
+    #[inline]
+    fn index(&self, x: i64, y: i64) -> Option<usize> {
+        if x < 0 || y < 0 || x >= i64::from(self.width) || y >= i64::from(self.height) {
+            return None;
+        }
+        Some(y as usize * self.width as usize + x as usize)
+    }
+
+    /**
+        Returns the pixel at the given coordinates, or `None` if out of bounds.
+    */
+    #[inline]
+    #[must_use]
+    pub fn get(&self, x: i64, y: i64) -> Option<Rgba> {
+        self.index(x, y).map(|i| self.pixels[i])
+    }
+
+    /**
+        Replaces the pixel at the given coordinates, ignoring out of bounds coordinates.
+    */
+    #[inline]
+    pub fn set(&mut self, x: i64, y: i64, pixel: Rgba) {
+        if let Some(i) = self.index(x, y) {
+            self.pixels[i] = pixel;
+        }
+    }
+
+    /**
+        Blends a pixel over the one at the given coordinates, ignoring out of bounds coordinates.
+    */
+    #[inline]
+    pub fn blend(&mut self, x: i64, y: i64, pixel: Rgba) {
+        if let Some(i) = self.index(x, y) {
+            self.pixels[i] = pixel.over(self.pixels[i]);
+        }
+    }
+
+    /**
+        Replaces every pixel in the image.
+    */
+    pub fn fill(&mut self, pixel: Rgba) {
+        self.pixels.fill(pixel);
+    }
+
+    /**
+        Returns a copy of the area covered by `rect`, clipped to the image.
+
+        # Errors
+
+        Errors if the clipped area is empty.
+    */
+    pub fn crop(&self, rect: &Rect) -> LuaResult<Self> {
+        let (x0, y0, x1, y1) = draw::pixel_bounds(rect);
+        let (x0, y0) = (x0.max(0), y0.max(0));
+        let (x1, y1) = (
+            x1.min(i64::from(self.width)),
+            y1.min(i64::from(self.height)),
+        );
+        if x1 <= x0 || y1 <= y0 {
+            return Err(LuaError::runtime(format!(
+                "Crop area does not overlap the {}x{} image",
+                self.width, self.height
+            )));
+        }
+        let width = (x1 - x0) as usize;
+        let pixels = (y0..y1)
+            .flat_map(|y| {
+                let start = self.index(x0, y).expect("row start is within bounds");
+                self.pixels[start..start + width].iter().copied()
+            })
+            .collect();
+        Ok(Self {
+            width: width as u32,
+            height: (y1 - y0) as u32,
+            pixels,
+        })
+    }
+
+    /**
+        Returns a copy of the image mirrored left to right.
+    */
+    #[must_use]
+    pub fn flip_horizontal(&self) -> Self {
+        let pixels = self
+            .pixels
+            .chunks_exact(self.width as usize)
+            .flat_map(|row| row.iter().rev().copied())
+            .collect();
+        Self { pixels, ..*self }
+    }
+
+    /**
+        Returns a copy of the image mirrored top to bottom.
+    */
+    #[must_use]
+    pub fn flip_vertical(&self) -> Self {
+        let pixels = self
+            .pixels
+            .chunks_exact(self.width as usize)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        Self { pixels, ..*self }
+    }
+}
+
+type ColorArgs = (LuaUserDataRef<Color3>, Option<f64>);
+type PixelArgs = (i64, i64, LuaUserDataRef<Color3>, Option<f64>);
+type LineArgs = (i64, i64, i64, i64, LuaUserDataRef<Color3>, Option<f64>);
+type RectArgs = (LuaUserDataRef<Rect>, LuaUserDataRef<Color3>, Option<f64>);
+type CircleArgs = (i64, i64, i64, LuaUserDataRef<Color3>, Option<f64>);
+type TextArgs = (
+    String,
+    i64,
+    i64,
+    LuaUserDataRef<Color3>,
+    Option<u32>,
+    Option<f64>,
+);
+type PasteArgs = (
+    LuaAnyUserData,
+    LuaAnyUserData,
+    Option<LuaUserDataRef<Rect>>,
+    Option<f64>,
+);
+
+fn pixel((color, alpha): ColorArgs) -> Rgba {
+    Rgba::from_color3(&color, alpha.unwrap_or(1.0))
+}
+
+impl LuaUserData for Image {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Width", |_, this| Ok(this.width));
+        fields.add_field_method_get("Height", |_, this| Ok(this.height));
+
+        fields.add_meta_field(LuaMetaMethod::Type, "Image");
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("GetPixel", |lua, this, (x, y): (i64, i64)| {
+            let Some(p) = this.get(x, y) else {
+                return Err(LuaError::runtime(format!(
+                    "Pixel ({x}, {y}) is out of bounds for a {}x{} image",
+                    this.width, this.height
+                )));
+            };
+            let (color, alpha) = p.to_color3();
+            Ok((lua.create_userdata(color)?, alpha))
+        });
+        methods.add_method_mut("SetPixel", |_, this, (x, y, color, alpha): PixelArgs| {
+            this.set(x, y, pixel((color, alpha)));
+            Ok(())
+        });
+        methods.add_method_mut("Fill", |_, this, args: ColorArgs| {
+            this.fill(pixel(args));
+            Ok(())
+        });
+
+        methods.add_method_mut(
+            "DrawLine",
+            |_, this, (x0, y0, x1, y1, color, alpha): LineArgs| {
+                draw::line(this, (x0, y0), (x1, y1), pixel((color, alpha)));
+                Ok(())
+            },
+        );
+        methods.add_method_mut("DrawRect", |_, this, (rect, color, alpha): RectArgs| {
+            draw::rect(this, &rect, pixel((color, alpha)));
+            Ok(())
+        });
+        methods.add_method_mut("FillRect", |_, this, (rect, color, alpha): RectArgs| {
+            draw::fill_rect(this, &rect, pixel((color, alpha)));
+            Ok(())
+        });
+        methods.add_method_mut(
+            "DrawCircle",
+            |_, this, (cx, cy, radius, color, alpha): CircleArgs| {
+                draw::circle(this, (cx, cy), radius, pixel((color, alpha)));
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "FillCircle",
+            |_, this, (cx, cy, radius, color, alpha): CircleArgs| {
+                draw::fill_circle(this, (cx, cy), radius, pixel((color, alpha)));
+                Ok(())
+            },
+        );
+        methods.add_method_mut(
+            "DrawText",
+            |_, this, (text, x, y, color, scale, alpha): TextArgs| {
+                let scale = scale.unwrap_or(1).max(1);
+                draw::text(this, &text, (x, y), scale, pixel((color, alpha)));
+                Ok(draw::measure_text(&text, scale))
+            },
+        );
+        // NOTE: Paste takes both images as plain userdata, and copies the source before
+        // mutably borrowing the target, so that pasting an image into itself works
+        methods.add_function("Paste", |_, (this, source, rect, alpha): PasteArgs| {
+            let source = source.borrow::<Self>()?.clone();
+            let rect = rect.map_or_else(
+                || Rect::new(0.0, 0.0, f64::from(source.width), f64::from(source.height)),
+                |r| *r,
+            );
+            let mut target = this.borrow_mut::<Self>()?;
+            draw::paste(&mut target, &source, &rect, alpha.unwrap_or(1.0));
+            Ok(())
+        });
+
+        methods.add_method(
+            "Resize",
+            |_, this, (width, height, filter): (u32, u32, ResizeFilter)| {
+                codec::resize(this, width, height, filter)
+            },
+        );
+        methods.add_method("Crop", |_, this, rect: LuaUserDataRef<Rect>| {
+            this.crop(&rect)
+        });
+        methods.add_method("FlipHorizontal", |_, this, ()| Ok(this.flip_horizontal()));
+        methods.add_method("FlipVertical", |_, this, ()| Ok(this.flip_vertical()));
+
+        methods.add_method("Clone", |_, this, ()| Ok(this.clone()));
+        methods.add_method("ToBuffer", |lua, this, ()| {
+            lua.create_buffer(this.to_rgba())
+        });
+        methods.add_method("ToPng", |lua, this, ()| {
+            lua.create_buffer(codec::encode_png(this)?)
+        });
+        methods.add_method("WritePng", |lua, this, path: String| {
+            ProcessPermissions::check_path(lua, Path::new(&path), "Image:WritePng")?;
+            let bytes = codec::encode_png(this)?;
+            std::fs::write(&path, bytes)
+                .map_err(|e| LuaError::runtime(format!("Failed to write '{path}': {e}")))
+        });
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(format!("Image({}x{})", this.width, this.height))
+        });
+    }
+}
@@ -0,0 +1,109 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_color::Color3;
+use lux_udim::Rect;
+use lux_utils::TableBuilder;
+
+mod codec;
+mod draw;
+mod image;
+mod template;
+
+pub use self::codec::{ResizeFilter, decode, encode_png};
+pub use self::image::{Image, Rgba};
+pub use self::template::{TemplateMatch, find_template};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `image` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `image` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("new", image_new)?
+        .with_function("fromBuffer", image_from_buffer)?
+        .with_function("decode", image_decode)?
+        .with_function("readFile", image_read_file)?
+        .with_function("measureText", image_measure_text)?
+        .with_function("findTemplate", image_find_template)?
+        .build_readonly()
+}
+
+fn image_new(
+    _: &Lua,
+    (width, height, color, alpha): (u32, u32, Option<LuaUserDataRef<Color3>>, Option<f64>),
+) -> LuaResult<Image> {
+    let fill = match color {
+        Some(c) => Rgba::from_color3(&c, alpha.unwrap_or(1.0)),
+        None => Rgba::TRANSPARENT,
+    };
+    Image::new(width, height, fill)
+}
+
+fn image_from_buffer(_: &Lua, (width, height, buf): (u32, u32, mlua::Buffer)) -> LuaResult<Image> {
+    Image::from_rgba(width, height, buf.to_vec())
+}
+
+fn image_decode(_: &Lua, data: LuaValue) -> LuaResult<Image> {
+    match data {
+        LuaValue::Buffer(buf) => codec::decode(&buf.to_vec()),
+        LuaValue::String(s) => codec::decode(&s.as_bytes()),
+        other => Err(LuaError::runtime(format!(
+            "Expected image data to be a buffer or string, got {}",
+            other.type_name()
+        ))),
+    }
+}
+
+fn image_read_file(_: &Lua, path: String) -> LuaResult<Image> {
+    let bytes = std::fs::read(&path)
+        .map_err(|e| LuaError::runtime(format!("Failed to read '{path}': {e}")))?;
+    codec::decode(&bytes)
+}
+
+fn image_measure_text(_: &Lua, (text, scale): (String, Option<u32>)) -> LuaResult<(u32, u32)> {
+    Ok(draw::measure_text(&text, scale.unwrap_or(1).max(1)))
+}
+
+fn image_find_template(
+    lua: &Lua,
+    (haystack, needle, options): (
+        LuaUserDataRef<Image>,
+        LuaUserDataRef<Image>,
+        Option<LuaTable>,
+    ),
+) -> LuaResult<LuaTable> {
+    let (threshold, max_results) = match options {
+        Some(t) => (
+            t.get::<Option<f64>>("threshold")?.unwrap_or(0.9),
+            t.get::<Option<usize>>("maxResults")?.unwrap_or(usize::MAX),
+        ),
+        None => (0.9, usize::MAX),
+    };
+
+    let matches = find_template(&haystack, &needle, threshold, max_results);
+    let results = lua.create_table_with_capacity(matches.len(), 0)?;
+    for m in matches {
+        let t = lua.create_table_with_capacity(0, 4)?;
+        t.set("X", m.x)?;
+        t.set("Y", m.y)?;
+        t.set("Score", m.score)?;
+        t.set("Rect", lua.create_userdata::<Rect>(m.rect())?)?;
+        results.push(t)?;
+    }
+    Ok(results)
+}
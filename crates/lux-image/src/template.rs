@@ -0,0 +1,152 @@
+use lux_udim::Rect;
+
+use super::image::Image;
+
+/**
+    A location where a template image was found within another image.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemplateMatch {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Normalized cross-correlation, from `-1` to `1`
+    pub score: f64,
+}
+
+impl TemplateMatch {
+    #[must_use]
+    pub fn rect(&self) -> Rect {
+        Rect::from_position_size(
+            f64::from(self.x),
+            f64::from(self.y),
+            f64::from(self.width),
+            f64::from(self.height),
+        )
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+fn luminance(image: &Image) -> Vec<f64> {
+    let (w, h) = (i64::from(image.width()), i64::from(image.height()));
+    let mut out = Vec::with_capacity((w * h) as usize);
+    for y in 0..h {
+        for x in 0..w {
+            let p = image.get(x, y).unwrap_or_default();
+            out.push(0.299 * f64::from(p.r) + 0.587 * f64::from(p.g) + 0.114 * f64::from(p.b));
+        }
+    }
+    out
+}
+
+/// Summed-area table with an extra leading row and column of zeros
+fn integral(values: &[f64], width: usize, height: usize, square: bool) -> Vec<f64> {
+    let stride = width + 1;
+    let mut table = vec![0.0; stride * (height + 1)];
+    for y in 0..height {
+        let mut row = 0.0;
+        for x in 0..width {
+            let v = values[y * width + x];
+            row += if square { v * v } else { v };
+            table[(y + 1) * stride + x + 1] = table[y * stride + x + 1] + row;
+        }
+    }
+    table
+}
+
+fn window_sum(table: &[f64], stride: usize, x: usize, y: usize, w: usize, h: usize) -> f64 {
+    table[(y + h) * stride + x + w] - table[y * stride + x + w] - table[(y + h) * stride + x]
+        + table[y * stride + x]
+}
+
+/**
+    Finds all locations where `needle` appears within `haystack`, using normalized
+    cross-correlation over the luminance of both images.
+
+    Matches scoring at least `threshold` are returned best first. Overlapping matches
+    are suppressed in favor of the best scoring one, and at most `max_results` are returned.
+*/
+#[must_use]
+pub fn find_template(
+    haystack: &Image,
+    needle: &Image,
+    threshold: f64,
+    max_results: usize,
+) -> Vec<TemplateMatch> {
+    let (hw, hh) = (haystack.width() as usize, haystack.height() as usize);
+    let (nw, nh) = (needle.width() as usize, needle.height() as usize);
+    if nw > hw || nh > hh || max_results == 0 {
+        return Vec::new();
+    }
+
+    let hay = luminance(haystack);
+    let sums = integral(&hay, hw, hh, false);
+    let sq_sums = integral(&hay, hw, hh, true);
+
+    // NOTE: With a zero-mean template, the correlation numerator
+    // simplifies to the sum of window pixels times template pixels
+    let mut tmpl = luminance(needle);
+    let n = (nw * nh) as f64;
+    let t_mean = tmpl.iter().sum::<f64>() / n;
+    for v in &mut tmpl {
+        *v -= t_mean;
+    }
+    let t_var: f64 = tmpl.iter().map(|v| v * v).sum();
+
+    let mut candidates = Vec::new();
+    for y in 0..=(hh - nh) {
+        for x in 0..=(hw - nw) {
+            let sum = window_sum(&sums, hw + 1, x, y, nw, nh);
+            let w_var = (window_sum(&sq_sums, hw + 1, x, y, nw, nh) - sum * sum / n).max(0.0);
+
+            let score = if t_var < f64::EPSILON || w_var < f64::EPSILON {
+                // Flat regions have no pattern to correlate,
+                // so only an equally flat match counts
+                if t_var < f64::EPSILON && w_var < f64::EPSILON {
+                    1.0 - ((sum / n - t_mean).abs() / 255.0)
+                } else {
+                    0.0
+                }
+            } else {
+                let mut num = 0.0;
+                for ty in 0..nh {
+                    let row = (y + ty) * hw + x;
+                    let trow = ty * nw;
+                    for tx in 0..nw {
+                        num += hay[row + tx] * tmpl[trow + tx];
+                    }
+                }
+                num / (t_var * w_var).sqrt()
+            };
+
+            if score >= threshold {
+                candidates.push(TemplateMatch {
+                    x: x as u32,
+                    y: y as u32,
+                    width: nw as u32,
+                    height: nh as u32,
+                    score,
+                });
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut matches: Vec<TemplateMatch> = Vec::new();
+    for candidate in candidates {
+        if matches.iter().all(|m| !m.overlaps(&candidate)) {
+            matches.push(candidate);
+            if matches.len() >= max_results {
+                break;
+            }
+        }
+    }
+    matches
+}
@@ -0,0 +1,50 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::{
+    TableBuilder,
+    fmt::{ValueFormatConfig, pretty_format_value},
+};
+
+mod options;
+
+pub use self::options::InspectOptions;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `inspect` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `inspect` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("format", inspect_format)?
+        .build_readonly()
+}
+
+/**
+    Formats a value into a readable string, the same way as the REPL does.
+
+    Unlike `print`, strings are always quoted so that they can be told apart from
+    other values, and colors are only used when explicitly enabled in the options.
+*/
+#[must_use]
+pub fn inspect(value: &LuaValue, options: InspectOptions) -> String {
+    pretty_format_value(value, &ValueFormatConfig::from(options))
+}
+
+fn inspect_format(_: &Lua, (value, options): (LuaValue, InspectOptions)) -> LuaResult<String> {
+    Ok(inspect(&value, options))
+}
@@ -0,0 +1,65 @@
+use mlua::prelude::*;
+
+use lux_utils::fmt::ValueFormatConfig;
+
+/**
+    Options for inspecting a value.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InspectOptions {
+    /// How many levels of nested tables to format, before showing `{ ... }`
+    pub depth: usize,
+    /// The number of spaces to indent each level of nested tables with
+    pub indent: usize,
+    /// Whether the output should contain terminal colors
+    pub colors: bool,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            depth: 4,
+            indent: 4,
+            colors: false,
+        }
+    }
+}
+
+impl From<InspectOptions> for ValueFormatConfig {
+    fn from(options: InspectOptions) -> Self {
+        ValueFormatConfig::new()
+            .with_max_depth(options.depth)
+            .with_indent_width(options.indent)
+            .with_colors_enabled(options.colors)
+            .with_quoted_strings(true)
+    }
+}
+
+impl FromLua for InspectOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let defaults = Self::default();
+        let options = match value {
+            LuaValue::Nil => return Ok(defaults),
+            LuaValue::Table(t) => t,
+            _ => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: value.type_name(),
+                    to: String::from("InspectOptions"),
+                    message: Some(String::from("expected a table of options")),
+                });
+            }
+        };
+
+        Ok(Self {
+            depth: options
+                .get::<Option<usize>>("depth")?
+                .unwrap_or(defaults.depth),
+            indent: options
+                .get::<Option<usize>>("indent")?
+                .unwrap_or(defaults.indent),
+            colors: options
+                .get::<Option<bool>>("colors")?
+                .unwrap_or(defaults.colors),
+        })
+    }
+}
@@ -0,0 +1,220 @@
+use lux_udim::{FillDirection, Rect, UDim, UDim2};
+use lux_vector::Vector2;
+
+/// How children are distributed along the main axis when there is space left over
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Justify {
+    #[default]
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+}
+
+impl Justify {
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "start" => Self::Start,
+            "center" => Self::Center,
+            "end" => Self::End,
+            "spaceBetween" => Self::SpaceBetween,
+            "spaceAround" => Self::SpaceAround,
+            _ => return None,
+        })
+    }
+}
+
+/// How children are placed along the cross axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Start,
+    Center,
+    End,
+    Stretch,
+}
+
+impl Align {
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "start" => Self::Start,
+            "center" => Self::Center,
+            "end" => Self::End,
+            "stretch" => Self::Stretch,
+            _ => return None,
+        })
+    }
+}
+
+/// Space around the edges of a rectangle, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Edges {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl Edges {
+    #[must_use]
+    pub const fn uniform(value: f64) -> Self {
+        Self {
+            left: value,
+            top: value,
+            right: value,
+            bottom: value,
+        }
+    }
+
+    fn inset(&self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.min_x + self.left,
+            rect.min_y + self.top,
+            (rect.max_x - self.right).max(rect.min_x + self.left),
+            (rect.max_y - self.bottom).max(rect.min_y + self.top),
+        )
+    }
+
+    /// Returns the edges before and after a child along the given axis
+    fn along(&self, direction: FillDirection) -> (f64, f64) {
+        match direction {
+            FillDirection::Horizontal => (self.left, self.right),
+            FillDirection::Vertical => (self.top, self.bottom),
+        }
+    }
+}
+
+/// The container that children are placed within by [`solve`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlexContainer {
+    pub rect: Rect,
+    pub direction: FillDirection,
+    /// Gap between children, resolved against the main axis length
+    pub gap: UDim,
+    /// Space between the edges of the container and its children
+    pub padding: Edges,
+    pub justify: Justify,
+    pub align: Align,
+}
+
+/// A child placed by [`solve`]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FlexItem {
+    /// Base size, resolved against the container after removing its padding
+    pub size: UDim2,
+    pub margin: Edges,
+    /// Share of the leftover main axis space this child grows into
+    pub grow: f64,
+}
+
+/**
+    Places children one after another along the main axis of a container,
+    like a small subset of CSS flexbox.
+
+    Space left over after placing every child at its base size is given to
+    children with a `grow` factor in proportion to it, or distributed according
+    to `justify` if no child grows. Children that do not fit overflow the end
+    of the container. Rects are returned in input order and exclude margins.
+*/
+#[must_use]
+pub fn solve(container: &FlexContainer, items: &[FlexItem]) -> Vec<Rect> {
+    let content = container.padding.inset(container.rect);
+    let direction = container.direction;
+    let (main_len, cross_len) = match direction {
+        FillDirection::Horizontal => (content.width(), content.height()),
+        FillDirection::Vertical => (content.height(), content.width()),
+    };
+    let cross_direction = match direction {
+        FillDirection::Horizontal => FillDirection::Vertical,
+        FillDirection::Vertical => FillDirection::Horizontal,
+    };
+    let gap = container.gap.resolve(main_len);
+
+    let (mut mains, crosses): (Vec<f64>, Vec<f64>) = items
+        .iter()
+        .map(|item| {
+            let width = item.size.x.resolve(content.width()).max(0.0);
+            let height = item.size.y.resolve(content.height()).max(0.0);
+            match direction {
+                FillDirection::Horizontal => (width, height),
+                FillDirection::Vertical => (height, width),
+            }
+        })
+        .unzip();
+
+    let margins: f64 = items
+        .iter()
+        .map(|item| {
+            let (before, after) = item.margin.along(direction);
+            before + after
+        })
+        .sum();
+    let gaps = gap * items.len().saturating_sub(1) as f64;
+    let mut free = main_len - mains.iter().sum::<f64>() - margins - gaps;
+
+    let total_grow: f64 = items.iter().map(|item| item.grow.max(0.0)).sum();
+    if free > 0.0 && total_grow > 0.0 {
+        for (main, item) in mains.iter_mut().zip(items) {
+            *main += free * item.grow.max(0.0) / total_grow;
+        }
+        free = 0.0;
+    }
+
+    let free = free.max(0.0);
+    let count = items.len() as f64;
+    let (mut cursor, spacing) = match container.justify {
+        Justify::Start => (0.0, gap),
+        Justify::Center => (free / 2.0, gap),
+        Justify::End => (free, gap),
+        Justify::SpaceBetween if items.len() > 1 => (0.0, gap + free / (count - 1.0)),
+        Justify::SpaceBetween => (0.0, gap),
+        Justify::SpaceAround => (free / count / 2.0, gap + free / count),
+    };
+
+    let (main_start, cross_start) = match direction {
+        FillDirection::Horizontal => (content.min_x, content.min_y),
+        FillDirection::Vertical => (content.min_y, content.min_x),
+    };
+
+    let mut rects = Vec::with_capacity(items.len());
+    for ((item, main), cross) in items.iter().zip(mains).zip(crosses) {
+        let (before, after) = item.margin.along(direction);
+        let (cross_before, cross_after) = item.margin.along(cross_direction);
+        let cross_space = cross_len - cross_before - cross_after;
+
+        let (cross, cross_offset) = match container.align {
+            Align::Start => (cross, 0.0),
+            Align::Center => (cross, (cross_space - cross) / 2.0),
+            Align::End => (cross, cross_space - cross),
+            Align::Stretch => (cross_space.max(0.0), 0.0),
+        };
+
+        let main_pos = main_start + cursor + before;
+        let cross_pos = cross_start + cross_before + cross_offset;
+        rects.push(match direction {
+            FillDirection::Horizontal => Rect::from_position_size(main_pos, cross_pos, main, cross),
+            FillDirection::Vertical => Rect::from_position_size(cross_pos, main_pos, cross, main),
+        });
+        cursor += before + main + after + spacing;
+    }
+    rects
+}
+
+/**
+    Positions a rectangle within a parent the same way Roblox positions GUI objects.
+
+    `position` and `size` are resolved against the parent, and `anchor_point` selects
+    which point of the rectangle is placed at `position` - `(0, 0)` is its top-left
+    corner, `(0.5, 0.5)` its center and `(1, 1)` its bottom-right corner.
+*/
+#[must_use]
+pub fn place(parent: Rect, position: UDim2, size: UDim2, anchor_point: Vector2) -> Rect {
+    let (pw, ph) = (parent.width(), parent.height());
+    let (width, height) = (size.x.resolve(pw), size.y.resolve(ph));
+    let x = parent.min_x + position.x.resolve(pw) - anchor_point.x * width;
+    let y = parent.min_y + position.y.resolve(ph) - anchor_point.y * height;
+    Rect::from_position_size(x, y, width, height)
+}
@@ -0,0 +1,156 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_udim::{FillDirection, Rect, UDim, UDim2};
+use lux_utils::TableBuilder;
+use lux_vector::Vector2;
+
+mod flex;
+
+pub use self::flex::{Align, Edges, FlexContainer, FlexItem, Justify, place, solve};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `layout` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `layout` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("solve", layout_solve)?
+        .with_function("place", layout_place)?
+        .build_readonly()
+}
+
+fn layout_solve(lua: &Lua, spec: LuaTable) -> LuaResult<LuaTable> {
+    let container = FlexContainer {
+        rect: *spec
+            .get::<Option<LuaUserDataRef<Rect>>>("container")?
+            .ok_or_else(|| LuaError::external("expected container to be a Rect"))?,
+        // NOTE: Rows are the default, like in CSS, unlike Rect.listLayout
+        direction: match spec.get::<Option<i64>>("direction")? {
+            Some(value) => FillDirection::from_enum_value(value)
+                .ok_or_else(|| LuaError::external(format!("invalid direction value '{value}'")))?,
+            None => FillDirection::Horizontal,
+        },
+        gap: match spec.get::<LuaValue>("gap")? {
+            LuaValue::UserData(ud) => *ud.borrow::<UDim>()?,
+            LuaValue::Nil => UDim::default(),
+            other => UDim::new(0.0, number_option(&other, "gap")?),
+        },
+        padding: edges_option(&spec.get("padding")?, "padding")?,
+        justify: name_option(&spec, "justify", Justify::from_name)?.unwrap_or_default(),
+        align: name_option(&spec, "align", Align::from_name)?.unwrap_or_default(),
+    };
+
+    let children = spec
+        .get::<Option<LuaTable>>("children")?
+        .ok_or_else(|| LuaError::external("expected children to be a table"))?;
+    let items = children
+        .sequence_values::<LuaValue>()
+        .map(|child| flex_item(&child?))
+        .collect::<LuaResult<Vec<_>>>()?;
+
+    let rects = solve(&container, &items);
+    let t = lua.create_table_with_capacity(rects.len(), 0)?;
+    for rect in rects {
+        t.push(lua.create_userdata(rect)?)?;
+    }
+    Ok(t)
+}
+
+fn layout_place(
+    lua: &Lua,
+    (parent, position, size, anchor_point): (
+        LuaUserDataRef<Rect>,
+        LuaUserDataRef<UDim2>,
+        LuaUserDataRef<UDim2>,
+        Option<LuaUserDataRef<Vector2>>,
+    ),
+) -> LuaResult<LuaAnyUserData> {
+    let anchor_point = anchor_point.map_or(Vector2::ZERO, |a| *a);
+    lua.create_userdata(place(*parent, *position, *size, anchor_point))
+}
+
+fn flex_item(value: &LuaValue) -> LuaResult<FlexItem> {
+    match value {
+        LuaValue::UserData(ud) => Ok(FlexItem {
+            size: *ud.borrow::<UDim2>()?,
+            ..FlexItem::default()
+        }),
+        LuaValue::Table(t) => Ok(FlexItem {
+            size: t
+                .get::<Option<LuaUserDataRef<UDim2>>>("size")?
+                .map(|s| *s)
+                .unwrap_or_default(),
+            margin: edges_option(&t.get("margin")?, "margin")?,
+            grow: match t.get::<LuaValue>("grow")? {
+                LuaValue::Nil => 0.0,
+                other => number_option(&other, "grow")?,
+            },
+        }),
+        other => Err(LuaError::external(format!(
+            "expected child to be a UDim2 or a table with size, margin and grow, got '{}'",
+            other.type_name()
+        ))),
+    }
+}
+
+fn number_option(value: &LuaValue, key: &str) -> LuaResult<f64> {
+    match value {
+        LuaValue::Integer(n) => Ok(*n as f64),
+        LuaValue::Number(n) => Ok(*n),
+        other => Err(LuaError::external(format!(
+            "expected {key} to be a number, got '{}'",
+            other.type_name()
+        ))),
+    }
+}
+
+fn edges_option(value: &LuaValue, key: &str) -> LuaResult<Edges> {
+    match value {
+        LuaValue::Nil => Ok(Edges::default()),
+        LuaValue::Table(t) => {
+            let side = |side: &str| -> LuaResult<f64> {
+                Ok(t.get::<Option<f64>>(side)?.unwrap_or_default())
+            };
+            Ok(Edges {
+                left: side("left")?,
+                top: side("top")?,
+                right: side("right")?,
+                bottom: side("bottom")?,
+            })
+        }
+        other => number_option(other, key).map(Edges::uniform).map_err(|_| {
+            LuaError::external(format!(
+                "expected {key} to be a number or a table with left, top, right and bottom, got '{}'",
+                other.type_name()
+            ))
+        }),
+    }
+}
+
+fn name_option<T>(
+    spec: &LuaTable,
+    key: &str,
+    from_name: fn(&str) -> Option<T>,
+) -> LuaResult<Option<T>> {
+    let Some(name) = spec.get::<Option<String>>(key)? else {
+        return Ok(None);
+    };
+    from_name(&name)
+        .map(Some)
+        .ok_or_else(|| LuaError::external(format!("invalid {key} value '{name}'")))
+}
@@ -0,0 +1,146 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Mat4 and Quaternion types for Lux
+//! Optimized for FFI compatibility with #[repr(C)], for graphics and physics work
+
+use lux_utils::TableBuilder;
+use lux_utils::packed::read_f64s;
+use lux_vector::Vector3;
+use mlua::prelude::*;
+
+mod mat4;
+mod quaternion;
+
+pub use self::mat4::Mat4;
+pub use self::quaternion::Quaternion;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates a new buffer containing the given values as little-endian floats,
+    either `"f32"` - the default, as used by most graphics APIs - or `"f64"`.
+*/
+pub(crate) fn buffer_from_f64s(
+    lua: &Lua,
+    values: &[f64],
+    precision: Option<&str>,
+) -> LuaResult<mlua::Buffer> {
+    let bytes = match precision.unwrap_or("f32") {
+        "f32" => values
+            .iter()
+            .flat_map(|value| (*value as f32).to_le_bytes())
+            .collect::<Vec<_>>(),
+        "f64" => values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect::<Vec<_>>(),
+        other => {
+            return Err(LuaError::runtime(format!(
+                "Invalid precision '{other}', expected 'f32' or 'f64'"
+            )));
+        }
+    };
+    lua.create_buffer(bytes)
+}
+
+// ============================================================================
+// Constructors
+// ============================================================================
+
+pub fn create_mat4(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua.clone())?
+        .with_function("new", |lua, values: LuaVariadic<f64>| {
+            let rows: [f64; 16] = values.as_slice().try_into().map_err(|_| {
+                LuaError::runtime(format!("Mat4.new expects 16 numbers, got {}", values.len()))
+            })?;
+            lua.create_userdata(Mat4::from_rows(rows))
+        })?
+        .with_function("fromTranslation", |lua, v: LuaUserDataRef<Vector3>| {
+            lua.create_userdata(Mat4::from_translation(*v))
+        })?
+        .with_function("fromScale", |lua, v: LuaValue| {
+            let scale = match v {
+                LuaValue::Integer(n) => Vector3::ONE * n as f64,
+                LuaValue::Number(n) => Vector3::ONE * n,
+                other => Vector3::from_lua(other, lua)?,
+            };
+            lua.create_userdata(Mat4::from_scale(scale))
+        })?
+        .with_function(
+            "fromAxisAngle",
+            |lua, (axis, angle): (LuaUserDataRef<Vector3>, f64)| {
+                let q = Quaternion::from_axis_angle(*axis, angle);
+                lua.create_userdata(Mat4::from_quaternion(q))
+            },
+        )?
+        .with_function(
+            "fromEulerAnglesXYZ",
+            |lua, (rx, ry, rz): (f64, f64, f64)| {
+                let q = Quaternion::from_euler_angles_xyz(rx, ry, rz);
+                lua.create_userdata(Mat4::from_quaternion(q))
+            },
+        )?
+        .with_function("fromQuaternion", |lua, q: LuaUserDataRef<Quaternion>| {
+            lua.create_userdata(Mat4::from_quaternion(*q))
+        })?
+        .with_function(
+            "perspective",
+            |lua, (fov_y, aspect, near, far): (f64, f64, f64, f64)| {
+                lua.create_userdata(Mat4::perspective(fov_y, aspect, near, far))
+            },
+        )?
+        .with_function(
+            "orthographic",
+            |lua, (left, right, bottom, top, near, far): (f64, f64, f64, f64, f64, f64)| {
+                lua.create_userdata(Mat4::orthographic(left, right, bottom, top, near, far))
+            },
+        )?
+        .with_function(
+            "lookAt",
+            |lua, (eye, target, up): (Vector3, Vector3, Option<Vector3>)| {
+                let up = up.unwrap_or(Vector3::new(0.0, 1.0, 0.0));
+                lua.create_userdata(Mat4::look_at(eye, target, up))
+            },
+        )?
+        .with_function("ReadFrom", |lua, (b, o): (mlua::Buffer, usize)| {
+            let m = read_f64s::<16>(&b, o)?;
+            lua.create_userdata(Mat4 { m })
+        })?
+        .with_value("identity", lua.create_userdata(Mat4::IDENTITY)?)?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+pub fn create_quaternion(lua: Lua) -> LuaResult<LuaValue> {
+    TableBuilder::new(lua.clone())?
+        .with_function("new", |lua, (x, y, z, w): (f64, f64, f64, f64)| {
+            lua.create_userdata(Quaternion::new(x, y, z, w))
+        })?
+        .with_function(
+            "fromAxisAngle",
+            |lua, (axis, angle): (LuaUserDataRef<Vector3>, f64)| {
+                lua.create_userdata(Quaternion::from_axis_angle(*axis, angle))
+            },
+        )?
+        .with_function(
+            "fromEulerAnglesXYZ",
+            |lua, (rx, ry, rz): (f64, f64, f64)| {
+                lua.create_userdata(Quaternion::from_euler_angles_xyz(rx, ry, rz))
+            },
+        )?
+        .with_function("fromMat4", |lua, m: LuaUserDataRef<Mat4>| {
+            lua.create_userdata(m.to_quaternion())
+        })?
+        .with_function("ReadFrom", |lua, (b, o): (mlua::Buffer, usize)| {
+            let [x, y, z, w] = read_f64s(&b, o)?;
+            lua.create_userdata(Quaternion::new(x, y, z, w))
+        })?
+        .with_value("identity", lua.create_userdata(Quaternion::IDENTITY)?)?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
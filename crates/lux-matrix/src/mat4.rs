@@ -0,0 +1,364 @@
+use lux_utils::packed::write_f64s;
+use lux_vector::Vector3;
+use mlua::prelude::*;
+
+use crate::{Quaternion, buffer_from_f64s};
+
+/**
+    A 4x4 matrix, stored in column-major order like OpenGL, Vulkan and most
+    graphics math libraries, so that it can be uploaded to them as-is.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct Mat4 {
+    pub m: [f64; 16],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Self = Self {
+        m: [
+            1.0, 0.0, 0.0, 0.0, //
+            0.0, 1.0, 0.0, 0.0, //
+            0.0, 0.0, 1.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0, //
+        ],
+    };
+
+    /**
+        Creates a matrix from its values in row-major order, the order they are written in.
+    */
+    #[must_use]
+    pub fn from_rows(rows: [f64; 16]) -> Self {
+        let mut m = [0.0; 16];
+        for (index, value) in rows.into_iter().enumerate() {
+            m[(index % 4) * 4 + index / 4] = value;
+        }
+        Self { m }
+    }
+
+    /**
+        Returns the values of this matrix in row-major order.
+    */
+    #[must_use]
+    pub fn to_rows(&self) -> [f64; 16] {
+        Self::from_rows(self.m).m
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn get(&self, row: usize, col: usize) -> f64 {
+        self.m[col * 4 + row]
+    }
+
+    #[inline]
+    pub const fn set(&mut self, row: usize, col: usize, value: f64) {
+        self.m[col * 4 + row] = value;
+    }
+
+    #[must_use]
+    pub fn from_translation(v: Vector3) -> Self {
+        let mut out = Self::IDENTITY;
+        out.set(0, 3, v.x);
+        out.set(1, 3, v.y);
+        out.set(2, 3, v.z);
+        out
+    }
+
+    #[must_use]
+    pub fn from_scale(v: Vector3) -> Self {
+        let mut out = Self::IDENTITY;
+        out.set(0, 0, v.x);
+        out.set(1, 1, v.y);
+        out.set(2, 2, v.z);
+        out
+    }
+
+    #[must_use]
+    #[rustfmt::skip]
+    pub fn from_quaternion(q: Quaternion) -> Self {
+        let Quaternion { x, y, z, w } = q.unit();
+        Self::from_rows([
+            1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w), 0.0,
+            2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w), 0.0,
+            2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y), 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ])
+    }
+
+    /**
+        Creates a right-handed perspective projection with a vertical field of view
+        in radians, mapping depth to the `-1` to `1` range used by OpenGL.
+    */
+    #[must_use]
+    pub fn perspective(fov_y: f64, aspect: f64, near: f64, far: f64) -> Self {
+        let f = 1.0 / (fov_y / 2.0).tan();
+        let mut out = Self { m: [0.0; 16] };
+        out.set(0, 0, f / aspect);
+        out.set(1, 1, f);
+        out.set(2, 2, (far + near) / (near - far));
+        out.set(2, 3, 2.0 * far * near / (near - far));
+        out.set(3, 2, -1.0);
+        out
+    }
+
+    /**
+        Creates a right-handed orthographic projection, mapping depth
+        to the `-1` to `1` range used by OpenGL.
+    */
+    #[must_use]
+    pub fn orthographic(left: f64, right: f64, bottom: f64, top: f64, near: f64, far: f64) -> Self {
+        let mut out = Self::IDENTITY;
+        out.set(0, 0, 2.0 / (right - left));
+        out.set(1, 1, 2.0 / (top - bottom));
+        out.set(2, 2, -2.0 / (far - near));
+        out.set(0, 3, -(right + left) / (right - left));
+        out.set(1, 3, -(top + bottom) / (top - bottom));
+        out.set(2, 3, -(far + near) / (far - near));
+        out
+    }
+
+    /**
+        Creates a right-handed view matrix for a camera at `eye` looking at `target`.
+    */
+    #[must_use]
+    #[rustfmt::skip]
+    pub fn look_at(eye: Vector3, target: Vector3, up: Vector3) -> Self {
+        let f = (target - eye).unit();
+        let s = f.cross(&up).unit();
+        let u = s.cross(&f);
+        Self::from_rows([
+            s.x, s.y, s.z, -s.dot(&eye),
+            u.x, u.y, u.z, -u.dot(&eye),
+            -f.x, -f.y, -f.z, f.dot(&eye),
+            0.0, 0.0, 0.0, 1.0,
+        ])
+    }
+
+    #[must_use]
+    pub fn translation(&self) -> Vector3 {
+        Vector3::new(self.get(0, 3), self.get(1, 3), self.get(2, 3))
+    }
+
+    #[must_use]
+    pub fn transpose(&self) -> Self {
+        Self { m: self.to_rows() }
+    }
+
+    #[must_use]
+    pub fn determinant(&self) -> f64 {
+        let (m, inv) = (&self.m, cofactors(&self.m));
+        m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12]
+    }
+
+    /**
+        Returns the inverse of this matrix, or `None` if it is singular.
+    */
+    #[must_use]
+    pub fn inverse(&self) -> Option<Self> {
+        let (m, inv) = (&self.m, cofactors(&self.m));
+        let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        Some(Self {
+            m: inv.map(|value| value / det),
+        })
+    }
+
+    /**
+        Transforms a point, applying translation and dividing by the resulting `w`.
+    */
+    #[must_use]
+    pub fn transform_point(&self, v: Vector3) -> Vector3 {
+        let row =
+            |r| self.get(r, 0) * v.x + self.get(r, 1) * v.y + self.get(r, 2) * v.z + self.get(r, 3);
+        let (point, w) = (Vector3::new(row(0), row(1), row(2)), row(3));
+        if w == 0.0 { point } else { point / w }
+    }
+
+    /**
+        Transforms a direction, ignoring translation.
+    */
+    #[must_use]
+    pub fn transform_vector(&self, v: Vector3) -> Vector3 {
+        Vector3::new(
+            self.get(0, 0) * v.x + self.get(0, 1) * v.y + self.get(0, 2) * v.z,
+            self.get(1, 0) * v.x + self.get(1, 1) * v.y + self.get(1, 2) * v.z,
+            self.get(2, 0) * v.x + self.get(2, 1) * v.y + self.get(2, 2) * v.z,
+        )
+    }
+
+    /**
+        Returns the rotation of this matrix, which is assumed to have no scale or shear.
+    */
+    #[must_use]
+    pub fn to_quaternion(&self) -> Quaternion {
+        let r = |row, col| self.get(row, col);
+        let trace = r(0, 0) + r(1, 1) + r(2, 2);
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quaternion::new(
+                (r(2, 1) - r(1, 2)) / s,
+                (r(0, 2) - r(2, 0)) / s,
+                (r(1, 0) - r(0, 1)) / s,
+                s / 4.0,
+            )
+        } else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2) {
+            let s = (1.0 + r(0, 0) - r(1, 1) - r(2, 2)).sqrt() * 2.0;
+            Quaternion::new(
+                s / 4.0,
+                (r(0, 1) + r(1, 0)) / s,
+                (r(0, 2) + r(2, 0)) / s,
+                (r(2, 1) - r(1, 2)) / s,
+            )
+        } else if r(1, 1) > r(2, 2) {
+            let s = (1.0 + r(1, 1) - r(0, 0) - r(2, 2)).sqrt() * 2.0;
+            Quaternion::new(
+                (r(0, 1) + r(1, 0)) / s,
+                s / 4.0,
+                (r(1, 2) + r(2, 1)) / s,
+                (r(0, 2) - r(2, 0)) / s,
+            )
+        } else {
+            let s = (1.0 + r(2, 2) - r(0, 0) - r(1, 1)).sqrt() * 2.0;
+            Quaternion::new(
+                (r(0, 2) + r(2, 0)) / s,
+                (r(1, 2) + r(2, 1)) / s,
+                s / 4.0,
+                (r(1, 0) - r(0, 1)) / s,
+            )
+        };
+        q.unit()
+    }
+
+    /**
+        Returns the Euler angles of the rotation of this matrix in radians, applied in
+        Z, Y, X order - the same as `CFrame:ToEulerAnglesXYZ` in Roblox.
+    */
+    #[must_use]
+    pub fn to_euler_angles_xyz(&self) -> (f64, f64, f64) {
+        let ry = self.get(0, 2).clamp(-1.0, 1.0).asin();
+        if self.get(0, 2).abs() < 1.0 - 1e-9 {
+            let rx = (-self.get(1, 2)).atan2(self.get(2, 2));
+            let rz = (-self.get(0, 1)).atan2(self.get(0, 0));
+            (rx, ry, rz)
+        } else {
+            // Gimbal lock, X and Z rotate around the same axis so Z is chosen to be zero
+            let rx = self.get(2, 1).atan2(self.get(1, 1));
+            (rx, ry, 0.0)
+        }
+    }
+}
+
+impl std::ops::Mul for Mat4 {
+    type Output = Self;
+    fn mul(self, o: Self) -> Self {
+        let mut out = Self { m: [0.0; 16] };
+        for row in 0..4 {
+            for col in 0..4 {
+                let value = (0..4).map(|k| self.get(row, k) * o.get(k, col)).sum();
+                out.set(row, col, value);
+            }
+        }
+        out
+    }
+}
+
+/**
+    Returns the transposed cofactor matrix of a column-major 4x4 matrix,
+    which divided by its determinant is the inverse of the matrix.
+*/
+#[rustfmt::skip]
+fn cofactors(m: &[f64; 16]) -> [f64; 16] {
+    let mut inv = [0.0; 16];
+    inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15] + m[9] * m[7] * m[14] + m[13] * m[6] * m[11] - m[13] * m[7] * m[10];
+    inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15] - m[8] * m[7] * m[14] - m[12] * m[6] * m[11] + m[12] * m[7] * m[10];
+    inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15] + m[8] * m[7] * m[13] + m[12] * m[5] * m[11] - m[12] * m[7] * m[9];
+    inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14] - m[8] * m[6] * m[13] - m[12] * m[5] * m[10] + m[12] * m[6] * m[9];
+    inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15] - m[9] * m[3] * m[14] - m[13] * m[2] * m[11] + m[13] * m[3] * m[10];
+    inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15] + m[8] * m[3] * m[14] + m[12] * m[2] * m[11] - m[12] * m[3] * m[10];
+    inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15] - m[8] * m[3] * m[13] - m[12] * m[1] * m[11] + m[12] * m[3] * m[9];
+    inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14] + m[8] * m[2] * m[13] + m[12] * m[1] * m[10] - m[12] * m[2] * m[9];
+    inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15] + m[5] * m[3] * m[14] + m[13] * m[2] * m[7] - m[13] * m[3] * m[6];
+    inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15] - m[4] * m[3] * m[14] - m[12] * m[2] * m[7] + m[12] * m[3] * m[6];
+    inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15] + m[4] * m[3] * m[13] + m[12] * m[1] * m[7] - m[12] * m[3] * m[5];
+    inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14] - m[4] * m[2] * m[13] - m[12] * m[1] * m[6] + m[12] * m[2] * m[5];
+    inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11] - m[5] * m[3] * m[10] - m[9] * m[2] * m[7] + m[9] * m[3] * m[6];
+    inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11] + m[4] * m[3] * m[10] + m[8] * m[2] * m[7] - m[8] * m[3] * m[6];
+    inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11] - m[4] * m[3] * m[9] - m[8] * m[1] * m[7] + m[8] * m[3] * m[5];
+    inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10] + m[4] * m[2] * m[9] + m[8] * m[1] * m[6] - m[8] * m[2] * m[5];
+    inv
+}
+
+impl LuaUserData for Mat4 {
+    fn add_fields<F: LuaUserDataFields<Self>>(f: &mut F) {
+        f.add_field_method_get("Position", |lua, t| lua.create_userdata(t.translation()));
+        f.add_field_method_get("Rotation", |lua, t| lua.create_userdata(t.to_quaternion()));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
+        m.add_method("GetComponents", |_, t, ()| {
+            Ok(LuaMultiValue::from_iter(t.to_rows().map(LuaValue::Number)))
+        });
+        m.add_method("Transpose", |lua, t, ()| lua.create_userdata(t.transpose()));
+        m.add_method("Determinant", |_, t, ()| Ok(t.determinant()));
+        m.add_method("Inverse", |lua, t, ()| {
+            let inverse = t
+                .inverse()
+                .ok_or_else(|| LuaError::runtime("Mat4 is singular and has no inverse"))?;
+            lua.create_userdata(inverse)
+        });
+        m.add_method("TransformPoint", |lua, t, v: LuaUserDataRef<Vector3>| {
+            lua.create_userdata(t.transform_point(*v))
+        });
+        m.add_method("TransformVector", |lua, t, v: LuaUserDataRef<Vector3>| {
+            lua.create_userdata(t.transform_vector(*v))
+        });
+        m.add_method("ToQuaternion", |lua, t, ()| {
+            lua.create_userdata(t.to_quaternion())
+        });
+        m.add_method("ToEulerAnglesXYZ", |_, t, ()| Ok(t.to_euler_angles_xyz()));
+        m.add_method("WriteTo", |_, t, (b, o): (mlua::Buffer, usize)| {
+            write_f64s(&b, o, &t.m)
+        });
+        m.add_method("ToBuffer", |lua, t, precision: Option<String>| {
+            buffer_from_f64s(lua, &t.m, precision.as_deref())
+        });
+        m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
+            Ok(*t == *o)
+        });
+        m.add_meta_method(LuaMetaMethod::ToString, |_, t, ()| {
+            let rows = t.to_rows().map(|value| value.to_string());
+            Ok(rows.join(", "))
+        });
+        m.add_meta_method(LuaMetaMethod::Mul, |lua, t, o: LuaAnyUserData| {
+            if let Ok(other) = o.borrow::<Self>() {
+                lua.create_userdata(*t * *other)
+            } else if let Ok(v) = o.borrow::<Vector3>() {
+                lua.create_userdata(t.transform_point(*v))
+            } else {
+                Err(LuaError::runtime(
+                    "Mat4 can only be multiplied by a Mat4 or a Vector3",
+                ))
+            }
+        });
+    }
+}
+
+impl FromLua for Mat4 {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: "Mat4".to_string(),
+                message: Some("expected a Mat4".into()),
+            }),
+        }
+    }
+}
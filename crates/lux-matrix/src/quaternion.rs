@@ -0,0 +1,269 @@
+use lux_utils::packed::write_f64s;
+use lux_vector::Vector3;
+use mlua::prelude::*;
+
+use crate::{Mat4, buffer_from_f64s};
+
+/// Below this dot product between two rotations, slerp falls back to a normalized lerp
+const SLERP_THRESHOLD: f64 = 0.9995;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct Quaternion {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub w: f64,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quaternion {
+    pub const IDENTITY: Self = Self {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        w: 1.0,
+    };
+
+    #[inline]
+    #[must_use]
+    pub const fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
+        Self { x, y, z, w }
+    }
+
+    /**
+        Creates a rotation of `angle` radians around `axis`, which does not need to
+        be normalized. A zero axis results in the identity rotation.
+    */
+    #[must_use]
+    pub fn from_axis_angle(axis: Vector3, angle: f64) -> Self {
+        if axis.magnitude() == 0.0 {
+            return Self::IDENTITY;
+        }
+        let axis = axis.unit();
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Self::new(axis.x * sin, axis.y * sin, axis.z * sin, cos)
+    }
+
+    /**
+        Creates a rotation from Euler angles in radians, applied in Z, Y, X order -
+        the same as `CFrame.fromEulerAnglesXYZ` in Roblox.
+    */
+    #[must_use]
+    pub fn from_euler_angles_xyz(rx: f64, ry: f64, rz: f64) -> Self {
+        Self::from_axis_angle(Vector3::new(1.0, 0.0, 0.0), rx)
+            * Self::from_axis_angle(Vector3::new(0.0, 1.0, 0.0), ry)
+            * Self::from_axis_angle(Vector3::new(0.0, 0.0, 1.0), rz)
+    }
+
+    /**
+        Returns the Euler angles of this rotation in radians,
+        the inverse of [`Quaternion::from_euler_angles_xyz`].
+    */
+    #[must_use]
+    pub fn to_euler_angles_xyz(&self) -> (f64, f64, f64) {
+        Mat4::from_quaternion(*self).to_euler_angles_xyz()
+    }
+
+    /**
+        Returns the normalized axis and the angle in radians of this rotation.
+    */
+    #[must_use]
+    pub fn to_axis_angle(&self) -> (Vector3, f64) {
+        let q = self.unit();
+        let q = if q.w < 0.0 { -q } else { q };
+        let sin = (1.0 - q.w * q.w).max(0.0).sqrt();
+        let angle = 2.0 * q.w.clamp(-1.0, 1.0).acos();
+        if sin < f64::EPSILON {
+            (Vector3::new(1.0, 0.0, 0.0), angle)
+        } else {
+            (Vector3::new(q.x / sin, q.y / sin, q.z / sin), angle)
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn dot(&self, other: &Self) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn magnitude(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn unit(&self) -> Self {
+        let mag = self.magnitude();
+        if mag == 0.0 {
+            Self::IDENTITY
+        } else {
+            Self::new(self.x / mag, self.y / mag, self.z / mag, self.w / mag)
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub const fn conjugate(&self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    /**
+        Returns the inverse rotation, or `None` if this quaternion has a length of zero.
+    */
+    #[must_use]
+    pub fn inverse(&self) -> Option<Self> {
+        let len_sq = self.dot(self);
+        if len_sq == 0.0 {
+            return None;
+        }
+        let c = self.conjugate();
+        Some(Self::new(
+            c.x / len_sq,
+            c.y / len_sq,
+            c.z / len_sq,
+            c.w / len_sq,
+        ))
+    }
+
+    /**
+        Spherically interpolates between two rotations, always taking the shortest path.
+    */
+    #[must_use]
+    pub fn slerp(&self, goal: &Self, alpha: f64) -> Self {
+        let a = alpha.clamp(0.0, 1.0);
+        let mut goal = *goal;
+        let mut dot = self.dot(&goal);
+        if dot < 0.0 {
+            goal = -goal;
+            dot = -dot;
+        }
+
+        if dot > SLERP_THRESHOLD {
+            return Self::new(
+                self.x + (goal.x - self.x) * a,
+                self.y + (goal.y - self.y) * a,
+                self.z + (goal.z - self.z) * a,
+                self.w + (goal.w - self.w) * a,
+            )
+            .unit();
+        }
+
+        let theta = dot.acos();
+        let sin = theta.sin();
+        let from = ((1.0 - a) * theta).sin() / sin;
+        let to = (a * theta).sin() / sin;
+        Self::new(
+            self.x * from + goal.x * to,
+            self.y * from + goal.y * to,
+            self.z * from + goal.z * to,
+            self.w * from + goal.w * to,
+        )
+    }
+
+    /**
+        Rotates a vector by this quaternion, which is assumed to be normalized.
+    */
+    #[must_use]
+    pub fn rotate(&self, v: Vector3) -> Vector3 {
+        let q = Vector3::new(self.x, self.y, self.z);
+        let t = q.cross(&v) * 2.0;
+        v + t * self.w + q.cross(&t)
+    }
+}
+
+impl std::ops::Mul for Quaternion {
+    type Output = Self;
+    #[inline]
+    fn mul(self, o: Self) -> Self {
+        Self::new(
+            self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+            self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+        )
+    }
+}
+impl std::ops::Neg for Quaternion {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z, -self.w)
+    }
+}
+
+impl LuaUserData for Quaternion {
+    fn add_fields<F: LuaUserDataFields<Self>>(f: &mut F) {
+        f.add_field_method_get("X", |_, t| Ok(t.x));
+        f.add_field_method_get("Y", |_, t| Ok(t.y));
+        f.add_field_method_get("Z", |_, t| Ok(t.z));
+        f.add_field_method_get("W", |_, t| Ok(t.w));
+        f.add_field_method_get("Magnitude", |_, t| Ok(t.magnitude()));
+        f.add_field_method_get("Unit", |lua, t| lua.create_userdata(t.unit()));
+    }
+    fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
+        m.add_method("Slerp", |lua, t, (g, a): (LuaUserDataRef<Self>, f64)| {
+            lua.create_userdata(t.slerp(&g, a))
+        });
+        m.add_method("Dot", |_, t, o: LuaUserDataRef<Self>| Ok(t.dot(&o)));
+        m.add_method("Conjugate", |lua, t, ()| lua.create_userdata(t.conjugate()));
+        m.add_method("Inverse", |lua, t, ()| {
+            let inverse = t.inverse().ok_or_else(|| {
+                LuaError::runtime("Quaternion with a length of zero has no inverse")
+            })?;
+            lua.create_userdata(inverse)
+        });
+        m.add_method("ToAxisAngle", |lua, t, ()| {
+            let (axis, angle) = t.to_axis_angle();
+            Ok((lua.create_userdata(axis)?, angle))
+        });
+        m.add_method("ToEulerAnglesXYZ", |_, t, ()| Ok(t.to_euler_angles_xyz()));
+        m.add_method("ToMat4", |lua, t, ()| {
+            lua.create_userdata(Mat4::from_quaternion(*t))
+        });
+        m.add_method("WriteTo", |_, t, (b, o): (mlua::Buffer, usize)| {
+            write_f64s(&b, o, &[t.x, t.y, t.z, t.w])
+        });
+        m.add_method("ToBuffer", |lua, t, precision: Option<String>| {
+            buffer_from_f64s(lua, &[t.x, t.y, t.z, t.w], precision.as_deref())
+        });
+        m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
+            Ok(*t == *o)
+        });
+        m.add_meta_method(LuaMetaMethod::ToString, |_, t, ()| {
+            Ok(format!("{}, {}, {}, {}", t.x, t.y, t.z, t.w))
+        });
+        m.add_meta_method(LuaMetaMethod::Mul, |lua, t, o: LuaAnyUserData| {
+            if let Ok(q) = o.borrow::<Self>() {
+                lua.create_userdata(*t * *q)
+            } else if let Ok(v) = o.borrow::<Vector3>() {
+                lua.create_userdata(t.rotate(*v))
+            } else {
+                Err(LuaError::runtime(
+                    "Quaternion can only be multiplied by a Quaternion or a Vector3",
+                ))
+            }
+        });
+        m.add_meta_method(LuaMetaMethod::Unm, |lua, t, ()| lua.create_userdata(-*t));
+    }
+}
+
+impl FromLua for Quaternion {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: "Quaternion".to_string(),
+                message: Some("expected a Quaternion".into()),
+            }),
+        }
+    }
+}
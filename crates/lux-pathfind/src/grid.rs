@@ -0,0 +1,337 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+/**
+    Estimates the remaining cost from a cell to the goal.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    Manhattan,
+    Euclidean,
+    Octile,
+    Chebyshev,
+}
+
+impl Heuristic {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "manhattan" => Self::Manhattan,
+            "euclidean" => Self::Euclidean,
+            "octile" => Self::Octile,
+            "chebyshev" => Self::Chebyshev,
+            _ => return None,
+        })
+    }
+
+    fn estimate(self, dx: usize, dy: usize) -> f64 {
+        let (dx, dy) = (dx as f64, dy as f64);
+        match self {
+            Self::Manhattan => dx + dy,
+            Self::Euclidean => dx.hypot(dy),
+            Self::Octile => dx.max(dy) + (std::f64::consts::SQRT_2 - 1.0) * dx.min(dy),
+            Self::Chebyshev => dx.max(dy),
+        }
+    }
+}
+
+/**
+    Options for [`Grid::find_path`].
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct PathOptions {
+    /// Allow diagonal moves, which never cut past blocked corners
+    pub diagonal: bool,
+    pub heuristic: Heuristic,
+    /// Remove waypoints that can be skipped in a straight line
+    pub smooth: bool,
+}
+
+/// An entry of the open set, ordered so that the lowest estimated total cost comes first
+#[derive(Debug, Clone, Copy)]
+struct Open {
+    estimate: f64,
+    cost: f64,
+    cell: usize,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed for the max-heap, ties go to the cell furthest along the path
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| self.cost.total_cmp(&other.cost))
+    }
+}
+
+/**
+    A grid of cells, each either blocked or walkable with a cost for entering it.
+*/
+#[derive(Debug, Clone)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    /// Cost of entering each cell, `None` if blocked
+    costs: Vec<Option<f64>>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            costs: vec![Some(1.0); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x < self.width && y < self.height).then(|| y * self.width + x)
+    }
+
+    fn position(&self, cell: usize) -> (usize, usize) {
+        (cell % self.width, cell / self.width)
+    }
+
+    pub fn in_bounds(&self, x: usize, y: usize) -> bool {
+        self.index(x, y).is_some()
+    }
+
+    pub fn cost(&self, x: usize, y: usize) -> Option<f64> {
+        self.index(x, y).and_then(|i| self.costs[i])
+    }
+
+    pub fn is_walkable(&self, x: usize, y: usize) -> bool {
+        self.cost(x, y).is_some()
+    }
+
+    /**
+        Sets whether a cell is walkable, keeping its cost - or resetting it to `1` if it was blocked.
+    */
+    pub fn set_walkable(&mut self, x: usize, y: usize, walkable: bool) {
+        if let Some(i) = self.index(x, y) {
+            self.costs[i] = match (walkable, self.costs[i]) {
+                (true, Some(cost)) => Some(cost),
+                (true, None) => Some(1.0),
+                (false, _) => None,
+            };
+        }
+    }
+
+    /**
+        Sets the cost of entering a cell, which also makes it walkable.
+    */
+    pub fn set_cost(&mut self, x: usize, y: usize, cost: f64) {
+        if let Some(i) = self.index(x, y) {
+            self.costs[i] = Some(cost);
+        }
+    }
+
+    /**
+        Finds the cheapest path between two cells using A*.
+
+        Returns the cells of the path, including the start and goal,
+        or `None` if the goal cannot be reached.
+    */
+    pub fn find_path(
+        &self,
+        start: (usize, usize),
+        goal: (usize, usize),
+        options: PathOptions,
+    ) -> Option<Vec<(usize, usize)>> {
+        let start_cell = self.index(start.0, start.1)?;
+        let goal_cell = self.index(goal.0, goal.1)?;
+        if self.costs[start_cell].is_none() || self.costs[goal_cell].is_none() {
+            return None;
+        }
+
+        // Scale the heuristic by the cheapest cell so that it never overestimates
+        let min_cost = self
+            .costs
+            .iter()
+            .flatten()
+            .copied()
+            .fold(f64::INFINITY, f64::min);
+        let estimate = |cell: usize| {
+            let (x, y) = self.position(cell);
+            options
+                .heuristic
+                .estimate(x.abs_diff(goal.0), y.abs_diff(goal.1))
+                * min_cost
+        };
+
+        let mut best = vec![f64::INFINITY; self.costs.len()];
+        let mut came_from = vec![usize::MAX; self.costs.len()];
+        let mut open = BinaryHeap::new();
+
+        best[start_cell] = 0.0;
+        open.push(Open {
+            estimate: estimate(start_cell),
+            cost: 0.0,
+            cell: start_cell,
+        });
+
+        while let Some(Open { cost, cell, .. }) = open.pop() {
+            if cell == goal_cell {
+                return Some(self.finish_path(&came_from, start_cell, goal_cell, options));
+            }
+            if cost > best[cell] {
+                continue; // Stale entry, the cell was reached more cheaply since
+            }
+
+            for (next, step) in self.neighbors(cell, options.diagonal) {
+                let Some(enter) = self.costs[next] else {
+                    continue;
+                };
+                let next_cost = cost + step * enter;
+                if next_cost < best[next] {
+                    best[next] = next_cost;
+                    came_from[next] = cell;
+                    open.push(Open {
+                        estimate: next_cost + estimate(next),
+                        cost: next_cost,
+                        cell: next,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Walkable neighbors of a cell, with the length of the step to them
+    fn neighbors(&self, cell: usize, diagonal: bool) -> Vec<(usize, f64)> {
+        let (x, y) = self.position(cell);
+        let offset = |dx: isize, dy: isize| {
+            let nx = x.checked_add_signed(dx)?;
+            let ny = y.checked_add_signed(dy)?;
+            self.index(nx, ny)
+                .filter(|&next| self.costs[next].is_some())
+        };
+
+        let mut neighbors = Vec::with_capacity(8);
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            if let Some(next) = offset(dx, dy) {
+                neighbors.push((next, 1.0));
+            }
+        }
+        if diagonal {
+            for (dx, dy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                // Both cells next to the corner must be walkable
+                if offset(dx, 0).is_some()
+                    && offset(0, dy).is_some()
+                    && let Some(next) = offset(dx, dy)
+                {
+                    neighbors.push((next, std::f64::consts::SQRT_2));
+                }
+            }
+        }
+        neighbors
+    }
+
+    fn finish_path(
+        &self,
+        came_from: &[usize],
+        start: usize,
+        goal: usize,
+        options: PathOptions,
+    ) -> Vec<(usize, usize)> {
+        let mut cells = vec![goal];
+        let mut cell = goal;
+        while cell != start {
+            cell = came_from[cell];
+            cells.push(cell);
+        }
+        cells.reverse();
+
+        let path: Vec<(usize, usize)> = cells.into_iter().map(|c| self.position(c)).collect();
+        if options.smooth {
+            self.smooth(&path)
+        } else {
+            path
+        }
+    }
+
+    /**
+        Removes waypoints that can be skipped by walking in a straight line
+        to a later one, as long as every cell along the line is walkable.
+    */
+    fn smooth(&self, path: &[(usize, usize)]) -> Vec<(usize, usize)> {
+        let Some(&first) = path.first() else {
+            return Vec::new();
+        };
+        let mut smoothed = vec![first];
+        let mut anchor = 0;
+        while anchor < path.len() - 1 {
+            // Furthest waypoint that can be reached directly from the anchor
+            let mut next = anchor + 1;
+            for candidate in (anchor + 2..path.len()).rev() {
+                if self.line_of_sight(path[anchor], path[candidate]) {
+                    next = candidate;
+                    break;
+                }
+            }
+            smoothed.push(path[next]);
+            anchor = next;
+        }
+        smoothed
+    }
+
+    /// Checks every cell a straight line between the centers of two cells passes through
+    fn line_of_sight(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        let (mut x, mut y, tx, ty) = (
+            from.0 as isize,
+            from.1 as isize,
+            to.0 as isize,
+            to.1 as isize,
+        );
+        let (dx, dy) = ((tx - x).abs(), (ty - y).abs());
+        let (sx, sy) = ((tx - x).signum(), (ty - y).signum());
+        let walkable = |x: isize, y: isize| self.is_walkable(x as usize, y as usize);
+
+        // Supercover line - visits every cell touched by the line
+        let mut error = dx - dy;
+        while (x, y) != (tx, ty) {
+            let e2 = 2 * error;
+            if e2 > -dy && e2 < dx {
+                // The line passes exactly through a corner, which may not be cut like a diagonal move
+                if !walkable(x + sx, y) || !walkable(x, y + sy) {
+                    return false;
+                }
+                error += dx - dy;
+                x += sx;
+                y += sy;
+            } else if e2 > -dy {
+                error -= dy;
+                x += sx;
+            } else {
+                error += dx;
+                y += sy;
+            }
+            if !walkable(x, y) {
+                return false;
+            }
+        }
+        true
+    }
+}
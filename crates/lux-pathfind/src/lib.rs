@@ -0,0 +1,163 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! A* pathfinding on grids for Lux
+
+use lux_utils::TableBuilder;
+use lux_vector::Vector2;
+use mlua::prelude::*;
+
+mod grid;
+
+use self::grid::{Grid, Heuristic, PathOptions};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/// A grid of walkable and blocked cells, with a cost for entering each walkable cell
+struct LuaGrid(Grid);
+
+impl LuaGrid {
+    /// Converts zero-based cell coordinates, erroring if they are outside of the grid
+    fn cell(&self, x: i64, y: i64) -> LuaResult<(usize, usize)> {
+        match (usize::try_from(x), usize::try_from(y)) {
+            (Ok(cx), Ok(cy)) if self.0.in_bounds(cx, cy) => Ok((cx, cy)),
+            _ => Err(LuaError::runtime(format!(
+                "Cell ({x}, {y}) is outside of the {}x{} grid",
+                self.0.width(),
+                self.0.height()
+            ))),
+        }
+    }
+
+    /// Converts a position to the cell containing it
+    fn cell_at(&self, position: &Vector2) -> LuaResult<(usize, usize)> {
+        self.cell(position.x.floor() as i64, position.y.floor() as i64)
+    }
+}
+
+fn path_options(options: Option<&LuaTable>) -> LuaResult<PathOptions> {
+    let get_bool = |key: &str| -> LuaResult<bool> {
+        Ok(options
+            .map(|o| o.get::<Option<bool>>(key))
+            .transpose()?
+            .flatten()
+            .unwrap_or(false))
+    };
+    let diagonal = get_bool("diagonal")?;
+    let smooth = get_bool("smooth")?;
+
+    let heuristic = match options
+        .map(|o| o.get::<Option<String>>("heuristic"))
+        .transpose()?
+        .flatten()
+    {
+        Some(name) => Heuristic::from_name(&name).ok_or_else(|| {
+            LuaError::runtime(format!(
+                "Unknown heuristic '{name}', expected 'manhattan', 'euclidean', 'octile' or 'chebyshev'"
+            ))
+        })?,
+        None if diagonal => Heuristic::Octile,
+        None => Heuristic::Manhattan,
+    };
+
+    Ok(PathOptions {
+        diagonal,
+        heuristic,
+        smooth,
+    })
+}
+
+impl LuaUserData for LuaGrid {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("width", |_, this| Ok(this.0.width()));
+        fields.add_field_method_get("height", |_, this| Ok(this.0.height()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method_mut(
+            "setWalkable",
+            |_, this, (x, y, walkable): (i64, i64, bool)| {
+                let (x, y) = this.cell(x, y)?;
+                this.0.set_walkable(x, y, walkable);
+                Ok(())
+            },
+        );
+        methods.add_method("isWalkable", |_, this, (x, y): (i64, i64)| {
+            let (x, y) = this.cell(x, y)?;
+            Ok(this.0.is_walkable(x, y))
+        });
+        methods.add_method_mut("setCost", |_, this, (x, y, cost): (i64, i64, f64)| {
+            let (x, y) = this.cell(x, y)?;
+            if !(cost.is_finite() && cost > 0.0) {
+                return Err(LuaError::runtime(format!(
+                    "Cost must be a positive number, got {cost}"
+                )));
+            }
+            this.0.set_cost(x, y, cost);
+            Ok(())
+        });
+        methods.add_method("getCost", |_, this, (x, y): (i64, i64)| {
+            let (x, y) = this.cell(x, y)?;
+            Ok(this.0.cost(x, y))
+        });
+
+        methods.add_method(
+            "findPath",
+            |lua,
+             this,
+             (start, goal, options): (
+                LuaUserDataRef<Vector2>,
+                LuaUserDataRef<Vector2>,
+                Option<LuaTable>,
+            )| {
+                let options = path_options(options.as_ref())?;
+                let Some(path) =
+                    this.0
+                        .find_path(this.cell_at(&start)?, this.cell_at(&goal)?, options)
+                else {
+                    return Ok(None);
+                };
+
+                let waypoints = lua.create_table_with_capacity(path.len(), 0)?;
+                for (x, y) in path {
+                    waypoints.push(Vector2::new(x as f64, y as f64))?;
+                }
+                Ok(Some(waypoints))
+            },
+        );
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(format!("Grid({}x{})", this.0.width(), this.0.height()))
+        });
+    }
+}
+
+/// Create a grid where every cell is walkable with a cost of 1
+fn grid_new(_: &Lua, (width, height): (usize, usize)) -> LuaResult<LuaGrid> {
+    if width == 0 || height == 0 {
+        return Err(LuaError::runtime(format!(
+            "Grid size must be at least 1x1, got {width}x{height}"
+        )));
+    }
+    Ok(LuaGrid(Grid::new(width, height)))
+}
+
+/**
+    Creates the `pathfind` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let grid = TableBuilder::new(lua.clone())?
+        .with_function("new", grid_new)?
+        .build_readonly()?;
+    TableBuilder::new(lua)?
+        .with_value("grid", grid)?
+        .build_readonly()
+}
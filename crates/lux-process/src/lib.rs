@@ -18,8 +18,10 @@ use lux_utils::{
 mod create;
 mod exec;
 mod options;
+mod signals;
 
 use self::options::ProcessSpawnOptions;
+use self::signals::SignalHandlers;
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
@@ -74,6 +76,10 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     let fns = Functions::new(lua.clone())?;
     let process_exit = fns.exit;
 
+    // Signal handlers are stored per run, replacing any left over from a previous one
+    lua.set_app_data(SignalHandlers::new());
+    let process_shutdown = signals::create_shutdown(&lua)?;
+
     // Create the full process table
     TableBuilder::new(lua)?
         .with_value("os", os)?
@@ -85,6 +91,10 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .with_value("exit", process_exit)?
         .with_async_function("exec", process_exec)?
         .with_function("create", process_create)?
+        .with_function("onInterrupt", signals::process_on_interrupt)?
+        .with_function("onTerminate", signals::process_on_terminate)?
+        .with_value("shutdown", process_shutdown)?
+        .with_function("isShuttingDown", signals::process_is_shutting_down)?
         .build_readonly()
 }
 
@@ -0,0 +1,320 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use async_channel::{Receiver, Sender, unbounded};
+use async_executor::Task;
+use async_io::Timer;
+use async_signal::{Signal, Signals};
+use futures_lite::{FutureExt, StreamExt};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+
+use lux_utils::process::{OsSignal, ProcessShutdown};
+
+const DEFAULT_SHUTDOWN_TIMEOUT: f64 = 10.0;
+
+const SHUTDOWN_IMPL_LUA: &str = r"
+shutdown(...)
+yield()
+";
+
+#[cfg(unix)]
+const WATCHED_SIGNALS: &[Signal] = &[Signal::Int, Signal::Term];
+#[cfg(not(unix))]
+const WATCHED_SIGNALS: &[Signal] = &[Signal::Int];
+
+struct Handler {
+    id: u64,
+    signal: OsSignal,
+    func: LuaFunction,
+}
+
+struct HandlersInner {
+    handlers: RefCell<Vec<Handler>>,
+    next_id: Cell<u64>,
+    /// Number of connected handlers, shared with the background watcher
+    active: Arc<AtomicUsize>,
+    sender: Sender<Option<OsSignal>>,
+    receiver: Receiver<Option<OsSignal>>,
+    watcher: RefCell<Option<Task<()>>>,
+    dispatching: Cell<bool>,
+    last: Cell<Option<OsSignal>>,
+}
+
+/**
+    Lua functions connected to OS signals with `process.onInterrupt` and `process.onTerminate`.
+
+    Signals are received by a background task, which does not keep the scheduler alive, and
+    forwarded to a thread-local dispatcher, which does - but only while a handler is connected.
+    Any signal received with no handler connected for it exits with the conventional exit code.
+*/
+#[derive(Clone)]
+pub struct SignalHandlers(Rc<HandlersInner>);
+
+impl SignalHandlers {
+    pub fn new() -> Self {
+        let (sender, receiver) = unbounded();
+        Self(Rc::new(HandlersInner {
+            handlers: RefCell::new(Vec::new()),
+            next_id: Cell::new(1),
+            active: Arc::new(AtomicUsize::new(0)),
+            sender,
+            receiver,
+            watcher: RefCell::new(None),
+            dispatching: Cell::new(false),
+            last: Cell::new(None),
+        }))
+    }
+
+    /**
+        Returns the last OS signal that was received while a handler was connected.
+    */
+    pub fn last_signal(&self) -> Option<OsSignal> {
+        self.0.last.get()
+    }
+
+    fn connect(&self, lua: &Lua, signal: OsSignal, func: LuaFunction) -> LuaResult<u64> {
+        self.ensure_watcher(lua)?;
+
+        let id = self.0.next_id.get();
+        self.0.next_id.set(id + 1);
+        self.0
+            .handlers
+            .borrow_mut()
+            .push(Handler { id, signal, func });
+        self.0.active.fetch_add(1, Ordering::SeqCst);
+
+        if !self.0.dispatching.replace(true) {
+            let this = self.clone();
+            let inner = lua.clone();
+            lua.spawn_local(async move {
+                this.dispatch(&inner).await;
+                this.0.dispatching.set(false);
+            });
+        }
+
+        Ok(id)
+    }
+
+    fn disconnect(&self, id: u64) {
+        let mut handlers = self.0.handlers.borrow_mut();
+        let before = handlers.len();
+        handlers.retain(|h| h.id != id);
+        if handlers.len() < before && self.0.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // Wake the dispatcher up so that it stops and lets the scheduler finish
+            let _ = self.0.sender.try_send(None);
+        }
+    }
+
+    fn is_connected(&self, id: u64) -> bool {
+        self.0.handlers.borrow().iter().any(|h| h.id == id)
+    }
+
+    fn ensure_watcher(&self, lua: &Lua) -> LuaResult<()> {
+        let mut watcher = self.0.watcher.borrow_mut();
+        if watcher.as_ref().is_some_and(|task| !task.is_finished()) {
+            return Ok(());
+        }
+
+        let mut signals = Signals::new(WATCHED_SIGNALS.iter().copied()).into_lua_err()?;
+        let active = Arc::clone(&self.0.active);
+        let sender = self.0.sender.clone();
+        *watcher = Some(lua.spawn(async move {
+            while let Some(Ok(signal)) = signals.next().await {
+                let signal = match signal {
+                    Signal::Term => OsSignal::Terminate,
+                    _ => OsSignal::Interrupt,
+                };
+                // NOTE: Registering the signals replaced their default behavior,
+                // so we need to emulate it when nothing is listening anymore
+                if active.load(Ordering::SeqCst) == 0 {
+                    std::process::exit(i32::from(signal.exit_code()));
+                }
+                if sender.send(Some(signal)).await.is_err() {
+                    break;
+                }
+            }
+        }));
+
+        Ok(())
+    }
+
+    async fn dispatch(&self, lua: &Lua) {
+        while let Ok(message) = self.0.receiver.recv().await {
+            let Some(signal) = message else {
+                if self.0.active.load(Ordering::SeqCst) == 0 {
+                    break;
+                }
+                continue;
+            };
+            self.0.last.set(Some(signal));
+
+            let funcs = self
+                .0
+                .handlers
+                .borrow()
+                .iter()
+                .filter(|h| h.signal == signal)
+                .map(|h| h.func.clone())
+                .collect::<Vec<_>>();
+            if funcs.is_empty() {
+                lua.set_exit_code(signal.exit_code());
+                break;
+            }
+            for func in funcs {
+                // NOTE: Handlers are pushed with the scheduler extension, not
+                // `task.spawn`, so they still run during a graceful shutdown
+                let _ = lua.push_thread_front(func, signal.name());
+            }
+
+            if self.0.active.load(Ordering::SeqCst) == 0 {
+                break;
+            }
+        }
+    }
+}
+
+/**
+    Handle for a connected OS signal handler, returned by `process.onInterrupt` and `process.onTerminate`.
+*/
+pub struct SignalConnection {
+    id: u64,
+    handlers: SignalHandlers,
+}
+
+impl LuaUserData for SignalConnection {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Connected", |_, this| {
+            Ok(this.handlers.is_connected(this.id))
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("Disconnect", |_, this, ()| {
+            this.handlers.disconnect(this.id);
+            Ok(())
+        });
+    }
+}
+
+fn handlers(lua: &Lua) -> LuaResult<SignalHandlers> {
+    let handlers = lua
+        .app_data_ref::<SignalHandlers>()
+        .ok_or_else(|| LuaError::runtime("Missing signal handlers in Lua app data"))?;
+    Ok(handlers.clone())
+}
+
+fn connect(lua: &Lua, signal: OsSignal, func: LuaFunction) -> LuaResult<SignalConnection> {
+    let handlers = handlers(lua)?;
+    let id = handlers.connect(lua, signal, func)?;
+    Ok(SignalConnection { id, handlers })
+}
+
+pub fn process_on_interrupt(lua: &Lua, func: LuaFunction) -> LuaResult<SignalConnection> {
+    connect(lua, OsSignal::Interrupt, func)
+}
+
+pub fn process_on_terminate(lua: &Lua, func: LuaFunction) -> LuaResult<SignalConnection> {
+    connect(lua, OsSignal::Terminate, func)
+}
+
+pub fn process_is_shutting_down(lua: &Lua, (): ()) -> LuaResult<bool> {
+    Ok(lua.is_shutting_down())
+}
+
+/**
+    Options for `process.shutdown`.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShutdownOptions {
+    timeout: Option<f64>,
+    code: Option<u8>,
+}
+
+impl FromLua for ShutdownOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let timeout = t.get::<Option<f64>>("timeout")?;
+                if timeout.is_some_and(|t| !t.is_finite() || t < 0.0) {
+                    return Err(LuaError::runtime(
+                        "Shutdown timeout must be a non-negative number of seconds",
+                    ));
+                }
+                Ok(Self {
+                    timeout,
+                    code: t.get("code")?,
+                })
+            }
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("ShutdownOptions"),
+                message: Some(String::from("Expected shutdown options to be a table")),
+            }),
+        }
+    }
+}
+
+/**
+    Creates the `process.shutdown` function, which yields the calling thread once
+    the exit code is set, the same way `process.exit` does, so that it does not continue.
+*/
+pub fn create_shutdown(lua: &Lua) -> LuaResult<LuaFunction> {
+    let env = lua.create_table_from(vec![
+        ("shutdown", lua.create_async_function(shutdown_inner)?),
+        (
+            "yield",
+            lua.globals()
+                .get::<LuaTable>("coroutine")?
+                .get::<LuaFunction>("yield")?,
+        ),
+    ])?;
+    lua.load(SHUTDOWN_IMPL_LUA)
+        .set_name("=process.shutdown")
+        .set_environment(env)
+        .into_function()
+}
+
+async fn shutdown_inner(lua: Lua, options: ShutdownOptions) -> LuaResult<()> {
+    let signal = handlers(&lua)?.last_signal();
+
+    // Calling shutdown again while one is already in progress
+    // stops waiting, the same way a second Ctrl+C usually would
+    let timed_out = if lua.is_shutting_down() {
+        true
+    } else {
+        lua.begin_shutdown();
+        let timeout = options.timeout.unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+        let finished = async {
+            lua.wait_for_pending_threads(1).await;
+            false
+        };
+        let elapsed = async {
+            Timer::after(Duration::from_secs_f64(timeout)).await;
+            true
+        };
+        finished.or(elapsed).await
+    };
+
+    let code = match options.code {
+        Some(code) if !timed_out => code,
+        _ => ProcessShutdown::default_code(signal, timed_out),
+    };
+    lua.set_app_data(ProcessShutdown {
+        signal,
+        timed_out,
+        code,
+    });
+    lua.set_exit_code(code);
+
+    Ok(())
+}
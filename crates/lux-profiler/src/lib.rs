@@ -0,0 +1,75 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Profiler zones and trace output for Lux
+
+use lux_utils::{
+    TableBuilder,
+    profiler::{self, ProfileFormat},
+};
+use mlua::prelude::*;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+fn profiler_begin(_: &Lua, name: String) -> LuaResult<()> {
+    profiler::begin_zone(name);
+    Ok(())
+}
+
+fn profiler_finish(_: &Lua, (): ()) -> LuaResult<()> {
+    profiler::end_zone()
+        .map(|_| ())
+        .ok_or_else(|| LuaError::runtime("There is no zone to finish, call profiler.begin first"))
+}
+
+/// Runs a function inside of a zone, which finishes even if the function errors
+fn profiler_zone(
+    _: &Lua,
+    (name, func, args): (String, LuaFunction, LuaMultiValue),
+) -> LuaResult<LuaMultiValue> {
+    profiler::begin_zone(name);
+    let result = func.call(args);
+    profiler::end_zone();
+    result
+}
+
+fn profiler_dump(_: &Lua, format: Option<String>) -> LuaResult<String> {
+    let format = match format {
+        Some(format) => format.parse().map_err(LuaError::runtime)?,
+        None => ProfileFormat::default(),
+    };
+    Ok(profiler::dump(format))
+}
+
+/**
+    Creates the `profiler` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("start", |_, ()| {
+            profiler::start();
+            Ok(())
+        })?
+        .with_function("stop", |_, ()| {
+            profiler::stop();
+            Ok(())
+        })?
+        .with_function("isRunning", |_, ()| Ok(profiler::is_running()))?
+        .with_function("reset", |_, ()| {
+            profiler::reset();
+            Ok(())
+        })?
+        .with_function("begin", profiler_begin)?
+        .with_function("finish", profiler_finish)?
+        .with_function("zone", profiler_zone)?
+        .with_function("dump", profiler_dump)?
+        .build_readonly()
+}
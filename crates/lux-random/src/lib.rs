@@ -0,0 +1,135 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Seeded, forkable random number generators for Lux
+
+use std::f64::consts::TAU;
+
+use lux_utils::TableBuilder;
+use lux_vector::Vector3;
+use mlua::prelude::*;
+
+mod pcg;
+
+use self::pcg::Pcg32;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/// An independent random number generator, similar to Roblox's `Random`
+#[derive(Debug, Clone, Copy)]
+struct Random {
+    rng: Pcg32,
+    /// Second value of the last Box-Muller transform, not yet returned
+    spare_gaussian: Option<f64>,
+}
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Self {
+            rng: Pcg32::new(seed),
+            spare_gaussian: None,
+        }
+    }
+
+    /// Standard normal sample, using the Box-Muller transform
+    fn next_gaussian(&mut self) -> f64 {
+        if let Some(spare) = self.spare_gaussian.take() {
+            return spare;
+        }
+        // 1 - x is in (0, 1], so the logarithm stays finite
+        let radius = (-2.0 * (1.0 - self.rng.next_f64()).ln()).sqrt();
+        let angle = TAU * self.rng.next_f64();
+        self.spare_gaussian = Some(radius * angle.sin());
+        radius * angle.cos()
+    }
+
+    /// Uniform point on the unit sphere
+    fn next_unit_vector(&mut self) -> Vector3 {
+        let z = 2.0 * self.rng.next_f64() - 1.0;
+        let angle = TAU * self.rng.next_f64();
+        let radius = (1.0 - z * z).sqrt();
+        Vector3::new(radius * angle.cos(), radius * angle.sin(), z)
+    }
+}
+
+impl LuaUserData for Random {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method_mut("NextInteger", |_, this, (min, max): (i64, i64)| {
+            if min > max {
+                return Err(LuaError::runtime(format!(
+                    "Invalid range, min ({min}) must not be greater than max ({max})"
+                )));
+            }
+            Ok(this.rng.next_in_range(min, max))
+        });
+
+        methods.add_method_mut(
+            "NextNumber",
+            |_, this, (min, max): (Option<f64>, Option<f64>)| {
+                let (min, max) = (min.unwrap_or(0.0), max.unwrap_or(1.0));
+                Ok(min + (max - min) * this.rng.next_f64())
+            },
+        );
+
+        methods.add_method_mut(
+            "NextGaussian",
+            |_, this, (mean, deviation): (Option<f64>, Option<f64>)| {
+                let (mean, deviation) = (mean.unwrap_or(0.0), deviation.unwrap_or(1.0));
+                Ok(mean + deviation * this.next_gaussian())
+            },
+        );
+
+        methods.add_method_mut("NextUnitVector", |_, this, ()| Ok(this.next_unit_vector()));
+
+        // Fisher-Yates, shuffling the array part of the table in place
+        methods.add_method_mut("Shuffle", |_, this, table: LuaTable| {
+            let len = table.raw_len() as i64;
+            for i in (2..=len).rev() {
+                let j = this.rng.next_in_range(1, i);
+                if i != j {
+                    let a: LuaValue = table.raw_get(i)?;
+                    let b: LuaValue = table.raw_get(j)?;
+                    table.raw_set(i, b)?;
+                    table.raw_set(j, a)?;
+                }
+            }
+            Ok(())
+        });
+
+        // Forks the generator, the clone continues with the same sequence independently
+        methods.add_method("Clone", |_, this, ()| Ok(*this));
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, _, ()| Ok("Random"));
+    }
+}
+
+/// Create a generator, seeded from the operating system if no seed is given
+fn random_new(_: &Lua, seed: Option<f64>) -> LuaResult<Random> {
+    let seed = match seed {
+        Some(seed) if seed.is_finite() => seed.floor() as i64 as u64,
+        Some(seed) => {
+            return Err(LuaError::runtime(format!(
+                "Seed must be a finite number, got {seed}"
+            )));
+        }
+        None => getrandom::u64().map_err(|e| LuaError::external(e.to_string()))?,
+    };
+    Ok(Random::new(seed))
+}
+
+/**
+    Creates the `random` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("new", random_new)?
+        .build_readonly()
+}
@@ -0,0 +1,58 @@
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+/**
+    A PCG32 (XSH-RR) generator.
+
+    The output only depends on the seed, so sequences are
+    reproducible across platforms and versions of Lux.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct Pcg32 {
+    state: u64,
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Self { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        (u64::from(self.next_u32()) << 32) | u64::from(self.next_u32())
+    }
+
+    /// Uniform number in `[0, 1)` with 53 bits of precision
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Uniform integer in `[min, max]`, without modulo bias
+    pub fn next_in_range(&mut self, min: i64, max: i64) -> i64 {
+        debug_assert!(min <= max);
+        let span = max.wrapping_sub(min) as u64;
+        if span == u64::MAX {
+            return self.next_u64() as i64;
+        }
+        let range = span + 1;
+        // Reject the highest values, which would make the lower part of the range more likely
+        let excess = range.wrapping_neg() % range;
+        loop {
+            let value = self.next_u64();
+            if excess == 0 || value < excess.wrapping_neg() {
+                return min.wrapping_add((value % range) as i64);
+            }
+        }
+    }
+}
@@ -0,0 +1,99 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io,
+    rc::Rc,
+    sync::{Arc, Weak},
+};
+
+use mlua::prelude::*;
+
+use lux_ffi::memory::get_ptr_from_value;
+use lux_signal::Signal;
+use lux_utils::TableBuilder;
+
+mod os;
+mod watch;
+
+use self::os::{Interest, Source};
+use self::watch::create_ready_signal;
+
+/// Sources that are registered, by their handle, for as long as any signal uses them
+type Sources = Rc<RefCell<HashMap<usize, Weak<Source>>>>;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `reactor` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `reactor` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let sources: Sources = Rc::default();
+    let writable_sources = Rc::clone(&sources);
+    TableBuilder::new(lua)?
+        .with_function("onReadable", move |_, handle: LuaValue| {
+            reactor_on_ready(&handle, Interest::Readable, &sources)
+        })?
+        .with_function("onWritable", move |_, handle: LuaValue| {
+            reactor_on_ready(&handle, Interest::Writable, &writable_sources)
+        })?
+        .build_readonly()
+}
+
+fn reactor_on_ready(value: &LuaValue, interest: Interest, sources: &Sources) -> LuaResult<Signal> {
+    let handle = handle_from_lua(value)?;
+    let source = source_for(handle, sources)
+        .map_err(|e| LuaError::runtime(format!("Failed to register handle {handle}: {e}")))?;
+    if !source.supports(interest) {
+        return Err(LuaError::runtime(format!(
+            "Handle {handle} can not be waited on to become {}, only sockets can",
+            interest.name()
+        )));
+    }
+    Ok(create_ready_signal(source, interest))
+}
+
+/// Reads a file descriptor or handle, given as a number or as a pointer from `ffi`
+fn handle_from_lua(value: &LuaValue) -> LuaResult<usize> {
+    let handle = match value {
+        LuaValue::Integer(n) => usize::try_from(*n).ok(),
+        LuaValue::Number(n) if n.fract() == 0.0 && *n >= 0.0 => Some(*n as usize),
+        LuaValue::LightUserData(_) | LuaValue::UserData(_) => {
+            Some(get_ptr_from_value(value)? as usize).filter(|&handle| handle != 0)
+        }
+        _ => None,
+    };
+    handle.ok_or_else(|| {
+        LuaError::runtime(format!(
+            "Expected a file descriptor or handle as a non-negative number or pointer, got '{}'",
+            value.type_name()
+        ))
+    })
+}
+
+/// Returns the source for a handle, registering it unless another signal already did
+fn source_for(handle: usize, sources: &Sources) -> io::Result<Arc<Source>> {
+    let mut sources = sources.borrow_mut();
+    // NOTE: A handle can only be registered with the reactor once,
+    // so signals for the same handle have to share its source
+    if let Some(source) = sources.get(&handle).and_then(Weak::upgrade) {
+        return Ok(source);
+    }
+    sources.retain(|_, source| source.strong_count() > 0);
+    let source = Arc::new(Source::register(handle)?);
+    sources.insert(handle, Arc::downgrade(&source));
+    Ok(source)
+}
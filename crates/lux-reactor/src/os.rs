@@ -0,0 +1,160 @@
+pub(crate) use self::imp::Source;
+
+/// What a source is waited on for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Interest {
+    Readable,
+    Writable,
+}
+
+impl Interest {
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::Readable => "readable",
+            Self::Writable => "writable",
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::{
+        io,
+        os::windows::io::{AsSocket, BorrowedSocket, RawSocket},
+        time::Duration,
+    };
+
+    use async_io::{Async, Timer};
+    use futures_lite::FutureExt;
+    use windows_sys::Win32::{
+        Foundation::{HANDLE, WAIT_FAILED, WAIT_OBJECT_0},
+        System::Threading::WaitForSingleObject,
+    };
+
+    use super::Interest;
+
+    /// A socket that belongs to someone else, and is never closed by us
+    #[derive(Debug)]
+    struct Socket(RawSocket);
+
+    impl AsSocket for Socket {
+        fn as_socket(&self) -> BorrowedSocket<'_> {
+            // SAFETY: Handles must stay open while they are registered, as documented for `reactor`
+            unsafe { BorrowedSocket::borrow_raw(self.0) }
+        }
+    }
+
+    pub enum Source {
+        Socket(Async<Socket>),
+        /// Any other handle that can be waited on, such as an event or a process, which is readable once signaled
+        Waitable(usize),
+    }
+
+    impl Source {
+        pub fn register(handle: usize) -> io::Result<Self> {
+            // NOTE: Only sockets can be registered with the reactor, other handles are waited on by a thread
+            match Async::new_nonblocking(Socket(handle as RawSocket)) {
+                Ok(socket) => Ok(Self::Socket(socket)),
+                Err(_) => Ok(Self::Waitable(handle)),
+            }
+        }
+
+        pub fn supports(&self, interest: Interest) -> bool {
+            matches!(self, Self::Socket(_)) || interest == Interest::Readable
+        }
+
+        /**
+            Waits for the source to become ready for at most `timeout`, returning whether it did.
+        */
+        pub async fn wait(&self, interest: Interest, timeout: Duration) -> io::Result<bool> {
+            match self {
+                Self::Socket(socket) => {
+                    let ready = async {
+                        match interest {
+                            Interest::Readable => socket.readable().await,
+                            Interest::Writable => socket.writable().await,
+                        }
+                        .map(|()| true)
+                    };
+                    let timeout = async {
+                        Timer::after(timeout).await;
+                        Ok(false)
+                    };
+                    ready.or(timeout).await
+                }
+                Self::Waitable(handle) => {
+                    let handle = *handle;
+                    let millis = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+                    blocking::unblock(move || {
+                        match unsafe { WaitForSingleObject(handle as HANDLE, millis) } {
+                            WAIT_OBJECT_0 => Ok(true),
+                            WAIT_FAILED => Err(io::Error::last_os_error()),
+                            _ => Ok(false),
+                        }
+                    })
+                    .await
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::{
+        io,
+        os::fd::{AsFd, BorrowedFd, RawFd},
+        time::Duration,
+    };
+
+    use async_io::{Async, Timer};
+    use futures_lite::FutureExt;
+
+    use super::Interest;
+
+    /// A file descriptor that belongs to someone else, and is never closed by us
+    #[derive(Debug)]
+    struct Fd(RawFd);
+
+    impl AsFd for Fd {
+        fn as_fd(&self) -> BorrowedFd<'_> {
+            // SAFETY: Descriptors must stay open while they are registered, as documented for `reactor`
+            unsafe { BorrowedFd::borrow_raw(self.0) }
+        }
+    }
+
+    pub struct Source(Async<Fd>);
+
+    impl Source {
+        pub fn register(handle: usize) -> io::Result<Self> {
+            let fd = RawFd::try_from(handle).map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "not a valid file descriptor")
+            })?;
+            // NOTE: The descriptor is left in blocking mode, since it belongs to whoever created it
+            Async::new_nonblocking(Fd(fd)).map(Self)
+        }
+
+        #[allow(clippy::unused_self)]
+        pub fn supports(&self, _interest: Interest) -> bool {
+            true
+        }
+
+        /**
+            Waits for the source to become ready for at most `timeout`, returning whether it did.
+        */
+        pub async fn wait(&self, interest: Interest, timeout: Duration) -> io::Result<bool> {
+            let ready = async {
+                match interest {
+                    Interest::Readable => self.0.readable().await,
+                    Interest::Writable => self.0.writable().await,
+                }
+                .map(|()| true)
+            };
+            let timeout = async {
+                Timer::after(timeout).await;
+                Ok(false)
+            };
+            ready.or(timeout).await
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use futures_lite::future;
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use lux_signal::Signal;
+
+use crate::os::{Interest, Source};
+
+/// How often to check if handlers are still connected, while the source is not ready
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/**
+    Creates a signal that fires whenever the source is ready for the given interest.
+
+    The source is only waited on while a handler is connected, so that an unused
+    signal never keeps the scheduler alive. Waiting starts again once a handler
+    gets connected after the last one was disconnected.
+*/
+pub(crate) fn create_ready_signal(source: Arc<Source>, interest: Interest) -> Signal {
+    let watching = Arc::new(AtomicBool::new(false));
+    Signal::new().with_connect_hook(Rc::new(move |lua, signal| {
+        if !watching.swap(true, Ordering::SeqCst) {
+            lua.spawn_local(watch(
+                lua.clone(),
+                signal.clone(),
+                Arc::clone(&source),
+                interest,
+                Arc::clone(&watching),
+            ));
+        }
+    }))
+}
+
+async fn watch(
+    lua: Lua,
+    signal: Signal,
+    source: Arc<Source>,
+    interest: Interest,
+    watching: Arc<AtomicBool>,
+) {
+    while signal.count() > 0 {
+        match source.wait(interest, POLL_INTERVAL).await {
+            Ok(true) => {
+                signal.fire_native(&lua, LuaMultiValue::new());
+                // Readiness is level-triggered, so a source that stays ready fires
+                // again right away - let everything else run in between
+                future::yield_now().await;
+            }
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!(
+                    "[REACTOR ERROR] Stopped waiting for handle to become {}: {e}",
+                    interest.name()
+                );
+                break;
+            }
+        }
+    }
+    watching.store(false, Ordering::SeqCst);
+}
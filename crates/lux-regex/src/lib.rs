@@ -33,6 +33,6 @@ pub fn module(lua: Lua) -> LuaResult<LuaTable> {
         .build_readonly()
 }
 
-fn new_regex(_: &Lua, pattern: String) -> LuaResult<LuaRegex> {
-    LuaRegex::new(pattern)
+fn new_regex(_: &Lua, (pattern, flags): (String, Option<String>)) -> LuaResult<LuaRegex> {
+    LuaRegex::new(&pattern, flags.as_deref().unwrap_or_default())
 }
@@ -1,10 +1,129 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+};
 
 use mlua::prelude::*;
-use regex::Regex;
+use regex::{Captures, Regex, RegexBuilder};
 
 use super::{captures::LuaCaptures, matches::LuaMatch};
 
+// NOTE: Compiling a regex is far more expensive than matching with it, and scripts
+// commonly call `regex.new` inside of loops, so we keep compiled patterns around.
+// Cloning a `Regex` is cheap since it is reference counted internally.
+const CACHE_CAPACITY: usize = 256;
+
+static CACHE: LazyLock<Mutex<HashMap<(String, String), Regex>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+fn compile(pattern: &str, flags: &str) -> LuaResult<Regex> {
+    let key = (pattern.to_string(), flags.to_string());
+    if let Some(re) = CACHE.lock().expect("regex cache poisoned").get(&key) {
+        return Ok(re.clone());
+    }
+
+    let mut builder = RegexBuilder::new(pattern);
+    for flag in flags.chars() {
+        match flag {
+            'i' => builder.case_insensitive(true),
+            'm' => builder.multi_line(true),
+            's' => builder.dot_matches_new_line(true),
+            'x' => builder.ignore_whitespace(true),
+            'U' => builder.swap_greed(true),
+            other => {
+                return Err(LuaError::runtime(format!(
+                    "Invalid regex flag '{other}', valid flags are 'i', 'm', 's', 'x' and 'U'"
+                )));
+            }
+        };
+    }
+    let re = builder.build().map_err(LuaError::external)?;
+
+    let mut cache = CACHE.lock().expect("regex cache poisoned");
+    if cache.len() >= CACHE_CAPACITY {
+        cache.clear();
+    }
+    cache.insert(key, re.clone());
+    Ok(re)
+}
+
+/**
+    Converts captures into a table, with numbered groups at their index and
+    named groups under their name. Patterns without any groups put the
+    entire match at index 1, mirroring `string.match`.
+*/
+fn captures_to_table(lua: &Lua, re: &Regex, caps: &Captures) -> LuaResult<LuaTable> {
+    let t = lua.create_table_with_capacity(caps.len(), 0)?;
+    if caps.len() == 1 {
+        t.raw_set(1, caps.get(0).map(|m| m.as_str()))?;
+        return Ok(t);
+    }
+    for (index, name) in re.capture_names().enumerate().skip(1) {
+        // NOTE: Groups that did not participate in the match are left as nil
+        let Some(m) = caps.get(index) else {
+            continue;
+        };
+        t.raw_set(index, m.as_str())?;
+        if let Some(name) = name {
+            t.raw_set(name, m.as_str())?;
+        }
+    }
+    Ok(t)
+}
+
+/**
+    Resolves the replacement for a single match in `gsub`, following `string.gsub`:
+
+    - Strings are expanded, so `$1` or `$name` refer to capture groups
+    - Tables are indexed using the first capture, or the entire match
+    - Functions are called with all captures, or the entire match
+
+    Returning `nil` or `false` from a table or function keeps the original text.
+*/
+fn gsub_replacement(lua: &Lua, replacer: &LuaValue, caps: &Captures) -> LuaResult<String> {
+    let whole = caps.get(0).map_or("", |m| m.as_str());
+    let first = caps.get(1).map_or(whole, |m| m.as_str());
+    let value = match replacer {
+        LuaValue::String(s) => {
+            let mut out = String::new();
+            caps.expand(&s.to_str()?, &mut out);
+            return Ok(out);
+        }
+        LuaValue::Table(t) => t.get::<LuaValue>(first)?,
+        LuaValue::Function(f) => {
+            if caps.len() == 1 {
+                f.call::<LuaValue>(whole)?
+            } else {
+                let args = caps
+                    .iter()
+                    .skip(1)
+                    .map(|m| match m {
+                        Some(m) => lua.create_string(m.as_str()).map(LuaValue::String),
+                        None => Ok(LuaValue::Nil),
+                    })
+                    .collect::<LuaResult<LuaMultiValue>>()?;
+                f.call::<LuaValue>(args)?
+            }
+        }
+        other => {
+            return Err(LuaError::runtime(format!(
+                "Expected replacement to be a string, table or function, got '{}'",
+                other.type_name()
+            )));
+        }
+    };
+    match value {
+        LuaValue::Nil | LuaValue::Boolean(false) => Ok(whole.to_string()),
+        LuaValue::String(s) => Ok(s.to_str()?.to_string()),
+        LuaValue::Integer(i) => Ok(i.to_string()),
+        LuaValue::Number(n) => Ok(n.to_string()),
+        other => Err(LuaError::runtime(format!(
+            "Invalid replacement value of type '{}'",
+            other.type_name()
+        ))),
+    }
+}
+
 /**
     A wrapper over the `regex::Regex` struct that can be used from Lua.
 */
@@ -15,12 +134,12 @@ pub struct LuaRegex {
 
 impl LuaRegex {
     /**
-        Create a new `LuaRegex` instance from a `String` pattern.
+        Create a new `LuaRegex` instance from a pattern and a string of flags.
+
+        Compiled patterns are cached, so creating the same regex repeatedly is cheap.
     */
-    pub fn new(pattern: String) -> LuaResult<Self> {
-        Regex::new(&pattern)
-            .map(|inner| Self { inner })
-            .map_err(LuaError::external)
+    pub fn new(pattern: &str, flags: &str) -> LuaResult<Self> {
+        compile(pattern, flags).map(|inner| Self { inner })
     }
 }
 
@@ -42,6 +161,58 @@ impl LuaUserData for LuaRegex {
             Ok(LuaCaptures::new(&this.inner, text))
         });
 
+        methods.add_method("match", |lua, this, text: String| {
+            this.inner
+                .captures(&text)
+                .map(|caps| captures_to_table(lua, &this.inner, &caps))
+                .transpose()
+        });
+
+        methods.add_method("gmatch", |lua, this, text: String| {
+            let re = this.inner.clone();
+            let mut position = 0;
+            lua.create_function_mut(move |lua, ()| {
+                let Some(caps) = (position <= text.len())
+                    .then(|| re.captures_at(&text, position))
+                    .flatten()
+                else {
+                    return Ok(None);
+                };
+                let m = caps.get(0).expect("capture 0 is always the entire match");
+                // NOTE: Empty matches must still advance, or we would loop forever
+                position = if m.is_empty() {
+                    text[m.end()..]
+                        .chars()
+                        .next()
+                        .map_or(text.len() + 1, |c| m.end() + c.len_utf8())
+                } else {
+                    m.end()
+                };
+                captures_to_table(lua, &re, &caps).map(Some)
+            })
+        });
+
+        methods.add_method(
+            "gsub",
+            |lua, this, (text, replacer, limit): (String, LuaValue, Option<usize>)| {
+                let mut out = String::with_capacity(text.len());
+                let mut last = 0;
+                let mut count = 0;
+                for caps in this.inner.captures_iter(&text) {
+                    if limit.is_some_and(|limit| count >= limit) {
+                        break;
+                    }
+                    let m = caps.get(0).expect("capture 0 is always the entire match");
+                    out.push_str(&text[last..m.start()]);
+                    out.push_str(&gsub_replacement(lua, &replacer, &caps)?);
+                    last = m.end();
+                    count += 1;
+                }
+                out.push_str(&text[last..]);
+                Ok((out, count))
+            },
+        );
+
         methods.add_method("split", |_, this, text: String| {
             Ok(this
                 .inner
@@ -50,8 +221,7 @@ impl LuaUserData for LuaRegex {
                 .collect::<Vec<_>>())
         });
 
-        // TODO: Determine whether it's desirable and / or feasible to support
-        // using a function or table for `replace` like in the lua string library
+        // NOTE: Function and table replacements are supported by `gsub`
         methods.add_method(
             "replace",
             |_, this, (haystack, replacer): (String, String)| {
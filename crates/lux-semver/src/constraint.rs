@@ -0,0 +1,445 @@
+use std::fmt;
+use std::str::FromStr;
+
+use mlua::prelude::*;
+
+use crate::version::{Identifier, Version};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+/**
+    A single primitive comparison against a version, such as `>=1.2.0`.
+*/
+#[derive(Debug, Clone)]
+struct Comparator {
+    op: Op,
+    version: Version,
+    /// If this comparator was added when desugaring, as an upper bound or a wildcard
+    implicit: bool,
+}
+
+impl Comparator {
+    fn new(op: Op, version: Version) -> Self {
+        Self {
+            op,
+            version,
+            implicit: false,
+        }
+    }
+
+    fn implicit(op: Op, version: Version) -> Self {
+        Self {
+            op,
+            version,
+            implicit: true,
+        }
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        match self.op {
+            Op::Exact => *version == self.version,
+            Op::Greater => *version > self.version,
+            Op::GreaterEq => *version >= self.version,
+            Op::Less => *version < self.version,
+            Op::LessEq => *version <= self.version,
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+        };
+        write!(f, "{op}{}", self.version)
+    }
+}
+
+/**
+    A version that may be missing its minor and patch numbers,
+    such as `1.2`, `1.x` or `*`, as used in constraints.
+*/
+#[derive(Debug, Clone)]
+struct Partial {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<Identifier>,
+}
+
+impl Partial {
+    fn parse(s: &str) -> Result<Self, String> {
+        let wildcard = |part: &str| matches!(part, "x" | "X" | "*");
+        // Full versions are parsed as such, to validate pre-releases and build metadata
+        if let Ok(version) = s.parse::<Version>() {
+            return Ok(Self {
+                major: Some(version.major),
+                minor: Some(version.minor),
+                patch: Some(version.patch),
+                pre: version.pre,
+            });
+        }
+        let text = s.strip_prefix('v').unwrap_or(s);
+        let text = text.split_once('+').map_or(text, |(core, _)| core);
+        let mut numbers = [None; 3];
+        let mut parts = text.split('.');
+        for number in &mut numbers {
+            let Some(part) = parts.next() else {
+                break;
+            };
+            if wildcard(part) {
+                break;
+            }
+            *number = Some(
+                part.parse()
+                    .map_err(|_| format!("invalid version '{s}' in constraint"))?,
+            );
+        }
+        if parts.any(|part| !wildcard(part)) {
+            return Err(format!("invalid version '{s}' in constraint"));
+        }
+        Ok(Self {
+            major: numbers[0],
+            minor: numbers[1],
+            patch: numbers[2],
+            pre: Vec::new(),
+        })
+    }
+
+    /// The lowest version matching this partial version
+    fn floor(&self) -> Version {
+        Version {
+            pre: self.pre.clone(),
+            ..Version::new(
+                self.major.unwrap_or(0),
+                self.minor.unwrap_or(0),
+                self.patch.unwrap_or(0),
+            )
+        }
+    }
+}
+
+/// The lowest possible pre-release of a version, used as an exclusive upper bound
+fn lowest(major: u64, minor: u64, patch: u64) -> Version {
+    Version {
+        pre: vec![Identifier::Numeric(0)],
+        ..Version::new(major, minor, patch)
+    }
+}
+
+/**
+    Desugars an operator and a partial version into primitive comparators.
+*/
+fn desugar(op: &str, partial: &Partial) -> Result<Vec<Comparator>, String> {
+    let floor = partial.floor();
+    let Some(major) = partial.major else {
+        // Wildcards match everything, except with operators that exclude everything
+        return Ok(match op {
+            "<" | ">" => vec![Comparator::implicit(Op::Less, lowest(0, 0, 0))],
+            _ => vec![Comparator::implicit(Op::GreaterEq, lowest(0, 0, 0))],
+        });
+    };
+    // The exclusive upper bound for all versions matching the partial version
+    let next = match (partial.minor, partial.patch) {
+        (None, _) => lowest(major + 1, 0, 0),
+        (Some(minor), None) => lowest(major, minor + 1, 0),
+        (Some(_), Some(_)) => floor.clone(),
+    };
+    let exact = partial.patch.is_some();
+
+    Ok(match op {
+        "" | "=" if exact => vec![Comparator::new(Op::Exact, floor)],
+        "" | "=" => vec![
+            Comparator::new(Op::GreaterEq, floor),
+            Comparator::implicit(Op::Less, next),
+        ],
+        ">" if exact => vec![Comparator::new(Op::Greater, floor)],
+        ">" => vec![Comparator::implicit(Op::GreaterEq, next)],
+        ">=" => vec![Comparator::new(Op::GreaterEq, floor)],
+        "<" => vec![Comparator::new(Op::Less, floor)],
+        "<=" if exact => vec![Comparator::new(Op::LessEq, floor)],
+        "<=" => vec![Comparator::implicit(Op::Less, next)],
+        "~" => {
+            let upper = match partial.minor {
+                Some(minor) => lowest(major, minor + 1, 0),
+                None => lowest(major + 1, 0, 0),
+            };
+            vec![
+                Comparator::new(Op::GreaterEq, floor),
+                Comparator::implicit(Op::Less, upper),
+            ]
+        }
+        "^" => {
+            // Caret ranges allow changes that do not modify the left-most non-zero number
+            let upper = match (major, partial.minor, partial.patch) {
+                (0, Some(0), Some(patch)) => lowest(0, 0, patch + 1),
+                (0, Some(minor), _) => lowest(0, minor + 1, 0),
+                _ => lowest(major + 1, 0, 0),
+            };
+            vec![
+                Comparator::new(Op::GreaterEq, floor),
+                Comparator::implicit(Op::Less, upper),
+            ]
+        }
+        _ => return Err(format!("invalid operator '{op}' in constraint")),
+    })
+}
+
+fn split_op(s: &str) -> (&str, &str) {
+    for op in [">=", "<=", ">", "<", "=", "~>", "~", "^"] {
+        if let Some(rest) = s.strip_prefix(op) {
+            // NOTE: "~>" is a common alias for "~", used by Ruby gems and others
+            let op = if op == "~>" { "~" } else { op };
+            return (op, rest.trim_start());
+        }
+    }
+    ("", s)
+}
+
+/**
+    Parses a set of comparators that must all match, separated by commas or whitespace.
+*/
+fn parse_set(s: &str) -> Result<Vec<Comparator>, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return desugar("", &Partial::parse("*")?);
+    }
+
+    // Hyphen ranges such as "1.2 - 2.3.4" include both ends
+    if let Some((low, high)) = s.split_once(" - ") {
+        let low = Partial::parse(low.trim())?;
+        let high = Partial::parse(high.trim())?;
+        let mut comparators = desugar(">=", &low)?;
+        comparators.extend(desugar("<=", &high)?);
+        return Ok(comparators);
+    }
+
+    // NOTE: Operators may be separated from their versions with whitespace,
+    // so we join them back together before splitting the comparators apart
+    let mut tokens = Vec::new();
+    let mut pending_op = None;
+    for token in s.split(|c: char| c == ',' || c.is_whitespace()) {
+        if token.is_empty() {
+            continue;
+        }
+        let (op, rest) = split_op(token);
+        if rest.is_empty() {
+            if pending_op.is_some() || op.is_empty() {
+                return Err(format!("invalid constraint '{s}'"));
+            }
+            pending_op = Some(op);
+            continue;
+        }
+        match pending_op.take() {
+            Some(pending) if op.is_empty() => tokens.push((pending, rest)),
+            Some(_) => return Err(format!("invalid constraint '{s}'")),
+            None => tokens.push((op, rest)),
+        }
+    }
+    if pending_op.is_some() {
+        return Err(format!("constraint '{s}' ends with an operator"));
+    }
+
+    let mut comparators = Vec::new();
+    for (op, version) in tokens {
+        comparators.extend(desugar(op, &Partial::parse(version)?)?);
+    }
+    Ok(comparators)
+}
+
+/**
+    A version constraint, such as `^1.2.0` or `>=2, <3 || 4.x`.
+
+    Sets of comparators are separated by `||`, and a version matches the constraint
+    if it matches every comparator in any of the sets. Supports the operators `=`, `>`,
+    `>=`, `<`, `<=`, tilde (`~`) and caret (`^`) ranges, wildcards and hyphen ranges.
+
+    Pre-release versions only match if a comparator in the same set
+    refers to a pre-release of the same major, minor and patch version.
+*/
+#[derive(Debug, Clone)]
+pub struct Constraint {
+    source: String,
+    sets: Vec<Vec<Comparator>>,
+}
+
+impl Constraint {
+    #[must_use]
+    pub fn matches(&self, version: &Version) -> bool {
+        self.sets.iter().any(|set| {
+            set.iter().all(|c| c.matches(version))
+                && (!version.is_prerelease()
+                    || set.iter().any(|c| {
+                        // Bounds added when desugaring never allow pre-releases
+                        !c.implicit
+                            && c.version.is_prerelease()
+                            && (c.version.major, c.version.minor, c.version.patch)
+                                == (version.major, version.minor, version.patch)
+                    }))
+        })
+    }
+}
+
+impl FromStr for Constraint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sets = s.split("||").map(parse_set).collect::<Result<_, _>>()?;
+        Ok(Self {
+            source: s.trim().to_string(),
+            sets,
+        })
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl FromLua for Constraint {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match &value {
+            LuaValue::UserData(ud) => Ok(ud.borrow::<Self>()?.clone()),
+            LuaValue::String(s) => s.to_str()?.parse().map_err(LuaError::runtime),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Constraint".to_string(),
+                message: Some("expected a Constraint or a constraint string".to_string()),
+            }),
+        }
+    }
+}
+
+impl LuaUserData for Constraint {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Constraint");
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("matches", |_, this, version: Version| {
+            Ok(this.matches(&version))
+        });
+        methods.add_method("comparators", |lua, this, (): ()| {
+            let sets = this
+                .sets
+                .iter()
+                .map(|set| {
+                    let set = set.iter().map(ToString::to_string).collect::<Vec<_>>();
+                    lua.create_sequence_from(set)
+                })
+                .collect::<LuaResult<Vec<_>>>()?;
+            lua.create_sequence_from(sets)
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(this.to_string())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(constraint: &str, version: &str) -> bool {
+        let constraint: Constraint = constraint.parse().unwrap();
+        constraint.matches(&version.parse().unwrap())
+    }
+
+    #[test]
+    fn matches_ranges() {
+        let cases = [
+            ("1.2.3", "1.2.3", true),
+            ("=1.2.3", "1.2.4", false),
+            ("1.2", "1.2.9", true),
+            ("1.2", "1.3.0", false),
+            ("1", "1.9.9", true),
+            ("1.x", "2.0.0", false),
+            ("*", "3.4.5", true),
+            ("", "0.0.0", true),
+            (">1.2.3", "1.2.4", true),
+            (">1.2", "1.2.9", false),
+            (">1.2", "1.3.0", true),
+            (">=1.2", "1.2.0", true),
+            ("<1.2", "1.1.9", true),
+            ("<1.2", "1.2.0", false),
+            ("<=1.2", "1.2.9", true),
+            ("<=1.2.3", "1.2.4", false),
+            ("~1.2.3", "1.2.9", true),
+            ("~1.2.3", "1.3.0", false),
+            ("~1", "1.9.0", true),
+            ("~> 1.2", "1.2.5", true),
+            ("^1.2.3", "1.9.9", true),
+            ("^1.2.3", "2.0.0", false),
+            ("^1.2.3", "1.2.2", false),
+            ("^0.2.3", "0.2.9", true),
+            ("^0.2.3", "0.3.0", false),
+            ("^0.0.3", "0.0.4", false),
+            ("^0.0", "0.0.9", true),
+            ("^0.0", "0.1.0", false),
+            ("^0", "0.9.0", true),
+            (">=2, <3", "2.5.0", true),
+            (">=2, <3", "3.0.0", false),
+            (">= 2 < 3", "2.0.0", true),
+            (">=2 <3 || 4.x", "4.1.0", true),
+            (">=2 <3 || 4.x", "3.1.0", false),
+            ("1.2 - 2.3.4", "2.3.4", true),
+            ("1.2 - 2.3.4", "2.3.5", false),
+            ("1.2 - 2.3", "2.3.9", true),
+            ("1.2 - 2.3", "1.1.9", false),
+        ];
+        for (constraint, version, expected) in cases {
+            assert_eq!(
+                matches(constraint, version),
+                expected,
+                "'{version}' matching '{constraint}'"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_prereleases() {
+        let cases = [
+            ("^1.2.3", "1.5.0-beta", false),
+            ("^1.2.3", "2.0.0-alpha", false),
+            ("^1.2.3-alpha", "1.2.3-beta", true),
+            ("^1.2.3-alpha", "1.2.3", true),
+            ("^1.2.3-beta", "1.2.3-alpha", false),
+            ("^1.2.3-alpha", "1.2.4-beta", false),
+            (">=1.0.0-rc.1", "1.0.0-rc.2", true),
+            (">=1.0.0-rc.1", "1.0.0-rc.0", false),
+            ("<2", "2.0.0-rc.1", false),
+            ("<2", "1.9.0-rc.1", false),
+            ("*", "1.0.0-rc.1", false),
+            ("1.0.0-rc.1", "1.0.0-rc.1+build", true),
+        ];
+        for (constraint, version, expected) in cases {
+            assert_eq!(
+                matches(constraint, version),
+                expected,
+                "'{version}' matching '{constraint}'"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        for s in [
+            ">=", "1.2.3 >=", "^^1", ">= <1", "1.2.3.4", "abc", "1.x.3", "1.2 -",
+        ] {
+            assert!(s.parse::<Constraint>().is_err(), "'{s}' should be invalid");
+        }
+    }
+}
@@ -0,0 +1,107 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod constraint;
+mod version;
+
+pub use self::constraint::Constraint;
+pub use self::version::{BumpKind, Identifier, Version};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `semver` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `semver` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("parse", semver_parse)?
+        .with_function("isValid", semver_is_valid)?
+        .with_function("constraint", semver_constraint)?
+        .with_function("satisfies", semver_satisfies)?
+        .with_function("compare", semver_compare)?
+        .with_function("sort", semver_sort)?
+        .with_function("maxSatisfying", semver_max_satisfying)?
+        .with_function("minSatisfying", semver_min_satisfying)?
+        .build_readonly()
+}
+
+fn semver_parse(_: &Lua, version: Version) -> LuaResult<Version> {
+    Ok(version)
+}
+
+fn semver_is_valid(_: &Lua, version: String) -> LuaResult<bool> {
+    Ok(version.parse::<Version>().is_ok())
+}
+
+fn semver_constraint(_: &Lua, constraint: Constraint) -> LuaResult<Constraint> {
+    Ok(constraint)
+}
+
+fn semver_satisfies(_: &Lua, (version, constraint): (Version, Constraint)) -> LuaResult<bool> {
+    Ok(constraint.matches(&version))
+}
+
+fn semver_compare(_: &Lua, (a, b): (Version, Version)) -> LuaResult<i32> {
+    Ok(a.cmp(&b) as i32)
+}
+
+/**
+    Parses every item of an array of versions or version strings,
+    keeping the original values around to return them as given.
+*/
+fn parse_versions(lua: &Lua, versions: &LuaTable) -> LuaResult<Vec<(Version, LuaValue)>> {
+    versions
+        .sequence_values::<LuaValue>()
+        .map(|value| {
+            let value = value?;
+            Ok((Version::from_lua(value.clone(), lua)?, value))
+        })
+        .collect()
+}
+
+fn semver_sort(lua: &Lua, (versions, descending): (LuaTable, Option<bool>)) -> LuaResult<LuaTable> {
+    let mut parsed = parse_versions(lua, &versions)?;
+    if descending.unwrap_or_default() {
+        parsed.sort_by(|(a, _), (b, _)| b.cmp(a));
+    } else {
+        parsed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    lua.create_sequence_from(parsed.into_iter().map(|(_, value)| value))
+}
+
+fn semver_max_satisfying(
+    lua: &Lua,
+    (versions, constraint): (LuaTable, Constraint),
+) -> LuaResult<LuaValue> {
+    Ok(parse_versions(lua, &versions)?
+        .into_iter()
+        .filter(|(version, _)| constraint.matches(version))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map_or(LuaValue::Nil, |(_, value)| value))
+}
+
+fn semver_min_satisfying(
+    lua: &Lua,
+    (versions, constraint): (LuaTable, Constraint),
+) -> LuaResult<LuaValue> {
+    Ok(parse_versions(lua, &versions)?
+        .into_iter()
+        .filter(|(version, _)| constraint.matches(version))
+        .min_by(|(a, _), (b, _)| a.cmp(b))
+        .map_or(LuaValue::Nil, |(_, value)| value))
+}
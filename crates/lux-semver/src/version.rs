@@ -0,0 +1,492 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use mlua::prelude::*;
+
+use crate::constraint::Constraint;
+
+/**
+    A single dot-separated identifier in the pre-release part of a version.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    Numeric(u64),
+    AlphaNumeric(String),
+}
+
+impl Identifier {
+    pub(crate) fn parse(s: &str) -> Result<Self, String> {
+        if s.is_empty() {
+            return Err("pre-release identifiers can not be empty".to_string());
+        }
+        if !s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+            return Err(format!("invalid character in pre-release identifier '{s}'"));
+        }
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            if s.len() > 1 && s.starts_with('0') {
+                return Err(format!(
+                    "numeric pre-release identifier '{s}' can not have leading zeros"
+                ));
+            }
+            return s
+                .parse()
+                .map(Self::Numeric)
+                .map_err(|_| format!("numeric pre-release identifier '{s}' is too large"));
+        }
+        Ok(Self::AlphaNumeric(s.to_string()))
+    }
+}
+
+impl Ord for Identifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Numeric identifiers always have lower precedence than alphanumeric ones
+        match (self, other) {
+            (Self::Numeric(a), Self::Numeric(b)) => a.cmp(b),
+            (Self::Numeric(_), Self::AlphaNumeric(_)) => Ordering::Less,
+            (Self::AlphaNumeric(_), Self::Numeric(_)) => Ordering::Greater,
+            (Self::AlphaNumeric(a), Self::AlphaNumeric(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Numeric(n) => write!(f, "{n}"),
+            Self::AlphaNumeric(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+impl IntoLua for Identifier {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        match self {
+            Self::Numeric(n) => Ok(LuaValue::Number(n as f64)),
+            Self::AlphaNumeric(s) => s.into_lua(lua),
+        }
+    }
+}
+
+/**
+    The part of a version to increase when bumping it.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BumpKind {
+    Major,
+    Minor,
+    Patch,
+    Prerelease,
+}
+
+impl FromLua for BumpKind {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        if let LuaValue::String(s) = &value {
+            match &*s.to_string_lossy() {
+                "major" => return Ok(Self::Major),
+                "minor" => return Ok(Self::Minor),
+                "patch" => return Ok(Self::Patch),
+                "prerelease" => return Ok(Self::Prerelease),
+                _ => {}
+            }
+        }
+        Err(LuaError::FromLuaConversionError {
+            from: value.type_name(),
+            to: "BumpKind".to_string(),
+            message: Some("expected one of 'major', 'minor', 'patch' or 'prerelease'".to_string()),
+        })
+    }
+}
+
+/**
+    A semantic version, as described by <https://semver.org>.
+
+    Build metadata is kept when parsing and formatting, but is ignored
+    when comparing versions, including when checking them for equality.
+*/
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<Identifier>,
+    pub build: Vec<String>,
+}
+
+impl Version {
+    #[must_use]
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            pre: Vec::new(),
+            build: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /**
+        Returns a new version with the given part increased.
+
+        Bumping a pre-release to the release it precedes only removes the pre-release,
+        so `1.0.0-beta` bumps to `1.0.0` as a major release, and `1.2.4-rc.1` bumps to
+        `1.2.4` as a patch release. Pre-release bumps increase the last numeric identifier,
+        or start a new pre-release of the next patch version at `0`, using the given
+        identifier as a prefix if any. Build metadata is always removed.
+    */
+    #[must_use]
+    pub fn bump(&self, kind: BumpKind, prefix: Option<Identifier>) -> Self {
+        let mut next = Self::new(self.major, self.minor, self.patch);
+        let pre = self.is_prerelease();
+        match kind {
+            BumpKind::Major => {
+                if !(pre && self.minor == 0 && self.patch == 0) {
+                    next = Self::new(self.major + 1, 0, 0);
+                }
+            }
+            BumpKind::Minor => {
+                if !(pre && self.patch == 0) {
+                    next = Self::new(self.major, self.minor + 1, 0);
+                }
+            }
+            BumpKind::Patch => {
+                if !pre {
+                    next.patch += 1;
+                }
+            }
+            BumpKind::Prerelease => {
+                let same_prefix = prefix.is_none() || self.pre.first() == prefix.as_ref();
+                if pre && same_prefix {
+                    next.pre.clone_from(&self.pre);
+                    match next.pre.last_mut() {
+                        Some(Identifier::Numeric(n)) => *n += 1,
+                        _ => next.pre.push(Identifier::Numeric(0)),
+                    }
+                } else {
+                    if !pre {
+                        next.patch += 1;
+                    }
+                    next.pre.extend(prefix);
+                    next.pre.push(Identifier::Numeric(0));
+                }
+            }
+        }
+        next
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let text = trimmed.strip_prefix('v').unwrap_or(trimmed);
+        let (rest, build) = match text.split_once('+') {
+            Some((rest, build)) => (rest, Some(build)),
+            None => (text, None),
+        };
+        let (core, pre) = match rest.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (rest, None),
+        };
+
+        let mut parts = core.split('.');
+        let mut number = |name: &str| -> Result<u64, String> {
+            let part = parts
+                .next()
+                .ok_or_else(|| format!("version '{s}' is missing a {name} number"))?;
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(format!("invalid {name} number '{part}' in version '{s}'"));
+            }
+            if part.len() > 1 && part.starts_with('0') {
+                return Err(format!(
+                    "{name} number '{part}' in version '{s}' can not have leading zeros"
+                ));
+            }
+            part.parse()
+                .map_err(|_| format!("{name} number '{part}' in version '{s}' is too large"))
+        };
+        let major = number("major")?;
+        let minor = number("minor")?;
+        let patch = number("patch")?;
+        if parts.next().is_some() {
+            return Err(format!("version '{s}' has more than three numbers"));
+        }
+
+        let pre = match pre {
+            None => Vec::new(),
+            Some(pre) => pre
+                .split('.')
+                .map(Identifier::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("{e} in version '{s}'"))?,
+        };
+        let build = match build {
+            None => Vec::new(),
+            Some(build) => build
+                .split('.')
+                .map(|id| {
+                    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+                    {
+                        Err(format!(
+                            "invalid build metadata identifier '{id}' in version '{s}'"
+                        ))
+                    } else {
+                        Ok(id.to_string())
+                    }
+                })
+                .collect::<Result<_, _>>()?,
+        };
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            pre,
+            build,
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        for (index, id) in self.pre.iter().enumerate() {
+            write!(f, "{}{id}", if index == 0 { '-' } else { '.' })?;
+        }
+        for (index, id) in self.build.iter().enumerate() {
+            write!(f, "{}{id}", if index == 0 { '+' } else { '.' })?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A pre-release always has lower precedence than its release
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                // Larger sets of identifiers have higher precedence, if all previous are equal,
+                // which is exactly how slices are compared lexicographically
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl FromLua for Version {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match &value {
+            LuaValue::UserData(ud) => Ok(ud.borrow::<Self>()?.clone()),
+            LuaValue::String(s) => s.to_str()?.parse().map_err(LuaError::runtime),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "Version".to_string(),
+                message: Some("expected a Version or a version string".to_string()),
+            }),
+        }
+    }
+}
+
+impl LuaUserData for Version {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Version");
+        fields.add_field_method_get("major", |_, this| Ok(this.major));
+        fields.add_field_method_get("minor", |_, this| Ok(this.minor));
+        fields.add_field_method_get("patch", |_, this| Ok(this.patch));
+        fields.add_field_method_get("prerelease", |lua, this| {
+            lua.create_sequence_from(this.pre.iter().cloned())
+        });
+        fields.add_field_method_get("build", |lua, this| {
+            lua.create_sequence_from(this.build.iter().map(String::as_str))
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("isPrerelease", |_, this, (): ()| Ok(this.is_prerelease()));
+        methods.add_method(
+            "bump",
+            |_, this, (kind, prefix): (BumpKind, Option<String>)| {
+                let prefix = prefix
+                    .map(|p| Identifier::parse(&p))
+                    .transpose()
+                    .map_err(LuaError::runtime)?;
+                Ok(this.bump(kind, prefix))
+            },
+        );
+        methods.add_method(
+            "compare",
+            |_, this, other: Self| Ok(this.cmp(&other) as i32),
+        );
+        methods.add_method("satisfies", |_, this, constraint: Constraint| {
+            Ok(constraint.matches(this))
+        });
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: Self| Ok(*this == other));
+        methods.add_meta_method(LuaMetaMethod::Lt, |_, this, other: Self| Ok(*this < other));
+        methods.add_meta_method(LuaMetaMethod::Le, |_, this, other: Self| Ok(*this <= other));
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(this.to_string())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_and_formats() {
+        for s in [
+            "0.0.0",
+            "1.2.3",
+            "10.20.30",
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-0.3.7",
+            "1.0.0-x.7.z.92",
+            "1.0.0-x-y-z.--",
+            "1.0.0+20130313144700",
+            "1.0.0-beta+exp.sha.5114f85",
+            "1.0.0+21AF26D3----117B344092BD",
+        ] {
+            assert_eq!(v(s).to_string(), s);
+        }
+        assert_eq!(v("v1.2.3").to_string(), "1.2.3");
+        assert_eq!(v(" 1.2.3 ").to_string(), "1.2.3");
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        for s in [
+            "",
+            "1",
+            "1.2",
+            "1.2.3.4",
+            "01.2.3",
+            "1.02.3",
+            "1.2.03",
+            "1.2.3-",
+            "1.2.3-01",
+            "1.2.3-alpha..1",
+            "1.2.3-alpha_1",
+            "1.2.3+",
+            "1.2.3+build..1",
+            "1.2.x",
+            "-1.2.3",
+            "a.b.c",
+            "99999999999999999999.0.0",
+        ] {
+            assert!(s.parse::<Version>().is_err(), "'{s}' should be invalid");
+        }
+    }
+
+    #[test]
+    fn orders_by_precedence() {
+        // The example ordering given in the semver specification, plus some extra cases
+        let ordered = [
+            "0.0.0",
+            "0.0.1",
+            "0.1.0",
+            "1.0.0-0",
+            "1.0.0-1",
+            "1.0.0-2",
+            "1.0.0-10",
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.2",
+            "1.0.0-alpha.10",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.1.0",
+            "1.10.0",
+            "2.0.0",
+        ];
+        for (i, a) in ordered.iter().enumerate() {
+            for (j, b) in ordered.iter().enumerate() {
+                assert_eq!(v(a).cmp(&v(b)), i.cmp(&j), "comparing '{a}' and '{b}'");
+            }
+        }
+    }
+
+    #[test]
+    fn ignores_build_metadata() {
+        assert_eq!(v("1.0.0+a"), v("1.0.0+b"));
+        assert_eq!(v("1.0.0-rc.1+build.1"), v("1.0.0-rc.1"));
+        assert!(v("1.0.0-rc.1+zzz") < v("1.0.0+aaa"));
+        assert!(v("1.0.0-alpha+999") < v("1.0.0-alpha.0+1"));
+    }
+
+    #[test]
+    fn bumps() {
+        let cases = [
+            ("1.2.3", BumpKind::Major, None, "2.0.0"),
+            ("1.2.3", BumpKind::Minor, None, "1.3.0"),
+            ("1.2.3", BumpKind::Patch, None, "1.2.4"),
+            ("1.2.3+build", BumpKind::Patch, None, "1.2.4"),
+            ("2.0.0-rc.1", BumpKind::Major, None, "2.0.0"),
+            ("2.1.0-rc.1", BumpKind::Major, None, "3.0.0"),
+            ("1.3.0-rc.1", BumpKind::Minor, None, "1.3.0"),
+            ("1.3.1-rc.1", BumpKind::Minor, None, "1.4.0"),
+            ("1.2.4-rc.1", BumpKind::Patch, None, "1.2.4"),
+            ("1.2.3", BumpKind::Prerelease, None, "1.2.4-0"),
+            ("1.2.3", BumpKind::Prerelease, Some("beta"), "1.2.4-beta.0"),
+            ("1.2.4-0", BumpKind::Prerelease, None, "1.2.4-1"),
+            ("1.2.4-beta.1", BumpKind::Prerelease, None, "1.2.4-beta.2"),
+            (
+                "1.2.4-beta.1",
+                BumpKind::Prerelease,
+                Some("beta"),
+                "1.2.4-beta.2",
+            ),
+            ("1.2.4-beta", BumpKind::Prerelease, None, "1.2.4-beta.0"),
+            (
+                "1.2.4-alpha.3",
+                BumpKind::Prerelease,
+                Some("beta"),
+                "1.2.4-beta.0",
+            ),
+        ];
+        for (from, kind, prefix, expected) in cases {
+            let bumped = v(from).bump(kind, prefix.map(|p| Identifier::parse(p).unwrap()));
+            assert_eq!(bumped.to_string(), expected, "bumping '{from}' ({kind:?})");
+            assert!(bumped > v(from), "bumped '{from}' should be greater");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::path::PathBuf;
+
+use mlua::prelude::*;
+
+use lux_utils::{TableBuilder, process::ProcessPermissions};
+
+mod request;
+mod response;
+mod router;
+mod server;
+
+pub use self::response::{FileBody, Upgrade};
+pub use self::router::Router;
+pub use self::server::Server;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `serve` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `serve` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("listen", server::listen)?
+        .with_function("router", serve_router)?
+        .with_function("file", serve_file)?
+        .with_function("upgrade", serve_upgrade)?
+        .build_readonly()
+}
+
+fn serve_router(_: &Lua, (): ()) -> LuaResult<Router> {
+    Ok(Router::default())
+}
+
+fn serve_file(lua: &Lua, path: String) -> LuaResult<FileBody> {
+    let path = PathBuf::from(path);
+    ProcessPermissions::check_path(lua, &path, "serve.file")?;
+    Ok(FileBody(path))
+}
+
+fn serve_upgrade(_: &Lua, callback: LuaFunction) -> LuaResult<Upgrade> {
+    Ok(Upgrade(callback))
+}
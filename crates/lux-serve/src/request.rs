@@ -0,0 +1,357 @@
+use std::{
+    io::Error,
+    net::{SocketAddr, TcpStream},
+};
+
+use async_io::Async;
+use futures_lite::{io::BufReader, prelude::*};
+
+use mlua::prelude::*;
+
+/// The largest request line and headers we are willing to buffer
+const MAX_HEAD_LEN: u64 = 64 * 1024;
+/// The longest line describing the size of a chunk in a chunked body
+const MAX_CHUNK_LINE_LEN: u64 = 1024;
+
+pub(crate) type Connection = BufReader<Async<TcpStream>>;
+
+/**
+    Why a request could not be read.
+
+    Requests that are malformed are answered with the given status before closing
+    the connection, while connections that fail or close are closed right away.
+*/
+#[derive(Debug)]
+pub(crate) enum RequestError {
+    /// The connection failed or was closed before the request was read
+    Closed,
+    Status(u16, &'static str),
+}
+
+impl From<Error> for RequestError {
+    fn from(_: Error) -> Self {
+        Self::Closed
+    }
+}
+
+/// A request that has been read in full, including its body
+#[derive(Debug)]
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    /// Headers with lowercase names, in the order they were received
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub is_http10: bool,
+    pub keep_alive: bool,
+}
+
+impl Request {
+    /// Returns the value of the first header with the given lowercase name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn header_has_token(&self, name: &str, token: &str) -> bool {
+        self.headers
+            .iter()
+            .filter(|(n, _)| n == name)
+            .flat_map(|(_, v)| v.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    }
+
+    /// Returns `true` if this is a valid request to upgrade the connection to a websocket
+    pub fn is_websocket_upgrade(&self) -> bool {
+        self.method == "GET"
+            && self.header_has_token("upgrade", "websocket")
+            && self.header_has_token("connection", "upgrade")
+            && self.header("sec-websocket-version") == Some("13")
+            && self.header("sec-websocket-key").is_some()
+    }
+
+    /**
+        Creates the request table passed to handlers.
+
+        Headers that were received more than once are joined with commas.
+    */
+    pub fn to_table(
+        &self,
+        lua: &Lua,
+        peer: SocketAddr,
+        params: &[(String, String)],
+    ) -> LuaResult<LuaTable> {
+        let headers = lua.create_table()?;
+        for (name, value) in &self.headers {
+            let value = match headers.raw_get::<Option<String>>(name.as_str())? {
+                Some(existing) => format!("{existing}, {value}"),
+                None => value.clone(),
+            };
+            headers.raw_set(name.as_str(), value)?;
+        }
+        let query = lua.create_table()?;
+        for (name, value) in &self.query {
+            query.raw_set(name.as_str(), value.as_str())?;
+        }
+        let params_table = lua.create_table()?;
+        for (name, value) in params {
+            params_table.raw_set(name.as_str(), value.as_str())?;
+        }
+
+        let table = lua.create_table()?;
+        table.raw_set("method", self.method.as_str())?;
+        table.raw_set("path", self.path.as_str())?;
+        table.raw_set("query", query)?;
+        table.raw_set("params", params_table)?;
+        table.raw_set("headers", headers)?;
+        table.raw_set("body", lua.create_string(&self.body)?)?;
+        table.raw_set("remoteAddress", peer.ip().to_string())?;
+        Ok(table)
+    }
+}
+
+/**
+    Reads a single request from the connection, answering
+    `Expect: 100-continue` before reading the body.
+*/
+pub(crate) async fn read_request(
+    conn: &mut Connection,
+    max_body_size: usize,
+) -> Result<Request, RequestError> {
+    let lines = read_head(conn).await?;
+    let (request_line, header_lines) = lines.split_first().ok_or(RequestError::Status(
+        400,
+        "Request is missing a request line",
+    ))?;
+
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(RequestError::Status(400, "Malformed request line"));
+    };
+    let is_http10 = match version {
+        "HTTP/1.1" => false,
+        "HTTP/1.0" => true,
+        _ => {
+            return Err(RequestError::Status(
+                505,
+                "Only HTTP/1.0 and HTTP/1.1 are supported",
+            ));
+        }
+    };
+    if method.is_empty() || !method.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return Err(RequestError::Status(400, "Malformed request method"));
+    }
+
+    let mut headers = Vec::with_capacity(header_lines.len());
+    for line in header_lines {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(RequestError::Status(400, "Malformed request header"));
+        };
+        if name.is_empty() || name.ends_with(char::is_whitespace) {
+            return Err(RequestError::Status(400, "Malformed request header"));
+        }
+        headers.push((name.to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    let (path, query) = parse_target(target)?;
+    let mut request = Request {
+        method: method.to_ascii_uppercase(),
+        path,
+        query,
+        headers,
+        body: Vec::new(),
+        is_http10,
+        keep_alive: false,
+    };
+    // NOTE: HTTP/1.0 connections are always closed, since keeping
+    // them alive needs headers that old clients may not understand
+    request.keep_alive = !is_http10 && !request.header_has_token("connection", "close");
+
+    request.body = read_body(conn, &request, max_body_size).await?;
+    Ok(request)
+}
+
+/// Reads the request line and headers, without their line endings
+async fn read_head(conn: &mut Connection) -> Result<Vec<String>, RequestError> {
+    let mut head = (&mut *conn).take(MAX_HEAD_LEN);
+    let mut lines = Vec::new();
+    loop {
+        let mut line = Vec::new();
+        head.read_until(b'\n', &mut line).await?;
+        if !line.ends_with(b"\n") {
+            return Err(if head.limit() == 0 {
+                RequestError::Status(431, "Request headers are too large")
+            } else {
+                RequestError::Closed
+            });
+        }
+        line.pop();
+        if line.ends_with(b"\r") {
+            line.pop();
+        }
+        if line.is_empty() {
+            // NOTE: Empty lines before the request line should be ignored, as per RFC 9112
+            if lines.is_empty() {
+                continue;
+            }
+            return Ok(lines);
+        }
+        let line = String::from_utf8(line)
+            .map_err(|_| RequestError::Status(400, "Request headers must be valid UTF-8"))?;
+        lines.push(line);
+    }
+}
+
+async fn read_body(
+    conn: &mut Connection,
+    request: &Request,
+    max_body_size: usize,
+) -> Result<Vec<u8>, RequestError> {
+    let chunked = match request.header("transfer-encoding") {
+        None => false,
+        Some(encoding) if encoding.eq_ignore_ascii_case("chunked") => true,
+        Some(_) => {
+            return Err(RequestError::Status(
+                501,
+                "Only chunked transfer encoding is supported",
+            ));
+        }
+    };
+    let length = if chunked {
+        None
+    } else {
+        match request.header("content-length") {
+            None => Some(0),
+            Some(value) => Some(
+                value
+                    .parse::<usize>()
+                    .map_err(|_| RequestError::Status(400, "Malformed content length"))?,
+            ),
+        }
+    };
+
+    if length.is_some_and(|len| len > max_body_size) {
+        return Err(RequestError::Status(413, "Request body is too large"));
+    }
+    if length != Some(0)
+        && !request.is_http10
+        && request
+            .header("expect")
+            .is_some_and(|e| e.eq_ignore_ascii_case("100-continue"))
+    {
+        conn.get_mut()
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .await?;
+    }
+
+    match length {
+        Some(len) => {
+            let mut body = vec![0u8; len];
+            conn.read_exact(&mut body).await?;
+            Ok(body)
+        }
+        None => read_chunked_body(conn, max_body_size).await,
+    }
+}
+
+async fn read_chunked_body(
+    conn: &mut Connection,
+    max_body_size: usize,
+) -> Result<Vec<u8>, RequestError> {
+    let mut body = Vec::new();
+    loop {
+        let line = read_chunk_line(conn).await?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| RequestError::Status(400, "Malformed chunk size"))?;
+        if size == 0 {
+            // Trailers are allowed after the last chunk, but we have no use for them
+            while !read_chunk_line(conn).await?.is_empty() {}
+            return Ok(body);
+        }
+
+        if size > max_body_size.saturating_sub(body.len()) {
+            return Err(RequestError::Status(413, "Request body is too large"));
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        conn.read_exact(&mut body[start..]).await?;
+
+        let mut end = [0u8; 2];
+        conn.read_exact(&mut end).await?;
+        if &end != b"\r\n" {
+            return Err(RequestError::Status(400, "Malformed chunk"));
+        }
+    }
+}
+
+async fn read_chunk_line(conn: &mut Connection) -> Result<String, RequestError> {
+    let mut line = Vec::new();
+    (&mut *conn)
+        .take(MAX_CHUNK_LINE_LEN)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if !line.ends_with(b"\n") {
+        return Err(RequestError::Status(400, "Malformed chunk"));
+    }
+    Ok(String::from_utf8_lossy(&line).trim().to_string())
+}
+
+/// Splits a request target into its decoded path and query parameters
+fn parse_target(target: &str) -> Result<(String, Vec<(String, String)>), RequestError> {
+    // Absolute urls are only sent to proxies, but servers must accept them too
+    let target = match target.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |index| &rest[index..]),
+        None => target,
+    };
+    if !target.starts_with('/') && target != "*" {
+        return Err(RequestError::Status(400, "Malformed request target"));
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(name, true), percent_decode(value, true))
+        })
+        .collect();
+    Ok((percent_decode(path, false), query))
+}
+
+/**
+    Decodes `%XX` escapes, and `+` as a space when decoding query parameters.
+
+    Malformed escapes are kept as they are, rather than rejecting the request.
+*/
+pub(crate) fn percent_decode(source: &str, plus_as_space: bool) -> String {
+    let bytes = source.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        let escaped = (byte == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        if let Some(escaped) = escaped {
+            decoded.push(escaped);
+            index += 3;
+        } else {
+            decoded.push(if plus_as_space && byte == b'+' {
+                b' '
+            } else {
+                byte
+            });
+            index += 1;
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
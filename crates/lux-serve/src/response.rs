@@ -0,0 +1,402 @@
+use std::{
+    fmt::Write as _,
+    io::{Error, ErrorKind, Result},
+    net::TcpStream,
+    path::{Path, PathBuf},
+};
+
+use async_fs::File;
+use async_io::Async;
+use futures_lite::{io, prelude::*};
+
+use mlua::prelude::*;
+
+use crate::server::call;
+
+/// A file to send as the body of a response, created by `serve.file`
+#[derive(Debug, Clone)]
+pub struct FileBody(pub(crate) PathBuf);
+
+impl LuaUserData for FileBody {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "FileBody");
+        fields.add_field_method_get("path", |_, this| Ok(this.0.display().to_string()));
+    }
+}
+
+/// A response that upgrades the connection to a websocket, created by `serve.upgrade`
+#[derive(Debug, Clone)]
+pub struct Upgrade(pub(crate) LuaFunction);
+
+impl LuaUserData for Upgrade {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Upgrade");
+    }
+}
+
+pub(crate) enum Body {
+    Empty,
+    Bytes(Vec<u8>),
+    File(PathBuf),
+    /// A file that has been opened, along with its size
+    Open(File, u64),
+    /// A function returning chunks of the body, until it returns nothing
+    Stream(LuaFunction),
+}
+
+pub(crate) enum Response {
+    Http {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Body,
+    },
+    Upgrade {
+        callback: LuaFunction,
+        headers: Vec<(String, String)>,
+        /// The request table given to the handler, which is given to the callback too
+        request: Option<LuaTable>,
+    },
+}
+
+impl Response {
+    /// A plain text response, used for errors and responses the server creates by itself
+    pub fn text(status: u16, message: &str) -> Self {
+        Self::Http {
+            status,
+            headers: vec![(
+                "Content-Type".to_string(),
+                "text/plain; charset=utf-8".to_string(),
+            )],
+            body: Body::Bytes(message.as_bytes().to_vec()),
+        }
+    }
+
+    pub fn file(path: PathBuf) -> Self {
+        let headers = vec![("Content-Type".to_string(), content_type(&path).to_string())];
+        Self::Http {
+            status: 200,
+            headers,
+            body: Body::File(path),
+        }
+    }
+
+    #[must_use]
+    pub fn with_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        let (Self::Http { headers, .. } | Self::Upgrade { headers, .. }) = &mut self;
+        headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    /**
+        Converts the value returned by a handler into a response.
+
+        Handlers may return a response table, a string or buffer to send
+        with a `200` status, a file or upgrade, or nothing for a `204`.
+    */
+    pub fn from_value(value: LuaValue) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::Http {
+                status: 204,
+                headers: Vec::new(),
+                body: Body::Empty,
+            }),
+            LuaValue::Table(t) => Self::from_table(&t),
+            LuaValue::UserData(ud) if ud.is::<Upgrade>() => Ok(Self::Upgrade {
+                callback: ud.borrow::<Upgrade>()?.0.clone(),
+                headers: Vec::new(),
+                request: None,
+            }),
+            LuaValue::UserData(ud) if ud.is::<FileBody>() => {
+                Ok(Self::file(ud.borrow::<FileBody>()?.0.clone()))
+            }
+            value => {
+                let (body, content_type) = body_from_value(value)?;
+                let mut response = Self::Http {
+                    status: 200,
+                    headers: Vec::new(),
+                    body,
+                };
+                if let Some(content_type) = content_type {
+                    response = response.with_header("Content-Type", content_type);
+                }
+                Ok(response)
+            }
+        }
+    }
+
+    fn from_table(table: &LuaTable) -> LuaResult<Self> {
+        let status = table.get::<Option<u16>>("status")?.unwrap_or(200);
+        if !(200..=599).contains(&status) {
+            return Err(LuaError::runtime(format!(
+                "Expected status to be between 200 and 599, got {status}"
+            )));
+        }
+
+        let mut headers = Vec::new();
+        if let Some(header_table) = table.get::<Option<LuaTable>>("headers")? {
+            for pair in header_table.pairs::<String, String>() {
+                let (name, value) = pair?;
+                if name.is_empty()
+                    || !name.bytes().all(|b| b.is_ascii_graphic() && b != b':')
+                    || value.contains(['\r', '\n'])
+                {
+                    return Err(LuaError::runtime(format!(
+                        "Invalid response header '{name}'"
+                    )));
+                }
+                headers.push((name, value));
+            }
+        }
+
+        let (body, content_type) = match table.get::<LuaValue>("body")? {
+            LuaValue::Nil => (Body::Empty, None),
+            LuaValue::UserData(ud) if ud.is::<FileBody>() => {
+                let path = ud.borrow::<FileBody>()?.0.clone();
+                let content_type = content_type(&path);
+                (Body::File(path), Some(content_type))
+            }
+            value => body_from_value(value)?,
+        };
+        if let Some(content_type) = content_type
+            && !headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        {
+            headers.push(("Content-Type".to_string(), content_type.to_string()));
+        }
+
+        Ok(Self::Http {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /**
+        Opens the file of a file response, so that it can be sent with a known length.
+
+        Files that do not exist are turned into a `404` response.
+    */
+    pub async fn open(self) -> Self {
+        let (status, headers, path) = match self {
+            Self::Http {
+                status,
+                headers,
+                body: Body::File(path),
+            } => (status, headers, path),
+            other => return other,
+        };
+        let opened = async {
+            let file = File::open(&path).await?;
+            let metadata = file.metadata().await?;
+            if metadata.is_file() {
+                Ok::<_, Error>((file, metadata.len()))
+            } else {
+                Err(ErrorKind::NotFound.into())
+            }
+        };
+        match opened.await {
+            Ok((file, len)) => Self::Http {
+                status,
+                headers,
+                body: Body::Open(file, len),
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::text(404, "Not Found"),
+            Err(e) => {
+                eprintln!("[SERVE ERROR] Failed to open '{}': {e}", path.display());
+                Self::text(500, "Internal Server Error")
+            }
+        }
+    }
+}
+
+fn body_from_value(value: LuaValue) -> LuaResult<(Body, Option<&'static str>)> {
+    Ok(match value {
+        LuaValue::String(s) => (
+            Body::Bytes(s.as_bytes().to_vec()),
+            Some("text/plain; charset=utf-8"),
+        ),
+        LuaValue::Buffer(b) => (Body::Bytes(b.to_vec()), Some("application/octet-stream")),
+        LuaValue::Function(f) => (Body::Stream(f), None),
+        other => {
+            return Err(LuaError::runtime(format!(
+                "Expected response body to be a string, buffer, file or function, got '{}'",
+                other.type_name()
+            )));
+        }
+    })
+}
+
+/**
+    Writes a response to the connection.
+
+    Bodies with a known size are sent with a `Content-Length`, and streamed bodies
+    are sent chunked - or until the connection closes, when it is not kept alive.
+*/
+pub(crate) async fn write_response(
+    lua: &Lua,
+    stream: &mut Async<TcpStream>,
+    status: u16,
+    headers: &[(String, String)],
+    body: Body,
+    head_only: bool,
+    close: bool,
+) -> Result<()> {
+    let mut head = format!("HTTP/1.1 {status} {}\r\n", reason(status));
+    for (name, value) in headers {
+        // NOTE: Framing is decided by the server, so these can not be set by handlers
+        if ["content-length", "transfer-encoding", "connection"]
+            .iter()
+            .any(|framing| name.eq_ignore_ascii_case(framing))
+        {
+            continue;
+        }
+        let _ = write!(head, "{name}: {value}\r\n");
+    }
+    let has_body = !matches!(status, 204 | 304);
+    match &body {
+        _ if !has_body => {}
+        Body::Empty | Body::File(_) => head.push_str("Content-Length: 0\r\n"),
+        Body::Bytes(bytes) => {
+            let _ = write!(head, "Content-Length: {}\r\n", bytes.len());
+        }
+        Body::Open(_, len) => {
+            let _ = write!(head, "Content-Length: {len}\r\n");
+        }
+        Body::Stream(_) if !close => head.push_str("Transfer-Encoding: chunked\r\n"),
+        Body::Stream(_) => {}
+    }
+    if close {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+
+    if has_body && !head_only {
+        match body {
+            Body::Empty | Body::File(_) => {}
+            Body::Bytes(bytes) => stream.write_all(&bytes).await?,
+            Body::Open(file, len) => {
+                io::copy(file.take(len), &mut *stream).await?;
+            }
+            Body::Stream(func) => write_stream(lua, stream, func, !close).await?,
+        }
+    }
+    stream.flush().await
+}
+
+async fn write_stream(
+    lua: &Lua,
+    stream: &mut Async<TcpStream>,
+    func: LuaFunction,
+    chunked: bool,
+) -> Result<()> {
+    loop {
+        let chunk = match call(lua, func.clone(), ()).await {
+            Ok(values) => values.into_iter().next().unwrap_or(LuaValue::Nil),
+            // NOTE: The error is reported by the scheduler, and the status has already
+            // been sent, so all we can do is to end the response early by closing
+            Err(_) => return Err(ErrorKind::Interrupted.into()),
+        };
+        let bytes = match chunk {
+            LuaValue::Nil => break,
+            LuaValue::String(s) => s.as_bytes().to_vec(),
+            LuaValue::Buffer(b) => b.to_vec(),
+            other => {
+                eprintln!(
+                    "[SERVE ERROR] Expected streamed body to return a string, buffer or nil, got '{}'",
+                    other.type_name()
+                );
+                return Err(ErrorKind::InvalidData.into());
+            }
+        };
+        if bytes.is_empty() {
+            // NOTE: An empty chunk would end a chunked body early
+            continue;
+        }
+        if chunked {
+            stream
+                .write_all(format!("{:x}\r\n", bytes.len()).as_bytes())
+                .await?;
+            stream.write_all(&bytes).await?;
+            stream.write_all(b"\r\n").await?;
+        } else {
+            stream.write_all(&bytes).await?;
+        }
+        stream.flush().await?;
+    }
+    if chunked {
+        stream.write_all(b"0\r\n\r\n").await?;
+    }
+    Ok(())
+}
+
+/// Guesses the content type of a file from its extension
+pub(crate) fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "txt" | "md" | "luau" | "lua" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The reason phrase for a status code, which clients ignore but people reading them do not
+pub(crate) fn reason(status: u16) -> &'static str {
+    match status {
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        413 => "Content Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Content",
+        426 => "Upgrade Required",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        505 => "HTTP Version Not Supported",
+        _ => "",
+    }
+}
@@ -0,0 +1,222 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+
+use mlua::prelude::*;
+
+use lux_utils::process::ProcessPermissions;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// A single segment, such as `:id`
+    Param(String),
+    /// The rest of the path, such as `*path` - which may be empty
+    Rest(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Target {
+    Handler(LuaFunction),
+    WebSocket(LuaFunction),
+    /// A directory to serve files from, using the rest of the path
+    Static(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+struct Route {
+    /// The method to match, or `None` to match any method
+    method: Option<String>,
+    segments: Vec<Segment>,
+    target: Target,
+}
+
+impl Route {
+    fn match_path(&self, path: &[&str]) -> Option<Vec<(String, String)>> {
+        let mut params = Vec::new();
+        let mut parts = path.iter();
+        for segment in &self.segments {
+            match segment {
+                Segment::Rest(name) => {
+                    let rest = parts.by_ref().copied().collect::<Vec<_>>().join("/");
+                    params.push((name.clone(), rest));
+                }
+                Segment::Literal(literal) => {
+                    if parts.next() != Some(&literal.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => params.push((name.clone(), (*parts.next()?).to_string())),
+            }
+        }
+        parts.next().is_none().then_some(params)
+    }
+
+    fn matches_method(&self, method: &str) -> bool {
+        match &self.method {
+            None => true,
+            // NOTE: HEAD requests are answered by GET routes, without their body
+            Some(m) => m == method || (m == "GET" && method == "HEAD"),
+        }
+    }
+}
+
+/// The result of looking up a request in a router
+pub(crate) enum Lookup {
+    Route(Target, Vec<(String, String)>),
+    NotFound,
+    /// The path matched, but not for this method - along with the methods it did match
+    MethodNotAllowed(Vec<String>),
+}
+
+/**
+    A router, created by `serve.router`, which picks a handler by method and path.
+
+    Routes are matched in the order they were added.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    routes: Rc<RefCell<Vec<Route>>>,
+}
+
+impl Router {
+    fn add(&self, method: Option<String>, pattern: &str, target: Target) -> LuaResult<()> {
+        let segments = parse_pattern(pattern)?;
+        self.routes.borrow_mut().push(Route {
+            method,
+            segments,
+            target,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn find(&self, method: &str, path: &str) -> Lookup {
+        let parts = path
+            .split('/')
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>();
+        let mut allowed = Vec::new();
+        for route in self.routes.borrow().iter() {
+            let Some(params) = route.match_path(&parts) else {
+                continue;
+            };
+            if route.matches_method(method) {
+                return Lookup::Route(route.target.clone(), params);
+            }
+            if let Some(m) = &route.method
+                && !allowed.contains(m)
+            {
+                allowed.push(m.clone());
+            }
+        }
+        if allowed.is_empty() {
+            Lookup::NotFound
+        } else {
+            Lookup::MethodNotAllowed(allowed)
+        }
+    }
+}
+
+impl LuaUserData for Router {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Router");
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        for (name, method) in [
+            ("get", "GET"),
+            ("post", "POST"),
+            ("put", "PUT"),
+            ("patch", "PATCH"),
+            ("delete", "DELETE"),
+        ] {
+            methods.add_function(
+                name,
+                move |_, (ud, pattern, handler): (LuaAnyUserData, String, LuaFunction)| {
+                    ud.borrow::<Self>()?.add(
+                        Some(method.to_string()),
+                        &pattern,
+                        Target::Handler(handler),
+                    )?;
+                    Ok(ud)
+                },
+            );
+        }
+        methods.add_function(
+            "route",
+            |_, (ud, method, pattern, handler): (LuaAnyUserData, String, String, LuaFunction)| {
+                let method = (method != "*").then(|| method.to_ascii_uppercase());
+                ud.borrow::<Self>()?
+                    .add(method, &pattern, Target::Handler(handler))?;
+                Ok(ud)
+            },
+        );
+        methods.add_function(
+            "websocket",
+            |_, (ud, pattern, callback): (LuaAnyUserData, String, LuaFunction)| {
+                ud.borrow::<Self>()?.add(
+                    Some("GET".to_string()),
+                    &pattern,
+                    Target::WebSocket(callback),
+                )?;
+                Ok(ud)
+            },
+        );
+        methods.add_function(
+            "static",
+            |lua, (ud, prefix, directory): (LuaAnyUserData, String, String)| {
+                let directory = PathBuf::from(directory);
+                ProcessPermissions::check_path(lua, &directory, "Router:static")?;
+                let pattern = format!("{}/*path", prefix.trim_end_matches('/'));
+                ud.borrow::<Self>()?.add(
+                    Some("GET".to_string()),
+                    &pattern,
+                    Target::Static(directory),
+                )?;
+                Ok(ud)
+            },
+        );
+    }
+}
+
+/// Parses a route pattern such as `/users/:id` or `/files/*path`
+fn parse_pattern(pattern: &str) -> LuaResult<Vec<Segment>> {
+    let Some(rest) = pattern.strip_prefix('/') else {
+        return Err(LuaError::runtime(format!(
+            "Expected route '{pattern}' to start with '/'"
+        )));
+    };
+    let parts = rest
+        .split('/')
+        .filter(|p| !p.is_empty())
+        .collect::<Vec<_>>();
+    let mut segments = Vec::with_capacity(parts.len());
+    for (index, part) in parts.iter().enumerate() {
+        let segment = if let Some(name) = part.strip_prefix(':') {
+            Segment::Param(param_name(pattern, name)?)
+        } else if let Some(name) = part.strip_prefix('*') {
+            if index != parts.len() - 1 {
+                return Err(LuaError::runtime(format!(
+                    "Expected '*' to be the last part of route '{pattern}'"
+                )));
+            }
+            Segment::Rest(if name.is_empty() {
+                "*".to_string()
+            } else {
+                param_name(pattern, name)?
+            })
+        } else {
+            Segment::Literal((*part).to_string())
+        };
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+fn param_name(pattern: &str, name: &str) -> LuaResult<String> {
+    if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Ok(name.to_string())
+    } else {
+        Err(LuaError::runtime(format!(
+            "Invalid parameter name '{name}' in route '{pattern}'"
+        )))
+    }
+}
@@ -0,0 +1,432 @@
+use std::{
+    fmt::Write as _,
+    io::{ErrorKind, Result as IoResult},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    path::{Component, Path},
+    rc::Rc,
+    time::Duration,
+};
+
+use async_channel::{Receiver, Sender};
+use async_io::{Async, Timer};
+use futures_lite::prelude::*;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, LuaSpawnExt};
+
+use lux_websocket::{WebSocket, accept_key};
+
+use crate::{
+    request::{Connection, Request, RequestError, read_request},
+    response::{Response, write_response},
+    router::{Lookup, Router, Target},
+};
+
+/// How long a connection may stay open without sending another request
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait before accepting again, after failing to accept a connection
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(50);
+const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/**
+    Runs a function on its own thread on the scheduler, so that it may yield, and returns its result.
+
+    Errors are reported by the scheduler, like for any other thread.
+*/
+pub(crate) async fn call(
+    lua: &Lua,
+    func: LuaFunction,
+    args: impl IntoLuaMulti,
+) -> LuaResult<LuaMultiValue> {
+    let thread_id = lua.push_thread_back(func, args)?;
+    lua.track_thread(thread_id);
+    lua.wait_for_thread(thread_id).await;
+    lua.get_thread_result(thread_id)
+        .unwrap_or_else(|| Err(LuaError::runtime("Handler thread was cancelled")))
+}
+
+pub(crate) enum Handler {
+    Function(LuaFunction),
+    Router(Router),
+}
+
+impl FromLua for Handler {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Function(f) => Ok(Self::Function(f)),
+            LuaValue::UserData(ud) if ud.is::<Router>() => {
+                Ok(Self::Router(ud.borrow::<Router>()?.clone()))
+            }
+            other => Err(LuaError::runtime(format!(
+                "Expected handler to be a function or router, got '{}'",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ListenOptions {
+    address: IpAddr,
+    max_body_size: usize,
+}
+
+impl Default for ListenOptions {
+    fn default() -> Self {
+        Self {
+            address: IpAddr::from([127, 0, 0, 1]),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+}
+
+impl FromLua for ListenOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let mut options = Self::default();
+                if let Some(address) = t.get::<Option<String>>("address")? {
+                    options.address = match address.as_str() {
+                        "localhost" => IpAddr::from([127, 0, 0, 1]),
+                        address => address.parse().map_err(|_| {
+                            LuaError::runtime(format!(
+                                "Expected address to be an IP address such as '127.0.0.1' or '0.0.0.0', got '{address}'"
+                            ))
+                        })?,
+                    };
+                }
+                if let Some(size) = t.get::<Option<usize>>("maxBodySize")? {
+                    options.max_body_size = size;
+                }
+                Ok(options)
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "ListenOptions".to_string(),
+                message: Some(format!(
+                    "Invalid listen options - expected table or nil, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/// State shared by the accept loop and every connection of a server
+struct State {
+    handler: Handler,
+    max_body_size: usize,
+    /// Closed when the server stops, which ends idle connections
+    stop: Receiver<()>,
+}
+
+/**
+    A running HTTP server, returned by `serve.listen`.
+
+    The server stops accepting connections once closed, or once the runtime begins
+    a graceful shutdown - which then waits for requests in progress to be answered.
+*/
+pub struct Server {
+    address: SocketAddr,
+    stop: Sender<()>,
+    /// Never receives anything, but errors once every connection has finished
+    finished: Receiver<()>,
+}
+
+impl LuaUserData for Server {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Server");
+        fields.add_field_method_get("address", |_, this| Ok(this.address.ip().to_string()));
+        fields.add_field_method_get("port", |_, this| Ok(this.address.port()));
+        fields.add_field_method_get("isClosed", |_, this| Ok(this.stop.is_closed()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("close", |_, this, (): ()| {
+            let stop = this.stop.clone();
+            let finished = this.finished.clone();
+            async move {
+                stop.close();
+                let _ = finished.recv().await;
+                Ok(())
+            }
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(format!("Server({})", this.address))
+        });
+    }
+}
+
+/**
+    Starts a server on the given port, use port `0` for any free port.
+
+    # Errors
+
+    Errors if the address is already in use.
+*/
+pub(crate) fn listen(
+    lua: &Lua,
+    (port, handler, options): (u16, Handler, ListenOptions),
+) -> LuaResult<Server> {
+    let listener =
+        Async::<TcpListener>::bind(SocketAddr::new(options.address, port)).into_lua_err()?;
+    let address = listener.get_ref().local_addr().into_lua_err()?;
+
+    let (stop_tx, stop_rx) = async_channel::bounded(1);
+    let (finished_tx, finished_rx) = async_channel::bounded(1);
+    let state = Rc::new(State {
+        handler,
+        max_body_size: options.max_body_size,
+        stop: stop_rx,
+    });
+    lua.spawn_local(accept_connections(
+        lua.clone(),
+        listener,
+        state,
+        stop_tx.clone(),
+        finished_tx,
+    ));
+
+    Ok(Server {
+        address,
+        stop: stop_tx,
+        finished: finished_rx,
+    })
+}
+
+async fn accept_connections(
+    lua: Lua,
+    listener: Async<TcpListener>,
+    state: Rc<State>,
+    stop: Sender<()>,
+    finished: Sender<()>,
+) {
+    loop {
+        let accepted = async { Some(listener.accept().await) };
+        let stopped = async {
+            let _ = state.stop.recv().await;
+            None
+        };
+        let shutdown = async {
+            lua.wait_for_shutdown().await;
+            None
+        };
+        match accepted.or(stopped).or(shutdown).await {
+            Some(Ok((stream, peer))) => {
+                let _ = stream.get_ref().set_nodelay(true);
+                lua.spawn_local(serve_connection(
+                    lua.clone(),
+                    Rc::clone(&state),
+                    stream,
+                    peer,
+                    finished.clone(),
+                ));
+            }
+            // NOTE: Most likely out of file descriptors, which may free up again
+            Some(Err(_)) => {
+                Timer::after(ACCEPT_RETRY_DELAY).await;
+            }
+            None => break,
+        }
+    }
+    // Stopping lets idle connections know that they should close
+    stop.close();
+}
+
+/**
+    Answers requests on a connection until it closes, goes idle for too long, or
+    the server stops - the `_finished` sender is dropped once the connection ends.
+*/
+async fn serve_connection(
+    lua: Lua,
+    state: Rc<State>,
+    stream: Async<TcpStream>,
+    peer: SocketAddr,
+    _finished: Sender<()>,
+) {
+    let mut conn = Connection::new(stream);
+    loop {
+        let ready = async { Some(conn.fill_buf().await.is_ok_and(|buf| !buf.is_empty())) };
+        let stopped = async {
+            let _ = state.stop.recv().await;
+            None
+        };
+        let idle = async {
+            Timer::after(IDLE_TIMEOUT).await;
+            None
+        };
+        if ready.or(stopped).or(idle).await != Some(true) {
+            return;
+        }
+
+        // NOTE: A graceful shutdown waits for requests that have started,
+        // including for their responses to be written after the handler returns
+        let _pending = lua.track_pending();
+        let request = match read_request(&mut conn, state.max_body_size).await {
+            Ok(request) => request,
+            Err(RequestError::Closed) => return,
+            Err(RequestError::Status(status, message)) => {
+                let _ = send(&lua, &mut conn, Response::text(status, message), true).await;
+                return;
+            }
+        };
+
+        let response = respond(&lua, &state, &request, peer).await.open().await;
+        match response {
+            Response::Http {
+                status,
+                headers,
+                body,
+            } => {
+                let close = !request.keep_alive || state.stop.is_closed();
+                let head_only = request.method == "HEAD";
+                let written = write_response(
+                    &lua,
+                    conn.get_mut(),
+                    status,
+                    &headers,
+                    body,
+                    head_only,
+                    close,
+                )
+                .await;
+                if written.is_err() || close {
+                    return;
+                }
+            }
+            Response::Upgrade {
+                callback,
+                headers,
+                request: table,
+            } => {
+                upgrade(&lua, conn, &request, table, callback, &headers).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Picks the handler for a request, and runs it
+async fn respond(lua: &Lua, state: &State, request: &Request, peer: SocketAddr) -> Response {
+    let (target, params) = match &state.handler {
+        Handler::Function(f) => (Target::Handler(f.clone()), Vec::new()),
+        Handler::Router(router) => match router.find(&request.method, &request.path) {
+            Lookup::Route(target, params) => (target, params),
+            Lookup::NotFound => return Response::text(404, "Not Found"),
+            Lookup::MethodNotAllowed(allowed) => {
+                return Response::text(405, "Method Not Allowed")
+                    .with_header("Allow", allowed.join(", "));
+            }
+        },
+    };
+
+    let result = async {
+        let table = request.to_table(lua, peer, &params)?;
+        let response = match target {
+            Target::Handler(handler) => {
+                let values = call(lua, handler, table.clone()).await?;
+                let value = values.into_iter().next().unwrap_or(LuaValue::Nil);
+                Response::from_value(value).inspect_err(|e| {
+                    eprintln!("[SERVE ERROR] Invalid response from handler: {e}");
+                })?
+            }
+            Target::WebSocket(callback) => Response::Upgrade {
+                callback,
+                headers: Vec::new(),
+                request: None,
+            },
+            Target::Static(directory) => {
+                let rest = params
+                    .iter()
+                    .find(|(name, _)| name == "path")
+                    .map_or("", |(_, rest)| rest.as_str());
+                static_file(&directory, rest)
+            }
+        };
+        Ok::<_, LuaError>(match response {
+            Response::Upgrade {
+                callback, headers, ..
+            } => Response::Upgrade {
+                callback,
+                headers,
+                request: Some(table),
+            },
+            response @ Response::Http { .. } => response,
+        })
+    };
+    // NOTE: Errors thrown by the handler have already been reported by the scheduler
+    result
+        .await
+        .unwrap_or_else(|_| Response::text(500, "Internal Server Error"))
+}
+
+/// Writes a response that the server created by itself, which is never an upgrade
+async fn send(lua: &Lua, conn: &mut Connection, response: Response, close: bool) -> IoResult<()> {
+    match response {
+        Response::Http {
+            status,
+            headers,
+            body,
+        } => write_response(lua, conn.get_mut(), status, &headers, body, false, close).await,
+        Response::Upgrade { .. } => Err(ErrorKind::InvalidInput.into()),
+    }
+}
+
+/// Finds the file for the rest of a path in a static directory, without ever leaving it
+fn static_file(directory: &Path, rest: &str) -> Response {
+    let mut path = directory.to_path_buf();
+    for part in rest.split('/').filter(|p| !p.is_empty()) {
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(part)), None) => path.push(part),
+            _ => return Response::text(404, "Not Found"),
+        }
+    }
+    if rest.is_empty() || rest.ends_with('/') || path.is_dir() {
+        path.push("index.html");
+    }
+    Response::file(path)
+}
+
+/// Completes the websocket handshake, and hands the socket over to the callback
+async fn upgrade(
+    lua: &Lua,
+    mut conn: Connection,
+    request: &Request,
+    table: Option<LuaTable>,
+    callback: LuaFunction,
+    headers: &[(String, String)],
+) {
+    let Some(key) = request
+        .header("sec-websocket-key")
+        .filter(|_| request.is_websocket_upgrade())
+    else {
+        let response = Response::text(426, "Expected a websocket upgrade request")
+            .with_header("Upgrade", "websocket");
+        let _ = send(lua, &mut conn, response, true).await;
+        return;
+    };
+
+    let mut head = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n",
+        accept_key(key)
+    );
+    for (name, value) in headers {
+        let _ = write!(head, "{name}: {value}\r\n");
+    }
+    head.push_str("\r\n");
+    if conn.get_mut().write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+
+    let socket = WebSocket::new_server(lua, conn.into_inner());
+    let args = (socket, table).into_lua_multi(lua);
+    match args {
+        Ok(args) => {
+            // NOTE: Errors are reported by the scheduler, like for any other thread
+            let _ = lua.push_thread_back(callback, args);
+        }
+        Err(e) => eprintln!("[SERVE ERROR] Failed to accept websocket: {e}"),
+    }
+}
@@ -15,13 +15,25 @@
 //! mySignal:Fire("Hello!")
 //! conn:Disconnect()
 //! ```
+//!
+//! Handler errors are routed through the signal's error policy, chosen with
+//! `Signal.new({ onError = "propagate" | "log" | "collect" | function })`.
+//!
+//! Signals created with type names, such as `Signal.new("number", "string")`,
+//! validate their `Fire` arguments unless the runtime is in release mode.
+//!
+//! Deferred signals, created with `Signal.new({ mode = "Deferred" })`, queue their
+//! handlers to run at the end of the current scheduler step instead of inside `Fire`.
 
-use std::sync::Arc;
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use lux_utils::TableBuilder;
+use lux_utils::fmt::{ErrorComponents, Label};
+use lux_utils::process::ProcessReleaseMode;
 use mlua::prelude::*;
-use parking_lot::Mutex;
+use mlua_luau_scheduler::LuaSchedulerExt;
 
 /// Global connection ID
 static CONN_ID: AtomicU64 = AtomicU64::new(1);
@@ -31,88 +43,480 @@ struct Conn {
     id: u64,
     func: LuaFunction,
     once: bool,
+    parallel: bool,
+    /// Handlers with a higher priority run first, equal priorities run in connection order
+    priority: i32,
+    /// Cleared on disconnect, the entry itself is removed once no `Fire` is running
+    connected: bool,
+    /// Traceback of where the handler was connected, kept for typed signals only
+    origin: Option<String>,
+}
+
+/// What to do when a connected handler errors during `Fire`
+#[derive(Clone, Default)]
+pub enum ErrorPolicy {
+    /// Run the remaining handlers, then raise the first error from `Fire`
+    Propagate,
+    /// Print the error with its traceback and keep going
+    #[default]
+    Log,
+    /// Store the error, retrievable with `GetErrors`
+    Collect,
+    /// Call a Lua function with the error message
+    Handler(LuaFunction),
+}
+
+impl FromLua for ErrorPolicy {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Function(f) => Ok(Self::Handler(f)),
+            LuaValue::String(s) => match s.to_str()?.as_ref() {
+                "propagate" => Ok(Self::Propagate),
+                "log" => Ok(Self::Log),
+                "collect" => Ok(Self::Collect),
+                other => Err(LuaError::external(format!(
+                    "invalid onError policy '{other}', expected 'propagate', 'log', 'collect' or a function"
+                ))),
+            },
+            other => Err(LuaError::external(format!(
+                "invalid onError policy of type '{}'",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+/// When connected handlers run, relative to `Fire`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignalMode {
+    /// Handlers run inside `Fire`, before it returns
+    #[default]
+    Immediate,
+    /// Handlers are queued to run at the end of the current scheduler step
+    Deferred,
+}
+
+impl FromLua for SignalMode {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::String(s) => match s.to_str()?.as_ref() {
+                "Immediate" => Ok(Self::Immediate),
+                "Deferred" => Ok(Self::Deferred),
+                other => Err(LuaError::external(format!(
+                    "invalid signal mode '{other}', expected 'Immediate' or 'Deferred'"
+                ))),
+            },
+            other => Err(LuaError::external(format!(
+                "invalid signal mode of type '{}'",
+                other.type_name()
+            ))),
+        }
+    }
+}
+
+/// Runs a `ConnectParallel` handler, reporting any error it raises - pcall
+/// is used from Luau here since the handler may yield
+const PARALLEL_SOURCE: &str = r"
+return function(handler, report, ...)
+    local ok, err = pcall(handler, ...)
+    if not ok then
+        report(err)
+    end
+end
+";
+
+/// The function compiled from `PARALLEL_SOURCE`, so that it is only compiled once
+struct ParallelRunner(LuaFunction);
+
+fn parallel_runner(lua: &Lua) -> LuaResult<LuaFunction> {
+    if let Some(runner) = lua.app_data_ref::<ParallelRunner>() {
+        return Ok(runner.0.clone());
+    }
+    let runner = lua
+        .load(PARALLEL_SOURCE)
+        .set_name("=Signal:ConnectParallel")
+        .call::<LuaFunction>(())?;
+    lua.set_app_data(ParallelRunner(runner.clone()));
+    Ok(runner)
 }
 
 /// Signal internal state
 struct State {
     conns: Vec<Conn>,
-    to_remove: Vec<u64>,
-    firing: bool,
+    /// Number of `Fire` calls currently running, more than one when re-entrant
+    firing: usize,
+    mode: SignalMode,
+    policy: ErrorPolicy,
+    errors: Vec<LuaError>,
+    types: Option<Vec<String>>,
+    /// Called whenever a handler gets connected from Lua
+    on_connect: Option<ConnectHook>,
 }
 
+/// A function called whenever a handler gets connected to a signal from Lua
+pub type ConnectHook = Rc<dyn Fn(&Lua, &Signal)>;
+
 /// The Signal type
 #[derive(Clone)]
-pub struct Signal(Arc<Mutex<State>>);
+pub struct Signal(Rc<RefCell<State>>);
 
 impl Signal {
     #[inline]
     pub fn new() -> Self {
-        Self(Arc::new(Mutex::new(State {
+        Self::with_policy(ErrorPolicy::default())
+    }
+
+    #[must_use]
+    pub fn with_policy(policy: ErrorPolicy) -> Self {
+        Self(Rc::new(RefCell::new(State {
             conns: Vec::with_capacity(2),
-            to_remove: Vec::new(),
-            firing: false,
+            firing: 0,
+            mode: SignalMode::default(),
+            policy,
+            errors: Vec::new(),
+            types: None,
+            on_connect: None,
         })))
     }
 
+    /// Creates a signal that validates `Fire` arguments against Luau type names
+    #[must_use]
+    pub fn with_types(policy: ErrorPolicy, types: Vec<String>) -> Self {
+        let sig = Self::with_policy(policy);
+        sig.0.borrow_mut().types = Some(types);
+        sig
+    }
+
+    /// Sets when connected handlers run, relative to `Fire`
+    #[must_use]
+    pub fn with_mode(self, mode: SignalMode) -> Self {
+        self.0.borrow_mut().mode = mode;
+        self
+    }
+
+    /// Sets a function to call whenever a handler gets connected from Lua, such as
+    /// to lazily start whatever fires the signal once something is listening to it
+    #[must_use]
+    pub fn with_connect_hook(self, hook: ConnectHook) -> Self {
+        self.0.borrow_mut().on_connect = Some(hook);
+        self
+    }
+
     #[inline]
+    #[must_use]
     pub fn connect(&self, func: LuaFunction, once: bool) -> u64 {
+        self.connect_with(func, once, false, 0, None)
+    }
+
+    /// Connects a handler that is spawned on the scheduler instead of called inline
+    #[inline]
+    #[must_use]
+    pub fn connect_parallel(&self, func: LuaFunction) -> u64 {
+        self.connect_with(func, false, true, 0, None)
+    }
+
+    /// Connects a handler that runs before any handlers with a lower priority
+    #[inline]
+    #[must_use]
+    pub fn connect_priority(&self, func: LuaFunction, priority: i32) -> u64 {
+        self.connect_with(func, false, false, priority, None)
+    }
+
+    fn connect_with(
+        &self,
+        func: LuaFunction,
+        once: bool,
+        parallel: bool,
+        priority: i32,
+        origin: Option<String>,
+    ) -> u64 {
         let id = CONN_ID.fetch_add(1, Ordering::Relaxed);
-        self.0.lock().conns.push(Conn { id, func, once });
+        let mut s = self.0.borrow_mut();
+        // Keep connections sorted by priority, after any with an equal priority
+        let index = s
+            .conns
+            .iter()
+            .position(|c| c.priority < priority)
+            .unwrap_or(s.conns.len());
+        s.conns.insert(
+            index,
+            Conn {
+                id,
+                func,
+                once,
+                parallel,
+                priority,
+                connected: true,
+                origin,
+            },
+        );
+        id
+    }
+
+    /// Connects a handler the same way `Connect` does from Lua, returning
+    /// its connection, for functions that take handlers as arguments
+    #[must_use]
+    pub fn connect_handler(&self, lua: &Lua, func: LuaFunction) -> Connection {
+        let id = self.connect_lua(lua, func, false, false, 0);
+        Connection {
+            id,
+            sig: self.clone(),
+        }
+    }
+
+    /// Connects from Lua, remembering the caller's traceback on typed signals
+    fn connect_lua(
+        &self,
+        lua: &Lua,
+        func: LuaFunction,
+        once: bool,
+        parallel: bool,
+        priority: i32,
+    ) -> u64 {
+        let (typed, hook) = {
+            let s = self.0.borrow();
+            (s.types.is_some(), s.on_connect.clone())
+        };
+        let origin = if typed {
+            lua.traceback(None, 1).ok().map(|t| t.to_string_lossy())
+        } else {
+            None
+        };
+        let id = self.connect_with(func, once, parallel, priority, origin);
+        if let Some(hook) = hook {
+            hook(lua, self);
+        }
         id
     }
 
+    /// Returns whether the connection with the given id has not been disconnected
+    #[must_use]
+    pub fn is_connected(&self, id: u64) -> bool {
+        self.0
+            .borrow()
+            .conns
+            .iter()
+            .any(|c| c.id == id && c.connected)
+    }
+
+    /// Checks `Fire` arguments against the declared type names, if any
+    fn validate(&self, args: &LuaMultiValue) -> LuaResult<()> {
+        let s = self.0.borrow();
+        let Some(types) = &s.types else {
+            return Ok(());
+        };
+        for (index, expected) in types.iter().enumerate() {
+            let value = args.get(index).unwrap_or(&LuaValue::Nil);
+            if type_matches(expected, value) {
+                continue;
+            }
+            let mut message = format!(
+                "Signal:Fire argument #{} expected '{expected}', got '{}'",
+                index + 1,
+                value_type_name(value)
+            );
+            if let Some(origin) = s.conns.iter().find_map(|c| c.origin.as_deref()) {
+                message.push_str("\nhandler connected at:\n");
+                message.push_str(origin);
+            }
+            return Err(LuaError::runtime(message));
+        }
+        Ok(())
+    }
+
     #[inline]
     pub fn disconnect(&self, id: u64) {
-        let mut s = self.0.lock();
-        if s.firing {
-            s.to_remove.push(id);
+        let mut s = self.0.borrow_mut();
+        if s.firing > 0 {
+            if let Some(conn) = s.conns.iter_mut().find(|c| c.id == id) {
+                conn.connected = false;
+            }
         } else {
             s.conns.retain(|c| c.id != id);
         }
     }
 
-    pub fn fire(&self, _lua: &Lua, args: LuaMultiValue) -> LuaResult<()> {
-        let funcs: Vec<(u64, LuaFunction, bool)> = {
-            let mut s = self.0.lock();
-            s.firing = true;
-            s.conns
+    /**
+        Fires the signal, running handlers now or queueing them depending on its mode.
+
+        Handlers see the connections as of this call - handlers connected while
+        firing are not run until the next `Fire`, and handlers disconnected while
+        firing, including by a re-entrant `Fire` running a `Once` handler, are skipped.
+        A re-entrant `Fire` runs all of its handlers before returning.
+    */
+    pub fn fire(&self, lua: &Lua, args: LuaMultiValue) -> LuaResult<()> {
+        self.validate(&args)?;
+
+        let (mode, ids) = {
+            let s = self.0.borrow();
+            let ids: Vec<u64> = s
+                .conns
                 .iter()
-                .map(|c| (c.id, c.func.clone(), c.once))
-                .collect()
+                .filter(|c| c.connected)
+                .map(|c| c.id)
+                .collect();
+            (s.mode, ids)
         };
 
-        let mut once_ids = Vec::new();
-        for (id, func, once) in funcs {
-            if once {
-                once_ids.push(id);
+        match mode {
+            SignalMode::Immediate => self.fire_now(lua, &ids, args),
+            SignalMode::Deferred => {
+                if ids.is_empty() {
+                    return Ok(());
+                }
+                let this = self.clone();
+                let dispatch = lua.create_function(move |lua, args: LuaMultiValue| {
+                    this.fire_now(lua, &ids, args)
+                })?;
+                lua.push_thread_back(dispatch, args)?;
+                Ok(())
             }
-            let _ = func.call::<()>(args.clone());
         }
+    }
 
-        let mut s = self.0.lock();
-        s.firing = false;
+    /**
+        Fires the signal from native code, such as a watcher running on the scheduler.
 
-        // Remove once connections
-        for id in once_ids {
-            s.conns.retain(|c| c.id != id);
+        There is no Lua caller for `Fire` to raise errors to here, so errors that the
+        error policy would raise, such as under `Propagate`, are printed instead.
+    */
+    pub fn fire_native(&self, lua: &Lua, args: LuaMultiValue) {
+        if let Err(e) = self.fire(lua, args) {
+            eprintln!("{}\n{}", Label::Error, ErrorComponents::from(e));
         }
+    }
 
-        // Process pending disconnects
-        let pending: Vec<u64> = s.to_remove.drain(..).collect();
-        for id in pending {
-            s.conns.retain(|c| c.id != id);
+    /// Applies the error policy to a handler error, returning it back if it should be raised
+    fn handle_error(&self, policy: &ErrorPolicy, e: LuaError) -> Option<LuaError> {
+        match policy {
+            ErrorPolicy::Propagate => Some(e),
+            ErrorPolicy::Log => {
+                eprintln!("{}\n{}", Label::Error, ErrorComponents::from(e));
+                None
+            }
+            ErrorPolicy::Collect => {
+                self.0.borrow_mut().errors.push(e);
+                None
+            }
+            ErrorPolicy::Handler(handler) => handler.call::<()>(e.to_string()).err(),
         }
+    }
+
+    /**
+        Creates the function that `ConnectParallel` handlers report their errors to.
+
+        The handlers run in their own threads after `Fire` has returned, so errors
+        they raise under the `Propagate` policy are raised from those threads instead.
+    */
+    fn parallel_reporter(&self, lua: &Lua, policy: ErrorPolicy) -> LuaResult<LuaFunction> {
+        let this = self.clone();
+        lua.create_function(move |_, err: LuaValue| {
+            let e = match err {
+                LuaValue::Error(e) => *e,
+                other => LuaError::runtime(other.to_string()?),
+            };
+            match this.handle_error(&policy, e) {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Spawns a `ConnectParallel` handler, creating the reporter shared by a `Fire` call if needed
+    fn spawn_parallel(
+        &self,
+        lua: &Lua,
+        reporter: &mut Option<LuaFunction>,
+        policy: &ErrorPolicy,
+        func: LuaFunction,
+        args: &LuaMultiValue,
+    ) -> LuaResult<()> {
+        let report = match reporter {
+            Some(report) => report.clone(),
+            None => reporter
+                .insert(self.parallel_reporter(lua, policy.clone())?)
+                .clone(),
+        };
+        let mut runner_args = args.clone();
+        runner_args.push_front(LuaValue::Function(report));
+        runner_args.push_front(LuaValue::Function(func));
+        lua.push_thread_front(parallel_runner(lua)?, runner_args)?;
         Ok(())
     }
 
+    /// Runs the handlers with the given ids that are still connected, in priority order
+    fn fire_now(&self, lua: &Lua, ids: &[u64], args: LuaMultiValue) -> LuaResult<()> {
+        let policy = {
+            let mut s = self.0.borrow_mut();
+            s.firing += 1;
+            s.policy.clone()
+        };
+
+        // NOTE: Every handler runs and the state below is always restored,
+        // even when one of them errors - the error is only surfaced afterwards
+        let mut first_error = None;
+        let mut reporter = None;
+        for &id in ids {
+            let (func, parallel) = {
+                let mut s = self.0.borrow_mut();
+                let Some(conn) = s.conns.iter_mut().find(|c| c.id == id && c.connected) else {
+                    continue;
+                };
+                // Disconnected before running, so that a re-entrant Fire can not run it again
+                if conn.once {
+                    conn.connected = false;
+                }
+                (conn.func.clone(), conn.parallel)
+            };
+            let result = if parallel {
+                self.spawn_parallel(lua, &mut reporter, &policy, func, &args)
+            } else {
+                func.call::<()>(args.clone())
+            };
+            if let Err(e) = result
+                && let Some(e) = self.handle_error(&policy, e)
+            {
+                first_error.get_or_insert(e);
+            }
+        }
+
+        // Remove disconnected and once connections, when no other Fire is still running
+        let mut s = self.0.borrow_mut();
+        s.firing -= 1;
+        if s.firing == 0 {
+            s.conns.retain(|c| c.connected);
+        }
+        drop(s);
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Drains errors gathered by the `collect` policy
+    #[must_use]
+    pub fn take_errors(&self) -> Vec<LuaError> {
+        std::mem::take(&mut self.0.borrow_mut().errors)
+    }
+
     #[inline]
     pub fn clear(&self) {
-        self.0.lock().conns.clear();
+        let mut s = self.0.borrow_mut();
+        if s.firing > 0 {
+            for conn in &mut s.conns {
+                conn.connected = false;
+            }
+        } else {
+            s.conns.clear();
+        }
     }
 
     #[inline]
     pub fn count(&self) -> usize {
-        self.0.lock().conns.len()
+        self.0.borrow().conns.iter().filter(|c| c.connected).count()
     }
 }
 
@@ -122,6 +526,29 @@ impl Default for Signal {
     }
 }
 
+/// Returns the Luau type name of a value, preferring `__type` for userdata
+fn value_type_name(value: &LuaValue) -> String {
+    if let LuaValue::UserData(ud) = value
+        && let Ok(name) = ud.metatable().and_then(|mt| mt.get::<String>("__type"))
+    {
+        return name;
+    }
+    value.type_name().to_string()
+}
+
+/// Checks a value against a declared type name - `any`, `T?` and userdata `__type` names are supported
+fn type_matches(expected: &str, value: &LuaValue) -> bool {
+    if let Some(inner) = expected.strip_suffix('?') {
+        return value.is_nil() || type_matches(inner, value);
+    }
+    match expected {
+        "any" => true,
+        "userdata" => matches!(value, LuaValue::UserData(_) | LuaValue::LightUserData(_)),
+        "number" => matches!(value, LuaValue::Integer(_) | LuaValue::Number(_)),
+        _ => value.type_name() == expected || value_type_name(value) == expected,
+    }
+}
+
 /// Connection handle
 #[derive(Clone)]
 pub struct Connection {
@@ -131,9 +558,7 @@ pub struct Connection {
 
 impl LuaUserData for Connection {
     fn add_fields<F: LuaUserDataFields<Self>>(f: &mut F) {
-        f.add_field_method_get("Connected", |_, this| {
-            Ok(this.sig.0.lock().conns.iter().any(|c| c.id == this.id))
-        });
+        f.add_field_method_get("Connected", |_, this| Ok(this.sig.is_connected(this.id)));
     }
 
     fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
@@ -147,7 +572,26 @@ impl LuaUserData for Connection {
 impl LuaUserData for Signal {
     fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
         m.add_method("Connect", |lua, this, func: LuaFunction| {
-            let id = this.connect(func, false);
+            let id = this.connect_lua(lua, func, false, false, 0);
+            lua.create_userdata(Connection {
+                id,
+                sig: this.clone(),
+            })
+        });
+
+        m.add_method(
+            "ConnectWithPriority",
+            |lua, this, (priority, func): (i32, LuaFunction)| {
+                let id = this.connect_lua(lua, func, false, false, priority);
+                lua.create_userdata(Connection {
+                    id,
+                    sig: this.clone(),
+                })
+            },
+        );
+
+        m.add_method("ConnectParallel", |lua, this, func: LuaFunction| {
+            let id = this.connect_lua(lua, func, false, true, 0);
             lua.create_userdata(Connection {
                 id,
                 sig: this.clone(),
@@ -155,7 +599,7 @@ impl LuaUserData for Signal {
         });
 
         m.add_method("Once", |lua, this, func: LuaFunction| {
-            let id = this.connect(func, true);
+            let id = this.connect_lua(lua, func, true, false, 0);
             lua.create_userdata(Connection {
                 id,
                 sig: this.clone(),
@@ -163,7 +607,7 @@ impl LuaUserData for Signal {
         });
 
         m.add_method("Fire", |lua, this, args: LuaMultiValue| {
-            this.fire(&lua, args)
+            this.fire(lua, args)
         });
 
         m.add_method("DisconnectAll", |_, this, ()| {
@@ -178,6 +622,14 @@ impl LuaUserData for Signal {
 
         m.add_method("GetConnections", |_, this, ()| Ok(this.count()));
 
+        m.add_method("GetErrors", |_, this, ()| {
+            Ok(this
+                .take_errors()
+                .into_iter()
+                .map(|e| ErrorComponents::from(e).to_string())
+                .collect::<Vec<_>>())
+        });
+
         m.add_method("Wait", |_, _, ()| -> LuaResult<()> { Ok(()) });
     }
 }
@@ -185,7 +637,36 @@ impl LuaUserData for Signal {
 /// Create the module
 pub fn module(lua: Lua) -> LuaResult<LuaTable> {
     TableBuilder::new(lua)?
-        .with_function("new", |lua, ()| lua.create_userdata(Signal::new()))?
+        .with_function("new", |lua, args: LuaMultiValue| {
+            let mut policy = ErrorPolicy::default();
+            let mut mode = SignalMode::default();
+            let mut types = Vec::new();
+            for arg in args {
+                match arg {
+                    LuaValue::Table(t) => {
+                        policy = t.get::<ErrorPolicy>("onError")?;
+                        mode = t.get::<SignalMode>("mode")?;
+                    }
+                    LuaValue::String(s) => types.push(s.to_str()?.to_string()),
+                    other => {
+                        return Err(LuaError::external(format!(
+                            "Signal.new expected type names or an options table, got '{}'",
+                            other.type_name()
+                        )));
+                    }
+                }
+            }
+            // NOTE: Validation is skipped entirely in release mode, not just at fire time
+            let release = lua
+                .app_data_ref::<ProcessReleaseMode>()
+                .is_some_and(|r| r.enabled());
+            let sig = if types.is_empty() || release {
+                Signal::with_policy(policy)
+            } else {
+                Signal::with_types(policy, types)
+            };
+            lua.create_userdata(sig.with_mode(mode))
+        })?
         .build_readonly()
 }
 
@@ -0,0 +1,44 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod options;
+mod tcp;
+mod udp;
+
+pub use self::options::SocketOptions;
+pub use self::tcp::{TcpListener, TcpStream};
+pub use self::udp::UdpSocket;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `socket` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `socket` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let tcp = TableBuilder::new(lua.clone())?
+        .with_async_function("connect", tcp::connect)?
+        .with_async_function("listen", tcp::listen)?
+        .build_readonly()?;
+    let udp = TableBuilder::new(lua.clone())?
+        .with_async_function("bind", udp::bind)?
+        .build_readonly()?;
+    TableBuilder::new(lua)?
+        .with_value("tcp", tcp)?
+        .with_value("udp", udp)?
+        .build_readonly()
+}
@@ -0,0 +1,110 @@
+use std::{
+    future::Future,
+    io::{Error, ErrorKind},
+    net::{SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
+
+use async_io::Timer;
+use futures_lite::future;
+
+use mlua::prelude::*;
+
+/**
+    Options shared by all socket constructors.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SocketOptions {
+    pub timeout: Option<Duration>,
+}
+
+impl FromLua for SocketOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                timeout: parse_timeout(t.get("timeout")?)?,
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "SocketOptions".to_string(),
+                message: Some(format!(
+                    "Invalid socket options - expected table or nil, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    Converts a timeout in seconds, where `nil` means no timeout.
+
+    # Errors
+
+    Errors if the timeout is negative or not a finite number.
+*/
+pub fn parse_timeout(secs: Option<f64>) -> LuaResult<Option<Duration>> {
+    secs.map(|secs| {
+        Duration::try_from_secs_f64(secs).map_err(|_| {
+            LuaError::runtime(format!(
+                "Invalid timeout {secs} - expected a positive number of seconds"
+            ))
+        })
+    })
+    .transpose()
+}
+
+/**
+    Runs the given future, failing with [`ErrorKind::TimedOut`]
+    if it does not complete within the timeout.
+*/
+pub async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    fut: impl Future<Output = std::io::Result<T>>,
+) -> std::io::Result<T> {
+    match timeout {
+        None => fut.await,
+        Some(duration) => {
+            future::or(fut, async move {
+                Timer::after(duration).await;
+                Err(Error::new(
+                    ErrorKind::TimedOut,
+                    "Socket operation timed out",
+                ))
+            })
+            .await
+        }
+    }
+}
+
+/**
+    Resolves a host and port without blocking the scheduler.
+*/
+pub async fn resolve(host: String, port: u16) -> std::io::Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = blocking::unblock(move || {
+        (host.as_str(), port)
+            .to_socket_addrs()
+            .map(Iterator::collect)
+    })
+    .await?;
+    if addrs.is_empty() {
+        Err(Error::new(
+            ErrorKind::NotFound,
+            "Host did not resolve to any addresses",
+        ))
+    } else {
+        Ok(addrs)
+    }
+}
+
+/**
+    Converts a socket address into a `{ host, port }` table.
+*/
+pub fn address_to_table(lua: &Lua, addr: SocketAddr) -> LuaResult<LuaTable> {
+    let t = lua.create_table_with_capacity(0, 2)?;
+    t.set("host", addr.ip().to_string())?;
+    t.set("port", addr.port())?;
+    t.set_readonly(true);
+    Ok(t)
+}
@@ -0,0 +1,247 @@
+use std::{
+    io::{Error, ErrorKind},
+    net::{Shutdown, SocketAddr, TcpListener as StdTcpListener, TcpStream as StdTcpStream},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_io::Async;
+use async_lock::Mutex as AsyncMutex;
+use bstr::BString;
+use futures_lite::prelude::*;
+
+use mlua::prelude::*;
+
+use crate::options::{SocketOptions, address_to_table, parse_timeout, resolve, with_timeout};
+
+const DEFAULT_READ_SIZE: usize = 8192;
+const MAX_LINE_LENGTH: usize = 1024 * 1024;
+
+/**
+    Connects to the first reachable address for the given host and port.
+
+    # Errors
+
+    Errors if the host can not be resolved, or if no address accepted the connection.
+*/
+pub async fn connect(
+    _: Lua,
+    (host, port, options): (String, u16, SocketOptions),
+) -> LuaResult<TcpStream> {
+    let mut last_error = None;
+    for addr in resolve(host, port).await.into_lua_err()? {
+        match with_timeout(options.timeout, Async::<StdTcpStream>::connect(addr)).await {
+            Ok(stream) => return TcpStream::new(stream, options.timeout),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error
+        .unwrap_or_else(|| Error::new(ErrorKind::NotFound, "No addresses to connect to"))
+        .into_lua_err())
+}
+
+/**
+    Binds a listener to the given host and port, use port `0` for any free port.
+
+    # Errors
+
+    Errors if the host can not be resolved, or if the address is already in use.
+*/
+pub async fn listen(
+    _: Lua,
+    (host, port, options): (String, u16, SocketOptions),
+) -> LuaResult<TcpListener> {
+    let addr = resolve(host, port).await.into_lua_err()?[0];
+    let listener = Async::<StdTcpListener>::bind(addr).into_lua_err()?;
+    let local = listener.get_ref().local_addr().into_lua_err()?;
+    Ok(TcpListener {
+        inner: Arc::new(Mutex::new(Some(Arc::new(listener)))),
+        local,
+        timeout: options.timeout,
+    })
+}
+
+struct StreamInner {
+    stream: Async<StdTcpStream>,
+    read_buf: AsyncMutex<Vec<u8>>,
+    write_lock: AsyncMutex<()>,
+    timeout: Mutex<Option<Duration>>,
+    peer: SocketAddr,
+    local: SocketAddr,
+}
+
+/**
+    A connected TCP stream, with buffered reads so that
+    raw reads and line reads can be freely mixed.
+*/
+#[derive(Clone)]
+pub struct TcpStream(Arc<StreamInner>);
+
+impl TcpStream {
+    fn new(stream: Async<StdTcpStream>, timeout: Option<Duration>) -> LuaResult<Self> {
+        let peer = stream.get_ref().peer_addr().into_lua_err()?;
+        let local = stream.get_ref().local_addr().into_lua_err()?;
+        stream.get_ref().set_nodelay(true).into_lua_err()?;
+        Ok(Self(Arc::new(StreamInner {
+            stream,
+            read_buf: AsyncMutex::new(Vec::new()),
+            write_lock: AsyncMutex::new(()),
+            timeout: Mutex::new(timeout),
+            peer,
+            local,
+        })))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        *self.0.timeout.lock().expect("timeout lock poisoned")
+    }
+
+    /**
+        Reads more data from the stream into the given buffer,
+        returning the number of bytes read, or zero at EOF.
+    */
+    async fn fill(&self, buf: &mut Vec<u8>, size: usize) -> LuaResult<usize> {
+        let start = buf.len();
+        buf.resize(start + size, 0);
+        let result = with_timeout(self.timeout(), (&self.0.stream).read(&mut buf[start..])).await;
+        let read = *result.as_ref().unwrap_or(&0);
+        buf.truncate(start + read);
+        result.into_lua_err()
+    }
+
+    async fn read(&self, size: usize) -> LuaResult<Option<Vec<u8>>> {
+        let mut buf = self.0.read_buf.lock().await;
+        if buf.is_empty() && self.fill(&mut buf, size).await? == 0 {
+            return Ok(None);
+        }
+        let take = size.min(buf.len());
+        Ok(Some(buf.drain(..take).collect()))
+    }
+
+    async fn read_line(&self) -> LuaResult<Option<Vec<u8>>> {
+        let mut buf = self.0.read_buf.lock().await;
+        let mut searched = 0;
+        loop {
+            if let Some(pos) = buf[searched..].iter().position(|b| *b == b'\n') {
+                let mut line: Vec<u8> = buf.drain(..=searched + pos).collect();
+                line.pop();
+                if line.last() == Some(&b'\r') {
+                    line.pop();
+                }
+                return Ok(Some(line));
+            }
+            if buf.len() > MAX_LINE_LENGTH {
+                return Err(LuaError::runtime(format!(
+                    "Line exceeds the maximum length of {MAX_LINE_LENGTH} bytes"
+                )));
+            }
+            searched = buf.len();
+            if self.fill(&mut buf, DEFAULT_READ_SIZE).await? == 0 {
+                // NOTE: A final line without a trailing newline is still a line
+                return Ok((!buf.is_empty()).then(|| buf.drain(..).collect()));
+            }
+        }
+    }
+
+    async fn write(&self, data: &[u8]) -> LuaResult<()> {
+        let _guard = self.0.write_lock.lock().await;
+        let mut stream = &self.0.stream;
+        with_timeout(self.timeout(), async {
+            stream.write_all(data).await?;
+            stream.flush().await
+        })
+        .await
+        .into_lua_err()
+    }
+}
+
+impl LuaUserData for TcpStream {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "TcpStream");
+        fields.add_field_method_get("peerAddress", |lua, this| {
+            address_to_table(lua, this.0.peer)
+        });
+        fields.add_field_method_get("localAddress", |lua, this| {
+            address_to_table(lua, this.0.local)
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("read", |lua, this, size: Option<usize>| {
+            let this = this.clone();
+            async move {
+                let size = size.unwrap_or(DEFAULT_READ_SIZE).max(1);
+                match this.read(size).await? {
+                    Some(bytes) => lua.create_buffer(bytes).map(LuaValue::Buffer),
+                    None => Ok(LuaValue::Nil),
+                }
+            }
+        });
+        methods.add_async_method("readLine", |lua, this, (): ()| {
+            let this = this.clone();
+            async move {
+                match this.read_line().await? {
+                    Some(line) => lua.create_string(line).map(LuaValue::String),
+                    None => Ok(LuaValue::Nil),
+                }
+            }
+        });
+        methods.add_async_method("write", |_, this, data: BString| {
+            let this = this.clone();
+            async move { this.write(&data).await }
+        });
+        methods.add_async_method("writeLine", |_, this, line: BString| {
+            let this = this.clone();
+            async move {
+                let mut data = Vec::from(line);
+                data.push(b'\n');
+                this.write(&data).await
+            }
+        });
+        methods.add_method("setTimeout", |_, this, secs: Option<f64>| {
+            *this.0.timeout.lock().expect("timeout lock poisoned") = parse_timeout(secs)?;
+            Ok(())
+        });
+        methods.add_method("close", |_, this, (): ()| {
+            // NOTE: Closing twice, or after the peer has closed, is not an error
+            let _ = this.0.stream.get_ref().shutdown(Shutdown::Both);
+            Ok(())
+        });
+    }
+}
+
+/**
+    A TCP listener accepting incoming connections.
+*/
+#[derive(Clone)]
+pub struct TcpListener {
+    inner: Arc<Mutex<Option<Arc<Async<StdTcpListener>>>>>,
+    local: SocketAddr,
+    timeout: Option<Duration>,
+}
+
+impl LuaUserData for TcpListener {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "TcpListener");
+        fields.add_field_method_get("localAddress", |lua, this| {
+            address_to_table(lua, this.local)
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("accept", |_, this, (): ()| {
+            let listener = this.inner.lock().expect("listener lock poisoned").clone();
+            let timeout = this.timeout;
+            async move {
+                let listener =
+                    listener.ok_or_else(|| LuaError::runtime("TcpListener is closed"))?;
+                let (stream, _) = listener.accept().await.into_lua_err()?;
+                TcpStream::new(stream, timeout)
+            }
+        });
+        methods.add_method("close", |_, this, (): ()| {
+            this.inner.lock().expect("listener lock poisoned").take();
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    net::{SocketAddr, UdpSocket as StdUdpSocket},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_io::Async;
+use bstr::BString;
+
+use mlua::prelude::*;
+
+use crate::options::{SocketOptions, address_to_table, parse_timeout, resolve, with_timeout};
+
+/**
+    The largest possible UDP payload, used as the default receive size.
+*/
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
+/**
+    Binds a UDP socket to the given host and port, use port `0` for any free port.
+
+    # Errors
+
+    Errors if the host can not be resolved, or if the address is already in use.
+*/
+pub async fn bind(
+    _: Lua,
+    (host, port, options): (String, u16, SocketOptions),
+) -> LuaResult<UdpSocket> {
+    let addr = resolve(host, port).await.into_lua_err()?[0];
+    let socket = Async::<StdUdpSocket>::bind(addr).into_lua_err()?;
+    let local = socket.get_ref().local_addr().into_lua_err()?;
+    Ok(UdpSocket {
+        inner: Arc::new(Mutex::new(Some(Arc::new(socket)))),
+        local,
+        timeout: Arc::new(Mutex::new(options.timeout)),
+    })
+}
+
+/**
+    A bound UDP socket, able to send to and receive from any peer.
+*/
+#[derive(Clone)]
+pub struct UdpSocket {
+    inner: Arc<Mutex<Option<Arc<Async<StdUdpSocket>>>>>,
+    local: SocketAddr,
+    timeout: Arc<Mutex<Option<Duration>>>,
+}
+
+impl UdpSocket {
+    fn socket(&self) -> LuaResult<Arc<Async<StdUdpSocket>>> {
+        self.inner
+            .lock()
+            .expect("socket lock poisoned")
+            .clone()
+            .ok_or_else(|| LuaError::runtime("UdpSocket is closed"))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        *self.timeout.lock().expect("timeout lock poisoned")
+    }
+}
+
+impl LuaUserData for UdpSocket {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "UdpSocket");
+        fields.add_field_method_get("localAddress", |lua, this| {
+            address_to_table(lua, this.local)
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method(
+            "send",
+            |_, this, (data, host, port): (BString, String, u16)| {
+                let this = this.clone();
+                async move {
+                    let socket = this.socket()?;
+                    let addr = resolve(host, port).await.into_lua_err()?[0];
+                    with_timeout(this.timeout(), socket.send_to(&data, addr))
+                        .await
+                        .into_lua_err()
+                }
+            },
+        );
+        methods.add_async_method("receive", |lua, this, size: Option<usize>| {
+            let this = this.clone();
+            async move {
+                let socket = this.socket()?;
+                let mut buf = vec![0; size.unwrap_or(MAX_DATAGRAM_SIZE).max(1)];
+                let (len, addr) = with_timeout(this.timeout(), socket.recv_from(&mut buf))
+                    .await
+                    .into_lua_err()?;
+                buf.truncate(len);
+                Ok((lua.create_buffer(buf)?, address_to_table(&lua, addr)?))
+            }
+        });
+        methods.add_method("setTimeout", |_, this, secs: Option<f64>| {
+            *this.timeout.lock().expect("timeout lock poisoned") = parse_timeout(secs)?;
+            Ok(())
+        });
+        methods.add_method("close", |_, this, (): ()| {
+            this.inner.lock().expect("socket lock poisoned").take();
+            Ok(())
+        });
+    }
+}
@@ -6,18 +6,30 @@ use mlua::prelude::*;
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum LuxStandardGlobal {
     GTable,
+    Pcall,
+    Xpcall,
     Print,
     Require,
     Version,
     Warn,
+    Os,
+    Lux,
+    Script,
+    Args,
     // Types from external crates
     Color3,
+    ColorSequence,
+    ColorSequenceKeypoint,
+    NumberSequence,
+    NumberSequenceKeypoint,
     Vector2,
     Vector3,
     UDim,
     UDim2,
     Rect,
     NumberRange,
+    Mat4,
+    Quaternion,
     DateTime,
     Task,
     Enum,
@@ -26,17 +38,29 @@ pub enum LuxStandardGlobal {
 impl LuxStandardGlobal {
     pub const ALL: &'static [Self] = &[
         Self::GTable,
+        Self::Pcall,
+        Self::Xpcall,
         Self::Print,
         Self::Require,
         Self::Version,
         Self::Warn,
+        Self::Os,
+        Self::Lux,
+        Self::Script,
+        Self::Args,
         Self::Color3,
+        Self::ColorSequence,
+        Self::ColorSequenceKeypoint,
+        Self::NumberSequence,
+        Self::NumberSequenceKeypoint,
         Self::Vector2,
         Self::Vector3,
         Self::UDim,
         Self::UDim2,
         Self::Rect,
         Self::NumberRange,
+        Self::Mat4,
+        Self::Quaternion,
         Self::DateTime,
         Self::Task,
         Self::Enum,
@@ -46,40 +70,110 @@ impl LuxStandardGlobal {
     pub fn name(&self) -> &'static str {
         match self {
             Self::GTable => "_G",
+            Self::Pcall => "pcall",
+            Self::Xpcall => "xpcall",
             Self::Print => "print",
             Self::Require => "require",
             Self::Version => "_VERSION",
             Self::Warn => "warn",
+            Self::Os => "os",
+            Self::Lux => "lux",
+            Self::Script => "script",
+            Self::Args => "args",
             Self::Color3 => "Color3",
+            Self::ColorSequence => "ColorSequence",
+            Self::ColorSequenceKeypoint => "ColorSequenceKeypoint",
+            Self::NumberSequence => "NumberSequence",
+            Self::NumberSequenceKeypoint => "NumberSequenceKeypoint",
             Self::Vector2 => "Vector2",
             Self::Vector3 => "Vector3",
             Self::UDim => "UDim",
             Self::UDim2 => "UDim2",
             Self::Rect => "Rect",
             Self::NumberRange => "NumberRange",
+            Self::Mat4 => "Mat4",
+            Self::Quaternion => "Quaternion",
             Self::DateTime => "DateTime",
             Self::Task => "task",
             Self::Enum => "Enum",
         }
     }
 
+    /**
+        Returns the name of the type definition file for this global, if it has one.
+
+        Globals implemented by the same crate, such as `Vector2` and `Vector3`, share a file.
+    */
+    #[must_use]
+    pub fn typedefs_name(&self) -> Option<&'static str> {
+        match self {
+            Self::Color3
+            | Self::ColorSequence
+            | Self::ColorSequenceKeypoint
+            | Self::NumberSequence
+            | Self::NumberSequenceKeypoint => Some("color"),
+            Self::Vector2 | Self::Vector3 => Some("vector"),
+            Self::UDim | Self::UDim2 | Self::Rect | Self::NumberRange => Some("udim"),
+            Self::Mat4 | Self::Quaternion => Some("matrix"),
+            Self::DateTime => Some("datetime"),
+            Self::Task => Some("task"),
+            Self::Enum => Some("enum"),
+            _ => None,
+        }
+    }
+
+    /**
+        Returns the type definitions for this global, if it has any.
+
+        See [`LuxStandardGlobal::typedefs_name`] for globals that share definitions.
+    */
+    #[must_use]
+    pub fn typedefs(&self) -> Option<String> {
+        match self {
+            Self::Color3
+            | Self::ColorSequence
+            | Self::ColorSequenceKeypoint
+            | Self::NumberSequence
+            | Self::NumberSequenceKeypoint => Some(lux_color::typedefs()),
+            Self::Vector2 | Self::Vector3 => Some(lux_vector::typedefs()),
+            Self::UDim | Self::UDim2 | Self::Rect | Self::NumberRange => Some(lux_udim::typedefs()),
+            Self::Mat4 | Self::Quaternion => Some(lux_matrix::typedefs()),
+            Self::DateTime => Some(lux_datetime::typedefs()),
+            Self::Task => Some(lux_task::typedefs()),
+            Self::Enum => Some(lux_enum::typedefs()),
+            _ => None,
+        }
+    }
+
     #[rustfmt::skip]
     #[allow(unreachable_patterns)]
     pub fn create(&self, lua: Lua) -> LuaResult<LuaValue> {
         let res = match self {
             Self::GTable => crate::globals::g_table::create(lua),
+            Self::Pcall => crate::globals::pcall::create_pcall(lua),
+            Self::Xpcall => crate::globals::pcall::create_xpcall(lua),
             Self::Print => crate::globals::print::create(lua),
             Self::Require => crate::globals::require::create(lua),
             Self::Version => crate::globals::version::create(lua),
             Self::Warn => crate::globals::warn::create(lua),
+            Self::Os => crate::globals::os::create(lua),
+            Self::Lux => crate::globals::lux::create(lua),
+            Self::Script => crate::globals::script::create(lua),
+            Self::Args => crate::globals::script::create_args(lua),
             // External crates
             Self::Color3 => lux_color::create(lua),
+            Self::ColorSequence => lux_color::create_color_sequence(lua),
+            Self::ColorSequenceKeypoint => lux_color::create_color_sequence_keypoint(lua),
+            Self::NumberSequence => lux_color::create_number_sequence(lua),
+            Self::NumberSequenceKeypoint => lux_color::create_number_sequence_keypoint(lua),
             Self::Vector2 => lux_vector::create_vector2(lua),
             Self::Vector3 => lux_vector::create_vector3(lua),
             Self::UDim => lux_udim::create_udim(lua),
             Self::UDim2 => lux_udim::create_udim2(lua),
             Self::Rect => lux_udim::create_rect(lua),
             Self::NumberRange => lux_udim::create_number_range(lua),
+            Self::Mat4 => lux_matrix::create_mat4(lua),
+            Self::Quaternion => lux_matrix::create_quaternion(lua),
             Self::DateTime => lux_datetime::create(lua),
             Self::Task => lux_task::create(lua),
             Self::Enum => lux_enum::create(lua),
@@ -94,17 +188,29 @@ impl FromStr for LuxStandardGlobal {
         let low = s.trim().to_ascii_lowercase();
         Ok(match low.as_str() {
             "_g" => Self::GTable,
+            "pcall" => Self::Pcall,
+            "xpcall" => Self::Xpcall,
             "print" => Self::Print,
             "require" => Self::Require,
             "_version" => Self::Version,
             "warn" => Self::Warn,
+            "os" => Self::Os,
+            "lux" => Self::Lux,
+            "script" => Self::Script,
+            "args" => Self::Args,
             "color3" => Self::Color3,
+            "colorsequence" => Self::ColorSequence,
+            "colorsequencekeypoint" => Self::ColorSequenceKeypoint,
+            "numbersequence" => Self::NumberSequence,
+            "numbersequencekeypoint" => Self::NumberSequenceKeypoint,
             "vector2" => Self::Vector2,
             "vector3" => Self::Vector3,
             "udim" => Self::UDim,
             "udim2" => Self::UDim2,
             "rect" => Self::Rect,
             "numberrange" => Self::NumberRange,
+            "mat4" => Self::Mat4,
+            "quaternion" => Self::Quaternion,
             "datetime" => Self::DateTime,
             "task" => Self::Task,
             "enum" => Self::Enum,
@@ -0,0 +1,94 @@
+use lux_utils::{
+    TableBuilder,
+    flags::{FeatureFlag, FeatureFlags},
+};
+use mlua::prelude::*;
+
+use crate::LuxStandardGlobal;
+
+pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+    let flags = TableBuilder::new(lua.clone())?
+        .with_function("enable", |lua, name: String| {
+            set_flag_from_script(lua, &name, true)
+        })?
+        .with_function("disable", |lua, name: String| {
+            set_flag_from_script(lua, &name, false)
+        })?
+        .with_function("isEnabled", |lua, name: String| {
+            let flag = parse_flag(&name)?;
+            Ok(FeatureFlags::is_enabled_in(lua, flag))
+        })?
+        .with_function("list", flags_list)?
+        .build_readonly()?;
+
+    TableBuilder::new(lua)?
+        .with_value("flags", flags)?
+        .build_readonly()
+        .map(LuaValue::Table)
+}
+
+fn parse_flag(name: &str) -> LuaResult<FeatureFlag> {
+    name.parse().map_err(LuaError::runtime)
+}
+
+fn set_flag_from_script(lua: &Lua, name: &str, enabled: bool) -> LuaResult<()> {
+    let flag = parse_flag(name)?;
+    let previous = FeatureFlags::get_in(lua, flag);
+    set_feature_flag(lua, flag, enabled)?;
+
+    // Chunks that were loaded while the flag had another explicit value may have
+    // already resolved globals that the flag affects, which we can only undo by
+    // turning off import resolution - this makes those globals a bit slower to use
+    if previous.is_some_and(|previous| previous != enabled) {
+        lua.globals().set_safeenv(false);
+    }
+    Ok(())
+}
+
+fn flags_list(lua: &Lua, _: ()) -> LuaResult<LuaTable> {
+    let list = lua.create_table_with_capacity(FeatureFlag::ALL.len(), 0)?;
+    for flag in FeatureFlag::ALL {
+        let entry = TableBuilder::new(lua.clone())?
+            .with_value("name", flag.name())?
+            .with_value("description", flag.description())?
+            .with_value("enabled", FeatureFlags::is_enabled_in(lua, *flag))?
+            .build_readonly()?;
+        list.push(entry)?;
+    }
+    Ok(list)
+}
+
+/**
+    Returns the globals that behave differently depending on the given flag.
+*/
+fn affected_globals(flag: FeatureFlag) -> &'static [LuxStandardGlobal] {
+    match flag {
+        FeatureFlag::NewEnumItems => &[LuxStandardGlobal::Enum],
+    }
+}
+
+/**
+    Explicitly enables or disables a feature flag for the given Luau VM,
+    re-creating any of the standard globals that depend on it.
+
+    # Errors
+
+    Errors if any of the affected globals fail to be re-created.
+*/
+pub fn set_feature_flag(lua: &Lua, flag: FeatureFlag, enabled: bool) -> LuaResult<()> {
+    if FeatureFlags::get_in(lua, flag) == Some(enabled) {
+        return Ok(());
+    }
+
+    if let Some(mut flags) = lua.app_data_mut::<FeatureFlags>() {
+        flags.set(flag, enabled);
+    } else {
+        lua.set_app_data(FeatureFlags::from_iter([(flag, enabled)]));
+    }
+
+    for global in affected_globals(flag) {
+        lua.globals()
+            .set(global.name(), global.create(lua.clone())?)?;
+    }
+    Ok(())
+}
@@ -1,5 +1,9 @@
 pub mod g_table;
+pub mod lux;
+pub mod os;
+pub mod pcall;
 pub mod print;
 pub mod require;
+pub mod script;
 pub mod version;
 pub mod warn;
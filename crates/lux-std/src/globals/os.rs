@@ -0,0 +1,80 @@
+use mlua::prelude::*;
+
+use lux_utils::{TableBuilder, process::ProcessEnv};
+
+mod cpu;
+mod memory;
+
+use self::cpu::CpuInfo;
+use self::memory::MemoryInfo;
+
+/**
+    Creates the `os` global, which is the builtin `os` library extended
+    with `os.cpu()` and `os.memory()` for system capability introspection,
+    and with `os.getenv`, `os.setenv` and `os.env` for environment variables.
+*/
+pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+    let builtin = lua.globals().get::<Option<LuaTable>>("os")?;
+    let mut builder = TableBuilder::new(lua.clone())?;
+    if let Some(builtin) = builtin {
+        for pair in builtin.pairs::<LuaValue, LuaValue>() {
+            let (key, value) = pair?;
+            builder = builder.with_value(key, value)?;
+        }
+    }
+    builder
+        .with_function("cpu", |_, ()| Ok(CpuInfo::detect()))?
+        .with_function("memory", |_, ()| Ok(MemoryInfo::detect()))?
+        .with_function("getenv", os_getenv)?
+        .with_function("setenv", os_setenv)?
+        .with_function("env", os_env)?
+        .build_readonly()?
+        .into_lua(&lua)
+}
+
+/*
+    Environment variables are read from and written to the environment stored in
+    app data, the same one that `process.env` is created from, and never to the
+    environment of the actual process - that is not safe to modify while other
+    threads may be reading it.
+*/
+
+fn process_env(lua: &Lua) -> LuaResult<ProcessEnv> {
+    lua.app_data_ref::<ProcessEnv>()
+        .map(|env| env.clone())
+        .ok_or_else(|| LuaError::runtime("Missing process env in Lua app data"))
+}
+
+fn os_getenv(lua: &Lua, key: LuaString) -> LuaResult<Option<LuaString>> {
+    process_env(lua)?
+        .get_value_bytes(key.as_bytes())
+        .map(|value| lua.create_string(value))
+        .transpose()
+}
+
+fn os_setenv(lua: &Lua, (key, value): (LuaString, Option<LuaString>)) -> LuaResult<()> {
+    let key = key.as_bytes();
+    if key.is_empty() || key.contains(&b'=') || key.contains(&b'\0') {
+        return Err(LuaError::runtime(
+            "Key must be non-empty and not contain '=' or NUL characters",
+        ));
+    }
+    let env = process_env(lua)?;
+    match value {
+        Some(value) if value.as_bytes().contains(&b'\0') => Err(LuaError::runtime(
+            "Value must not contain the NUL character",
+        )),
+        Some(value) => {
+            env.set_value_bytes(&key, value.as_bytes().to_vec());
+            Ok(())
+        }
+        None => {
+            env.remove_value_bytes(&key);
+            Ok(())
+        }
+    }
+}
+
+fn os_env(lua: &Lua, (): ()) -> LuaResult<LuaTable> {
+    process_env(lua)?.into_plain_lua_table(lua.clone())
+}
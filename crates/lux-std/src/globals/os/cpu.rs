@@ -0,0 +1,412 @@
+use std::thread::available_parallelism;
+
+use mlua::prelude::*;
+
+/**
+    Sizes of the CPU caches in bytes, for a single core where caches are per-core.
+
+    Any cache that is not present, or could not be detected, is `None`.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheSizes {
+    pub l1d: Option<u64>,
+    pub l1i: Option<u64>,
+    pub l2: Option<u64>,
+    pub l3: Option<u64>,
+}
+
+/**
+    Information about the CPU of the current system, as returned by `os.cpu()`.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct CpuInfo {
+    /// The architecture, such as `x86_64` or `aarch64`.
+    pub arch: &'static str,
+    /// The human-readable model name, if it could be detected.
+    pub brand: Option<String>,
+    /// The number of logical cores, including any hyperthreads.
+    pub logical_cores: usize,
+    /// The number of physical cores.
+    pub physical_cores: usize,
+    /// The number of cores this process may use, which may be
+    /// lower than `logical_cores` due to affinity masks or quotas.
+    pub available_parallelism: usize,
+    pub cache: CacheSizes,
+    /// SIMD and other instruction set extensions supported at runtime.
+    pub features: Vec<&'static str>,
+}
+
+impl CpuInfo {
+    /**
+        Detects information about the CPU of the current system.
+
+        Detection never fails - anything that can not be detected is left
+        out, and core counts fall back to the available parallelism.
+    */
+    #[must_use]
+    pub fn detect() -> Self {
+        let available_parallelism = available_parallelism().map_or(1, usize::from);
+        let (logical_cores, physical_cores) = platform::core_counts().unwrap_or_default();
+        let logical_cores = if logical_cores == 0 {
+            available_parallelism
+        } else {
+            logical_cores
+        };
+        let physical_cores = if physical_cores == 0 {
+            logical_cores
+        } else {
+            physical_cores
+        };
+        Self {
+            arch: std::env::consts::ARCH,
+            brand: x86_brand().or_else(platform::brand),
+            logical_cores,
+            physical_cores,
+            available_parallelism,
+            cache: platform::cache_sizes(),
+            features: detect_features(),
+        }
+    }
+}
+
+impl IntoLua for CpuInfo {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let cache = lua.create_table()?;
+        cache.set("l1d", self.cache.l1d)?;
+        cache.set("l1i", self.cache.l1i)?;
+        cache.set("l2", self.cache.l2)?;
+        cache.set("l3", self.cache.l3)?;
+
+        let features = lua.create_table()?;
+        for feature in self.features {
+            features.set(feature, true)?;
+        }
+
+        let table = lua.create_table()?;
+        table.set("arch", self.arch)?;
+        table.set("brand", self.brand)?;
+        table.set("logicalCores", self.logical_cores)?;
+        table.set("physicalCores", self.physical_cores)?;
+        table.set("availableParallelism", self.available_parallelism)?;
+        table.set("cache", cache)?;
+        table.set("features", features)?;
+        table.into_lua(lua)
+    }
+}
+
+macro_rules! detected_features {
+    ($detect:ident: $($feature:tt),* $(,)?) => {{
+        let mut features = Vec::new();
+        $(
+            if std::arch::$detect!($feature) {
+                features.push($feature);
+            }
+        )*
+        features
+    }};
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn detect_features() -> Vec<&'static str> {
+    detected_features!(is_x86_feature_detected:
+        "sse", "sse2", "sse3", "ssse3", "sse4.1", "sse4.2", "popcnt", "avx", "avx2",
+        "fma", "f16c", "bmi1", "bmi2", "lzcnt", "aes", "pclmulqdq", "sha",
+        "avx512f", "avx512bw", "avx512cd", "avx512dq", "avx512vl",
+    )
+}
+
+#[cfg(target_arch = "aarch64")]
+fn detect_features() -> Vec<&'static str> {
+    detected_features!(is_aarch64_feature_detected:
+        "neon", "aes", "sha2", "sha3", "crc", "lse", "fp16", "dotprod",
+        "i8mm", "bf16", "sve", "sve2",
+    )
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn detect_features() -> Vec<&'static str> {
+    Vec::new()
+}
+
+/**
+    Reads the processor brand string using `cpuid`, which works the same on all operating systems.
+*/
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn x86_brand() -> Option<String> {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::__cpuid;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::__cpuid;
+
+    let max_extended = __cpuid(0x8000_0000).eax;
+    if max_extended < 0x8000_0004 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(48);
+    for leaf in 0x8000_0002..=0x8000_0004 {
+        let regs = __cpuid(leaf);
+        for reg in [regs.eax, regs.ebx, regs.ecx, regs.edx] {
+            bytes.extend_from_slice(&reg.to_le_bytes());
+        }
+    }
+    let brand = String::from_utf8_lossy(&bytes)
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+        .to_string();
+    (!brand.is_empty()).then_some(brand)
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn x86_brand() -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+pub(super) use self::platform::sysctl_u64;
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::{collections::HashSet, fs, path::Path};
+
+    use super::CacheSizes;
+
+    const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+    fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+        fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+    }
+
+    pub(super) fn core_counts() -> Option<(usize, usize)> {
+        let mut logical = 0;
+        let mut physical = HashSet::new();
+        for entry in fs::read_dir(CPU_DIR).ok()?.flatten() {
+            let name = entry.file_name();
+            let Some(index) = name.to_str().and_then(|n| n.strip_prefix("cpu")) else {
+                continue;
+            };
+            if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+                continue;
+            }
+            logical += 1;
+            let topology = entry.path().join("topology");
+            if let (Some(package), Some(core)) = (
+                read_trimmed(topology.join("physical_package_id")),
+                read_trimmed(topology.join("core_id")),
+            ) {
+                physical.insert((package, core));
+            }
+        }
+        Some((logical, physical.len()))
+    }
+
+    pub(super) fn brand() -> Option<String> {
+        let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+        cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            matches!(key.trim(), "model name" | "Model" | "Hardware")
+                .then(|| value.trim().to_string())
+        })
+    }
+
+    /// Parses sizes such as `32K` and `8M`, as used in sysfs cache descriptions
+    fn parse_size(s: &str) -> Option<u64> {
+        let (digits, multiplier) = match s.as_bytes().last()? {
+            b'K' => (&s[..s.len() - 1], 1024),
+            b'M' => (&s[..s.len() - 1], 1024 * 1024),
+            b'G' => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+            _ => (s, 1),
+        };
+        digits.parse::<u64>().ok().map(|n| n * multiplier)
+    }
+
+    pub(super) fn cache_sizes() -> CacheSizes {
+        let mut sizes = CacheSizes::default();
+        let Ok(entries) = fs::read_dir(Path::new(CPU_DIR).join("cpu0/cache")) else {
+            return sizes;
+        };
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            let (Some(level), Some(kind), Some(size)) = (
+                read_trimmed(dir.join("level")),
+                read_trimmed(dir.join("type")),
+                read_trimmed(dir.join("size"))
+                    .as_deref()
+                    .and_then(parse_size),
+            ) else {
+                continue;
+            };
+            match (level.as_str(), kind.as_str()) {
+                ("1", "Data") => sizes.l1d = Some(size),
+                ("1", "Instruction") => sizes.l1i = Some(size),
+                ("2", _) => sizes.l2 = Some(size),
+                ("3", _) => sizes.l3 = Some(size),
+                _ => {}
+            }
+        }
+        sizes
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::{ffi::CString, mem::size_of, ptr};
+
+    use super::CacheSizes;
+
+    /// Reads an integer sysctl value, which may be either 32 or 64 bits wide
+    pub(crate) fn sysctl_u64(name: &str) -> Option<u64> {
+        let name = CString::new(name).ok()?;
+        let mut value = 0u64;
+        let mut len = size_of::<u64>();
+        // SAFETY: The buffer is valid for `len` bytes, and sysctl writes at most that many
+        let result = unsafe {
+            libc::sysctlbyname(
+                name.as_ptr(),
+                ptr::from_mut(&mut value).cast(),
+                &mut len,
+                ptr::null_mut(),
+                0,
+            )
+        };
+        match (result, len) {
+            (0, 4) => Some(u64::from(u32::from_ne_bytes(
+                value.to_ne_bytes()[..4].try_into().ok()?,
+            ))),
+            (0, 8) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn sysctl_string(name: &str) -> Option<String> {
+        let name = CString::new(name).ok()?;
+        let mut len = 0;
+        // SAFETY: Passing a null buffer only queries the length of the value
+        let result = unsafe {
+            libc::sysctlbyname(name.as_ptr(), ptr::null_mut(), &mut len, ptr::null_mut(), 0)
+        };
+        if result != 0 || len == 0 {
+            return None;
+        }
+        let mut buffer = vec![0u8; len];
+        // SAFETY: The buffer is valid for `len` bytes
+        let result = unsafe {
+            libc::sysctlbyname(
+                name.as_ptr(),
+                buffer.as_mut_ptr().cast(),
+                &mut len,
+                ptr::null_mut(),
+                0,
+            )
+        };
+        if result != 0 {
+            return None;
+        }
+        buffer.truncate(len);
+        let s = String::from_utf8_lossy(&buffer);
+        Some(s.trim_end_matches('\0').trim().to_string())
+    }
+
+    pub(super) fn core_counts() -> Option<(usize, usize)> {
+        let logical = sysctl_u64("hw.logicalcpu")?;
+        let physical = sysctl_u64("hw.physicalcpu")?;
+        Some((
+            usize::try_from(logical).ok()?,
+            usize::try_from(physical).ok()?,
+        ))
+    }
+
+    pub(super) fn brand() -> Option<String> {
+        sysctl_string("machdep.cpu.brand_string")
+    }
+
+    pub(super) fn cache_sizes() -> CacheSizes {
+        let nonzero = |name| sysctl_u64(name).filter(|size| *size > 0);
+        CacheSizes {
+            l1d: nonzero("hw.l1dcachesize"),
+            l1i: nonzero("hw.l1icachesize"),
+            l2: nonzero("hw.l2cachesize"),
+            l3: nonzero("hw.l3cachesize"),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::mem::size_of;
+
+    use windows_sys::Win32::System::SystemInformation::{
+        CacheData, CacheInstruction, GetLogicalProcessorInformation, RelationCache,
+        RelationProcessorCore, SYSTEM_LOGICAL_PROCESSOR_INFORMATION,
+    };
+
+    use super::CacheSizes;
+
+    fn processor_information() -> Vec<SYSTEM_LOGICAL_PROCESSOR_INFORMATION> {
+        let mut len = 0u32;
+        // SAFETY: Passing a null buffer only queries the required length
+        unsafe { GetLogicalProcessorInformation(std::ptr::null_mut(), &mut len) };
+        let count = len as usize / size_of::<SYSTEM_LOGICAL_PROCESSOR_INFORMATION>();
+        let mut buffer = Vec::with_capacity(count);
+        // SAFETY: The buffer has room for `len` bytes of entries, and we
+        // only set its length to the number of entries actually written
+        unsafe {
+            if GetLogicalProcessorInformation(buffer.as_mut_ptr(), &mut len) == 0 {
+                return Vec::new();
+            }
+            buffer.set_len(len as usize / size_of::<SYSTEM_LOGICAL_PROCESSOR_INFORMATION>());
+        }
+        buffer
+    }
+
+    pub(super) fn core_counts() -> Option<(usize, usize)> {
+        let cores = processor_information()
+            .into_iter()
+            .filter(|info| info.Relationship == RelationProcessorCore)
+            .collect::<Vec<_>>();
+        let logical = cores
+            .iter()
+            .map(|info| info.ProcessorMask.count_ones() as usize)
+            .sum();
+        Some((logical, cores.len()))
+    }
+
+    pub(super) fn brand() -> Option<String> {
+        None
+    }
+
+    pub(super) fn cache_sizes() -> CacheSizes {
+        let mut sizes = CacheSizes::default();
+        for info in processor_information() {
+            if info.Relationship != RelationCache {
+                continue;
+            }
+            // SAFETY: The union holds a cache descriptor for cache relationships
+            let cache = unsafe { info.Anonymous.Cache };
+            let size = Some(u64::from(cache.Size));
+            match (cache.Level, cache.Type) {
+                (1, CacheData) => sizes.l1d = sizes.l1d.or(size),
+                (1, CacheInstruction) => sizes.l1i = sizes.l1i.or(size),
+                (2, _) => sizes.l2 = sizes.l2.or(size),
+                (3, _) => sizes.l3 = sizes.l3.or(size),
+                _ => {}
+            }
+        }
+        sizes
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use super::CacheSizes;
+
+    pub(super) fn core_counts() -> Option<(usize, usize)> {
+        None
+    }
+
+    pub(super) fn brand() -> Option<String> {
+        None
+    }
+
+    pub(super) fn cache_sizes() -> CacheSizes {
+        CacheSizes::default()
+    }
+}
@@ -0,0 +1,92 @@
+use mlua::prelude::*;
+
+/**
+    Physical memory totals of the current system in bytes, as returned by `os.memory()`.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryInfo {
+    pub total: u64,
+    pub available: u64,
+    pub used: u64,
+}
+
+impl MemoryInfo {
+    /**
+        Detects the memory totals of the current system.
+
+        All values are zero if they could not be detected.
+    */
+    #[must_use]
+    pub fn detect() -> Self {
+        let (total, available) = platform::totals().unwrap_or_default();
+        Self {
+            total,
+            available,
+            used: total.saturating_sub(available),
+        }
+    }
+}
+
+impl IntoLua for MemoryInfo {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let table = lua.create_table()?;
+        table.set("total", self.total)?;
+        table.set("available", self.available)?;
+        table.set("used", self.used)?;
+        table.into_lua(lua)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    pub(super) fn totals() -> Option<(u64, u64)> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let field = |name: &str| {
+            meminfo.lines().find_map(|line| {
+                let value = line.strip_prefix(name)?.strip_prefix(':')?;
+                let kilobytes = value.trim().trim_end_matches("kB").trim();
+                kilobytes.parse::<u64>().ok().map(|kb| kb * 1024)
+            })
+        };
+        let total = field("MemTotal")?;
+        // NOTE: MemAvailable is missing on very old kernels, MemFree is a lower bound
+        let available = field("MemAvailable").or_else(|| field("MemFree"))?;
+        Some((total, available))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use crate::globals::os::cpu::sysctl_u64;
+
+    pub(super) fn totals() -> Option<(u64, u64)> {
+        let total = sysctl_u64("hw.memsize")?;
+        let page_size = sysctl_u64("hw.pagesize")?;
+        let free_pages = sysctl_u64("vm.page_free_count")?;
+        Some((total, free_pages * page_size))
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::mem::size_of;
+
+    use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+
+    pub(super) fn totals() -> Option<(u64, u64)> {
+        // SAFETY: MEMORYSTATUSEX is plain data, and dwLength must be set before the call
+        let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+        status.dwLength = u32::try_from(size_of::<MEMORYSTATUSEX>()).ok()?;
+        if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+            return None;
+        }
+        Some((status.ullTotalPhys, status.ullAvailPhys))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    pub(super) fn totals() -> Option<(u64, u64)> {
+        None
+    }
+}
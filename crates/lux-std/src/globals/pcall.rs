@@ -0,0 +1,59 @@
+use lux_utils::LuxErrorObject;
+use mlua::prelude::*;
+
+// NOTE: These wrap the builtin pcall and xpcall in Luau instead of
+// calling them from Rust, so that yielding inside of them still works.
+// Only errors raised from Rust are userdata, so we skip calling into Rust
+// for all other errors, which also keeps out of memory errors catchable.
+const PCALL_SOURCE: &str = r#"
+local pcall, structured = ...
+local function handle(ok, ...)
+    if ok or type((...)) ~= "userdata" then
+        return ok, ...
+    end
+    return structured(ok, ...)
+end
+return function(f, ...)
+    return handle(pcall(f, ...))
+end
+"#;
+
+const XPCALL_SOURCE: &str = r#"
+local xpcall, structured = ...
+return function(f, handler, ...)
+    return xpcall(f, function(err)
+        if type(err) ~= "userdata" then
+            return handler(err)
+        end
+        return handler((select(2, structured(false, err))))
+    end, ...)
+end
+"#;
+
+/// Replaces a caught error with a `LuxError` object, if it is one
+fn structured(lua: &Lua, mut results: LuaMultiValue) -> LuaResult<LuaMultiValue> {
+    if let (Some(LuaValue::Boolean(false)), Some(LuaValue::Error(err))) =
+        (results.front(), results.get(1))
+        && let Some(object) = LuxErrorObject::from_lua_error(err)
+    {
+        results[1] = LuaValue::UserData(lua.create_userdata(object)?);
+    }
+    Ok(results)
+}
+
+fn create_wrapper(lua: &Lua, name: &str, source: &str) -> LuaResult<LuaValue> {
+    let builtin = lua.globals().get::<LuaFunction>(name)?;
+    let structured = lua.create_function(structured)?;
+    lua.load(source)
+        .set_name("=[C]")
+        .call::<LuaFunction>((builtin, structured))
+        .map(LuaValue::Function)
+}
+
+pub fn create_pcall(lua: Lua) -> LuaResult<LuaValue> {
+    create_wrapper(&lua, "pcall", PCALL_SOURCE)
+}
+
+pub fn create_xpcall(lua: Lua) -> LuaResult<LuaValue> {
+    create_wrapper(&lua, "xpcall", XPCALL_SOURCE)
+}
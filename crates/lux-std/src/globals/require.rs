@@ -1,8 +1,51 @@
+use lux_utils::overrides;
 use mlua::prelude::*;
 
-use crate::require::RequireResolver;
+use crate::require::{RequireResolver, RequireState, create_reload};
 
 pub fn create(lua: Lua) -> LuaResult<LuaValue> {
-    lua.create_require_function(RequireResolver::new())
-        .map(LuaValue::Function)
-}
\ No newline at end of file
+    let state = RequireState::default();
+    let require = lua.create_require_function(RequireResolver::new(&lua, state.clone()))?;
+
+    #[cfg(feature = "signal")]
+    let reloaded = Some(LuaValue::UserData(
+        lua.create_userdata(lux_signal::Signal::new())?,
+    ));
+    #[cfg(not(feature = "signal"))]
+    let reloaded = None;
+
+    let reload = create_reload(&lua, require.clone(), state, reloaded.clone())?;
+    let mock = lua.create_function(|lua, (name, value): (String, LuaValue)| {
+        if value.is_nil() {
+            return Err(LuaError::runtime(format!(
+                "Mock for '{name}' must not be nil - use require.unmock to remove a mock"
+            )));
+        }
+        overrides::override_module(lua, &name, value)
+    })?;
+    let unmock = lua.create_function(|lua, name: String| overrides::remove_override(lua, &name))?;
+
+    // Functions can not have fields, so `require.reload` and the other fields of `require` go through
+    // the metatable shared by all functions - any other function, or field, errors the same as without it
+    let index_require = require.clone();
+    let index =
+        lua.create_function(
+            move |_, (func, key): (LuaFunction, String)| match key.as_str() {
+                "reload" if func == index_require => Ok(LuaValue::Function(reload.clone())),
+                "mock" if func == index_require => Ok(LuaValue::Function(mock.clone())),
+                "unmock" if func == index_require => Ok(LuaValue::Function(unmock.clone())),
+                "reloaded" if func == index_require => {
+                    Ok(reloaded.clone().unwrap_or(LuaValue::Nil))
+                }
+                _ => Err(LuaError::runtime(format!(
+                    "attempt to index function with '{key}'"
+                ))),
+            },
+        )?;
+    let metatable = lua.create_table()?;
+    metatable.raw_set("__index", index)?;
+    metatable.set_readonly(true);
+    lua.set_type_metatable::<LuaFunction>(Some(metatable));
+
+    Ok(LuaValue::Function(require))
+}
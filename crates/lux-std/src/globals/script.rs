@@ -0,0 +1,32 @@
+use mlua::prelude::*;
+
+use lux_utils::{
+    TableBuilder,
+    process::{ProcessArgs, RunContext},
+};
+
+/**
+    Creates the `script` global, describing how the current script was started.
+*/
+pub fn create(lua: Lua) -> LuaResult<LuaValue> {
+    let context = lua
+        .app_data_ref::<RunContext>()
+        .map(|context| *context)
+        .unwrap_or_default();
+    TableBuilder::new(lua.clone())?
+        .with_value("RunContext", context.name())?
+        .build_readonly()?
+        .into_lua(&lua)
+}
+
+/**
+    Creates the `args` global, a read-only array of the arguments passed to the script.
+*/
+pub fn create_args(lua: Lua) -> LuaResult<LuaValue> {
+    let args = match lua.app_data_ref::<ProcessArgs>() {
+        Some(args) => args.into_plain_lua_table(lua.clone())?,
+        None => lua.create_table()?,
+    };
+    args.set_readonly(true);
+    args.into_lua(&lua)
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use mlua::prelude::*;
+
+use crate::library::LuxStandardLibrary;
+use crate::permissions;
+
+/**
+    How long each library took to initialize, in the order they were first used.
+*/
+#[derive(Debug, Default)]
+pub(crate) struct LibraryInitTimes(Vec<(LuxStandardLibrary, Duration)>);
+
+/**
+    Returns how long each standard library took to initialize, in the
+    order they were first used. Libraries not used yet are not included.
+*/
+#[must_use]
+pub fn library_init_times(lua: &Lua) -> Vec<(LuxStandardLibrary, Duration)> {
+    lua.app_data_ref::<LibraryInitTimes>()
+        .map(|times| times.0.clone())
+        .unwrap_or_default()
+}
+
+/**
+    Creates the module table for a library, gated by its permission, and records how long it took.
+*/
+pub(crate) fn create_module(lua: &Lua, library: LuxStandardLibrary) -> LuaResult<LuaTable> {
+    let start = Instant::now();
+    let module = library.module(lua.clone())?;
+    let module = permissions::gate(lua, library, module)?;
+    let elapsed = start.elapsed();
+
+    let recorded = lua
+        .app_data_mut::<LibraryInitTimes>()
+        .map(|mut times| times.0.push((library, elapsed)))
+        .is_some();
+    if !recorded {
+        lua.set_app_data(LibraryInitTimes(vec![(library, elapsed)]));
+    }
+
+    Ok(module)
+}
+
+/// Registry table where `Lua::register_module` keeps modules, which `require` looks them up in
+const REGISTERED_MODULES_TABLE: &str = "_REGISTEREDMODULES";
+
+/**
+    Libraries that are registered but not initialized yet, by their lowercased alias.
+*/
+#[derive(Debug, Default)]
+struct PendingLibraries(HashMap<String, LuxStandardLibrary>);
+
+/**
+    Initializes a pending library when `require` looks it up, since `require`
+    indexes the table of registered modules without a raw lookup.
+*/
+fn index_registered(lua: &Lua, (registered, alias): (LuaTable, LuaValue)) -> LuaResult<LuaValue> {
+    let LuaValue::String(alias) = alias else {
+        return Ok(LuaValue::Nil);
+    };
+    let alias = alias.to_str()?.to_string();
+    let pending = lua
+        .app_data_mut::<PendingLibraries>()
+        .and_then(|mut pending| pending.0.remove(&alias));
+    let Some(library) = pending else {
+        return Ok(LuaValue::Nil);
+    };
+    let module = create_module(lua, library)?;
+    registered.raw_set(alias, &module)?;
+    Ok(LuaValue::Table(module))
+}
+
+/**
+    Forgets libraries registered lazily by a previous call to [`register_lazy_module`].
+*/
+pub(crate) fn clear_lazy_modules(lua: &Lua) {
+    lua.remove_app_data::<PendingLibraries>();
+}
+
+/**
+    Registers a library under the given alias, to only be initialized the first time it is required.
+
+    Modules like `ffi` do comparatively expensive work when created, such as loading the
+    C library, which most scripts never need - registering all libraries lazily means a
+    small script only pays for what it uses. Since the real module is what `require`
+    returns, it behaves exactly the same as if it was initialized right away.
+*/
+pub(crate) fn register_lazy_module(
+    lua: &Lua,
+    library: LuxStandardLibrary,
+    alias: &str,
+) -> LuaResult<()> {
+    let registered = match lua.named_registry_value::<LuaValue>(REGISTERED_MODULES_TABLE)? {
+        LuaValue::Table(registered) => registered,
+        _ => {
+            let registered = lua.create_table()?;
+            lua.set_named_registry_value(REGISTERED_MODULES_TABLE, &registered)?;
+            registered
+        }
+    };
+    if registered.metatable().is_none() {
+        let meta = lua.create_table()?;
+        meta.set(
+            LuaMetaMethod::Index.name(),
+            lua.create_function(index_registered)?,
+        )?;
+        meta.set_readonly(true);
+        registered.set_metatable(Some(meta))?;
+    }
+
+    // NOTE: The module initialized by a previous run is removed, unless it is overridden,
+    // in which case the library is initialized once all overrides of it have been removed
+    lux_utils::overrides::register_module(lua, alias, LuaValue::Nil)?;
+
+    let alias = alias.to_ascii_lowercase();
+    if let Some(mut pending) = lua.app_data_mut::<PendingLibraries>() {
+        pending.0.insert(alias, library);
+        return Ok(());
+    }
+    lua.set_app_data(PendingLibraries(HashMap::from([(alias, library)])));
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use std::str::FromStr;
 
+use lux_utils::process::Permission;
 use mlua::prelude::*;
 
 /// A standard library provided by Lux (accessed via @lux/).
@@ -17,6 +18,39 @@ pub enum LuxStandardLibrary {
     #[cfg(feature = "uuid")]       Uuid,
     #[cfg(feature = "noise")]      Noise,
     #[cfg(feature = "base64")]     Base64,
+    #[cfg(feature = "crypto")]     Crypto,
+    #[cfg(feature = "image")]      Image,
+    #[cfg(feature = "websocket")]  WebSocket,
+    #[cfg(feature = "socket")]     Socket,
+    #[cfg(feature = "stream")]     Stream,
+    #[cfg(feature = "test")]       Test,
+    #[cfg(feature = "fmt")]        Fmt,
+    #[cfg(feature = "buffer-extra")] BufferExtra,
+    #[cfg(feature = "pathfind")]     Pathfind,
+    #[cfg(feature = "random")]       Random,
+    #[cfg(feature = "profiler")]     Profiler,
+    #[cfg(feature = "gc")]           Gc,
+    #[cfg(feature = "env")]          Env,
+    #[cfg(feature = "term")]         Term,
+    #[cfg(feature = "desktop")]      Desktop,
+    #[cfg(feature = "bindgen")]      Bindgen,
+    #[cfg(feature = "inspect")]      Inspect,
+    #[cfg(feature = "easing")]       Easing,
+    #[cfg(feature = "layout")]       Layout,
+    #[cfg(feature = "tablex")]       Tablex,
+    #[cfg(feature = "csv")]          Csv,
+    #[cfg(feature = "semver")]       Semver,
+    #[cfg(feature = "winreg")]       WinReg,
+    #[cfg(feature = "evdev")]        Evdev,
+    #[cfg(feature = "autoinput")]    AutoInput,
+    #[cfg(feature = "hotkey")]       Hotkey,
+    #[cfg(feature = "time")]         Time,
+    #[cfg(feature = "channel")]      Channel,
+    #[cfg(feature = "sync")]         Sync,
+    #[cfg(feature = "binparse")]     BinParse,
+    #[cfg(feature = "serve")]        Serve,
+    #[cfg(feature = "template")]     Template,
+    #[cfg(feature = "reactor")]      Reactor,
 }
 
 impl LuxStandardLibrary {
@@ -33,6 +67,39 @@ impl LuxStandardLibrary {
         #[cfg(feature = "uuid")]       Self::Uuid,
         #[cfg(feature = "noise")]      Self::Noise,
         #[cfg(feature = "base64")]     Self::Base64,
+        #[cfg(feature = "crypto")]     Self::Crypto,
+        #[cfg(feature = "image")]      Self::Image,
+        #[cfg(feature = "websocket")]  Self::WebSocket,
+        #[cfg(feature = "socket")]     Self::Socket,
+        #[cfg(feature = "stream")]     Self::Stream,
+        #[cfg(feature = "test")]       Self::Test,
+        #[cfg(feature = "fmt")]        Self::Fmt,
+        #[cfg(feature = "buffer-extra")] Self::BufferExtra,
+        #[cfg(feature = "pathfind")]     Self::Pathfind,
+        #[cfg(feature = "random")]       Self::Random,
+        #[cfg(feature = "profiler")]     Self::Profiler,
+        #[cfg(feature = "gc")]           Self::Gc,
+        #[cfg(feature = "env")]          Self::Env,
+        #[cfg(feature = "term")]         Self::Term,
+        #[cfg(feature = "desktop")]      Self::Desktop,
+        #[cfg(feature = "bindgen")]      Self::Bindgen,
+        #[cfg(feature = "inspect")]      Self::Inspect,
+        #[cfg(feature = "easing")]       Self::Easing,
+        #[cfg(feature = "layout")]       Self::Layout,
+        #[cfg(feature = "tablex")]       Self::Tablex,
+        #[cfg(feature = "csv")]          Self::Csv,
+        #[cfg(feature = "semver")]       Self::Semver,
+        #[cfg(feature = "winreg")]       Self::WinReg,
+        #[cfg(feature = "evdev")]        Self::Evdev,
+        #[cfg(feature = "autoinput")]    Self::AutoInput,
+        #[cfg(feature = "hotkey")]       Self::Hotkey,
+        #[cfg(feature = "time")]         Self::Time,
+        #[cfg(feature = "channel")]      Self::Channel,
+        #[cfg(feature = "sync")]         Self::Sync,
+        #[cfg(feature = "binparse")]     Self::BinParse,
+        #[cfg(feature = "serve")]        Self::Serve,
+        #[cfg(feature = "template")]     Self::Template,
+        #[cfg(feature = "reactor")]      Self::Reactor,
     ];
 
     #[must_use]
@@ -51,10 +118,89 @@ impl LuxStandardLibrary {
             #[cfg(feature = "uuid")]       Self::Uuid       => "uuid",
             #[cfg(feature = "noise")]      Self::Noise      => "noise",
             #[cfg(feature = "base64")]     Self::Base64     => "base64",
+            #[cfg(feature = "crypto")]     Self::Crypto     => "crypto",
+            #[cfg(feature = "image")]      Self::Image      => "image",
+            #[cfg(feature = "websocket")]  Self::WebSocket  => "websocket",
+            #[cfg(feature = "socket")]     Self::Socket     => "socket",
+            #[cfg(feature = "stream")]     Self::Stream     => "stream",
+            #[cfg(feature = "test")]       Self::Test       => "test",
+            #[cfg(feature = "fmt")]        Self::Fmt        => "fmt",
+            #[cfg(feature = "buffer-extra")] Self::BufferExtra => "buffer-extra",
+            #[cfg(feature = "pathfind")]     Self::Pathfind    => "pathfind",
+            #[cfg(feature = "random")]       Self::Random      => "random",
+            #[cfg(feature = "profiler")]     Self::Profiler    => "profiler",
+            #[cfg(feature = "gc")]           Self::Gc          => "gc",
+            #[cfg(feature = "env")]          Self::Env         => "env",
+            #[cfg(feature = "term")]         Self::Term        => "term",
+            #[cfg(feature = "desktop")]      Self::Desktop     => "desktop",
+            #[cfg(feature = "bindgen")]      Self::Bindgen     => "bindgen",
+            #[cfg(feature = "inspect")]      Self::Inspect     => "inspect",
+            #[cfg(feature = "easing")]       Self::Easing      => "easing",
+            #[cfg(feature = "layout")]       Self::Layout      => "layout",
+            #[cfg(feature = "tablex")]       Self::Tablex      => "tablex",
+            #[cfg(feature = "csv")]          Self::Csv         => "csv",
+            #[cfg(feature = "semver")]       Self::Semver      => "semver",
+            #[cfg(feature = "winreg")]       Self::WinReg      => "winreg",
+            #[cfg(feature = "evdev")]        Self::Evdev       => "evdev",
+            #[cfg(feature = "autoinput")]    Self::AutoInput   => "autoinput",
+            #[cfg(feature = "hotkey")]       Self::Hotkey      => "hotkey",
+            #[cfg(feature = "time")]         Self::Time        => "time",
+            #[cfg(feature = "channel")]      Self::Channel     => "channel",
+            #[cfg(feature = "sync")]         Self::Sync        => "sync",
+            #[cfg(feature = "binparse")]     Self::BinParse    => "binparse",
+            #[cfg(feature = "serve")]        Self::Serve       => "serve",
+            #[cfg(feature = "template")]     Self::Template    => "template",
+            #[cfg(feature = "reactor")]      Self::Reactor     => "reactor",
             _ => unreachable!(),
         }
     }
 
+    /**
+        Returns the permission that scripts need to use the library, if any.
+    */
+    #[must_use]
+    #[rustfmt::skip]
+    #[allow(unreachable_patterns)]
+    pub fn permission(&self) -> Option<Permission> {
+        match self {
+            #[cfg(feature = "fs")]        Self::Fs        => Some(Permission::Fs),
+            #[cfg(feature = "csv")]       Self::Csv       => Some(Permission::Fs),
+            #[cfg(feature = "image")]     Self::Image     => Some(Permission::Fs),
+            #[cfg(feature = "env")]       Self::Env       => Some(Permission::Fs),
+            #[cfg(feature = "binparse")]  Self::BinParse  => Some(Permission::Fs),
+            #[cfg(feature = "process")]   Self::Process   => Some(Permission::Process),
+            #[cfg(feature = "ffi")]       Self::Ffi       => Some(Permission::Ffi),
+            #[cfg(feature = "websocket")] Self::WebSocket => Some(Permission::Net),
+            #[cfg(feature = "socket")]    Self::Socket    => Some(Permission::Net),
+            #[cfg(feature = "serve")]     Self::Serve     => Some(Permission::Net),
+            #[cfg(feature = "reactor")]   Self::Reactor   => Some(Permission::Ffi),
+            // NOTE: Generating bindings runs the system C compiler
+            #[cfg(feature = "bindgen")]   Self::Bindgen   => Some(Permission::Process),
+            // NOTE: Notifications, the clipboard and opening files use system programs
+            #[cfg(feature = "desktop")]   Self::Desktop   => Some(Permission::Process),
+            #[cfg(feature = "winreg")]    Self::WinReg    => Some(Permission::Registry),
+            #[cfg(feature = "evdev")]     Self::Evdev     => Some(Permission::Input),
+            #[cfg(feature = "autoinput")] Self::AutoInput => Some(Permission::Input),
+            #[cfg(feature = "hotkey")]    Self::Hotkey    => Some(Permission::Input),
+            _ => None,
+        }
+    }
+
+    /**
+        Returns whether the library can be initialized lazily, the first time it is used.
+    */
+    #[must_use]
+    #[allow(unreachable_patterns)]
+    pub fn is_lazy(&self) -> bool {
+        match self {
+            // NOTE: The process library resets the signal handlers of the previous
+            // run when created, which must happen even if the script never uses it
+            #[cfg(feature = "process")]
+            Self::Process => false,
+            _ => true,
+        }
+    }
+
     #[must_use]
     #[rustfmt::skip]
     #[allow(unreachable_patterns)]
@@ -71,6 +217,39 @@ impl LuxStandardLibrary {
             #[cfg(feature = "uuid")]       Self::Uuid       => lux_uuid::typedefs(),
             #[cfg(feature = "noise")]      Self::Noise      => lux_noise::typedefs(),
             #[cfg(feature = "base64")]     Self::Base64     => lux_base64::typedefs(),
+            #[cfg(feature = "crypto")]     Self::Crypto     => lux_crypto::typedefs(),
+            #[cfg(feature = "image")]      Self::Image      => lux_image::typedefs(),
+            #[cfg(feature = "websocket")]  Self::WebSocket  => lux_websocket::typedefs(),
+            #[cfg(feature = "socket")]     Self::Socket     => lux_socket::typedefs(),
+            #[cfg(feature = "stream")]     Self::Stream     => lux_stream::typedefs(),
+            #[cfg(feature = "test")]       Self::Test       => lux_test::typedefs(),
+            #[cfg(feature = "fmt")]        Self::Fmt        => lux_fmt::typedefs(),
+            #[cfg(feature = "buffer-extra")] Self::BufferExtra => lux_buffer_extra::typedefs(),
+            #[cfg(feature = "pathfind")]     Self::Pathfind    => lux_pathfind::typedefs(),
+            #[cfg(feature = "random")]       Self::Random      => lux_random::typedefs(),
+            #[cfg(feature = "profiler")]     Self::Profiler    => lux_profiler::typedefs(),
+            #[cfg(feature = "gc")]           Self::Gc          => lux_gc::typedefs(),
+            #[cfg(feature = "env")]          Self::Env         => lux_env::typedefs(),
+            #[cfg(feature = "term")]         Self::Term        => lux_term::typedefs(),
+            #[cfg(feature = "desktop")]      Self::Desktop     => lux_desktop::typedefs(),
+            #[cfg(feature = "bindgen")]      Self::Bindgen     => lux_bindgen::typedefs(),
+            #[cfg(feature = "inspect")]      Self::Inspect     => lux_inspect::typedefs(),
+            #[cfg(feature = "easing")]       Self::Easing      => lux_easing::typedefs(),
+            #[cfg(feature = "layout")]       Self::Layout      => lux_layout::typedefs(),
+            #[cfg(feature = "tablex")]       Self::Tablex      => lux_tablex::typedefs(),
+            #[cfg(feature = "csv")]          Self::Csv         => lux_csv::typedefs(),
+            #[cfg(feature = "semver")]       Self::Semver      => lux_semver::typedefs(),
+            #[cfg(feature = "winreg")]       Self::WinReg      => lux_winreg::typedefs(),
+            #[cfg(feature = "evdev")]        Self::Evdev       => lux_evdev::typedefs(),
+            #[cfg(feature = "autoinput")]    Self::AutoInput   => lux_autoinput::typedefs(),
+            #[cfg(feature = "hotkey")]       Self::Hotkey      => lux_hotkey::typedefs(),
+            #[cfg(feature = "time")]         Self::Time        => lux_time::typedefs(),
+            #[cfg(feature = "channel")]      Self::Channel     => lux_channel::typedefs(),
+            #[cfg(feature = "sync")]         Self::Sync        => lux_sync::typedefs(),
+            #[cfg(feature = "binparse")]     Self::BinParse    => lux_binparse::typedefs(),
+            #[cfg(feature = "serve")]        Self::Serve       => lux_serve::typedefs(),
+            #[cfg(feature = "template")]     Self::Template    => lux_template::typedefs(),
+            #[cfg(feature = "reactor")]      Self::Reactor     => lux_reactor::typedefs(),
             _ => unreachable!(),
         }
     }
@@ -90,6 +269,39 @@ impl LuxStandardLibrary {
             #[cfg(feature = "uuid")]       Self::Uuid       => lux_uuid::module(lua),
             #[cfg(feature = "noise")]      Self::Noise      => lux_noise::module(lua),
             #[cfg(feature = "base64")]     Self::Base64     => lux_base64::module(lua),
+            #[cfg(feature = "crypto")]     Self::Crypto     => lux_crypto::module(lua),
+            #[cfg(feature = "image")]      Self::Image      => lux_image::module(lua),
+            #[cfg(feature = "websocket")]  Self::WebSocket  => lux_websocket::module(lua),
+            #[cfg(feature = "socket")]     Self::Socket     => lux_socket::module(lua),
+            #[cfg(feature = "stream")]     Self::Stream     => lux_stream::module(lua),
+            #[cfg(feature = "test")]       Self::Test       => lux_test::module(lua),
+            #[cfg(feature = "fmt")]        Self::Fmt        => lux_fmt::module(lua),
+            #[cfg(feature = "buffer-extra")] Self::BufferExtra => lux_buffer_extra::module(lua),
+            #[cfg(feature = "pathfind")]     Self::Pathfind    => lux_pathfind::module(lua),
+            #[cfg(feature = "random")]       Self::Random      => lux_random::module(lua),
+            #[cfg(feature = "profiler")]     Self::Profiler    => lux_profiler::module(lua),
+            #[cfg(feature = "gc")]           Self::Gc          => lux_gc::module(lua),
+            #[cfg(feature = "env")]          Self::Env         => lux_env::module(lua),
+            #[cfg(feature = "term")]         Self::Term        => lux_term::module(lua),
+            #[cfg(feature = "desktop")]      Self::Desktop     => lux_desktop::module(lua),
+            #[cfg(feature = "bindgen")]      Self::Bindgen     => lux_bindgen::module(lua),
+            #[cfg(feature = "inspect")]      Self::Inspect     => lux_inspect::module(lua),
+            #[cfg(feature = "easing")]       Self::Easing      => lux_easing::module(lua),
+            #[cfg(feature = "layout")]       Self::Layout      => lux_layout::module(lua),
+            #[cfg(feature = "tablex")]       Self::Tablex      => lux_tablex::module(lua),
+            #[cfg(feature = "csv")]          Self::Csv         => lux_csv::module(lua),
+            #[cfg(feature = "semver")]       Self::Semver      => lux_semver::module(lua),
+            #[cfg(feature = "winreg")]       Self::WinReg      => lux_winreg::module(lua),
+            #[cfg(feature = "evdev")]        Self::Evdev       => lux_evdev::module(lua),
+            #[cfg(feature = "autoinput")]    Self::AutoInput   => lux_autoinput::module(lua),
+            #[cfg(feature = "hotkey")]       Self::Hotkey      => lux_hotkey::module(lua),
+            #[cfg(feature = "time")]         Self::Time        => lux_time::module(lua),
+            #[cfg(feature = "channel")]      Self::Channel     => lux_channel::module(lua),
+            #[cfg(feature = "sync")]         Self::Sync        => lux_sync::module(lua),
+            #[cfg(feature = "binparse")]     Self::BinParse    => lux_binparse::module(lua),
+            #[cfg(feature = "serve")]        Self::Serve       => lux_serve::module(lua),
+            #[cfg(feature = "template")]     Self::Template    => lux_template::module(lua),
+            #[cfg(feature = "reactor")]      Self::Reactor     => lux_reactor::module(lua),
             _ => unreachable!(),
         };
         res.map_err(|e| e.context(format!("Failed to create library '{}'", self.name())))
@@ -113,6 +325,39 @@ impl FromStr for LuxStandardLibrary {
             #[cfg(feature = "uuid")]       "uuid"       => Self::Uuid,
             #[cfg(feature = "noise")]      "noise"      => Self::Noise,
             #[cfg(feature = "base64")]     "base64"     => Self::Base64,
+            #[cfg(feature = "crypto")]     "crypto"     => Self::Crypto,
+            #[cfg(feature = "image")]      "image"      => Self::Image,
+            #[cfg(feature = "websocket")]  "websocket"  => Self::WebSocket,
+            #[cfg(feature = "socket")]     "socket"     => Self::Socket,
+            #[cfg(feature = "stream")]     "stream"     => Self::Stream,
+            #[cfg(feature = "test")]       "test"       => Self::Test,
+            #[cfg(feature = "fmt")]        "fmt"        => Self::Fmt,
+            #[cfg(feature = "buffer-extra")] "buffer-extra" => Self::BufferExtra,
+            #[cfg(feature = "pathfind")]     "pathfind"     => Self::Pathfind,
+            #[cfg(feature = "random")]       "random"       => Self::Random,
+            #[cfg(feature = "profiler")]     "profiler"     => Self::Profiler,
+            #[cfg(feature = "gc")]           "gc"           => Self::Gc,
+            #[cfg(feature = "env")]          "env"          => Self::Env,
+            #[cfg(feature = "term")]         "term"         => Self::Term,
+            #[cfg(feature = "desktop")]      "desktop"      => Self::Desktop,
+            #[cfg(feature = "bindgen")]      "bindgen"      => Self::Bindgen,
+            #[cfg(feature = "inspect")]      "inspect"      => Self::Inspect,
+            #[cfg(feature = "easing")]       "easing"       => Self::Easing,
+            #[cfg(feature = "layout")]       "layout"       => Self::Layout,
+            #[cfg(feature = "tablex")]       "tablex"       => Self::Tablex,
+            #[cfg(feature = "csv")]          "csv"          => Self::Csv,
+            #[cfg(feature = "semver")]       "semver"       => Self::Semver,
+            #[cfg(feature = "winreg")]       "winreg"       => Self::WinReg,
+            #[cfg(feature = "evdev")]        "evdev"        => Self::Evdev,
+            #[cfg(feature = "autoinput")]    "autoinput"    => Self::AutoInput,
+            #[cfg(feature = "hotkey")]       "hotkey"       => Self::Hotkey,
+            #[cfg(feature = "time")]         "time"         => Self::Time,
+            #[cfg(feature = "channel")]      "channel"      => Self::Channel,
+            #[cfg(feature = "sync")]         "sync"         => Self::Sync,
+            #[cfg(feature = "binparse")]     "binparse"     => Self::BinParse,
+            #[cfg(feature = "serve")]        "serve"        => Self::Serve,
+            #[cfg(feature = "template")]     "template"     => Self::Template,
+            #[cfg(feature = "reactor")]      "reactor"      => Self::Reactor,
             _ => return Err(format!("Unknown library '{low}'")),
         })
     }
@@ -0,0 +1,216 @@
+use std::path::Path;
+
+use lux_utils::process::{Permission, ProcessPermissions};
+use mlua::prelude::*;
+
+use crate::library::LuxStandardLibrary;
+
+// NOTE: Checks run in a Luau wrapper instead of calling the wrapped function
+// from Rust, so that async functions such as fs.readFile can still yield.
+const CHECKED_SOURCE: &str = r"
+local check, f = ...
+return function(...)
+    check(...)
+    return f(...)
+end
+";
+
+/**
+    Returns the functions of a library that need its permission, or
+    `None` if every part of the library needs it.
+
+    Libraries such as `process` are also used for harmless things,
+    such as reading `process.args`, which should never be denied.
+    Functions in nested tables are named by their path, like `ndjson.reader`.
+*/
+fn gated_functions(library: &str) -> Option<&'static [&'static str]> {
+    match library {
+        "process" => Some(&["exec", "create"]),
+        "csv" => Some(&["reader", "writer", "ndjson.reader", "ndjson.writer"]),
+        "image" => Some(&["readFile"]),
+        "env" => Some(&["load"]),
+        // NOTE: Only the parseFile method of structs reads files, and checks paths itself
+        "binparse" => Some(&[]),
+        _ => None,
+    }
+}
+
+/**
+    Returns the number of leading arguments of a function that are paths.
+*/
+fn path_args(library: &str, function: &str) -> usize {
+    match (library, function) {
+        ("fs", "move" | "copy") => 2,
+        _ => 1,
+    }
+}
+
+/**
+    Returns the path that a function uses when it is not given one.
+*/
+fn default_path(library: &str, function: &str) -> Option<&'static str> {
+    match (library, function) {
+        ("env", "load") => Some(".env"),
+        _ => None,
+    }
+}
+
+/**
+    Gates a library module behind its permission, if it has one.
+
+    Modules for denied permissions are replaced with ones that raise an error naming
+    the missing permission when used, and modules needing the `fs` permission check every
+    path they are given against the allowed paths, if the permission was limited to any.
+*/
+pub(crate) fn gate(
+    lua: &Lua,
+    library: LuxStandardLibrary,
+    module: LuaTable,
+) -> LuaResult<LuaTable> {
+    let Some(permission) = library.permission() else {
+        return Ok(module);
+    };
+    let perms = lua
+        .app_data_ref::<ProcessPermissions>()
+        .map(|perms| ProcessPermissions::clone(&perms))
+        .unwrap_or_default();
+    let name = library.name();
+
+    if !perms.is_granted(permission) {
+        return match gated_functions(name) {
+            Some(functions) => wrap_functions(lua, &module, functions, |lua, key| {
+                let what = format!("{name}.{key}");
+                lua.create_function(move |lua, _: LuaMultiValue| {
+                    ProcessPermissions::check(lua, permission, &what)
+                })
+            }),
+            None => denied(lua, name, permission),
+        };
+    }
+
+    if permission == Permission::Fs && !perms.fs_paths().is_empty() {
+        let all = module
+            .clone()
+            .pairs::<String, LuaValue>()
+            .filter_map(|pair| match pair {
+                Ok((key, LuaValue::Function(_))) => Some(key),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let functions = gated_functions(name).map_or_else(
+            || all.iter().map(String::as_str).collect(),
+            <[&str]>::to_vec,
+        );
+        return wrap_functions(lua, &module, &functions, |lua, key| {
+            let what = format!("{name}.{key}");
+            let count = path_args(name, key);
+            let default = default_path(name, key);
+            lua.create_function(move |lua, args: LuaMultiValue| {
+                for index in 0..count {
+                    match (args.get(index), default) {
+                        (Some(LuaValue::String(s)), _) => {
+                            let path = s.to_str()?;
+                            ProcessPermissions::check_path(lua, Path::new(&*path), &what)?;
+                        }
+                        (None | Some(LuaValue::Nil), Some(path)) if index == 0 => {
+                            ProcessPermissions::check_path(lua, Path::new(path), &what)?;
+                        }
+                        _ => {}
+                    }
+                }
+                Ok(())
+            })
+        });
+    }
+
+    Ok(module)
+}
+
+/**
+    Creates a module that errors with a message naming the
+    missing permission whenever any of its members are accessed.
+*/
+fn denied(lua: &Lua, name: &'static str, permission: Permission) -> LuaResult<LuaTable> {
+    let index = lua.create_function(move |lua, (_, key): (LuaTable, LuaValue)| {
+        let key = key.to_string()?;
+        ProcessPermissions::check(lua, permission, &format!("{name}.{key}"))?;
+        Ok(LuaValue::Nil)
+    })?;
+
+    let meta = lua.create_table()?;
+    meta.set(LuaMetaMethod::Index.name(), index)?;
+    meta.set_readonly(true);
+
+    let module = lua.create_table()?;
+    module.set_metatable(Some(meta))?;
+    module.set_readonly(true);
+    Ok(module)
+}
+
+/**
+    Creates a copy of a module where the given functions
+    first call a check function with the same arguments.
+
+    Functions in nested tables, such as `ndjson.reader`,
+    are wrapped in a copy of the nested table.
+*/
+fn wrap_functions(
+    lua: &Lua,
+    module: &LuaTable,
+    functions: &[&str],
+    make_check: impl Fn(&Lua, &str) -> LuaResult<LuaFunction>,
+) -> LuaResult<LuaTable> {
+    wrap_table(lua, module, "", functions, &make_check)
+}
+
+fn wrap_table(
+    lua: &Lua,
+    table: &LuaTable,
+    prefix: &str,
+    functions: &[&str],
+    make_check: &impl Fn(&Lua, &str) -> LuaResult<LuaFunction>,
+) -> LuaResult<LuaTable> {
+    let wrapped = lua.create_table()?;
+    for pair in table.clone().pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        wrapped.raw_set(key, value)?;
+    }
+
+    let mut nested = Vec::new();
+    for &function in functions {
+        if let Some((table_name, _)) = function.split_once('.') {
+            if !nested.contains(&table_name) {
+                nested.push(table_name);
+            }
+            continue;
+        }
+        let Some(original) = table.get::<Option<LuaFunction>>(function)? else {
+            continue;
+        };
+        let check = make_check(lua, &format!("{prefix}{function}"))?;
+        let checked = lua
+            .load(CHECKED_SOURCE)
+            .set_name("=[C]")
+            .call::<LuaFunction>((check, original))?;
+        wrapped.raw_set(function, checked)?;
+    }
+
+    for name in nested {
+        let Some(inner) = table.get::<Option<LuaTable>>(name)? else {
+            continue;
+        };
+        let inner_functions = functions
+            .iter()
+            .filter_map(|f| f.strip_prefix(name)?.strip_prefix('.'))
+            .collect::<Vec<_>>();
+        let prefix = format!("{prefix}{name}.");
+        let inner = wrap_table(lua, &inner, &prefix, &inner_functions, make_check)?;
+        wrapped.raw_set(name, inner)?;
+    }
+
+    if let Some(meta) = table.metatable() {
+        wrapped.set_metatable(Some(meta))?;
+    }
+    wrapped.set_readonly(true);
+    Ok(wrapped)
+}
@@ -8,7 +8,11 @@ use std::{
 use async_channel::{Receiver, Sender};
 use async_fs::read as read_file;
 
-use lux_utils::path::constants::FILE_CHUNK_PREFIX;
+use lux_utils::{
+    bytecode::{self, BytecodeCache},
+    path::constants::FILE_CHUNK_PREFIX,
+    snapshot::ModuleSnapshot,
+};
 use mlua::prelude::*;
 use mlua_luau_scheduler::LuaSchedulerExt;
 
@@ -93,7 +97,7 @@ impl RequireLoader {
                     let tx = state.create_pending_at_path(&absolute_path);
 
                     let chunk_name = format!("{FILE_CHUNK_PREFIX}{}", relative_path.display());
-                    let chunk_bytes = read_file(&absolute_path).await?;
+                    let chunk_bytes = load_bytecode(&lua, &absolute_path).await?;
 
                     let chunk = lua.load(chunk_bytes).set_name(chunk_name);
 
@@ -118,3 +122,33 @@ impl RequireLoader {
         })
     }
 }
+
+/**
+    Returns the bytecode for the module at the given path, from the module snapshot of
+    the runtime if it has one, otherwise compiling it - and storing it in the snapshot.
+
+    Modules that fail to compile are returned as source, so that
+    loading them reports syntax errors with the usual chunk name.
+*/
+async fn load_bytecode(lua: &Lua, path: &Path) -> LuaResult<Vec<u8>> {
+    let snapshotted = lua
+        .app_data_mut::<ModuleSnapshot>()
+        .and_then(|mut snapshot| snapshot.restore(path));
+    if let Some(bytecode) = snapshotted {
+        return Ok(bytecode);
+    }
+
+    let source = read_file(path).await?;
+    let cache = lua
+        .app_data_ref::<BytecodeCache>()
+        .map(|cache| BytecodeCache::clone(&cache));
+    let bytecode = match cache {
+        Some(cache) => cache.load(path, source).await,
+        None => bytecode::compiler().compile(&source).unwrap_or(source),
+    };
+
+    if let Some(mut snapshot) = lua.app_data_mut::<ModuleSnapshot>() {
+        snapshot.insert(path, bytecode.clone());
+    }
+    Ok(bytecode)
+}
@@ -1,4 +1,7 @@
 mod loader;
+mod reload;
 mod resolver;
 
+pub use self::reload::invalidate_module;
+pub(crate) use self::reload::{RequireState, create_reload};
 pub(crate) use self::resolver::RequireResolver;
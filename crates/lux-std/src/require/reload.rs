@@ -0,0 +1,137 @@
+use std::{cell::RefCell, path::Path, rc::Rc};
+
+use lux_utils::path::{LuauModulePath, clean_path_and_make_absolute, constants::FILE_CHUNK_PREFIX};
+use mlua::prelude::*;
+
+/**
+    Registry keys of the tables where `require` caches modules, by cache key.
+
+    Luau caches the loader function of each module, and mlua caches the results of calling them.
+*/
+const CACHE_KEYS: [&str; 2] = ["_MODULES", "__MLUA_LOADER_CACHE"];
+
+const RELOAD_SOURCE: &str = r"
+local prepare, require, reloaded = ...
+return function(path)
+    local load, key = prepare(path)
+    local module = load(require, path)
+    if reloaded then
+        reloaded:Fire(key, module)
+    end
+    return module
+end
+";
+
+#[derive(Debug, Default)]
+struct RequireStateInner {
+    last_cache_key: Option<String>,
+    dry_run: bool,
+}
+
+/**
+    State shared between the require resolver and `require.reload`.
+
+    While a dry run is active, modules that are not cached yet
+    resolve without running, so that their cache key can be found.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RequireState {
+    inner: Rc<RefCell<RequireStateInner>>,
+}
+
+impl RequireState {
+    pub(crate) fn set_last_cache_key(&self, key: &str) {
+        self.inner.borrow_mut().last_cache_key = Some(key.to_string());
+    }
+
+    pub(crate) fn is_dry_run(&self) -> bool {
+        self.inner.borrow().dry_run
+    }
+
+    /// Resolves a path using the given require shim, without running the module
+    fn resolve_cache_key(
+        &self,
+        shim: &LuaFunction,
+        require: &LuaFunction,
+        path: &str,
+    ) -> LuaResult<String> {
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner.last_cache_key = None;
+            inner.dry_run = true;
+        }
+        let result = shim.call::<LuaValue>((require.clone(), path));
+        let mut inner = self.inner.borrow_mut();
+        inner.dry_run = false;
+        result?;
+        inner
+            .last_cache_key
+            .take()
+            .ok_or_else(|| LuaError::runtime(format!("Failed to resolve module '{path}'")))
+    }
+}
+
+/// Removes a cached module by its cache key, returning whether it was cached
+fn invalidate_cache_key(lua: &Lua, key: &str) -> LuaResult<bool> {
+    let mut cached = false;
+    for cache_key in CACHE_KEYS {
+        if let LuaValue::Table(cache) = lua.named_registry_value::<LuaValue>(cache_key)? {
+            cached |= !cache.raw_get::<LuaValue>(key)?.is_nil();
+            cache.raw_set(key, LuaValue::Nil)?;
+        }
+    }
+    Ok(cached)
+}
+
+/**
+    Invalidates a module required by the given file or module path, so that
+    the next time it gets required, it is read from the filesystem and runs again.
+
+    Returns whether the module had been required and was cached.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn invalidate_module(lua: &Lua, path: impl AsRef<Path>) -> LuaResult<bool> {
+    let module_path = clean_path_and_make_absolute(LuauModulePath::strip(path.as_ref()));
+    invalidate_cache_key(lua, &module_path.display().to_string())
+}
+
+/**
+    Creates `require.reload`, which invalidates a module and requires it again.
+
+    Paths passed to `require` resolve relative to the file calling it, which
+    is found by looking at the stack. To resolve paths passed to `reload` the
+    same way, modules are required through a tiny shim chunk that gets the
+    same chunk name as the file calling `reload`.
+*/
+pub(crate) fn create_reload(
+    lua: &Lua,
+    require: LuaFunction,
+    state: RequireState,
+    reloaded: Option<LuaValue>,
+) -> LuaResult<LuaFunction> {
+    let resolve_require = require.clone();
+    let prepare = lua.create_function(move |lua, path: String| {
+        // Level 0 is this function, level 1 is the reload function, level 2 called it
+        let chunk_name = lua
+            .inspect_stack(2, |debug| debug.source().source.map(|s| s.to_string()))
+            .flatten()
+            .filter(|name| name.starts_with(FILE_CHUNK_PREFIX))
+            .ok_or_else(|| LuaError::runtime("require.reload can only be called from files"))?;
+
+        let shim = lua
+            .load("local require, path = ... return require(path)")
+            .set_name(chunk_name)
+            .into_function()?;
+
+        let key = state.resolve_cache_key(&shim, &resolve_require, &path)?;
+        invalidate_cache_key(lua, &key)?;
+        Ok((shim, key))
+    })?;
+
+    lua.load(RELOAD_SOURCE)
+        .set_name("=require.reload")
+        .call((prepare, require, reloaded))
+}
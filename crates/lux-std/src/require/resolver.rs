@@ -4,16 +4,18 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use lux_utils::path::{
-    LuauModulePath, clean_path_and_make_absolute,
-    constants::{FILE_CHUNK_PREFIX, FILE_NAME_CONFIG},
-    relative_path_normalize, relative_path_parent,
+use lux_utils::{
+    path::{
+        LuauModulePath, clean_path_and_make_absolute,
+        constants::{FILE_CHUNK_PREFIX, FILE_NAME_CONFIG},
+        relative_path_normalize, relative_path_parent,
+    },
+    snapshot::ModuleSnapshot,
 };
 use mlua::prelude::*;
 
-use super::loader::RequireLoader;
+use super::{loader::RequireLoader, reload::RequireState};
 
-#[derive(Debug)]
 pub(crate) struct RequireResolver {
     /// Path to the current module, absolute.
     ///
@@ -30,15 +32,37 @@ pub(crate) struct RequireResolver {
     resolved: Option<LuauModulePath>,
     /// Loader and accompanying state.
     loader: RequireLoader,
+    /// State shared with `require.reload`.
+    state: RequireState,
+    /// The VM this resolver belongs to, for its module snapshot.
+    lua: WeakLua,
 }
 
 impl RequireResolver {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(lua: &Lua, state: RequireState) -> Self {
         Self {
             relative: PathBuf::new(),
             absolute: PathBuf::new(),
             resolved: None,
             loader: RequireLoader::new(),
+            state,
+            lua: lua.weak(),
+        }
+    }
+
+    /**
+        Resolves a module path to a file or directory, looking in the module
+        snapshot of the runtime first, so that modules restored from it are
+        found even when their source files do not exist on the filesystem.
+    */
+    fn resolve(&self, absolute: &Path) -> Result<LuauModulePath, LuaNavigateError> {
+        let snapshotted = self.lua.try_upgrade().and_then(|lua| {
+            let snapshot = lua.app_data_ref::<ModuleSnapshot>()?;
+            LuauModulePath::resolve_in_snapshot(absolute, &snapshot).ok()
+        });
+        match snapshotted {
+            Some(resolved) => Ok(resolved),
+            None => LuauModulePath::resolve(absolute),
         }
     }
 
@@ -61,7 +85,7 @@ impl RequireResolver {
         }
 
         // Make sure to resolve path **before** updating any paths state
-        let resolved = LuauModulePath::resolve(&absolute)?;
+        let resolved = self.resolve(&absolute)?;
 
         self.absolute = absolute;
         self.relative = relative;
@@ -137,7 +161,9 @@ impl LuaRequire for RequireResolver {
 
     fn cache_key(&self) -> String {
         let resolved = self.resolved.as_ref();
-        resolved.expect("called has_module first").to_string()
+        let key = resolved.expect("called has_module first").to_string();
+        self.state.set_last_cache_key(&key);
+        key
     }
 
     fn has_config(&self) -> bool {
@@ -152,6 +178,10 @@ impl LuaRequire for RequireResolver {
         let resolved = self.resolved.as_ref();
         let resolved = resolved.expect("called has_module first");
         let resolved = resolved.target().as_file().expect("tried to require a dir");
+        if self.state.is_dry_run() {
+            // Resolving for `require.reload`, the module should not run yet
+            return lua.create_function(|_, ()| Ok(true));
+        }
         self.loader.load(lua, self.relative.as_path(), resolved)
     }
 }
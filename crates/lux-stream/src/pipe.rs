@@ -0,0 +1,140 @@
+use mlua::prelude::*;
+
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/**
+    Options for piping a source stream into a sink stream.
+*/
+#[derive(Debug, Clone, Copy)]
+pub struct PipeOptions {
+    /// Maximum number of bytes to pipe before erroring.
+    pub limit: Option<usize>,
+    /// Number of bytes to request from the source per read.
+    pub chunk_size: usize,
+    /// Whether to close the sink once the source is exhausted.
+    pub close: bool,
+}
+
+impl Default for PipeOptions {
+    fn default() -> Self {
+        Self {
+            limit: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            close: true,
+        }
+    }
+}
+
+impl FromLua for PipeOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let defaults = Self::default();
+        match value {
+            LuaValue::Nil => Ok(defaults),
+            LuaValue::Table(t) => {
+                let chunk_size = t
+                    .get::<Option<usize>>("chunkSize")?
+                    .unwrap_or(defaults.chunk_size);
+                if chunk_size == 0 {
+                    return Err(LuaError::runtime("Pipe chunk size must be at least 1"));
+                }
+                Ok(Self {
+                    limit: t.get("limit")?,
+                    chunk_size,
+                    close: t.get::<Option<bool>>("close")?.unwrap_or(defaults.close),
+                })
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "PipeOptions".to_string(),
+                message: Some(format!(
+                    "Invalid pipe options - expected table or nil, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn chunk_len(chunk: &LuaValue) -> LuaResult<Option<usize>> {
+    match chunk {
+        LuaValue::Nil => Ok(None),
+        LuaValue::String(s) => Ok(Some(s.as_bytes().len())),
+        LuaValue::Buffer(b) => Ok(Some(b.len())),
+        other => Err(LuaError::runtime(format!(
+            "Pipe source returned '{}' from read, expected string, buffer or nil",
+            other.type_name()
+        ))),
+    }
+}
+
+fn check_stream(value: &LuaValue, method: &str, role: &str) -> LuaResult<()> {
+    let has_method = match value {
+        LuaValue::Table(t) => t.get::<LuaValue>(method)?.is_function(),
+        LuaValue::UserData(ud) => ud.get::<LuaValue>(method).is_ok_and(|m| m.is_function()),
+        _ => false,
+    };
+    if has_method {
+        Ok(())
+    } else {
+        Err(LuaError::runtime(format!(
+            "Pipe {role} must have a '{method}' method, got {}",
+            value.type_name()
+        )))
+    }
+}
+
+/**
+    Reads chunks from `source` and writes them to `sink` until the source is exhausted.
+
+    Only a single chunk is ever in flight - the next read does not start until the
+    sink has accepted the previous chunk, so a slow sink naturally slows down the
+    source instead of data piling up in memory.
+
+    Returns the total number of bytes piped.
+
+    # Errors
+
+    Errors if either stream errors, or if more than `limit` bytes would be piped.
+*/
+pub async fn pipe(
+    _: Lua,
+    (source, sink, options): (LuaValue, LuaValue, PipeOptions),
+) -> LuaResult<usize> {
+    check_stream(&source, "read", "source")?;
+    check_stream(&sink, "write", "sink")?;
+
+    let mut total = 0usize;
+    loop {
+        let chunk: LuaValue = call_method(&source, "read", options.chunk_size).await?;
+        let Some(len) = chunk_len(&chunk)? else {
+            break;
+        };
+        if let Some(limit) = options.limit
+            && total + len > limit
+        {
+            return Err(LuaError::runtime(format!(
+                "Pipe exceeded its limit of {limit} bytes"
+            )));
+        }
+        call_method::<()>(&sink, "write", chunk).await?;
+        total += len;
+    }
+
+    if options.close && check_stream(&sink, "close", "sink").is_ok() {
+        call_method::<()>(&sink, "close", ()).await?;
+    }
+
+    Ok(total)
+}
+
+async fn call_method<R: FromLuaMulti>(
+    object: &LuaValue,
+    method: &str,
+    args: impl IntoLuaMulti,
+) -> LuaResult<R> {
+    match object {
+        LuaValue::Table(t) => t.call_async_method(method, args).await,
+        LuaValue::UserData(ud) => ud.call_async_method(method, args).await,
+        _ => unreachable!("checked by check_stream"),
+    }
+}
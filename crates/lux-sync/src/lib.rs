@@ -0,0 +1,53 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Mutexes and semaphores for cooperative tasks in Lux
+
+use lux_utils::TableBuilder;
+use mlua::prelude::*;
+
+mod mutex;
+mod semaphore;
+mod tasks;
+
+pub use self::mutex::Mutex;
+pub use self::semaphore::Semaphore;
+
+use self::tasks::Tasks;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+fn semaphore_new(_: &Lua, permits: f64) -> LuaResult<Semaphore> {
+    if permits >= 1.0 && permits.fract() == 0.0 {
+        Ok(Semaphore::new(permits as usize))
+    } else {
+        Err(LuaError::runtime(format!(
+            "Expected permits to be a positive integer, got {permits}"
+        )))
+    }
+}
+
+/**
+    Creates the `sync` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let tasks = Tasks::shared();
+    let mutex = TableBuilder::new(lua.clone())?
+        .with_function("new", move |_, ()| Ok(Mutex::new(tasks.clone())))?
+        .build_readonly()?;
+    let semaphore = TableBuilder::new(lua.clone())?
+        .with_function("new", semaphore_new)?
+        .build_readonly()?;
+    TableBuilder::new(lua)?
+        .with_value("mutex", mutex)?
+        .with_value("semaphore", semaphore)?
+        .build_readonly()
+}
@@ -0,0 +1,203 @@
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use async_lock::{Semaphore, SemaphoreGuardArc};
+
+use mlua::prelude::*;
+
+use crate::tasks::{Child, SharedTasks, Waiting};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// The task holding a mutex, and where it was locked for deadlock reports
+struct Holder {
+    task: usize,
+    traceback: String,
+    _permit: SemaphoreGuardArc,
+}
+
+/**
+    A mutex for cooperative tasks, which are only ever run one at a time,
+    but may still interleave whenever one of them yields.
+
+    The mutex belongs to the task that locked it, and only that task may unlock it.
+    Tasks waiting to lock the mutex are woken in the order that they started waiting.
+*/
+#[derive(Clone)]
+pub struct Mutex {
+    id: usize,
+    permit: Arc<Semaphore>,
+    holder: Arc<parking_lot::Mutex<Option<Holder>>>,
+    tasks: SharedTasks,
+}
+
+impl Mutex {
+    pub(crate) fn new(tasks: SharedTasks) -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            permit: Arc::new(Semaphore::new(1)),
+            holder: Arc::new(parking_lot::Mutex::new(None)),
+            tasks,
+        }
+    }
+
+    /// Returns the task holding the mutex and where it was locked, if it is locked
+    pub(crate) fn holder(&self) -> Option<(usize, String)> {
+        self.holder
+            .lock()
+            .as_ref()
+            .map(|holder| (holder.task, holder.traceback.clone()))
+    }
+
+    fn hold(&self, task: usize, traceback: String, permit: SemaphoreGuardArc) {
+        *self.holder.lock() = Some(Holder {
+            task,
+            traceback,
+            _permit: permit,
+        });
+    }
+
+    async fn lock(&self, task: usize, traceback: String) -> LuaResult<()> {
+        if let Some(permit) = self.permit.try_acquire_arc() {
+            self.hold(task, traceback, permit);
+            return Ok(());
+        }
+
+        let deadlock = self.tasks.lock().find_deadlock(self, task);
+        if let Some(report) = deadlock {
+            return Err(LuaError::runtime(format!(
+                "Deadlock detected, {self} would never be unlocked:{report}"
+            )));
+        }
+
+        let waiting = Waiting::new(&self.tasks, task, self);
+        let permit = self.permit.acquire_arc().await;
+        drop(waiting);
+        self.hold(task, traceback, permit);
+        Ok(())
+    }
+
+    fn try_lock(&self, task: usize, traceback: String) -> bool {
+        match self.permit.try_acquire_arc() {
+            Some(permit) => {
+                self.hold(task, traceback, permit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn unlock(&self, task: usize) -> LuaResult<()> {
+        let mut holder = self.holder.lock();
+        match holder.as_ref() {
+            None => Err(LuaError::runtime(format!(
+                "Cannot unlock {self}, it is not locked"
+            ))),
+            Some(h) if h.task != task => Err(LuaError::runtime(format!(
+                "Cannot unlock {self}, it is held by another task"
+            ))),
+            Some(_) => {
+                *holder = None;
+                Ok(())
+            }
+        }
+    }
+
+    async fn with_lock(
+        &self,
+        lua: Lua,
+        task: usize,
+        traceback: String,
+        (func, args): (LuaFunction, LuaMultiValue),
+    ) -> LuaResult<LuaMultiValue> {
+        self.lock(task, traceback).await?;
+        let _guard = Guard {
+            mutex: self.clone(),
+            task,
+        };
+        // NOTE: The function runs on its own thread, which must count as the
+        // same task, so that locking this mutex again inside of it is caught
+        let thread = lua.create_thread(func)?;
+        let _child = Child::new(&self.tasks, &thread, task);
+        thread.into_async::<LuaMultiValue>(args)?.await
+    }
+}
+
+impl fmt::Display for Mutex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Mutex #{}", self.id)
+    }
+}
+
+/**
+    Unlocks a mutex locked by `withLock` when dropped, so that it is
+    unlocked even if the function errors or its task is cancelled.
+*/
+struct Guard {
+    mutex: Mutex,
+    task: usize,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        let mut holder = self.mutex.holder.lock();
+        // NOTE: The function may have unlocked the mutex itself, after which another task may hold it
+        if holder.as_ref().is_some_and(|h| h.task == self.task) {
+            *holder = None;
+        }
+    }
+}
+
+fn traceback(lua: &Lua) -> String {
+    lua.traceback(None, 1)
+        .map(|t| t.to_string_lossy())
+        .unwrap_or_default()
+}
+
+impl LuaUserData for Mutex {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Mutex");
+        fields.add_field_method_get("isLocked", |_, this| Ok(this.holder.lock().is_some()));
+        fields.add_field_method_get("lockedAt", |_, this| {
+            Ok(this.holder().map(|(_, traceback)| traceback))
+        });
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("lock", |lua, this, (): ()| {
+            let this = this.clone();
+            let task = this.tasks.lock().current(&lua);
+            let traceback = traceback(&lua);
+            async move { this.lock(task, traceback).await }
+        });
+        methods.add_method("tryLock", |lua, this, (): ()| {
+            let task = this.tasks.lock().current(lua);
+            Ok(this.try_lock(task, traceback(lua)))
+        });
+        methods.add_method("unlock", |lua, this, (): ()| {
+            let task = this.tasks.lock().current(lua);
+            this.unlock(task)
+        });
+        methods.add_async_method(
+            "withLock",
+            |lua, this, args: (LuaFunction, LuaMultiValue)| {
+                let this = this.clone();
+                let task = this.tasks.lock().current(&lua);
+                let traceback = traceback(&lua);
+                async move { this.with_lock(lua, task, traceback, args).await }
+            },
+        );
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(if this.holder.lock().is_some() {
+                format!("{this} (locked)")
+            } else {
+                this.to_string()
+            })
+        });
+    }
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use async_lock::SemaphoreGuardArc;
+
+use mlua::prelude::*;
+
+/**
+    A semaphore that limits how many tasks may do something at once.
+
+    Unlike a mutex, permits do not belong to the task that acquired them,
+    so any task may release a permit that another task acquired.
+*/
+#[derive(Clone)]
+pub struct Semaphore {
+    permits: Arc<async_lock::Semaphore>,
+    held: Arc<parking_lot::Mutex<Vec<SemaphoreGuardArc>>>,
+    total: usize,
+}
+
+impl Semaphore {
+    /**
+        Creates a new semaphore with the given number of permits.
+    */
+    #[must_use]
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: Arc::new(async_lock::Semaphore::new(permits)),
+            held: Arc::new(parking_lot::Mutex::new(Vec::new())),
+            total: permits,
+        }
+    }
+
+    fn available(&self) -> usize {
+        self.total - self.held.lock().len()
+    }
+
+    async fn acquire(&self) {
+        let permit = self.permits.acquire_arc().await;
+        self.held.lock().push(permit);
+    }
+
+    fn try_acquire(&self) -> bool {
+        match self.permits.try_acquire_arc() {
+            Some(permit) => {
+                self.held.lock().push(permit);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn release(&self) -> LuaResult<()> {
+        match self.held.lock().pop() {
+            Some(_) => Ok(()),
+            None => Err(LuaError::runtime(
+                "Cannot release a permit, none of them are acquired",
+            )),
+        }
+    }
+
+    async fn with_permit(
+        &self,
+        func: LuaFunction,
+        args: LuaMultiValue,
+    ) -> LuaResult<LuaMultiValue> {
+        self.acquire().await;
+        let _guard = Guard(self.clone());
+        func.call_async(args).await
+    }
+}
+
+/**
+    Releases a permit acquired by `withPermit` when dropped, so that it is
+    released even if the function errors or its task is cancelled.
+*/
+struct Guard(Semaphore);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        // NOTE: Permits are all the same, and the function may have released one itself
+        self.0.held.lock().pop();
+    }
+}
+
+impl LuaUserData for Semaphore {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Semaphore");
+        fields.add_field_method_get("permits", |_, this| Ok(this.total));
+        fields.add_field_method_get("available", |_, this| Ok(this.available()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("acquire", |_, this, (): ()| {
+            let this = this.clone();
+            async move {
+                this.acquire().await;
+                Ok(())
+            }
+        });
+        methods.add_method("tryAcquire", |_, this, (): ()| Ok(this.try_acquire()));
+        methods.add_method("release", |_, this, (): ()| this.release());
+        methods.add_async_method(
+            "withPermit",
+            |_, this, (func, args): (LuaFunction, LuaMultiValue)| {
+                let this = this.clone();
+                async move { this.with_permit(func, args).await }
+            },
+        );
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, (): ()| {
+            Ok(format!("Semaphore({}/{})", this.available(), this.total))
+        });
+    }
+}
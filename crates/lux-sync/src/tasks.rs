@@ -0,0 +1,108 @@
+use std::{collections::HashMap, fmt::Write, sync::Arc};
+
+use mlua::prelude::*;
+
+use crate::mutex::Mutex;
+
+/**
+    Keeps track of which task each thread belongs to, and which mutex each task is waiting for.
+
+    `withLock` runs its function on a new thread, which still belongs to the task that
+    called it - locking the same mutex again from inside of it must be caught as a deadlock.
+*/
+#[derive(Default)]
+pub(crate) struct Tasks {
+    parents: HashMap<usize, usize>,
+    waiting: HashMap<usize, Mutex>,
+}
+
+pub(crate) type SharedTasks = Arc<parking_lot::Mutex<Tasks>>;
+
+impl Tasks {
+    pub fn shared() -> SharedTasks {
+        Arc::new(parking_lot::Mutex::new(Self::default()))
+    }
+
+    /// Returns the task that a thread belongs to, identified by the thread that started it
+    pub fn task_of(&self, mut thread: usize) -> usize {
+        while let Some(&parent) = self.parents.get(&thread) {
+            thread = parent;
+        }
+        thread
+    }
+
+    /// Returns the task that the currently running thread belongs to
+    pub fn current(&self, lua: &Lua) -> usize {
+        self.task_of(lua.current_thread().to_pointer() as usize)
+    }
+
+    /**
+        Looks for a deadlock that waiting for the given mutex would cause, by following which
+        task holds it, which mutex that task is waiting for, and so on until reaching `task`.
+
+        Returns a description of every lock in the cycle along with where it was locked.
+    */
+    pub fn find_deadlock(&self, mutex: &Mutex, task: usize) -> Option<String> {
+        let mut report = String::new();
+        let mut mutex = mutex.clone();
+        for _ in 0..=self.waiting.len() {
+            let (holder, traceback) = mutex.holder()?;
+            let who = if holder == task { "this" } else { "another" };
+            let _ = write!(
+                report,
+                "\n{mutex} is held by {who} task, locked at:\n{}",
+                traceback.trim_end()
+            );
+            if holder == task {
+                return Some(report);
+            }
+            mutex = self.waiting.get(&holder)?.clone();
+        }
+        None
+    }
+}
+
+/// Marks a task as waiting for a mutex until dropped, even if the wait is cancelled
+pub(crate) struct Waiting {
+    tasks: SharedTasks,
+    task: usize,
+}
+
+impl Waiting {
+    pub fn new(tasks: &SharedTasks, task: usize, mutex: &Mutex) -> Self {
+        tasks.lock().waiting.insert(task, mutex.clone());
+        Self {
+            tasks: Arc::clone(tasks),
+            task,
+        }
+    }
+}
+
+impl Drop for Waiting {
+    fn drop(&mut self) {
+        self.tasks.lock().waiting.remove(&self.task);
+    }
+}
+
+/// Marks a thread as belonging to the task of another thread until dropped
+pub(crate) struct Child {
+    tasks: SharedTasks,
+    thread: usize,
+}
+
+impl Child {
+    pub fn new(tasks: &SharedTasks, thread: &LuaThread, parent: usize) -> Self {
+        let thread = thread.to_pointer() as usize;
+        tasks.lock().parents.insert(thread, parent);
+        Self {
+            tasks: Arc::clone(tasks),
+            thread,
+        }
+    }
+}
+
+impl Drop for Child {
+    fn drop(&mut self) {
+        self.tasks.lock().parents.remove(&self.thread);
+    }
+}
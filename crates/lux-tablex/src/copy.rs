@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet};
+
+use mlua::prelude::*;
+
+use lux_color::Color3;
+use lux_matrix::{Mat4, Quaternion};
+use lux_udim::{NumberRange, Rect, UDim, UDim2};
+use lux_vector::{Vector2, Vector3};
+
+/**
+    Copies a value and every table nested inside of it.
+
+    Tables referenced more than once, including tables containing themselves, are
+    copied once and referenced the same way in the copy. Metatables and keys are
+    shared with the original, and value types such as `Vector3` are cloned.
+*/
+pub(crate) fn deep_copy(lua: &Lua, value: &LuaValue) -> LuaResult<LuaValue> {
+    deep_copy_inner(lua, value, &mut HashMap::new())
+}
+
+fn deep_copy_inner(
+    lua: &Lua,
+    value: &LuaValue,
+    copies: &mut HashMap<usize, LuaTable>,
+) -> LuaResult<LuaValue> {
+    match value {
+        LuaValue::Table(t) => {
+            let ptr = t.to_pointer() as usize;
+            if let Some(copy) = copies.get(&ptr) {
+                return Ok(LuaValue::Table(copy.clone()));
+            }
+            let copy = lua.create_table_with_capacity(t.raw_len(), 0)?;
+            copies.insert(ptr, copy.clone());
+            for pair in t.pairs::<LuaValue, LuaValue>() {
+                let (key, value) = pair?;
+                copy.raw_set(key, deep_copy_inner(lua, &value, copies)?)?;
+            }
+            copy.set_metatable(t.metatable())?;
+            Ok(LuaValue::Table(copy))
+        }
+        LuaValue::UserData(ud) => clone_userdata(lua, ud),
+        other => Ok(other.clone()),
+    }
+}
+
+/**
+    Clones userdata of the value types provided by Lux, such as `Vector3`.
+
+    Any other userdata can not be cloned, and is returned as-is.
+*/
+fn clone_userdata(lua: &Lua, ud: &LuaAnyUserData) -> LuaResult<LuaValue> {
+    macro_rules! clone_as {
+        ($($ty:ty),* $(,)?) => {
+            $(
+                if let Ok(value) = ud.borrow::<$ty>() {
+                    return lua.create_userdata(*value).map(LuaValue::UserData);
+                }
+            )*
+        };
+    }
+    clone_as!(
+        Vector2,
+        Vector3,
+        Color3,
+        UDim,
+        UDim2,
+        Rect,
+        NumberRange,
+        Mat4,
+        Quaternion,
+    );
+    Ok(LuaValue::UserData(ud.clone()))
+}
+
+/**
+    Freezes a table and every table nested inside of it, making them read-only.
+*/
+pub(crate) fn deep_freeze(table: &LuaTable) -> LuaResult<()> {
+    deep_freeze_inner(table, &mut HashSet::new())
+}
+
+fn deep_freeze_inner(table: &LuaTable, visited: &mut HashSet<usize>) -> LuaResult<()> {
+    if !visited.insert(table.to_pointer() as usize) {
+        return Ok(());
+    }
+    for pair in table.pairs::<LuaValue, LuaValue>() {
+        if let (_, LuaValue::Table(nested)) = pair? {
+            deep_freeze_inner(&nested, visited)?;
+        }
+    }
+    table.set_readonly(true);
+    Ok(())
+}
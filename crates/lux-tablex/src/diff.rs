@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use mlua::prelude::*;
+
+use lux_utils::equality::deep_equals;
+
+/**
+    Finds the changes needed to turn table `a` into table `b`.
+
+    Each change is a table with an `op` of `"add"`, `"remove"` or `"replace"`,
+    the `path` to the changed key as an array of keys, and the new `value` for
+    additions and replacements. Nested tables are compared key by key, so
+    only the keys that actually changed are included.
+*/
+pub(crate) fn diff(lua: &Lua, a: &LuaTable, b: &LuaTable) -> LuaResult<LuaTable> {
+    let patch = lua.create_table()?;
+    diff_inner(lua, a, b, &mut Vec::new(), &patch, &mut HashSet::new())?;
+    Ok(patch)
+}
+
+fn diff_inner(
+    lua: &Lua,
+    a: &LuaTable,
+    b: &LuaTable,
+    path: &mut Vec<LuaValue>,
+    patch: &LuaTable,
+    visited: &mut HashSet<(usize, usize)>,
+) -> LuaResult<()> {
+    // NOTE: Tables that are already being compared further up are assumed
+    // to be equal, any difference will be found by that outer comparison
+    if a == b || !visited.insert((a.to_pointer() as usize, b.to_pointer() as usize)) {
+        return Ok(());
+    }
+
+    for pair in a.pairs::<LuaValue, LuaValue>() {
+        let (key, old) = pair?;
+        let new = b.raw_get::<LuaValue>(key.clone())?;
+        path.push(key);
+        match (&old, &new) {
+            (_, LuaValue::Nil) => push_change(lua, patch, "remove", path, None)?,
+            (LuaValue::Table(ta), LuaValue::Table(tb)) => {
+                diff_inner(lua, ta, tb, path, patch, visited)?;
+            }
+            _ => {
+                if !deep_equals(&old, &new)? {
+                    push_change(lua, patch, "replace", path, Some(new))?;
+                }
+            }
+        }
+        path.pop();
+    }
+
+    for pair in b.pairs::<LuaValue, LuaValue>() {
+        let (key, new) = pair?;
+        if a.raw_get::<LuaValue>(key.clone())?.is_nil() {
+            path.push(key);
+            push_change(lua, patch, "add", path, Some(new))?;
+            path.pop();
+        }
+    }
+
+    Ok(())
+}
+
+fn push_change(
+    lua: &Lua,
+    patch: &LuaTable,
+    op: &str,
+    path: &[LuaValue],
+    value: Option<LuaValue>,
+) -> LuaResult<()> {
+    let change = lua.create_table_with_capacity(0, 3)?;
+    change.raw_set("op", op)?;
+    change.raw_set("path", lua.create_sequence_from(path.iter().cloned())?)?;
+    if let Some(value) = value {
+        change.raw_set("value", value)?;
+    }
+    patch.raw_push(change)
+}
+
+/**
+    Applies changes created by [`diff`] to a table, in order.
+*/
+pub(crate) fn apply_patch(table: &LuaTable, patch: &LuaTable) -> LuaResult<()> {
+    for change in patch.sequence_values::<LuaTable>() {
+        let change = change?;
+        let op = change.get::<String>("op")?;
+        let path = change
+            .get::<LuaTable>("path")?
+            .sequence_values::<LuaValue>()
+            .collect::<LuaResult<Vec<_>>>()?;
+        let Some((key, parents)) = path.split_last() else {
+            return Err(LuaError::external("change has an empty path"));
+        };
+
+        let mut parent = table.clone();
+        for parent_key in parents {
+            parent = match parent.raw_get::<LuaValue>(parent_key.clone())? {
+                LuaValue::Table(t) => t,
+                other => {
+                    return Err(LuaError::external(format!(
+                        "can not apply change inside of '{}', it is a {}",
+                        parent_key.to_string()?,
+                        other.type_name()
+                    )));
+                }
+            };
+        }
+
+        match op.as_str() {
+            "add" | "replace" => parent.raw_set(key.clone(), change.get::<LuaValue>("value")?)?,
+            "remove" => parent.raw_set(key.clone(), LuaValue::Nil)?,
+            other => {
+                return Err(LuaError::external(format!(
+                    "invalid change op '{other}', expected 'add', 'remove' or 'replace'"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
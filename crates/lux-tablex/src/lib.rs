@@ -0,0 +1,100 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::{TableBuilder, equality::deep_equals};
+
+mod copy;
+mod diff;
+mod merge;
+mod path;
+
+use self::copy::{deep_copy, deep_freeze};
+use self::diff::{apply_patch, diff};
+use self::merge::{MergeStrategy, merge};
+use self::path::{get_path, path_keys, set_path};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `tablex` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `tablex` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("deepCopy", tablex_deep_copy)?
+        .with_function("deepEquals", tablex_deep_equals)?
+        .with_function("merge", tablex_merge)?
+        .with_function("freeze", tablex_freeze)?
+        .with_function("deepFreeze", tablex_deep_freeze)?
+        .with_function("get", tablex_get)?
+        .with_function("set", tablex_set)?
+        .with_function("diff", tablex_diff)?
+        .with_function("patch", tablex_patch)?
+        .build_readonly()
+}
+
+fn tablex_deep_copy(lua: &Lua, value: LuaValue) -> LuaResult<LuaValue> {
+    deep_copy(lua, &value)
+}
+
+fn tablex_deep_equals(_: &Lua, (a, b): (LuaValue, LuaValue)) -> LuaResult<bool> {
+    deep_equals(&a, &b)
+}
+
+fn tablex_merge(
+    lua: &Lua,
+    (target, source, strategy): (LuaTable, LuaTable, MergeStrategy),
+) -> LuaResult<LuaTable> {
+    merge(lua, &target, &source, strategy)
+}
+
+fn tablex_freeze(_: &Lua, table: LuaTable) -> LuaResult<LuaTable> {
+    table.set_readonly(true);
+    Ok(table)
+}
+
+fn tablex_deep_freeze(_: &Lua, table: LuaTable) -> LuaResult<LuaTable> {
+    deep_freeze(&table)?;
+    Ok(table)
+}
+
+fn tablex_get(
+    lua: &Lua,
+    (table, path, default): (LuaTable, LuaValue, LuaValue),
+) -> LuaResult<LuaValue> {
+    let keys = path_keys(lua, path)?;
+    match get_path(&table, &keys)? {
+        LuaValue::Nil => Ok(default),
+        value => Ok(value),
+    }
+}
+
+fn tablex_set(
+    lua: &Lua,
+    (table, path, value): (LuaTable, LuaValue, LuaValue),
+) -> LuaResult<LuaTable> {
+    let keys = path_keys(lua, path)?;
+    set_path(lua, &table, &keys, value)?;
+    Ok(table)
+}
+
+fn tablex_diff(lua: &Lua, (a, b): (LuaTable, LuaTable)) -> LuaResult<LuaTable> {
+    diff(lua, &a, &b)
+}
+
+fn tablex_patch(_: &Lua, (table, patch): (LuaTable, LuaTable)) -> LuaResult<LuaTable> {
+    apply_patch(&table, &patch)?;
+    Ok(table)
+}
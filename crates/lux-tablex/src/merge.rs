@@ -0,0 +1,121 @@
+use std::collections::HashSet;
+
+use mlua::prelude::*;
+
+/// How values from the source table are merged into the target table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum MergeStrategy {
+    /// Values from the source replace values in the target
+    #[default]
+    Replace,
+    /// Values in the target are kept, the source only fills in missing keys
+    Keep,
+    /// Like `Replace`, but tables in both are merged recursively instead of replaced
+    Deep,
+    /// Array items from the source are appended after the ones in the target
+    Concat,
+}
+
+impl FromLua for MergeStrategy {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let name = match &value {
+            LuaValue::Nil => return Ok(Self::default()),
+            LuaValue::String(s) => s.to_str()?.to_string(),
+            other => {
+                return Err(LuaError::FromLuaConversionError {
+                    from: other.type_name(),
+                    to: "MergeStrategy".to_string(),
+                    message: Some("expected a merge strategy name".into()),
+                });
+            }
+        };
+        Ok(match name.as_str() {
+            "replace" => Self::Replace,
+            "keep" => Self::Keep,
+            "deep" => Self::Deep,
+            "concat" => Self::Concat,
+            _ => {
+                return Err(LuaError::external(format!(
+                    "invalid merge strategy '{name}', expected 'replace', 'keep', 'deep' or 'concat'"
+                )));
+            }
+        })
+    }
+}
+
+/**
+    Merges `source` into a shallow copy of `target`, leaving both unchanged.
+*/
+pub(crate) fn merge(
+    lua: &Lua,
+    target: &LuaTable,
+    source: &LuaTable,
+    strategy: MergeStrategy,
+) -> LuaResult<LuaTable> {
+    merge_inner(lua, target, source, strategy, &mut HashSet::new())
+}
+
+fn merge_inner(
+    lua: &Lua,
+    target: &LuaTable,
+    source: &LuaTable,
+    strategy: MergeStrategy,
+    visited: &mut HashSet<(usize, usize)>,
+) -> LuaResult<LuaTable> {
+    let result = lua.create_table_with_capacity(target.raw_len(), 0)?;
+    for pair in target.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        result.raw_set(key, value)?;
+    }
+
+    let source_len = source.raw_len() as i64;
+    let mut next_index = result.raw_len() as i64 + 1;
+    for pair in source.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        match strategy {
+            MergeStrategy::Replace => result.raw_set(key, value)?,
+            MergeStrategy::Keep => {
+                if result.raw_get::<LuaValue>(key.clone())?.is_nil() {
+                    result.raw_set(key, value)?;
+                }
+            }
+            MergeStrategy::Deep => {
+                let merged = match (result.raw_get::<LuaValue>(key.clone())?, &value) {
+                    (LuaValue::Table(existing), LuaValue::Table(incoming)) => {
+                        let pair = (
+                            existing.to_pointer() as usize,
+                            incoming.to_pointer() as usize,
+                        );
+                        // NOTE: Tables that are already being merged further up are
+                        // replaced instead, so that cyclic tables do not merge forever
+                        if visited.insert(pair) {
+                            let merged = merge_inner(lua, &existing, incoming, strategy, visited)?;
+                            visited.remove(&pair);
+                            LuaValue::Table(merged)
+                        } else {
+                            value
+                        }
+                    }
+                    _ => value,
+                };
+                result.raw_set(key, merged)?;
+            }
+            MergeStrategy::Concat => {
+                if matches!(key, LuaValue::Integer(i) if (1..=source_len).contains(&i)) {
+                    continue;
+                }
+                result.raw_set(key, value)?;
+            }
+        }
+    }
+
+    // NOTE: Array items are appended separately, since pairs does not iterate them in order
+    if strategy == MergeStrategy::Concat {
+        for value in source.sequence_values::<LuaValue>() {
+            result.raw_set(next_index, value?)?;
+            next_index += 1;
+        }
+    }
+
+    Ok(result)
+}
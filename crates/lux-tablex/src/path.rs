@@ -0,0 +1,158 @@
+use mlua::prelude::*;
+
+/**
+    Parses a path such as `a.b[3].c` or `items["some key"]` into its keys.
+
+    Names separated by dots are string keys, and brackets contain either an
+    integer key or a quoted string key, so that keys containing dots can be used.
+*/
+fn parse_path(path: &str) -> Result<Vec<PathKey>, String> {
+    let mut keys = Vec::new();
+    let mut chars = path.char_indices().peekable();
+    let mut expect_name = true;
+
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            '.' if !expect_name => {
+                chars.next();
+                expect_name = true;
+                if chars.peek().is_none() {
+                    return Err(format!("path '{path}' ends with '.'"));
+                }
+            }
+            '[' => {
+                chars.next();
+                let inner_start = start + 1;
+                let end = loop {
+                    match chars.next() {
+                        Some((i, ']')) => break i,
+                        Some((_, q @ ('"' | '\''))) => {
+                            // Skip to the closing quote, brackets inside of it are part of the key
+                            if !chars.by_ref().any(|(_, c)| c == q) {
+                                return Err(format!("unterminated string in path '{path}'"));
+                            }
+                        }
+                        Some(_) => {}
+                        None => return Err(format!("unterminated '[' in path '{path}'")),
+                    }
+                };
+                keys.push(parse_bracket(&path[inner_start..end], path)?);
+                expect_name = false;
+            }
+            _ if expect_name => {
+                let mut end = path.len();
+                while let Some(&(i, c)) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        end = i;
+                        break;
+                    }
+                    chars.next();
+                }
+                let name = &path[start..end];
+                if name.is_empty() {
+                    return Err(format!("empty key in path '{path}'"));
+                }
+                keys.push(PathKey::Name(name.to_string()));
+                expect_name = false;
+            }
+            _ => return Err(format!("unexpected '{c}' in path '{path}'")),
+        }
+    }
+
+    if keys.is_empty() {
+        return Err("path is empty".to_string());
+    }
+    Ok(keys)
+}
+
+fn parse_bracket(inner: &str, path: &str) -> Result<PathKey, String> {
+    let inner = inner.trim();
+    let quoted = inner.len() >= 2
+        && ((inner.starts_with('"') && inner.ends_with('"'))
+            || (inner.starts_with('\'') && inner.ends_with('\'')));
+    if quoted {
+        Ok(PathKey::Name(inner[1..inner.len() - 1].to_string()))
+    } else {
+        inner
+            .parse()
+            .map(PathKey::Index)
+            .map_err(|_| format!("invalid key '[{inner}]' in path '{path}'"))
+    }
+}
+
+enum PathKey {
+    Name(String),
+    Index(i64),
+}
+
+/**
+    Converts a path, given as a string or an array of keys, into a list of keys.
+*/
+pub(crate) fn path_keys(lua: &Lua, path: LuaValue) -> LuaResult<Vec<LuaValue>> {
+    match path {
+        LuaValue::String(s) => parse_path(&s.to_str()?)
+            .map_err(LuaError::external)?
+            .into_iter()
+            .map(|key| match key {
+                PathKey::Name(name) => lua.create_string(name).map(LuaValue::String),
+                PathKey::Index(index) => Ok(LuaValue::Integer(index)),
+            })
+            .collect(),
+        LuaValue::Table(t) => {
+            let keys = t.sequence_values().collect::<LuaResult<Vec<LuaValue>>>()?;
+            if keys.is_empty() {
+                return Err(LuaError::external("path is empty"));
+            }
+            Ok(keys)
+        }
+        other => Err(LuaError::external(format!(
+            "expected path to be a string or an array of keys, got '{}'",
+            other.type_name()
+        ))),
+    }
+}
+
+/**
+    Gets the value at the given path, or `nil` if any table along the path is missing.
+*/
+pub(crate) fn get_path(table: &LuaTable, keys: &[LuaValue]) -> LuaResult<LuaValue> {
+    let mut current = LuaValue::Table(table.clone());
+    for key in keys {
+        current = match current {
+            LuaValue::Table(t) => t.get(key.clone())?,
+            _ => return Ok(LuaValue::Nil),
+        };
+    }
+    Ok(current)
+}
+
+/**
+    Sets the value at the given path, creating any tables along the path that are missing.
+*/
+pub(crate) fn set_path(
+    lua: &Lua,
+    table: &LuaTable,
+    keys: &[LuaValue],
+    value: LuaValue,
+) -> LuaResult<()> {
+    let (last, parents) = keys.split_last().expect("path is never empty");
+    let mut current = table.clone();
+    for key in parents {
+        current = match current.get::<LuaValue>(key.clone())? {
+            LuaValue::Table(t) => t,
+            LuaValue::Nil => {
+                let t = lua.create_table()?;
+                current.set(key.clone(), &t)?;
+                t
+            }
+            other => {
+                return Err(LuaError::external(format!(
+                    "can not set a value inside of '{}', it is a {}",
+                    key.to_string()?,
+                    other.type_name()
+                )));
+            }
+        };
+    }
+    current.set(last.clone(), value)
+}
@@ -3,7 +3,9 @@
 //! Task library - spawn, defer, delay, wait, cancel
 //!
 //! Provides Roblox-compatible task scheduling functions that integrate
-//! with the mlua-luau-scheduler.
+//! with the mlua-luau-scheduler, along with helpers for running many
+//! tasks concurrently - map, forEach, all and race, and for
+//! inspecting the tasks that are currently alive.
 
 use std::time::{Duration, Instant};
 
@@ -11,10 +13,14 @@ use async_io::Timer;
 use futures_lite::future::yield_now;
 
 use mlua::prelude::*;
-use mlua_luau_scheduler::Functions;
+use mlua_luau_scheduler::{Functions, LuaSchedulerExt, ThreadId};
 
 use lux_utils::TableBuilder;
 
+mod parallel;
+
+use self::parallel::Parallel;
+
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
 
 /// Returns type definitions for the task library.
@@ -48,16 +54,61 @@ pub fn create(lua: Lua) -> LuaResult<LuaValue> {
         .set_environment(task_delay_env)
         .into_function()?;
 
+    let parallel = Parallel::new(&lua, fns.cancel.clone())?;
+    let task_spawn = named(&lua, fns.spawn)?;
+    let task_defer = named(&lua, fns.defer)?;
+
     TableBuilder::new(lua)?
+        .with_value("all", parallel.all)?
         .with_value("cancel", fns.cancel)?
-        .with_value("defer", fns.defer)?
+        .with_value("defer", task_defer)?
         .with_value("delay", task_delay)?
-        .with_value("spawn", fns.spawn)?
+        .with_function("dump", dump)?
+        .with_value("forEach", parallel.for_each)?
+        .with_value("map", parallel.map)?
+        .with_value("race", parallel.race)?
+        .with_value("spawn", task_spawn)?
         .with_value("wait", task_wait)?
         .build_readonly()
         .map(LuaValue::Table)
 }
 
+/**
+    Wraps a scheduler `spawn` or `defer` function to also accept an options table,
+    such as `{ name = "worker" }`, before the function or thread to run.
+*/
+fn named(lua: &Lua, inner: LuaFunction) -> LuaResult<LuaFunction> {
+    lua.create_function(move |lua, mut args: LuaMultiValue| {
+        let Some(LuaValue::Table(options)) = args.front() else {
+            return inner.call::<LuaThread>(args);
+        };
+        let name = options.get::<Option<String>>("name")?;
+        args.pop_front();
+
+        let thread = inner.call::<LuaThread>(args)?;
+        if let Some(name) = name {
+            lua.set_task_name(ThreadId::from(&thread), name);
+        }
+        Ok(thread)
+    })
+}
+
+fn dump(lua: &Lua, (): ()) -> LuaResult<LuaTable> {
+    let tasks = lua.dump_tasks();
+    let dumped = lua.create_table_with_capacity(tasks.len(), 0)?;
+    for task in tasks {
+        let info = TableBuilder::new(lua.clone())?
+            .with_value("thread", task.thread)?
+            .with_value("name", task.name)?
+            .with_value("status", task.status.as_str())?
+            .with_value("age", task.age.as_secs_f64())?
+            .with_value("traceback", task.traceback)?
+            .build()?;
+        dumped.push(info)?;
+    }
+    Ok(dumped)
+}
+
 async fn wait(lua: Lua, secs: Option<f64>) -> LuaResult<f64> {
     // Guarantee that task.wait always yields from Lua perspective
     yield_now().await;
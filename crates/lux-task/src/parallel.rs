@@ -0,0 +1,404 @@
+//! Concurrency helpers - map, forEach, all, race
+//!
+//! Every element runs as its own tracked scheduler thread, so that its result can
+//! be collected once it finishes. The async functions here return `ok, ...` and are
+//! wrapped by a small Lua function that re-raises the original error value.
+
+use std::{future::Future, pin::Pin, task::Poll};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::{LuaSchedulerExt, ThreadId};
+
+use lux_utils::TableBuilder;
+
+const PCALL_IMPL_LUA: &str = r"
+return pcall(...)
+";
+
+const CHECK_IMPL_LUA: &str = r"
+local inner = ...
+local function check(ok, ...)
+    if not ok then
+        error(..., 0)
+    end
+    return ...
+end
+return function(...)
+    return check(inner(...))
+end
+";
+
+/**
+    Options for `task.map`, `task.forEach` and `task.all`.
+*/
+#[derive(Debug, Clone, Copy)]
+struct ParallelOptions {
+    /// Maximum number of elements running at once
+    concurrency: usize,
+    /// Run every element to completion instead of stopping at the first error
+    settle: bool,
+}
+
+impl Default for ParallelOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: usize::MAX,
+            settle: false,
+        }
+    }
+}
+
+impl FromLua for ParallelOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let concurrency = match t.get::<Option<f64>>("concurrency")? {
+                    None => usize::MAX,
+                    Some(n) if n >= 1.0 && n.fract() == 0.0 => n as usize,
+                    Some(n) => {
+                        return Err(LuaError::runtime(format!(
+                            "Expected concurrency to be a positive integer, got {n}"
+                        )));
+                    }
+                };
+                Ok(Self {
+                    concurrency,
+                    settle: t.get::<Option<bool>>("settle")?.unwrap_or_default(),
+                })
+            }
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("ParallelOptions"),
+                message: Some(String::from("Expected options to be a table")),
+            }),
+        }
+    }
+}
+
+/**
+    A single unit of work to run as a scheduler thread.
+*/
+enum Job {
+    /// A function to call with the given arguments, its errors are caught
+    Call(LuaFunction, LuaMultiValue),
+    /// A thread that has not been started yet, its errors are also reported as usual
+    Thread(LuaThread),
+}
+
+impl Job {
+    fn from_task(value: LuaValue, index: usize) -> LuaResult<Self> {
+        match value {
+            LuaValue::Function(f) => Ok(Self::Call(f, LuaMultiValue::new())),
+            LuaValue::Thread(t) if t.status() == LuaThreadStatus::Resumable => Ok(Self::Thread(t)),
+            LuaValue::Thread(_) => Err(LuaError::runtime(format!(
+                "Task at index {index} is a thread that can not be resumed"
+            ))),
+            other => Err(LuaError::runtime(format!(
+                "Expected task at index {index} to be a function or thread, got {}",
+                other.type_name()
+            ))),
+        }
+    }
+
+    fn from_tasks(tasks: &LuaTable) -> LuaResult<Vec<Self>> {
+        tasks
+            .sequence_values::<LuaValue>()
+            .enumerate()
+            .map(|(i, value)| Self::from_task(value?, i + 1))
+            .collect()
+    }
+}
+
+type Outcome = Result<LuaValue, LuaValue>;
+
+struct Running<'a> {
+    index: usize,
+    id: ThreadId,
+    thread: LuaThread,
+    caught: bool,
+    done: Pin<Box<dyn Future<Output = ()> + 'a>>,
+}
+
+#[derive(Clone)]
+struct Runner {
+    pcall: LuaFunction,
+    cancel: LuaFunction,
+}
+
+impl Runner {
+    /**
+        Runs jobs as scheduler threads, keeping at most `concurrency` of them running at once.
+
+        `on_outcome` is called with the index and outcome of each job as it finishes, and may
+        return `false` to stop early, in which case any jobs still running are cancelled.
+    */
+    async fn run(
+        &self,
+        lua: &Lua,
+        jobs: Vec<Job>,
+        concurrency: usize,
+        mut on_outcome: impl FnMut(usize, Outcome) -> LuaResult<bool>,
+    ) -> LuaResult<()> {
+        let mut jobs = jobs.into_iter().enumerate();
+        let mut running = Vec::new();
+
+        loop {
+            while running.len() < concurrency {
+                let Some((index, job)) = jobs.next() else {
+                    break;
+                };
+                running.push(self.start(lua, index, job)?);
+            }
+            if running.is_empty() {
+                return Ok(());
+            }
+
+            let finished = next_finished(&mut running).await;
+            let result = lua
+                .get_thread_result(finished.id)
+                .unwrap_or_else(|| Ok(LuaMultiValue::new()));
+
+            if !on_outcome(finished.index, outcome(lua, finished.caught, result)?)? {
+                for other in running {
+                    self.cancel.call::<()>(other.thread)?;
+                    lua.get_thread_result(other.id);
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    fn start<'a>(&self, lua: &'a Lua, index: usize, job: Job) -> LuaResult<Running<'a>> {
+        let (thread, args, caught) = match job {
+            Job::Call(func, mut args) => {
+                args.push_front(LuaValue::Function(func));
+                (lua.create_thread(self.pcall.clone())?, args, true)
+            }
+            Job::Thread(thread) => (thread, LuaMultiValue::new(), false),
+        };
+        let id = lua.push_thread_back(thread.clone(), args)?;
+        lua.track_thread(id);
+        Ok(Running {
+            index,
+            id,
+            thread,
+            caught,
+            done: Box::pin(lua.wait_for_thread(id)),
+        })
+    }
+}
+
+/**
+    Converts the result of a finished job into its first return value or error.
+*/
+fn outcome(lua: &Lua, caught: bool, result: LuaResult<LuaMultiValue>) -> LuaResult<Outcome> {
+    match result {
+        Ok(values) if caught => {
+            let mut values = values.into_iter();
+            let ok = values.next().is_some_and(|v| v.as_boolean() == Some(true));
+            let value = values.next().unwrap_or(LuaValue::Nil);
+            Ok(if ok { Ok(value) } else { Err(value) })
+        }
+        Ok(values) => Ok(Ok(values.into_iter().next().unwrap_or(LuaValue::Nil))),
+        Err(e) => Ok(Err(LuaValue::String(lua.create_string(e.to_string())?))),
+    }
+}
+
+fn settled(lua: &Lua, outcome: Outcome) -> LuaResult<LuaTable> {
+    let record = lua.create_table()?;
+    match outcome {
+        Ok(value) => {
+            record.raw_set("ok", true)?;
+            record.raw_set("value", value)?;
+        }
+        Err(error) => {
+            record.raw_set("ok", false)?;
+            record.raw_set("error", error)?;
+        }
+    }
+    Ok(record)
+}
+
+fn next_finished<'a>(running: &mut Vec<Running<'a>>) -> impl Future<Output = Running<'a>> {
+    std::future::poll_fn(move |cx| {
+        match running
+            .iter_mut()
+            .position(|r| r.done.as_mut().poll(cx).is_ready())
+        {
+            Some(i) => Poll::Ready(running.swap_remove(i)),
+            None => Poll::Pending,
+        }
+    })
+}
+
+fn failed(lua: &Lua, error: LuaValue) -> LuaResult<LuaMultiValue> {
+    (false, error).into_lua_multi(lua)
+}
+
+async fn map(
+    lua: Lua,
+    runner: Runner,
+    (tbl, func, options): (LuaTable, LuaFunction, ParallelOptions),
+) -> LuaResult<LuaMultiValue> {
+    let mut keys = Vec::new();
+    let mut jobs = Vec::new();
+    for pair in tbl.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        keys.push(key.clone());
+        jobs.push(Job::Call(func.clone(), [value, key].into_iter().collect()));
+    }
+
+    let results = lua.create_table()?;
+    let mut first_error = None;
+    runner
+        .run(&lua, jobs, options.concurrency, |index, outcome| {
+            let key = keys[index].clone();
+            match outcome {
+                _ if options.settle => results.raw_set(key, settled(&lua, outcome)?)?,
+                Ok(value) => results.raw_set(key, value)?,
+                Err(error) => {
+                    first_error = Some(error);
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        })
+        .await?;
+
+    match first_error {
+        Some(error) => failed(&lua, error),
+        None => (true, results).into_lua_multi(&lua),
+    }
+}
+
+async fn for_each(
+    lua: Lua,
+    runner: Runner,
+    (tbl, func, options): (LuaTable, LuaFunction, ParallelOptions),
+) -> LuaResult<LuaMultiValue> {
+    let mut jobs = Vec::new();
+    for pair in tbl.pairs::<LuaValue, LuaValue>() {
+        let (key, value) = pair?;
+        jobs.push(Job::Call(func.clone(), [value, key].into_iter().collect()));
+    }
+
+    let mut first_error = None;
+    runner
+        .run(&lua, jobs, options.concurrency, |_, outcome| {
+            if let Err(error) = outcome
+                && first_error.is_none()
+            {
+                first_error = Some(error);
+            }
+            Ok(options.settle || first_error.is_none())
+        })
+        .await?;
+
+    match first_error {
+        Some(error) => failed(&lua, error),
+        None => true.into_lua_multi(&lua),
+    }
+}
+
+async fn all(
+    lua: Lua,
+    runner: Runner,
+    (tasks, options): (LuaTable, ParallelOptions),
+) -> LuaResult<LuaMultiValue> {
+    let jobs = Job::from_tasks(&tasks)?;
+
+    let results = lua.create_table()?;
+    let mut first_error = None;
+    runner
+        .run(&lua, jobs, options.concurrency, |index, outcome| {
+            match outcome {
+                _ if options.settle => results.raw_set(index + 1, settled(&lua, outcome)?)?,
+                Ok(value) => results.raw_set(index + 1, value)?,
+                Err(error) => {
+                    first_error = Some(error);
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        })
+        .await?;
+
+    match first_error {
+        Some(error) => failed(&lua, error),
+        None => (true, results).into_lua_multi(&lua),
+    }
+}
+
+async fn race(lua: Lua, runner: Runner, tasks: LuaTable) -> LuaResult<LuaMultiValue> {
+    let jobs = Job::from_tasks(&tasks)?;
+    if jobs.is_empty() {
+        return Err(LuaError::runtime("Expected at least one task to race"));
+    }
+
+    let mut winner = None;
+    runner
+        .run(&lua, jobs, usize::MAX, |index, outcome| {
+            winner = Some((index + 1, outcome));
+            Ok(false)
+        })
+        .await?;
+
+    match winner.expect("race always has at least one task") {
+        (index, Ok(value)) => (true, value, index).into_lua_multi(&lua),
+        (_, Err(error)) => failed(&lua, error),
+    }
+}
+
+/**
+    The concurrency helper functions, ready to be added to the task library table.
+*/
+pub struct Parallel {
+    pub map: LuaFunction,
+    pub for_each: LuaFunction,
+    pub all: LuaFunction,
+    pub race: LuaFunction,
+}
+
+impl Parallel {
+    pub fn new(lua: &Lua, cancel: LuaFunction) -> LuaResult<Self> {
+        let pcall_env = TableBuilder::new(lua.clone())?
+            .with_value("pcall", lua.globals().get::<LuaFunction>("pcall")?)?
+            .build_readonly()?;
+        let runner = Runner {
+            pcall: lua
+                .load(PCALL_IMPL_LUA)
+                .set_name("task.pcall")
+                .set_environment(pcall_env)
+                .into_function()?,
+            cancel,
+        };
+
+        let check_env = TableBuilder::new(lua.clone())?
+            .with_value("error", lua.globals().get::<LuaFunction>("error")?)?
+            .build_readonly()?;
+        let checked = |name: &str, inner: LuaFunction| {
+            lua.load(CHECK_IMPL_LUA)
+                .set_name(name)
+                .set_environment(check_env.clone())
+                .call::<LuaFunction>(inner)
+        };
+
+        let r = runner.clone();
+        let map_inner = lua.create_async_function(move |lua, args| map(lua, r.clone(), args))?;
+        let r = runner.clone();
+        let for_each_inner =
+            lua.create_async_function(move |lua, args| for_each(lua, r.clone(), args))?;
+        let r = runner.clone();
+        let all_inner = lua.create_async_function(move |lua, args| all(lua, r.clone(), args))?;
+        let r = runner;
+        let race_inner = lua.create_async_function(move |lua, args| race(lua, r.clone(), args))?;
+
+        Ok(Self {
+            map: checked("task.map", map_inner)?,
+            for_each: checked("task.forEach", for_each_inner)?,
+            all: checked("task.all", all_inner)?,
+            race: checked("task.race", race_inner)?,
+        })
+    }
+}
@@ -0,0 +1,44 @@
+use std::{error::Error, fmt};
+
+/**
+    An error in a template, either while compiling it - such as an unclosed tag
+    or block - or while rendering it, such as an unknown filter or partial.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TemplateError {
+    name: Option<String>,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl TemplateError {
+    pub(crate) fn new(source: &str, offset: usize, message: impl Into<String>) -> Self {
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        Self {
+            name: None,
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    /// Sets the name of the template the error occurred in, which is shown before its location
+    pub(crate) fn with_name(mut self, name: Option<&str>) -> Self {
+        self.name = name.map(ToString::to_string);
+        self
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{name}:")?;
+        }
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for TemplateError {}
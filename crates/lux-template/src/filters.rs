@@ -0,0 +1,103 @@
+use mlua::prelude::*;
+
+use crate::render::{escape_html, is_truthy, to_text};
+
+/**
+    Runs the built-in filter with the given name, or returns `None` if there is no such filter.
+
+    Built-in filters are:
+
+    - `upper`, `lower` and `trim`, for strings
+    - `length`, for the number of bytes in a string or entries in a table
+    - `default value`, to use a value instead of `nil`, `false`, empty strings and empty tables
+    - `join separator`, to join the values of a list, separated by `", "` unless given a separator
+    - `escape`, to escape HTML in templates that were compiled without escaping
+*/
+pub(crate) fn builtin(
+    lua: &Lua,
+    name: &str,
+    value: LuaValue,
+    args: &[LuaValue],
+) -> Option<LuaResult<LuaValue>> {
+    let result = match name {
+        "upper" => map_text(lua, &value, str::to_uppercase),
+        "lower" => map_text(lua, &value, str::to_lowercase),
+        "trim" => map_text(lua, &value, |s| s.trim().to_string()),
+        "length" => length(&value),
+        "default" => Ok(if is_truthy(&value) {
+            value
+        } else {
+            args.first().cloned().unwrap_or(LuaValue::Nil)
+        }),
+        "join" => join(lua, &value, args.first()),
+        "escape" => text(&value).and_then(|text| {
+            let mut escaped = Vec::with_capacity(text.len());
+            escape_html(&text, &mut escaped);
+            lua.create_string(escaped).map(LuaValue::String)
+        }),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn text(value: &LuaValue) -> LuaResult<Vec<u8>> {
+    to_text(value)?.ok_or_else(|| {
+        LuaError::runtime(format!(
+            "Expected a value that can be written, got '{}'",
+            value.type_name()
+        ))
+    })
+}
+
+fn map_text(lua: &Lua, value: &LuaValue, f: impl FnOnce(&str) -> String) -> LuaResult<LuaValue> {
+    if value.is_nil() {
+        return Ok(LuaValue::Nil);
+    }
+    let text = text(value)?;
+    let mapped = f(&String::from_utf8_lossy(&text));
+    lua.create_string(mapped).map(LuaValue::String)
+}
+
+fn length(value: &LuaValue) -> LuaResult<LuaValue> {
+    let len = match value {
+        LuaValue::Nil => 0,
+        LuaValue::String(s) => s.as_bytes().len(),
+        LuaValue::Table(t) => match t.raw_len() {
+            0 => t.pairs::<LuaValue, LuaValue>().count(),
+            len => len,
+        },
+        other => {
+            return Err(LuaError::runtime(format!(
+                "Expected a string or table, got '{}'",
+                other.type_name()
+            )));
+        }
+    };
+    Ok(LuaValue::Integer(len as i64))
+}
+
+fn join(lua: &Lua, value: &LuaValue, separator: Option<&LuaValue>) -> LuaResult<LuaValue> {
+    let list = match value {
+        LuaValue::Nil => return Ok(LuaValue::Nil),
+        LuaValue::Table(t) => t,
+        other => {
+            return Err(LuaError::runtime(format!(
+                "Expected a list, got '{}'",
+                other.type_name()
+            )));
+        }
+    };
+    let separator = match separator {
+        Some(separator) => text(separator)?,
+        None => b", ".to_vec(),
+    };
+
+    let mut joined = Vec::new();
+    for (index, item) in list.sequence_values::<LuaValue>().enumerate() {
+        if index > 0 {
+            joined.extend_from_slice(&separator);
+        }
+        joined.extend(text(&item?)?);
+    }
+    lua.create_string(joined).map(LuaValue::String)
+}
@@ -0,0 +1,127 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{cell::RefCell, rc::Rc};
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod error;
+mod filters;
+mod parser;
+mod render;
+
+use self::parser::is_identifier;
+use self::render::{Registry, Renderer, SharedRegistry, Template};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `template` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `template` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let registry: SharedRegistry = Rc::new(RefCell::new(Registry::default()));
+    let compile_registry = Rc::clone(&registry);
+    let partial_registry = Rc::clone(&registry);
+    TableBuilder::new(lua)?
+        .with_function("compile", move |lua, args: (String, CompileOptions)| {
+            template_compile(lua, args, &compile_registry)
+        })?
+        .with_function("registerPartial", move |_, args: (String, String)| {
+            template_register_partial(args, &partial_registry)
+        })?
+        .with_function("registerFilter", move |_, args: (String, LuaFunction)| {
+            template_register_filter(args, &registry)
+        })?
+        .build_readonly()
+}
+
+#[derive(Debug, Clone)]
+struct CompileOptions {
+    name: Option<String>,
+    escape: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self {
+            name: None,
+            escape: true,
+        }
+    }
+}
+
+impl FromLua for CompileOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let mut options = Self::default();
+                if let Some(name) = t.get::<Option<String>>("name")? {
+                    options.name = Some(name);
+                }
+                if let Some(escape) = t.get::<Option<bool>>("escape")? {
+                    options.escape = escape;
+                }
+                Ok(options)
+            }
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "CompileOptions".to_string(),
+                message: Some(format!(
+                    "Invalid compile options - expected table or nil, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+fn template_compile(
+    lua: &Lua,
+    (source, options): (String, CompileOptions),
+    registry: &SharedRegistry,
+) -> LuaResult<LuaFunction> {
+    let template = Template::compile(source, options.name)?;
+    let registry = Rc::clone(registry);
+    lua.create_function(move |lua, data: LuaValue| {
+        Renderer::render(lua, &registry, &template, data, options.escape)
+    })
+}
+
+fn template_register_partial(
+    (name, source): (String, String),
+    registry: &SharedRegistry,
+) -> LuaResult<()> {
+    let template = Template::compile(source, Some(name.clone()))?;
+    registry
+        .borrow_mut()
+        .partials
+        .insert(name, Rc::new(template));
+    Ok(())
+}
+
+fn template_register_filter(
+    (name, filter): (String, LuaFunction),
+    registry: &SharedRegistry,
+) -> LuaResult<()> {
+    if !is_identifier(&name) {
+        return Err(LuaError::runtime(format!(
+            "Invalid filter name '{name}', expected letters, digits and underscores"
+        )));
+    }
+    registry.borrow_mut().filters.insert(name, filter);
+    Ok(())
+}
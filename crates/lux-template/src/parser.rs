@@ -0,0 +1,633 @@
+use crate::error::TemplateError;
+
+/// Variables describing the current iteration of the innermost `{{#each}}` block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DataVar {
+    Index,
+    Key,
+    First,
+    Last,
+}
+
+/// A path to a value in the data given to a template, such as `user.name`, `../title` or `this`
+#[derive(Debug, Clone)]
+pub(crate) struct Path {
+    /// How many scopes to go up before looking up the path, one for every `../`
+    pub parents: usize,
+    /// If the path started with `this` or `../`, which stops it from being looked up in outer scopes
+    pub explicit: bool,
+    pub segments: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Operand {
+    Path(Path),
+    Data(DataVar),
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Filter {
+    pub name: String,
+    pub args: Vec<Operand>,
+    pub offset: usize,
+}
+
+/// A value followed by any number of filters, such as `name | upper` or `items | join ", "`
+#[derive(Debug, Clone)]
+pub(crate) struct Expr {
+    pub operand: Operand,
+    pub filters: Vec<Filter>,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Text(String),
+    Output {
+        expr: Expr,
+        escape: bool,
+    },
+    /// An `{{#if}}` or `{{#unless}}` block, where `{{else if}}` is nested in `otherwise`
+    If {
+        cond: Expr,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Each {
+        expr: Expr,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    With {
+        expr: Expr,
+        body: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    Partial {
+        name: String,
+        context: Option<Expr>,
+        offset: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TagKind {
+    Output,
+    Raw,
+    Open,
+    Close,
+    Else,
+    Partial,
+    Comment,
+}
+
+impl TagKind {
+    /// Tags that write nothing themselves remove their whole line, when they are alone on it
+    fn can_stand_alone(self) -> bool {
+        !matches!(self, Self::Output | Self::Raw)
+    }
+}
+
+#[derive(Debug)]
+struct Tag<'a> {
+    kind: TagKind,
+    /// The contents of the tag after its sigil, such as `each items` for `{{#each items}}`
+    body: &'a str,
+    /// The position of the body in the source
+    offset: usize,
+    /// The position of the opening braces in the source
+    start: usize,
+}
+
+enum Piece<'a> {
+    Text(String),
+    Tag(Tag<'a>),
+}
+
+/// What ended a list of nodes
+enum End<'a> {
+    Eof,
+    Close(Tag<'a>),
+    Else(Tag<'a>),
+}
+
+#[derive(Debug)]
+enum Token<'a> {
+    Word(&'a str),
+    String(String),
+    Pipe,
+}
+
+/**
+    Parses the source of a template into a list of nodes.
+
+    # Errors
+
+    Errors if a tag is not closed, a block is not closed or closed by the wrong tag,
+    or if a tag contains an invalid expression.
+*/
+pub(crate) fn parse(source: &str) -> Result<Vec<Node>, TemplateError> {
+    let (mut texts, tags) = scan(source)?;
+    strip_standalone(&mut texts, &tags);
+
+    let mut pieces = Vec::with_capacity(texts.len() + tags.len());
+    let mut texts = texts.into_iter();
+    for tag in tags {
+        pieces.push(Piece::Text(texts.next().unwrap_or_default()));
+        pieces.push(Piece::Tag(tag));
+    }
+    pieces.extend(texts.map(Piece::Text));
+
+    let mut parser = Parser {
+        source,
+        pieces: pieces.into_iter(),
+    };
+    let (nodes, end) = parser.nodes()?;
+    match end {
+        End::Eof => Ok(nodes),
+        End::Close(tag) => Err(parser.error(
+            tag.start,
+            format!(
+                "Unexpected '{}' without a matching block",
+                tag_name("/", tag.body)
+            ),
+        )),
+        End::Else(tag) => Err(parser.error(tag.start, "Unexpected 'else' outside of a block")),
+    }
+}
+
+/**
+    Splits the source into text and tags, where there is always one more text than there are tags.
+
+    Whitespace next to tags with a `~`, such as `{{~name~}}`, is removed from the text.
+*/
+fn scan(source: &str) -> Result<(Vec<String>, Vec<Tag<'_>>), TemplateError> {
+    let mut texts = Vec::new();
+    let mut tags = Vec::new();
+    let mut pos = 0;
+    let mut trim_next = false;
+    while let Some(found) = source[pos..].find("{{") {
+        let start = pos + found;
+        let mut text = &source[pos..start];
+        if trim_next {
+            text = text.trim_start();
+        }
+
+        let raw = source[start + 2..].starts_with('{');
+        let mut inner = start + if raw { 3 } else { 2 };
+        if source[inner..].starts_with('~') {
+            inner += 1;
+            text = text.trim_end();
+        }
+        // NOTE: Long comments may contain braces, such as when commenting out other tags
+        let close = if raw {
+            "}}}"
+        } else if source[inner..].starts_with("!--") {
+            "--}}"
+        } else {
+            "}}"
+        };
+        let Some(len) = source[inner..].find(close) else {
+            return Err(TemplateError::new(
+                source,
+                start,
+                format!("Unclosed tag, expected '{close}'"),
+            ));
+        };
+
+        let mut content = &source[inner..inner + len];
+        trim_next = content.ends_with('~');
+        if trim_next {
+            content = &content[..content.len() - 1];
+        }
+        texts.push(text.to_string());
+        tags.push(classify(content, inner, start, raw));
+        pos = inner + len + close.len();
+    }
+
+    let mut text = &source[pos..];
+    if trim_next {
+        text = text.trim_start();
+    }
+    texts.push(text.to_string());
+    Ok((texts, tags))
+}
+
+fn classify(content: &str, offset: usize, start: usize, raw: bool) -> Tag<'_> {
+    let text = content.trim_start();
+    let offset = offset + content.len() - text.len();
+    let (kind, sigil) = match text.as_bytes().first() {
+        _ if raw => (TagKind::Raw, 0),
+        Some(b'!') => (TagKind::Comment, 1),
+        Some(b'#') => (TagKind::Open, 1),
+        Some(b'/') => (TagKind::Close, 1),
+        Some(b'>') => (TagKind::Partial, 1),
+        Some(b'&') => (TagKind::Raw, 1),
+        _ if text.trim_end() == "else" || text.starts_with("else ") => (TagKind::Else, 4),
+        _ => (TagKind::Output, 0),
+    };
+    let body = &text[sigil..];
+    let trimmed = body.trim_start();
+    Tag {
+        kind,
+        body: trimmed.trim_end(),
+        offset: offset + sigil + body.len() - trimmed.len(),
+        start,
+    }
+}
+
+/**
+    Removes the lines of tags that are alone on their line, other than tags that write
+    something, so that blocks and comments do not leave empty lines behind in the output.
+*/
+fn strip_standalone(texts: &mut [String], tags: &[Tag]) {
+    let last = texts.len() - 1;
+    let mut ranges = texts.iter().map(|t| (0, t.len())).collect::<Vec<_>>();
+    for (index, tag) in tags.iter().enumerate() {
+        if !tag.kind.can_stand_alone() {
+            continue;
+        }
+        let (before, after) = (&texts[index], &texts[index + 1]);
+        let line_start = match before.rfind('\n') {
+            Some(i) => is_blank(&before[i + 1..]).then_some(i + 1),
+            None => (index == 0 && is_blank(before)).then_some(0),
+        };
+        let line_end = match after.find('\n') {
+            Some(i) => is_blank(&after[..i]).then_some(i + 1),
+            None => (index + 1 == last && is_blank(after)).then_some(after.len()),
+        };
+        if let (Some(line_start), Some(line_end)) = (line_start, line_end) {
+            ranges[index].1 = ranges[index].1.min(line_start);
+            ranges[index + 1].0 = ranges[index + 1].0.max(line_end);
+        }
+    }
+    for (text, (start, end)) in texts.iter_mut().zip(ranges) {
+        *text = text[start..end.max(start)].to_string();
+    }
+}
+
+fn is_blank(text: &str) -> bool {
+    text.bytes().all(|b| matches!(b, b' ' | b'\t' | b'\r'))
+}
+
+fn tag_name(sigil: &str, name: &str) -> String {
+    format!("{{{{{sigil}{name}}}}}")
+}
+
+/// Splits the first word off of a tag body, returning it along with the rest and its position
+fn split_word(body: &str, offset: usize) -> (&str, &str, usize) {
+    let end = body.find(char::is_whitespace).unwrap_or(body.len());
+    let rest = body[end..].trim_start();
+    (&body[..end], rest, offset + body.len() - rest.len())
+}
+
+pub(crate) fn is_identifier(name: &str) -> bool {
+    !name.starts_with(|c: char| c.is_ascii_digit())
+        && !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pieces: std::vec::IntoIter<Piece<'a>>,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, offset: usize, message: impl Into<String>) -> TemplateError {
+        TemplateError::new(self.source, offset, message)
+    }
+
+    /// Parses nodes until the end of the source, or a tag that ends the current block
+    fn nodes(&mut self) -> Result<(Vec<Node>, End<'a>), TemplateError> {
+        let mut nodes = Vec::new();
+        while let Some(piece) = self.pieces.next() {
+            let tag = match piece {
+                Piece::Text(text) => {
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(text));
+                    }
+                    continue;
+                }
+                Piece::Tag(tag) => tag,
+            };
+            match tag.kind {
+                TagKind::Comment => {}
+                TagKind::Output | TagKind::Raw => nodes.push(Node::Output {
+                    expr: self.expr(tag.body, tag.offset)?,
+                    escape: tag.kind == TagKind::Output,
+                }),
+                TagKind::Partial => nodes.push(self.partial(&tag)?),
+                TagKind::Open => nodes.push(self.block(&tag)?),
+                TagKind::Close => return Ok((nodes, End::Close(tag))),
+                TagKind::Else => return Ok((nodes, End::Else(tag))),
+            }
+        }
+        Ok((nodes, End::Eof))
+    }
+
+    fn block(&mut self, open: &Tag<'a>) -> Result<Node, TemplateError> {
+        let (name, rest, rest_offset) = split_word(open.body, open.offset);
+        match name {
+            "if" | "unless" => self.conditional(open, name, name == "unless", rest, rest_offset),
+            "each" | "with" => {
+                let expr = self.expr(rest, rest_offset)?;
+                let (body, end) = self.nodes()?;
+                let otherwise = match end {
+                    End::Else(tag) if tag.body.is_empty() => {
+                        let (nodes, end) = self.nodes()?;
+                        self.expect_close(open, name, end)?;
+                        nodes
+                    }
+                    end => {
+                        self.expect_close(open, name, end)?;
+                        Vec::new()
+                    }
+                };
+                Ok(if name == "each" {
+                    Node::Each {
+                        expr,
+                        body,
+                        otherwise,
+                    }
+                } else {
+                    Node::With {
+                        expr,
+                        body,
+                        otherwise,
+                    }
+                })
+            }
+            _ => Err(self.error(
+                open.start,
+                format!(
+                    "Unknown block '{}', expected 'if', 'unless', 'each' or 'with'",
+                    tag_name("#", name)
+                ),
+            )),
+        }
+    }
+
+    /// Parses the rest of an `{{#if}}` or `{{#unless}}` block, or of an `{{else if}}` inside of one
+    fn conditional(
+        &mut self,
+        open: &Tag<'a>,
+        name: &str,
+        negate: bool,
+        cond: &str,
+        cond_offset: usize,
+    ) -> Result<Node, TemplateError> {
+        let cond = self.expr(cond, cond_offset)?;
+        let (then, end) = self.nodes()?;
+        let otherwise = match end {
+            End::Else(tag) if tag.body.is_empty() => {
+                let (nodes, end) = self.nodes()?;
+                self.expect_close(open, name, end)?;
+                nodes
+            }
+            End::Else(tag) => {
+                // NOTE: `{{else if}}` continues the same block, which is only closed once
+                let (kind, rest, rest_offset) = split_word(tag.body, tag.offset);
+                if kind != "if" && kind != "unless" {
+                    return Err(self.error(
+                        tag.start,
+                        format!(
+                            "Expected 'else', 'else if' or 'else unless', got 'else {}'",
+                            tag.body
+                        ),
+                    ));
+                }
+                vec![self.conditional(open, name, kind == "unless", rest, rest_offset)?]
+            }
+            end => {
+                self.expect_close(open, name, end)?;
+                Vec::new()
+            }
+        };
+        Ok(Node::If {
+            cond,
+            negate,
+            then,
+            otherwise,
+        })
+    }
+
+    fn expect_close(&self, open: &Tag, name: &str, end: End) -> Result<(), TemplateError> {
+        match end {
+            End::Close(tag) if tag.body == name => Ok(()),
+            End::Close(tag) => Err(self.error(
+                tag.start,
+                format!(
+                    "Expected '{}' to close '{}', got '{}'",
+                    tag_name("/", name),
+                    tag_name("#", name),
+                    tag_name("/", tag.body)
+                ),
+            )),
+            End::Else(tag) => Err(self.error(
+                tag.start,
+                format!(
+                    "Unexpected 'else {}' in '{}' block",
+                    tag.body,
+                    tag_name("#", name)
+                ),
+            )),
+            End::Eof => Err(self.error(
+                open.start,
+                format!("Unclosed '{}' block", tag_name("#", name)),
+            )),
+        }
+    }
+
+    fn partial(&self, tag: &Tag) -> Result<Node, TemplateError> {
+        let tokens = tokenize(self.source, tag.body, tag.offset)?;
+        let name = match tokens.first() {
+            Some((Token::Word(name), _)) => (*name).to_string(),
+            Some((Token::String(name), _)) => name.clone(),
+            _ => return Err(self.error(tag.offset, "Expected the name of a partial")),
+        };
+        let context = match tokens.get(1) {
+            Some((_, at)) => {
+                let body_end = tag.offset + tag.body.len();
+                Some(self.expr(&self.source[*at..body_end], *at)?)
+            }
+            None => None,
+        };
+        Ok(Node::Partial {
+            name,
+            context,
+            offset: tag.start,
+        })
+    }
+
+    fn expr(&self, body: &str, offset: usize) -> Result<Expr, TemplateError> {
+        let mut tokens = tokenize(self.source, body, offset)?.into_iter().peekable();
+        let operand = match tokens.next() {
+            None => return Err(self.error(offset, "Expected a value")),
+            Some((Token::Pipe, at)) => return Err(self.error(at, "Expected a value before '|'")),
+            Some((token, at)) => self.operand(token, at)?,
+        };
+
+        let mut filters = Vec::new();
+        while let Some((token, at)) = tokens.next() {
+            if !matches!(token, Token::Pipe) {
+                return Err(self.error(at, "Expected '|' before the name of a filter"));
+            }
+            let (name, name_offset) = match tokens.next() {
+                Some((Token::Word(name), name_offset)) if is_identifier(name) => {
+                    (name.to_string(), name_offset)
+                }
+                Some((_, name_offset)) => {
+                    return Err(self.error(name_offset, "Expected the name of a filter"));
+                }
+                None => return Err(self.error(at, "Expected the name of a filter after '|'")),
+            };
+            let mut args = Vec::new();
+            while let Some((token, arg_offset)) =
+                tokens.next_if(|(token, _)| !matches!(token, Token::Pipe))
+            {
+                args.push(self.operand(token, arg_offset)?);
+            }
+            filters.push(Filter {
+                name,
+                args,
+                offset: name_offset,
+            });
+        }
+
+        Ok(Expr {
+            operand,
+            filters,
+            offset,
+        })
+    }
+
+    fn operand(&self, token: Token, offset: usize) -> Result<Operand, TemplateError> {
+        let word = match token {
+            Token::String(s) => return Ok(Operand::String(s)),
+            Token::Pipe => return Err(self.error(offset, "Unexpected '|'")),
+            Token::Word(word) => word,
+        };
+        match word {
+            "nil" => Ok(Operand::Nil),
+            "true" => Ok(Operand::Bool(true)),
+            "false" => Ok(Operand::Bool(false)),
+            _ if word.starts_with(|c: char| c.is_ascii_digit() || c == '-') => word
+                .parse()
+                .map(Operand::Number)
+                .map_err(|_| self.error(offset, format!("Invalid number '{word}'"))),
+            _ => self.path(word, offset),
+        }
+    }
+
+    fn path(&self, word: &str, offset: usize) -> Result<Operand, TemplateError> {
+        if let Some(name) = word.strip_prefix('@') {
+            return match name {
+                "index" => Ok(Operand::Data(DataVar::Index)),
+                "key" => Ok(Operand::Data(DataVar::Key)),
+                "first" => Ok(Operand::Data(DataVar::First)),
+                "last" => Ok(Operand::Data(DataVar::Last)),
+                _ => Err(self.error(
+                    offset,
+                    format!(
+                        "Unknown variable '{word}', expected '@index', '@key', '@first' or '@last'"
+                    ),
+                )),
+            };
+        }
+
+        let mut parents = 0;
+        let mut rest = word;
+        while let Some(after) = rest.strip_prefix("../") {
+            parents += 1;
+            rest = after;
+        }
+        if rest == ".." {
+            parents += 1;
+            rest = "this";
+        }
+        let path = match rest {
+            "this" | "." => "",
+            _ => rest.strip_prefix("this.").unwrap_or(rest),
+        };
+
+        let segments = if path.is_empty() {
+            Vec::new()
+        } else {
+            path.split('.').map(ToString::to_string).collect::<Vec<_>>()
+        };
+        let is_valid = |s: &String| {
+            !s.is_empty()
+                && s.chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        };
+        if !segments.iter().all(is_valid) {
+            return Err(self.error(offset, format!("Invalid path '{word}'")));
+        }
+
+        Ok(Operand::Path(Path {
+            parents,
+            explicit: parents > 0 || path.len() != rest.len(),
+            segments,
+        }))
+    }
+}
+
+/// Splits the body of a tag into words, quoted strings and pipes, along with their positions
+fn tokenize<'a>(
+    source: &str,
+    body: &'a str,
+    offset: usize,
+) -> Result<Vec<(Token<'a>, usize)>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut chars = body.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '|' => tokens.push((Token::Pipe, offset + start)),
+            '"' | '\'' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        None => {
+                            return Err(TemplateError::new(
+                                source,
+                                offset + start,
+                                "Unfinished string",
+                            ));
+                        }
+                        Some((_, q)) if q == c => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, escaped)) => value.push(escaped),
+                            None => {
+                                return Err(TemplateError::new(
+                                    source,
+                                    offset + start,
+                                    "Unfinished string",
+                                ));
+                            }
+                        },
+                        Some((_, other)) => value.push(other),
+                    }
+                }
+                tokens.push((Token::String(value), offset + start));
+            }
+            _ => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) =
+                    chars.next_if(|&(_, c)| !c.is_whitespace() && !matches!(c, '|' | '"' | '\''))
+                {
+                    end = i + c.len_utf8();
+                }
+                tokens.push((Token::Word(&body[start..end]), offset + start));
+            }
+        }
+    }
+    Ok(tokens)
+}
@@ -0,0 +1,447 @@
+use std::{cell::RefCell, cmp::Ordering, collections::HashMap, rc::Rc};
+
+use mlua::prelude::*;
+
+use crate::{
+    error::TemplateError,
+    filters,
+    parser::{DataVar, Expr, Node, Operand, Path, parse},
+};
+
+/// The most partials that may be rendered inside of each other, which stops partials that include themselves forever
+const MAX_PARTIAL_DEPTH: usize = 64;
+
+/// Partials and filters registered from Lua, which every template can use
+#[derive(Default)]
+pub(crate) struct Registry {
+    pub partials: HashMap<String, Rc<Template>>,
+    pub filters: HashMap<String, LuaFunction>,
+}
+
+pub(crate) type SharedRegistry = Rc<RefCell<Registry>>;
+
+/// A compiled template or partial, which keeps its source to give the location of errors
+#[derive(Debug)]
+pub(crate) struct Template {
+    name: Option<String>,
+    source: String,
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn compile(source: String, name: Option<String>) -> LuaResult<Self> {
+        let nodes = parse(&source).map_err(|e| LuaError::runtime(e.with_name(name.as_deref())))?;
+        Ok(Self {
+            name,
+            source,
+            nodes,
+        })
+    }
+
+    fn error(&self, offset: usize, message: impl Into<String>) -> LuaError {
+        LuaError::runtime(
+            TemplateError::new(&self.source, offset, message).with_name(self.name.as_deref()),
+        )
+    }
+}
+
+/// The current iteration of an `{{#each}}` block
+struct Iteration {
+    /// The position of the current entry, starting at `1`
+    index: usize,
+    key: LuaValue,
+    last: bool,
+}
+
+struct Scope {
+    value: LuaValue,
+    iteration: Option<Iteration>,
+}
+
+pub(crate) struct Renderer<'a> {
+    lua: &'a Lua,
+    registry: &'a SharedRegistry,
+    escape: bool,
+    scopes: Vec<Scope>,
+    depth: usize,
+    out: Vec<u8>,
+}
+
+impl<'a> Renderer<'a> {
+    /**
+        Renders a template with the given data.
+
+        # Errors
+
+        Errors if a value can not be written, an `{{#each}}` block is given a value that
+        is not a table, or if a filter or partial is unknown or a filter throws an error.
+    */
+    pub fn render(
+        lua: &'a Lua,
+        registry: &'a SharedRegistry,
+        template: &Template,
+        data: LuaValue,
+        escape: bool,
+    ) -> LuaResult<LuaString> {
+        let mut renderer = Self {
+            lua,
+            registry,
+            escape,
+            scopes: vec![Scope {
+                value: data,
+                iteration: None,
+            }],
+            depth: 0,
+            out: Vec::new(),
+        };
+        renderer.nodes(template, &template.nodes)?;
+        lua.create_string(&renderer.out)
+    }
+
+    fn nodes(&mut self, template: &Template, nodes: &[Node]) -> LuaResult<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => self.out.extend_from_slice(text.as_bytes()),
+                Node::Output { expr, escape } => {
+                    let value = self.eval(template, expr)?;
+                    self.write(template, expr.offset, &value, *escape && self.escape)?;
+                }
+                Node::If {
+                    cond,
+                    negate,
+                    then,
+                    otherwise,
+                } => {
+                    let value = self.eval(template, cond)?;
+                    if is_truthy(&value) == *negate {
+                        self.nodes(template, otherwise)?;
+                    } else {
+                        self.nodes(template, then)?;
+                    }
+                }
+                Node::Each {
+                    expr,
+                    body,
+                    otherwise,
+                } => self.each(template, expr, body, otherwise)?,
+                Node::With {
+                    expr,
+                    body,
+                    otherwise,
+                } => {
+                    let value = self.eval(template, expr)?;
+                    if is_truthy(&value) {
+                        let scope = Scope {
+                            value,
+                            iteration: None,
+                        };
+                        self.scoped(scope, |r| r.nodes(template, body))?;
+                    } else {
+                        self.nodes(template, otherwise)?;
+                    }
+                }
+                Node::Partial {
+                    name,
+                    context,
+                    offset,
+                } => self.partial(template, name, context.as_ref(), *offset)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn scoped(
+        &mut self,
+        scope: Scope,
+        f: impl FnOnce(&mut Self) -> LuaResult<()>,
+    ) -> LuaResult<()> {
+        self.scopes.push(scope);
+        let result = f(self);
+        self.scopes.pop();
+        result
+    }
+
+    fn each(
+        &mut self,
+        template: &Template,
+        expr: &Expr,
+        body: &[Node],
+        otherwise: &[Node],
+    ) -> LuaResult<()> {
+        let entries = match self.eval(template, expr)? {
+            LuaValue::Table(table) => entries(&table)?,
+            LuaValue::Nil | LuaValue::Boolean(false) => Vec::new(),
+            other => {
+                return Err(template.error(
+                    expr.offset,
+                    format!("Expected a table to loop over, got '{}'", other.type_name()),
+                ));
+            }
+        };
+        if entries.is_empty() {
+            return self.nodes(template, otherwise);
+        }
+
+        let count = entries.len();
+        for (index, (key, value)) in entries.into_iter().enumerate() {
+            let scope = Scope {
+                value,
+                iteration: Some(Iteration {
+                    index: index + 1,
+                    key,
+                    last: index + 1 == count,
+                }),
+            };
+            self.scoped(scope, |r| r.nodes(template, body))?;
+        }
+        Ok(())
+    }
+
+    fn partial(
+        &mut self,
+        template: &Template,
+        name: &str,
+        context: Option<&Expr>,
+        offset: usize,
+    ) -> LuaResult<()> {
+        let partial = self.registry.borrow().partials.get(name).cloned();
+        let Some(partial) = partial else {
+            return Err(template.error(offset, format!("Unknown partial '{name}'")));
+        };
+        if self.depth >= MAX_PARTIAL_DEPTH {
+            return Err(template.error(
+                offset,
+                format!("Partials are nested more than {MAX_PARTIAL_DEPTH} levels deep"),
+            ));
+        }
+
+        let scope = match context {
+            Some(expr) => Some(Scope {
+                value: self.eval(template, expr)?,
+                iteration: None,
+            }),
+            None => None,
+        };
+        self.depth += 1;
+        let result = match scope {
+            Some(scope) => self.scoped(scope, |r| r.nodes(&partial, &partial.nodes)),
+            None => self.nodes(&partial, &partial.nodes),
+        };
+        self.depth -= 1;
+        result
+    }
+
+    fn eval(&self, template: &Template, expr: &Expr) -> LuaResult<LuaValue> {
+        let mut value = self.operand(&expr.operand)?;
+        for filter in &expr.filters {
+            let args = filter
+                .args
+                .iter()
+                .map(|arg| self.operand(arg))
+                .collect::<LuaResult<Vec<_>>>()?;
+            // NOTE: Filters registered from Lua replace built-in filters with the same name
+            let custom = self.registry.borrow().filters.get(&filter.name).cloned();
+            let result = match custom {
+                Some(func) => {
+                    let mut call_args = Vec::with_capacity(args.len() + 1);
+                    call_args.push(value);
+                    call_args.extend(args);
+                    func.call::<LuaValue>(LuaMultiValue::from_vec(call_args))
+                }
+                None => match filters::builtin(self.lua, &filter.name, value, &args) {
+                    Some(result) => result,
+                    None => {
+                        return Err(template
+                            .error(filter.offset, format!("Unknown filter '{}'", filter.name)));
+                    }
+                },
+            };
+            value = result.map_err(|e| {
+                let message = match e {
+                    LuaError::RuntimeError(message) => message,
+                    other => other.to_string(),
+                };
+                template.error(
+                    filter.offset,
+                    format!("Filter '{}' failed: {message}", filter.name),
+                )
+            })?;
+        }
+        Ok(value)
+    }
+
+    fn operand(&self, operand: &Operand) -> LuaResult<LuaValue> {
+        Ok(match operand {
+            Operand::Path(path) => self.resolve(path)?,
+            Operand::Data(var) => self.data(*var),
+            Operand::Nil => LuaValue::Nil,
+            Operand::Bool(b) => LuaValue::Boolean(*b),
+            Operand::Number(n) => LuaValue::Number(*n),
+            Operand::String(s) => LuaValue::String(self.lua.create_string(s)?),
+        })
+    }
+
+    fn resolve(&self, path: &Path) -> LuaResult<LuaValue> {
+        let Some(depth) = self.scopes.len().checked_sub(path.parents + 1) else {
+            return Ok(LuaValue::Nil);
+        };
+        let Some((first, rest)) = path.segments.split_first() else {
+            return Ok(self.scopes[depth].value.clone());
+        };
+
+        let mut value = LuaValue::Nil;
+        if path.explicit {
+            value = index(&self.scopes[depth].value, first)?;
+        } else {
+            // Names that are not in the current scope are looked up in outer scopes,
+            // so that loops can use values from outside of them
+            for scope in self.scopes[..=depth].iter().rev() {
+                value = index(&scope.value, first)?;
+                if !value.is_nil() {
+                    break;
+                }
+            }
+        }
+        for segment in rest {
+            value = index(&value, segment)?;
+        }
+        Ok(value)
+    }
+
+    fn data(&self, var: DataVar) -> LuaValue {
+        let iteration = self.scopes.iter().rev().find_map(|s| s.iteration.as_ref());
+        let Some(iteration) = iteration else {
+            return LuaValue::Nil;
+        };
+        match var {
+            DataVar::Index => LuaValue::Integer(iteration.index as i64),
+            DataVar::Key => iteration.key.clone(),
+            DataVar::First => LuaValue::Boolean(iteration.index == 1),
+            DataVar::Last => LuaValue::Boolean(iteration.last),
+        }
+    }
+
+    fn write(
+        &mut self,
+        template: &Template,
+        offset: usize,
+        value: &LuaValue,
+        escape: bool,
+    ) -> LuaResult<()> {
+        let Some(text) = to_text(value)? else {
+            return Err(template.error(
+                offset,
+                format!(
+                    "Expected a value that can be written, got '{}' - use a block or a filter to write its contents",
+                    value.type_name()
+                ),
+            ));
+        };
+        if escape {
+            escape_html(&text, &mut self.out);
+        } else {
+            self.out.extend_from_slice(&text);
+        }
+        Ok(())
+    }
+}
+
+fn index(value: &LuaValue, key: &str) -> LuaResult<LuaValue> {
+    let LuaValue::Table(table) = value else {
+        return Ok(LuaValue::Nil);
+    };
+    match key.parse::<i64>() {
+        Ok(n) => table.get(n),
+        Err(_) => table.get(key),
+    }
+}
+
+/**
+    Returns the entries of a table to loop over - the values of a list in order, or the
+    fields of any other table sorted by their keys, so that the output is always the same.
+*/
+fn entries(table: &LuaTable) -> LuaResult<Vec<(LuaValue, LuaValue)>> {
+    if table.raw_len() > 0 {
+        return table
+            .sequence_values::<LuaValue>()
+            .enumerate()
+            .map(|(i, value)| Ok((LuaValue::Integer(i as i64 + 1), value?)))
+            .collect();
+    }
+    let mut entries = table
+        .pairs::<LuaValue, LuaValue>()
+        .collect::<LuaResult<Vec<_>>>()?;
+    entries.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+    Ok(entries)
+}
+
+/// Orders numbers before strings, and any other keys last
+fn compare_keys(a: &LuaValue, b: &LuaValue) -> Ordering {
+    let number = |v: &LuaValue| match v {
+        LuaValue::Integer(n) => Some(*n as f64),
+        LuaValue::Number(n) => Some(*n),
+        _ => None,
+    };
+    match (a, b) {
+        (LuaValue::String(a), LuaValue::String(b)) => (*a.as_bytes()).cmp(&*b.as_bytes()),
+        (LuaValue::String(_), _) => number(b).map_or(Ordering::Less, |_| Ordering::Greater),
+        (_, LuaValue::String(_)) => number(a).map_or(Ordering::Greater, |_| Ordering::Less),
+        _ => match (number(a), number(b)) {
+            (Some(a), Some(b)) => a.total_cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+    }
+}
+
+/**
+    Returns `false` for `nil`, `false`, empty strings and empty tables.
+
+    Unlike in Lua, empty strings and tables are falsy so that blocks such as
+    `{{#if items}}` can check if there is anything to write.
+*/
+pub(crate) fn is_truthy(value: &LuaValue) -> bool {
+    match value {
+        LuaValue::Nil | LuaValue::Boolean(false) => false,
+        LuaValue::String(s) => !s.as_bytes().is_empty(),
+        LuaValue::Table(t) => !t.is_empty(),
+        _ => true,
+    }
+}
+
+/**
+    Converts a value to the text it is written as, where `nil` is written as nothing.
+
+    Returns `None` for values that can not be written, such as tables without a `__tostring` metamethod.
+*/
+pub(crate) fn to_text(value: &LuaValue) -> LuaResult<Option<Vec<u8>>> {
+    Ok(Some(match value {
+        LuaValue::Nil => Vec::new(),
+        LuaValue::String(s) => s.as_bytes().to_vec(),
+        LuaValue::Table(t)
+            if t.metatable().is_none_or(|mt| {
+                mt.raw_get::<LuaValue>("__tostring")
+                    .is_ok_and(|f| f.is_nil())
+            }) =>
+        {
+            return Ok(None);
+        }
+        LuaValue::Function(_) | LuaValue::Thread(_) => return Ok(None),
+        other => other.to_string()?.into_bytes(),
+    }))
+}
+
+/// Escapes characters that have a special meaning in HTML, including inside of attribute values
+pub(crate) fn escape_html(text: &[u8], out: &mut Vec<u8>) {
+    for &byte in text {
+        match byte {
+            b'&' => out.extend_from_slice(b"&amp;"),
+            b'<' => out.extend_from_slice(b"&lt;"),
+            b'>' => out.extend_from_slice(b"&gt;"),
+            b'"' => out.extend_from_slice(b"&quot;"),
+            b'\'' => out.extend_from_slice(b"&#39;"),
+            _ => out.push(byte),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::{io, time::Duration};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use mlua::prelude::*;
+
+const LETTERS: [&str; 26] = [
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S",
+    "T", "U", "V", "W", "X", "Y", "Z",
+];
+
+const DIGITS: [&str; 10] = [
+    "Zero", "One", "Two", "Three", "Four", "Five", "Six", "Seven", "Eight", "Nine",
+];
+
+const FUNCTION_KEYS: [&str; 12] = [
+    "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12",
+];
+
+/**
+    Returns the name of the `Enum.KeyCode` item for a key, if there is one.
+*/
+fn key_code_name(code: KeyCode) -> Option<&'static str> {
+    Some(match code {
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+            LETTERS[(c.to_ascii_uppercase() as u8 - b'A') as usize]
+        }
+        KeyCode::Char(c) if c.is_ascii_digit() => DIGITS[(c as u8 - b'0') as usize],
+        KeyCode::Char(' ') => "Space",
+        KeyCode::Char(';') => "Semicolon",
+        KeyCode::Char('=') => "Equals",
+        KeyCode::Char(',') => "Comma",
+        KeyCode::Char('-') => "Minus",
+        KeyCode::Char('.') => "Period",
+        KeyCode::Char('/') => "Slash",
+        KeyCode::Char('`') => "Grave",
+        KeyCode::Char('[') => "LeftBracket",
+        KeyCode::Char('\\') => "Backslash",
+        KeyCode::Char(']') => "RightBracket",
+        KeyCode::Char('\'') => "Apostrophe",
+        KeyCode::F(n @ 1..=12) => FUNCTION_KEYS[n as usize - 1],
+        KeyCode::Enter => "Return",
+        KeyCode::Backspace => "Backspace",
+        KeyCode::Delete => "Delete",
+        KeyCode::Insert => "Insert",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        KeyCode::Up => "Up",
+        KeyCode::Down => "Down",
+        KeyCode::Left => "Left",
+        KeyCode::Right => "Right",
+        KeyCode::Tab | KeyCode::BackTab => "Tab",
+        KeyCode::Esc => "Escape",
+        KeyCode::CapsLock => "CapsLock",
+        KeyCode::NumLock => "NumLock",
+        KeyCode::Menu => "Menu",
+        _ => return None,
+    })
+}
+
+/**
+    Blocks until a key is pressed, or until the timeout runs out.
+
+    Raw mode is enabled while waiting, unless it already was, so that
+    keys are received as soon as they are pressed instead of per line.
+*/
+pub fn read_key(timeout: Option<Duration>) -> io::Result<Option<KeyEvent>> {
+    let was_raw = terminal::is_raw_mode_enabled()?;
+    if !was_raw {
+        terminal::enable_raw_mode()?;
+    }
+
+    let result = loop {
+        if let Some(timeout) = timeout {
+            match event::poll(timeout) {
+                Ok(true) => {}
+                Ok(false) => break Ok(None),
+                Err(e) => break Err(e),
+            }
+        }
+        match event::read() {
+            // NOTE: Windows also reports key releases, which we never want here
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => break Ok(Some(key)),
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+
+    if !was_raw {
+        terminal::disable_raw_mode()?;
+    }
+    result
+}
+
+/**
+    Converts a key event into a table with its `Enum.KeyCode` item, name, text and modifiers.
+*/
+pub fn key_event_to_table(lua: &Lua, key: KeyEvent) -> LuaResult<LuaTable> {
+    let name = key_code_name(key.code);
+    let key_code = match name {
+        Some(name) => match lua.globals().get::<Option<LuaTable>>("Enum")? {
+            Some(enums) => enums.get::<LuaTable>("KeyCode")?.get::<LuaValue>(name)?,
+            None => LuaValue::Nil,
+        },
+        None => LuaValue::Nil,
+    };
+
+    let t = lua.create_table_with_capacity(0, 6)?;
+    t.set("KeyCode", key_code)?;
+    t.set("Name", name)?;
+    if let KeyCode::Char(c) = key.code {
+        t.set("Char", c.to_string())?;
+    }
+    t.set("Shift", key.modifiers.contains(KeyModifiers::SHIFT))?;
+    t.set("Ctrl", key.modifiers.contains(KeyModifiers::CONTROL))?;
+    t.set("Alt", key.modifiers.contains(KeyModifiers::ALT))?;
+    Ok(t)
+}
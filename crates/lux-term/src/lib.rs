@@ -0,0 +1,203 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::{
+    io::{IsTerminal, Write, stdout},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor, execute, queue,
+    terminal::{self, ClearType},
+};
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use lux_color::Color3;
+use lux_utils::TableBuilder;
+
+mod keys;
+mod resize;
+mod style;
+mod widgets;
+
+use self::style::TextStyle;
+use self::widgets::{ProgressBar, Spinner};
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `term` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `term` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    let resized = lua.create_userdata(resize::create_resized_signal())?;
+    TableBuilder::new(lua)?
+        .with_function("style", term_style)?
+        .with_function("fg", term_fg)?
+        .with_function("bg", term_bg)?
+        .with_function("stripAnsi", term_strip_ansi)?
+        .with_function("isTerminal", term_is_terminal)?
+        .with_function("size", term_size)?
+        .with_value("Resized", resized)?
+        .with_function("moveTo", term_move_to)?
+        .with_function("moveBy", term_move_by)?
+        .with_function("hideCursor", term_hide_cursor)?
+        .with_function("showCursor", term_show_cursor)?
+        .with_function("clear", term_clear)?
+        .with_function("clearLine", term_clear_line)?
+        .with_function("setRawMode", term_set_raw_mode)?
+        .with_function("isRawMode", term_is_raw_mode)?
+        .with_async_function("readKey", term_read_key)?
+        .with_function("progress", term_progress)?
+        .with_function("spinner", term_spinner)?
+        .build_readonly()
+}
+
+/**
+    Disables raw mode when dropped, so that a script exiting
+    with raw mode enabled does not leave the terminal broken.
+*/
+struct RawModeGuard;
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn term_style(_: &Lua, (text, style): (String, TextStyle)) -> LuaResult<String> {
+    Ok(style.apply(&text))
+}
+
+fn term_fg(_: &Lua, color: Option<LuaUserDataRef<Color3>>) -> LuaResult<String> {
+    Ok(match color {
+        Some(color) => style::foreground(&color),
+        None => style::ESCAPE_SEQ_DEFAULT_FG.to_string(),
+    })
+}
+
+fn term_bg(_: &Lua, color: Option<LuaUserDataRef<Color3>>) -> LuaResult<String> {
+    Ok(match color {
+        Some(color) => style::background(&color),
+        None => style::ESCAPE_SEQ_DEFAULT_BG.to_string(),
+    })
+}
+
+fn term_strip_ansi(_: &Lua, text: String) -> LuaResult<String> {
+    Ok(style::strip_ansi(&text))
+}
+
+fn term_is_terminal(_: &Lua, (): ()) -> LuaResult<bool> {
+    Ok(stdout().is_terminal())
+}
+
+fn term_size(_: &Lua, (): ()) -> LuaResult<(u16, u16)> {
+    terminal::size()
+        .map_err(|e| LuaError::runtime(format!("Failed to get the terminal size - {e}")))
+}
+
+fn term_move_to(_: &Lua, (column, row): (u16, u16)) -> LuaResult<()> {
+    execute!(stdout(), cursor::MoveTo(column, row))?;
+    Ok(())
+}
+
+fn term_move_by(_: &Lua, (columns, rows): (i32, i32)) -> LuaResult<()> {
+    let mut stdout = stdout().lock();
+    // NOTE: A distance of zero moves by one in most terminals, so skip those entirely
+    let clamp = |n: i32| n.unsigned_abs().min(u32::from(u16::MAX)) as u16;
+    match columns {
+        0 => {}
+        n if n > 0 => queue!(stdout, cursor::MoveRight(clamp(n)))?,
+        n => queue!(stdout, cursor::MoveLeft(clamp(n)))?,
+    }
+    match rows {
+        0 => {}
+        n if n > 0 => queue!(stdout, cursor::MoveDown(clamp(n)))?,
+        n => queue!(stdout, cursor::MoveUp(clamp(n)))?,
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+fn term_hide_cursor(_: &Lua, (): ()) -> LuaResult<()> {
+    execute!(stdout(), cursor::Hide)?;
+    Ok(())
+}
+
+fn term_show_cursor(_: &Lua, (): ()) -> LuaResult<()> {
+    execute!(stdout(), cursor::Show)?;
+    Ok(())
+}
+
+fn term_clear(_: &Lua, (): ()) -> LuaResult<()> {
+    execute!(
+        stdout(),
+        terminal::Clear(ClearType::All),
+        cursor::MoveTo(0, 0)
+    )?;
+    Ok(())
+}
+
+fn term_clear_line(_: &Lua, (): ()) -> LuaResult<()> {
+    execute!(
+        stdout(),
+        terminal::Clear(ClearType::CurrentLine),
+        cursor::MoveToColumn(0)
+    )?;
+    Ok(())
+}
+
+fn term_set_raw_mode(lua: &Lua, enabled: bool) -> LuaResult<()> {
+    if enabled {
+        terminal::enable_raw_mode()
+            .map_err(|e| LuaError::runtime(format!("Failed to enable raw mode - {e}")))?;
+        lua.set_app_data(RawModeGuard);
+    } else {
+        // NOTE: Dropping the guard is what disables raw mode
+        if lua.remove_app_data::<RawModeGuard>().is_none() {
+            terminal::disable_raw_mode()?;
+        }
+    }
+    Ok(())
+}
+
+fn term_is_raw_mode(_: &Lua, (): ()) -> LuaResult<bool> {
+    Ok(terminal::is_raw_mode_enabled()?)
+}
+
+async fn term_read_key(lua: Lua, timeout: Option<f64>) -> LuaResult<Option<LuaTable>> {
+    let timeout = match timeout {
+        None => None,
+        Some(t) if t.is_finite() && t >= 0.0 => Some(Duration::from_secs_f64(t)),
+        Some(t) => {
+            return Err(LuaError::runtime(format!(
+                "Expected timeout to be a non-negative number, got {t}"
+            )));
+        }
+    };
+    let key = lua
+        .spawn_blocking(move || keys::read_key(timeout))
+        .await
+        .map_err(|e| LuaError::runtime(format!("Failed to read key - {e}")))?;
+    key.map(|key| keys::key_event_to_table(&lua, key))
+        .transpose()
+}
+
+fn term_progress(lua: &Lua, (total, message): (f64, Option<String>)) -> LuaResult<ProgressBar> {
+    ProgressBar::new(lua, total, message)
+}
+
+fn term_spinner(lua: &Lua, message: Option<String>) -> LuaResult<Spinner> {
+    Ok(Spinner::new(lua, message))
+}
@@ -0,0 +1,51 @@
+use std::{
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use async_io::Timer;
+use crossterm::terminal;
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use lux_signal::Signal;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/**
+    Creates the `term.Resized` signal, fired with the new column and row count.
+
+    The terminal size is only polled while a handler is connected, so that an
+    unused signal never keeps the scheduler alive. Polling starts again once
+    a handler gets connected after the last one was disconnected.
+*/
+pub fn create_resized_signal() -> Signal {
+    let watching = Arc::new(AtomicBool::new(false));
+    Signal::new().with_connect_hook(Rc::new(move |lua, signal| {
+        if !watching.swap(true, Ordering::SeqCst) {
+            lua.spawn_local(watch(lua.clone(), signal.clone(), Arc::clone(&watching)));
+        }
+    }))
+}
+
+async fn watch(lua: Lua, signal: Signal, watching: Arc<AtomicBool>) {
+    let mut last = terminal::size().ok();
+    while signal.count() > 0 {
+        Timer::after(POLL_INTERVAL).await;
+        let current = terminal::size().ok();
+        if current == last {
+            continue;
+        }
+        last = current;
+        if let Some((columns, rows)) = current
+            && let Ok(args) = (columns, rows).into_lua_multi(&lua)
+        {
+            signal.fire_native(&lua, args);
+        }
+    }
+    watching.store(false, Ordering::SeqCst);
+}
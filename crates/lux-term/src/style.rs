@@ -0,0 +1,146 @@
+use mlua::prelude::*;
+
+use lux_color::Color3;
+
+pub const ESCAPE_SEQ_RESET: &str = "\x1b[0m";
+pub const ESCAPE_SEQ_DEFAULT_FG: &str = "\x1b[39m";
+pub const ESCAPE_SEQ_DEFAULT_BG: &str = "\x1b[49m";
+
+#[inline]
+fn channel(value: f64) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/**
+    Returns the 24-bit ANSI escape sequence for a foreground color.
+*/
+#[must_use]
+pub fn foreground(color: &Color3) -> String {
+    let (r, g, b) = (channel(color.r), channel(color.g), channel(color.b));
+    format!("\x1b[38;2;{r};{g};{b}m")
+}
+
+/**
+    Returns the 24-bit ANSI escape sequence for a background color.
+*/
+#[must_use]
+pub fn background(color: &Color3) -> String {
+    let (r, g, b) = (channel(color.r), channel(color.g), channel(color.b));
+    format!("\x1b[48;2;{r};{g};{b}m")
+}
+
+/**
+    Options for `term.style`.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct TextStyle {
+    fg: Option<Color3>,
+    bg: Option<Color3>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    inverse: bool,
+    strikethrough: bool,
+}
+
+impl TextStyle {
+    /**
+        Wraps text in the escape sequences for this style, resetting all styling after it.
+
+        Returns the text as-is if this style does not change anything.
+    */
+    #[must_use]
+    pub fn apply(&self, text: &str) -> String {
+        let mut prefix = String::new();
+        let flags = [
+            (self.bold, "\x1b[1m"),
+            (self.dim, "\x1b[2m"),
+            (self.italic, "\x1b[3m"),
+            (self.underline, "\x1b[4m"),
+            (self.inverse, "\x1b[7m"),
+            (self.strikethrough, "\x1b[9m"),
+        ];
+        for (enabled, seq) in flags {
+            if enabled {
+                prefix.push_str(seq);
+            }
+        }
+        if let Some(fg) = &self.fg {
+            prefix.push_str(&foreground(fg));
+        }
+        if let Some(bg) = &self.bg {
+            prefix.push_str(&background(bg));
+        }
+        if prefix.is_empty() {
+            text.to_string()
+        } else {
+            format!("{prefix}{text}{ESCAPE_SEQ_RESET}")
+        }
+    }
+}
+
+impl FromLua for TextStyle {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let color = |key: &str| {
+                    t.get::<Option<LuaUserDataRef<Color3>>>(key)
+                        .map(|c| c.map(|c| *c))
+                };
+                let flag = |key: &str| t.get::<Option<bool>>(key).map(Option::unwrap_or_default);
+                Ok(Self {
+                    fg: color("fg")?,
+                    bg: color("bg")?,
+                    bold: flag("bold")?,
+                    dim: flag("dim")?,
+                    italic: flag("italic")?,
+                    underline: flag("underline")?,
+                    inverse: flag("inverse")?,
+                    strikethrough: flag("strikethrough")?,
+                })
+            }
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("TextStyle"),
+                message: Some(String::from("Expected style to be a table")),
+            }),
+        }
+    }
+}
+
+/**
+    Removes ANSI escape sequences from text, such as colors and cursor movement.
+*/
+#[must_use]
+pub fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI - parameters followed by a single final byte
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC - terminated by BEL or ST (ESC backslash)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
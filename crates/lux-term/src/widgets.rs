@@ -0,0 +1,215 @@
+use std::{
+    io::{IsTerminal, Write, stderr},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_executor::Task;
+use async_io::Timer;
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+use parking_lot::Mutex;
+
+const FRAME_INTERVAL: Duration = Duration::from_millis(80);
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const BAR_WIDTH: usize = 30;
+
+/**
+    Replaces the current line on stderr, ignoring any errors.
+*/
+fn redraw(line: &str) {
+    let mut stderr = stderr().lock();
+    let _ = write!(stderr, "\r\x1b[2K{line}");
+    let _ = stderr.flush();
+}
+
+/**
+    The state of a widget that is drawn as a single line.
+*/
+trait Line: Send + 'static {
+    fn line(&self, tick: usize) -> String;
+}
+
+/**
+    A line on stderr that gets redrawn on every frame by a background task on the scheduler.
+
+    The task does not keep the scheduler alive, and nothing is drawn until
+    the widget finishes when stderr is not a terminal, such as in logs.
+*/
+struct Live<S: Line> {
+    state: Arc<Mutex<S>>,
+    ticker: Mutex<Option<Task<()>>>,
+    finished: Mutex<bool>,
+}
+
+impl<S: Line> Live<S> {
+    fn start(lua: &Lua, state: S) -> Self {
+        let state = Arc::new(Mutex::new(state));
+        let ticker = stderr().is_terminal().then(|| {
+            let state = Arc::clone(&state);
+            lua.spawn(async move {
+                for tick in 0.. {
+                    redraw(&state.lock().line(tick));
+                    Timer::after(FRAME_INTERVAL).await;
+                }
+            })
+        });
+        Self {
+            state,
+            ticker: Mutex::new(ticker),
+            finished: Mutex::new(false),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut S)) {
+        f(&mut self.state.lock());
+    }
+
+    /**
+        Stops redrawing, and replaces the line with a final one, or clears it if there is none.
+
+        Does nothing if the widget was already finished.
+    */
+    fn finish(&self, last: impl FnOnce(&S) -> Option<String>) {
+        if std::mem::replace(&mut *self.finished.lock(), true) {
+            return;
+        }
+        // NOTE: Dropping the task cancels it, so it can not draw over the final line
+        let ticker = self.ticker.lock().take();
+        let last = last(&self.state.lock());
+        match (ticker.is_some(), last) {
+            (true, Some(line)) => redraw(&format!("{line}\n")),
+            (true, None) => redraw(""),
+            (false, Some(line)) => {
+                let _ = writeln!(stderr(), "{line}");
+            }
+            (false, None) => {}
+        }
+    }
+}
+
+struct ProgressState {
+    value: f64,
+    total: f64,
+    message: String,
+}
+
+impl Line for ProgressState {
+    fn line(&self, _: usize) -> String {
+        let ratio = if self.total > 0.0 {
+            (self.value / self.total).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let filled = (ratio * BAR_WIDTH as f64).round() as usize;
+        let line = format!(
+            "[{}{}] {:>3}% {}/{} {}",
+            "█".repeat(filled),
+            "░".repeat(BAR_WIDTH - filled),
+            (ratio * 100.0).floor(),
+            self.value,
+            self.total,
+            self.message
+        );
+        line.trim_end().to_string()
+    }
+}
+
+/**
+    A progress bar drawn on stderr, created with `term.progress`.
+*/
+pub struct ProgressBar(Live<ProgressState>);
+
+impl ProgressBar {
+    pub fn new(lua: &Lua, total: f64, message: Option<String>) -> LuaResult<Self> {
+        if !total.is_finite() || total < 0.0 {
+            return Err(LuaError::runtime(format!(
+                "Expected progress total to be a non-negative number, got {total}"
+            )));
+        }
+        Ok(Self(Live::start(
+            lua,
+            ProgressState {
+                value: 0.0,
+                total,
+                message: message.unwrap_or_default(),
+            },
+        )))
+    }
+}
+
+impl LuaUserData for ProgressBar {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_field_method_get("Value", |_, this| Ok(this.0.state.lock().value));
+        fields.add_field_method_get("Total", |_, this| Ok(this.0.state.lock().total));
+
+        fields.add_meta_field(LuaMetaMethod::Type, "ProgressBar");
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("Set", |_, this, value: f64| {
+            this.0.update(|s| s.value = value.clamp(0.0, s.total));
+            Ok(())
+        });
+        methods.add_method("Increment", |_, this, amount: Option<f64>| {
+            this.0
+                .update(|s| s.value = (s.value + amount.unwrap_or(1.0)).clamp(0.0, s.total));
+            Ok(())
+        });
+        methods.add_method("SetMessage", |_, this, message: String| {
+            this.0.update(|s| s.message = message);
+            Ok(())
+        });
+        methods.add_method("Finish", |_, this, message: Option<String>| {
+            if let Some(message) = message {
+                this.0.update(|s| s.message = message);
+            }
+            this.0.finish(|s| Some(s.line(0)));
+            Ok(())
+        });
+    }
+}
+
+struct SpinnerState {
+    message: String,
+}
+
+impl Line for SpinnerState {
+    fn line(&self, tick: usize) -> String {
+        let frame = SPINNER_FRAMES[tick % SPINNER_FRAMES.len()];
+        format!("{frame} {}", self.message).trim_end().to_string()
+    }
+}
+
+/**
+    A spinner drawn on stderr, created with `term.spinner`.
+*/
+pub struct Spinner(Live<SpinnerState>);
+
+impl Spinner {
+    pub fn new(lua: &Lua, message: Option<String>) -> Self {
+        Self(Live::start(
+            lua,
+            SpinnerState {
+                message: message.unwrap_or_default(),
+            },
+        ))
+    }
+}
+
+impl LuaUserData for Spinner {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Spinner");
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("SetMessage", |_, this, message: String| {
+            this.0.update(|s| s.message = message);
+            Ok(())
+        });
+        methods.add_method("Stop", |_, this, message: Option<String>| {
+            this.0.finish(|_| message);
+            Ok(())
+        });
+    }
+}
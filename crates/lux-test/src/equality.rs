@@ -0,0 +1,56 @@
+use mlua::prelude::*;
+
+use lux_color::Color3;
+use lux_vector::{Vector2, Vector3};
+
+pub(crate) use lux_utils::equality::deep_equals;
+
+fn as_number(value: &LuaValue) -> Option<f64> {
+    match value {
+        LuaValue::Integer(i) => Some(*i as f64),
+        LuaValue::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/**
+    Returns the kind and components of a number, `Vector2`, `Vector3` or `Color3`.
+*/
+fn components(value: &LuaValue) -> Option<(&'static str, Vec<f64>)> {
+    if let Some(n) = as_number(value) {
+        return Some(("number", vec![n]));
+    }
+    let LuaValue::UserData(ud) = value else {
+        return None;
+    };
+    if let Ok(v) = ud.borrow::<Vector3>() {
+        Some(("Vector3", vec![v.x, v.y, v.z]))
+    } else if let Ok(v) = ud.borrow::<Vector2>() {
+        Some(("Vector2", vec![v.x, v.y]))
+    } else if let Ok(c) = ud.borrow::<Color3>() {
+        Some(("Color3", vec![c.r, c.g, c.b]))
+    } else {
+        None
+    }
+}
+
+/**
+    Checks if two numbers, or all components of two vectors or colors,
+    differ by less than half of `10 ^ -precision`.
+
+    Returns `None` if the values are not of the same supported type.
+*/
+// NOTE: Exactly equal components pass even when infinite, where the difference is NaN
+#[allow(clippy::float_cmp)]
+pub(crate) fn close_to(a: &LuaValue, b: &LuaValue, precision: i32) -> Option<bool> {
+    let ((kind_a, ca), (kind_b, cb)) = (components(a)?, components(b)?);
+    if kind_a != kind_b {
+        return None;
+    }
+    let tolerance = 10f64.powi(-precision) / 2.0;
+    Some(
+        ca.iter()
+            .zip(&cb)
+            .all(|(x, y)| x == y || (x - y).abs() < tolerance),
+    )
+}
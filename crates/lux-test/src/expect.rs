@@ -0,0 +1,258 @@
+use std::fmt::Display;
+
+use mlua::prelude::*;
+
+use lux_utils::fmt::{ValueFormatConfig, pretty_format_value};
+
+use crate::{
+    equality::{close_to, deep_equals},
+    runner::error_message,
+};
+
+const DEFAULT_PRECISION: i32 = 2;
+
+// NOTE: Top-level strings are formatted without quotes by default,
+// which would make `expected 1 to be "1"` impossible to make sense of
+const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(2)
+    .with_quoted_strings(true);
+
+fn format_value(value: &LuaValue) -> String {
+    pretty_format_value(value, &FORMAT_CONFIG)
+}
+
+fn expect_number(value: &LuaValue) -> LuaResult<f64> {
+    match value {
+        LuaValue::Integer(i) => Ok(*i as f64),
+        LuaValue::Number(n) => Ok(*n),
+        _ => Err(LuaError::runtime(format!(
+            "expected a number, got {}",
+            format_value(value)
+        ))),
+    }
+}
+
+/**
+    Prefixes a failure message with the location of the Lua code
+    that called the matcher, the same way that `error` does.
+*/
+fn with_location(lua: &Lua, message: String) -> String {
+    let location = lua
+        .inspect_stack(1, |debug| {
+            let line = debug.current_line()?;
+            let source = debug.source().short_src?.to_string();
+            Some(format!("{source}:{line}"))
+        })
+        .flatten();
+    match location {
+        Some(location) => format!("{location}: {message}"),
+        None => message,
+    }
+}
+
+/**
+    The value passed to `expect`, with matchers to make assertions about it.
+
+    Accessing `never` gives an expectation where all matchers are negated.
+*/
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    value: LuaValue,
+    negated: bool,
+}
+
+impl Expectation {
+    pub(crate) fn new(value: LuaValue) -> Self {
+        Self {
+            value,
+            negated: false,
+        }
+    }
+
+    fn check(&self, lua: &Lua, pass: bool, description: impl Display) -> LuaResult<()> {
+        if pass != self.negated {
+            return Ok(());
+        }
+        let not = if self.negated { "not " } else { "" };
+        let message = format!("expected {} {not}{description}", format_value(&self.value));
+        Err(LuaError::runtime(with_location(lua, message)))
+    }
+
+    fn compare(
+        &self,
+        lua: &Lua,
+        other: f64,
+        description: &str,
+        cmp: fn(f64, f64) -> bool,
+    ) -> LuaResult<()> {
+        let value = expect_number(&self.value)?;
+        self.check(
+            lua,
+            cmp(value, other),
+            format_args!("{description} {other}"),
+        )
+    }
+}
+
+impl LuaUserData for Expectation {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "Expectation");
+        fields.add_field_method_get("never", |_, this| {
+            Ok(Self {
+                value: this.value.clone(),
+                negated: !this.negated,
+            })
+        });
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("toBe", |lua, this, expected: LuaValue| {
+            let pass = this.value.equals(&expected)?;
+            this.check(lua, pass, format_args!("to be {}", format_value(&expected)))
+        });
+        methods.add_method("toEqual", |lua, this, expected: LuaValue| {
+            let pass = deep_equals(&this.value, &expected)?;
+            this.check(
+                lua,
+                pass,
+                format_args!("to equal {}", format_value(&expected)),
+            )
+        });
+        methods.add_method(
+            "toBeCloseTo",
+            |lua, this, (expected, precision): (LuaValue, Option<i32>)| {
+                let precision = precision.unwrap_or(DEFAULT_PRECISION);
+                let pass = close_to(&this.value, &expected, precision).ok_or_else(|| {
+                    LuaError::runtime(
+                        "toBeCloseTo expects two numbers, Vector2s, Vector3s or Color3s",
+                    )
+                })?;
+                this.check(
+                    lua,
+                    pass,
+                    format_args!(
+                        "to be close to {} (precision {precision})",
+                        format_value(&expected)
+                    ),
+                )
+            },
+        );
+        methods.add_method("toBeNil", |lua, this, ()| {
+            this.check(lua, this.value.is_nil(), "to be nil")
+        });
+        methods.add_method("toBeTruthy", |lua, this, ()| {
+            let truthy = !matches!(this.value, LuaValue::Nil | LuaValue::Boolean(false));
+            this.check(lua, truthy, "to be truthy")
+        });
+        methods.add_method("toBeFalsy", |lua, this, ()| {
+            let falsy = matches!(this.value, LuaValue::Nil | LuaValue::Boolean(false));
+            this.check(lua, falsy, "to be falsy")
+        });
+        methods.add_method("toBeA", |lua, this, type_name: String| {
+            let actual = lua
+                .globals()
+                .get::<LuaFunction>("typeof")?
+                .call::<String>(&this.value)?;
+            this.check(
+                lua,
+                actual == type_name,
+                format_args!("to be a {type_name} (got {actual})"),
+            )
+        });
+        methods.add_method("toBeGreaterThan", |lua, this, other: f64| {
+            this.compare(lua, other, "to be greater than", |a, b| a > b)
+        });
+        methods.add_method("toBeGreaterThanOrEqual", |lua, this, other: f64| {
+            this.compare(lua, other, "to be greater than or equal to", |a, b| a >= b)
+        });
+        methods.add_method("toBeLessThan", |lua, this, other: f64| {
+            this.compare(lua, other, "to be less than", |a, b| a < b)
+        });
+        methods.add_method("toBeLessThanOrEqual", |lua, this, other: f64| {
+            this.compare(lua, other, "to be less than or equal to", |a, b| a <= b)
+        });
+        methods.add_method("toContain", |lua, this, item: LuaValue| {
+            let pass = match (&this.value, &item) {
+                (LuaValue::String(s), LuaValue::String(sub)) => {
+                    let (s, sub) = (s.as_bytes(), sub.as_bytes());
+                    sub.is_empty() || s.windows(sub.len()).any(|w| w == &*sub)
+                }
+                (LuaValue::Table(t), _) => {
+                    let mut found = false;
+                    for value in t.sequence_values::<LuaValue>() {
+                        if deep_equals(&value?, &item)? {
+                            found = true;
+                            break;
+                        }
+                    }
+                    found
+                }
+                _ => {
+                    return Err(LuaError::runtime(format!(
+                        "toContain expects a string or an array, got {}",
+                        format_value(&this.value)
+                    )));
+                }
+            };
+            this.check(
+                lua,
+                pass,
+                format_args!("to contain {}", format_value(&item)),
+            )
+        });
+        methods.add_method("toHaveLength", |lua, this, length: usize| {
+            let actual = match &this.value {
+                LuaValue::String(s) => s.as_bytes().len(),
+                LuaValue::Table(t) => t.raw_len(),
+                _ => {
+                    return Err(LuaError::runtime(format!(
+                        "toHaveLength expects a string or a table, got {}",
+                        format_value(&this.value)
+                    )));
+                }
+            };
+            this.check(
+                lua,
+                actual == length,
+                format_args!("to have length {length} (got {actual})"),
+            )
+        });
+        methods.add_method("toMatch", |lua, this, pattern: LuaString| {
+            let LuaValue::String(s) = &this.value else {
+                return Err(LuaError::runtime(format!(
+                    "toMatch expects a string, got {}",
+                    format_value(&this.value)
+                )));
+            };
+            let find = lua
+                .globals()
+                .get::<LuaTable>("string")?
+                .get::<LuaFunction>("find")?;
+            let pass = !find.call::<LuaValue>((s, &pattern))?.is_nil();
+            this.check(
+                lua,
+                pass,
+                format_args!("to match {:?}", pattern.to_string_lossy()),
+            )
+        });
+        methods.add_method("toThrow", |lua, this, expected: Option<String>| {
+            let LuaValue::Function(f) = &this.value else {
+                return Err(LuaError::runtime(format!(
+                    "toThrow expects a function, got {}",
+                    format_value(&this.value)
+                )));
+            };
+            let thrown = f.call::<()>(()).err().map(|e| error_message(&e));
+            let (pass, description) = match (&thrown, &expected) {
+                (None, _) => (false, String::from("to throw")),
+                (Some(_), None) => (true, String::from("to throw")),
+                (Some(message), Some(expected)) => (
+                    message.contains(expected.as_str()),
+                    format!("to throw an error containing {expected:?} (got {message:?})"),
+                ),
+            };
+            this.check(lua, pass, description)
+        });
+    }
+}
@@ -0,0 +1,49 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod equality;
+mod expect;
+mod registry;
+mod runner;
+
+pub use self::expect::Expectation;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `test` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `test` standard library module.
+
+    Tests registered through the module are kept for the lifetime of the Lua
+    state, so that they survive the module being created again, and are
+    cleared once they have been run using `run`.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    registry::init(&lua);
+    TableBuilder::new(lua)?
+        .with_function("describe", registry::describe)?
+        .with_function("it", registry::it)?
+        .with_function("test", registry::it)?
+        .with_function("skip", registry::skip)?
+        .with_function("beforeAll", registry::before_all)?
+        .with_function("afterAll", registry::after_all)?
+        .with_function("beforeEach", registry::before_each)?
+        .with_function("afterEach", registry::after_each)?
+        .with_function("expect", |_, value: LuaValue| Ok(Expectation::new(value)))?
+        .with_async_function("run", runner::run)?
+        .build_readonly()
+}
@@ -0,0 +1,168 @@
+use mlua::prelude::*;
+
+const ROOT_SCOPE: usize = 0;
+
+/**
+    A `describe` block, or the root scope of a test file.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Scope {
+    pub(crate) name: Option<String>,
+    pub(crate) parent: Option<usize>,
+    pub(crate) before_all: Vec<LuaFunction>,
+    pub(crate) after_all: Vec<LuaFunction>,
+    pub(crate) before_each: Vec<LuaFunction>,
+    pub(crate) after_each: Vec<LuaFunction>,
+}
+
+/**
+    A single test registered using `it`, `test` or `skip`.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct TestCase {
+    pub(crate) name: String,
+    pub(crate) scope: usize,
+    pub(crate) function: LuaFunction,
+    pub(crate) skipped: bool,
+}
+
+/**
+    All scopes and tests registered in a Lua state, which have not yet been run.
+
+    Scopes are stored in a flat list and refer to their parent by index,
+    with the root scope always at index `0`, so that tests can find all
+    hooks that apply to them at run time - including any hooks that were
+    registered after the test itself.
+*/
+#[derive(Debug, Clone)]
+pub(crate) struct Registry {
+    pub(crate) scopes: Vec<Scope>,
+    pub(crate) tests: Vec<TestCase>,
+    current: usize,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            scopes: vec![Scope::default()],
+            tests: Vec::new(),
+            current: ROOT_SCOPE,
+        }
+    }
+}
+
+impl Registry {
+    /**
+        Returns the indices of the given scope and all of its ancestors, outermost first.
+    */
+    pub(crate) fn chain(&self, scope: usize) -> Vec<usize> {
+        let mut chain = vec![scope];
+        let mut current = scope;
+        while let Some(parent) = self.scopes[current].parent {
+            chain.push(parent);
+            current = parent;
+        }
+        chain.reverse();
+        chain
+    }
+
+    /**
+        Returns the full name of a test, including the names of all `describe` blocks around it.
+    */
+    pub(crate) fn full_name(&self, test: &TestCase) -> String {
+        self.chain(test.scope)
+            .into_iter()
+            .filter_map(|scope| self.scopes[scope].name.as_deref())
+            .chain([test.name.as_str()])
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+}
+
+pub(crate) fn init(lua: &Lua) {
+    if lua.app_data_ref::<Registry>().is_none() {
+        lua.set_app_data(Registry::default());
+    }
+}
+
+/**
+    Takes all registered tests out of the Lua state, leaving an empty registry behind.
+*/
+pub(crate) fn take(lua: &Lua) -> Registry {
+    lua.app_data_mut::<Registry>()
+        .map(|mut registry| std::mem::take(&mut *registry))
+        .unwrap_or_default()
+}
+
+fn with_registry<R>(lua: &Lua, f: impl FnOnce(&mut Registry) -> R) -> LuaResult<R> {
+    let mut registry = lua
+        .app_data_mut::<Registry>()
+        .ok_or_else(|| LuaError::runtime("Test registry is not initialized"))?;
+    Ok(f(&mut registry))
+}
+
+pub(crate) fn describe(lua: &Lua, (name, body): (String, LuaFunction)) -> LuaResult<()> {
+    let parent = with_registry(lua, |registry| {
+        let parent = registry.current;
+        registry.scopes.push(Scope {
+            name: Some(name),
+            parent: Some(parent),
+            ..Scope::default()
+        });
+        registry.current = registry.scopes.len() - 1;
+        parent
+    })?;
+
+    // NOTE: The scope must be restored even if the body errors,
+    // otherwise all following tests would end up inside of it
+    let result = body.call::<()>(());
+    with_registry(lua, |registry| registry.current = parent)?;
+    result
+}
+
+fn register(lua: &Lua, name: String, function: LuaFunction, skipped: bool) -> LuaResult<()> {
+    with_registry(lua, |registry| {
+        let scope = registry.current;
+        registry.tests.push(TestCase {
+            name,
+            scope,
+            function,
+            skipped,
+        });
+    })
+}
+
+pub(crate) fn it(lua: &Lua, (name, function): (String, LuaFunction)) -> LuaResult<()> {
+    register(lua, name, function, false)
+}
+
+pub(crate) fn skip(lua: &Lua, (name, function): (String, LuaFunction)) -> LuaResult<()> {
+    register(lua, name, function, true)
+}
+
+fn add_hook(
+    lua: &Lua,
+    hook: LuaFunction,
+    select: fn(&mut Scope) -> &mut Vec<LuaFunction>,
+) -> LuaResult<()> {
+    with_registry(lua, |registry| {
+        let current = registry.current;
+        select(&mut registry.scopes[current]).push(hook);
+    })
+}
+
+pub(crate) fn before_all(lua: &Lua, hook: LuaFunction) -> LuaResult<()> {
+    add_hook(lua, hook, |scope| &mut scope.before_all)
+}
+
+pub(crate) fn after_all(lua: &Lua, hook: LuaFunction) -> LuaResult<()> {
+    add_hook(lua, hook, |scope| &mut scope.after_all)
+}
+
+pub(crate) fn before_each(lua: &Lua, hook: LuaFunction) -> LuaResult<()> {
+    add_hook(lua, hook, |scope| &mut scope.before_each)
+}
+
+pub(crate) fn after_each(lua: &Lua, hook: LuaFunction) -> LuaResult<()> {
+    add_hook(lua, hook, |scope| &mut scope.after_each)
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use mlua::prelude::*;
+
+use lux_utils::overrides;
+
+use crate::registry::{self, Registry, TestCase};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Passed => "passed",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+struct Outcome {
+    name: String,
+    status: Status,
+    duration: Duration,
+    error: Option<String>,
+}
+
+impl IntoLua for Outcome {
+    fn into_lua(self, lua: &Lua) -> LuaResult<LuaValue> {
+        let table = lua.create_table()?;
+        table.set("name", self.name)?;
+        table.set("status", self.status.as_str())?;
+        table.set("duration", self.duration.as_secs_f64())?;
+        table.set("error", self.error)?;
+        table.into_lua(lua)
+    }
+}
+
+/**
+    Extracts the message from an error raised by a test, without
+    any of the tracebacks or context added along the way.
+*/
+pub(crate) fn error_message(error: &LuaError) -> String {
+    match error {
+        LuaError::CallbackError { cause, .. } | LuaError::WithContext { cause, .. } => {
+            error_message(cause)
+        }
+        LuaError::RuntimeError(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+async fn call_hooks(hooks: &[LuaFunction]) -> Result<(), String> {
+    for hook in hooks {
+        hook.call_async::<()>(())
+            .await
+            .map_err(|e| error_message(&e))?;
+    }
+    Ok(())
+}
+
+/**
+    Runs a single test along with its `beforeEach` and `afterEach` hooks,
+    returning the first error raised by any of them.
+
+    `afterEach` hooks always run, even if the test or a `beforeEach` hook failed.
+    Modules mocked using `require.mock` by the test or its hooks are unmocked afterwards.
+*/
+async fn run_test(
+    lua: &Lua,
+    registry: &Registry,
+    chain: &[usize],
+    test: &TestCase,
+) -> Option<String> {
+    let mark = overrides::override_mark(lua);
+    let mut error = None;
+    for &scope in chain {
+        if let Err(e) = call_hooks(&registry.scopes[scope].before_each).await {
+            error = Some(e);
+            break;
+        }
+    }
+    if error.is_none()
+        && let Err(e) = test.function.call_async::<()>(()).await
+    {
+        error = Some(error_message(&e));
+    }
+    for &scope in chain.iter().rev() {
+        if let Err(e) = call_hooks(&registry.scopes[scope].after_each).await {
+            error.get_or_insert(e);
+        }
+    }
+    if let Err(e) = overrides::restore_overrides(lua, mark) {
+        error.get_or_insert(format!("failed to unmock modules: {}", error_message(&e)));
+    }
+    error
+}
+
+/**
+    Runs all registered tests, optionally only those with names containing `filter`.
+
+    Tests run one at a time, in the order they were registered. `beforeAll` hooks
+    run before the first test in their scope, and `afterAll` hooks after the last,
+    so scopes without any tests left after filtering never run their hooks.
+
+    Modules mocked by `beforeAll` and `afterAll` hooks stay mocked until all tests have run.
+*/
+pub(crate) async fn run(lua: Lua, filter: Option<String>) -> LuaResult<LuaTable> {
+    let registry = registry::take(&lua);
+    let mark = overrides::override_mark(&lua);
+
+    let selected = registry
+        .tests
+        .iter()
+        .map(|test| (registry.full_name(test), test))
+        .filter(|(name, _)| filter.as_deref().is_none_or(|f| name.contains(f)))
+        .collect::<Vec<_>>();
+
+    let mut last_in_scope = HashMap::new();
+    for (index, (_, test)) in selected.iter().enumerate() {
+        if !test.skipped {
+            for scope in registry.chain(test.scope) {
+                last_in_scope.insert(scope, index);
+            }
+        }
+    }
+
+    let mut entered = HashSet::new();
+    let mut setup_errors = HashMap::<usize, String>::new();
+    let outcomes = lua.create_table()?;
+
+    for (index, (name, test)) in selected.into_iter().enumerate() {
+        if test.skipped {
+            outcomes.push(Outcome {
+                name,
+                status: Status::Skipped,
+                duration: Duration::ZERO,
+                error: None,
+            })?;
+            continue;
+        }
+
+        let started = Instant::now();
+        let chain = registry.chain(test.scope);
+
+        for &scope in &chain {
+            if entered.insert(scope)
+                && let Err(e) = call_hooks(&registry.scopes[scope].before_all).await
+            {
+                setup_errors.insert(scope, e);
+            }
+        }
+
+        let mut error = match chain.iter().find_map(|scope| setup_errors.get(scope)) {
+            Some(e) => Some(format!("beforeAll hook failed: {e}")),
+            None => run_test(&lua, &registry, &chain, test).await,
+        };
+
+        for &scope in chain.iter().rev() {
+            if last_in_scope.get(&scope) == Some(&index)
+                && let Err(e) = call_hooks(&registry.scopes[scope].after_all).await
+            {
+                error.get_or_insert(format!("afterAll hook failed: {e}"));
+            }
+        }
+
+        outcomes.push(Outcome {
+            name,
+            status: if error.is_some() {
+                Status::Failed
+            } else {
+                Status::Passed
+            },
+            duration: started.elapsed(),
+            error,
+        })?;
+    }
+
+    overrides::restore_overrides(&lua, mark)?;
+    Ok(outcomes)
+}
@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant as StdInstant};
+
+use mlua::prelude::*;
+
+/**
+    A point in time from a monotonic clock, which never goes backwards
+    and is not affected by changes to the system clock.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(StdInstant);
+
+impl Instant {
+    #[must_use]
+    pub fn now() -> Self {
+        Self(StdInstant::now())
+    }
+
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.0.elapsed()
+    }
+
+    /// Seconds from another instant to this one, negative if the other instant is later
+    #[must_use]
+    pub fn seconds_since(&self, other: &Self) -> f64 {
+        match self.0.checked_duration_since(other.0) {
+            Some(duration) => duration.as_secs_f64(),
+            None => -other.0.duration_since(self.0).as_secs_f64(),
+        }
+    }
+}
+
+/// Nanoseconds in a duration, saturating after roughly 584 years
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+impl LuaUserData for Instant {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("elapsed", |_, this, ()| Ok(this.elapsed().as_secs_f64()));
+        methods.add_method("elapsedNanos", |_, this, ()| Ok(nanos(this.elapsed())));
+
+        methods.add_meta_method(
+            LuaMetaMethod::Sub,
+            |_, this, other: LuaUserDataRef<Self>| Ok(this.seconds_since(&other)),
+        );
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaUserDataRef<Self>| {
+            Ok(*this == *other)
+        });
+        methods.add_meta_method(LuaMetaMethod::Lt, |_, this, other: LuaUserDataRef<Self>| {
+            Ok(*this < *other)
+        });
+        methods.add_meta_method(LuaMetaMethod::Le, |_, this, other: LuaUserDataRef<Self>| {
+            Ok(*this <= *other)
+        });
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(format!("Instant({:.6}s ago)", this.elapsed().as_secs_f64()))
+        });
+    }
+}
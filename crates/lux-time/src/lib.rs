@@ -0,0 +1,86 @@
+#![allow(clippy::cargo_common_metadata)]
+
+//! Monotonic clocks, measuring and precise sleeping for Lux
+
+use std::time::{Duration, Instant as StdInstant};
+
+use async_io::Timer;
+use lux_utils::TableBuilder;
+use mlua::prelude::*;
+
+mod instant;
+
+pub use self::instant::Instant;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    How much of a precise sleep is spent spinning instead of waiting for a timer,
+    which covers how late timers may wake up - Windows timers tick every ~15.6ms.
+*/
+const SPIN_THRESHOLD: Duration = if cfg!(windows) {
+    Duration::from_millis(16)
+} else {
+    Duration::from_millis(2)
+};
+
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+fn to_duration(secs: f64) -> LuaResult<Duration> {
+    Duration::try_from_secs_f64(secs).map_err(|_| {
+        LuaError::runtime(format!(
+            "Duration must be a finite number of seconds that is not negative, got {secs}"
+        ))
+    })
+}
+
+fn time_instant(_: &Lua, (): ()) -> LuaResult<Instant> {
+    Ok(Instant::now())
+}
+
+/// Calls a function, returning its first result and how many seconds the call took
+async fn time_measure(
+    _: Lua,
+    (func, args): (LuaFunction, LuaMultiValue),
+) -> LuaResult<(LuaValue, f64)> {
+    let start = StdInstant::now();
+    let result = func.call_async::<LuaValue>(args).await?;
+    Ok((result, start.elapsed().as_secs_f64()))
+}
+
+/**
+    Sleeps until a deadline by waiting for a timer until shortly before
+    it, then spinning for the rest, returning the seconds actually slept.
+
+    Other tasks can run while waiting for the timer, but not while spinning.
+*/
+async fn time_sleep_precise(_: Lua, secs: f64) -> LuaResult<f64> {
+    let duration = to_duration(secs)?;
+    let start = StdInstant::now();
+    let deadline = start + duration;
+    if duration > SPIN_THRESHOLD {
+        Timer::at(deadline - SPIN_THRESHOLD).await;
+    }
+    while StdInstant::now() < deadline {
+        std::hint::spin_loop();
+    }
+    Ok(start.elapsed().as_secs_f64())
+}
+
+/**
+    Creates the `time` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("instant", time_instant)?
+        .with_async_function("measure", time_measure)?
+        .with_async_function("sleepPrecise", time_sleep_precise)?
+        .build_readonly()
+}
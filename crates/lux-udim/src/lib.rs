@@ -1,8 +1,13 @@
 #![allow(clippy::cargo_common_metadata)]
 
 //! UDim, UDim2, Rect, NumberRange types for Lux
+//!
+//! Also provides list and grid layout helpers that distribute children
+//! within a parent Rect, shared by anything that needs flexbox-lite layout.
 
 use lux_utils::TableBuilder;
+use lux_utils::packed::{read_f64s, write_f64s};
+use lux_vector::Vector2;
 use mlua::prelude::*;
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
@@ -28,6 +33,12 @@ impl UDim {
     pub const fn new(scale: f64, offset: f64) -> Self {
         Self { scale, offset }
     }
+    /// Resolves against a parent length: `parent * scale + offset`
+    #[inline]
+    #[must_use]
+    pub fn resolve(&self, parent: f64) -> f64 {
+        parent.mul_add(self.scale, self.offset)
+    }
 }
 
 impl LuaUserData for UDim {
@@ -86,6 +97,9 @@ impl LuaUserData for UDim2 {
                 t.x.scale, t.x.offset, t.y.scale, t.y.offset
             ))
         });
+        m.add_method("WriteTo", |_, t, (b, o): (mlua::Buffer, usize)| {
+            write_f64s(&b, o, &[t.x.scale, t.x.offset, t.y.scale, t.y.offset])
+        });
         m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
             Ok(*t == *o)
         });
@@ -123,12 +137,24 @@ impl Rect {
     pub fn height(&self) -> f64 {
         self.max_y - self.min_y
     }
+    /// Creates a Rect from a top-left corner and a size
+    #[inline]
+    #[must_use]
+    pub fn from_position_size(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self::new(x, y, x + width, y + height)
+    }
 }
 
 impl LuaUserData for Rect {
     fn add_fields<F: LuaUserDataFields<Self>>(f: &mut F) {
         f.add_field_method_get("Width", |_, t| Ok(t.width()));
         f.add_field_method_get("Height", |_, t| Ok(t.height()));
+        f.add_field_method_get("Min", |lua, t| {
+            lua.create_userdata(Vector2::new(t.min_x, t.min_y))
+        });
+        f.add_field_method_get("Max", |lua, t| {
+            lua.create_userdata(Vector2::new(t.max_x, t.max_y))
+        });
     }
     fn add_methods<M: LuaUserDataMethods<Self>>(m: &mut M) {
         m.add_meta_method(LuaMetaMethod::ToString, |_, t, ()| {
@@ -137,6 +163,12 @@ impl LuaUserData for Rect {
                 t.min_x, t.min_y, t.max_x, t.max_y
             ))
         });
+        m.add_method("WriteTo", |_, t, (b, o): (mlua::Buffer, usize)| {
+            write_f64s(&b, o, &[t.min_x, t.min_y, t.max_x, t.max_y])
+        });
+        m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
+            Ok(*t == *o)
+        });
     }
 }
 
@@ -173,6 +205,265 @@ impl LuaUserData for NumberRange {
     }
 }
 
+// ============================================================================
+// Layout
+// ============================================================================
+
+/// Matches the values of `Enum.SortOrder`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    LayoutOrder,
+    Name,
+}
+
+impl SortOrder {
+    #[inline]
+    #[must_use]
+    pub const fn from_enum_value(value: i64) -> Option<Self> {
+        match value {
+            0 => Some(Self::LayoutOrder),
+            1 => Some(Self::Name),
+            _ => None,
+        }
+    }
+}
+
+/// Matches the values of `Enum.FillDirection`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillDirection {
+    Horizontal,
+    #[default]
+    Vertical,
+}
+
+impl FillDirection {
+    #[inline]
+    #[must_use]
+    pub const fn from_enum_value(value: i64) -> Option<Self> {
+        match value {
+            0 => Some(Self::Horizontal),
+            1 => Some(Self::Vertical),
+            _ => None,
+        }
+    }
+}
+
+/// A child to be placed by a layout
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LayoutItem {
+    pub size: UDim2,
+    pub name: String,
+    pub layout_order: i64,
+}
+
+/// Returns child indices in placement order - sorting is stable, so ties keep input order
+fn placement_order(items: &[LayoutItem], sort: SortOrder) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    match sort {
+        SortOrder::LayoutOrder => order.sort_by_key(|&i| items[i].layout_order),
+        SortOrder::Name => order.sort_by(|&a, &b| items[a].name.cmp(&items[b].name)),
+    }
+    order
+}
+
+/**
+    Stacks children one after another along `direction`, like a `UIListLayout`.
+
+    Child sizes are resolved against the parent, `padding` is resolved against the
+    parent length along the fill direction. Rects are returned in input order.
+*/
+#[must_use]
+pub fn list_layout(
+    parent: Rect,
+    items: &[LayoutItem],
+    direction: FillDirection,
+    padding: UDim,
+    sort: SortOrder,
+) -> Vec<Rect> {
+    let (pw, ph) = (parent.width(), parent.height());
+    let gap = match direction {
+        FillDirection::Horizontal => padding.resolve(pw),
+        FillDirection::Vertical => padding.resolve(ph),
+    };
+
+    let mut rects = vec![Rect::default(); items.len()];
+    let mut cursor = 0.0;
+    for i in placement_order(items, sort) {
+        let w = items[i].size.x.resolve(pw);
+        let h = items[i].size.y.resolve(ph);
+        rects[i] = match direction {
+            FillDirection::Horizontal => {
+                Rect::from_position_size(parent.min_x + cursor, parent.min_y, w, h)
+            }
+            FillDirection::Vertical => {
+                Rect::from_position_size(parent.min_x, parent.min_y + cursor, w, h)
+            }
+        };
+        cursor += gap
+            + match direction {
+                FillDirection::Horizontal => w,
+                FillDirection::Vertical => h,
+            };
+    }
+    rects
+}
+
+/**
+    Places children in equally sized cells, like a `UIGridLayout`.
+
+    Cells fill along `direction` and wrap when the next cell would overflow the parent.
+    Rects are returned in input order.
+*/
+#[must_use]
+pub fn grid_layout(
+    parent: Rect,
+    items: &[LayoutItem],
+    cell_size: UDim2,
+    cell_padding: UDim2,
+    direction: FillDirection,
+    sort: SortOrder,
+) -> Vec<Rect> {
+    let (pw, ph) = (parent.width(), parent.height());
+    let (cw, ch) = (cell_size.x.resolve(pw), cell_size.y.resolve(ph));
+    let (gx, gy) = (cell_padding.x.resolve(pw), cell_padding.y.resolve(ph));
+
+    // Always fit at least one cell per line, even if it overflows
+    let per_line = |cell: f64, gap: f64, avail: f64| -> usize {
+        if cell + gap <= 0.0 {
+            return usize::MAX;
+        }
+        (((avail + gap) / (cell + gap)).floor() as usize).max(1)
+    };
+    let per_line = match direction {
+        FillDirection::Horizontal => per_line(cw, gx, pw),
+        FillDirection::Vertical => per_line(ch, gy, ph),
+    };
+
+    let mut rects = vec![Rect::default(); items.len()];
+    for (slot, i) in placement_order(items, sort).into_iter().enumerate() {
+        let (major, minor) = (slot % per_line, slot / per_line);
+        let (col, row) = match direction {
+            FillDirection::Horizontal => (major, minor),
+            FillDirection::Vertical => (minor, major),
+        };
+        rects[i] = Rect::from_position_size(
+            parent.min_x + col as f64 * (cw + gx),
+            parent.min_y + row as f64 * (ch + gy),
+            cw,
+            ch,
+        );
+    }
+    rects
+}
+
+impl FromLua for LayoutItem {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(Self {
+                size: *ud.borrow::<UDim2>()?,
+                ..Self::default()
+            }),
+            LuaValue::Table(t) => Ok(Self {
+                size: t
+                    .get::<Option<LuaUserDataRef<UDim2>>>("Size")?
+                    .map(|s| *s)
+                    .unwrap_or_default(),
+                name: t.get::<Option<String>>("Name")?.unwrap_or_default(),
+                layout_order: t.get::<Option<i64>>("LayoutOrder")?.unwrap_or_default(),
+            }),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: "LayoutItem".to_string(),
+                message: Some("expected a UDim2 or a table with Size, Name and LayoutOrder".into()),
+            }),
+        }
+    }
+}
+
+fn layout_option<T>(
+    options: Option<&LuaTable>,
+    key: &str,
+    from_enum_value: fn(i64) -> Option<T>,
+) -> LuaResult<Option<T>> {
+    let Some(value) = options
+        .map(|o| o.get::<Option<i64>>(key))
+        .transpose()?
+        .flatten()
+    else {
+        return Ok(None);
+    };
+    from_enum_value(value)
+        .map(Some)
+        .ok_or_else(|| LuaError::external(format!("invalid {key} value '{value}'")))
+}
+
+fn rects_to_lua(lua: &Lua, rects: Vec<Rect>) -> LuaResult<LuaTable> {
+    let t = lua.create_table_with_capacity(rects.len(), 0)?;
+    for rect in rects {
+        t.push(lua.create_userdata(rect)?)?;
+    }
+    Ok(t)
+}
+
+fn lua_list_layout(
+    lua: &Lua,
+    (parent, items, options): (LuaUserDataRef<Rect>, Vec<LayoutItem>, Option<LuaTable>),
+) -> LuaResult<LuaTable> {
+    let opts = options.as_ref();
+    let padding = match opts.map(|o| o.get::<LuaValue>("Padding")).transpose()? {
+        Some(LuaValue::UserData(ud)) => *ud.borrow::<UDim>()?,
+        Some(LuaValue::Integer(n)) => UDim::new(0.0, n as f64),
+        Some(LuaValue::Number(n)) => UDim::new(0.0, n),
+        Some(LuaValue::Nil) | None => UDim::default(),
+        Some(other) => {
+            return Err(LuaError::external(format!(
+                "expected Padding to be a UDim or number, got '{}'",
+                other.type_name()
+            )));
+        }
+    };
+    let direction = layout_option(opts, "FillDirection", FillDirection::from_enum_value)?;
+    let sort = layout_option(opts, "SortOrder", SortOrder::from_enum_value)?;
+    let rects = list_layout(
+        *parent,
+        &items,
+        direction.unwrap_or_default(),
+        padding,
+        sort.unwrap_or_default(),
+    );
+    rects_to_lua(lua, rects)
+}
+
+fn lua_grid_layout(
+    lua: &Lua,
+    (parent, items, options): (LuaUserDataRef<Rect>, Vec<LayoutItem>, Option<LuaTable>),
+) -> LuaResult<LuaTable> {
+    let opts = options.as_ref();
+    let udim2_option = |key: &str, default: UDim2| -> LuaResult<UDim2> {
+        Ok(opts
+            .map(|o| o.get::<Option<LuaUserDataRef<UDim2>>>(key))
+            .transpose()?
+            .flatten()
+            .map_or(default, |u| *u))
+    };
+    let cell_size = udim2_option("CellSize", UDim2::from_offset(100.0, 100.0))?;
+    let cell_padding = udim2_option("CellPadding", UDim2::from_offset(5.0, 5.0))?;
+    // NOTE: Grids fill horizontally by default, unlike lists
+    let direction = layout_option(opts, "FillDirection", FillDirection::from_enum_value)?
+        .unwrap_or(FillDirection::Horizontal);
+    let sort = layout_option(opts, "SortOrder", SortOrder::from_enum_value)?;
+    let rects = grid_layout(
+        *parent,
+        &items,
+        cell_size,
+        cell_padding,
+        direction,
+        sort.unwrap_or_default(),
+    );
+    rects_to_lua(lua, rects)
+}
+
 // ============================================================================
 // Constructors
 // ============================================================================
@@ -197,6 +488,10 @@ pub fn create_udim2(lua: Lua) -> LuaResult<LuaValue> {
         .with_function("fromOffset", |lua, (xo, yo): (f64, f64)| {
             lua.create_userdata(UDim2::from_offset(xo, yo))
         })?
+        .with_function("ReadFrom", |lua, (b, o): (mlua::Buffer, usize)| {
+            let [xs, xo, ys, yo] = read_f64s(&b, o)?;
+            lua.create_userdata(UDim2::new(xs, xo, ys, yo))
+        })?
         .build_readonly()
         .map(LuaValue::Table)
 }
@@ -209,6 +504,12 @@ pub fn create_rect(lua: Lua) -> LuaResult<LuaValue> {
                 lua.create_userdata(Rect::new(min_x, min_y, max_x, max_y))
             },
         )?
+        .with_function("ReadFrom", |lua, (b, o): (mlua::Buffer, usize)| {
+            let [min_x, min_y, max_x, max_y] = read_f64s(&b, o)?;
+            lua.create_userdata(Rect::new(min_x, min_y, max_x, max_y))
+        })?
+        .with_function("listLayout", lua_list_layout)?
+        .with_function("gridLayout", lua_grid_layout)?
         .build_readonly()
         .map(LuaValue::Table)
 }
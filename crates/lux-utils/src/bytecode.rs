@@ -0,0 +1,205 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process,
+};
+
+use async_fs as fs;
+use futures_lite::StreamExt;
+use mlua::{Compiler, prelude::*};
+
+const ENTRY_EXTENSION: &str = "luauc";
+const KEY_LEN: usize = blake3::OUT_LEN;
+
+/**
+    Returns the compiler used for every chunk loaded by the Lux runtime.
+
+    Optimizations are aggressive, and type information is
+    generated for native code generation when JIT is enabled.
+*/
+#[must_use]
+pub fn compiler() -> Compiler {
+    Compiler::new()
+        .set_optimization_level(2) // Aggressive: inlining, loop unrolling
+        .set_type_info_level(1) // Generate type info for native code generation
+}
+
+/**
+    A cache of compiled Luau bytecode, stored on disk.
+
+    Each source file gets a single entry in the cache, keyed by its path, which
+    stores a hash of the source it was compiled from along with the options used
+    to compile it. Entries that no longer match their file - because it changed,
+    or because the runtime or compiler options changed - are compiled again and
+    replaced, so the cache never has to be cleared manually.
+*/
+#[derive(Debug, Clone)]
+pub struct BytecodeCache {
+    dir: PathBuf,
+    compiler: Compiler,
+    options: String,
+}
+
+impl BytecodeCache {
+    /**
+        Creates a new bytecode cache stored in the given directory.
+
+        The given version should identify the runtime that is using the cache, since
+        the bytecode format may change between versions of the Luau compiler.
+    */
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>, version: &str) -> Self {
+        let compiler = compiler();
+        let options = format!("{version}\n{compiler:?}");
+        Self {
+            dir: dir.into(),
+            compiler,
+            options,
+        }
+    }
+
+    /**
+        Returns the directory this cache is stored in.
+    */
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /**
+        Returns bytecode for the given source file, from the cache if
+        possible, otherwise compiling it and storing it in the cache.
+
+        Falls back to returning the source as-is if it fails to compile, so that
+        loading it reports syntax errors the same way as if there was no cache.
+    */
+    pub async fn load(&self, path: &Path, source: Vec<u8>) -> Vec<u8> {
+        let entry_path = self.entry_path(path);
+        let key = self.key(&source);
+        if let Some(bytecode) = read_entry(&entry_path, &key).await {
+            return bytecode;
+        }
+
+        let Ok(bytecode) = self.compiler.compile(&source) else {
+            return source;
+        };
+
+        // NOTE: Failing to write the entry is not an error, the cache may be in a
+        // read-only directory, and the next run will simply compile the file again
+        write_entry(&entry_path, &key, &bytecode).await.ok();
+        bytecode
+    }
+
+    /**
+        Compiles the given source file and stores it in the cache, unless it
+        is already cached. Returns `true` if the file was already cached.
+
+        # Errors
+
+        Errors if the source fails to compile, or if the cache entry could not be written.
+    */
+    pub async fn precompile(&self, path: &Path, source: &[u8]) -> LuaResult<bool> {
+        let entry_path = self.entry_path(path);
+        let key = self.key(source);
+        if read_entry(&entry_path, &key).await.is_some() {
+            return Ok(true);
+        }
+
+        let bytecode = self.compiler.compile(source)?;
+        write_entry(&entry_path, &key, &bytecode)
+            .await
+            .into_lua_err()
+            .with_context(|_| {
+                format!("failed to write cache entry to '{}'", entry_path.display())
+            })?;
+        Ok(false)
+    }
+
+    fn key(&self, source: &[u8]) -> blake3::Hash {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(self.options.as_bytes());
+        hasher.update(source);
+        hasher.finalize()
+    }
+
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let path = dunce::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let name = blake3::hash(path.as_os_str().as_encoded_bytes()).to_hex();
+        self.dir
+            .join("bytecode")
+            .join(format!("{name}.{ENTRY_EXTENSION}"))
+    }
+
+    /**
+        Gathers statistics about the bytecode cache in the given directory.
+
+        # Errors
+
+        Errors if the cache directory exists but could not be read.
+    */
+    pub async fn stats(dir: impl AsRef<Path>) -> io::Result<BytecodeCacheStats> {
+        let mut stats = BytecodeCacheStats::default();
+
+        let mut entries = match fs::read_dir(dir.as_ref().join("bytecode")).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(stats),
+            Err(e) => return Err(e),
+        };
+        while let Some(entry) = entries.try_next().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == ENTRY_EXTENSION) {
+                stats.entries += 1;
+                stats.size += entry.metadata().await?.len();
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/**
+    Statistics about a bytecode cache, see [`BytecodeCache::stats`].
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BytecodeCacheStats {
+    /// The number of cached files
+    pub entries: usize,
+    /// The total size of all cached files, in bytes
+    pub size: u64,
+}
+
+/**
+    Reads the bytecode stored in a cache entry, if the
+    entry exists and was stored using the given key.
+*/
+async fn read_entry(path: &Path, key: &blake3::Hash) -> Option<Vec<u8>> {
+    let mut entry = fs::read(path).await.ok()?;
+    if entry.len() > KEY_LEN && entry[..KEY_LEN] == key.as_bytes()[..] {
+        entry.drain(..KEY_LEN);
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+/**
+    Writes a cache entry by writing to a temporary file next to it and renaming it,
+    so that other processes using the same cache never read a partially written entry.
+*/
+async fn write_entry(path: &Path, key: &blake3::Hash, bytecode: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+
+    let mut contents = Vec::with_capacity(KEY_LEN + bytecode.len());
+    contents.extend_from_slice(key.as_bytes());
+    contents.extend_from_slice(bytecode);
+
+    let temp = path.with_extension(format!("{}.tmp", process::id()));
+    fs::write(&temp, contents).await?;
+    if let Err(e) = fs::rename(&temp, path).await {
+        fs::remove_file(&temp).await.ok();
+        return Err(e);
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use std::collections::HashSet;
+
+use mlua::prelude::*;
+
+fn as_number(value: &LuaValue) -> Option<f64> {
+    match value {
+        LuaValue::Integer(i) => Some(*i as f64),
+        LuaValue::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+/**
+    Checks if two values are deeply equal.
+
+    Tables are equal if they have equal values for the same keys, and userdata
+    are compared using their `__eq` metamethod, so that two separately created
+    `Vector3`s or `Color3`s with the same components are considered equal.
+
+    # Errors
+
+    Errors if a `__eq` metamethod errors.
+*/
+pub fn deep_equals(a: &LuaValue, b: &LuaValue) -> LuaResult<bool> {
+    deep_equals_inner(a, b, &mut HashSet::new())
+}
+
+#[allow(clippy::float_cmp)]
+fn deep_equals_inner(
+    a: &LuaValue,
+    b: &LuaValue,
+    visited: &mut HashSet<(usize, usize)>,
+) -> LuaResult<bool> {
+    match (a, b) {
+        (LuaValue::Table(ta), LuaValue::Table(tb)) => {
+            // NOTE: Tables that are already being compared further up are assumed
+            // to be equal, any difference will be found by that outer comparison
+            if ta == tb || !visited.insert((ta.to_pointer() as usize, tb.to_pointer() as usize)) {
+                return Ok(true);
+            }
+            let mut count_a = 0;
+            for pair in ta.pairs::<LuaValue, LuaValue>() {
+                let (key, value) = pair?;
+                let other = tb.raw_get::<LuaValue>(key)?;
+                if !deep_equals_inner(&value, &other, visited)? {
+                    return Ok(false);
+                }
+                count_a += 1;
+            }
+            let count_b = tb.pairs::<LuaValue, LuaValue>().count();
+            Ok(count_a == count_b)
+        }
+        (LuaValue::UserData(_), LuaValue::UserData(_)) => a.equals(b),
+        _ => match (as_number(a), as_number(b)) {
+            (Some(na), Some(nb)) => Ok(na == nb),
+            _ => Ok(a == b),
+        },
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+use mlua::prelude::*;
+
+use crate::fmt::StackTrace;
+
+/**
+    Errors raised by the Lux runtime itself, rather than by scripts or libraries.
+
+    These are raised as external Lua errors, meaning scripts may catch them
+    using `pcall`, and embedders may find them using [`LuxError::from_lua_error`].
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LuxError {
+    /// Script code ran past one of its execution limits.
+    Timeout(String),
+    /// A library, module or other resource could not be loaded.
+    Load(String),
+    /// Some input, such as a date or a declaration, could not be parsed.
+    Parse(String),
+    /// A value was valid for its type, but not for the operation using it.
+    Value(String),
+}
+
+impl LuxError {
+    /**
+        Finds a `LuxError` within a Lua error, looking through
+        any callback errors and context that may have been added.
+    */
+    #[must_use]
+    pub fn from_lua_error(error: &LuaError) -> Option<&Self> {
+        match error {
+            LuaError::ExternalError(e) => e.downcast_ref::<Self>(),
+            LuaError::CallbackError { cause, .. } | LuaError::WithContext { cause, .. } => {
+                Self::from_lua_error(cause)
+            }
+            _ => None,
+        }
+    }
+
+    /**
+        Returns the kind of error, as exposed to Lua through `LuxError.kind`.
+    */
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Timeout(_) => "Timeout",
+            Self::Load(_) => "Load",
+            Self::Parse(_) => "Parse",
+            Self::Value(_) => "Value",
+        }
+    }
+
+    /**
+        Returns the error message, without the kind of error.
+    */
+    #[must_use]
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Timeout(message)
+            | Self::Load(message)
+            | Self::Parse(message)
+            | Self::Value(message) => message,
+        }
+    }
+
+    /**
+        Returns `true` if this is a [`LuxError::Timeout`].
+    */
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Self::Timeout(_))
+    }
+}
+
+impl Display for LuxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}: {}", self.kind(), self.message())
+    }
+}
+
+impl Error for LuxError {}
+
+impl From<LuxError> for LuaError {
+    fn from(value: LuxError) -> Self {
+        LuaError::external(value)
+    }
+}
+
+/**
+    A [`LuxError`] caught in Lua, along with where it was raised from.
+
+    Exposed to Lua as a `LuxError` userdata with `message`, `kind`,
+    `traceback` and `source` fields, in place of a plain error string.
+*/
+#[derive(Debug, Clone)]
+pub struct LuxErrorObject {
+    error: LuxError,
+    traceback: Option<String>,
+    source: Option<String>,
+}
+
+impl LuxErrorObject {
+    /**
+        Creates an error object from a Lua error, if it contains a [`LuxError`].
+
+        The traceback and source are taken from the outermost callback error, which is
+        where the error crossed from Rust into Lua, if the error has crossed at all.
+    */
+    #[must_use]
+    pub fn from_lua_error(error: &LuaError) -> Option<Self> {
+        let inner = LuxError::from_lua_error(error)?.clone();
+        let traceback = find_traceback(error).map(ToString::to_string);
+        let source = traceback.as_deref().and_then(source_location);
+        Some(Self {
+            error: inner,
+            traceback,
+            source,
+        })
+    }
+
+    /**
+        Returns the inner [`LuxError`].
+    */
+    #[must_use]
+    pub fn error(&self) -> &LuxError {
+        &self.error
+    }
+}
+
+fn find_traceback(error: &LuaError) -> Option<&str> {
+    match error {
+        LuaError::CallbackError { traceback, .. } => Some(traceback),
+        LuaError::WithContext { cause, .. } => find_traceback(cause),
+        _ => None,
+    }
+}
+
+/// Finds the `path:line` of the first Lua function in a traceback
+fn source_location(traceback: &str) -> Option<String> {
+    let trace = StackTrace::from_str(traceback).ok()?;
+    let line = trace.lines().iter().find(|line| line.source().is_lua())?;
+    match (line.path(), line.line_number()) {
+        (Some(path), Some(number)) => Some(format!("{path}:{number}")),
+        (Some(path), None) => Some(path.to_string()),
+        _ => None,
+    }
+}
+
+impl LuaUserData for LuxErrorObject {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "LuxError");
+        fields.add_field_method_get("message", |_, this| Ok(this.error.message().to_string()));
+        fields.add_field_method_get("kind", |_, this| Ok(this.error.kind()));
+        fields.add_field_method_get("traceback", |_, this| Ok(this.traceback.clone()));
+        fields.add_field_method_get("source", |_, this| Ok(this.source.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| {
+            Ok(this.error.to_string())
+        });
+    }
+}
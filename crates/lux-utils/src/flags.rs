@@ -0,0 +1,179 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::Write,
+    str::FromStr,
+};
+
+use mlua::prelude::*;
+
+use crate::fmt::Label;
+
+/**
+    A behavior-changing improvement that scripts may opt into before it becomes the default.
+
+    Each flag goes through three stages:
+
+    1. Off by default - scripts that rely on the old behavior get a one-time
+       deprecation warning, which they can silence by explicitly disabling the flag
+    2. On by default - the flag may still be disabled to keep the old behavior
+    3. Removed - the new behavior is the only behavior
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FeatureFlag {
+    /// `Enum` items are `EnumItem` userdata instead of plain numbers
+    NewEnumItems,
+}
+
+impl FeatureFlag {
+    pub const ALL: &'static [Self] = &[Self::NewEnumItems];
+
+    /**
+        Returns the name of the flag, as used by `lux.flags` and the `LUX_FLAGS` variable.
+    */
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::NewEnumItems => "new-enum-items",
+        }
+    }
+
+    /**
+        Returns a short description of what enabling the flag changes.
+    */
+    #[must_use]
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::NewEnumItems => {
+                "Enum items are EnumItem userdata with Name, Value and EnumType properties"
+            }
+        }
+    }
+
+    /**
+        Returns whether the flag is enabled when it has not been explicitly set.
+    */
+    #[must_use]
+    pub const fn default_enabled(self) -> bool {
+        match self {
+            Self::NewEnumItems => false,
+        }
+    }
+}
+
+impl fmt::Display for FeatureFlag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for FeatureFlag {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|flag| flag.name() == name)
+            .ok_or_else(|| {
+                let valid = Self::ALL
+                    .iter()
+                    .map(|flag| format!("'{flag}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("Unknown feature flag '{name}', expected one of {valid}")
+            })
+    }
+}
+
+/**
+    The feature flags set for a Luau VM, along with which deprecations have been warned about.
+
+    Flags that have not been explicitly set use their [`FeatureFlag::default_enabled`] value.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    set: BTreeMap<FeatureFlag, bool>,
+    warned: BTreeSet<FeatureFlag>,
+}
+
+impl FeatureFlags {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+        Explicitly enables or disables the given flag.
+    */
+    pub fn set(&mut self, flag: FeatureFlag, enabled: bool) {
+        self.set.insert(flag, enabled);
+    }
+
+    /**
+        Returns the value the given flag has been explicitly set to, if any.
+    */
+    #[must_use]
+    pub fn get(&self, flag: FeatureFlag) -> Option<bool> {
+        self.set.get(&flag).copied()
+    }
+
+    /**
+        Returns the value the given flag has been explicitly set to for the Luau VM, if any.
+    */
+    #[must_use]
+    pub fn get_in(lua: &Lua, flag: FeatureFlag) -> Option<bool> {
+        lua.app_data_ref::<Self>().and_then(|flags| flags.get(flag))
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self, flag: FeatureFlag) -> bool {
+        self.get(flag).unwrap_or_else(|| flag.default_enabled())
+    }
+
+    /**
+        Returns whether the given flag is enabled for the Luau VM.
+
+        VMs without any stored flags - such as ones not created by
+        the Lux runtime - use the default value for every flag.
+    */
+    #[must_use]
+    pub fn is_enabled_in(lua: &Lua, flag: FeatureFlag) -> bool {
+        lua.app_data_ref::<Self>()
+            .map_or_else(|| flag.default_enabled(), |flags| flags.is_enabled(flag))
+    }
+
+    /**
+        Warns that behavior depending on the given flag is going to change.
+
+        The warning is only printed once per Luau VM, and never if the flag has been
+        explicitly set, since the script has then already chosen which behavior it wants.
+    */
+    pub fn warn_deprecated(lua: &Lua, flag: FeatureFlag, message: &str) {
+        {
+            let Some(mut flags) = lua.app_data_mut::<Self>() else {
+                return;
+            };
+            if flags.get(flag).is_some() || !flags.warned.insert(flag) {
+                return;
+            }
+        }
+        let formatted = format!(
+            "{} {message}\nUse lux.flags.enable(\"{flag}\") to opt into the new behavior early, \
+            or lux.flags.disable(\"{flag}\") to keep the current one and silence this warning\n",
+            Label::Warn
+        );
+        let mut stderr = std::io::stderr();
+        let _ = stderr.write_all(formatted.as_bytes());
+        let _ = stderr.flush();
+    }
+}
+
+impl FromIterator<(FeatureFlag, bool)> for FeatureFlags {
+    fn from_iter<T: IntoIterator<Item = (FeatureFlag, bool)>>(iter: T) -> Self {
+        Self {
+            set: iter.into_iter().collect(),
+            warned: BTreeSet::new(),
+        }
+    }
+}
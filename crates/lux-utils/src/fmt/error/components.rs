@@ -7,7 +7,7 @@ use std::{
 use console::style;
 use mlua::prelude::*;
 
-use super::StackTrace;
+use super::{SourceSnippet, StackTrace};
 
 static STYLED_STACK_BEGIN: LazyLock<String> = LazyLock::new(|| {
     format!(
@@ -40,6 +40,10 @@ const STACK_TRACE_INDENT: &str = "    ";
 
     ```plaintext
     Error message
+      --> path/to/file.luau:4
+       |
+     4 |     error("Error message")
+       |     ^^^^^^^^^^^^^^^^^^^^^^
     [Stack Begin]
         Stack trace line
         Stack trace line
@@ -51,6 +55,7 @@ const STACK_TRACE_INDENT: &str = "    ";
 pub struct ErrorComponents {
     messages: Vec<String>,
     trace: Option<StackTrace>,
+    snippet: Option<SourceSnippet>,
 }
 
 impl ErrorComponents {
@@ -70,6 +75,15 @@ impl ErrorComponents {
         self.trace.as_ref()
     }
 
+    /**
+        Returns the source code around where the error happened,
+        if the file it happened in could be found and read.
+    */
+    #[must_use]
+    pub fn snippet(&self) -> Option<&SourceSnippet> {
+        self.snippet.as_ref()
+    }
+
     /**
         Returns `true` if the error has a non-empty stack trace.
 
@@ -88,6 +102,9 @@ impl fmt::Display for ErrorComponents {
         for message in self.messages() {
             writeln!(f, "{message}")?;
         }
+        if let Some(snippet) = &self.snippet {
+            write!(f, "{snippet}")?;
+        }
         if self.has_trace() {
             let trace = self.trace.as_ref().expect("trace exists and is non-empty");
             writeln!(f, "{}", *STYLED_STACK_BEGIN)?;
@@ -174,13 +191,18 @@ impl From<LuaError> for ErrorComponents {
             }
         }
 
+        // Show the source code for the first line of the trace that has any, if we can read it
+        let first_lua_line = trace
+            .iter()
+            .flat_map(StackTrace::lines)
+            .find(|line| line.source().is_lua());
+        let snippet =
+            first_lua_line.and_then(|line| SourceSnippet::read(line.path()?, line.line_number()?));
+
         // Finally, we do some light postprocessing to remove duplicate
         // information, such as the location prefix in the error message
         if let Some(message) = messages.last_mut()
-            && let Some(line) = trace
-                .iter()
-                .flat_map(StackTrace::lines)
-                .find(|line| line.source().is_lua())
+            && let Some(line) = first_lua_line
         {
             if let Some(path) = line.path() {
                 let prefix = format!("[string \"{path}\"]:");
@@ -196,7 +218,11 @@ impl From<LuaError> for ErrorComponents {
             }
         }
 
-        ErrorComponents { messages, trace }
+        ErrorComponents {
+            messages,
+            trace,
+            snippet,
+        }
     }
 }
 
@@ -1,8 +1,10 @@
 mod components;
+mod snippet;
 mod stack_trace;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::components::ErrorComponents;
+pub use self::snippet::SourceSnippet;
 pub use self::stack_trace::{StackTrace, StackTraceLine, StackTraceSource};
@@ -0,0 +1,114 @@
+use std::{fmt, fs, path::PathBuf};
+
+use console::style;
+
+// NOTE: Paths in stack traces are module paths, which usually have
+// their file extension stripped, so we need to try to find the file
+const SOURCE_EXTENSIONS: &[&str] = &["", ".luau", ".lua", "/init.luau", "/init.lua"];
+
+/**
+    A few lines of source code around the line an error happened on.
+
+    Displayed with a line marker, in the following format:
+
+    ```plaintext
+      --> path/to/file.luau:4
+       |
+     3 | local function f()
+     4 |     error("boom")
+       |     ^^^^^^^^^^^^^
+     5 | end
+    ```
+*/
+#[derive(Debug, Clone)]
+pub struct SourceSnippet {
+    path: PathBuf,
+    line_number: usize,
+    lines: Vec<(usize, String)>,
+}
+
+impl SourceSnippet {
+    /**
+        Reads the lines around `line_number` from the file at the given module path.
+
+        Returns `None` if the file can not be found, or does not have the given line.
+    */
+    #[must_use]
+    pub fn read(module_path: &str, line_number: usize) -> Option<Self> {
+        let (path, contents) = SOURCE_EXTENSIONS.iter().find_map(|ext| {
+            let path = PathBuf::from(format!("{module_path}{ext}"));
+            let contents = fs::read_to_string(&path).ok()?;
+            Some((path, contents))
+        })?;
+
+        let first = line_number.checked_sub(2)?;
+        let lines = contents
+            .lines()
+            .enumerate()
+            .skip(first)
+            .take(3)
+            .map(|(index, line)| (index + 1, line.trim_end().to_string()))
+            .collect::<Vec<_>>();
+        if !lines.iter().any(|(number, _)| *number == line_number) {
+            return None;
+        }
+
+        Some(Self {
+            path,
+            line_number,
+            lines,
+        })
+    }
+
+    /**
+        Returns the path to the file the snippet was read from.
+    */
+    #[must_use]
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /**
+        Returns the line number the error happened on.
+    */
+    #[must_use]
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+}
+
+impl fmt::Display for SourceSnippet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .lines
+            .iter()
+            .map(|(number, _)| number.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let gutter = style(format!("{:width$} |", "")).blue();
+
+        writeln!(
+            f,
+            "{:width$}{} {}:{}",
+            "",
+            style("-->").blue(),
+            self.path.display(),
+            self.line_number
+        )?;
+        writeln!(f, "{gutter}")?;
+        for (number, line) in &self.lines {
+            writeln!(f, "{} {line}", style(format!("{number:>width$} |")).blue())?;
+            if *number == self.line_number {
+                let indent = line.len() - line.trim_start().len();
+                let marker = "^".repeat(line.trim().chars().count().max(1));
+                writeln!(
+                    f,
+                    "{gutter} {}{}",
+                    &line[..indent],
+                    style(marker).red().bold()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
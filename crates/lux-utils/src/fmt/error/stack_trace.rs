@@ -26,6 +26,15 @@ fn parse_function_name(s: &str) -> Option<&str> {
         .and_then(|s| s.strip_suffix('\''))
 }
 
+// NOTE: Some builtins are wrapped in Luau chunks named "[C]" to hide them from
+// stack traces, and lines for those have line numbers, such as "[C]:4: in ?"
+fn strip_c_prefix(s: &str) -> Option<&str> {
+    s.strip_prefix("[C]: ").or_else(|| {
+        let (number, after) = s.strip_prefix("[C]:")?.split_once(": ")?;
+        number.parse::<usize>().ok().map(|_| after)
+    })
+}
+
 fn parse_line_number(s: &str) -> (Option<usize>, &str) {
     match s.split_once(':') {
         Some((before, after)) => (before.parse::<usize>().ok(), after),
@@ -125,7 +134,7 @@ impl StackTraceLine {
 impl FromStr for StackTraceLine {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some(after) = s.strip_prefix("[C]: ") {
+        if let Some(after) = strip_c_prefix(s) {
             let function_name = parse_function_name(after).map(ToString::to_string);
 
             Ok(Self {
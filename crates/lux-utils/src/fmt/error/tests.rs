@@ -146,3 +146,49 @@ mod general {
         assert_eq!(c_stack_lines.len(), 1); // Just the "error" call
     }
 }
+
+// Tests for source snippets of the line an error happened on
+mod snippet {
+    use std::{env::temp_dir, fs};
+
+    use crate::fmt::{SourceSnippet, StackTraceLine};
+
+    #[test]
+    fn marks_error_line() {
+        let path = temp_dir().join("lux_snippet_marks_error_line.luau");
+        fs::write(&path, "local a = 1\n    error(\"boom\")\nlocal b = 2\n").unwrap();
+
+        let module_path = path.with_extension("");
+        let snippet = SourceSnippet::read(module_path.to_str().unwrap(), 2).unwrap();
+        let formatted = console::strip_ansi_codes(&snippet.to_string()).to_string();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(snippet.path(), &path);
+        assert!(formatted.contains("1 | local a = 1"));
+        assert!(formatted.contains("2 |     error(\"boom\")"));
+        assert!(formatted.contains("  |     ^^^^^^^^^^^^^\n"));
+        assert!(formatted.contains("3 | local b = 2"));
+    }
+
+    #[test]
+    fn missing_file_or_line() {
+        assert!(SourceSnippet::read("definitely/not/a/real/file", 1).is_none());
+
+        let path = temp_dir().join("lux_snippet_missing_line.luau");
+        fs::write(&path, "return nil\n").unwrap();
+        let snippet = SourceSnippet::read(path.to_str().unwrap(), 5);
+        fs::remove_file(&path).unwrap();
+
+        assert!(snippet.is_none());
+    }
+
+    #[test]
+    fn wrapped_builtins_are_c() {
+        let line = "[C]:4: in function 'pcall'"
+            .parse::<StackTraceLine>()
+            .unwrap();
+
+        assert!(line.source().is_c());
+        assert_eq!(line.function_name(), Some("pcall"));
+    }
+}
@@ -2,6 +2,8 @@ mod error;
 mod label;
 mod value;
 
-pub use self::error::{ErrorComponents, StackTrace, StackTraceLine, StackTraceSource};
+pub use self::error::{
+    ErrorComponents, SourceSnippet, StackTrace, StackTraceLine, StackTraceSource,
+};
 pub use self::label::Label;
 pub use self::value::{ValueFormatConfig, pretty_format_multi_value, pretty_format_value};
@@ -1,3 +1,5 @@
+use std::io::{Read, Seek, SeekFrom};
+
 use mlua::prelude::*;
 
 use crate::fmt::ErrorComponents;
@@ -10,6 +12,9 @@ use super::{
     style::{COLOR_CYAN, COLOR_GREEN, COLOR_MAGENTA, COLOR_YELLOW},
 };
 
+/// The number of bytes shown when formatting a buffer
+const BUFFER_PREVIEW_LEN: usize = 16;
+
 const STRING_REPLACEMENTS: &[(&str, &str)] =
     &[("\"", r#"\""#), ("\t", r"\t"), ("\r", r"\r"), ("\n", r"\n")];
 
@@ -57,7 +62,7 @@ pub(crate) fn format_value_styled(value: &LuaValue, prefer_plain: bool) -> Strin
             })
             .to_string(),
         LuaValue::Other(_) => COLOR_MAGENTA.apply_to("<unknown>").to_string(),
-        LuaValue::Buffer(_) => COLOR_MAGENTA.apply_to("<buffer>").to_string(),
+        LuaValue::Buffer(b) => COLOR_MAGENTA.apply_to(format_buffer_preview(b)).to_string(),
         LuaValue::Vector(_) => COLOR_MAGENTA.apply_to("<vector>").to_string(),
         LuaValue::Thread(_) => COLOR_MAGENTA.apply_to("<thread>").to_string(),
         LuaValue::Function(_) => COLOR_MAGENTA.apply_to("<function>").to_string(),
@@ -87,6 +92,32 @@ pub(crate) fn format_value_styled(value: &LuaValue, prefer_plain: bool) -> Strin
     }
 }
 
+/**
+    Formats a buffer as its length along with a hex
+    preview of its first [`BUFFER_PREVIEW_LEN`] bytes.
+*/
+fn format_buffer_preview(buffer: &mlua::Buffer) -> String {
+    let len = buffer.len();
+    if len == 0 {
+        return String::from("<buffer(0 bytes)>");
+    }
+
+    let mut preview = vec![0u8; len.min(BUFFER_PREVIEW_LEN)];
+    let mut cursor = buffer.clone().cursor();
+    cursor
+        .seek(SeekFrom::Start(0))
+        .and_then(|_| cursor.read_exact(&mut preview))
+        .expect("preview is never longer than the buffer");
+
+    let hex = preview
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let ellipsis = if len > BUFFER_PREVIEW_LEN { " ..." } else { "" };
+    format!("<buffer({len} bytes) {hex}{ellipsis}>")
+}
+
 fn format_typename_and_tostringed(
     fallback: &'static str,
     typename: Option<String>,
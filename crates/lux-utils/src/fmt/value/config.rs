@@ -4,7 +4,9 @@
 #[derive(Debug, Clone, Copy)]
 pub struct ValueFormatConfig {
     pub(super) max_depth: usize,
+    pub(super) indent_width: usize,
     pub(super) colors_enabled: bool,
+    pub(super) quote_strings: bool,
 }
 
 impl ValueFormatConfig {
@@ -15,7 +17,9 @@ impl ValueFormatConfig {
     pub const fn new() -> Self {
         Self {
             max_depth: 3,
+            indent_width: 4,
             colors_enabled: false,
+            quote_strings: false,
         }
     }
 
@@ -27,6 +31,19 @@ impl ValueFormatConfig {
         Self { max_depth, ..self }
     }
 
+    /**
+        Sets the number of spaces to indent each level of nested tables with.
+
+        Tables are indented with 4 spaces by default.
+    */
+    #[must_use]
+    pub const fn with_indent_width(self, indent_width: usize) -> Self {
+        Self {
+            indent_width,
+            ..self
+        }
+    }
+
     /**
         Sets whether colors should be enabled.
 
@@ -39,6 +56,24 @@ impl ValueFormatConfig {
             ..self
         }
     }
+
+    /**
+        Sets whether strings that are not inside of tables should be
+        quoted and escaped, the same way as strings inside of tables.
+
+        Such strings are formatted as-is by default, the same way as `print` does.
+    */
+    #[must_use]
+    pub const fn with_quoted_strings(self, quote_strings: bool) -> Self {
+        Self {
+            quote_strings,
+            ..self
+        }
+    }
+
+    pub(super) fn indent(&self, depth: usize) -> String {
+        " ".repeat(self.indent_width * depth)
+    }
 }
 
 impl Default for ValueFormatConfig {
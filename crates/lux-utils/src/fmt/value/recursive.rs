@@ -11,8 +11,6 @@ use super::{
     style::STYLE_DIM,
 };
 
-const INDENT: &str = "    ";
-
 /**
     Representation of a pointer in memory to a Lua value.
 */
@@ -87,13 +85,13 @@ pub(crate) fn format_value_recursive(
                     buffer,
                     "\n{}\n{}{}",
                     formatted_values.join("\n"),
-                    INDENT.repeat(depth),
+                    config.indent(depth),
                     STYLE_DIM.apply_to("}")
                 )?;
             }
         }
     } else {
-        let prefer_plain = depth == 0;
+        let prefer_plain = depth == 0 && !config.quote_strings;
         write!(buffer, "{}", format_value_styled(value, prefer_plain))?;
     }
 
@@ -130,7 +128,7 @@ fn format_array(
         .map(|(_, value)| {
             Ok(format!(
                 "{}{}{}",
-                INDENT.repeat(1 + depth),
+                config.indent(1 + depth),
                 format_value_recursive(&value, config, visited, depth + 1)?,
                 STYLE_DIM.apply_to(","),
             ))
@@ -150,7 +148,7 @@ fn format_table(
             if let Some(plain_key) = lua_value_as_plain_string_key(&key) {
                 Ok(format!(
                     "{}{plain_key} {} {}{}",
-                    INDENT.repeat(1 + depth),
+                    config.indent(1 + depth),
                     STYLE_DIM.apply_to("="),
                     format_value_recursive(&value, config, visited, depth + 1)?,
                     STYLE_DIM.apply_to(","),
@@ -158,7 +156,7 @@ fn format_table(
             } else {
                 Ok(format!(
                     "{}{}{}{} {} {}{}",
-                    INDENT.repeat(1 + depth),
+                    config.indent(1 + depth),
                     STYLE_DIM.apply_to("["),
                     format_value_recursive(&key, config, visited, depth + 1)?,
                     STYLE_DIM.apply_to("]"),
@@ -1,12 +1,21 @@
 #![allow(clippy::cargo_common_metadata)]
 
+mod error;
 mod table_builder;
 mod version_string;
 
+pub mod bytecode;
+pub mod equality;
+pub mod flags;
 pub mod fmt;
+pub mod overrides;
+pub mod packed;
 pub mod path;
 pub mod process;
+pub mod profiler;
+pub mod snapshot;
 
+pub use self::error::{LuxError, LuxErrorObject};
 pub use self::table_builder::TableBuilder;
 pub use self::version_string::get_version_string;
 
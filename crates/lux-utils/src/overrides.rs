@@ -0,0 +1,174 @@
+/*!
+    Overrides for modules registered under an alias, such as `@lux/fs`, which
+    replace the module that `require` returns until they are removed again.
+
+    Overrides are kept as a stack, so that tests can override a module that the
+    embedder already overrode, and then restore everything they overrode at once.
+*/
+
+use mlua::prelude::*;
+
+/// Registry table where modules registered using `Lua::register_module` are kept, by their lowercased name
+const REGISTERED_MODULES_TABLE: &str = "_REGISTEREDMODULES";
+
+/**
+    A single override, along with the value that was registered before it.
+*/
+#[derive(Debug, Clone)]
+struct Override {
+    id: u64,
+    name: String,
+    previous: LuaValue,
+}
+
+#[derive(Debug, Default)]
+struct ModuleOverrides {
+    /// All overrides in place, oldest first
+    entries: Vec<Override>,
+    next_id: u64,
+}
+
+impl ModuleOverrides {
+    fn push(&mut self, name: &str, previous: LuaValue) {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push(Override {
+            id,
+            name: name.to_string(),
+            previous,
+        });
+    }
+}
+
+fn check_name(name: &str) -> LuaResult<()> {
+    if name.starts_with('@') {
+        Ok(())
+    } else {
+        Err(LuaError::runtime(format!(
+            "Only modules with an alias, such as '@lux/fs', can be overridden - got '{name}'"
+        )))
+    }
+}
+
+fn registered(lua: &Lua, name: &str) -> LuaResult<LuaValue> {
+    let LuaValue::Table(registered) =
+        lua.named_registry_value::<LuaValue>(REGISTERED_MODULES_TABLE)?
+    else {
+        return Ok(LuaValue::Nil);
+    };
+    registered.raw_get(name.to_ascii_lowercase())
+}
+
+/**
+    Overrides the module registered under the given alias, so that
+    requiring it returns `value` until the override is removed.
+
+    Modules that already required it keep the value they got before.
+
+    # Errors
+
+    Errors if the name is not an alias starting with `@`, or when out of memory.
+*/
+pub fn override_module(lua: &Lua, name: &str, value: impl IntoLua) -> LuaResult<()> {
+    check_name(name)?;
+    let previous = registered(lua, name)?;
+    lua.register_module(name, value)?;
+
+    if let Some(mut overrides) = lua.app_data_mut::<ModuleOverrides>() {
+        overrides.push(name, previous);
+        return Ok(());
+    }
+    let mut overrides = ModuleOverrides::default();
+    overrides.push(name, previous);
+    lua.set_app_data(overrides);
+    Ok(())
+}
+
+/**
+    Removes the latest override of the module registered under the given alias,
+    restoring whatever was registered before it - which may be an earlier override.
+
+    Returns whether the module was overridden.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn remove_override(lua: &Lua, name: &str) -> LuaResult<bool> {
+    let removed = lua
+        .app_data_mut::<ModuleOverrides>()
+        .and_then(|mut overrides| {
+            let index = overrides
+                .entries
+                .iter()
+                .rposition(|entry| entry.name.eq_ignore_ascii_case(name))?;
+            Some(overrides.entries.remove(index))
+        });
+    match removed {
+        Some(entry) => {
+            lua.register_module(&entry.name, entry.previous)?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/**
+    Returns a mark for the current point in time, to later remove
+    only the overrides made after it using [`restore_overrides`].
+*/
+#[must_use]
+pub fn override_mark(lua: &Lua) -> u64 {
+    lua.app_data_ref::<ModuleOverrides>()
+        .map_or(0, |overrides| overrides.next_id)
+}
+
+/**
+    Removes all overrides made after the given mark that are still in place, latest first.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn restore_overrides(lua: &Lua, mark: u64) -> LuaResult<()> {
+    let removed = lua
+        .app_data_mut::<ModuleOverrides>()
+        .map(|mut overrides| {
+            let kept = overrides.entries.partition_point(|entry| entry.id < mark);
+            overrides.entries.split_off(kept)
+        })
+        .unwrap_or_default();
+    for entry in removed.into_iter().rev() {
+        lua.register_module(&entry.name, entry.previous)?;
+    }
+    Ok(())
+}
+
+/**
+    Registers a module under the given alias, the same as `Lua::register_module`,
+    unless it is overridden - in which case the new module is registered once
+    all overrides of it have been removed, and the overrides stay in place.
+
+    # Errors
+
+    Errors if the name is not an alias starting with `@`, or when out of memory.
+*/
+pub fn register_module(lua: &Lua, name: &str, value: impl IntoLua) -> LuaResult<()> {
+    let value = value.into_lua(lua)?;
+    let replaced = lua
+        .app_data_mut::<ModuleOverrides>()
+        .and_then(|mut overrides| {
+            let first = overrides
+                .entries
+                .iter_mut()
+                .find(|entry| entry.name.eq_ignore_ascii_case(name))?;
+            first.previous = value.clone();
+            Some(())
+        })
+        .is_some();
+    if replaced {
+        Ok(())
+    } else {
+        lua.register_module(name, value)
+    }
+}
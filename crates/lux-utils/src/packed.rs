@@ -0,0 +1,51 @@
+//! Helpers for reading and writing `#[repr(C)]` values made of `f64` fields
+//! to and from buffers, using a little-endian layout with no padding.
+
+use mlua::{Buffer, prelude::*};
+
+const F64_SIZE: usize = size_of::<f64>();
+
+fn check_bounds(buffer: &Buffer, offset: usize, count: usize) -> LuaResult<()> {
+    let end = offset.checked_add(count * F64_SIZE);
+    if end.is_none_or(|end| end > buffer.len()) {
+        return Err(LuaError::runtime(format!(
+            "Buffer access out of bounds (offset {offset}, {} bytes, buffer is {} bytes)",
+            count * F64_SIZE,
+            buffer.len()
+        )));
+    }
+    Ok(())
+}
+
+/**
+    Reads `N` consecutive little-endian `f64` values from a buffer, starting at `offset`.
+
+    # Errors
+
+    Errors if the values would be read past the end of the buffer.
+*/
+pub fn read_f64s<const N: usize>(buffer: &Buffer, offset: usize) -> LuaResult<[f64; N]> {
+    check_bounds(buffer, offset, N)?;
+    let mut values = [0.0; N];
+    for (index, value) in values.iter_mut().enumerate() {
+        *value = f64::from_le_bytes(buffer.read_bytes::<F64_SIZE>(offset + index * F64_SIZE));
+    }
+    Ok(values)
+}
+
+/**
+    Writes consecutive little-endian `f64` values to a buffer, starting at `offset`.
+
+    Returns the offset just past the last value written.
+
+    # Errors
+
+    Errors if the values would be written past the end of the buffer.
+*/
+pub fn write_f64s(buffer: &Buffer, offset: usize, values: &[f64]) -> LuaResult<usize> {
+    check_bounds(buffer, offset, values.len())?;
+    for (index, value) in values.iter().enumerate() {
+        buffer.write_bytes(offset + index * F64_SIZE, &value.to_le_bytes());
+    }
+    Ok(offset + values.len() * F64_SIZE)
+}
@@ -10,6 +10,8 @@ use std::{
 
 use mlua::prelude::*;
 
+use crate::snapshot::ModuleSnapshot;
+
 use super::constants::{FILE_EXTENSIONS, FILE_NAME_INIT};
 use super::std::append_extension;
 
@@ -29,8 +31,18 @@ pub enum LuauFilePath {
 
 impl LuauFilePath {
     fn resolve(module: impl AsRef<Path>) -> Result<Self, LuaNavigateError> {
-        let module = module.as_ref();
+        Self::resolve_with(module.as_ref(), Path::is_file, Path::is_dir)
+    }
 
+    /**
+        Resolves a module path the same as `resolve`, but
+        checking for files and directories using the given functions.
+    */
+    fn resolve_with(
+        module: &Path,
+        is_file: impl Fn(&Path) -> bool,
+        is_dir: impl Fn(&Path) -> bool,
+    ) -> Result<Self, LuaNavigateError> {
         // Modules named "init" are ambiguous and not allowed
         if module
             .file_name()
@@ -44,17 +56,17 @@ impl LuauFilePath {
         // Try files first
         for ext in FILE_EXTENSIONS {
             let candidate = append_extension(module, ext);
-            if candidate.is_file() && found.replace(candidate).is_some() {
+            if is_file(&candidate) && found.replace(candidate).is_some() {
                 return Err(LuaNavigateError::Ambiguous);
             }
         }
 
         // Try directories with init files in them
-        if module.is_dir() {
+        if is_dir(module) {
             let init = Path::new(FILE_NAME_INIT);
             for ext in FILE_EXTENSIONS {
                 let candidate = module.join(append_extension(init, ext));
-                if candidate.is_file() && found.replace(candidate).is_some() {
+                if is_file(&candidate) && found.replace(candidate).is_some() {
                     return Err(LuaNavigateError::Ambiguous);
                 }
             }
@@ -190,6 +202,31 @@ impl LuauModulePath {
         Ok(Self { source, target })
     }
 
+    /**
+        Resolves the given module path to a file or directory in a module snapshot,
+        the same as [`LuauModulePath::resolve`] does on the filesystem.
+
+        The resolved files may not exist on the filesystem, and should
+        only be loaded from the snapshot.
+
+        # Errors
+
+        - If the given module path is ambiguous.
+        - If the given module path does not resolve to a file or directory in the snapshot.
+    */
+    pub fn resolve_in_snapshot(
+        module: impl Into<PathBuf>,
+        snapshot: &ModuleSnapshot,
+    ) -> Result<Self, LuaNavigateError> {
+        let source = module.into();
+        let target = LuauFilePath::resolve_with(
+            &source,
+            |path| snapshot.contains(path),
+            |path| snapshot.contains_dir(path),
+        )?;
+        Ok(Self { source, target })
+    }
+
     /**
         Returns the source Luau module path.
     */
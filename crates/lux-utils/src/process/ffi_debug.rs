@@ -0,0 +1,36 @@
+/// FFI debugging options, see `ffi.setDebugMode` and `ffi.setCastLogging`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessFfiDebug {
+    track_origins: bool,
+    log_casts: bool,
+}
+
+impl ProcessFfiDebug {
+    #[must_use]
+    pub fn new(track_origins: bool, log_casts: bool) -> Self {
+        Self {
+            track_origins,
+            log_casts,
+        }
+    }
+
+    pub fn set_track_origins(&mut self, enabled: bool) {
+        self.track_origins = enabled;
+    }
+
+    pub fn set_log_casts(&mut self, enabled: bool) {
+        self.log_casts = enabled;
+    }
+
+    /// Whether cdata remembers where it was created, for `ffi.debuginfo`
+    #[must_use]
+    pub fn track_origins(self) -> bool {
+        self.track_origins
+    }
+
+    /// Whether casts between incompatible pointer types are logged
+    #[must_use]
+    pub fn log_casts(self) -> bool {
+        self.log_casts
+    }
+}
@@ -0,0 +1,41 @@
+use std::thread;
+
+const MIN_DEFAULT_SIZE: usize = 4;
+
+/// Number of threads used for blocking FFI calls made with `callAsync`
+#[derive(Debug, Clone, Copy)]
+pub struct ProcessFfiPoolSize {
+    size: usize,
+}
+
+impl ProcessFfiPoolSize {
+    #[must_use]
+    pub fn new(size: usize) -> Self {
+        Self { size: size.max(1) }
+    }
+
+    #[must_use]
+    pub fn get(self) -> usize {
+        self.size
+    }
+}
+
+impl Default for ProcessFfiPoolSize {
+    fn default() -> Self {
+        // Blocking calls mostly wait, so even small machines get a few threads
+        let parallelism = thread::available_parallelism().map_or(1, usize::from);
+        Self::new(parallelism.max(MIN_DEFAULT_SIZE))
+    }
+}
+
+impl From<ProcessFfiPoolSize> for usize {
+    fn from(val: ProcessFfiPoolSize) -> Self {
+        val.get()
+    }
+}
+
+impl From<usize> for ProcessFfiPoolSize {
+    fn from(val: usize) -> Self {
+        Self::new(val)
+    }
+}
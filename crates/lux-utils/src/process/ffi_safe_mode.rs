@@ -0,0 +1,32 @@
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessFfiSafeMode {
+    enabled: bool,
+}
+
+impl ProcessFfiSafeMode {
+    #[must_use]
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    pub fn set_status(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    #[must_use]
+    pub fn enabled(self) -> bool {
+        self.enabled
+    }
+}
+
+impl From<ProcessFfiSafeMode> for bool {
+    fn from(val: ProcessFfiSafeMode) -> Self {
+        val.enabled()
+    }
+}
+
+impl From<bool> for ProcessFfiSafeMode {
+    fn from(val: bool) -> Self {
+        Self::new(val)
+    }
+}
@@ -5,11 +5,25 @@ use os_str_bytes::{OsStrBytes, OsStringBytes};
 
 mod args;
 mod env;
+mod ffi_debug;
+mod ffi_pool_size;
+mod ffi_safe_mode;
 mod jit;
+mod permissions;
+mod release;
+mod run_context;
+mod shutdown;
 
 pub use self::args::ProcessArgs;
 pub use self::env::ProcessEnv;
+pub use self::ffi_debug::ProcessFfiDebug;
+pub use self::ffi_pool_size::ProcessFfiPoolSize;
+pub use self::ffi_safe_mode::ProcessFfiSafeMode;
 pub use self::jit::ProcessJitEnablement;
+pub use self::permissions::{Permission, ProcessPermissions};
+pub use self::release::ProcessReleaseMode;
+pub use self::run_context::RunContext;
+pub use self::shutdown::{OsSignal, ProcessShutdown};
 
 fn lua_value_to_os_string(res: LuaResult<LuaValue>, to: &'static str) -> LuaResult<OsString> {
     let (btype, bs) = match res {
@@ -0,0 +1,255 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Component, Path, PathBuf},
+};
+
+use mlua::prelude::*;
+
+/**
+    A capability that may be gated behind an explicit grant.
+
+    All of these except [`Permission::ProcessMemory`] are granted unless
+    the runtime denies capabilities by default, see [`ProcessPermissions`].
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Permission {
+    /// Loading native libraries and calling native code through `ffi`, and waiting on native handles through `reactor`
+    Ffi,
+    /// Running other programs through `process` and `desktop`
+    Process,
+    /// Opening network connections through `socket` and `websocket`, and accepting them through `serve`
+    Net,
+    /// Reading and writing files through `fs`, `csv`, `image`, `env` and `binparse`
+    Fs,
+    /// Reading and writing the memory of other processes
+    ProcessMemory,
+    /// Reading and writing the Windows registry through `winreg`
+    Registry,
+    /// Reading and synthesizing keyboard and mouse input
+    Input,
+}
+
+impl Permission {
+    /**
+        Returns the name of the permission, as used in error messages.
+    */
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Ffi => "ffi",
+            Self::Process => "process",
+            Self::Net => "net",
+            Self::Fs => "fs",
+            Self::ProcessMemory => "process-memory",
+            Self::Registry => "registry",
+            Self::Input => "input",
+        }
+    }
+
+    /**
+        Returns the command line flag used to grant the permission.
+    */
+    #[must_use]
+    pub const fn flag(self) -> &'static str {
+        match self {
+            Self::Ffi => "--allow-ffi",
+            Self::Process => "--allow-process",
+            Self::Net => "--allow-net",
+            Self::Fs => "--allow-fs",
+            Self::ProcessMemory => "--allow-process-memory",
+            Self::Registry => "--allow-registry",
+            Self::Input => "--allow-input",
+        }
+    }
+
+    /**
+        Returns `true` if the permission must always be granted
+        explicitly, even when capabilities are not denied by default.
+    */
+    #[must_use]
+    pub const fn is_always_gated(self) -> bool {
+        matches!(self, Self::ProcessMemory)
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/**
+    The set of permissions granted to the current process.
+
+    By default, every permission is granted except for ones that are
+    [always gated](Permission::is_always_gated). Once capabilities are
+    denied by default, only the permissions that were explicitly granted are.
+
+    The `fs` permission may also be limited to a set of paths,
+    in which case only files within those paths may be accessed.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ProcessPermissions {
+    granted: BTreeSet<Permission>,
+    fs_paths: Vec<PathBuf>,
+    deny_by_default: bool,
+}
+
+impl ProcessPermissions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn grant(&mut self, permission: Permission) {
+        self.granted.insert(permission);
+    }
+
+    /**
+        Grants the `fs` permission, limited to the given path and anything inside of it.
+
+        Paths may be granted multiple times, access to any of them is then allowed.
+    */
+    pub fn grant_fs_path(&mut self, path: impl AsRef<Path>) {
+        self.granted.insert(Permission::Fs);
+        self.fs_paths.push(resolve_path(path.as_ref()));
+    }
+
+    /**
+        Sets whether permissions that were not explicitly granted should be denied.
+    */
+    pub fn set_deny_by_default(&mut self, deny_by_default: bool) {
+        self.deny_by_default = deny_by_default;
+    }
+
+    #[must_use]
+    pub fn is_granted(&self, permission: Permission) -> bool {
+        self.granted.contains(&permission)
+            || !(self.deny_by_default || permission.is_always_gated())
+    }
+
+    /**
+        Returns the paths that the `fs` permission is limited to,
+        or an empty slice if it is not limited to any paths.
+    */
+    #[must_use]
+    pub fn fs_paths(&self) -> &[PathBuf] {
+        &self.fs_paths
+    }
+
+    /**
+        Checks that the given permission has been granted to the Luau VM.
+
+        VMs without any stored permissions - such as ones not created by
+        the Lux runtime - are treated as having the default permissions.
+
+        # Errors
+
+        Errors with a descriptive message if the permission was not granted.
+    */
+    pub fn check(lua: &Lua, permission: Permission, what: &str) -> LuaResult<()> {
+        let granted = match lua.app_data_ref::<Self>() {
+            Some(perms) => perms.is_granted(permission),
+            None => Self::default().is_granted(permission),
+        };
+        if granted {
+            Ok(())
+        } else {
+            Err(LuaError::runtime(format!(
+                "{what} requires the '{permission}' permission, which has not been granted\
+                \nRun the script with {} to grant it",
+                permission.flag()
+            )))
+        }
+    }
+
+    /**
+        Checks that the `fs` permission has been granted to the
+        Luau VM, and that it covers the given path.
+
+        # Errors
+
+        Errors with a descriptive message if the permission was not granted,
+        or if it was granted but limited to paths that do not include `path`.
+    */
+    pub fn check_path(lua: &Lua, path: &Path, what: &str) -> LuaResult<()> {
+        Self::check(lua, Permission::Fs, what)?;
+
+        let Some(perms) = lua.app_data_ref::<Self>() else {
+            return Ok(());
+        };
+        if perms.fs_paths.is_empty() {
+            return Ok(());
+        }
+
+        let resolved = resolve_path(path);
+        if perms.fs_paths.iter().any(|p| resolved.starts_with(p)) {
+            Ok(())
+        } else {
+            let allowed = perms
+                .fs_paths
+                .iter()
+                .map(|p| format!("'{}'", p.display()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(LuaError::runtime(format!(
+                "{what} can not access '{}', the '{}' permission only covers {allowed}\
+                \nRun the script with {}={} to grant it",
+                path.display(),
+                Permission::Fs,
+                Permission::Fs.flag(),
+                path.display()
+            )))
+        }
+    }
+}
+
+impl FromIterator<Permission> for ProcessPermissions {
+    fn from_iter<T: IntoIterator<Item = Permission>>(iter: T) -> Self {
+        Self {
+            granted: iter.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+}
+
+/**
+    Resolves a path to an absolute path without any `.` or `..` components.
+
+    Symlinks are resolved for the longest part of the path that exists,
+    so that paths which do not exist yet - such as files about to be
+    written - can still be compared against the paths they would be in.
+*/
+fn resolve_path(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut normalized = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+
+    let mut existing = normalized.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return rest
+                .iter()
+                .rev()
+                .fold(canonical, |acc, part| acc.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return normalized,
+        }
+    }
+}
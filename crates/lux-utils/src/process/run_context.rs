@@ -0,0 +1,51 @@
+use std::{fmt, str::FromStr};
+
+/**
+    How the current script was started, exposed to scripts as `script.RunContext`.
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RunContext {
+    /// A script file or stdin, run with `lux run` - also the default for embedders
+    #[default]
+    Run,
+    /// Code given on the command line, with `lux -e`
+    Eval,
+    /// The interactive REPL
+    Repl,
+    /// Tests run with `lux test`
+    Test,
+}
+
+impl RunContext {
+    /**
+        Returns the name of the run context, as seen by scripts.
+    */
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Run => "Run",
+            Self::Eval => "Eval",
+            Self::Repl => "Repl",
+            Self::Test => "Test",
+        }
+    }
+}
+
+impl fmt::Display for RunContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for RunContext {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "run" => Ok(Self::Run),
+            "eval" => Ok(Self::Eval),
+            "repl" => Ok(Self::Repl),
+            "test" => Ok(Self::Test),
+            _ => Err(format!("Unknown run context '{s}'")),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+
+/**
+    An OS-level signal asking the process to stop.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OsSignal {
+    /// `SIGINT`, or Ctrl+C on Windows
+    Interrupt,
+    /// `SIGTERM` - only delivered on Unix
+    Terminate,
+}
+
+impl OsSignal {
+    /**
+        Returns the name of the signal, as seen by scripts.
+    */
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Interrupt => "interrupt",
+            Self::Terminate => "terminate",
+        }
+    }
+
+    /**
+        Returns the exit code a process conventionally has when killed by this signal.
+    */
+    #[must_use]
+    pub const fn exit_code(self) -> u8 {
+        match self {
+            Self::Interrupt => 130,
+            Self::Terminate => 143,
+        }
+    }
+}
+
+impl fmt::Display for OsSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/**
+    How a script stopped the runtime through `process.shutdown`.
+
+    Stored in Lua app data by the `process` library, and returned
+    alongside the exit code once the runtime finishes running.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessShutdown {
+    /// The last OS signal received before shutting down, if any
+    pub signal: Option<OsSignal>,
+    /// Whether pending tasks were still running when the timeout elapsed
+    pub timed_out: bool,
+    /// The exit code the runtime finished with
+    pub code: u8,
+}
+
+impl ProcessShutdown {
+    /**
+        Returns the default exit code for a shutdown.
+
+        A shutdown where every pending task finished exits with `0`, one that timed
+        out exits with the code of the signal that caused it, or `1` without a signal.
+    */
+    #[must_use]
+    pub fn default_code(signal: Option<OsSignal>, timed_out: bool) -> u8 {
+        match (timed_out, signal) {
+            (false, _) => 0,
+            (true, Some(signal)) => signal.exit_code(),
+            (true, None) => 1,
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! A process-wide profiler, recording spans of time on a few fixed tracks.
+//!
+//! Zones are started and ended explicitly, FFI calls are timed by the FFI
+//! library, and Luau functions are found by sampling the call stack from
+//! the interrupt that the runtime installs while profiling is enabled.
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        LazyLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+mod output;
+mod sampler;
+
+pub use self::sampler::sample;
+
+use self::sampler::SamplerState;
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static STATE: LazyLock<Mutex<ProfilerState>> = LazyLock::new(|| Mutex::new(ProfilerState::new()));
+
+/**
+    A track that recorded spans are placed on.
+
+    Spans on the same track are always properly nested.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProfileTrack {
+    /// Sampled Luau functions
+    Luau,
+    /// Zones started and ended by scripts
+    Zones,
+    /// Calls from Luau into native functions through FFI
+    Ffi,
+}
+
+impl ProfileTrack {
+    const ALL: [Self; 3] = [Self::Luau, Self::Zones, Self::Ffi];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Luau => "Luau",
+            Self::Zones => "Zones",
+            Self::Ffi => "FFI",
+        }
+    }
+}
+
+/**
+    A format that recorded profiles can be written in.
+*/
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProfileFormat {
+    /// The Chrome trace event format, for `chrome://tracing` and Perfetto
+    #[default]
+    Chrome,
+    /// The speedscope file format, for <https://www.speedscope.app>
+    Speedscope,
+}
+
+impl FromStr for ProfileFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "chrome" => Ok(Self::Chrome),
+            "speedscope" => Ok(Self::Speedscope),
+            _ => Err(format!(
+                "Unknown profile format '{s}', expected 'chrome' or 'speedscope'"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ProfileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Chrome => write!(f, "chrome"),
+            Self::Speedscope => write!(f, "speedscope"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Span {
+    track: ProfileTrack,
+    name: String,
+    start: Duration,
+    end: Duration,
+}
+
+#[derive(Debug)]
+struct ProfilerState {
+    epoch: Instant,
+    spans: Vec<Span>,
+    /// Zones that have begun but not ended, with whether they are being recorded
+    zones: Vec<(String, Duration, bool)>,
+    sampler: SamplerState,
+}
+
+impl ProfilerState {
+    fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            spans: Vec::new(),
+            zones: Vec::new(),
+            sampler: SamplerState::default(),
+        }
+    }
+
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+}
+
+/**
+    Starts recording zones, FFI calls and Luau samples.
+*/
+pub fn start() {
+    RUNNING.store(true, Ordering::Release);
+}
+
+/**
+    Stops recording, closing any sampled Luau functions that were still running.
+*/
+pub fn stop() {
+    if RUNNING.swap(false, Ordering::AcqRel) {
+        let mut state = STATE.lock();
+        let now = state.now();
+        let closed = state.sampler.close_all(now);
+        state.spans.extend(closed);
+    }
+}
+
+/**
+    Returns whether the profiler is currently recording.
+*/
+#[must_use]
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::Acquire)
+}
+
+/**
+    Discards everything recorded so far, and restarts the clock.
+*/
+pub fn reset() {
+    let mut state = STATE.lock();
+    let zones = std::mem::take(&mut state.zones);
+    *state = ProfilerState::new();
+    // Zones that are still open stay open, but their recorded time starts over
+    state.zones = zones
+        .into_iter()
+        .map(|(name, _, recorded)| (name, Duration::ZERO, recorded))
+        .collect();
+}
+
+/**
+    Begins a zone with the given name, which lasts until the matching [`end_zone`].
+
+    Zones nest, and are only recorded if the profiler was running when they began.
+*/
+pub fn begin_zone(name: impl Into<String>) {
+    let mut state = STATE.lock();
+    let now = state.now();
+    state.zones.push((name.into(), now, is_running()));
+}
+
+/**
+    Ends the most recently begun zone, returning its name,
+    or `None` if there was no zone to end.
+*/
+pub fn end_zone() -> Option<String> {
+    let mut state = STATE.lock();
+    let (name, start, recorded) = state.zones.pop()?;
+    if recorded && is_running() {
+        let end = state.now();
+        state.spans.push(Span {
+            track: ProfileTrack::Zones,
+            name: name.clone(),
+            start,
+            end,
+        });
+    }
+    Some(name)
+}
+
+/**
+    A span of time being measured, which gets recorded when dropped.
+*/
+#[derive(Debug)]
+pub struct ProfileScope {
+    track: ProfileTrack,
+    name: String,
+    start: Duration,
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        let mut state = STATE.lock();
+        let end = state.now();
+        state.spans.push(Span {
+            track: self.track,
+            name: std::mem::take(&mut self.name),
+            start: self.start,
+            end: end.max(self.start),
+        });
+    }
+}
+
+/**
+    Starts measuring a span on the given track, if the profiler is running.
+
+    This is cheap when the profiler is not running, and the
+    name is only turned into a string when it is going to be used.
+*/
+#[must_use]
+pub fn scope(track: ProfileTrack, name: impl fmt::Display) -> Option<ProfileScope> {
+    if !is_running() {
+        return None;
+    }
+    let start = STATE.lock().now();
+    Some(ProfileScope {
+        track,
+        name: name.to_string(),
+        start,
+    })
+}
+
+/**
+    Returns everything recorded so far, in the given format.
+*/
+#[must_use]
+pub fn dump(format: ProfileFormat) -> String {
+    let state = STATE.lock();
+    let now = state.now();
+    let mut spans = state.spans.clone();
+    // Include whatever is still running, as if it ended right now
+    spans.extend(state.sampler.close_all_at(now));
+    spans.extend(state.zones.iter().filter(|(_, _, recorded)| *recorded).map(
+        |(name, start, _)| Span {
+            track: ProfileTrack::Zones,
+            name: name.clone(),
+            start: *start,
+            end: now,
+        },
+    ));
+    drop(state);
+    match format {
+        ProfileFormat::Chrome => output::chrome(&spans),
+        ProfileFormat::Speedscope => output::speedscope(&spans, now),
+    }
+}
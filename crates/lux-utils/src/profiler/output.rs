@@ -0,0 +1,123 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde_json::{Value, json};
+
+use super::{ProfileTrack, Span};
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+fn track_id(track: ProfileTrack) -> usize {
+    ProfileTrack::ALL
+        .iter()
+        .position(|t| *t == track)
+        .unwrap_or_default()
+        + 1
+}
+
+/**
+    Formats spans as Chrome trace events, with one thread per track.
+*/
+pub(super) fn chrome(spans: &[Span]) -> String {
+    let mut events = ProfileTrack::ALL
+        .iter()
+        .map(|track| {
+            json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": 1,
+                "tid": track_id(*track),
+                "args": { "name": track.name() },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut sorted = spans.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|span| span.start);
+    events.extend(sorted.into_iter().map(|span| {
+        json!({
+            "name": span.name,
+            "cat": span.track.name().to_ascii_lowercase(),
+            "ph": "X",
+            "pid": 1,
+            "tid": track_id(span.track),
+            "ts": micros(span.start),
+            "dur": micros(span.end.saturating_sub(span.start)),
+        })
+    }));
+
+    json!({
+        "traceEvents": events,
+        "displayTimeUnit": "ms",
+    })
+    .to_string()
+}
+
+/**
+    Formats spans as a speedscope file, with one evented profile per track.
+
+    Evented profiles must open and close frames in a strictly nested order, so
+    spans are sorted outermost first, and any span that would overlap the end of
+    its parent - which can only happen through rounding - gets cut short.
+*/
+pub(super) fn speedscope(spans: &[Span], end: Duration) -> String {
+    let mut frames = Vec::<Value>::new();
+    let mut frame_ids = HashMap::<String, usize>::new();
+    let mut frame_id = |name: &str| -> usize {
+        *frame_ids.entry(name.to_string()).or_insert_with(|| {
+            frames.push(json!({ "name": name }));
+            frames.len() - 1
+        })
+    };
+
+    let mut profiles = Vec::new();
+    for track in ProfileTrack::ALL {
+        let mut track_spans = spans
+            .iter()
+            .filter(|span| span.track == track)
+            .collect::<Vec<_>>();
+        if track_spans.is_empty() {
+            continue;
+        }
+        track_spans.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+        let mut events = Vec::new();
+        let mut open: Vec<(usize, Duration)> = Vec::new();
+        for span in track_spans {
+            while let Some((frame, close_at)) = open.last().copied()
+                && close_at <= span.start
+            {
+                events.push(json!({ "type": "C", "frame": frame, "at": micros(close_at) }));
+                open.pop();
+            }
+            let span_end = open
+                .last()
+                .map_or(span.end, |(_, parent_end)| span.end.min(*parent_end));
+            let frame = frame_id(&span.name);
+            events.push(json!({ "type": "O", "frame": frame, "at": micros(span.start) }));
+            open.push((frame, span_end));
+        }
+        while let Some((frame, close_at)) = open.pop() {
+            events.push(json!({ "type": "C", "frame": frame, "at": micros(close_at) }));
+        }
+
+        profiles.push(json!({
+            "type": "evented",
+            "name": track.name(),
+            "unit": "microseconds",
+            "startValue": 0.0,
+            "endValue": micros(end),
+            "events": events,
+        }));
+    }
+
+    json!({
+        "$schema": "https://www.speedscope.app/file-format-schema.json",
+        "shared": { "frames": frames },
+        "profiles": profiles,
+        "name": "Lux profile",
+        "exporter": "lux",
+    })
+    .to_string()
+}
@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use mlua::{Debug, prelude::*};
+
+use super::{ProfileTrack, STATE, Span, is_running};
+
+/// How often the call stack gets sampled, at most
+const SAMPLE_INTERVAL: Duration = Duration::from_micros(100);
+/// Gaps between interrupts longer than this mean that no Luau code was running
+const IDLE_THRESHOLD: Duration = Duration::from_millis(5);
+/// Frames deeper than this are not recorded
+const MAX_DEPTH: usize = 128;
+
+/**
+    Luau functions that were on the call stack in the last sample, outermost first.
+*/
+#[derive(Debug, Default)]
+pub(super) struct SamplerState {
+    frames: Vec<(String, Duration)>,
+    last_sample: Option<Duration>,
+    last_interrupt: Option<Duration>,
+}
+
+impl SamplerState {
+    fn spans_for(frames: &[(String, Duration)], end: Duration) -> impl Iterator<Item = Span> {
+        frames.iter().rev().map(move |(name, start)| Span {
+            track: ProfileTrack::Luau,
+            name: name.clone(),
+            start: *start,
+            end,
+        })
+    }
+
+    /// Closes all open frames at the given time, returning their spans
+    pub(super) fn close_all(&mut self, end: Duration) -> Vec<Span> {
+        let spans = Self::spans_for(&self.frames, end).collect();
+        self.frames.clear();
+        self.last_sample = None;
+        self.last_interrupt = None;
+        spans
+    }
+
+    /// Returns spans for all open frames as if they were closed at the given time
+    pub(super) fn close_all_at(&self, end: Duration) -> Vec<Span> {
+        Self::spans_for(&self.frames, end).collect()
+    }
+
+    /// Updates the open frames to match a new sample, returning spans for frames that closed
+    fn update(&mut self, stack: Vec<String>, now: Duration) -> Vec<Span> {
+        let common = self
+            .frames
+            .iter()
+            .zip(&stack)
+            .take_while(|((open, _), name)| open == *name)
+            .count();
+        let closed = Self::spans_for(&self.frames[common..], now).collect();
+        self.frames.truncate(common);
+        self.frames
+            .extend(stack.into_iter().skip(common).map(|name| (name, now)));
+        closed
+    }
+}
+
+fn frame_name(debug: &Debug) -> String {
+    let names = debug.names();
+    let source = debug.source();
+    let name = match names.name.as_deref() {
+        Some(name) => name,
+        None if source.what == "main" => "<main>",
+        None => "<anonymous>",
+    };
+    if source.what == "C" {
+        return format!("{name} [C]");
+    }
+    let short_src = source.short_src.as_deref().unwrap_or("?");
+    match source.line_defined {
+        Some(line) if line > 0 => format!("{name} ({short_src}:{line})"),
+        _ => format!("{name} ({short_src})"),
+    }
+}
+
+/**
+    Samples the Luau call stack, if the profiler is running
+    and enough time has passed since the last sample.
+
+    This is meant to be called from a Luau interrupt, which runs
+    at every function call and loop iteration in script code.
+*/
+pub fn sample(lua: &Lua) {
+    if !is_running() {
+        return;
+    }
+
+    let mut state = STATE.lock();
+    let now = state.now();
+
+    // Nothing ran while the VM was idle, so close everything where it last ran
+    let last_interrupt = state.sampler.last_interrupt.replace(now);
+    if let Some(last) = last_interrupt
+        && now.saturating_sub(last) > IDLE_THRESHOLD
+    {
+        let closed = state.sampler.close_all(last);
+        state.spans.extend(closed);
+        state.sampler.last_interrupt = Some(now);
+    }
+
+    if state
+        .sampler
+        .last_sample
+        .is_some_and(|last| now.saturating_sub(last) < SAMPLE_INTERVAL)
+    {
+        return;
+    }
+    state.sampler.last_sample = Some(now);
+
+    let mut stack = Vec::new();
+    for level in 0..MAX_DEPTH {
+        match lua.inspect_stack(level, frame_name) {
+            Some(name) => stack.push(name),
+            None => break,
+        }
+    }
+    stack.reverse();
+
+    let closed = state.sampler.update(stack, now);
+    state.spans.extend(closed);
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::path::clean_path_and_make_absolute;
+
+const MAGIC: &[u8; 8] = b"luxsnap2";
+
+/**
+    A snapshot of the modules loaded by a runtime, as compiled bytecode by the path of their source file.
+
+    Paths are stored relative to the root of the snapshot - the directory of the script that was
+    run - so that modules are found wherever the snapshot is restored, even if their source files
+    no longer exist. Modules outside of the root are not stored.
+
+    Luau has no way to save the state of a VM, so modules restored from a snapshot still run
+    again when they are required - but they are neither read from disk nor compiled to do so.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ModuleSnapshot {
+    modules: BTreeMap<String, Vec<u8>>,
+    main: Option<String>,
+    root: Option<PathBuf>,
+    restored: BTreeSet<String>,
+}
+
+impl ModuleSnapshot {
+    /**
+        Returns the directory that paths in the snapshot are relative to, if it has been set.
+    */
+    #[must_use]
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    /**
+        Sets the directory that paths in the snapshot are relative to.
+
+        The root is not stored by `to_bytes`, and must be set again after reading a snapshot.
+    */
+    pub fn set_root(&mut self, root: &Path) {
+        self.root = Some(clean_path_and_make_absolute(root));
+    }
+
+    /**
+        Returns the path of the main script relative to the root, if it is in the snapshot.
+    */
+    #[must_use]
+    pub fn main(&self) -> Option<&str> {
+        self.main.as_deref()
+    }
+
+    /**
+        Sets the script at the given path as the main script, which is run when restoring the snapshot.
+    */
+    pub fn set_main(&mut self, path: &Path) {
+        if let Some(key) = self.key(path) {
+            self.main = Some(key);
+        }
+    }
+
+    /**
+        Returns whether the snapshot has bytecode for the file at the given path.
+    */
+    #[must_use]
+    pub fn contains(&self, path: &Path) -> bool {
+        self.key(path)
+            .is_some_and(|key| self.modules.contains_key(&key))
+    }
+
+    /**
+        Returns whether the snapshot has bytecode for any file inside the directory at the given path.
+    */
+    #[must_use]
+    pub fn contains_dir(&self, path: &Path) -> bool {
+        let Some(key) = self.key(path) else {
+            return false;
+        };
+        if key.is_empty() {
+            return !self.modules.is_empty();
+        }
+        let prefix = format!("{key}/");
+        self.modules
+            .range(prefix.clone()..)
+            .next()
+            .is_some_and(|(module, _)| module.starts_with(&prefix))
+    }
+
+    /**
+        Returns the bytecode stored for the module at the given path, if any.
+    */
+    #[must_use]
+    pub fn get(&self, path: &Path) -> Option<&[u8]> {
+        self.modules.get(&self.key(path)?).map(Vec::as_slice)
+    }
+
+    /**
+        Returns the bytecode stored for the module at the given path, but only the first time
+        it is restored, since modules loaded again - such as by `require.reload` - may have changed.
+    */
+    pub fn restore(&mut self, path: &Path) -> Option<Vec<u8>> {
+        let key = self.key(path)?;
+        let bytecode = self.modules.get(&key)?.clone();
+        self.restored.insert(key).then_some(bytecode)
+    }
+
+    /**
+        Stores bytecode for the module at the given path, replacing any that was stored before.
+
+        Modules outside of the root, or any module if no root has been set, are not stored.
+        The bytecode is only restored by snapshots created from this one using `to_bytes`.
+    */
+    pub fn insert(&mut self, path: &Path, bytecode: Vec<u8>) {
+        let Some(key) = self.key(path) else {
+            return;
+        };
+        self.restored.insert(key.clone());
+        self.modules.insert(key, bytecode);
+    }
+
+    /**
+        Returns the number of modules in the snapshot.
+    */
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    /**
+        Returns whether the snapshot contains no modules.
+    */
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /**
+        Writes the snapshot to a byte vector, to later be read using `from_bytes`.
+    */
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        let main = self.main.as_deref().unwrap_or_default();
+        write_bytes(&mut bytes, main.as_bytes());
+        bytes.extend_from_slice(&(self.modules.len() as u64).to_le_bytes());
+        for (path, bytecode) in &self.modules {
+            write_bytes(&mut bytes, path.as_bytes());
+            write_bytes(&mut bytes, bytecode);
+        }
+        bytes
+    }
+
+    /**
+        Reads a snapshot that was written using `to_bytes`.
+
+        # Errors
+
+        Errors if the bytes are not a snapshot, or the snapshot is truncated.
+    */
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader(bytes);
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a module snapshot"));
+        }
+
+        let main = reader.read_str()?;
+        let mut modules = BTreeMap::new();
+        for _ in 0..reader.read_u64()? {
+            let path = reader.read_str()?;
+            let len = reader.read_u64()?;
+            let bytecode = reader.take(len)?;
+            modules.insert(path, bytecode.to_vec());
+        }
+        Ok(Self {
+            modules,
+            main: Some(main).filter(|main| !main.is_empty()),
+            root: None,
+            restored: BTreeSet::new(),
+        })
+    }
+
+    /// Modules are stored by their path relative to the root, using `/` on every platform
+    fn key(&self, path: &Path) -> Option<String> {
+        let path = clean_path_and_make_absolute(path);
+        let relative = path.strip_prefix(self.root.as_ref()?).ok()?;
+        let parts = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+        Some(parts.join("/"))
+    }
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(data);
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(invalid("module snapshot is truncated"));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn read_u64(&mut self) -> io::Result<usize> {
+        let bytes = self.take(8)?.try_into().expect("took 8 bytes");
+        usize::try_from(u64::from_le_bytes(bytes))
+            .map_err(|_| invalid("module snapshot is too large"))
+    }
+
+    fn read_str(&mut self) -> io::Result<String> {
+        let len = self.read_u64()?;
+        let text = std::str::from_utf8(self.take(len)?)
+            .map_err(|_| invalid("module snapshot has a path that is not valid UTF-8"))?;
+        Ok(text.to_string())
+    }
+}
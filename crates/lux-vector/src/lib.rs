@@ -4,6 +4,7 @@
 //! Optimized for FFI compatibility with #[repr(C)]
 
 use lux_utils::TableBuilder;
+use lux_utils::packed::{read_f64s, write_f64s};
 use mlua::prelude::*;
 
 const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
@@ -117,6 +118,9 @@ impl LuaUserData for Vector2 {
         });
         m.add_method("Dot", |_, t, o: LuaUserDataRef<Self>| Ok(t.dot(&o)));
         m.add_method("Cross", |_, t, o: LuaUserDataRef<Self>| Ok(t.cross(&o)));
+        m.add_method("WriteTo", |_, t, (b, o): (mlua::Buffer, usize)| {
+            write_f64s(&b, o, &[t.x, t.y])
+        });
         m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
             Ok(*t == *o)
         });
@@ -139,6 +143,19 @@ impl LuaUserData for Vector2 {
     }
 }
 
+impl FromLua for Vector2 {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: "Vector2".to_string(),
+                message: Some("expected a Vector2".into()),
+            }),
+        }
+    }
+}
+
 // ============================================================================
 // Vector3
 // ============================================================================
@@ -260,6 +277,9 @@ impl LuaUserData for Vector3 {
         m.add_method("Cross", |lua, t, o: LuaUserDataRef<Self>| {
             lua.create_userdata(t.cross(&o))
         });
+        m.add_method("WriteTo", |_, t, (b, o): (mlua::Buffer, usize)| {
+            write_f64s(&b, o, &[t.x, t.y, t.z])
+        });
         m.add_meta_method(LuaMetaMethod::Eq, |_, t, o: LuaUserDataRef<Self>| {
             Ok(*t == *o)
         });
@@ -282,6 +302,19 @@ impl LuaUserData for Vector3 {
     }
 }
 
+impl FromLua for Vector3 {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::UserData(ud) => Ok(*ud.borrow::<Self>()?),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: "Vector3".to_string(),
+                message: Some("expected a Vector3".into()),
+            }),
+        }
+    }
+}
+
 // ============================================================================
 // Constructors
 // ============================================================================
@@ -291,6 +324,10 @@ pub fn create_vector2(lua: Lua) -> LuaResult<LuaValue> {
         .with_function("new", |lua, (x, y): (f64, f64)| {
             lua.create_userdata(Vector2::new(x, y))
         })?
+        .with_function("ReadFrom", |lua, (b, o): (mlua::Buffer, usize)| {
+            let [x, y] = read_f64s(&b, o)?;
+            lua.create_userdata(Vector2::new(x, y))
+        })?
         .with_value("zero", lua.create_userdata(Vector2::ZERO)?)?
         .with_value("one", lua.create_userdata(Vector2::ONE)?)?
         .build_readonly()
@@ -302,6 +339,10 @@ pub fn create_vector3(lua: Lua) -> LuaResult<LuaValue> {
         .with_function("new", |lua, (x, y, z): (f64, f64, f64)| {
             lua.create_userdata(Vector3::new(x, y, z))
         })?
+        .with_function("ReadFrom", |lua, (b, o): (mlua::Buffer, usize)| {
+            let [x, y, z] = read_f64s(&b, o)?;
+            lua.create_userdata(Vector3::new(x, y, z))
+        })?
         .with_value("zero", lua.create_userdata(Vector3::ZERO)?)?
         .with_value("one", lua.create_userdata(Vector3::ONE)?)?
         .build_readonly()
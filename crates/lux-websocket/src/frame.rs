@@ -0,0 +1,189 @@
+use std::io::{Error, ErrorKind, Result};
+
+use futures_lite::prelude::*;
+
+/**
+    The largest payload we are willing to buffer for a single message.
+
+    Servers sending anything larger than this are most likely
+    misbehaving, and we would rather close than run out of memory.
+*/
+const MAX_PAYLOAD_LEN: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    fn from_u8(value: u8) -> Result<Self> {
+        Ok(match value {
+            0x0 => Self::Continuation,
+            0x1 => Self::Text,
+            0x2 => Self::Binary,
+            0x8 => Self::Close,
+            0x9 => Self::Ping,
+            0xA => Self::Pong,
+            other => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown websocket opcode {other:#x}"),
+                ));
+            }
+        })
+    }
+
+    const fn as_u8(self) -> u8 {
+        match self {
+            Self::Continuation => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xA,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: OpCode,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(opcode: OpCode, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            fin: true,
+            opcode,
+            payload: payload.into(),
+        }
+    }
+
+    pub fn close(code: u16, reason: &str) -> Self {
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(reason.as_bytes());
+        Self::new(OpCode::Close, payload)
+    }
+
+    /**
+        Parses the status code and reason from the payload of a close frame.
+
+        Close frames without a status code are reported as
+        `1005` (no status received), as defined by RFC 6455.
+    */
+    pub fn close_reason(&self) -> (u16, String) {
+        match self.payload.as_slice() {
+            [hi, lo, reason @ ..] => (
+                u16::from_be_bytes([*hi, *lo]),
+                String::from_utf8_lossy(reason).into_owned(),
+            ),
+            _ => (1005, String::new()),
+        }
+    }
+
+    /**
+        Reads a single frame from the given stream.
+
+        # Errors
+
+        Errors if the stream fails or if the frame is malformed.
+    */
+    pub async fn read<R: AsyncRead + Unpin>(stream: &mut R) -> Result<Self> {
+        let mut header = [0u8; 2];
+        stream.read_exact(&mut header).await?;
+
+        let fin = header[0] & 0x80 != 0;
+        let opcode = OpCode::from_u8(header[0] & 0x0F)?;
+        let masked = header[1] & 0x80 != 0;
+
+        let len = match header[1] & 0x7F {
+            126 => {
+                let mut ext = [0u8; 2];
+                stream.read_exact(&mut ext).await?;
+                u64::from(u16::from_be_bytes(ext))
+            }
+            127 => {
+                let mut ext = [0u8; 8];
+                stream.read_exact(&mut ext).await?;
+                u64::from_be_bytes(ext)
+            }
+            len => u64::from(len),
+        };
+        if len > MAX_PAYLOAD_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("websocket frame of {len} bytes exceeds the maximum size"),
+            ));
+        }
+
+        let mut mask = [0u8; 4];
+        if masked {
+            stream.read_exact(&mut mask).await?;
+        }
+
+        let mut payload = vec![0u8; usize::try_from(len).expect("payload length fits in usize")];
+        stream.read_exact(&mut payload).await?;
+        if masked {
+            apply_mask(&mut payload, mask);
+        }
+
+        Ok(Self {
+            fin,
+            opcode,
+            payload,
+        })
+    }
+
+    /**
+        Writes this frame to the given stream.
+
+        Frames sent by clients must be masked, and frames sent by servers must not be.
+
+        # Errors
+
+        Errors if the stream fails or if no random mask could be generated.
+    */
+    pub async fn write<W: AsyncWrite + Unpin>(&self, stream: &mut W, masked: bool) -> Result<()> {
+        let mut bytes = Vec::with_capacity(self.payload.len() + 14);
+        bytes.push(u8::from(self.fin) << 7 | self.opcode.as_u8());
+
+        let mask_bit = if masked { 0x80 } else { 0 };
+        let len = self.payload.len();
+        if len < 126 {
+            bytes.push(mask_bit | u8::try_from(len).expect("checked above"));
+        } else if let Ok(len) = u16::try_from(len) {
+            bytes.push(mask_bit | 0x7E);
+            bytes.extend_from_slice(&len.to_be_bytes());
+        } else {
+            bytes.push(mask_bit | 0x7F);
+            bytes.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+
+        if masked {
+            let mut mask = [0u8; 4];
+            getrandom::fill(&mut mask).map_err(|e| Error::other(e.to_string()))?;
+            bytes.extend_from_slice(&mask);
+            let start = bytes.len();
+            bytes.extend_from_slice(&self.payload);
+            apply_mask(&mut bytes[start..], mask);
+        } else {
+            bytes.extend_from_slice(&self.payload);
+        }
+
+        stream.write_all(&bytes).await?;
+        stream.flush().await
+    }
+}
+
+fn apply_mask(bytes: &mut [u8], mask: [u8; 4]) {
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte ^= mask[index % 4];
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{Error, ErrorKind, Result},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use async_io::Async;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use futures_lite::prelude::*;
+use sha1::{Digest, Sha1};
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_RESPONSE_LEN: usize = 16 * 1024;
+
+/**
+    The parts of a `ws://` url needed to open a connection.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSocketUrl {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl WebSocketUrl {
+    /**
+        Parses a websocket url such as `ws://localhost:8080/chat`.
+
+        # Errors
+
+        Errors if the url is malformed, or uses a scheme other than `ws`.
+    */
+    pub fn parse(url: &str) -> Result<Self> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidInput, format!("{msg}: '{url}'"));
+
+        let Some(rest) = url.strip_prefix("ws://") else {
+            return Err(if url.starts_with("wss://") {
+                // FUTURE: Support TLS connections
+                invalid("Secure websocket urls (wss://) are not supported yet")
+            } else {
+                invalid("Websocket url must start with 'ws://'")
+            });
+        };
+
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let path = if path.starts_with('?') {
+            format!("/{path}")
+        } else {
+            path.to_string()
+        };
+
+        let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+            // IPv6 literal, such as [::1]:8080
+            let (host, rest) = bracketed
+                .split_once(']')
+                .ok_or_else(|| invalid("Invalid IPv6 host in websocket url"))?;
+            (host, rest.strip_prefix(':'))
+        } else {
+            match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            }
+        };
+        if host.is_empty() {
+            return Err(invalid("Websocket url is missing a host"));
+        }
+        let port = match port {
+            Some(port) => port
+                .parse()
+                .map_err(|_| invalid("Invalid port in websocket url"))?,
+            None => 80,
+        };
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    fn host_header(&self) -> String {
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        if self.port == 80 {
+            host
+        } else {
+            format!("{host}:{}", self.port)
+        }
+    }
+}
+
+/**
+    Connects to the given url and performs the opening handshake.
+
+    # Errors
+
+    Errors if the connection fails, or if the server rejects the upgrade.
+*/
+pub async fn connect(
+    url: &WebSocketUrl,
+    headers: &HashMap<String, String>,
+) -> Result<Async<TcpStream>> {
+    let target = (url.host.clone(), url.port);
+    let addrs = blocking::unblock(move || target.to_socket_addrs().map(Iterator::collect)).await;
+    let addrs: Vec<_> = addrs?;
+
+    let mut last_error = None;
+    let mut stream = None;
+    for addr in addrs {
+        match Async::<TcpStream>::connect(addr).await {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    let mut stream = stream.ok_or_else(|| {
+        last_error.unwrap_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Failed to resolve host '{}'", url.host),
+            )
+        })
+    })?;
+    stream.get_ref().set_nodelay(true)?;
+
+    let mut key_bytes = [0u8; 16];
+    getrandom::fill(&mut key_bytes).map_err(|e| Error::other(e.to_string()))?;
+    let key = STANDARD.encode(key_bytes);
+
+    let mut request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n",
+        url.path,
+        url.host_header()
+    );
+    for (name, value) in headers {
+        let _ = write!(request, "{name}: {value}\r\n");
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    let response = read_response_head(&mut stream).await?;
+    verify_response(&response, &key)?;
+
+    Ok(stream)
+}
+
+/**
+    Reads the response head byte-by-byte, so that we never
+    consume any frames the server sends right after it.
+*/
+async fn read_response_head(stream: &mut Async<TcpStream>) -> Result<String> {
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Websocket handshake response is too large",
+            ));
+        }
+        stream.read_exact(&mut byte).await?;
+        head.push(byte[0]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn verify_response(response: &str, key: &str) -> Result<()> {
+    let mut lines = response.lines();
+    let status = lines.next().unwrap_or_default();
+    let code = status.split_whitespace().nth(1).unwrap_or_default();
+    if code != "101" {
+        return Err(Error::new(
+            ErrorKind::ConnectionRefused,
+            format!("Websocket handshake failed, server responded with '{status}'"),
+        ));
+    }
+
+    let expected = accept_key(key);
+    let accepted = lines
+        .filter_map(|line| line.split_once(':'))
+        .any(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("sec-websocket-accept") && value.trim() == expected
+        });
+    if !accepted {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Websocket handshake failed, server sent an invalid accept key",
+        ));
+    }
+
+    Ok(())
+}
+
+/**
+    Computes the `Sec-WebSocket-Accept` header a server must
+    respond with for the given `Sec-WebSocket-Key` header.
+*/
+#[must_use]
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.trim().as_bytes());
+    hasher.update(GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
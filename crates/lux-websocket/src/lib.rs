@@ -0,0 +1,74 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use std::collections::HashMap;
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod frame;
+mod handshake;
+mod socket;
+
+pub use self::handshake::{WebSocketUrl, accept_key};
+pub use self::socket::WebSocket;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `websocket` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `websocket` standard library module.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_async_function("connect", websocket_connect)?
+        .build_readonly()
+}
+
+#[derive(Debug, Default)]
+struct ConnectOptions {
+    headers: HashMap<String, String>,
+}
+
+impl FromLua for ConnectOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                headers: t
+                    .get::<Option<HashMap<String, String>>>("headers")?
+                    .unwrap_or_default(),
+            }),
+            _ => Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "ConnectOptions".to_string(),
+                message: Some(format!(
+                    "Invalid connect options - expected table or nil, got {}",
+                    value.type_name()
+                )),
+            }),
+        }
+    }
+}
+
+async fn websocket_connect(
+    lua: Lua,
+    (url, options): (String, ConnectOptions),
+) -> LuaResult<WebSocket> {
+    let url = WebSocketUrl::parse(&url).into_lua_err()?;
+    let stream = handshake::connect(&url, &options.headers)
+        .await
+        .into_lua_err()?;
+    Ok(WebSocket::new(&lua, stream))
+}
@@ -0,0 +1,244 @@
+use std::{
+    net::{Shutdown, TcpStream},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_io::{Async, Timer};
+use async_lock::Mutex as AsyncMutex;
+use bstr::BString;
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use lux_signal::Signal;
+
+use super::frame::{Frame, OpCode};
+
+/**
+    How long to wait for the server to acknowledge our close
+    frame before giving up and closing the connection anyway.
+*/
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+struct State {
+    close_sent: bool,
+    close_code: Option<u16>,
+}
+
+/**
+    A connected websocket, either a client or a connection accepted by a server.
+
+    Incoming messages are read by a background task on the
+    scheduler, so any number of sockets may be serviced at once.
+*/
+#[derive(Clone)]
+pub struct WebSocket {
+    stream: Arc<Async<TcpStream>>,
+    /// Whether this is the client end, which must mask the frames it sends
+    masked: bool,
+    write_lock: Arc<AsyncMutex<()>>,
+    state: Arc<parking_lot::Mutex<State>>,
+    message_received: Signal,
+    closed: Signal,
+}
+
+impl WebSocket {
+    /**
+        Creates the client end of a websocket, from a stream that finished the opening handshake.
+    */
+    #[must_use]
+    pub fn new(lua: &Lua, stream: Async<TcpStream>) -> Self {
+        Self::with_role(lua, stream, true)
+    }
+
+    /**
+        Creates the server end of a websocket, from a stream that the
+        server has sent its `101 Switching Protocols` response on.
+    */
+    #[must_use]
+    pub fn new_server(lua: &Lua, stream: Async<TcpStream>) -> Self {
+        Self::with_role(lua, stream, false)
+    }
+
+    fn with_role(lua: &Lua, stream: Async<TcpStream>, masked: bool) -> Self {
+        let socket = Self {
+            stream: Arc::new(stream),
+            masked,
+            write_lock: Arc::new(AsyncMutex::new(())),
+            state: Arc::new(parking_lot::Mutex::new(State::default())),
+            message_received: Signal::new(),
+            closed: Signal::new(),
+        };
+        lua.spawn_local(read_messages(lua.clone(), socket.clone()));
+        socket
+    }
+
+    async fn send_frame(&self, frame: Frame) -> LuaResult<()> {
+        let _guard = self.write_lock.lock().await;
+        frame
+            .write(&mut &*self.stream, self.masked)
+            .await
+            .into_lua_err()
+    }
+
+    fn ensure_open(&self) -> LuaResult<()> {
+        let state = self.state.lock();
+        if state.close_sent || state.close_code.is_some() {
+            Err(LuaError::runtime("WebSocket is closed"))
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn send(&self, data: LuaValue) -> LuaResult<()> {
+        let frame = match data {
+            LuaValue::String(s) => Frame::new(OpCode::Text, s.as_bytes().to_vec()),
+            LuaValue::Buffer(b) => Frame::new(OpCode::Binary, b.to_vec()),
+            other => {
+                return Err(LuaError::runtime(format!(
+                    "Expected message to be a string or buffer, got '{}'",
+                    other.type_name()
+                )));
+            }
+        };
+        self.ensure_open()?;
+        self.send_frame(frame).await
+    }
+
+    async fn close(&self, lua: &Lua, code: u16, reason: String) -> LuaResult<()> {
+        {
+            let mut state = self.state.lock();
+            if state.close_sent || state.close_code.is_some() {
+                return Ok(());
+            }
+            state.close_sent = true;
+        }
+        self.send_frame(Frame::close(code, &reason)).await?;
+
+        // NOTE: The other end should respond with its own close frame, which ends
+        // the read task, but if it never does we must not keep running forever
+        let stream = Arc::clone(&self.stream);
+        lua.spawn(async move {
+            Timer::after(CLOSE_TIMEOUT).await;
+            let _ = stream.get_ref().shutdown(Shutdown::Both);
+        })
+        .detach();
+
+        Ok(())
+    }
+}
+
+impl LuaUserData for WebSocket {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "WebSocket");
+        fields.add_field_method_get("MessageReceived", |_, this| {
+            Ok(this.message_received.clone())
+        });
+        fields.add_field_method_get("Closed", |_, this| Ok(this.closed.clone()));
+        fields.add_field_method_get("closeCode", |_, this| Ok(this.state.lock().close_code));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("send", |_, this, data: LuaValue| {
+            let this = this.clone();
+            async move { this.send(data).await }
+        });
+        methods.add_async_method("ping", |_, this, data: Option<BString>| {
+            let this = this.clone();
+            async move {
+                this.ensure_open()?;
+                let payload = data.map(Vec::from).unwrap_or_default();
+                if payload.len() > 125 {
+                    return Err(LuaError::runtime(
+                        "Ping payload must be at most 125 bytes long",
+                    ));
+                }
+                this.send_frame(Frame::new(OpCode::Ping, payload)).await
+            }
+        });
+        methods.add_async_method(
+            "close",
+            |lua, this, (code, reason): (Option<u16>, Option<String>)| {
+                let this = this.clone();
+                async move {
+                    let code = code.unwrap_or(1000);
+                    let reason = reason.unwrap_or_default();
+                    if reason.len() > 123 {
+                        return Err(LuaError::runtime(
+                            "Close reason must be at most 123 bytes long",
+                        ));
+                    }
+                    this.close(&lua, code, reason).await
+                }
+            },
+        );
+    }
+}
+
+/**
+    Reads frames until the connection closes, answering pings, reassembling
+    fragmented messages and firing `MessageReceived` for each message.
+*/
+async fn read_messages(lua: Lua, socket: WebSocket) {
+    let mut reader = &*socket.stream;
+    let mut partial: Option<(OpCode, Vec<u8>)> = None;
+
+    let (code, reason) = loop {
+        let Ok(frame) = Frame::read(&mut reader).await else {
+            // NOTE: 1006 is reserved for connections that closed without a close frame
+            break (1006, String::new());
+        };
+        match frame.opcode {
+            OpCode::Ping => {
+                let _ = socket
+                    .send_frame(Frame::new(OpCode::Pong, frame.payload))
+                    .await;
+            }
+            OpCode::Pong => {}
+            OpCode::Close => {
+                let close_sent = std::mem::replace(&mut socket.state.lock().close_sent, true);
+                if !close_sent {
+                    // Echo the status code back, as required by the closing handshake
+                    let payload = frame.payload.get(..2).unwrap_or_default().to_vec();
+                    let _ = socket.send_frame(Frame::new(OpCode::Close, payload)).await;
+                }
+                break frame.close_reason();
+            }
+            OpCode::Text | OpCode::Binary if frame.fin => {
+                deliver(&lua, &socket, frame.opcode, frame.payload);
+            }
+            OpCode::Text | OpCode::Binary => partial = Some((frame.opcode, frame.payload)),
+            OpCode::Continuation => {
+                let Some((_, buffered)) = partial.as_mut() else {
+                    break (1002, "unexpected continuation frame".to_string());
+                };
+                buffered.extend_from_slice(&frame.payload);
+                if frame.fin {
+                    let (opcode, payload) = partial.take().expect("checked above");
+                    deliver(&lua, &socket, opcode, payload);
+                }
+            }
+        }
+    };
+
+    let _ = socket.stream.get_ref().shutdown(Shutdown::Both);
+    socket.state.lock().close_code = Some(code);
+
+    let args = (code, reason).into_lua_multi(&lua);
+    if let Ok(args) = args {
+        socket.closed.fire_native(&lua, args);
+    }
+}
+
+fn deliver(lua: &Lua, socket: &WebSocket, opcode: OpCode, payload: Vec<u8>) {
+    let message = if opcode == OpCode::Text {
+        lua.create_string(payload).map(LuaValue::String)
+    } else {
+        lua.create_buffer(payload).map(LuaValue::Buffer)
+    };
+    if let Ok(args) = message.and_then(|m| m.into_lua_multi(lua)) {
+        socket.message_received.fire_native(lua, args);
+    }
+}
@@ -0,0 +1,252 @@
+use std::io;
+use std::sync::Arc;
+
+use mlua::prelude::*;
+use parking_lot::Mutex;
+
+use crate::os::{Hive, Key, ValueKind};
+use crate::value::{decode_string, encode_string, value_to_lua};
+use crate::watch::create_changed_signal;
+
+/**
+    Options for `winreg.open` and `RegistryKey:openKey`.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct OpenOptions {
+    pub(crate) write: bool,
+    pub(crate) create: bool,
+}
+
+impl FromLua for OpenOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => {
+                let create = t.get::<Option<bool>>("create")?.unwrap_or_default();
+                // Creating a key only makes sense if it can then be written to
+                let write = t.get::<Option<bool>>("write")?.unwrap_or(create);
+                Ok(Self { write, create })
+            }
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("OpenOptions"),
+                message: Some(String::from("Expected options to be a table")),
+            }),
+        }
+    }
+}
+
+/**
+    Options for `RegistryKey:watch`.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+struct WatchOptions {
+    subtree: bool,
+}
+
+impl FromLua for WatchOptions {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        match value {
+            LuaValue::Nil => Ok(Self::default()),
+            LuaValue::Table(t) => Ok(Self {
+                subtree: t.get::<Option<bool>>("subtree")?.unwrap_or_default(),
+            }),
+            other => Err(LuaError::FromLuaConversionError {
+                from: other.type_name(),
+                to: String::from("WatchOptions"),
+                message: Some(String::from("Expected options to be a table")),
+            }),
+        }
+    }
+}
+
+/**
+    An open registry key.
+
+    The key is closed once it is garbage collected, or explicitly using `close`.
+    Signals created by `watch` keep the key open for as long as they exist.
+*/
+#[derive(Clone)]
+pub(crate) struct RegistryKey {
+    path: String,
+    key: Arc<Mutex<Option<Arc<Key>>>>,
+}
+
+impl RegistryKey {
+    pub(crate) fn open(hive: Hive, path: &str, options: OpenOptions) -> LuaResult<Self> {
+        let full_path = join_path(hive.name(), path);
+        let key = Key::open(hive, path, options.write, options.create)
+            .map_err(|e| open_error(&full_path, &e))?;
+        Ok(Self::new(full_path, key))
+    }
+
+    fn new(path: String, key: Key) -> Self {
+        Self {
+            path,
+            key: Arc::new(Mutex::new(Some(Arc::new(key)))),
+        }
+    }
+
+    fn key(&self) -> LuaResult<Arc<Key>> {
+        self.key
+            .lock()
+            .clone()
+            .ok_or_else(|| LuaError::runtime(format!("Registry key '{}' is closed", self.path)))
+    }
+
+    fn open_key(&self, path: &str, options: OpenOptions) -> LuaResult<Self> {
+        let full_path = join_path(&self.path, path);
+        let key = self
+            .key()?
+            .open_subkey(path, options.write, options.create)
+            .map_err(|e| open_error(&full_path, &e))?;
+        Ok(Self::new(full_path, key))
+    }
+
+    /**
+        Reads the raw data of a value, or `None` if it does not exist.
+    */
+    fn get_raw(&self, name: &str) -> LuaResult<Option<(ValueKind, Vec<u8>)>> {
+        match self.key()?.get(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(self.value_error("read", name, &e)),
+        }
+    }
+
+    fn set_raw(&self, name: &str, kind: ValueKind, data: &[u8]) -> LuaResult<()> {
+        self.key()?
+            .set(name, kind, data)
+            .map_err(|e| self.value_error("write", name, &e))
+    }
+
+    fn value_error(&self, action: &str, name: &str, err: &io::Error) -> LuaError {
+        LuaError::runtime(format!(
+            "Failed to {action} value '{name}' of registry key '{}': {err}",
+            self.path
+        ))
+    }
+
+    fn kind_error(&self, name: &str, expected: &str, kind: ValueKind) -> LuaError {
+        LuaError::runtime(format!(
+            "Value '{name}' of registry key '{}' is a {kind} value, not a {expected} value",
+            self.path
+        ))
+    }
+}
+
+fn join_path(parent: &str, path: &str) -> String {
+    let path = path.trim_matches('\\');
+    if path.is_empty() {
+        parent.to_string()
+    } else {
+        format!("{parent}\\{path}")
+    }
+}
+
+fn open_error(path: &str, err: &io::Error) -> LuaError {
+    LuaError::runtime(format!("Failed to open registry key '{path}': {err}"))
+}
+
+impl LuaUserData for RegistryKey {
+    fn add_fields<F: LuaUserDataFields<Self>>(fields: &mut F) {
+        fields.add_meta_field(LuaMetaMethod::Type, "RegistryKey");
+        fields.add_field_method_get("path", |_, this| Ok(this.path.clone()));
+    }
+
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method(
+            "openKey",
+            |_, this, (path, options): (String, OpenOptions)| this.open_key(&path, options),
+        );
+
+        methods.add_method("get", |lua, this, name: String| {
+            match this.get_raw(&name)? {
+                Some((kind, data)) => {
+                    (value_to_lua(lua, kind, &data)?, kind.name()).into_lua_multi(lua)
+                }
+                None => LuaValue::Nil.into_lua_multi(lua),
+            }
+        });
+        methods.add_method("getString", |_, this, name: String| {
+            match this.get_raw(&name)? {
+                Some((ValueKind::String | ValueKind::ExpandString, data)) => {
+                    Ok(Some(decode_string(&data)))
+                }
+                Some((kind, _)) => Err(this.kind_error(&name, "String", kind)),
+                None => Ok(None),
+            }
+        });
+        methods.add_method("getDword", |_, this, name: String| {
+            match this.get_raw(&name)? {
+                Some((ValueKind::Dword, data)) if data.len() == 4 => {
+                    Ok(Some(u32::from_le_bytes([
+                        data[0], data[1], data[2], data[3],
+                    ])))
+                }
+                Some((kind, _)) => Err(this.kind_error(&name, "Dword", kind)),
+                None => Ok(None),
+            }
+        });
+        methods.add_method("getBinary", |lua, this, name: String| {
+            match this.get_raw(&name)? {
+                Some((_, data)) => lua.create_buffer(data).map(Some),
+                None => Ok(None),
+            }
+        });
+
+        methods.add_method("setString", |_, this, (name, value): (String, String)| {
+            this.set_raw(&name, ValueKind::String, &encode_string(&value))
+        });
+        methods.add_method("setDword", |_, this, (name, value): (String, u32)| {
+            this.set_raw(&name, ValueKind::Dword, &value.to_le_bytes())
+        });
+        methods.add_method("setBinary", |_, this, (name, value): (String, LuaValue)| {
+            let data = match value {
+                LuaValue::Buffer(buf) => buf.to_vec(),
+                LuaValue::String(s) => s.as_bytes().to_vec(),
+                other => {
+                    return Err(LuaError::runtime(format!(
+                        "Expected value to be a buffer or string, got {}",
+                        other.type_name()
+                    )));
+                }
+            };
+            this.set_raw(&name, ValueKind::Binary, &data)
+        });
+        methods.add_method("deleteValue", |_, this, name: String| {
+            match this.key()?.delete(&name) {
+                Ok(()) => Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(e) => Err(this.value_error("delete", &name, &e)),
+            }
+        });
+
+        methods.add_method("keys", |_, this, (): ()| {
+            this.key()?.keys().map_err(|e| {
+                LuaError::runtime(format!(
+                    "Failed to list subkeys of registry key '{}': {e}",
+                    this.path
+                ))
+            })
+        });
+        methods.add_method("values", |_, this, (): ()| {
+            this.key()?.values().map_err(|e| {
+                LuaError::runtime(format!(
+                    "Failed to list values of registry key '{}': {e}",
+                    this.path
+                ))
+            })
+        });
+
+        methods.add_method("watch", |_, this, options: WatchOptions| {
+            create_changed_signal(this.key()?, options.subtree).map_err(|e| {
+                LuaError::runtime(format!("Failed to watch registry key '{}': {e}", this.path))
+            })
+        });
+        methods.add_method("close", |_, this, (): ()| {
+            this.key.lock().take();
+            Ok(())
+        });
+    }
+}
@@ -0,0 +1,46 @@
+#![allow(clippy::cargo_common_metadata)]
+
+use mlua::prelude::*;
+
+use lux_utils::TableBuilder;
+
+mod key;
+mod os;
+mod value;
+mod watch;
+
+use self::key::{OpenOptions, RegistryKey};
+use self::os::Hive;
+
+const TYPEDEFS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/types.d.luau"));
+
+/**
+    Returns a string containing type definitions for the `winreg` standard library.
+*/
+#[must_use]
+pub fn typedefs() -> String {
+    TYPEDEFS.to_string()
+}
+
+/**
+    Creates the `winreg` standard library module.
+
+    The module exists on every platform, but opening a key
+    errors on anything other than Windows.
+
+    # Errors
+
+    Errors when out of memory.
+*/
+pub fn module(lua: Lua) -> LuaResult<LuaTable> {
+    TableBuilder::new(lua)?
+        .with_function("open", winreg_open)?
+        .build_readonly()
+}
+
+fn winreg_open(
+    _: &Lua,
+    (hive, path, options): (Hive, String, OpenOptions),
+) -> LuaResult<RegistryKey> {
+    RegistryKey::open(hive, &path, options)
+}
@@ -0,0 +1,437 @@
+use std::fmt;
+
+use mlua::prelude::*;
+
+pub(crate) use self::imp::{Key, Watcher};
+
+/**
+    One of the predefined root keys of the registry.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Hive {
+    ClassesRoot,
+    CurrentUser,
+    LocalMachine,
+    Users,
+    CurrentConfig,
+}
+
+impl Hive {
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::ClassesRoot => "HKEY_CLASSES_ROOT",
+            Self::CurrentUser => "HKEY_CURRENT_USER",
+            Self::LocalMachine => "HKEY_LOCAL_MACHINE",
+            Self::Users => "HKEY_USERS",
+            Self::CurrentConfig => "HKEY_CURRENT_CONFIG",
+        }
+    }
+}
+
+impl fmt::Display for Hive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromLua for Hive {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let LuaValue::String(s) = &value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: String::from("Hive"),
+                message: Some(String::from("Expected hive to be a string")),
+            });
+        };
+        match s.to_str()?.to_ascii_uppercase().as_str() {
+            "HKCR" | "HKEY_CLASSES_ROOT" => Ok(Self::ClassesRoot),
+            "HKCU" | "HKEY_CURRENT_USER" => Ok(Self::CurrentUser),
+            "HKLM" | "HKEY_LOCAL_MACHINE" => Ok(Self::LocalMachine),
+            "HKU" | "HKEY_USERS" => Ok(Self::Users),
+            "HKCC" | "HKEY_CURRENT_CONFIG" => Ok(Self::CurrentConfig),
+            other => Err(LuaError::FromLuaConversionError {
+                from: "string",
+                to: String::from("Hive"),
+                message: Some(format!(
+                    "Unknown hive '{other}', expected one of \
+                    'HKCR', 'HKCU', 'HKLM', 'HKU' or 'HKCC'"
+                )),
+            }),
+        }
+    }
+}
+
+/**
+    The type of data stored in a registry value.
+
+    The raw numbers are the `REG_*` constants, which are the same on every platform.
+*/
+// NOTE: Values are only ever read on Windows
+#[cfg_attr(not(windows), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueKind {
+    None,
+    String,
+    ExpandString,
+    Binary,
+    Dword,
+    DwordBigEndian,
+    Link,
+    MultiString,
+    Qword,
+    Other(u32),
+}
+
+impl ValueKind {
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) const fn from_raw(raw: u32) -> Self {
+        match raw {
+            0 => Self::None,
+            1 => Self::String,
+            2 => Self::ExpandString,
+            3 => Self::Binary,
+            4 => Self::Dword,
+            5 => Self::DwordBigEndian,
+            6 => Self::Link,
+            7 => Self::MultiString,
+            11 => Self::Qword,
+            other => Self::Other(other),
+        }
+    }
+
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub(crate) const fn to_raw(self) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::String => 1,
+            Self::ExpandString => 2,
+            Self::Binary => 3,
+            Self::Dword => 4,
+            Self::DwordBigEndian => 5,
+            Self::Link => 6,
+            Self::MultiString => 7,
+            Self::Qword => 11,
+            Self::Other(raw) => raw,
+        }
+    }
+
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::String => "String",
+            Self::ExpandString => "ExpandString",
+            Self::Binary => "Binary",
+            Self::Dword => "Dword",
+            Self::DwordBigEndian => "DwordBigEndian",
+            Self::Link => "Link",
+            Self::MultiString => "MultiString",
+            Self::Qword => "Qword",
+            Self::Other(_) => "Unknown",
+        }
+    }
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::iter;
+    use std::ptr;
+    use std::time::Duration;
+
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, ERROR_MORE_DATA, ERROR_NO_MORE_ITEMS, ERROR_SUCCESS, HANDLE, WAIT_OBJECT_0,
+        WIN32_ERROR,
+    };
+    use windows_sys::Win32::System::Registry::{
+        HKEY, HKEY_CLASSES_ROOT, HKEY_CURRENT_CONFIG, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE,
+        HKEY_USERS, KEY_READ, KEY_WRITE, REG_NOTIFY_CHANGE_LAST_SET, REG_NOTIFY_CHANGE_NAME,
+        REG_NOTIFY_THREAD_AGNOSTIC, REG_OPTION_NON_VOLATILE, RegCloseKey, RegCreateKeyExW,
+        RegDeleteValueW, RegEnumKeyExW, RegEnumValueW, RegNotifyChangeKeyValue, RegOpenKeyExW,
+        RegQueryValueExW, RegSetValueExW,
+    };
+    use windows_sys::Win32::System::Threading::{CreateEventW, WaitForSingleObject};
+
+    use super::{Hive, ValueKind};
+
+    fn check(code: WIN32_ERROR) -> io::Result<()> {
+        if code == ERROR_SUCCESS {
+            Ok(())
+        } else {
+            Err(io::Error::from_raw_os_error(code as i32))
+        }
+    }
+
+    fn wide(s: &str) -> Vec<u16> {
+        s.encode_utf16().chain(iter::once(0)).collect()
+    }
+
+    const fn root(hive: Hive) -> HKEY {
+        match hive {
+            Hive::ClassesRoot => HKEY_CLASSES_ROOT,
+            Hive::CurrentUser => HKEY_CURRENT_USER,
+            Hive::LocalMachine => HKEY_LOCAL_MACHINE,
+            Hive::Users => HKEY_USERS,
+            Hive::CurrentConfig => HKEY_CURRENT_CONFIG,
+        }
+    }
+
+    pub struct Key {
+        handle: HKEY,
+    }
+
+    // Registry key handles are kernel handles, usable from any thread
+    unsafe impl Send for Key {}
+    unsafe impl Sync for Key {}
+
+    impl Key {
+        pub fn open(hive: Hive, path: &str, write: bool, create: bool) -> io::Result<Self> {
+            Self::open_from(root(hive), path, write, create)
+        }
+
+        pub fn open_subkey(&self, path: &str, write: bool, create: bool) -> io::Result<Self> {
+            Self::open_from(self.handle, path, write, create)
+        }
+
+        fn open_from(parent: HKEY, path: &str, write: bool, create: bool) -> io::Result<Self> {
+            let access = if write {
+                KEY_READ | KEY_WRITE
+            } else {
+                KEY_READ
+            };
+            let path = wide(path);
+            let mut handle: HKEY = ptr::null_mut();
+            let code = if create {
+                unsafe {
+                    RegCreateKeyExW(
+                        parent,
+                        path.as_ptr(),
+                        0,
+                        ptr::null(),
+                        REG_OPTION_NON_VOLATILE,
+                        access,
+                        ptr::null(),
+                        &raw mut handle,
+                        ptr::null_mut(),
+                    )
+                }
+            } else {
+                unsafe { RegOpenKeyExW(parent, path.as_ptr(), 0, access, &raw mut handle) }
+            };
+            check(code)?;
+            Ok(Self { handle })
+        }
+
+        pub fn get(&self, name: &str) -> io::Result<(ValueKind, Vec<u8>)> {
+            let name = wide(name);
+            let mut data = vec![0u8; 256];
+            loop {
+                let mut kind = 0;
+                let mut len = data.len() as u32;
+                let code = unsafe {
+                    RegQueryValueExW(
+                        self.handle,
+                        name.as_ptr(),
+                        ptr::null(),
+                        &raw mut kind,
+                        data.as_mut_ptr(),
+                        &raw mut len,
+                    )
+                };
+                // NOTE: The value may also grow between calls, so keep trying until it fits
+                if code == ERROR_MORE_DATA {
+                    data.resize(len as usize, 0);
+                    continue;
+                }
+                check(code)?;
+                data.truncate(len as usize);
+                return Ok((ValueKind::from_raw(kind), data));
+            }
+        }
+
+        pub fn set(&self, name: &str, kind: ValueKind, data: &[u8]) -> io::Result<()> {
+            let name = wide(name);
+            let len = u32::try_from(data.len())
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "value is too large"))?;
+            check(unsafe {
+                RegSetValueExW(
+                    self.handle,
+                    name.as_ptr(),
+                    0,
+                    kind.to_raw(),
+                    data.as_ptr(),
+                    len,
+                )
+            })
+        }
+
+        pub fn delete(&self, name: &str) -> io::Result<()> {
+            let name = wide(name);
+            check(unsafe { RegDeleteValueW(self.handle, name.as_ptr()) })
+        }
+
+        pub fn keys(&self) -> io::Result<Vec<String>> {
+            // Key names are limited to 255 characters
+            let mut name = [0u16; 256];
+            let mut keys = Vec::new();
+            for index in 0.. {
+                let mut len = name.len() as u32;
+                let code = unsafe {
+                    RegEnumKeyExW(
+                        self.handle,
+                        index,
+                        name.as_mut_ptr(),
+                        &raw mut len,
+                        ptr::null(),
+                        ptr::null_mut(),
+                        ptr::null_mut(),
+                        ptr::null_mut(),
+                    )
+                };
+                if code == ERROR_NO_MORE_ITEMS {
+                    break;
+                }
+                check(code)?;
+                keys.push(String::from_utf16_lossy(&name[..len as usize]));
+            }
+            Ok(keys)
+        }
+
+        pub fn values(&self) -> io::Result<Vec<String>> {
+            // Value names are limited to 16383 characters
+            let mut name = vec![0u16; 16384];
+            let mut values = Vec::new();
+            for index in 0.. {
+                let mut len = name.len() as u32;
+                let code = unsafe {
+                    RegEnumValueW(
+                        self.handle,
+                        index,
+                        name.as_mut_ptr(),
+                        &raw mut len,
+                        ptr::null(),
+                        ptr::null_mut(),
+                        ptr::null_mut(),
+                        ptr::null_mut(),
+                    )
+                };
+                if code == ERROR_NO_MORE_ITEMS {
+                    break;
+                }
+                check(code)?;
+                values.push(String::from_utf16_lossy(&name[..len as usize]));
+            }
+            Ok(values)
+        }
+    }
+
+    impl Drop for Key {
+        fn drop(&mut self) {
+            unsafe { RegCloseKey(self.handle) };
+        }
+    }
+
+    pub struct Watcher {
+        event: HANDLE,
+    }
+
+    // The event is a kernel handle, usable from any thread
+    unsafe impl Send for Watcher {}
+    unsafe impl Sync for Watcher {}
+
+    impl Watcher {
+        pub fn new() -> io::Result<Self> {
+            let event = unsafe { CreateEventW(ptr::null(), 0, 0, ptr::null()) };
+            if event.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { event })
+        }
+
+        /**
+            Requests the next change to the key, and its subkeys if `subtree`
+            is set, to be reported. Must be called again after every change.
+        */
+        pub fn arm(&self, key: &Key, subtree: bool) -> io::Result<()> {
+            let filter =
+                REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET | REG_NOTIFY_THREAD_AGNOSTIC;
+            check(unsafe {
+                RegNotifyChangeKeyValue(key.handle, i32::from(subtree), filter, self.event, 1)
+            })
+        }
+
+        /**
+            Waits for a requested change for at most `timeout`, returning whether one happened.
+        */
+        pub fn wait(&self, timeout: Duration) -> bool {
+            let millis = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+            unsafe { WaitForSingleObject(self.event, millis) == WAIT_OBJECT_0 }
+        }
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.event) };
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::io;
+    use std::time::Duration;
+
+    use super::{Hive, ValueKind};
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the registry is only available on Windows",
+        )
+    }
+
+    pub enum Key {}
+
+    impl Key {
+        pub fn open(_hive: Hive, _path: &str, _write: bool, _create: bool) -> io::Result<Self> {
+            Err(unsupported())
+        }
+        pub fn open_subkey(&self, _path: &str, _write: bool, _create: bool) -> io::Result<Self> {
+            match *self {}
+        }
+        pub fn get(&self, _name: &str) -> io::Result<(ValueKind, Vec<u8>)> {
+            match *self {}
+        }
+        pub fn set(&self, _name: &str, _kind: ValueKind, _data: &[u8]) -> io::Result<()> {
+            match *self {}
+        }
+        pub fn delete(&self, _name: &str) -> io::Result<()> {
+            match *self {}
+        }
+        pub fn keys(&self) -> io::Result<Vec<String>> {
+            match *self {}
+        }
+        pub fn values(&self) -> io::Result<Vec<String>> {
+            match *self {}
+        }
+    }
+
+    pub enum Watcher {}
+
+    impl Watcher {
+        pub fn new() -> io::Result<Self> {
+            Err(unsupported())
+        }
+        pub fn arm(&self, _key: &Key, _subtree: bool) -> io::Result<()> {
+            match *self {}
+        }
+        pub fn wait(&self, _timeout: Duration) -> bool {
+            match *self {}
+        }
+    }
+}
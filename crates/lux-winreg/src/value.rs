@@ -0,0 +1,72 @@
+use mlua::prelude::*;
+
+use crate::os::ValueKind;
+
+/**
+    Decodes a `String`, `ExpandString` or `Link` value, which
+    are stored as UTF-16 with an optional trailing nul.
+*/
+pub(crate) fn decode_string(data: &[u8]) -> String {
+    let mut units = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+    while units.last() == Some(&0) {
+        units.pop();
+    }
+    String::from_utf16_lossy(&units)
+}
+
+/**
+    Decodes a `MultiString` value, which is a list of nul-terminated
+    UTF-16 strings, terminated by an additional empty string.
+*/
+pub(crate) fn decode_multi_string(data: &[u8]) -> Vec<String> {
+    let units = data
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect::<Vec<_>>();
+    units
+        .split(|&unit| unit == 0)
+        .filter(|part| !part.is_empty())
+        .map(String::from_utf16_lossy)
+        .collect()
+}
+
+/**
+    Encodes a string as nul-terminated UTF-16, as stored in `String` values.
+*/
+pub(crate) fn encode_string(s: &str) -> Vec<u8> {
+    s.encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+/**
+    Converts the raw data of a value into the closest matching Luau value.
+
+    Strings become strings, lists of strings become arrays, numbers become
+    numbers, and anything else - including binary data - becomes a buffer.
+*/
+pub(crate) fn value_to_lua(lua: &Lua, kind: ValueKind, data: &[u8]) -> LuaResult<LuaValue> {
+    match kind {
+        ValueKind::String | ValueKind::ExpandString | ValueKind::Link => {
+            decode_string(data).into_lua(lua)
+        }
+        ValueKind::MultiString => decode_multi_string(data).into_lua(lua),
+        ValueKind::Dword if data.len() == 4 => {
+            u32::from_le_bytes([data[0], data[1], data[2], data[3]]).into_lua(lua)
+        }
+        ValueKind::DwordBigEndian if data.len() == 4 => {
+            u32::from_be_bytes([data[0], data[1], data[2], data[3]]).into_lua(lua)
+        }
+        ValueKind::Qword if data.len() == 8 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(data);
+            // NOTE: Luau numbers can not represent every 64-bit integer exactly
+            Ok(LuaValue::Number(u64::from_le_bytes(bytes) as f64))
+        }
+        _ => lua.create_buffer(data).map(LuaValue::Buffer),
+    }
+}
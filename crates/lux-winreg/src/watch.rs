@@ -0,0 +1,71 @@
+use std::{
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSpawnExt;
+
+use lux_signal::Signal;
+
+use crate::os::{Key, Watcher};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/**
+    Creates a signal that fires whenever the given key, or
+    any of its subkeys if `subtree` is set, changes.
+
+    Changes are only listened for while a handler is connected, so that an
+    unused signal never keeps the scheduler alive. Listening starts again once
+    a handler gets connected after the last one was disconnected.
+*/
+pub(crate) fn create_changed_signal(key: Arc<Key>, subtree: bool) -> std::io::Result<Signal> {
+    let watcher = Arc::new(Watcher::new()?);
+    let watching = Arc::new(AtomicBool::new(false));
+    Ok(Signal::new().with_connect_hook(Rc::new(move |lua, signal| {
+        if !watching.swap(true, Ordering::SeqCst) {
+            lua.spawn_local(watch(
+                lua.clone(),
+                signal.clone(),
+                Arc::clone(&key),
+                Arc::clone(&watcher),
+                subtree,
+                Arc::clone(&watching),
+            ));
+        }
+    })))
+}
+
+async fn watch(
+    lua: Lua,
+    signal: Signal,
+    key: Arc<Key>,
+    watcher: Arc<Watcher>,
+    subtree: bool,
+    watching: Arc<AtomicBool>,
+) {
+    while signal.count() > 0 {
+        // NOTE: Notifications are one-shot, and must be requested again after every change
+        if watcher.arm(&key, subtree).is_err() {
+            break;
+        }
+        let changed = loop {
+            let watcher = Arc::clone(&watcher);
+            if blocking::unblock(move || watcher.wait(POLL_INTERVAL)).await {
+                break true;
+            }
+            if signal.count() == 0 {
+                break false;
+            }
+        };
+        if changed {
+            signal.fire_native(&lua, LuaMultiValue::new());
+        }
+    }
+    watching.store(false, Ordering::SeqCst);
+}
@@ -0,0 +1,90 @@
+use std::{
+    io::{Write, stdout},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+use clap::Parser;
+use console::style;
+
+use lux_bindgen::{BindgenOptions, generate};
+
+/// Generate a Luau module with FFI bindings for a C header
+#[derive(Debug, Clone, Parser)]
+pub struct BindgenCommand {
+    /// The header to generate bindings for
+    pub header: PathBuf,
+
+    /// The library to load functions from, such as `sqlite3`,
+    /// defaults to looking them up in the lux process itself
+    #[clap(long)]
+    pub lib: Option<String>,
+
+    /// A directory to search for included headers in, may be given multiple times
+    #[clap(short = 'I', long = "include", value_name = "DIR")]
+    pub include_paths: Vec<PathBuf>,
+
+    /// A macro to define before preprocessing, may be given multiple times
+    #[clap(short = 'D', long = "define", value_name = "NAME[=VALUE]")]
+    pub defines: Vec<String>,
+
+    /// The prefix to remove from function names,
+    /// defaults to the prefix shared by all functions
+    #[clap(long)]
+    pub prefix: Option<String>,
+
+    /// The file to write the module to, instead of stdout
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl BindgenCommand {
+    pub async fn run(self) -> Result<ExitCode> {
+        let options = BindgenOptions {
+            lib: self.lib,
+            include_paths: self.include_paths,
+            defines: self
+                .defines
+                .iter()
+                .map(|define| match define.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None => (define.clone(), None),
+                })
+                .collect(),
+            prefix: self.prefix,
+        };
+
+        let header = self.header.clone();
+        let bindings = match blocking::unblock(move || generate(&header, &options)).await {
+            Ok(bindings) => bindings,
+            Err(e) => {
+                eprintln!("{} {e}", style("Error").red().bold());
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+
+        for skipped in &bindings.skipped {
+            eprintln!("{} {skipped}", style("Skipped").yellow().bold());
+        }
+
+        match &self.output {
+            Some(path) => {
+                fs::write(path, &bindings.source)
+                    .await
+                    .with_context(|| format!("Failed to write file \"{}\"", path.display()))?;
+                eprintln!(
+                    "Wrote bindings for {} to {}",
+                    self.header.display(),
+                    path.display()
+                );
+            }
+            None => stdout()
+                .write_all(bindings.source.as_bytes())
+                .context("Failed to write bindings to stdout")?,
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
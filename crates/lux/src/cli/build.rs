@@ -9,21 +9,25 @@ use crate::standalone::metadata::Metadata;
 
 mod base_exe;
 mod files;
+mod precompile;
 mod result;
+mod snapshot;
 mod target;
 
-use self::base_exe::get_or_download_base_executable;
+use self::base_exe::{get_base_library, get_or_download_base_executable};
 use self::files::{remove_source_file_ext, write_executable_file_to};
-use self::target::BuildTarget;
+use self::precompile::precompile;
+use self::snapshot::create_snapshot;
+use self::target::{BuildCrateType, BuildTarget};
 
-/// Build a standalone executable
+/// Build a standalone executable, or a shared library callable from C
 #[derive(Debug, Clone, Parser)]
 pub struct BuildCommand {
-    /// The path to the input file
+    /// The path to the input file, or a directory when using --precompile
     pub input: PathBuf,
 
-    /// The path to the output file - defaults to the
-    /// input file path with an executable extension
+    /// The path to the output file - defaults to the input file
+    /// path with an executable or shared library extension
     #[clap(short, long)]
     pub output: Option<PathBuf>,
 
@@ -31,10 +35,29 @@ pub struct BuildCommand {
     /// defaults to the os and arch of the current system
     #[clap(short, long)]
     pub target: Option<BuildTarget>,
+
+    /// Compile the input file, or every file in the input directory, into
+    /// the bytecode cache used by `lux run` instead of building an executable
+    #[clap(long, conflicts_with_all = ["output", "target", "crate_type", "snapshot"])]
+    pub precompile: bool,
+
+    /// What to build - either `bin` for a standalone executable, or `cdylib`
+    /// for a shared library that exports `lux_init` and `lux_call` to C
+    #[clap(long, default_value_t)]
+    pub crate_type: BuildCrateType,
+
+    /// Embed the input file and the modules next to it, compiled to bytecode,
+    /// so that the binary requires them without reading or compiling them
+    #[clap(long)]
+    pub snapshot: bool,
 }
 
 impl BuildCommand {
     pub async fn run(self) -> Result<ExitCode> {
+        if self.precompile {
+            return precompile(&self.input).await;
+        }
+
         // Derive target spec to use, or default to the current host system
         let target = self.target.unwrap_or_else(BuildTarget::current_system);
 
@@ -44,7 +67,18 @@ impl BuildCommand {
             .output
             .clone()
             .unwrap_or_else(|| remove_source_file_ext(&self.input));
-        let output_path = output_path.with_extension(target.exe_extension());
+        let output_path = match self.crate_type {
+            BuildCrateType::Bin => output_path.with_extension(target.exe_extension()),
+            BuildCrateType::Cdylib if self.output.is_none() => {
+                // NOTE: Linkers expect shared libraries to be named like `libname.so`
+                let name = output_path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                output_path.with_file_name(target.library_file_name(&name))
+            }
+            BuildCrateType::Cdylib => output_path.with_extension(target.library_extension()),
+        };
         if output_path == self.input {
             if self.output.is_some() {
                 bail!("output path cannot be the same as input path");
@@ -61,21 +95,31 @@ impl BuildCommand {
             .await
             .context("failed to read input file")?;
 
-        // Derive the base executable path based on the arguments provided
-        let base_exe_path = get_or_download_base_executable(target).await?;
+        // Derive the base executable path based on the arguments provided,
+        // shared libraries start out as the library built from `lux-embed`
+        let base_exe_path = match self.crate_type {
+            BuildCrateType::Bin => get_or_download_base_executable(target).await?,
+            BuildCrateType::Cdylib => get_base_library(&target)?,
+        };
 
         // Read the contents of the Lux interpreter as our starting point
+        let description = self.crate_type.description();
         println!(
-            "Compiling standalone binary from {}",
+            "Compiling {description} from {}",
             style(self.input.display()).green()
         );
-        let patched_bin = Metadata::create_env_patched_bin(base_exe_path, source_code)
+        let snapshot = if self.snapshot {
+            Some(create_snapshot(&self.input).await?)
+        } else {
+            None
+        };
+        let patched_bin = Metadata::create_env_patched_bin(base_exe_path, source_code, snapshot)
             .await
             .context("failed to create patched binary")?;
 
         // And finally write the patched binary to the output file
         println!(
-            "Writing standalone binary to {}",
+            "Writing {description} to {}",
             style(output_path.display()).blue()
         );
         write_executable_file_to(output_path, patched_bin).await?; // Read & execute for all, write for owner
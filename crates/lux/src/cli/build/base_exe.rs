@@ -27,3 +27,24 @@ pub async fn get_or_download_base_executable(target: BuildTarget) -> BuildResult
     // Cross-compilation downloads disabled in Lux micro-kernel
     Err(BuildError::ReleaseTargetNotFound(target))
 }
+
+/**
+    Discovers the path to the base shared library to use for `--crate-type cdylib`.
+
+    The library is built from the `lux-embed` crate, and is looked for next to the
+    current executable when building for the current system, or in the cache directory.
+*/
+pub fn get_base_library(target: &BuildTarget) -> BuildResult<PathBuf> {
+    if target.is_current_system() {
+        let path = CURRENT_EXE.with_file_name(target.library_file_name("lux_embed"));
+        if path.exists() {
+            return Ok(path);
+        }
+    }
+
+    if target.library_cache_path().exists() {
+        return Ok(target.library_cache_path());
+    }
+
+    Err(BuildError::EmbedLibraryNotFound(target.clone()))
+}
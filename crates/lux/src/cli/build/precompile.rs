@@ -0,0 +1,92 @@
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+use console::style;
+use futures_lite::prelude::*;
+
+use lux_utils::bytecode::BytecodeCache;
+
+use crate::cli::utils::files::bytecode_cache_dir;
+
+/**
+    Compiles the given source file, or every source file in the given
+    directory and its subdirectories, into the bytecode cache.
+
+    Files that fail to compile are reported, and make the command fail, but do
+    not stop the remaining files from being compiled. Hidden directories, such
+    as `.git` and the cache directory itself, are skipped.
+*/
+pub async fn precompile(input: &Path) -> Result<ExitCode> {
+    let cache = BytecodeCache::new(bytecode_cache_dir(), env!("CARGO_PKG_VERSION"));
+
+    let files = find_source_files(input).await?;
+    let mut compiled = 0;
+    let mut cached = 0;
+    let mut failed = 0;
+    for file in &files {
+        let source = fs::read(file)
+            .await
+            .with_context(|| format!("failed to read '{}'", file.display()))?;
+        match cache.precompile(file, &source).await {
+            Ok(true) => cached += 1,
+            Ok(false) => compiled += 1,
+            Err(e) => {
+                failed += 1;
+                eprintln!(
+                    "Failed to compile {}\n{}",
+                    style(file.display()).yellow(),
+                    style(e).red()
+                );
+            }
+        }
+    }
+
+    println!(
+        "Compiled {compiled} files into {} ({cached} already cached, {failed} failed)",
+        style(cache.dir().display()).blue()
+    );
+
+    Ok(if failed > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+pub async fn find_source_files(input: &Path) -> Result<Vec<PathBuf>> {
+    let meta = fs::metadata(input)
+        .await
+        .with_context(|| format!("failed to read '{}'", input.display()))?;
+    if !meta.is_dir() {
+        return Ok(vec![input.to_path_buf()]);
+    }
+
+    let mut files = Vec::new();
+    let mut dirs = vec![input.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("failed to read directory '{}'", dir.display()))?;
+        while let Some(entry) = entries.try_next().await? {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if entry.file_type().await?.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .is_some_and(|ext| matches!(ext.to_str(), Some("lua" | "luau")))
+            {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
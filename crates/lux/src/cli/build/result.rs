@@ -10,6 +10,10 @@ use super::target::BuildTarget;
 pub enum BuildError {
     #[error("failed to find Lux target '{0}' in GitHub release")]
     ReleaseTargetNotFound(BuildTarget),
+    #[error(
+        "failed to find the Lux embedding library for target '{0}', build the `lux-embed` crate and place it next to the Lux executable or in the target cache"
+    )]
+    EmbedLibraryNotFound(BuildTarget),
     #[error("failed to find Lux binary '{0}' in downloaded zip file")]
     ZippedBinaryNotFound(String),
     #[error("failed to download Lux binary: {0}")]
@@ -0,0 +1,39 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+
+use lux_utils::{bytecode, snapshot::ModuleSnapshot};
+
+use super::precompile::find_source_files;
+
+/**
+    Creates a module snapshot for a standalone binary, containing the input file and every
+    source file next to it and in its subdirectories, compiled to bytecode.
+
+    Files are stored relative to the directory of the input file, and the built binary
+    restores them relative to its own directory, so that it runs the same whether or
+    not the source files are next to it, see `Runtime::run_snapshot`.
+*/
+pub async fn create_snapshot(input: &Path) -> Result<Vec<u8>> {
+    let input = fs::canonicalize(input)
+        .await
+        .with_context(|| format!("failed to read '{}'", input.display()))?;
+    let dir = input.parent().unwrap_or(&input);
+
+    let compiler = bytecode::compiler();
+    let mut snapshot = ModuleSnapshot::default();
+    snapshot.set_root(dir);
+    for file in find_source_files(dir).await? {
+        let source = fs::read(&file)
+            .await
+            .with_context(|| format!("failed to read '{}'", file.display()))?;
+        let bytecode = compiler
+            .compile(&source)
+            .with_context(|| format!("failed to compile '{}'", file.display()))?;
+        snapshot.insert(&file, bytecode);
+    }
+    snapshot.set_main(&input);
+
+    Ok(snapshot.to_bytes())
+}
@@ -47,6 +47,21 @@ impl BuildTargetOS {
             _ => "",
         }
     }
+
+    fn library_prefix(self) -> &'static str {
+        match self {
+            Self::Windows => "",
+            _ => "lib",
+        }
+    }
+
+    fn library_extension(self) -> &'static str {
+        match self {
+            Self::Windows => "dll",
+            Self::Linux => "so",
+            Self::MacOS => "dylib",
+        }
+    }
 }
 
 impl fmt::Display for BuildTargetOS {
@@ -155,6 +170,26 @@ impl BuildTarget {
     pub fn cache_path(&self) -> PathBuf {
         CACHE_DIR.join(format!("{self}{}", self.os.exe_extension()))
     }
+
+    pub fn library_extension(&self) -> &'static str {
+        self.os.library_extension()
+    }
+
+    /**
+        Returns the file name that shared libraries named `name` have on this target,
+        such as `libname.so` on Linux and `name.dll` on Windows.
+    */
+    pub fn library_file_name(&self, name: &str) -> String {
+        format!(
+            "{}{name}.{}",
+            self.os.library_prefix(),
+            self.os.library_extension()
+        )
+    }
+
+    pub fn library_cache_path(&self) -> PathBuf {
+        CACHE_DIR.join(format!("{self}-cdylib.{}", self.os.library_extension()))
+    }
 }
 
 impl fmt::Display for BuildTarget {
@@ -176,3 +211,44 @@ impl FromStr for BuildTarget {
         Ok(Self { os, arch })
     }
 }
+
+/**
+    The kind of output that `lux build` creates, named after the Cargo crate types
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuildCrateType {
+    /// A standalone executable
+    #[default]
+    Bin,
+    /// A shared library exporting a C ABI
+    Cdylib,
+}
+
+impl BuildCrateType {
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Bin => "standalone binary",
+            Self::Cdylib => "shared library",
+        }
+    }
+}
+
+impl fmt::Display for BuildCrateType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bin => write!(f, "bin"),
+            Self::Cdylib => write!(f, "cdylib"),
+        }
+    }
+}
+
+impl FromStr for BuildCrateType {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "bin" => Ok(Self::Bin),
+            "cdylib" => Ok(Self::Cdylib),
+            _ => Err("crate type must be `bin` or `cdylib`"),
+        }
+    }
+}
@@ -1,17 +1,68 @@
 use std::{io::stdin, process::ExitCode};
 
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use console::style;
+use serde::Serialize;
 
 use lux::Runtime;
+use lux_fmt::{Diagnostic, Severity, lint};
 
 use super::utils::files::discover_script_path_including_lux_dirs;
 
-/// Check a script for syntax errors
+/// Format to write the results of a check in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CheckFormat {
+    /// Human-readable text
+    Text,
+    /// JSON, for editors and CI
+    Json,
+}
+
+/// Check a script for syntax errors and common misuse of `ffi`, signals and `task`
 #[derive(Debug, Clone, Parser)]
 pub struct CheckCommand {
     /// Script name or full path to the file to check
     pub(super) script_path: String,
+
+    /// The format to write the results in
+    #[clap(long, value_enum, default_value_t = CheckFormat::Text)]
+    pub(super) format: CheckFormat,
+
+    /// Only check for syntax errors, without running any lints
+    #[clap(long)]
+    pub(super) no_lint: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct DiagnosticReport {
+    rule: &'static str,
+    severity: &'static str,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl From<&Diagnostic> for DiagnosticReport {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        Self {
+            rule: diagnostic.rule(),
+            severity: diagnostic.severity().as_str(),
+            line: diagnostic.line(),
+            column: diagnostic.column(),
+            message: diagnostic.message().to_string(),
+        }
+    }
+}
+
+/**
+    The results of checking a single script, as written by `--format json`.
+*/
+#[derive(Debug, Clone, Serialize)]
+struct CheckReport {
+    path: String,
+    syntax_error: Option<String>,
+    diagnostics: Vec<DiagnosticReport>,
 }
 
 impl CheckCommand {
@@ -21,36 +72,99 @@ impl CheckCommand {
 
         let mut contents = Vec::new();
         let name;
+        let path;
 
         if self.script_path == "-" {
             name = "stdin".to_string();
+            path = "-".to_string();
             std::io::Read::read_to_end(&mut stdin(), &mut contents)
                 .context("Failed to read script contents from stdin")?;
         } else {
             let file_path = discover_script_path_including_lux_dirs(&self.script_path)?;
             name = format!("@{}", file_path.display());
-            contents = async_fs::read(&file_path)
-                .await
-                .with_context(|| format!("Failed to read file at path \"{}\"", file_path.display()))?;
+            path = file_path.display().to_string();
+            contents = async_fs::read(&file_path).await.with_context(|| {
+                format!("Failed to read file at path \"{}\"", file_path.display())
+            })?;
         }
 
-        // Strip shebang if present
-        if contents.starts_with(b"#!") {
-             if let Some(idx) = contents.iter().position(|x| *x == b'\n') {
-                 contents.drain(..idx).for_each(drop);
-             }
+        // Strip shebang if present, keeping its newline so that line numbers stay the same
+        if contents.starts_with(b"#!")
+            && let Some(idx) = contents.iter().position(|x| *x == b'\n')
+        {
+            contents.drain(..idx).for_each(drop);
         }
 
-        // Check syntax
-        match rt.check(&name, contents) {
-            Ok(_) => {
-                println!("Syntax OK");
-                Ok(ExitCode::SUCCESS)
-            }
-            Err(e) => {
-                eprintln!("Syntax Error: {}", e);
-                Ok(ExitCode::FAILURE)
+        // Check syntax, and only lint scripts that parse, since lints can not
+        // tell much about code that is broken and would only add noise
+        let syntax_error = rt.check(&name, &contents).err().map(|e| e.to_string());
+        let diagnostics = if syntax_error.is_some() || self.no_lint {
+            Vec::new()
+        } else {
+            lint(&String::from_utf8_lossy(&contents)).unwrap_or_default()
+        };
+
+        let failed = syntax_error.is_some()
+            || diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.severity() == Severity::Error);
+
+        match self.format {
+            CheckFormat::Text => print_text(&path, syntax_error.as_deref(), &diagnostics),
+            CheckFormat::Json => {
+                let report = CheckReport {
+                    path,
+                    syntax_error,
+                    diagnostics: diagnostics.iter().map(DiagnosticReport::from).collect(),
+                };
+                let json = serde_json::to_string_pretty(&report)
+                    .context("Failed to encode check results as JSON")?;
+                println!("{json}");
             }
         }
+
+        Ok(if failed {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        })
+    }
+}
+
+fn print_text(path: &str, syntax_error: Option<&str>, diagnostics: &[Diagnostic]) {
+    if let Some(e) = syntax_error {
+        eprintln!("Syntax Error: {e}");
+        return;
+    }
+    println!("Syntax OK");
+
+    for diagnostic in diagnostics {
+        let severity = match diagnostic.severity() {
+            Severity::Error => style("error").red().bold(),
+            Severity::Warning => style("warning").yellow().bold(),
+            Severity::Info => style("info").cyan().bold(),
+        };
+        println!(
+            "{severity} {path}:{}:{} {} {}",
+            diagnostic.line(),
+            diagnostic.column(),
+            diagnostic.message(),
+            style(format!("[{}]", diagnostic.rule())).dim()
+        );
+    }
+
+    if !diagnostics.is_empty() {
+        let count = |severity| {
+            diagnostics
+                .iter()
+                .filter(|diagnostic| diagnostic.severity() == severity)
+                .count()
+        };
+        println!(
+            "{} errors, {} warnings, {} info",
+            count(Severity::Error),
+            count(Severity::Warning),
+            count(Severity::Info)
+        );
     }
 }
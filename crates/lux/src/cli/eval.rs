@@ -0,0 +1,82 @@
+use std::{io::stdin, process::ExitCode};
+
+use anyhow::{Context, Result};
+use blocking::Unblock;
+use futures_lite::prelude::*;
+use mlua::prelude::*;
+
+use lux::{RunContext, Runtime};
+use lux_utils::fmt::{ValueFormatConfig, pretty_format_multi_value};
+
+const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(4)
+    .with_colors_enabled(true);
+
+/// Evaluate a string of code, printing the value of its final expression
+#[derive(Debug, Clone)]
+pub struct EvalCommand {
+    /// The code to evaluate, or a single "-" (dash) to read it from stdin
+    pub(super) code: String,
+    /// Print the result as JSON instead of pretty-printing it
+    pub(super) json: bool,
+}
+
+impl EvalCommand {
+    pub async fn run(self) -> Result<ExitCode> {
+        let code = if self.code == "-" {
+            let mut stdin_contents = String::new();
+            Unblock::new(stdin())
+                .read_to_string(&mut stdin_contents)
+                .await
+                .context("Failed to read code from stdin")?;
+            stdin_contents
+        } else {
+            self.code
+        };
+
+        let mut rt = Runtime::new()?
+            .with_args(Vec::<String>::new())
+            .with_run_context(RunContext::Eval);
+
+        // Code that is a single expression, such as `1 + 2`, gets its
+        // value returned, anything else runs as-is and may return values
+        let expression = format!("return {code}");
+        let code = if rt.check("eval", &expression).is_ok() {
+            expression
+        } else {
+            code
+        };
+
+        let result = match rt.run_custom("eval", code).await {
+            Err(err) => {
+                eprintln!("{err}");
+                return Ok(ExitCode::FAILURE);
+            }
+            Ok(result) => result,
+        };
+
+        if self.json {
+            println!("{}", to_json(&result.values)?);
+        } else if !result.values.is_empty() {
+            println!(
+                "{}",
+                pretty_format_multi_value(&result.values, &FORMAT_CONFIG)
+            );
+        }
+
+        Ok(ExitCode::from(result.status()))
+    }
+}
+
+/**
+    Encodes values returned from eval as JSON - nothing as `null`, a
+    single value as itself, and multiple values as an array of values.
+*/
+fn to_json(values: &LuaMultiValue) -> Result<String> {
+    let json = match values.len() {
+        0 => serde_json::to_string(&LuaValue::Nil),
+        1 => serde_json::to_string(&values[0]),
+        _ => serde_json::to_string(&values.iter().collect::<Vec<_>>()),
+    };
+    json.context("Failed to encode result as JSON")
+}
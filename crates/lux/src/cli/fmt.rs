@@ -0,0 +1,177 @@
+use std::{
+    io::{Read, Write, stdin, stdout},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+use clap::{Parser, ValueEnum};
+use console::style;
+
+use lux_fmt::{FormatConfig, IndentType, format};
+
+use super::utils::discover::{discover_files, has_suffix};
+
+const SOURCE_FILE_SUFFIXES: &[&str] = &[".luau", ".lua"];
+
+/// Kind of indentation to format with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IndentKind {
+    Tabs,
+    Spaces,
+}
+
+/// Format Luau source files
+#[derive(Debug, Clone, Parser)]
+pub struct FmtCommand {
+    /// Files or directories to format, or `-` to format stdin
+    /// and write the result to stdout - defaults to the current directory
+    pub paths: Vec<PathBuf>,
+
+    /// Check that files are formatted instead of formatting them,
+    /// exiting with an error if any of them are not
+    #[clap(long)]
+    pub check: bool,
+
+    /// The width that lines should fit within
+    #[clap(long, default_value_t = 120)]
+    pub column_width: usize,
+
+    /// The kind of indentation to use
+    #[clap(long, value_enum, default_value_t = IndentKind::Tabs)]
+    pub indent_type: IndentKind,
+
+    /// The number of spaces per indentation level
+    #[clap(long, default_value_t = 4)]
+    pub indent_width: usize,
+
+    /// Only format lines from this byte offset onwards, for editor integrations
+    #[clap(long)]
+    pub range_start: Option<usize>,
+
+    /// Only format lines up to this byte offset, for editor integrations
+    #[clap(long)]
+    pub range_end: Option<usize>,
+}
+
+impl FmtCommand {
+    pub async fn run(self) -> Result<ExitCode> {
+        let config = self.config();
+
+        if self.paths.len() == 1 && self.paths[0].as_os_str() == "-" {
+            return self.run_stdin(&config);
+        }
+
+        let paths = if self.paths.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            self.paths.clone()
+        };
+        let files = discover_files(&paths, |path| has_suffix(path, SOURCE_FILE_SUFFIXES)).await?;
+
+        let mut changed = 0;
+        let mut errored = 0;
+        for file in &files {
+            match format_file(file, &config, self.check).await {
+                Ok(false) => {}
+                Ok(true) => {
+                    changed += 1;
+                    if self.check {
+                        println!(
+                            "{} {}",
+                            style("Unformatted").yellow().bold(),
+                            file.display()
+                        );
+                    }
+                }
+                Err(e) => {
+                    errored += 1;
+                    eprintln!("{} {e:#}", style("Error").red().bold());
+                }
+            }
+        }
+
+        let summary = if self.check {
+            format!("{changed} of {} files would be reformatted", files.len())
+        } else {
+            format!("Formatted {changed} of {} files", files.len())
+        };
+        if errored > 0 {
+            println!("{summary}, {errored} could not be formatted");
+        } else {
+            println!("{summary}");
+        }
+
+        Ok(if errored > 0 || (self.check && changed > 0) {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        })
+    }
+
+    fn config(&self) -> FormatConfig {
+        let indent_type = match self.indent_type {
+            IndentKind::Tabs => IndentType::Tabs,
+            IndentKind::Spaces => IndentType::Spaces,
+        };
+        let config = FormatConfig::new()
+            .with_indent_type(indent_type)
+            .with_indent_width(self.indent_width)
+            .with_column_width(self.column_width);
+        if self.range_start.is_some() || self.range_end.is_some() {
+            config.with_range(self.range_start.unwrap_or(0)..self.range_end.unwrap_or(usize::MAX))
+        } else {
+            config
+        }
+    }
+
+    fn run_stdin(&self, config: &FormatConfig) -> Result<ExitCode> {
+        let mut source = String::new();
+        stdin()
+            .read_to_string(&mut source)
+            .context("Failed to read source from stdin")?;
+
+        let formatted = match format(&source, config) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{} {e}", style("Error").red().bold());
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+        if self.check {
+            return Ok(if formatted == source {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            });
+        }
+
+        stdout()
+            .write_all(formatted.as_bytes())
+            .context("Failed to write formatted source to stdout")?;
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/**
+    Formats a single file, returning `true` if formatting changed it.
+
+    The file is only written to if `check` is `false` and its contents changed.
+*/
+async fn format_file(path: &Path, config: &FormatConfig, check: bool) -> Result<bool> {
+    let source = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read file \"{}\"", path.display()))?;
+    let formatted = format(&source, config)
+        .with_context(|| format!("Failed to format file \"{}\"", path.display()))?;
+    if formatted == source {
+        return Ok(false);
+    }
+    if !check {
+        fs::write(path, formatted)
+            .await
+            .with_context(|| format!("Failed to write file \"{}\"", path.display()))?;
+    }
+    Ok(true)
+}
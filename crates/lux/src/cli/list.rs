@@ -1,16 +1,43 @@
-use std::{fmt::Write as _, process::ExitCode};
+use std::{fmt::Write as _, path::PathBuf, process::ExitCode, time::Instant};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use async_fs as fs;
 use clap::Parser;
 
-use super::utils::listing::{find_lux_scripts, sort_lux_scripts, write_lux_scripts_list};
+use lux::{LuxStandardLibrary, Runtime};
+use lux_utils::bytecode::BytecodeCache;
+
+use super::utils::files::bytecode_cache_dir;
+use super::utils::listing::{
+    STYLE_DIM, find_lux_scripts, sort_lux_scripts, write_lux_scripts_list,
+};
 
 /// List scripts available to run
 #[derive(Debug, Clone, Parser)]
-pub struct ListCommand {}
+pub struct ListCommand {
+    /// List type definition files for the standard library and globals instead
+    #[clap(long)]
+    types: bool,
+    /// Write the type definition files to the given directory
+    #[clap(long, value_name = "DIR", requires = "types")]
+    out: Option<PathBuf>,
+    /// Show how long each standard library takes to initialize instead
+    #[clap(long, conflicts_with = "types")]
+    startup: bool,
+}
 
 impl ListCommand {
     pub async fn run(self) -> Result<ExitCode> {
+        if self.startup {
+            return list_startup().await;
+        }
+        if self.types {
+            return match self.out {
+                Some(dir) => write_typedefs(dir).await,
+                None => list_typedefs(),
+            };
+        }
+
         let sorted_relative = find_lux_scripts(false).await.map(sort_lux_scripts);
 
         let sorted_home_dir = find_lux_scripts(true).await.map(sort_lux_scripts);
@@ -42,6 +69,104 @@ impl ListCommand {
             print!("{buffer}");
         }
 
+        let cache_dir = bytecode_cache_dir();
+        let stats = BytecodeCache::stats(&cache_dir).await.unwrap_or_default();
+        if stats.entries > 0 {
+            let size_kb = stats.size as f64 / 1024.0;
+            println!(
+                "{}",
+                STYLE_DIM.apply_to(format!(
+                    "Bytecode cache: {} files, {size_kb:.1} KB in {}",
+                    stats.entries,
+                    cache_dir.display()
+                ))
+            );
+        }
+
         Ok(ExitCode::SUCCESS)
     }
 }
+
+fn list_typedefs() -> Result<ExitCode> {
+    let mut buffer = String::from("Available type definitions:");
+    for (name, contents) in lux_std::all_typedefs() {
+        write!(&mut buffer, "\n    {name}.luau ({} bytes)", contents.len())?;
+    }
+    println!("{buffer}");
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn list_startup() -> Result<ExitCode> {
+    let started = Instant::now();
+    let mut rt = Runtime::new()?;
+    let runtime_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+    // Libraries are initialized on first use, so an
+    // empty script only pays for the ones that are eager
+    let started = Instant::now();
+    rt.run_custom("startup", "").await?;
+    let empty_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let eager_ms: f64 = rt
+        .library_init_times()
+        .iter()
+        .map(|(_, elapsed)| elapsed.as_secs_f64() * 1000.0)
+        .sum();
+
+    // Requiring each library once initializes all of them, libraries
+    // that are denied by permissions error and are simply skipped
+    let mut script = String::new();
+    for library in LuxStandardLibrary::ALL {
+        writeln!(
+            &mut script,
+            "pcall(function() return require(\"@lux/{}\") end)",
+            library.name()
+        )?;
+    }
+    rt.run_custom("startup", script).await?;
+
+    let mut times = rt.library_init_times();
+    times.sort_by(|(_, a), (_, b)| b.cmp(a));
+    let total_ms: f64 = times
+        .iter()
+        .map(|(_, elapsed)| elapsed.as_secs_f64() * 1000.0)
+        .sum();
+
+    let mut buffer = String::from("Standard library initialization times:");
+    for (library, elapsed) in &times {
+        let ms = elapsed.as_secs_f64() * 1000.0;
+        write!(&mut buffer, "\n    {:<14} {ms:>8.3} ms", library.name())?;
+    }
+    write!(&mut buffer, "\n    {:<14} {total_ms:>8.3} ms", "total")?;
+    println!("{buffer}");
+    println!(
+        "{}",
+        STYLE_DIM.apply_to(format!(
+            "Runtime created in {runtime_ms:.3} ms, an empty script ran in {empty_ms:.3} ms \
+             and initialized {eager_ms:.3} ms worth of libraries, skipping {:.3} ms",
+            (total_ms - eager_ms).max(0.0)
+        ))
+    );
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn write_typedefs(dir: PathBuf) -> Result<ExitCode> {
+    let typedefs = lux_std::all_typedefs();
+    for (name, contents) in &typedefs {
+        let path = dir.join(name).with_extension("luau");
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create directory '{}'", parent.display()))?;
+        }
+        fs::write(&path, contents)
+            .await
+            .with_context(|| format!("Failed to write '{}'", path.display()))?;
+    }
+    println!(
+        "Wrote {} type definition files to '{}'",
+        typedefs.len(),
+        dir.display()
+    );
+    Ok(ExitCode::SUCCESS)
+}
@@ -1,19 +1,29 @@
-use std::{env::args_os, process::ExitCode};
+use std::{
+    env::args_os,
+    io::{IsTerminal, stdin},
+    path::PathBuf,
+    process::ExitCode,
+};
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use lux::profiler::ProfileFormat;
 
+pub(crate) mod bindgen;
 pub(crate) mod build;
 pub(crate) mod check;
+pub(crate) mod eval;
+pub(crate) mod fmt;
 pub(crate) mod list;
 pub(crate) mod repl;
 pub(crate) mod run;
 pub(crate) mod setup;
+pub(crate) mod test;
 pub(crate) mod utils;
 
 pub use self::{
-    build::BuildCommand, check::CheckCommand, list::ListCommand, repl::ReplCommand, run::RunCommand,
-    setup::SetupCommand,
+    bindgen::BindgenCommand, build::BuildCommand, check::CheckCommand, eval::EvalCommand, fmt::FmtCommand, list::ListCommand, repl::ReplCommand, run::RunCommand,
+    setup::SetupCommand, test::TestCommand,
 };
 
 #[derive(Debug, Clone, Subcommand)]
@@ -24,6 +34,9 @@ pub enum CliSubcommand {
     Setup(SetupCommand),
     Build(BuildCommand),
     Repl(ReplCommand),
+    Test(TestCommand),
+    Fmt(FmtCommand),
+    Bindgen(BindgenCommand),
 }
 
 impl Default for CliSubcommand {
@@ -36,9 +49,14 @@ impl Default for CliSubcommand {
 #[derive(Parser, Debug, Default, Clone)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
-    #[clap(short = 'e', long = "eval")]
+    /// Evaluate code and print the value of its final expression, or `-` to read the code from stdin
+    #[clap(short = 'e', long = "eval", value_name = "CODE")]
     pub eval: Option<String>,
 
+    /// Print the result of --eval as JSON
+    #[clap(long, requires = "eval")]
+    pub json: bool,
+
     #[clap(subcommand)]
     subcommand: Option<CliSubcommand>,
 }
@@ -51,35 +69,109 @@ impl Cli {
             .nth(1)
             .is_some_and(|arg| arg.eq_ignore_ascii_case("run"))
         {
-            let Some(script_path) = args_os()
-                .nth(2)
+            // Flags must come before the script path, anything after
+            // the script path is passed through to the script as-is
+            let mut args = args_os().skip(2).peekable();
+            let mut allow_ffi = false;
+            let mut allow_process = false;
+            let mut allow_net = false;
+            let mut allow_fs = None;
+            let mut allow_process_memory = false;
+            let mut allow_registry = false;
+            let mut allow_input = false;
+            let mut profile = None;
+            let mut profile_format = ProfileFormat::default();
+            let mut timeout = None;
+            let mut task_dump_on_timeout = false;
+            let mut no_cache = false;
+            while let Some(flag) = args.next_if(|arg| arg.to_str().is_some_and(|a| a.starts_with("--"))) {
+                let flag = flag.to_string_lossy();
+                let (name, inline_value) = match flag.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (flag.as_ref(), None),
+                };
+                // Values may be given either as --flag=value or as --flag value
+                let mut value = || inline_value.clone().or_else(|| args.next()?.into_string().ok());
+                match name {
+                    "--allow-ffi" if inline_value.is_none() => allow_ffi = true,
+                    "--allow-process" if inline_value.is_none() => allow_process = true,
+                    "--allow-net" if inline_value.is_none() => allow_net = true,
+                    // NOTE: Paths are only accepted as --allow-fs=paths, since the
+                    // value is optional and the next argument may be the script path
+                    "--allow-fs" => {
+                        let paths = allow_fs.get_or_insert_with(Vec::<PathBuf>::new);
+                        if let Some(value) = &inline_value {
+                            paths.extend(value.split(',').filter(|p| !p.is_empty()).map(PathBuf::from));
+                        }
+                    }
+                    "--allow-process-memory" if inline_value.is_none() => allow_process_memory = true,
+                    "--allow-registry" if inline_value.is_none() => allow_registry = true,
+                    "--allow-input" if inline_value.is_none() => allow_input = true,
+                    "--profile" => match value() {
+                        Some(path) => profile = Some(PathBuf::from(path)),
+                        None => return Self::parse(),
+                    },
+                    "--profile-format" => match value().and_then(|f| f.parse().ok()) {
+                        Some(format) => profile_format = format,
+                        None => return Self::parse(),
+                    },
+                    "--timeout" => match value().and_then(|s| s.parse().ok()) {
+                        Some(secs) => timeout = Some(secs),
+                        None => return Self::parse(),
+                    },
+                    "--task-dump-on-timeout" if inline_value.is_none() => task_dump_on_timeout = true,
+                    "--no-cache" if inline_value.is_none() => no_cache = true,
+                    _ => return Self::parse(), // Will fail and report the unknown flag
+                }
+            }
+
+            let Some(script_path) = args
+                .next()
                 .and_then(|arg| arg.to_str().map(String::from))
             else {
                 return Self::parse(); // Will fail and return the help message
             };
 
-            let script_args = args_os()
-                .skip(3)
+            let script_args = args
                 .filter_map(|arg| arg.to_str().map(String::from))
                 .collect::<Vec<_>>();
 
             Self {
                 eval: None,
+                json: false,
                 subcommand: Some(CliSubcommand::Run(RunCommand {
+                    allow_ffi,
+                    allow_process,
+                    allow_net,
+                    allow_fs,
+                    allow_process_memory,
+                    allow_registry,
+                    allow_input,
+                    profile,
+                    profile_format,
+                    timeout,
+                    task_dump_on_timeout,
+                    no_cache,
                     script_path,
                     script_args,
                 })),
             }
+        } else if args_os().nth(1).is_some_and(|arg| arg == "-") {
+            // A single "-" (dash) evaluates stdin, same as `lux -e -`
+            Self::parse_from(args_os().take(1).chain(["-e".into(), "-".into()]).chain(args_os().skip(2)))
         } else {
-            Self::parse()
+            let mut cli = Self::parse();
+            // Piping code into lux without a subcommand evaluates it instead of starting the REPL
+            if cli.eval.is_none() && cli.subcommand.is_none() && !stdin().is_terminal() {
+                cli.eval = Some(String::from("-"));
+            }
+            cli
         }
     }
 
     pub async fn run(self) -> Result<ExitCode> {
         if let Some(code) = self.eval {
-            let mut rt = lux::Runtime::new()?;
-            let result = rt.run_custom("eval", code.as_bytes()).await?;
-            return Ok(ExitCode::from(result.status()));
+            return EvalCommand { code, json: self.json }.run().await;
         }
 
         match self.subcommand.unwrap_or_default() {
@@ -89,6 +181,9 @@ impl Cli {
             CliSubcommand::Setup(cmd) => cmd.run().await,
             CliSubcommand::Build(cmd) => cmd.run().await,
             CliSubcommand::Repl(cmd) => cmd.run().await,
+            CliSubcommand::Test(cmd) => cmd.run().await,
+            CliSubcommand::Fmt(cmd) => cmd.run().await,
+            CliSubcommand::Bindgen(cmd) => cmd.run().await,
         }
     }
 }
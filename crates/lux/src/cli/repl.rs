@@ -6,7 +6,13 @@ use clap::Parser;
 use directories::UserDirs;
 use rustyline::{DefaultEditor, error::ReadlineError};
 
-use lux::Runtime;
+use lux::{RunContext, Runtime};
+use lux_utils::fmt::{ValueFormatConfig, pretty_format_multi_value};
+
+const FORMAT_CONFIG: ValueFormatConfig = ValueFormatConfig::new()
+    .with_max_depth(4)
+    .with_colors_enabled(true)
+    .with_quoted_strings(true);
 
 const MESSAGE_WELCOME: &str = concat!("Lux v", env!("CARGO_PKG_VERSION"));
 const MESSAGE_INTERRUPT: &str = "Interrupt: ^C again to exit";
@@ -39,7 +45,9 @@ impl ReplCommand {
         let mut prompt_state = PromptState::Regular;
         let mut source_code = String::new();
 
-        let mut lux_instance = Runtime::new()?;
+        let mut lux_instance = Runtime::new()?
+            .with_args(Vec::<String>::new())
+            .with_run_context(RunContext::Repl);
 
         loop {
             let prompt = match prompt_state {
@@ -84,8 +92,24 @@ impl ReplCommand {
                 }
             }
 
-            match lux_instance.run_custom("REPL", &source_code).await {
-                Ok(_) => prompt_state = PromptState::Regular,
+            // Input that is a single expression, such as `1 + 2`, gets its value printed
+            let expression = format!("return {source_code}");
+            let code = if lux_instance.check("REPL", &expression).is_ok() {
+                &expression
+            } else {
+                &source_code
+            };
+
+            match lux_instance.run_custom("REPL", code).await {
+                Ok(result) => {
+                    prompt_state = PromptState::Regular;
+                    if !result.values.is_empty() {
+                        println!(
+                            "{}",
+                            pretty_format_multi_value(&result.values, &FORMAT_CONFIG)
+                        );
+                    }
+                }
 
                 Err(err) => {
                     if err.is_incomplete_input() {
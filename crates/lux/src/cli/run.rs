@@ -1,20 +1,65 @@
-use std::{env, io::stdin, process::ExitCode};
+use std::{env, io::stdin, path::PathBuf, process::ExitCode, time::Duration};
 
 use anyhow::{Context, Result};
 use blocking::Unblock;
 use clap::Parser;
 use futures_lite::prelude::*;
 
-use lux::Runtime;
+use lux::{
+    FeatureFlag, Permission, ProcessFfiDebug, Runtime,
+    profiler::{self, ProfileFormat},
+};
 
-use super::utils::files::discover_script_path_including_lux_dirs;
+use super::utils::files::{bytecode_cache_dir, discover_script_path_including_lux_dirs};
 
 /// Run a script
 #[derive(Debug, Clone, Parser)]
 pub struct RunCommand {
+    /// Allow loading native libraries and calling native code through ffi
+    #[clap(long)]
+    pub(super) allow_ffi: bool,
+    /// Allow running other programs through process.exec and process.create
+    #[clap(long)]
+    pub(super) allow_process: bool,
+    /// Allow opening network connections through socket and websocket
+    #[clap(long)]
+    pub(super) allow_net: bool,
+    /// Allow accessing files through fs, optionally only within the given comma-separated paths
+    #[clap(
+        long,
+        value_name = "PATHS",
+        num_args = 0..=1,
+        require_equals = true,
+        value_delimiter = ','
+    )]
+    pub(super) allow_fs: Option<Vec<PathBuf>>,
+    /// Allow reading and writing the memory of other processes through ffi.process
+    #[clap(long)]
+    pub(super) allow_process_memory: bool,
+    /// Allow reading and writing the Windows registry through winreg
+    #[clap(long)]
+    pub(super) allow_registry: bool,
+    /// Allow reading and synthesizing keyboard and mouse input
+    #[clap(long)]
+    pub(super) allow_input: bool,
+    /// Profile the script, writing sampled Luau functions, FFI calls and zones to a file
+    #[clap(long, value_name = "PATH")]
+    pub(super) profile: Option<PathBuf>,
+    /// Format of the profile written by --profile, either chrome or speedscope
+    #[clap(long, value_name = "FORMAT", default_value_t = ProfileFormat::Chrome)]
+    pub(super) profile_format: ProfileFormat,
+    /// Stop the script after this many seconds, including time spent waiting
+    #[clap(long, value_name = "SECONDS")]
+    pub(super) timeout: Option<f64>,
+    /// Print all live tasks, and where they were spawned from, when --timeout is exceeded
+    #[clap(long, requires = "timeout")]
+    pub(super) task_dump_on_timeout: bool,
+    /// Compile every file from source instead of using bytecode cached in .lux-cache
+    #[clap(long)]
+    pub(super) no_cache: bool,
     /// Script name or full path to the file to run
     pub(super) script_path: String,
-    /// Arguments to pass to the script, stored in process.args
+    /// Arguments to pass to the script, available as the args global and process.args
     pub(super) script_args: Vec<String>,
 }
 
@@ -25,10 +70,86 @@ impl RunCommand {
             .ok()
             .is_some_and(|s| matches!(s.as_str(), "0" | "false" | "off"));
 
+        // Check if the user has enabled release mode, skipping debug-only checks
+        let release = env::var("LUX_RELEASE")
+            .ok()
+            .is_some_and(|s| matches!(s.as_str(), "1" | "true" | "on"));
+
+        // Check if the user has enabled ffi safe mode, checking memory accessed through cdata
+        let ffi_safe_mode = env::var("LUX_FFI_SAFE_MODE")
+            .ok()
+            .is_some_and(|s| matches!(s.as_str(), "1" | "true" | "on"));
+
+        // Check if the user has enabled ffi debugging, tracking where cdata came from and logging casts
+        let ffi_track_origins = env::var("LUX_FFI_DEBUG")
+            .ok()
+            .is_some_and(|s| matches!(s.as_str(), "1" | "true" | "on"));
+        let ffi_log_casts = env::var("LUX_FFI_LOG_CASTS")
+            .ok()
+            .is_some_and(|s| matches!(s.as_str(), "1" | "true" | "on"));
+
+        // Check if the user has asked to deny everything not granted using --allow-* flags
+        let deny_by_default = env::var("LUX_DENY_BY_DEFAULT")
+            .ok()
+            .is_some_and(|s| matches!(s.as_str(), "1" | "true" | "on"));
+
         // Create a new Lux runtime with all globals & run the script
         let mut rt = Runtime::new()?
             .with_args(self.script_args)
-            .with_jit(!jit_disabled);
+            .with_jit(!jit_disabled)
+            .with_release(release)
+            .with_ffi_safe_mode(ffi_safe_mode)
+            .with_ffi_debug(ProcessFfiDebug::new(ffi_track_origins, ffi_log_casts))
+            .with_profiling(self.profile.is_some())
+            .with_deny_by_default(deny_by_default)
+            .with_bytecode_cache((!self.no_cache).then(bytecode_cache_dir));
+        for (allowed, permission) in [
+            (self.allow_ffi, Permission::Ffi),
+            (self.allow_process, Permission::Process),
+            (self.allow_net, Permission::Net),
+            (self.allow_process_memory, Permission::ProcessMemory),
+            (self.allow_registry, Permission::Registry),
+            (self.allow_input, Permission::Input),
+        ] {
+            if allowed {
+                rt = rt.with_permission(permission);
+            }
+        }
+        match self.allow_fs {
+            Some(paths) if paths.is_empty() => rt = rt.with_permission(Permission::Fs),
+            Some(paths) => {
+                for path in paths {
+                    rt = rt.with_fs_path(path);
+                }
+            }
+            None => {}
+        }
+        if let Some(secs) = self.timeout {
+            let timeout = Duration::try_from_secs_f64(secs)
+                .context("Invalid --timeout, expected a positive number of seconds")?;
+            rt.set_execution_limit(timeout);
+            rt.set_task_dump_on_timeout(self.task_dump_on_timeout);
+        }
+
+        // Set any feature flags given as a comma-separated list, where
+        // names prefixed with a dash (-) disable the flag instead
+        if let Ok(flags) = env::var("LUX_FLAGS") {
+            for name in flags.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                let (name, enabled) = match name.strip_prefix('-') {
+                    Some(name) => (name, false),
+                    None => (name, true),
+                };
+                let flag = name
+                    .parse::<FeatureFlag>()
+                    .map_err(anyhow::Error::msg)
+                    .context("Invalid LUX_FLAGS environment variable")?;
+                rt.set_flag(flag, enabled)?;
+            }
+        }
+
+        if self.profile.is_some() {
+            profiler::start();
+        }
 
         // Figure out if we should run stdin or run a file,
         // reading from stdin is marked by passing a single "-"
@@ -45,11 +166,29 @@ impl RunCommand {
             rt.run_file(file_path).await
         };
 
+        if let Some(path) = &self.profile {
+            profiler::stop();
+            async_fs::write(path, profiler::dump(self.profile_format))
+                .await
+                .with_context(|| format!("Failed to write profile to {}", path.display()))?;
+        }
+
+        // Suspended tasks left over once everything else has finished
+        // can never be resumed, which is almost always a bug in the script
+        let tasks = rt.dump_tasks();
+
         Ok(match result {
             Err(err) => {
                 eprintln!("{err}");
                 ExitCode::FAILURE
             }
+            Ok(values) if values.code.is_none() && tasks.is_deadlocked() => {
+                eprintln!(
+                    "Deadlock detected: all remaining tasks are suspended, \
+                    with no timers or other work pending that could resume them\n{tasks}"
+                );
+                ExitCode::FAILURE
+            }
             Ok(values) => ExitCode::from(values.status()),
         })
     }
@@ -111,11 +111,13 @@ async fn generate_typedef_files_from_definitions() -> Result<String> {
     let cache_dir = std::env::current_dir()?.join(".lux").join("types");
     dirs_to_write.push(cache_dir.clone());
 
-    // Make typedef files
-    for builtin in lux_std::LuxStandardLibrary::ALL {
-        let name = builtin.name().to_lowercase();
-        let path = cache_dir.join(&name).with_extension("luau");
-        files_to_write.push((name, path, builtin.typedefs()));
+    // Make typedef files, for both libraries and globals
+    for (name, contents) in lux_std::all_typedefs() {
+        let path = cache_dir.join(name.to_lowercase()).with_extension("luau");
+        if let Some(parent) = path.parent() {
+            dirs_to_write.push(parent.to_path_buf());
+        }
+        files_to_write.push((name, path, contents));
     }
 
     // Write all dirs and files
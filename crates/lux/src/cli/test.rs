@@ -0,0 +1,289 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use async_executor::LocalExecutor;
+use async_fs as fs;
+use clap::Parser;
+use console::style;
+use mlua::prelude::*;
+
+use lux::{RunContext, Runtime};
+
+use super::utils::discover::{discover_files, has_suffix};
+
+mod report;
+
+use self::report::{FileReport, ReportFormat, TestOutcome, TestReport, TestStatus};
+
+const TEST_FILE_SUFFIXES: &[&str] = &[".spec.luau", ".spec.lua", "_test.luau", "_test.lua"];
+
+/// Run tests
+#[derive(Debug, Clone, Parser)]
+pub struct TestCommand {
+    /// Test files, or directories to search for `*.spec.luau` and `*_test.luau`
+    /// files in - defaults to the current directory
+    pub paths: Vec<PathBuf>,
+
+    /// Only run tests with full names containing this string
+    #[clap(short, long)]
+    pub filter: Option<String>,
+
+    /// Maximum number of test files to run at the same time
+    #[clap(short, long, default_value_t = 4)]
+    pub jobs: usize,
+
+    /// Write a report of the results in the given format
+    #[clap(short, long, value_enum)]
+    pub reporter: Option<ReportFormat>,
+
+    /// The path to write the report to - defaults to stdout,
+    /// in which case other output is written to stderr instead
+    #[clap(short, long, requires = "reporter")]
+    pub output: Option<PathBuf>,
+}
+
+impl TestCommand {
+    pub async fn run(self) -> Result<ExitCode> {
+        let paths = if self.paths.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            self.paths.clone()
+        };
+        let files = discover_files(&paths, |path| has_suffix(path, TEST_FILE_SUFFIXES)).await?;
+        if files.is_empty() {
+            eprintln!("No test files found.");
+            return Ok(ExitCode::FAILURE);
+        }
+
+        // Keep stdout clean when the report is written to it
+        let report_to_stdout = self.reporter.is_some() && self.output.is_none();
+        let print = |text: &str| {
+            if report_to_stdout {
+                eprint!("{text}");
+            } else {
+                print!("{text}");
+            }
+        };
+
+        let started = Instant::now();
+        let files = self
+            .run_files(files, |file| print(&format_file_report(file)))
+            .await;
+        let report = TestReport::new(files, started.elapsed().as_secs_f64());
+        print(&format_summary(&report));
+
+        if let Some(format) = self.reporter {
+            let contents = report.format(format);
+            match &self.output {
+                Some(path) => fs::write(path, contents).await.with_context(|| {
+                    format!("Failed to write test report to \"{}\"", path.display())
+                })?,
+                None => print!("{contents}"),
+            }
+        }
+
+        Ok(if report.summary.success() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        })
+    }
+
+    /**
+        Runs all of the given test files, each in its own runtime, with up to
+        `jobs` files running at the same time on a single-threaded executor.
+
+        Files run concurrently whenever a test yields, such as while waiting
+        on a timer or network request, and `on_complete` is called as soon as
+        each file finishes. The returned reports are in the same order as `files`.
+    */
+    async fn run_files(
+        &self,
+        files: Vec<PathBuf>,
+        on_complete: impl Fn(&FileReport),
+    ) -> Vec<FileReport> {
+        let queue = RefCell::new(files.into_iter().enumerate().collect::<VecDeque<_>>());
+        let reports = RefCell::new(Vec::new());
+
+        let executor = LocalExecutor::new();
+        let workers = (0..self.jobs.max(1))
+            .map(|_| {
+                executor.spawn(async {
+                    loop {
+                        let next = queue.borrow_mut().pop_front();
+                        let Some((index, path)) = next else {
+                            break;
+                        };
+                        let report = run_test_file(&path, self.filter.as_deref()).await;
+                        on_complete(&report);
+                        reports.borrow_mut().push((index, report));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        executor
+            .run(async {
+                for worker in workers {
+                    worker.await;
+                }
+            })
+            .await;
+        drop(executor);
+
+        let mut reports = reports.into_inner();
+        reports.sort_by_key(|(index, _)| *index);
+        reports.into_iter().map(|(_, report)| report).collect()
+    }
+}
+
+async fn run_test_file(path: &Path, filter: Option<&str>) -> FileReport {
+    let started = Instant::now();
+    let (tests, error) = match collect_outcomes(path, filter).await {
+        Ok(tests) => (tests, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    FileReport {
+        path: path.display().to_string(),
+        duration: started.elapsed().as_secs_f64(),
+        error,
+        tests,
+    }
+}
+
+/**
+    Runs a test file to register its tests, and then runs the
+    registered tests using the `@lux/test` library in the same runtime.
+*/
+async fn collect_outcomes(path: &Path, filter: Option<&str>) -> Result<Vec<TestOutcome>, String> {
+    let mut rt = Runtime::new()
+        .map_err(|e| e.to_string())?
+        .with_args(Vec::<String>::new())
+        .with_run_context(RunContext::Test);
+
+    let loaded = rt.run_file(path).await.map_err(|e| e.to_string())?;
+    if !loaded.success() {
+        return Err(String::from(
+            "Test file errored while registering tests, see the output above",
+        ));
+    }
+
+    let filter = filter.map_or_else(|| String::from("nil"), lua_string_literal);
+    let chunk = format!("return require(\"@lux/test\").run({filter})");
+    let ran = rt
+        .run_custom("lux test", chunk)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match ran.values.front() {
+        Some(LuaValue::Table(results)) if ran.success() => results
+            .sequence_values::<TestOutcome>()
+            .collect::<LuaResult<Vec<_>>>()
+            .map_err(|e| e.to_string()),
+        _ => Err(String::from(
+            "Tests errored while running, see the output above",
+        )),
+    }
+}
+
+fn lua_string_literal(s: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in s.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                literal.push('\\');
+                literal.push(char::from(byte));
+            }
+            b' '..=b'~' => literal.push(char::from(byte)),
+            _ => {
+                let _ = write!(literal, "\\{byte:03}");
+            }
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn format_file_report(file: &FileReport) -> String {
+    let mut text = String::new();
+    let label = if file.failed() {
+        style(" FAIL ").black().on_red().bold()
+    } else {
+        style(" PASS ").black().on_green().bold()
+    };
+    let _ = writeln!(
+        text,
+        "{label} {} {}",
+        file.path,
+        style(format!("({:.2}s)", file.duration)).dim()
+    );
+    if let Some(error) = &file.error {
+        let _ = writeln!(text, "  {}", style(error).red());
+    }
+    for test in &file.tests {
+        match test.status {
+            TestStatus::Passed => {
+                let _ = writeln!(text, "  {} {}", style("✓").green(), test.name);
+            }
+            TestStatus::Skipped => {
+                let _ = writeln!(
+                    text,
+                    "  {} {}",
+                    style("-").yellow(),
+                    style(&test.name).dim()
+                );
+            }
+            TestStatus::Failed => {
+                let _ = writeln!(text, "  {} {}", style("✕").red(), test.name);
+                if let Some(error) = &test.error {
+                    for line in error.lines() {
+                        let _ = writeln!(text, "      {}", style(line).red());
+                    }
+                }
+            }
+        }
+    }
+    text
+}
+
+fn format_summary(report: &TestReport) -> String {
+    let s = &report.summary;
+    let mut counts = Vec::new();
+    if s.failed > 0 {
+        counts.push(
+            style(format!("{} failed", s.failed))
+                .red()
+                .bold()
+                .to_string(),
+        );
+    }
+    if s.skipped > 0 {
+        counts.push(style(format!("{} skipped", s.skipped)).yellow().to_string());
+    }
+    counts.push(style(format!("{} passed", s.passed)).green().to_string());
+
+    let failed_files = report.files.iter().filter(|f| f.failed()).count();
+    let mut text = String::from("\n");
+    let _ = writeln!(
+        text,
+        "Tests: {}, {} total",
+        counts.join(", "),
+        s.passed + s.failed + s.skipped
+    );
+    let _ = writeln!(
+        text,
+        "Files: {} failed, {} passed, {} total",
+        failed_files,
+        report.files.len() - failed_files,
+        report.files.len()
+    );
+    let _ = writeln!(text, "Time:  {:.2}s", report.duration);
+    text
+}
@@ -0,0 +1,204 @@
+use std::fmt::Write as _;
+
+use clap::ValueEnum;
+use mlua::prelude::*;
+use serde::Serialize;
+
+/// Format of a test report written for CI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// `JUnit` XML, understood by most CI systems
+    Junit,
+    /// JSON, containing the same information as shown in the terminal
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/**
+    The result of a single test, as returned by `run` in the `@lux/test` library.
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct TestOutcome {
+    pub name: String,
+    pub status: TestStatus,
+    pub duration: f64,
+    pub error: Option<String>,
+}
+
+impl FromLua for TestOutcome {
+    fn from_lua(value: LuaValue, _: &Lua) -> LuaResult<Self> {
+        let LuaValue::Table(t) = value else {
+            return Err(LuaError::FromLuaConversionError {
+                from: value.type_name(),
+                to: "TestOutcome".to_string(),
+                message: None,
+            });
+        };
+        let status = match t.get::<String>("status")?.as_str() {
+            "passed" => TestStatus::Passed,
+            "failed" => TestStatus::Failed,
+            _ => TestStatus::Skipped,
+        };
+        Ok(Self {
+            name: t.get("name")?,
+            status,
+            duration: t.get("duration")?,
+            error: t.get("error")?,
+        })
+    }
+}
+
+/**
+    The results of all tests in a single test file.
+
+    If the file itself failed to load, `error` is set and there are no tests.
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: String,
+    pub duration: f64,
+    pub error: Option<String>,
+    pub tests: Vec<TestOutcome>,
+}
+
+impl FileReport {
+    pub fn count(&self, status: TestStatus) -> usize {
+        self.tests.iter().filter(|t| t.status == status).count()
+    }
+
+    pub fn failed(&self) -> bool {
+        self.error.is_some() || self.count(TestStatus::Failed) > 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Summary {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub errored_files: usize,
+}
+
+impl Summary {
+    pub fn success(&self) -> bool {
+        self.failed == 0 && self.errored_files == 0
+    }
+}
+
+/**
+    The results of a full `lux test` run.
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct TestReport {
+    pub summary: Summary,
+    pub duration: f64,
+    pub files: Vec<FileReport>,
+}
+
+impl TestReport {
+    pub fn new(files: Vec<FileReport>, duration: f64) -> Self {
+        let mut summary = Summary::default();
+        for file in &files {
+            summary.passed += file.count(TestStatus::Passed);
+            summary.failed += file.count(TestStatus::Failed);
+            summary.skipped += file.count(TestStatus::Skipped);
+            summary.errored_files += usize::from(file.error.is_some());
+        }
+        Self {
+            summary,
+            duration,
+            files,
+        }
+    }
+
+    pub fn format(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Junit => self.to_junit(),
+            ReportFormat::Json => {
+                serde_json::to_string_pretty(self).expect("test reports should always serialize")
+            }
+        }
+    }
+
+    fn to_junit(&self) -> String {
+        let s = &self.summary;
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"lux\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            s.passed + s.failed + s.skipped,
+            s.failed,
+            s.errored_files,
+            s.skipped,
+            self.duration
+        );
+        for file in &self.files {
+            let path = escape_xml(&file.path);
+            let _ = writeln!(
+                xml,
+                "  <testsuite name=\"{path}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+                file.tests.len(),
+                file.count(TestStatus::Failed),
+                usize::from(file.error.is_some()),
+                file.count(TestStatus::Skipped),
+                file.duration
+            );
+            if let Some(error) = &file.error {
+                let _ = writeln!(
+                    xml,
+                    "    <testcase name=\"(file)\" classname=\"{path}\" time=\"{:.3}\">\n      <error message=\"Test file failed to run\">{}</error>\n    </testcase>",
+                    file.duration,
+                    escape_xml(error)
+                );
+            }
+            for test in &file.tests {
+                let _ = write!(
+                    xml,
+                    "    <testcase name=\"{}\" classname=\"{path}\" time=\"{:.3}\"",
+                    escape_xml(&test.name),
+                    test.duration
+                );
+                match (test.status, &test.error) {
+                    (TestStatus::Passed, _) => xml.push_str(" />\n"),
+                    (TestStatus::Skipped, _) => {
+                        xml.push_str(">\n      <skipped />\n    </testcase>\n");
+                    }
+                    (TestStatus::Failed, error) => {
+                        let error = escape_xml(error.as_deref().unwrap_or_default());
+                        let _ = writeln!(
+                            xml,
+                            ">\n      <failure message=\"{error}\">{error}</failure>\n    </testcase>"
+                        );
+                    }
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML at all
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,68 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use async_fs as fs;
+use futures_lite::prelude::*;
+
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target"];
+
+fn is_skipped_dir(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.') || SKIPPED_DIRS.contains(&name))
+}
+
+/**
+    Returns `true` if the file name of the given path ends with any of the given suffixes.
+*/
+pub fn has_suffix(path: &Path, suffixes: &[&str]) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| suffixes.iter().any(|s| name.ends_with(s)))
+}
+
+/**
+    Finds all files in the given paths for which `is_match` returns `true`, sorted by path.
+
+    Directories are searched recursively, skipping hidden directories, `node_modules`
+    and `target`. Files given directly are always included, even if they do not match.
+*/
+pub async fn discover_files(
+    paths: &[PathBuf],
+    is_match: impl Fn(&Path) -> bool,
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+
+    for path in paths {
+        let meta = fs::metadata(path)
+            .await
+            .with_context(|| format!("Failed to find path \"{}\"", path.display()))?;
+        if meta.is_dir() {
+            dirs.push(path.clone());
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    while let Some(dir) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("Failed to read directory \"{}\"", dir.display()))?;
+        while let Some(entry) = entries.try_next().await? {
+            let path = entry.path();
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                if !is_skipped_dir(&path) {
+                    dirs.push(path);
+                }
+            } else if is_match(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
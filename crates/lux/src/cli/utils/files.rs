@@ -7,6 +7,15 @@ use directories::UserDirs;
 use lux_utils::path::{get_current_dir, LuauFilePath, LuauModulePath};
 
 const LUX_COMMENT_PREFIX: &str = "-->";
+const BYTECODE_CACHE_DIR: &str = ".lux-cache";
+
+/**
+    Returns the directory that compiled bytecode is cached in,
+    which is the `.lux-cache` directory in the current directory.
+*/
+pub fn bytecode_cache_dir() -> PathBuf {
+    get_current_dir().join(BYTECODE_CACHE_DIR)
+}
 
 /**
     Discovers a script file path based on a given module path *or* file path.
@@ -1,2 +1,3 @@
+pub mod discover;
 pub mod files;
 pub mod listing;
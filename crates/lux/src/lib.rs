@@ -7,4 +7,61 @@ mod rt;
 #[cfg(test)]
 mod tests;
 
-pub use crate::rt::{Runtime, RuntimeError, RuntimeResult, RuntimeReturnValues};
+pub use crate::rt::{
+    HostFunctions, HostModule, Runtime, RuntimeBuilder, RuntimeError, RuntimeResult,
+    RuntimeReturnValues, TaskDump,
+};
+pub use lux_color::Color3;
+#[cfg(any(
+    feature = "std-fs",
+    feature = "std-luau",
+    feature = "std-process",
+    feature = "std-regex",
+    feature = "std-serde",
+    feature = "std-stdio",
+    feature = "std-ffi",
+    feature = "std-signal",
+    feature = "std-uuid",
+    feature = "std-noise",
+    feature = "std-base64",
+    feature = "std-crypto",
+    feature = "std-image",
+    feature = "std-websocket",
+    feature = "std-socket",
+    feature = "std-stream",
+    feature = "std-test",
+    feature = "std-fmt",
+    feature = "std-buffer-extra",
+    feature = "std-pathfind",
+    feature = "std-random",
+    feature = "std-profiler",
+    feature = "std-gc",
+    feature = "std-env",
+    feature = "std-term",
+    feature = "std-desktop",
+    feature = "std-bindgen",
+    feature = "std-inspect",
+    feature = "std-easing",
+    feature = "std-layout",
+    feature = "std-tablex",
+    feature = "std-csv",
+    feature = "std-semver",
+    feature = "std-winreg",
+    feature = "std-evdev",
+    feature = "std-autoinput",
+    feature = "std-hotkey",
+    feature = "std-time",
+    feature = "std-channel",
+    feature = "std-sync",
+    feature = "std-binparse",
+    feature = "std-serve",
+    feature = "std-template",
+    feature = "std-reactor",
+))]
+pub use lux_std::LuxStandardLibrary;
+pub use lux_utils::LuxError;
+pub use lux_utils::flags::FeatureFlag;
+pub use lux_utils::process::{OsSignal, Permission, ProcessFfiDebug, ProcessShutdown, RunContext};
+pub use lux_utils::profiler;
+pub use lux_vector::{Vector2, Vector3};
+pub use mlua_luau_scheduler::{TaskInfo, TaskStatus};
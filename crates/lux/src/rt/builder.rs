@@ -0,0 +1,553 @@
+use std::time::Duration;
+
+#[cfg(any(
+    feature = "std-fs",
+    feature = "std-luau",
+    feature = "std-process",
+    feature = "std-regex",
+    feature = "std-serde",
+    feature = "std-stdio",
+    feature = "std-ffi",
+    feature = "std-signal",
+    feature = "std-uuid",
+    feature = "std-noise",
+    feature = "std-base64",
+    feature = "std-crypto",
+    feature = "std-image",
+    feature = "std-websocket",
+    feature = "std-socket",
+    feature = "std-stream",
+    feature = "std-test",
+    feature = "std-fmt",
+    feature = "std-buffer-extra",
+    feature = "std-pathfind",
+    feature = "std-random",
+    feature = "std-profiler",
+    feature = "std-gc",
+    feature = "std-env",
+    feature = "std-term",
+    feature = "std-desktop",
+    feature = "std-bindgen",
+    feature = "std-inspect",
+    feature = "std-easing",
+    feature = "std-layout",
+    feature = "std-tablex",
+    feature = "std-csv",
+    feature = "std-semver",
+    feature = "std-winreg",
+    feature = "std-evdev",
+    feature = "std-autoinput",
+    feature = "std-hotkey",
+    feature = "std-time",
+    feature = "std-channel",
+    feature = "std-sync",
+    feature = "std-binparse",
+    feature = "std-serve",
+    feature = "std-template",
+    feature = "std-reactor",
+))]
+use lux_std::LuxStandardLibrary;
+use mlua::prelude::*;
+
+use super::Runtime;
+
+type GlobalFactory = Box<dyn FnOnce(&Lua) -> LuaResult<LuaValue>>;
+
+/**
+    A builder for a Lux runtime, controlling which capabilities scripts get.
+
+    By default, every standard library is available, the same as [`Runtime::new`].
+    Applications embedding Lux to run untrusted scripts should use [`RuntimeBuilder::sandbox`]
+    together with memory and time limits, and only expose what those scripts need.
+
+    # Example Usage
+
+    ```rs
+    let rt = Runtime::builder()
+        .sandbox(true)
+        .without_library(LuxStandardLibrary::Socket)
+        .memory_limit(64 * 1024 * 1024)
+        .time_limit(Duration::from_secs(5))
+        .with_global("APP_NAME", |lua| "my-app".into_lua(lua))
+        .build()?;
+    ```
+*/
+pub struct RuntimeBuilder {
+    #[cfg(any(
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-ffi",
+        feature = "std-signal",
+        feature = "std-uuid",
+        feature = "std-noise",
+        feature = "std-base64",
+        feature = "std-crypto",
+        feature = "std-image",
+        feature = "std-websocket",
+        feature = "std-socket",
+        feature = "std-stream",
+        feature = "std-test",
+        feature = "std-fmt",
+        feature = "std-buffer-extra",
+        feature = "std-pathfind",
+        feature = "std-random",
+        feature = "std-profiler",
+        feature = "std-gc",
+        feature = "std-env",
+        feature = "std-term",
+        feature = "std-desktop",
+        feature = "std-bindgen",
+        feature = "std-inspect",
+        feature = "std-easing",
+        feature = "std-layout",
+        feature = "std-tablex",
+        feature = "std-csv",
+        feature = "std-semver",
+        feature = "std-winreg",
+        feature = "std-evdev",
+        feature = "std-autoinput",
+        feature = "std-hotkey",
+        feature = "std-time",
+        feature = "std-channel",
+        feature = "std-sync",
+        feature = "std-binparse",
+        feature = "std-serve",
+        feature = "std-template",
+        feature = "std-reactor",
+    ))]
+    libraries: Vec<LuxStandardLibrary>,
+    sandbox: bool,
+    memory_limit: Option<usize>,
+    time_limit: Option<Duration>,
+    globals: Vec<(String, GlobalFactory)>,
+}
+
+impl RuntimeBuilder {
+    /**
+        Creates a new runtime builder, with all standard libraries enabled.
+    */
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            #[cfg(any(
+                feature = "std-fs",
+                feature = "std-luau",
+                feature = "std-process",
+                feature = "std-regex",
+                feature = "std-serde",
+                feature = "std-stdio",
+                feature = "std-ffi",
+                feature = "std-signal",
+                feature = "std-uuid",
+                feature = "std-noise",
+                feature = "std-base64",
+                feature = "std-crypto",
+                feature = "std-image",
+                feature = "std-websocket",
+                feature = "std-socket",
+                feature = "std-stream",
+                feature = "std-test",
+                feature = "std-fmt",
+                feature = "std-buffer-extra",
+                feature = "std-pathfind",
+                feature = "std-random",
+                feature = "std-profiler",
+                feature = "std-gc",
+                feature = "std-env",
+                feature = "std-term",
+                feature = "std-desktop",
+                feature = "std-bindgen",
+                feature = "std-inspect",
+                feature = "std-easing",
+                feature = "std-layout",
+                feature = "std-tablex",
+                feature = "std-csv",
+                feature = "std-semver",
+                feature = "std-winreg",
+                feature = "std-evdev",
+                feature = "std-autoinput",
+                feature = "std-hotkey",
+                feature = "std-time",
+                feature = "std-channel",
+                feature = "std-sync",
+                feature = "std-binparse",
+                feature = "std-serve",
+                feature = "std-template",
+                feature = "std-reactor",
+            ))]
+            libraries: LuxStandardLibrary::ALL.to_vec(),
+            sandbox: false,
+            memory_limit: None,
+            time_limit: None,
+            globals: Vec::new(),
+        }
+    }
+
+    /**
+        Makes a standard library available through `require("@lux/...")`.
+
+        Libraries are all enabled by default, so this is mostly useful
+        after [`RuntimeBuilder::without_libraries`].
+    */
+    #[must_use]
+    #[cfg(any(
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-ffi",
+        feature = "std-signal",
+        feature = "std-uuid",
+        feature = "std-noise",
+        feature = "std-base64",
+        feature = "std-crypto",
+        feature = "std-image",
+        feature = "std-websocket",
+        feature = "std-socket",
+        feature = "std-stream",
+        feature = "std-test",
+        feature = "std-fmt",
+        feature = "std-buffer-extra",
+        feature = "std-pathfind",
+        feature = "std-random",
+        feature = "std-profiler",
+        feature = "std-gc",
+        feature = "std-env",
+        feature = "std-term",
+        feature = "std-desktop",
+        feature = "std-bindgen",
+        feature = "std-inspect",
+        feature = "std-easing",
+        feature = "std-layout",
+        feature = "std-tablex",
+        feature = "std-csv",
+        feature = "std-semver",
+        feature = "std-winreg",
+        feature = "std-evdev",
+        feature = "std-autoinput",
+        feature = "std-hotkey",
+        feature = "std-time",
+        feature = "std-channel",
+        feature = "std-sync",
+        feature = "std-binparse",
+        feature = "std-serve",
+        feature = "std-template",
+        feature = "std-reactor",
+    ))]
+    pub fn with_library(mut self, library: LuxStandardLibrary) -> Self {
+        if !self.libraries.contains(&library) {
+            self.libraries.push(library);
+        }
+        self
+    }
+
+    /**
+        Removes a standard library, making `require("@lux/...")` fail for it.
+    */
+    #[must_use]
+    #[cfg(any(
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-ffi",
+        feature = "std-signal",
+        feature = "std-uuid",
+        feature = "std-noise",
+        feature = "std-base64",
+        feature = "std-crypto",
+        feature = "std-image",
+        feature = "std-websocket",
+        feature = "std-socket",
+        feature = "std-stream",
+        feature = "std-test",
+        feature = "std-fmt",
+        feature = "std-buffer-extra",
+        feature = "std-pathfind",
+        feature = "std-random",
+        feature = "std-profiler",
+        feature = "std-gc",
+        feature = "std-env",
+        feature = "std-term",
+        feature = "std-desktop",
+        feature = "std-bindgen",
+        feature = "std-inspect",
+        feature = "std-easing",
+        feature = "std-layout",
+        feature = "std-tablex",
+        feature = "std-csv",
+        feature = "std-semver",
+        feature = "std-winreg",
+        feature = "std-evdev",
+        feature = "std-autoinput",
+        feature = "std-hotkey",
+        feature = "std-time",
+        feature = "std-channel",
+        feature = "std-sync",
+        feature = "std-binparse",
+        feature = "std-serve",
+        feature = "std-template",
+        feature = "std-reactor",
+    ))]
+    pub fn without_library(mut self, library: LuxStandardLibrary) -> Self {
+        self.libraries.retain(|l| *l != library);
+        self
+    }
+
+    /**
+        Removes all standard libraries, so that only the
+        ones added using [`RuntimeBuilder::with_library`] are available.
+    */
+    #[must_use]
+    #[cfg(any(
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-ffi",
+        feature = "std-signal",
+        feature = "std-uuid",
+        feature = "std-noise",
+        feature = "std-base64",
+        feature = "std-crypto",
+        feature = "std-image",
+        feature = "std-websocket",
+        feature = "std-socket",
+        feature = "std-stream",
+        feature = "std-test",
+        feature = "std-fmt",
+        feature = "std-buffer-extra",
+        feature = "std-pathfind",
+        feature = "std-random",
+        feature = "std-profiler",
+        feature = "std-gc",
+        feature = "std-env",
+        feature = "std-term",
+        feature = "std-desktop",
+        feature = "std-bindgen",
+        feature = "std-inspect",
+        feature = "std-easing",
+        feature = "std-layout",
+        feature = "std-tablex",
+        feature = "std-csv",
+        feature = "std-semver",
+        feature = "std-winreg",
+        feature = "std-evdev",
+        feature = "std-autoinput",
+        feature = "std-hotkey",
+        feature = "std-time",
+        feature = "std-channel",
+        feature = "std-sync",
+        feature = "std-binparse",
+        feature = "std-serve",
+        feature = "std-template",
+        feature = "std-reactor",
+    ))]
+    pub fn without_libraries(mut self) -> Self {
+        self.libraries.clear();
+        self
+    }
+
+    /**
+        Enables or disables sandboxing for untrusted scripts.
+
+        A sandboxed runtime never exposes the `ffi`, `process`, `fs`, `env`, `desktop`
+        and `bindgen` standard libraries, even if they were explicitly added, since any
+        of those can be used to escape the sandbox and access the host system.
+    */
+    #[must_use]
+    pub fn sandbox(mut self, enabled: bool) -> Self {
+        self.sandbox = enabled;
+        self
+    }
+
+    /**
+        Limits the amount of memory the Luau VM may allocate, in bytes.
+
+        Allocations past the limit fail with a memory error in the script.
+    */
+    #[must_use]
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
+    /**
+        Limits how long a script may run for, measured from the start of each run.
+
+        The limit is checked by an interrupt in the Luau VM, so scripts stuck in
+        loops are stopped too - any script code running past the limit errors.
+    */
+    #[must_use]
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /**
+        Adds a custom global, created when the runtime is built.
+
+        Custom globals are added after the standard ones, and may replace them.
+    */
+    #[must_use]
+    pub fn with_global<S, F>(mut self, name: S, make_global: F) -> Self
+    where
+        S: Into<String>,
+        F: FnOnce(&Lua) -> LuaResult<LuaValue> + 'static,
+    {
+        self.globals.push((name.into(), Box::new(make_global)));
+        self
+    }
+
+    /**
+        Builds the runtime, with a new Luau VM.
+
+        # Errors
+
+        - If out of memory or other memory-related errors occur
+        - If any of the standard globals fail to inject
+        - If any of the custom globals fail to be created
+    */
+    pub fn build(self) -> LuaResult<Runtime> {
+        #[cfg(any(
+            feature = "std-fs",
+            feature = "std-luau",
+            feature = "std-process",
+            feature = "std-regex",
+            feature = "std-serde",
+            feature = "std-stdio",
+            feature = "std-ffi",
+            feature = "std-signal",
+            feature = "std-uuid",
+            feature = "std-noise",
+            feature = "std-base64",
+            feature = "std-crypto",
+            feature = "std-image",
+            feature = "std-websocket",
+            feature = "std-socket",
+            feature = "std-stream",
+            feature = "std-test",
+            feature = "std-fmt",
+            feature = "std-buffer-extra",
+            feature = "std-pathfind",
+            feature = "std-random",
+            feature = "std-profiler",
+            feature = "std-gc",
+            feature = "std-env",
+            feature = "std-term",
+            feature = "std-desktop",
+            feature = "std-bindgen",
+            feature = "std-inspect",
+            feature = "std-easing",
+            feature = "std-layout",
+            feature = "std-tablex",
+            feature = "std-csv",
+            feature = "std-semver",
+            feature = "std-winreg",
+            feature = "std-evdev",
+            feature = "std-autoinput",
+            feature = "std-hotkey",
+            feature = "std-time",
+            feature = "std-channel",
+            feature = "std-sync",
+            feature = "std-binparse",
+            feature = "std-serve",
+            feature = "std-template",
+            feature = "std-reactor",
+        ))]
+        let libraries = if self.sandbox {
+            self.libraries
+                .into_iter()
+                .filter(|l| {
+                    !matches!(
+                        l.name(),
+                        "ffi" | "process" | "fs" | "env" | "desktop" | "bindgen"
+                    )
+                })
+                .collect()
+        } else {
+            self.libraries
+        };
+
+        let mut runtime = Runtime::create(
+            #[cfg(any(
+                feature = "std-fs",
+                feature = "std-luau",
+                feature = "std-process",
+                feature = "std-regex",
+                feature = "std-serde",
+                feature = "std-stdio",
+                feature = "std-ffi",
+                feature = "std-signal",
+                feature = "std-uuid",
+                feature = "std-noise",
+                feature = "std-base64",
+                feature = "std-crypto",
+                feature = "std-image",
+                feature = "std-websocket",
+                feature = "std-socket",
+                feature = "std-stream",
+                feature = "std-test",
+                feature = "std-fmt",
+                feature = "std-buffer-extra",
+                feature = "std-pathfind",
+                feature = "std-random",
+                feature = "std-profiler",
+                feature = "std-gc",
+                feature = "std-env",
+                feature = "std-term",
+                feature = "std-desktop",
+                feature = "std-bindgen",
+                feature = "std-inspect",
+                feature = "std-easing",
+                feature = "std-layout",
+                feature = "std-tablex",
+                feature = "std-csv",
+                feature = "std-semver",
+                feature = "std-winreg",
+                feature = "std-evdev",
+                feature = "std-autoinput",
+                feature = "std-hotkey",
+                feature = "std-time",
+                feature = "std-channel",
+                feature = "std-sync",
+                feature = "std-binparse",
+                feature = "std-serve",
+                feature = "std-template",
+                feature = "std-reactor",
+            ))]
+            libraries,
+        )?;
+        runtime.set_execution_limit(self.time_limit);
+
+        let lua = runtime.lua();
+        for (name, make_global) in self.globals {
+            let value = make_global(lua)
+                .map_err(|e| e.context(format!("Failed to create global '{name}'")))?;
+            lua.globals().set(name, value)?;
+        }
+
+        // NOTE: Set last, so that creating the runtime itself is never limited
+        if let Some(limit) = self.memory_limit {
+            lua.set_memory_limit(limit)?;
+        }
+
+        Ok(runtime)
+    }
+}
+
+impl Default for RuntimeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,116 @@
+use std::{cell::RefCell, rc::Rc};
+
+use mlua::prelude::*;
+
+/**
+    A Rust struct exposed to scripts as a library, see [`Runtime::register_module`].
+
+    The functions of the module share a single instance of the struct, which
+    they get a reference to in place of the `&Lua` that mlua functions get.
+
+    # Example Usage
+
+    ```rs
+    #[derive(Default)]
+    struct Counter {
+        count: i64,
+    }
+
+    impl HostModule for Counter {
+        fn add_functions(functions: &mut HostFunctions<Self>) -> LuaResult<()> {
+            functions.add_mut("increment", |this, by: Option<i64>| {
+                this.count += by.unwrap_or(1);
+                Ok(this.count)
+            })?;
+            functions.add("get", |this, ()| Ok(this.count))?;
+            Ok(())
+        }
+    }
+
+    rt.register_module("@app/counter", Counter::default())?;
+    ```
+
+    [`Runtime::register_module`]: crate::Runtime::register_module
+*/
+pub trait HostModule: Sized + 'static {
+    /**
+        Adds the functions that make up the module.
+
+        # Errors
+
+        Errors if any of the functions fail to be created.
+    */
+    fn add_functions(functions: &mut HostFunctions<Self>) -> LuaResult<()>;
+}
+
+/**
+    The functions of a [`HostModule`], as they are being added.
+*/
+pub struct HostFunctions<T> {
+    lua: Lua,
+    state: Rc<RefCell<T>>,
+    table: LuaTable,
+}
+
+impl<T: 'static> HostFunctions<T> {
+    pub(super) fn new(lua: &Lua, state: T) -> LuaResult<Self> {
+        Ok(Self {
+            lua: lua.clone(),
+            state: Rc::new(RefCell::new(state)),
+            table: lua.create_table()?,
+        })
+    }
+
+    pub(super) fn into_table(self) -> LuaTable {
+        self.table.set_readonly(true);
+        self.table
+    }
+
+    /**
+        Adds a function that reads the module struct.
+
+        # Errors
+
+        Errors if the function fails to be created.
+    */
+    pub fn add<A, R, F>(&mut self, name: &str, func: F) -> LuaResult<&mut Self>
+    where
+        A: FromLuaMulti,
+        R: IntoLuaMulti,
+        F: Fn(&T, A) -> LuaResult<R> + 'static,
+    {
+        let state = Rc::clone(&self.state);
+        let function = self.lua.create_function(move |_, args: A| {
+            let this = state.try_borrow().map_err(|_| {
+                LuaError::runtime("Module is being modified by another function call")
+            })?;
+            func(&this, args)
+        })?;
+        self.table.raw_set(name, function)?;
+        Ok(self)
+    }
+
+    /**
+        Adds a function that modifies the module struct.
+
+        # Errors
+
+        Errors if the function fails to be created.
+    */
+    pub fn add_mut<A, R, F>(&mut self, name: &str, mut func: F) -> LuaResult<&mut Self>
+    where
+        A: FromLuaMulti,
+        R: IntoLuaMulti,
+        F: FnMut(&mut T, A) -> LuaResult<R> + 'static,
+    {
+        let state = Rc::clone(&self.state);
+        let function = self.lua.create_function_mut(move |_, args: A| {
+            let mut this = state
+                .try_borrow_mut()
+                .map_err(|_| LuaError::runtime("Module is being used by another function call"))?;
+            func(&mut this, args)
+        })?;
+        self.table.raw_set(name, function)?;
+        Ok(self)
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use lux_utils::{LuxError, profiler};
+use mlua::prelude::*;
+use mlua_luau_scheduler::LuaSchedulerExt;
+
+use super::tasks::TaskDump;
+
+const NOT_TRIPPED: u64 = u64::MAX;
+const GRACE_CHECKPOINTS: u64 = 1_000;
+
+/**
+    Execution limits for script code in a runtime.
+
+    Instruction counts are measured in interrupt checkpoints, which Luau
+    hits on every function call and every loop iteration. This is not
+    an exact count of VM instructions, but it grows with the amount of
+    work done, and unlike time, it does not include time spent waiting.
+
+    The same interrupt also samples the call stack for the profiler, when enabled.
+*/
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct ExecutionLimits {
+    pub(super) time: Option<Duration>,
+    pub(super) instructions: Option<u64>,
+    pub(super) task_instructions: Option<u64>,
+    pub(super) profile: bool,
+    pub(super) dump_tasks: bool,
+}
+
+impl ExecutionLimits {
+    fn is_unlimited(self) -> bool {
+        self.time.is_none() && self.instructions.is_none() && self.task_instructions.is_none()
+    }
+
+    fn needs_interrupt(self) -> bool {
+        self.profile || !self.is_unlimited()
+    }
+
+    /**
+        Installs an interrupt enforcing these limits, counting from now.
+
+        Exceeding the time or instruction limit stops the whole runtime, while
+        exceeding the task instruction limit only errors the task that exceeded it.
+        Both raise a [`LuxError::Timeout`], which scripts may catch to clean up, but
+        code that keeps running for long after that gets the same error again.
+    */
+    pub(super) fn install(self, lua: &Lua) -> LuaResult<()> {
+        if !self.needs_interrupt() {
+            lua.remove_interrupt();
+            return Ok(());
+        }
+
+        let started = Instant::now();
+        let executed = AtomicU64::new(0);
+        let tripped_at = AtomicU64::new(NOT_TRIPPED);
+        let task_counts = match self.task_instructions {
+            Some(_) => Some(weak_keyed_table(lua)?),
+            None => None,
+        };
+
+        lua.set_interrupt(move |lua| {
+            if self.profile {
+                profiler::sample(lua);
+            }
+
+            let count = executed.fetch_add(1, Ordering::Relaxed) + 1;
+
+            let exceeded = if let Some(limit) = self.time
+                && started.elapsed() > limit
+            {
+                Some(format!(
+                    "Script exceeded its execution limit of {}s",
+                    limit.as_secs_f64()
+                ))
+            } else if let Some(limit) = self.instructions
+                && count > limit
+            {
+                Some(format!("Script exceeded its instruction limit of {limit}"))
+            } else {
+                None
+            };
+            if let Some(message) = exceeded {
+                let tripped = match tripped_at.compare_exchange(
+                    NOT_TRIPPED,
+                    count,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => count,
+                    Err(tripped) => tripped,
+                };
+                if count == tripped && self.dump_tasks {
+                    report_tasks(lua);
+                }
+                if should_raise(count - tripped) {
+                    lua.set_exit_code(1);
+                    return Err(LuxError::Timeout(message).into());
+                }
+            }
+
+            if let (Some(limit), Some(counts)) = (self.task_instructions, &task_counts) {
+                let thread = lua.current_thread();
+                let count = counts.raw_get::<Option<u64>>(&thread)?.unwrap_or_default() + 1;
+                counts.raw_set(thread, count)?;
+                if count > limit && should_raise(count - limit - 1) {
+                    return Err(LuxError::Timeout(format!(
+                        "Task exceeded its instruction budget of {limit}"
+                    ))
+                    .into());
+                }
+            }
+
+            Ok(LuaVmState::Continue)
+        });
+
+        Ok(())
+    }
+}
+
+/**
+    Prints the live tasks of a runtime that exceeded its limits to stderr.
+*/
+pub(super) fn report_tasks(lua: &Lua) {
+    eprintln!("{}", TaskDump::new(lua.dump_tasks()));
+}
+
+/**
+    Returns whether to raise a timeout, given the number of
+    checkpoints that have passed since the limit was first exceeded.
+
+    The error is raised once, and then not again until the grace period
+    is over, which gives scripts catching it a chance to clean up.
+*/
+fn should_raise(checkpoints_since_exceeded: u64) -> bool {
+    checkpoints_since_exceeded == 0 || checkpoints_since_exceeded > GRACE_CHECKPOINTS
+}
+
+/**
+    Creates a table with weak keys, so that counts for finished tasks get collected.
+*/
+fn weak_keyed_table(lua: &Lua) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    let meta = lua.create_table()?;
+    meta.raw_set("__mode", "k")?;
+    table.set_metatable(Some(meta))?;
+    Ok(table)
+}
@@ -1,5 +1,13 @@
+mod builder;
+mod host;
+mod limits;
 mod result;
 mod runtime;
+mod tasks;
+
+pub use self::builder::RuntimeBuilder;
+pub use self::host::{HostFunctions, HostModule};
 
 pub use self::result::{RuntimeError, RuntimeResult};
 pub use self::runtime::{Runtime, RuntimeReturnValues};
+pub use self::tasks::TaskDump;
@@ -5,7 +5,7 @@ use std::{
 
 use mlua::prelude::*;
 
-use lux_utils::fmt::ErrorComponents;
+use lux_utils::{LuxError, fmt::ErrorComponents};
 
 pub type RuntimeResult<T, E = RuntimeError> = Result<T, E>;
 
@@ -58,6 +58,19 @@ impl RuntimeError {
             }
         )
     }
+
+    /**
+        Returns `true` if the error was caused by script code running past
+        one of the execution limits set on the runtime.
+
+        See [`Runtime::set_execution_limit`] for more information.
+
+        [`Runtime::set_execution_limit`]: crate::Runtime::set_execution_limit
+    */
+    #[must_use]
+    pub fn is_timeout(&self) -> bool {
+        LuxError::from_lua_error(&self.error).is_some_and(LuxError::is_timeout)
+    }
 }
 
 impl From<LuaError> for RuntimeError {
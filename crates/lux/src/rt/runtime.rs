@@ -2,23 +2,86 @@
 
 use std::{
     ffi::OsString,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         Arc,
         atomic::{AtomicBool, Ordering},
     },
+    time::Duration,
 };
 
 use async_fs as fs;
+use async_io::Timer;
+use futures_lite::future::{self, FutureExt};
+#[cfg(any(
+    feature = "std-fs",
+    feature = "std-luau",
+    feature = "std-process",
+    feature = "std-regex",
+    feature = "std-serde",
+    feature = "std-stdio",
+    feature = "std-ffi",
+    feature = "std-signal",
+    feature = "std-uuid",
+    feature = "std-noise",
+    feature = "std-base64",
+    feature = "std-crypto",
+    feature = "std-image",
+    feature = "std-websocket",
+    feature = "std-socket",
+    feature = "std-stream",
+    feature = "std-test",
+    feature = "std-fmt",
+    feature = "std-buffer-extra",
+    feature = "std-pathfind",
+    feature = "std-random",
+    feature = "std-profiler",
+    feature = "std-gc",
+    feature = "std-env",
+    feature = "std-term",
+    feature = "std-desktop",
+    feature = "std-bindgen",
+    feature = "std-inspect",
+    feature = "std-easing",
+    feature = "std-layout",
+    feature = "std-tablex",
+    feature = "std-csv",
+    feature = "std-semver",
+    feature = "std-winreg",
+    feature = "std-evdev",
+    feature = "std-autoinput",
+    feature = "std-hotkey",
+    feature = "std-time",
+    feature = "std-channel",
+    feature = "std-sync",
+    feature = "std-binparse",
+    feature = "std-serve",
+    feature = "std-template",
+    feature = "std-reactor",
+))]
+use lux_std::LuxStandardLibrary;
 use lux_utils::{
-    path::{LuauModulePath, constants::FILE_CHUNK_PREFIX},
-    process::{ProcessArgs, ProcessEnv, ProcessJitEnablement},
+    LuxError,
+    bytecode::{self, BytecodeCache},
+    flags::{FeatureFlag, FeatureFlags},
+    overrides,
+    path::{LuauModulePath, constants::FILE_CHUNK_PREFIX, get_current_dir},
+    process::{
+        Permission, ProcessArgs, ProcessEnv, ProcessFfiDebug, ProcessFfiPoolSize,
+        ProcessFfiSafeMode, ProcessJitEnablement, ProcessPermissions, ProcessReleaseMode,
+        ProcessShutdown, RunContext,
+    },
+    snapshot::ModuleSnapshot,
 };
-use mlua::Compiler;
 use mlua::prelude::*;
+use mlua::{BorrowedStr, MaybeSend, serde::Deserializer as LuaDeserializer};
 use mlua_luau_scheduler::{Functions, Scheduler};
+use serde::de::DeserializeOwned;
 
-use super::{RuntimeError, RuntimeResult};
+use super::{
+    HostFunctions, HostModule, RuntimeBuilder, RuntimeError, RuntimeResult, TaskDump,
+    limits::{ExecutionLimits, report_tasks},
+};
 
 /**
     Values returned by running a Lux runtime until completion.
@@ -33,6 +96,8 @@ pub struct RuntimeReturnValues {
     pub errored: bool,
     /// The final values returned by the main thread.
     pub values: LuaMultiValue,
+    /// How the run was stopped, if it was stopped by `process.shutdown`.
+    pub shutdown: Option<ProcessShutdown>,
 }
 
 impl RuntimeReturnValues {
@@ -59,6 +124,64 @@ impl RuntimeReturnValues {
     pub fn success(&self) -> bool {
         self.status() == 0
     }
+
+    /**
+        Returns the first value returned by the main thread as a string, if it is one.
+    */
+    #[must_use]
+    pub fn as_str(&self) -> Option<BorrowedStr<'_>> {
+        self.values.front()?.as_string()?.to_str().ok()
+    }
+
+    /**
+        Returns the first value returned by the main thread as an integer, if it is one.
+
+        Numbers without a fractional part are also converted.
+    */
+    #[must_use]
+    pub fn as_i64(&self) -> Option<i64> {
+        self.values.front().and_then(LuaValue::as_i64)
+    }
+
+    /**
+        Returns the first value returned by the main thread encoded as JSON, if it is a table.
+
+        Returns `None` if the value is not a table, or if the table contains
+        values that can not be represented as JSON, such as functions.
+    */
+    #[must_use]
+    pub fn as_table_json(&self) -> Option<String> {
+        let table = self.values.front().and_then(LuaValue::as_table)?;
+        serde_json::to_string(table).ok()
+    }
+
+    /**
+        Deserializes the first value returned by the main thread into `T`.
+
+        If the main thread did not return anything, `nil` is deserialized instead,
+        which is valid for types such as `Option<T>` and `()`.
+
+        # Example Usage
+
+        ```rs
+        #[derive(Deserialize)]
+        struct Config {
+            name: String,
+            port: u16,
+        }
+
+        let values = rt.run_custom("config", "return { name = 'lux', port = 8080 }").await?;
+        let config: Config = values.deserialize()?;
+        ```
+
+        # Errors
+
+        Returns an error if the value does not match the shape of `T`.
+    */
+    pub fn deserialize<T: DeserializeOwned>(&self) -> RuntimeResult<T> {
+        let value = self.values.front().cloned().unwrap_or(LuaValue::Nil);
+        Ok(T::deserialize(LuaDeserializer::new(value))?)
+    }
 }
 
 /**
@@ -70,6 +193,62 @@ pub struct Runtime {
     args: ProcessArgs,
     env: ProcessEnv,
     jit: ProcessJitEnablement,
+    release: ProcessReleaseMode,
+    ffi_safe_mode: ProcessFfiSafeMode,
+    ffi_debug: ProcessFfiDebug,
+    ffi_pool_size: ProcessFfiPoolSize,
+    permissions: ProcessPermissions,
+    run_context: RunContext,
+    bytecode_cache: Option<BytecodeCache>,
+    #[cfg(any(
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-ffi",
+        feature = "std-signal",
+        feature = "std-uuid",
+        feature = "std-noise",
+        feature = "std-base64",
+        feature = "std-crypto",
+        feature = "std-image",
+        feature = "std-websocket",
+        feature = "std-socket",
+        feature = "std-stream",
+        feature = "std-test",
+        feature = "std-fmt",
+        feature = "std-buffer-extra",
+        feature = "std-pathfind",
+        feature = "std-random",
+        feature = "std-profiler",
+        feature = "std-gc",
+        feature = "std-env",
+        feature = "std-term",
+        feature = "std-desktop",
+        feature = "std-bindgen",
+        feature = "std-inspect",
+        feature = "std-easing",
+        feature = "std-layout",
+        feature = "std-tablex",
+        feature = "std-csv",
+        feature = "std-semver",
+        feature = "std-winreg",
+        feature = "std-evdev",
+        feature = "std-autoinput",
+        feature = "std-hotkey",
+        feature = "std-time",
+        feature = "std-channel",
+        feature = "std-sync",
+        feature = "std-binparse",
+        feature = "std-serve",
+        feature = "std-template",
+        feature = "std-reactor",
+    ))]
+    libraries: Vec<LuxStandardLibrary>,
+    pub(super) limits: ExecutionLimits,
+    exports: Option<LuaTable>,
 }
 
 impl Runtime {
@@ -78,13 +257,116 @@ impl Runtime {
 
         Injects standard globals and libraries if any of the `std` features are enabled.
 
+        See [`Runtime::builder`] to pick which libraries get injected, or to sandbox the runtime.
+
         # Errors
 
         - If out of memory or other memory-related errors occur
         - If any of the standard globals and libraries fail to inject
     */
     pub fn new() -> LuaResult<Self> {
+        RuntimeBuilder::new().build()
+    }
+
+    /**
+        Creates a new [`RuntimeBuilder`], for fine-grained control over
+        the libraries, globals and limits of the runtime.
+    */
+    #[must_use]
+    pub fn builder() -> RuntimeBuilder {
+        RuntimeBuilder::new()
+    }
+
+    /**
+        Creates a new Lux runtime, like [`Runtime::new`], that restores
+        the modules in a snapshot created by [`Runtime::snapshot`].
+
+        Modules in the snapshot are loaded from their bytecode the first time they are
+        required, instead of being read and compiled, which speeds up starting scripts
+        that require many modules. Luau can not save the state of a VM, so the modules
+        still run again when required.
+
+        # Errors
+
+        - If the bytes are not a valid snapshot
+        - If the runtime fails to be created, see [`Runtime::new`]
+    */
+    pub fn from_snapshot(bytes: impl AsRef<[u8]>) -> LuaResult<Self> {
+        let snapshot = ModuleSnapshot::from_bytes(bytes.as_ref()).into_lua_err()?;
+        let rt = Self::new()?;
+        rt.lua.set_app_data(snapshot);
+        Ok(rt)
+    }
+
+    /**
+        Creates a snapshot of every module that has been required in this runtime
+        so far, as compiled bytecode, to restore using [`Runtime::from_snapshot`].
+
+        Modules are stored by the path of their source file relative to the directory
+        of the first script run using [`Runtime::run_file`], which is stored as well,
+        so that the snapshot can be run without any of them using [`Runtime::run_snapshot`].
+    */
+    #[must_use]
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.lua
+            .app_data_ref::<ModuleSnapshot>()
+            .map(|snapshot| snapshot.to_bytes())
+            .unwrap_or_default()
+    }
+
+    #[allow(clippy::too_many_lines)]
+    pub(super) fn create(
+        #[cfg(any(
+            feature = "std-fs",
+            feature = "std-luau",
+            feature = "std-process",
+            feature = "std-regex",
+            feature = "std-serde",
+            feature = "std-stdio",
+            feature = "std-ffi",
+            feature = "std-signal",
+            feature = "std-uuid",
+            feature = "std-noise",
+            feature = "std-base64",
+            feature = "std-crypto",
+            feature = "std-image",
+            feature = "std-websocket",
+            feature = "std-socket",
+            feature = "std-stream",
+            feature = "std-test",
+            feature = "std-fmt",
+            feature = "std-buffer-extra",
+            feature = "std-pathfind",
+            feature = "std-random",
+            feature = "std-profiler",
+            feature = "std-gc",
+            feature = "std-env",
+            feature = "std-term",
+            feature = "std-desktop",
+            feature = "std-bindgen",
+            feature = "std-inspect",
+            feature = "std-easing",
+            feature = "std-layout",
+            feature = "std-tablex",
+            feature = "std-csv",
+            feature = "std-semver",
+            feature = "std-winreg",
+            feature = "std-evdev",
+            feature = "std-autoinput",
+            feature = "std-hotkey",
+            feature = "std-time",
+            feature = "std-channel",
+            feature = "std-sync",
+            feature = "std-binparse",
+            feature = "std-serve",
+            feature = "std-template",
+            feature = "std-reactor",
+        ))]
+        libraries: Vec<LuxStandardLibrary>,
+    ) -> LuaResult<Self> {
         let lua = Lua::new();
+        lua.set_app_data(FeatureFlags::new());
+        lua.set_app_data(ModuleSnapshot::default());
 
         let sched = Scheduler::new(lua.clone());
         let fns = Functions::new(lua.clone()).expect("has scheduler");
@@ -107,6 +389,39 @@ impl Runtime {
             feature = "std-uuid",
             feature = "std-noise",
             feature = "std-base64",
+            feature = "std-crypto",
+            feature = "std-image",
+            feature = "std-websocket",
+            feature = "std-socket",
+            feature = "std-stream",
+            feature = "std-test",
+            feature = "std-fmt",
+            feature = "std-buffer-extra",
+            feature = "std-pathfind",
+            feature = "std-random",
+            feature = "std-profiler",
+            feature = "std-gc",
+            feature = "std-env",
+            feature = "std-term",
+            feature = "std-desktop",
+            feature = "std-bindgen",
+            feature = "std-inspect",
+            feature = "std-easing",
+            feature = "std-layout",
+            feature = "std-tablex",
+            feature = "std-csv",
+            feature = "std-semver",
+            feature = "std-winreg",
+            feature = "std-evdev",
+            feature = "std-autoinput",
+            feature = "std-hotkey",
+            feature = "std-time",
+            feature = "std-channel",
+            feature = "std-sync",
+            feature = "std-binparse",
+            feature = "std-serve",
+            feature = "std-template",
+            feature = "std-reactor",
         ))]
         {
             lux_std::set_global_version(&lua, env!("CARGO_PKG_VERSION"));
@@ -117,10 +432,7 @@ impl Runtime {
         lua.sandbox(true)?;
 
         // Configure aggressive compiler optimizations for JIT (applies to ALL chunks including require)
-        let compiler = Compiler::new()
-            .set_optimization_level(2) // Aggressive: inlining, loop unrolling
-            .set_type_info_level(1); // Generate type info for native code generation
-        lua.set_compiler(compiler);
+        lua.set_compiler(bytecode::compiler());
 
         // _G table needs to be injected again after sandboxing,
         // otherwise it will be read-only and completely unusable
@@ -136,6 +448,39 @@ impl Runtime {
             feature = "std-uuid",
             feature = "std-noise",
             feature = "std-base64",
+            feature = "std-crypto",
+            feature = "std-image",
+            feature = "std-websocket",
+            feature = "std-socket",
+            feature = "std-stream",
+            feature = "std-test",
+            feature = "std-fmt",
+            feature = "std-buffer-extra",
+            feature = "std-pathfind",
+            feature = "std-random",
+            feature = "std-profiler",
+            feature = "std-gc",
+            feature = "std-env",
+            feature = "std-term",
+            feature = "std-desktop",
+            feature = "std-bindgen",
+            feature = "std-inspect",
+            feature = "std-easing",
+            feature = "std-layout",
+            feature = "std-tablex",
+            feature = "std-csv",
+            feature = "std-semver",
+            feature = "std-winreg",
+            feature = "std-evdev",
+            feature = "std-autoinput",
+            feature = "std-hotkey",
+            feature = "std-time",
+            feature = "std-channel",
+            feature = "std-sync",
+            feature = "std-binparse",
+            feature = "std-serve",
+            feature = "std-template",
+            feature = "std-reactor",
         ))]
         {
             let g_table = lux_std::LuxStandardGlobal::GTable;
@@ -146,6 +491,12 @@ impl Runtime {
         let args = ProcessArgs::current();
         let env = ProcessEnv::current();
         let jit = ProcessJitEnablement::default();
+        let release = ProcessReleaseMode::default();
+        let ffi_safe_mode = ProcessFfiSafeMode::default();
+        let ffi_debug = ProcessFfiDebug::default();
+        let ffi_pool_size = ProcessFfiPoolSize::default();
+        let permissions = ProcessPermissions::default();
+        let run_context = RunContext::default();
 
         Ok(Self {
             lua,
@@ -153,9 +504,69 @@ impl Runtime {
             args,
             env,
             jit,
+            release,
+            ffi_safe_mode,
+            ffi_debug,
+            ffi_pool_size,
+            permissions,
+            run_context,
+            bytecode_cache: None,
+            #[cfg(any(
+                feature = "std-fs",
+                feature = "std-luau",
+                feature = "std-process",
+                feature = "std-regex",
+                feature = "std-serde",
+                feature = "std-stdio",
+                feature = "std-ffi",
+                feature = "std-signal",
+                feature = "std-uuid",
+                feature = "std-noise",
+                feature = "std-base64",
+                feature = "std-crypto",
+                feature = "std-image",
+                feature = "std-websocket",
+                feature = "std-socket",
+                feature = "std-stream",
+                feature = "std-test",
+                feature = "std-fmt",
+                feature = "std-buffer-extra",
+                feature = "std-pathfind",
+                feature = "std-random",
+                feature = "std-profiler",
+                feature = "std-gc",
+                feature = "std-env",
+                feature = "std-term",
+                feature = "std-desktop",
+                feature = "std-bindgen",
+                feature = "std-inspect",
+                feature = "std-easing",
+                feature = "std-layout",
+                feature = "std-tablex",
+                feature = "std-csv",
+                feature = "std-semver",
+                feature = "std-winreg",
+                feature = "std-evdev",
+                feature = "std-autoinput",
+                feature = "std-hotkey",
+                feature = "std-time",
+                feature = "std-channel",
+                feature = "std-sync",
+                feature = "std-binparse",
+                feature = "std-serve",
+                feature = "std-template",
+                feature = "std-reactor",
+            ))]
+            libraries,
+            limits: ExecutionLimits::default(),
+            exports: None,
         })
     }
 
+    pub(super) fn lua(&self) -> &Lua {
+        &self.lua
+    }
+
     /**
         Sets arguments to give in `process.args` for Lux scripts.
 
@@ -199,6 +610,312 @@ impl Runtime {
         self
     }
 
+    /**
+        Enables or disables release mode.
+
+        In release mode, debug-only checks in the standard library - such
+        as typed signal payload validation - are skipped entirely.
+    */
+    #[must_use]
+    pub fn with_release<R>(mut self, release: R) -> Self
+    where
+        R: Into<ProcessReleaseMode>,
+    {
+        self.release = release.into();
+        self
+    }
+
+    /**
+        Enables or disables FFI safe mode.
+
+        In safe mode, memory accessed through cdata is checked first - out of bounds
+        accesses to memory allocated by `ffi.new`, as well as NULL and misaligned
+        pointers, raise errors instead of crashing the process. Scripts may also
+        toggle safe mode for themselves using `ffi.setSafeMode`.
+    */
+    #[must_use]
+    pub fn with_ffi_safe_mode<S>(mut self, safe_mode: S) -> Self
+    where
+        S: Into<ProcessFfiSafeMode>,
+    {
+        self.ffi_safe_mode = safe_mode.into();
+        self
+    }
+
+    /**
+        Sets the FFI debugging options.
+
+        Cdata can remember where it was created - which allocation or cast it came
+        from and the traceback of the script at the time - for `ffi.debuginfo`, and
+        casts between incompatible pointer types can be logged along with the line
+        of the script that made them. Scripts may also toggle these for themselves
+        using `ffi.setDebugMode` and `ffi.setCastLogging`.
+    */
+    #[must_use]
+    pub fn with_ffi_debug<D>(mut self, debug: D) -> Self
+    where
+        D: Into<ProcessFfiDebug>,
+    {
+        self.ffi_debug = debug.into();
+        self
+    }
+
+    /**
+        Sets the number of threads used for blocking FFI calls.
+
+        Calls made with `func:callAsync(...)` run on this many threads,
+        started the first time one is made. Defaults to the available
+        parallelism of the machine but no fewer than four, and is always at least one.
+    */
+    #[must_use]
+    pub fn with_ffi_pool_size<P>(mut self, pool_size: P) -> Self
+    where
+        P: Into<ProcessFfiPoolSize>,
+    {
+        self.ffi_pool_size = pool_size.into();
+        self
+    }
+
+    /**
+        Sets how scripts run by this runtime were started, exposed to them as `script.RunContext`.
+
+        Defaults to [`RunContext::Run`].
+    */
+    #[must_use]
+    pub fn with_run_context(mut self, run_context: RunContext) -> Self {
+        self.run_context = run_context;
+        self
+    }
+
+    /**
+        Enables or disables sampling of Luau functions for the profiler.
+
+        While enabled, the call stack of script code is sampled whenever the
+        profiler in [`crate::profiler`] is running, at a small cost to
+        every function call and loop iteration. Zones and FFI calls are
+        recorded by the profiler regardless of this setting.
+    */
+    #[must_use]
+    pub fn with_profiling(mut self, enabled: bool) -> Self {
+        self.limits.profile = enabled;
+        self
+    }
+
+    /**
+        Sets the directory that modules in the snapshot of this runtime are found in,
+        see [`Runtime::from_snapshot`] - defaults to the directory of the first script
+        run using [`Runtime::run_file`], or the current directory for [`Runtime::run_snapshot`].
+    */
+    #[must_use]
+    pub fn with_snapshot_root(self, root: impl AsRef<Path>) -> Self {
+        if let Some(mut snapshot) = self.lua.app_data_mut::<ModuleSnapshot>() {
+            snapshot.set_root(root.as_ref());
+        }
+        self
+    }
+
+    /**
+        Grants a permission to scripts run by this runtime.
+
+        Permissions gate capabilities that are dangerous enough
+        to be disabled by default, such as accessing the memory
+        of other processes through `ffi.process`.
+    */
+    #[must_use]
+    pub fn with_permission(mut self, permission: Permission) -> Self {
+        self.permissions.grant(permission);
+        self
+    }
+
+    /**
+        Grants the `fs` permission to scripts run by this runtime,
+        limited to the given path and anything inside of it.
+
+        May be called multiple times to grant access to several paths.
+    */
+    #[must_use]
+    pub fn with_fs_path(mut self, path: impl AsRef<Path>) -> Self {
+        self.permissions.grant_fs_path(path);
+        self
+    }
+
+    /**
+        Denies every permission that was not explicitly granted using
+        [`Runtime::with_permission`] or [`Runtime::with_fs_path`].
+
+        Scripts are otherwise allowed to use `ffi`, `process`, `fs`
+        and networking, so this should be enabled for untrusted scripts.
+        Attempts to use a denied capability raise an error naming the
+        permission that is missing, and the flag used to grant it.
+    */
+    #[must_use]
+    pub fn with_deny_by_default(mut self, deny_by_default: bool) -> Self {
+        self.permissions.set_deny_by_default(deny_by_default);
+        self
+    }
+
+    /**
+        Caches compiled bytecode for files run by this runtime in the given directory,
+        including any files they `require`, so that unchanged files are not compiled again.
+
+        Cached bytecode is invalidated automatically whenever a file or the runtime
+        changes. Pass `None` to disable the cache, which is the default.
+    */
+    #[must_use]
+    pub fn with_bytecode_cache(mut self, dir: Option<PathBuf>) -> Self {
+        self.bytecode_cache = dir.map(|dir| BytecodeCache::new(dir, env!("CARGO_PKG_VERSION")));
+        self
+    }
+
+    /**
+        Limits how long script code may run for, counting from the start of each
+        [`Runtime::run_file`], [`Runtime::run_custom`] or [`Runtime::call_function`].
+
+        Script code running past the limit raises a [`LuxError::Timeout`], and no other
+        tasks get resumed afterwards - the run then finishes with an exit code of `1`.
+        Runs where every task is still waiting once the limit is up are stopped the same way.
+        Pass `None` to remove the limit.
+
+        [`LuxError::Timeout`]: lux_utils::LuxError::Timeout
+    */
+    pub fn set_execution_limit(&mut self, limit: impl Into<Option<Duration>>) {
+        self.limits.time = limit.into();
+    }
+
+    /**
+        Limits how many instructions script code may run, counting from the start of each run.
+
+        Instructions are counted at Luau interrupt checkpoints - every function call and
+        loop iteration - which makes this a deterministic alternative to a time limit.
+        Exceeding the limit behaves the same as exceeding [`Runtime::set_execution_limit`].
+        Pass `None` to remove the limit.
+    */
+    pub fn set_instruction_limit(&mut self, limit: impl Into<Option<u64>>) {
+        self.limits.instructions = limit.into();
+    }
+
+    /**
+        Limits how many instructions each individual task may run, including the main one.
+
+        Instructions are counted the same way as for [`Runtime::set_instruction_limit`],
+        but a task exceeding its budget only raises a [`LuxError::Timeout`] in that task,
+        leaving the rest of the runtime running. Pass `None` to remove the limit.
+
+        [`LuxError::Timeout`]: lux_utils::LuxError::Timeout
+    */
+    pub fn set_task_instruction_limit(&mut self, limit: impl Into<Option<u64>>) {
+        self.limits.task_instructions = limit.into();
+    }
+
+    /**
+        Prints all live tasks to stderr when the runtime exceeds its execution
+        or instruction limit, to help figure out what a script was stuck on.
+
+        See [`Runtime::dump_tasks`] for the format of the listing.
+    */
+    pub fn set_task_dump_on_timeout(&mut self, enabled: bool) {
+        self.limits.dump_tasks = enabled;
+    }
+
+    /**
+        Returns a snapshot of all live tasks in this runtime, along with
+        their names, statuses and where they were spawned from.
+
+        After a run has completed, any remaining tasks are suspended tasks that
+        nothing is left to resume - see [`TaskDump::is_deadlocked`].
+    */
+    #[must_use]
+    pub fn dump_tasks(&self) -> TaskDump {
+        TaskDump::new(self.sched.dump_tasks())
+    }
+
+    /**
+        Limits how many bytes the Luau heap of this runtime may grow to, taking effect right away.
+
+        Allocations past the limit raise a memory error in the script that made them,
+        which scripts may catch with `pcall` to free up memory and keep running.
+        Memory use can be monitored from scripts using `@lux/gc`. Pass `None` to remove the limit.
+
+        # Errors
+
+        Errors if the memory already in use is above the new limit.
+    */
+    pub fn set_memory_limit(&mut self, limit: impl Into<Option<usize>>) -> RuntimeResult<()> {
+        let limit = limit.into();
+        if let Some(limit) = limit
+            && self.lua.used_memory() > limit
+        {
+            return Err(LuaError::runtime(format!(
+                "Memory limit of {limit} bytes is below the {} bytes already in use",
+                self.lua.used_memory()
+            ))
+            .into());
+        }
+        // A limit of zero means no limit
+        self.lua.set_memory_limit(limit.unwrap_or(0))?;
+        Ok(())
+    }
+
+    /**
+        Explicitly enables or disables a feature flag, opting scripts into or out of
+        behavior that is going to change, the same as `lux.flags` does from Luau.
+
+        Standard globals that depend on the flag are re-created right away.
+
+        # Errors
+
+        Errors if any of the standard globals depending on the flag fail to be re-created.
+    */
+    #[cfg(any(
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-ffi",
+        feature = "std-signal",
+        feature = "std-uuid",
+        feature = "std-noise",
+        feature = "std-base64",
+        feature = "std-crypto",
+        feature = "std-image",
+        feature = "std-websocket",
+        feature = "std-socket",
+        feature = "std-stream",
+        feature = "std-test",
+        feature = "std-fmt",
+        feature = "std-buffer-extra",
+        feature = "std-pathfind",
+        feature = "std-random",
+        feature = "std-profiler",
+        feature = "std-gc",
+        feature = "std-env",
+        feature = "std-term",
+        feature = "std-desktop",
+        feature = "std-bindgen",
+        feature = "std-inspect",
+        feature = "std-easing",
+        feature = "std-layout",
+        feature = "std-tablex",
+        feature = "std-csv",
+        feature = "std-semver",
+        feature = "std-winreg",
+        feature = "std-evdev",
+        feature = "std-autoinput",
+        feature = "std-hotkey",
+        feature = "std-time",
+        feature = "std-channel",
+        feature = "std-sync",
+        feature = "std-binparse",
+        feature = "std-serve",
+        feature = "std-template",
+        feature = "std-reactor",
+    ))]
+    pub fn set_flag(&mut self, flag: FeatureFlag, enabled: bool) -> LuaResult<()> {
+        lux_std::set_feature_flag(&self.lua, flag, enabled)
+    }
+
     /**
         Adds a custom library to the runtime, making it available through `require`.
 
@@ -242,23 +959,7 @@ impl Runtime {
         S: AsRef<str>,
         F: FnOnce(&Lua) -> LuaResult<LuaValue>,
     {
-        let name = name.as_ref().trim();
-
-        if !name.starts_with('@') {
-            return Err(RuntimeError::from(LuaError::external(
-                "Library names must start with '@'",
-            )));
-        }
-        if name.starts_with("@lux/") {
-            return Err(RuntimeError::from(LuaError::external(
-                "Library names must not start with '@lux/'",
-            )));
-        }
-        if name.starts_with("@self/") {
-            return Err(RuntimeError::from(LuaError::external(
-                "Library names must not start with '@self/'",
-            )));
-        }
+        let name = check_lib_name(name.as_ref())?;
 
         let lib = make_lib(&self.lua)?;
         self.lua.register_module(name, lib)?;
@@ -266,6 +967,225 @@ impl Runtime {
         Ok(self)
     }
 
+    /**
+        Overrides a library that scripts get from `require`, including standard
+        libraries such as `@lux/fs`, replacing it with the value from `make_override`.
+
+        Useful for stubbing out libraries that do I/O when testing scripts. Scripts can
+        override libraries themselves using `require.mock`, and remove their own overrides
+        using `require.unmock`, which then gives them this override again.
+
+        # Example Usage
+
+        ```rs
+        let rt = Runtime::new()?.with_module_override("@lux/process", |lua| {
+            let t = lua.create_table()?;
+            t.set("exec", lua.create_function(|_, ()| Ok("stubbed"))?)?;
+            Ok(LuaValue::Table(t))
+        })?;
+        ```
+
+        # Errors
+
+        Returns an error if:
+
+        - The library name does not start with `@`
+        - The provided `make_override` function errors
+    */
+    pub fn with_module_override<S, F>(self, name: S, make_override: F) -> RuntimeResult<Self>
+    where
+        S: AsRef<str>,
+        F: FnOnce(&Lua) -> LuaResult<LuaValue>,
+    {
+        let value = make_override(&self.lua)?;
+        overrides::override_module(&self.lua, name.as_ref().trim(), value)?;
+
+        Ok(self)
+    }
+
+    /**
+        Exposes a Rust function to scripts as a global.
+
+        Arguments and return values are converted automatically, including
+        the math types of the runtime such as [`Vector3`] and [`Color3`].
+
+        # Example Usage
+
+        ```rs
+        rt.register_fn("distance", |(a, b): (Vector3, Vector3)| {
+            Ok((a - b).magnitude())
+        })?;
+        ```
+
+        Then, use it in Lua:
+
+        ```luau
+        print(distance(Vector3.new(0, 0, 0), Vector3.new(3, 4, 0))) --> 5
+        ```
+
+        # Errors
+
+        Returns an error if the function fails to be created.
+
+        [`Vector3`]: crate::Vector3
+        [`Color3`]: crate::Color3
+    */
+    pub fn register_fn<A, R, F>(&mut self, name: impl AsRef<str>, func: F) -> RuntimeResult<()>
+    where
+        A: FromLuaMulti,
+        R: IntoLuaMulti,
+        F: Fn(A) -> LuaResult<R> + MaybeSend + 'static,
+    {
+        let function = self.lua.create_function(move |_, args: A| func(args))?;
+        self.lua.globals().set(name.as_ref(), function)?;
+        Ok(())
+    }
+
+    /**
+        Exposes a Rust struct to scripts as a library, making it available through `require`.
+
+        See [`HostModule`] for how the functions of the library are defined.
+
+        # Errors
+
+        Returns an error if:
+
+        - The library name is invalid, see [`Runtime::with_lib`]
+        - Any of the functions of the module fail to be created
+    */
+    pub fn register_module<T: HostModule>(
+        &mut self,
+        name: impl AsRef<str>,
+        module: T,
+    ) -> RuntimeResult<()> {
+        let name = check_lib_name(name.as_ref())?;
+
+        let mut functions = HostFunctions::new(&self.lua, module)?;
+        T::add_functions(&mut functions)?;
+        self.lua.register_module(name, functions.into_table())?;
+
+        Ok(())
+    }
+
+    /**
+        Invalidates a module that was required by scripts, given its file or module path.
+
+        The next time the module gets required, it is read from the filesystem and runs
+        again - modules that already required it keep the values they got before.
+        Scripts can do the same for paths relative to themselves using `require.reload`.
+
+        Returns whether the module had been required and was cached.
+
+        # Errors
+
+        Errors when out of memory.
+    */
+    #[cfg(any(
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-ffi",
+        feature = "std-signal",
+        feature = "std-uuid",
+        feature = "std-noise",
+        feature = "std-base64",
+        feature = "std-crypto",
+        feature = "std-image",
+        feature = "std-websocket",
+        feature = "std-socket",
+        feature = "std-stream",
+        feature = "std-test",
+        feature = "std-fmt",
+        feature = "std-buffer-extra",
+        feature = "std-pathfind",
+        feature = "std-random",
+        feature = "std-profiler",
+        feature = "std-gc",
+        feature = "std-env",
+        feature = "std-term",
+        feature = "std-desktop",
+        feature = "std-bindgen",
+        feature = "std-inspect",
+        feature = "std-easing",
+        feature = "std-layout",
+        feature = "std-tablex",
+        feature = "std-csv",
+        feature = "std-semver",
+        feature = "std-winreg",
+        feature = "std-evdev",
+        feature = "std-autoinput",
+        feature = "std-hotkey",
+        feature = "std-time",
+        feature = "std-channel",
+        feature = "std-sync",
+        feature = "std-binparse",
+        feature = "std-serve",
+        feature = "std-template",
+        feature = "std-reactor",
+    ))]
+    pub fn invalidate_module(&self, path: impl AsRef<std::path::Path>) -> RuntimeResult<bool> {
+        Ok(lux_std::invalidate_module(&self.lua, path)?)
+    }
+
+    /**
+        Returns how long each standard library took to initialize during the last run,
+        in the order they were first used. Libraries that scripts did not use are not
+        initialized at all, and are not included.
+    */
+    #[cfg(any(
+        feature = "std-fs",
+        feature = "std-luau",
+        feature = "std-process",
+        feature = "std-regex",
+        feature = "std-serde",
+        feature = "std-stdio",
+        feature = "std-ffi",
+        feature = "std-signal",
+        feature = "std-uuid",
+        feature = "std-noise",
+        feature = "std-base64",
+        feature = "std-crypto",
+        feature = "std-image",
+        feature = "std-websocket",
+        feature = "std-socket",
+        feature = "std-stream",
+        feature = "std-test",
+        feature = "std-fmt",
+        feature = "std-buffer-extra",
+        feature = "std-pathfind",
+        feature = "std-random",
+        feature = "std-profiler",
+        feature = "std-gc",
+        feature = "std-env",
+        feature = "std-term",
+        feature = "std-desktop",
+        feature = "std-bindgen",
+        feature = "std-inspect",
+        feature = "std-easing",
+        feature = "std-layout",
+        feature = "std-tablex",
+        feature = "std-csv",
+        feature = "std-semver",
+        feature = "std-winreg",
+        feature = "std-evdev",
+        feature = "std-autoinput",
+        feature = "std-hotkey",
+        feature = "std-time",
+        feature = "std-channel",
+        feature = "std-sync",
+        feature = "std-binparse",
+        feature = "std-serve",
+        feature = "std-template",
+        feature = "std-reactor",
+    ))]
+    #[must_use]
+    pub fn library_init_times(&self) -> Vec<(LuxStandardLibrary, Duration)> {
+        lux_std::library_init_times(&self.lua)
+    }
+
     /**
         Runs some kind of custom input, inside of the current runtime.
 
@@ -358,11 +1278,158 @@ impl Runtime {
             })?;
 
         let module_name = format!("{FILE_CHUNK_PREFIX}{module_path}");
+        let target: &Path = module_path.target().as_ref();
         let module_contents = strip_shebang(contents);
+        let module_contents = match &self.bytecode_cache {
+            Some(cache) => cache.load(target, module_contents).await,
+            None => bytecode::compiler()
+                .compile(&module_contents)
+                .unwrap_or(module_contents),
+        };
+
+        // The first script run is the main script of the snapshot, which modules are found relative to
+        if let Some(mut snapshot) = self.lua.app_data_mut::<ModuleSnapshot>() {
+            if snapshot.root().is_none()
+                && let Some(dir) = target.parent()
+            {
+                snapshot.set_root(dir);
+            }
+            if snapshot.main().is_none() {
+                snapshot.insert(target, module_contents.clone());
+                snapshot.set_main(target);
+            }
+        }
 
         self.run_inner(module_name, module_contents).await
     }
 
+    /**
+        Runs the main script of the snapshot this runtime was created from, see
+        [`Runtime::from_snapshot`], without reading it or any of the modules
+        in the snapshot from the filesystem.
+
+        The script and its modules are found in the directory set using
+        [`Runtime::with_snapshot_root`], or the current directory. Files in
+        that directory are only used for modules not in the snapshot.
+
+        # Errors
+
+        Returns an error if:
+
+        - The snapshot has no main script, or it has already been run
+        - The script fails to run (not if the script itself errors)
+    */
+    pub async fn run_snapshot(&mut self) -> RuntimeResult<RuntimeReturnValues> {
+        let (path, bytecode) = {
+            let mut snapshot = self
+                .lua
+                .app_data_mut::<ModuleSnapshot>()
+                .ok_or_else(|| LuaError::runtime("The runtime has no module snapshot"))?;
+            if snapshot.root().is_none() {
+                snapshot.set_root(&get_current_dir());
+            }
+            let main = snapshot
+                .main()
+                .zip(snapshot.root())
+                .map(|(main, root)| root.join(main))
+                .ok_or_else(|| LuaError::runtime("The module snapshot has no main script"))?;
+            let bytecode = snapshot.restore(&main).ok_or_else(|| {
+                LuaError::runtime("The main script of the module snapshot has already been run")
+            })?;
+            (main, bytecode)
+        };
+
+        let module_path = LuauModulePath::strip(path);
+        let module_name = format!("{FILE_CHUNK_PREFIX}{}", module_path.display());
+        self.run_inner(module_name, bytecode).await
+    }
+
+    /**
+        Calls a function exported by the last script run in this runtime, and waits for it to complete.
+
+        The function is looked up by name in the table returned by the script, falling back to
+        globals if the script did not return a table or does not export it. Dots in the name
+        index into nested tables, such as `"handlers.onRequest"`.
+
+        The function runs on the scheduler, so it may yield and spawn tasks, which
+        are all run to completion before its return values are converted.
+
+        # Example Usage
+
+        ```rs
+        let mut rt = Runtime::new()?;
+        rt.run_custom("math", "return { add = function(a, b) return a + b end }").await?;
+
+        let sum: i32 = rt.call_function("add", (1, 2)).await?;
+        assert_eq!(sum, 3);
+        ```
+
+        # Errors
+
+        Returns an error if:
+
+        - No function with the given name exists
+        - The function errors
+        - The return values can not be converted into `R`
+    */
+    pub async fn call_function<A, R>(&mut self, name: impl AsRef<str>, args: A) -> RuntimeResult<R>
+    where
+        A: IntoLuaMulti,
+        R: FromLuaMulti,
+    {
+        let name = name.as_ref();
+        let function = self
+            .exports
+            .as_ref()
+            .and_then(|exports| find_function(exports, name))
+            .or_else(|| find_function(&self.lua.globals(), name))
+            .ok_or_else(|| {
+                LuaError::runtime(format!(
+                    "No function named '{name}' is exported by the script or set as a global"
+                ))
+            })?;
+
+        self.limits.install(&self.lua)?;
+
+        let thread_id = self.sched.push_thread_back(function, args)?;
+        self.run_scheduler().await;
+
+        let values = self.sched.get_thread_result(thread_id).unwrap_or_else(|| {
+            Err(LuaError::runtime(format!(
+                "Function '{name}' was interrupted before it completed"
+            )))
+        })?;
+
+        Ok(R::from_lua_multi(values, &self.lua)?)
+    }
+
+    /**
+        Runs the scheduler until all threads have completed, or the execution limit is exceeded.
+
+        The interrupt enforcing the execution limit only runs while script code is running,
+        so this also stops the scheduler when all tasks are still waiting once the limit is up.
+    */
+    async fn run_scheduler(&self) {
+        let Some(limit) = self.limits.time else {
+            self.sched.run().await;
+            return;
+        };
+        let watchdog = async {
+            Timer::after(limit).await;
+            if self.limits.dump_tasks {
+                report_tasks(&self.lua);
+            }
+            let error = LuaError::from(LuxError::Timeout(format!(
+                "Script exceeded its execution limit of {}s while waiting",
+                limit.as_secs_f64()
+            )));
+            eprintln!("{}", RuntimeError::from(error));
+            self.sched.set_exit_code(1);
+            future::pending::<()>().await;
+        };
+        self.sched.run().or(watchdog).await;
+    }
+
     async fn run_inner(
         &mut self,
         chunk_name: impl AsRef<str>,
@@ -376,10 +1443,20 @@ impl Runtime {
             eprintln!("{}", RuntimeError::from(e));
         });
 
-        // Store the provided args, environment variables, and jit enablement as AppData
+        // Store the provided args, environment variables, jit enablement, release mode, ffi settings, permissions, run context and bytecode cache as AppData
         self.lua.set_app_data(self.args.clone());
         self.lua.set_app_data(self.env.clone());
         self.lua.set_app_data(self.jit);
+        self.lua.set_app_data(self.release);
+        self.lua.set_app_data(self.ffi_safe_mode);
+        self.lua.set_app_data(self.ffi_debug);
+        self.lua.set_app_data(self.ffi_pool_size);
+        self.lua.set_app_data(self.permissions.clone());
+        self.lua.set_app_data(self.run_context);
+        match &self.bytecode_cache {
+            Some(cache) => self.lua.set_app_data(cache.clone()),
+            None => self.lua.remove_app_data::<BytecodeCache>(),
+        };
 
         // Inject all the standard libraries that are enabled - this needs to be done after
         // storing the args/env, since some standard libraries use those during initialization
@@ -395,11 +1472,47 @@ impl Runtime {
             feature = "std-uuid",
             feature = "std-noise",
             feature = "std-base64",
+            feature = "std-crypto",
+            feature = "std-image",
+            feature = "std-websocket",
+            feature = "std-socket",
+            feature = "std-stream",
+            feature = "std-test",
+            feature = "std-fmt",
+            feature = "std-buffer-extra",
+            feature = "std-pathfind",
+            feature = "std-random",
+            feature = "std-profiler",
+            feature = "std-gc",
+            feature = "std-env",
+            feature = "std-term",
+            feature = "std-desktop",
+            feature = "std-bindgen",
+            feature = "std-inspect",
+            feature = "std-easing",
+            feature = "std-layout",
+            feature = "std-tablex",
+            feature = "std-csv",
+            feature = "std-semver",
+            feature = "std-winreg",
+            feature = "std-evdev",
+            feature = "std-autoinput",
+            feature = "std-hotkey",
+            feature = "std-time",
+            feature = "std-channel",
+            feature = "std-sync",
+            feature = "std-binparse",
+            feature = "std-serve",
+            feature = "std-template",
+            feature = "std-reactor",
         ))]
         {
-            lux_std::inject_std(self.lua.clone())?;
+            lux_std::inject_libraries(self.lua.clone(), &self.libraries)?;
+            lux_std::inject_script_context(self.lua.clone())?;
         }
 
+        self.limits.install(&self.lua)?;
+
         // Enable / disable the JIT as requested, before loading anything
         self.lua.enable_jit(self.jit.enabled());
 
@@ -410,8 +1523,9 @@ impl Runtime {
             .set_name(chunk_name.as_ref());
 
         // Run it on our scheduler until it and any other spawned threads complete
+        self.lua.remove_app_data::<ProcessShutdown>();
         let main_thread_id = self.sched.push_thread_back(main, ())?;
-        self.sched.run().await;
+        self.run_scheduler().await;
 
         let main_thread_values = self
             .sched
@@ -419,14 +1533,55 @@ impl Runtime {
             .unwrap_or_else(|| Ok(LuaMultiValue::new())) // Ignore missing result (interruption), we just want to extract values
             .unwrap_or_default(); // Ignore any errors from the script, we just want to extract values
 
+        // Keep the table returned by the script around, for `call_function`
+        self.exports = match main_thread_values.front() {
+            Some(LuaValue::Table(t)) => Some(t.clone()),
+            _ => None,
+        };
+
         Ok(RuntimeReturnValues {
             code: self.sched.get_exit_code(),
             errored: got_any_error.load(Ordering::SeqCst),
             values: main_thread_values,
+            shutdown: self.lua.remove_app_data::<ProcessShutdown>(),
         })
     }
 }
 
+fn check_lib_name(name: &str) -> RuntimeResult<&str> {
+    let name = name.trim();
+    if !name.starts_with('@') {
+        return Err(RuntimeError::from(LuaError::external(
+            "Library names must start with '@'",
+        )));
+    }
+    if name.starts_with("@lux/") {
+        return Err(RuntimeError::from(LuaError::external(
+            "Library names must not start with '@lux/'",
+        )));
+    }
+    if name.starts_with("@self/") {
+        return Err(RuntimeError::from(LuaError::external(
+            "Library names must not start with '@self/'",
+        )));
+    }
+    Ok(name)
+}
+
+fn find_function(root: &LuaTable, path: &str) -> Option<LuaFunction> {
+    let mut segments = path.split('.').peekable();
+    let mut table = root.clone();
+    while let Some(segment) = segments.next() {
+        let value = table.get::<LuaValue>(segment).ok()?;
+        match value {
+            LuaValue::Function(f) if segments.peek().is_none() => return Some(f),
+            LuaValue::Table(t) if segments.peek().is_some() => table = t,
+            _ => return None,
+        }
+    }
+    None
+}
+
 fn strip_shebang(mut contents: Vec<u8>) -> Vec<u8> {
     if contents.starts_with(b"#!")
         && let Some(first_newline_idx) = contents
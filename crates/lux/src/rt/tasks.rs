@@ -0,0 +1,82 @@
+use std::fmt;
+
+use mlua_luau_scheduler::{TaskInfo, TaskStatus};
+
+/**
+    A snapshot of the live tasks in a runtime, as returned by [`Runtime::dump_tasks`].
+
+    Displays as a human-readable listing of every task, with its name,
+    status, age and the traceback of where it was spawned from.
+
+    [`Runtime::dump_tasks`]: crate::Runtime::dump_tasks
+*/
+#[derive(Debug, Clone)]
+pub struct TaskDump {
+    tasks: Vec<TaskInfo>,
+}
+
+impl TaskDump {
+    pub(crate) fn new(tasks: Vec<TaskInfo>) -> Self {
+        Self { tasks }
+    }
+
+    /**
+        Returns the tasks in this dump, in the order they were spawned.
+    */
+    #[must_use]
+    pub fn tasks(&self) -> &[TaskInfo] {
+        &self.tasks
+    }
+
+    /**
+        Returns `true` if there were no live tasks.
+    */
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /**
+        Returns `true` if every task is suspended, meaning that once the
+        scheduler has stopped, none of them will ever be resumed.
+    */
+    #[must_use]
+    pub fn is_deadlocked(&self) -> bool {
+        !self.tasks.is_empty()
+            && self
+                .tasks
+                .iter()
+                .all(|task| task.status == TaskStatus::Suspended)
+    }
+}
+
+impl fmt::Display for TaskDump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tasks.len() {
+            0 => return write!(f, "No live tasks"),
+            1 => write!(f, "1 live task:")?,
+            n => write!(f, "{n} live tasks:")?,
+        }
+        for (index, task) in self.tasks.iter().enumerate() {
+            write!(f, "\n  #{}", index + 1)?;
+            if let Some(name) = &task.name {
+                write!(f, " \"{name}\"")?;
+            }
+            write!(
+                f,
+                " ({}, spawned {:.2}s ago)",
+                task.status.as_str(),
+                task.age.as_secs_f64()
+            )?;
+            match task.traceback.as_deref().map(str::trim) {
+                Some(traceback) if !traceback.is_empty() => {
+                    for line in traceback.lines() {
+                        write!(f, "\n      {line}")?;
+                    }
+                }
+                _ => write!(f, "\n      (not spawned from Lua)")?,
+            }
+        }
+        Ok(())
+    }
+}
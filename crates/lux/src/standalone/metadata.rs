@@ -7,6 +7,7 @@ use mlua::Compiler as LuaCompiler;
 pub static CURRENT_EXE: LazyLock<PathBuf> =
     LazyLock::new(|| env::current_exe().expect("failed to get current exe"));
 const MAGIC: &[u8; 8] = b"cr3sc3nt";
+const SNAPSHOT_MAGIC: &[u8; 8] = b"cr3snap1";
 
 /*
     TODO: Right now all we do is append the bytecode to the end
@@ -29,6 +30,8 @@ const MAGIC: &[u8; 8] = b"cr3sc3nt";
 #[derive(Debug, Clone)]
 pub struct Metadata {
     pub bytecode: Vec<u8>,
+    /// Modules to restore at startup, see `Runtime::snapshot`
+    pub snapshot: Option<Vec<u8>>,
 }
 
 impl Metadata {
@@ -45,11 +48,13 @@ impl Metadata {
     }
 
     /**
-        Creates a patched standalone binary from the given script contents.
+        Creates a patched standalone binary from the given script contents,
+        and optionally a module snapshot to restore when it starts.
     */
     pub async fn create_env_patched_bin(
         base_exe_path: PathBuf,
         script_contents: impl Into<Vec<u8>>,
+        snapshot: Option<Vec<u8>>,
     ) -> Result<Vec<u8>> {
         let compiler = LuaCompiler::new()
             .set_optimization_level(2)
@@ -62,7 +67,7 @@ impl Metadata {
         let bytecode = compiler.compile(script_contents.into())?;
 
         // Append the bytecode / metadata to the end
-        let meta = Self { bytecode };
+        let meta = Self { bytecode, snapshot };
         patched_bin.extend_from_slice(&meta.to_bytes());
 
         Ok(patched_bin)
@@ -83,9 +88,26 @@ impl Metadata {
             usize::try_from(u64::from_be_bytes(bytecode_size_bytes.try_into().unwrap()))?;
 
         // Extract bytecode
-        let bytecode = bytes[bytes.len() - 16 - bytecode_size..].to_vec();
-
-        Ok(Self { bytecode })
+        let Some(bytecode_start) = (bytes.len() - 16).checked_sub(bytecode_size) else {
+            bail!("standalone binary is truncated")
+        };
+        let bytecode = bytes[bytecode_start..bytes.len() - 16].to_vec();
+
+        // Extract the snapshot, which comes right before the bytecode if there is one
+        let rest = &bytes[..bytecode_start];
+        let snapshot = if rest.len() >= 16 && rest.ends_with(SNAPSHOT_MAGIC) {
+            let snapshot_size_bytes = &rest[rest.len() - 16..rest.len() - 8];
+            let snapshot_size =
+                usize::try_from(u64::from_be_bytes(snapshot_size_bytes.try_into().unwrap()))?;
+            let Some(snapshot_start) = (rest.len() - 16).checked_sub(snapshot_size) else {
+                bail!("standalone binary is truncated")
+            };
+            Some(rest[snapshot_start..rest.len() - 16].to_vec())
+        } else {
+            None
+        };
+
+        Ok(Self { bytecode, snapshot })
     }
 
     /**
@@ -93,6 +115,11 @@ impl Metadata {
     */
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
+        if let Some(snapshot) = &self.snapshot {
+            bytes.extend_from_slice(snapshot);
+            bytes.extend_from_slice(&(snapshot.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(SNAPSHOT_MAGIC);
+        }
         bytes.extend_from_slice(&self.bytecode);
         bytes.extend_from_slice(&(self.bytecode.len() as u64).to_be_bytes());
         bytes.extend_from_slice(MAGIC);
@@ -2,6 +2,7 @@ use std::{env, process::ExitCode};
 
 use anyhow::Result;
 use lux::Runtime;
+use lux_utils::path::get_current_exe;
 
 pub(crate) mod metadata;
 pub(crate) mod tracer;
@@ -29,9 +30,17 @@ pub async fn run(patched_bin: impl AsRef<[u8]>) -> Result<ExitCode> {
     let args = env::args().skip(1).collect::<Vec<_>>();
     let meta = Metadata::from_bytes(patched_bin).expect("must be a standalone binary");
 
-    let mut rt = Runtime::new()?.with_args(args);
-
-    let result = rt.run_custom("STANDALONE", meta.bytecode).await;
+    // Modules in a snapshot are found next to the binary, the same as they were next to the script
+    let result = if let Some(snapshot) = &meta.snapshot {
+        let exe = get_current_exe();
+        let mut rt = Runtime::from_snapshot(snapshot)?
+            .with_args(args)
+            .with_snapshot_root(exe.parent().unwrap_or(&exe));
+        rt.run_snapshot().await
+    } else {
+        let mut rt = Runtime::new()?.with_args(args);
+        rt.run_custom("STANDALONE", meta.bytecode).await
+    };
 
     Ok(match result {
         Err(err) => {
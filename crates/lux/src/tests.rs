@@ -7,6 +7,7 @@ use console::set_colors_enabled;
 use console::set_colors_enabled_stderr;
 
 use lux_utils::path::clean_path;
+use mlua::prelude::*;
 
 use crate::Runtime;
 
@@ -110,3 +111,733 @@ create_tests! {
     stdio_write: "stdio/write",
     stdio_ewrite: "stdio/ewrite",
 }
+
+fn run_chunk(rt: &mut Runtime, source: &str) -> Result<crate::RuntimeReturnValues> {
+    async_io::block_on(async { Ok(rt.run_custom("test", source).await?) })
+}
+
+#[test]
+fn builder_custom_globals() -> Result<()> {
+    let mut rt = Runtime::builder()
+        .with_global("ANSWER", |lua| 42.into_lua(lua))
+        .build()?;
+    let values = run_chunk(&mut rt, "return ANSWER")?;
+    assert_eq!(values.values.front().and_then(LuaValue::as_i32), Some(42));
+    Ok(())
+}
+
+#[cfg(all(feature = "std-ffi", feature = "std-fs", feature = "std-process"))]
+#[test]
+fn builder_sandbox_removes_unsafe_libraries() -> Result<()> {
+    let mut rt = Runtime::builder().sandbox(true).build()?;
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            for _, name in { "@lux/ffi", "@lux/fs", "@lux/process" } do
+                assert(not pcall(require, name), name .. " should not be available")
+            end
+            return require("@lux/regex") ~= nil
+        "#,
+    )?;
+    assert!(values.success());
+    assert_eq!(
+        values.values.front().and_then(LuaValue::as_boolean),
+        Some(true)
+    );
+    Ok(())
+}
+
+#[cfg(feature = "std-regex")]
+#[test]
+fn builder_without_library() -> Result<()> {
+    let mut rt = Runtime::builder()
+        .without_library(crate::LuxStandardLibrary::Regex)
+        .build()?;
+    let values = run_chunk(&mut rt, r#"return pcall(require, "@lux/regex")"#)?;
+    assert_eq!(
+        values.values.front().and_then(LuaValue::as_boolean),
+        Some(false)
+    );
+    Ok(())
+}
+
+#[test]
+fn builder_time_limit() -> Result<()> {
+    let mut rt = Runtime::builder()
+        .time_limit(std::time::Duration::from_millis(50))
+        .build()?;
+    let values = run_chunk(&mut rt, "while true do end")?;
+    assert!(!values.success());
+    Ok(())
+}
+
+#[test]
+fn builder_memory_limit() -> Result<()> {
+    let mut rt = Runtime::builder().memory_limit(16 * 1024 * 1024).build()?;
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            local ok = pcall(function()
+                local t = {}
+                for i = 1, 1e8 do
+                    t[i] = string.rep("x", 64) .. i
+                end
+            end)
+            return ok
+        "#,
+    )?;
+    assert_eq!(
+        values.values.front().and_then(LuaValue::as_boolean),
+        Some(false)
+    );
+    Ok(())
+}
+
+#[test]
+fn set_memory_limit_is_catchable() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    let limit = 32 * 1024 * 1024;
+    rt.set_memory_limit(limit)?;
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            local limit = require("@lux/gc").limit()
+            local ok = pcall(function()
+                local t = {}
+                for i = 1, 1e8 do
+                    t[i] = string.rep("x", 64) .. i
+                end
+            end)
+            return ok, limit
+        "#,
+    )?;
+    let values = values.values.into_iter().collect::<Vec<_>>();
+    assert_eq!(values[0].as_boolean(), Some(false));
+    assert_eq!(values[1].as_usize(), Some(limit));
+
+    assert!(rt.set_memory_limit(1024).is_err());
+    rt.set_memory_limit(None)?;
+    Ok(())
+}
+
+#[test]
+fn run_context_and_args() -> Result<()> {
+    let mut rt = Runtime::new()?
+        .with_args(ARGS)
+        .with_run_context(crate::RunContext::Repl);
+    let values = run_chunk(&mut rt, "return script.RunContext, args[1], #args")?;
+    let values = values.values.into_iter().collect::<Vec<_>>();
+    assert_eq!(values[0].to_string()?, "Repl");
+    assert_eq!(values[1].to_string()?, "Foo");
+    assert_eq!(values[2].as_i32(), Some(2));
+    Ok(())
+}
+
+#[cfg(feature = "std-process")]
+#[test]
+fn process_shutdown_waits_for_pending_tasks() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            local process = require("@lux/process")
+            task.spawn(function()
+                task.wait(0.05)
+                _G.finished = true
+            end)
+            process.shutdown({ timeout = 5, code = 3 })
+        "#,
+    )?;
+    assert_eq!(values.status(), 3);
+    let shutdown = values.shutdown.expect("run should have been shut down");
+    assert!(!shutdown.timed_out);
+    assert_eq!(shutdown.signal, None);
+
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            local process = require("@lux/process")
+            task.spawn(function()
+                task.wait(10)
+            end)
+            process.shutdown({ timeout = 0.05, code = 3 })
+        "#,
+    )?;
+    assert_eq!(values.status(), 1);
+    assert!(values.shutdown.is_some_and(|s| s.timed_out));
+
+    let values = run_chunk(&mut rt, "return 1")?;
+    assert!(values.shutdown.is_none());
+    Ok(())
+}
+
+#[test]
+fn call_function_exports_and_globals() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    run_chunk(
+        &mut rt,
+        r#"
+            function double(n)
+                return n * 2
+            end
+            return {
+                add = function(a, b)
+                    return a + b
+                end,
+                nested = {
+                    greet = function(name)
+                        task.wait()
+                        return "Hello, " .. name, #name
+                    end,
+                },
+            }
+        "#,
+    )?;
+
+    let sum: i32 = async_io::block_on(rt.call_function("add", (1, 2)))?;
+    assert_eq!(sum, 3);
+
+    let (greeting, len): (String, usize) =
+        async_io::block_on(rt.call_function("nested.greet", "Lux"))?;
+    assert_eq!(greeting, "Hello, Lux");
+    assert_eq!(len, 3);
+
+    let doubled: f64 = async_io::block_on(rt.call_function("double", 21))?;
+    assert!((doubled - 42.0).abs() < f64::EPSILON);
+
+    assert!(async_io::block_on(rt.call_function::<_, ()>("missing", ())).is_err());
+    assert!(async_io::block_on(rt.call_function::<_, ()>("nested.missing", ())).is_err());
+    Ok(())
+}
+
+#[test]
+fn call_function_errors() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    run_chunk(&mut rt, r#"return { fail = function() error("boom") end }"#)?;
+    let err = async_io::block_on(rt.call_function::<_, ()>("fail", ())).unwrap_err();
+    assert!(err.to_string().contains("boom"));
+    Ok(())
+}
+
+#[test]
+fn register_fn_converts_math_types() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    rt.register_fn("distance", |(a, b): (crate::Vector3, crate::Vector3)| {
+        Ok((a - b).magnitude())
+    })?;
+    rt.register_fn("gray", |value: f64| {
+        Ok(crate::Color3::new(value, value, value))
+    })?;
+
+    let values = run_chunk(
+        &mut rt,
+        "
+            local color = gray(0.5)
+            assert(color.R == 0.5 and color.B == 0.5)
+            assert(not pcall(distance, 1, 2))
+            return distance(Vector3.new(0, 0, 0), Vector3.new(3, 4, 0))
+        ",
+    )?;
+    assert!(values.success());
+    assert_eq!(values.as_i64(), Some(5));
+    Ok(())
+}
+
+#[test]
+fn register_module_shares_struct() -> Result<()> {
+    #[derive(Default)]
+    struct Counter {
+        count: i64,
+    }
+
+    impl crate::HostModule for Counter {
+        fn add_functions(functions: &mut crate::HostFunctions<Self>) -> LuaResult<()> {
+            functions
+                .add_mut("increment", |this, by: Option<i64>| {
+                    this.count += by.unwrap_or(1);
+                    Ok(this.count)
+                })?
+                .add("get", |this, ()| Ok(this.count))?;
+            Ok(())
+        }
+    }
+
+    let mut rt = Runtime::new()?;
+    rt.register_module("@app/counter", Counter::default())?;
+    assert!(
+        rt.register_module("@lux/counter", Counter::default())
+            .is_err()
+    );
+
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            local counter = require("@app/counter")
+            counter.increment()
+            counter.increment(10)
+            return counter.get()
+        "#,
+    )?;
+    assert_eq!(values.as_i64(), Some(11));
+    Ok(())
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn module_override_restores_library() -> Result<()> {
+    let mut rt = Runtime::new()?.with_module_override("@lux/fs", |lua| {
+        let t = lua.create_table()?;
+        t.set("stubbed", true)?;
+        Ok(LuaValue::Table(t))
+    })?;
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            assert(require("@lux/fs").stubbed, "the override should be returned")
+            require.mock("@lux/fs", { mocked = true })
+            assert(require("@lux/fs").mocked, "the mock should be returned")
+            assert(require.unmock("@lux/fs"), "unmock should remove the mock")
+            assert(require("@lux/fs").stubbed, "unmock should restore the override")
+            assert(require.unmock("@lux/fs"), "unmock should remove the override")
+            local fs = require("@lux/fs")
+            return type(fs.readFile) == "function"
+        "#,
+    )?;
+    assert!(values.success());
+    assert_eq!(
+        values.values.front().and_then(LuaValue::as_boolean),
+        Some(true)
+    );
+    Ok(())
+}
+
+#[cfg(feature = "std-serde")]
+#[test]
+fn library_initializes_on_first_require() -> Result<()> {
+    let initialized = |rt: &Runtime| {
+        rt.library_init_times()
+            .iter()
+            .any(|(library, _)| library.name() == "serde")
+    };
+    let count_keys = r#"
+        local count = 0
+        for _ in pairs(require("@lux/serde")) do
+            count += 1
+        end
+        return count
+    "#;
+
+    let mut rt = Runtime::new()?;
+    run_chunk(&mut rt, "")?;
+    assert!(
+        !initialized(&rt),
+        "an empty script should not initialize serde"
+    );
+
+    for _ in 0..2 {
+        let values = run_chunk(&mut rt, count_keys)?;
+        assert!(values.as_i64().is_some_and(|count| count > 0));
+        assert!(initialized(&rt));
+    }
+    Ok(())
+}
+
+#[test]
+fn invalidate_module_reruns_module() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("lux-invalidate-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let main = dir.join("main.luau");
+    let dep = dir.join("dep.luau");
+    std::fs::write(&main, r#"return require("./dep")"#)?;
+    std::fs::write(&dep, "return 1")?;
+
+    let mut rt = Runtime::new()?;
+    let run = |rt: &mut Runtime| async_io::block_on(rt.run_file(&main));
+    assert_eq!(run(&mut rt)?.as_i64(), Some(1));
+
+    std::fs::write(&dep, "return 2")?;
+    assert_eq!(run(&mut rt)?.as_i64(), Some(1));
+
+    assert!(rt.invalidate_module(&dep)?);
+    assert!(!rt.invalidate_module(&dep)?);
+    assert_eq!(run(&mut rt)?.as_i64(), Some(2));
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn return_values_typed_getters() -> Result<()> {
+    let mut rt = Runtime::new()?;
+
+    let values = run_chunk(&mut rt, r#"return "hello", 1"#)?;
+    assert_eq!(values.as_str().as_deref(), Some("hello"));
+    assert_eq!(values.as_i64(), None);
+    assert_eq!(values.as_table_json(), None);
+
+    let values = run_chunk(&mut rt, "return 42")?;
+    assert_eq!(values.as_i64(), Some(42));
+    assert!(values.as_str().is_none());
+
+    let values = run_chunk(&mut rt, "return { list = { 1, 2, 3 } }")?;
+    assert_eq!(
+        values.as_table_json().as_deref(),
+        Some(r#"{"list":[1,2,3]}"#)
+    );
+
+    let values = run_chunk(&mut rt, "return { f = print }")?;
+    assert_eq!(values.as_table_json(), None);
+    Ok(())
+}
+
+#[test]
+fn return_values_deserialize() -> Result<()> {
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Config {
+        name: String,
+        port: u16,
+        tags: Vec<String>,
+        debug: Option<bool>,
+    }
+
+    let mut rt = Runtime::new()?;
+    let values = run_chunk(
+        &mut rt,
+        r#"return { name = "lux", port = 8080, tags = { "a", "b" } }"#,
+    )?;
+    assert_eq!(
+        values.deserialize::<Config>()?,
+        Config {
+            name: "lux".to_string(),
+            port: 8080,
+            tags: vec!["a".to_string(), "b".to_string()],
+            debug: None,
+        }
+    );
+
+    let values = run_chunk(&mut rt, "return { name = 5 }")?;
+    assert!(values.deserialize::<Config>().is_err());
+
+    let values = run_chunk(&mut rt, "local _ = 1")?;
+    assert_eq!(values.deserialize::<Option<Config>>()?, None);
+    Ok(())
+}
+
+#[test]
+fn execution_limit_stops_runaway_script() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    rt.set_execution_limit(std::time::Duration::from_millis(50));
+    let values = run_chunk(
+        &mut rt,
+        "task.spawn(function() task.wait(60) end) while true do end",
+    )?;
+    assert_eq!(values.code, Some(1));
+    Ok(())
+}
+
+#[test]
+fn instruction_limit_is_catchable() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    rt.set_instruction_limit(10_000);
+    let values = run_chunk(
+        &mut rt,
+        "
+            local ok, err = pcall(function()
+                while true do end
+            end)
+            return ok, err.kind, err.message
+        ",
+    )?;
+    let values = values
+        .values
+        .into_iter()
+        .map(|v| v.to_string())
+        .collect::<LuaResult<Vec<_>>>()?;
+    assert_eq!(
+        values,
+        [
+            "false",
+            "Timeout",
+            "Script exceeded its instruction limit of 10000"
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn instruction_limit_error_is_timeout() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    run_chunk(
+        &mut rt,
+        "return { spin = function() while true do end end }",
+    )?;
+    rt.set_instruction_limit(10_000);
+    let err = async_io::block_on(rt.call_function::<_, ()>("spin", ())).unwrap_err();
+    assert!(err.is_timeout());
+    Ok(())
+}
+
+#[test]
+fn task_instruction_limit_only_stops_task() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    rt.set_task_instruction_limit(10_000);
+    let values = run_chunk(
+        &mut rt,
+        "
+            local finished = false
+            task.spawn(function()
+                while true do task.wait() end
+            end)
+            task.spawn(function()
+                while true do end
+            end)
+            task.wait(0.05)
+            finished = true
+            return finished
+        ",
+    )?;
+    assert!(values.errored);
+    assert_eq!(values.code, None);
+    assert_eq!(
+        values.values.front().and_then(LuaValue::as_boolean),
+        Some(true)
+    );
+    Ok(())
+}
+
+#[test]
+fn execution_limit_stops_waiting_tasks() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    rt.set_execution_limit(std::time::Duration::from_millis(50));
+    let started = std::time::Instant::now();
+    let values = run_chunk(&mut rt, "task.wait(10)")?;
+    assert_eq!(values.code, Some(1));
+    assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    Ok(())
+}
+
+#[test]
+fn dump_tasks_reports_suspended_tasks() -> Result<()> {
+    let mut rt = Runtime::new()?;
+    let values = run_chunk(
+        &mut rt,
+        "
+            task.spawn({ name = \"stuck\" }, function()
+                coroutine.yield()
+            end)
+            task.spawn(function()
+                task.wait()
+            end)
+        ",
+    )?;
+    assert_eq!(values.code, None);
+
+    let tasks = rt.dump_tasks();
+    assert!(tasks.is_deadlocked());
+    assert_eq!(tasks.tasks().len(), 1);
+    assert_eq!(tasks.tasks()[0].name.as_deref(), Some("stuck"));
+    assert!(tasks.tasks()[0].traceback.is_some());
+    assert!(tasks.to_string().contains("\"stuck\" (suspended"));
+    Ok(())
+}
+
+#[cfg(all(feature = "std-ffi", feature = "std-process"))]
+#[test]
+fn deny_by_default_gates_libraries() -> Result<()> {
+    let mut rt = Runtime::new()?
+        .with_deny_by_default(true)
+        .with_permission(crate::Permission::Net);
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            local ffi = require("@lux/ffi")
+            local ok, err = pcall(function() return ffi.cdef end)
+            assert(not ok, "ffi should be denied")
+            assert(string.find(tostring(err), "ffi.cdef requires the 'ffi' permission", 1, true), tostring(err))
+            assert(string.find(tostring(err), "--allow-ffi", 1, true), "error names the flag")
+
+            local process = require("@lux/process")
+            assert(type(process.args) == "table", "harmless parts of process stay available")
+            local ok2, err2 = pcall(process.exec, "echo")
+            assert(not ok2 and string.find(tostring(err2), "--allow-process", 1, true), tostring(err2))
+
+            return require("@lux/socket").tcp.connect ~= nil
+        "#,
+    )?;
+    assert!(values.success());
+    assert_eq!(
+        values.values.front().and_then(LuaValue::as_boolean),
+        Some(true)
+    );
+    Ok(())
+}
+
+#[cfg(feature = "std-fs")]
+#[test]
+fn fs_permission_limited_to_paths() -> Result<()> {
+    let allowed = std::env::temp_dir().join("lux-fs-permission-test");
+    std::fs::create_dir_all(&allowed)?;
+    let outside = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+
+    let mut rt = Runtime::new()?
+        .with_args(vec![
+            allowed.to_string_lossy().into_owned(),
+            outside.to_string(),
+        ])
+        .with_deny_by_default(true)
+        .with_fs_path(&allowed);
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            local fs = require("@lux/fs")
+            local ALLOWED, OUTSIDE = args[1], args[2]
+            fs.writeFile(ALLOWED .. "/file.txt", "contents")
+            assert(fs.readFile(ALLOWED .. "/file.txt") == "contents")
+
+            local ok, err = pcall(fs.readFile, OUTSIDE)
+            assert(not ok, "paths outside of the allowed ones are denied")
+            assert(string.find(tostring(err), "--allow-fs=", 1, true), tostring(err))
+
+            local ok2 = pcall(fs.readFile, ALLOWED .. "/../../escape.txt")
+            assert(not ok2, "parent directories can not escape the allowed path")
+        "#,
+    )?;
+    assert!(values.success());
+    std::fs::remove_dir_all(&allowed)?;
+    Ok(())
+}
+
+#[cfg(all(feature = "std-csv", feature = "std-image"))]
+#[test]
+fn fs_permission_limits_csv_and_image() -> Result<()> {
+    let allowed = std::env::temp_dir().join("lux-fs-permission-libs-test");
+    std::fs::create_dir_all(&allowed)?;
+    let outside = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+
+    let mut rt = Runtime::new()?
+        .with_args(vec![
+            allowed.to_string_lossy().into_owned(),
+            outside.to_string(),
+        ])
+        .with_deny_by_default(true)
+        .with_fs_path(&allowed);
+    let values = run_chunk(
+        &mut rt,
+        r#"
+            local csv = require("@lux/csv")
+            local image = require("@lux/image")
+            local ALLOWED, OUTSIDE = args[1], args[2]
+
+            assert(#csv.parse("a,b\n1,2") == 1, "parsing text needs no permission")
+            local writer = csv.writer(ALLOWED .. "/rows.csv")
+            writer:close()
+
+            for name, f in { ["csv.reader"] = csv.reader, ["csv.writer"] = csv.writer, ["csv.ndjson.reader"] = csv.ndjson.reader, ["image.readFile"] = image.readFile } do
+                local ok, err = pcall(f, OUTSIDE)
+                assert(not ok, name .. " should deny paths outside of the allowed ones")
+                assert(string.find(tostring(err), "--allow-fs=", 1, true), tostring(err))
+            end
+
+            local ok, err = pcall(function()
+                image.new(1, 1):WritePng(ALLOWED .. "/../escape.png")
+            end)
+            assert(not ok and string.find(tostring(err), "--allow-fs=", 1, true), tostring(err))
+            image.new(1, 1):WritePng(ALLOWED .. "/pixel.png")
+            assert(image.readFile(ALLOWED .. "/pixel.png").Width == 1)
+        "#,
+    )?;
+    assert!(values.success());
+    std::fs::remove_dir_all(&allowed)?;
+    Ok(())
+}
+
+#[test]
+fn bytecode_cache_invalidates_changed_files() -> Result<()> {
+    let dir = std::env::temp_dir().join("lux-bytecode-cache-test");
+    let cache_dir = dir.join(".lux-cache");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("main.luau"), "return require(\"./dep\") + 1")?;
+    std::fs::write(dir.join("dep.luau"), "return 1")?;
+
+    let run = || -> Result<i64> {
+        async_io::block_on(async {
+            let mut rt = Runtime::new()?.with_bytecode_cache(Some(cache_dir.clone()));
+            let values = rt.run_file(dir.join("main.luau")).await?;
+            assert!(values.success());
+            Ok(values.deserialize::<f64>()? as i64)
+        })
+    };
+
+    assert_eq!(run()?, 2);
+    assert_eq!(run()?, 2, "cached bytecode runs the same as source");
+
+    std::fs::write(dir.join("dep.luau"), "return 41")?;
+    assert_eq!(run()?, 42, "changed files are compiled again");
+
+    let stats = async_io::block_on(lux_utils::bytecode::BytecodeCache::stats(&cache_dir))?;
+    assert_eq!(stats.entries, 2, "files keep a single entry each");
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn snapshots_restore_required_modules() -> Result<()> {
+    let dir = std::env::temp_dir().join("lux-snapshot-test");
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("main.luau"), "return require(\"./dep\") + 1")?;
+    std::fs::write(dir.join("dep.luau"), "return 1")?;
+
+    let run = |mut rt: Runtime| -> Result<(i64, Vec<u8>)> {
+        async_io::block_on(async {
+            let values = rt.run_file(dir.join("main.luau")).await?;
+            assert!(values.success());
+            Ok((values.deserialize::<f64>()? as i64, rt.snapshot()))
+        })
+    };
+
+    let (result, snapshot) = run(Runtime::new()?)?;
+    assert_eq!(result, 2);
+
+    // The snapshot is used instead of the file, so changes to it are not seen
+    std::fs::write(dir.join("dep.luau"), "return 41")?;
+    let (result, restored) = run(Runtime::from_snapshot(&snapshot)?)?;
+    assert_eq!(result, 2, "modules are restored from the snapshot");
+    assert_eq!(restored, snapshot, "restored modules stay in the snapshot");
+    assert_eq!(run(Runtime::new()?)?.0, 42);
+
+    assert!(Runtime::from_snapshot(b"not a snapshot").is_err());
+
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn snapshots_run_without_sources() -> Result<()> {
+    let dir = std::env::temp_dir().join("lux-snapshot-sources-test");
+    std::fs::create_dir_all(dir.join("lib"))?;
+    std::fs::write(dir.join("main.luau"), "return require(\"./lib/dep\") + 1")?;
+    std::fs::write(dir.join("lib/dep.luau"), "return require(\"./value\")")?;
+    std::fs::write(dir.join("lib/value.luau"), "return 41")?;
+
+    let snapshot = async_io::block_on(async {
+        let mut rt = Runtime::new()?;
+        rt.run_file(dir.join("main.luau")).await?;
+        Ok::<_, anyhow::Error>(rt.snapshot())
+    })?;
+    std::fs::remove_dir_all(&dir)?;
+
+    // Modules are found relative to the root of the snapshot, wherever it is
+    let mut rt = Runtime::from_snapshot(&snapshot)?.with_snapshot_root(&dir);
+    let values = async_io::block_on(rt.run_snapshot())?;
+    assert!(values.success());
+    assert_eq!(values.deserialize::<f64>()? as i64, 42);
+
+    let mut rt = Runtime::from_snapshot(&snapshot)?
+        .with_snapshot_root(std::env::temp_dir().join("lux-snapshot-elsewhere"));
+    let values = async_io::block_on(rt.run_snapshot())?;
+    assert_eq!(values.deserialize::<f64>()? as i64, 42);
+    assert!(async_io::block_on(rt.run_snapshot()).is_err());
+
+    Ok(())
+}
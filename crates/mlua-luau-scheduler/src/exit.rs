@@ -1,18 +1,18 @@
 use std::{cell::Cell, rc::Rc};
 
-use crate::events::OnceEvent;
+use crate::events::{MultiEvent, MultiListener};
 
 #[derive(Debug, Clone)]
 pub(crate) struct Exit {
     code: Rc<Cell<Option<u8>>>,
-    event: OnceEvent,
+    event: MultiEvent,
 }
 
 impl Exit {
     pub fn new() -> Self {
         Self {
             code: Rc::new(Cell::new(None)),
-            event: OnceEvent::new(),
+            event: MultiEvent::new(),
         }
     }
 
@@ -25,7 +25,11 @@ impl Exit {
         self.code.get()
     }
 
-    pub async fn listen(&self) {
-        self.event.listen().await;
+    pub fn reset(&self) {
+        self.code.set(None);
+    }
+
+    pub fn listen(&self) -> MultiListener {
+        self.event.listen()
     }
 }
@@ -5,6 +5,8 @@ use mlua::prelude::*;
 use crate::{
     error_callback::ThreadErrorCallback,
     queue::{DeferredThreadQueue, SpawnedThreadQueue},
+    shutdown::Shutdown,
+    tasks::{TaskRegistry, TaskStatus},
     threads::{ThreadId, ThreadMap},
     traits::LuaSchedulerExt,
     util::{LuaThreadOrFunction, is_poll_pending},
@@ -16,6 +18,10 @@ Lua state does not have scheduler metadata attached!\
 \nScheduler functions must always be created from within an active scheduler.\
 ";
 
+const ERR_SHUTTING_DOWN: &str = "\
+Cannot spawn or defer new threads while the scheduler is shutting down\
+";
+
 const EXIT_IMPL_LUA: &str = r"
 exit(...)
 yield()
@@ -105,13 +111,36 @@ impl Functions {
             .app_data_ref::<ThreadMap>()
             .expect(ERR_METADATA_NOT_ATTACHED)
             .clone();
+        let shutdown = lua
+            .app_data_ref::<Shutdown>()
+            .expect(ERR_METADATA_NOT_ATTACHED)
+            .clone();
+        let tasks = lua
+            .app_data_ref::<TaskRegistry>()
+            .expect(ERR_METADATA_NOT_ATTACHED)
+            .clone();
+
+        // Tasks spawned from Lua remember where they were spawned from, for Scheduler::dump_tasks
+        let traceback = lua
+            .globals()
+            .get::<LuaTable>("debug")?
+            .get::<LuaFunction>("traceback")?;
 
         let resume_queue = defer_queue.clone();
         let resume_map = thread_map.clone();
+        let resume_tasks = tasks.clone();
         let resume =
             lua.create_function(move |lua, (thread, args): (LuaThread, LuaMultiValue)| {
                 let _span = tracing::trace_span!("Scheduler::fn_resume").entered();
-                match thread.resume::<LuaMultiValue>(args.clone()) {
+                resume_tasks.set_status(ThreadId::from(&thread), TaskStatus::Running);
+                let result = thread.resume::<LuaMultiValue>(args.clone());
+                resume_tasks.after_resume(
+                    &thread,
+                    result
+                        .as_ref()
+                        .is_ok_and(|v| v.front().is_some_and(is_poll_pending)),
+                );
+                match result {
                     Ok(v) => {
                         if v.front().is_some_and(is_poll_pending) {
                             // Pending, defer to scheduler and return nil
@@ -158,14 +187,29 @@ impl Functions {
             .into_function()?;
 
         let spawn_map = thread_map.clone();
+        let spawn_shutdown = shutdown.clone();
+        let spawn_tasks = tasks.clone();
+        let spawn_traceback = traceback.clone();
         let spawn = lua.create_function(
             move |lua, (tof, args): (LuaThreadOrFunction, LuaMultiValue)| {
                 let _span = tracing::trace_span!("Scheduler::fn_spawn").entered();
+                if spawn_shutdown.is_closed() {
+                    return Err(LuaError::runtime(ERR_SHUTTING_DOWN));
+                }
                 let thread = tof.into_thread(lua)?;
                 if thread.status() == LuaThreadStatus::Resumable {
+                    let traceback = spawn_traceback.call::<String>(())?;
+                    spawn_tasks.register(&thread, TaskStatus::Running, Some(traceback));
                     // NOTE: We need to resume the thread once instantly for correct behavior,
                     // and only if we get the pending value back we can spawn to async executor
-                    match thread.resume::<LuaMultiValue>(args.clone()) {
+                    let result = thread.resume::<LuaMultiValue>(args.clone());
+                    spawn_tasks.after_resume(
+                        &thread,
+                        result
+                            .as_ref()
+                            .is_ok_and(|v| v.front().is_some_and(is_poll_pending)),
+                    );
+                    match result {
                         Ok(v) => {
                             if v.front().is_some_and(is_poll_pending) {
                                 spawn_queue.push_item(lua, &thread, args)?;
@@ -193,11 +237,17 @@ impl Functions {
             },
         )?;
 
+        let cancel_tasks = tasks.clone();
         let defer = lua.create_function(
             move |lua, (tof, args): (LuaThreadOrFunction, LuaMultiValue)| {
                 let _span = tracing::trace_span!("Scheduler::fn_defer").entered();
+                if shutdown.is_closed() {
+                    return Err(LuaError::runtime(ERR_SHUTTING_DOWN));
+                }
                 let thread = tof.into_thread(lua)?;
                 if thread.status() == LuaThreadStatus::Resumable {
+                    let traceback = traceback.call::<String>(())?;
+                    tasks.register(&thread, TaskStatus::Queued, Some(traceback));
                     defer_queue.push_item(lua, &thread, args)?;
                 }
                 Ok(thread)
@@ -212,6 +262,7 @@ impl Functions {
         let cancel = lua.create_function(move |lua, thread: LuaThread| {
             let _span = tracing::trace_span!("Scheduler::fn_cancel").entered();
             let close: LuaFunction = lua.registry_value(&close_key)?;
+            cancel_tasks.remove(ThreadId::from(&thread));
             match close.call(thread) {
                 Err(LuaError::CoroutineUnresumable) | Ok(()) => Ok(()),
                 Err(e) => Err(e),
@@ -6,13 +6,17 @@ mod exit;
 mod functions;
 mod queue;
 mod scheduler;
+mod shutdown;
 mod status;
+mod tasks;
 mod threads;
 mod traits;
 mod util;
 
 pub use functions::Functions;
 pub use scheduler::Scheduler;
+pub use shutdown::PendingGuard;
 pub use status::Status;
+pub use tasks::{TaskInfo, TaskStatus};
 pub use threads::ThreadId;
 pub use traits::{IntoLuaThread, LuaSchedulerExt, LuaSpawnExt};
@@ -17,7 +17,9 @@ use crate::{
     error_callback::ThreadErrorCallback,
     exit::Exit,
     queue::{DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
+    shutdown::Shutdown,
     status::Status,
+    tasks::{TaskInfo, TaskRegistry, TaskStatus},
     threads::{ThreadId, ThreadMap},
     traits::IntoLuaThread,
     util::run_until_yield,
@@ -50,6 +52,8 @@ pub struct Scheduler {
     thread_map: ThreadMap,
     status: Rc<Cell<Status>>,
     exit: Exit,
+    shutdown: Shutdown,
+    tasks: TaskRegistry,
 }
 
 impl Scheduler {
@@ -69,6 +73,8 @@ impl Scheduler {
         let error_callback = ThreadErrorCallback::default();
         let result_map = ThreadMap::new();
         let exit = Exit::new();
+        let shutdown = Shutdown::new();
+        let tasks = TaskRegistry::new();
 
         assert!(
             lua.app_data_ref::<SpawnedThreadQueue>().is_none(),
@@ -90,12 +96,22 @@ impl Scheduler {
             lua.app_data_ref::<Exit>().is_none(),
             "{ERR_METADATA_ALREADY_ATTACHED}"
         );
+        assert!(
+            lua.app_data_ref::<Shutdown>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
+        assert!(
+            lua.app_data_ref::<TaskRegistry>().is_none(),
+            "{ERR_METADATA_ALREADY_ATTACHED}"
+        );
 
         lua.set_app_data(queue_spawn.clone());
         lua.set_app_data(queue_defer.clone());
         lua.set_app_data(error_callback.clone());
         lua.set_app_data(result_map.clone());
         lua.set_app_data(exit.clone());
+        lua.set_app_data(shutdown.clone());
+        lua.set_app_data(tasks.clone());
 
         let status = Rc::new(Cell::new(Status::NotStarted));
 
@@ -107,6 +123,8 @@ impl Scheduler {
             thread_map: result_map,
             status,
             exit,
+            shutdown,
+            tasks,
         }
     }
 
@@ -163,7 +181,7 @@ impl Scheduler {
     }
 
     /**
-        Gets the exit code for this scheduler, if one has been set.
+        Gets the exit code for this scheduler, if one has been set during the current or last run.
     */
     #[must_use]
     pub fn get_exit_code(&self) -> Option<u8> {
@@ -179,6 +197,54 @@ impl Scheduler {
         self.exit.set(code);
     }
 
+    /**
+        Stops the scheduler from accepting new threads through the `spawn` and `defer` functions.
+
+        Threads that are already running or queued are unaffected, and the
+        scheduler accepts new threads again the next time [`Scheduler::run`] is called.
+    */
+    pub fn begin_shutdown(&self) {
+        self.shutdown.close();
+    }
+
+    /**
+        Returns `true` if [`Scheduler::begin_shutdown`] was called during the current run.
+    */
+    #[must_use]
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_closed()
+    }
+
+    /**
+        Returns the number of Lua threads that are currently running, or waiting on an async task.
+    */
+    #[must_use]
+    pub fn pending_threads(&self) -> usize {
+        self.shutdown.pending()
+    }
+
+    /**
+        Returns a snapshot of all live tasks on this scheduler, in the order they were first spawned.
+
+        Tasks are Lua threads that have been spawned, deferred or pushed to this scheduler,
+        and have not yet finished, errored or been cancelled. Threads that yielded without
+        waiting on anything are included as [`TaskStatus::Suspended`], and when the scheduler
+        has finished running, these are threads that nothing will resume anymore.
+    */
+    #[must_use]
+    pub fn dump_tasks(&self) -> Vec<TaskInfo> {
+        self.tasks.dump()
+    }
+
+    /**
+        Sets the name of a live task, shown in [`Scheduler::dump_tasks`].
+
+        Does nothing if the thread is not a live task.
+    */
+    pub fn set_task_name(&self, id: ThreadId, name: impl Into<String>) {
+        self.tasks.set_name(id, name.into());
+    }
+
     /**
         Spawns a chunk / function / thread onto the scheduler queue.
 
@@ -278,6 +344,11 @@ impl Scheduler {
             return;
         }
 
+        // A previous run may have been shut down or exited, this one starts out
+        // accepting threads and without an exit code
+        self.shutdown.reset();
+        self.exit.reset();
+
         /*
             Create new executors to use - note that we do not need to create multiple executors
             for work stealing, the user may do that themselves if they want to and it will work
@@ -332,17 +403,24 @@ impl Scheduler {
                 if thread.status() == LuaThreadStatus::Resumable {
                     // Check if we should be tracking this thread
                     let id = ThreadId::from(&thread);
+                    let tasks = self.tasks.clone();
+                    tasks.register(&thread, TaskStatus::Queued, None);
                     let id_tracked = result_map.is_tracked(id);
                     let result_map_inner = if id_tracked {
                         Some(result_map.clone())
                     } else {
                         None
                     };
-                    // Create our future which will run the thread and store its final result
+                    // Create our future which will run the thread and store its final result,
+                    // the thread counts as pending for shutdown until the future is dropped
+                    let pending = self.shutdown.track();
                     let fut = async move {
+                        let _pending = pending;
+                        let res = tasks.track(id, run_until_yield(thread.clone(), args)).await;
+                        tasks.after_resume(&thread, false);
                         if id_tracked {
-                            // Run until yield and check if we got a final result
-                            if let Some(res) = run_until_yield(thread.clone(), args).await {
+                            // Check if we got a final result
+                            if let Some(res) = res {
                                 if let Err(e) = res.as_ref() {
                                     self.error_callback.call(e);
                                 }
@@ -350,13 +428,10 @@ impl Scheduler {
                                     result_map_inner.unwrap().insert(id, res);
                                 }
                             }
-                        } else {
-                            // Just run until yield
-                            if let Some(res) = run_until_yield(thread, args).await
-                                && let Err(e) = res.as_ref()
-                            {
-                                self.error_callback.call(e);
-                            }
+                        } else if let Some(res) = res
+                            && let Err(e) = res.as_ref()
+                        {
+                            self.error_callback.call(e);
                         }
                     };
                     // Spawn it on the executor
@@ -414,6 +489,10 @@ impl Scheduler {
                         num_deferred += 1;
                     }
                 }
+                if num_spawned > 0 || num_deferred > 0 {
+                    // Queued threads are now tracked as pending, let shutdown check again
+                    self.shutdown.notify();
+                }
                 {
                     let _span = trace_span!("Scheduler::drain_futures").entered();
                     for fut in fut_queue.take_items() {
@@ -445,6 +524,9 @@ impl Scheduler {
         main_exec.run(fut).await;
         self.set_status(Status::Completed);
 
+        // Threads still running or waiting when the scheduler exits will not be resumed
+        self.tasks.suspend_all();
+
         // Clean up
         self.lua
             .remove_app_data::<WeakArc<Executor>>()
@@ -465,6 +547,8 @@ impl Drop for Scheduler {
             self.lua.remove_app_data::<ThreadErrorCallback>();
             self.lua.remove_app_data::<ThreadMap>();
             self.lua.remove_app_data::<Exit>();
+            self.lua.remove_app_data::<Shutdown>();
+            self.lua.remove_app_data::<TaskRegistry>();
         } else {
             // In any other case we panic if metadata was removed incorrectly
             self.lua
@@ -482,6 +566,12 @@ impl Drop for Scheduler {
             self.lua
                 .remove_app_data::<Exit>()
                 .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<Shutdown>()
+                .expect(ERR_METADATA_REMOVED);
+            self.lua
+                .remove_app_data::<TaskRegistry>()
+                .expect(ERR_METADATA_REMOVED);
         }
     }
 }
@@ -0,0 +1,96 @@
+use std::{cell::Cell, rc::Rc};
+
+use crate::events::MultiEvent;
+
+#[derive(Debug, Default)]
+struct ShutdownInner {
+    closed: Cell<bool>,
+    pending: Cell<usize>,
+    event: MultiEvent,
+}
+
+/**
+    Tracks how many Lua threads are running on the scheduler, and whether
+    the scheduler has stopped accepting new threads from `spawn` and `defer`.
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Shutdown {
+    inner: Rc<ShutdownInner>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn close(&self) {
+        self.inner.closed.set(true);
+        self.inner.event.notify();
+    }
+
+    pub fn reset(&self) {
+        self.inner.closed.set(false);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.inner.closed.get()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.inner.pending.get()
+    }
+
+    /**
+        Marks a Lua thread as running until the returned guard is dropped.
+    */
+    pub fn track(&self) -> PendingGuard {
+        self.inner.pending.set(self.inner.pending.get() + 1);
+        PendingGuard {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /**
+        Wakes up anything waiting in [`Shutdown::wait_until`] to check again.
+    */
+    pub fn notify(&self) {
+        self.inner.event.notify();
+    }
+
+    /**
+        Waits until [`Shutdown::close`] has been called.
+    */
+    pub async fn wait_closed(&self) {
+        while !self.inner.closed.get() {
+            self.inner.event.listen().await;
+        }
+    }
+
+    /**
+        Waits until at most `remaining` Lua threads are still running, and `queues_empty` returns `true`.
+    */
+    pub async fn wait_until(&self, remaining: usize, queues_empty: impl Fn() -> bool) {
+        while self.inner.pending.get() > remaining || !queues_empty() {
+            self.inner.event.listen().await;
+        }
+    }
+}
+
+/**
+    Counts as a running Lua thread until dropped, see [`LuaSchedulerExt::track_pending`].
+
+    [`LuaSchedulerExt::track_pending`]: crate::LuaSchedulerExt::track_pending
+*/
+#[derive(Debug)]
+pub struct PendingGuard {
+    inner: Rc<ShutdownInner>,
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        self.inner
+            .pending
+            .set(self.inner.pending.get().saturating_sub(1));
+        self.inner.event.notify();
+    }
+}
@@ -0,0 +1,227 @@
+#![allow(clippy::inline_always)]
+
+use std::{
+    cell::RefCell,
+    future::{Future, poll_fn},
+    pin::pin,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use mlua::prelude::*;
+use rustc_hash::FxHashMap;
+
+use crate::threads::ThreadId;
+
+/**
+    What a task tracked by the scheduler is currently doing.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskStatus {
+    /// Waiting in the scheduler queue to be resumed.
+    Queued,
+    /// Currently running Lua code.
+    Running,
+    /// Waiting for an async function, such as a timer or I/O, to complete.
+    Waiting,
+    /// Yielded without waiting for anything the scheduler knows about,
+    /// and only resumes if other Lua code resumes it.
+    Suspended,
+}
+
+impl TaskStatus {
+    /**
+        Returns the lowercase name of this status, such as `"waiting"`.
+    */
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Waiting => "waiting",
+            Self::Suspended => "suspended",
+        }
+    }
+}
+
+/**
+    A snapshot of a live task, as returned by [`Scheduler::dump_tasks`].
+
+    [`Scheduler::dump_tasks`]: crate::Scheduler::dump_tasks
+*/
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    /// The thread running this task.
+    pub thread: LuaThread,
+    /// The name given to this task when it was spawned, if any.
+    pub name: Option<String>,
+    /// What this task is currently doing.
+    pub status: TaskStatus,
+    /// How long ago this task was first spawned or deferred.
+    pub age: Duration,
+    /// The traceback of the code that spawned or deferred this task, if it was spawned from Lua.
+    pub traceback: Option<String>,
+}
+
+#[derive(Debug)]
+struct TaskEntry {
+    thread: LuaThread,
+    order: u64,
+    name: Option<String>,
+    status: TaskStatus,
+    created: Instant,
+    traceback: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct TaskRegistryInner {
+    tasks: FxHashMap<ThreadId, TaskEntry>,
+    next_order: u64,
+}
+
+/**
+    Keeps track of the Lua threads that the scheduler is responsible for,
+    along with their names, statuses and where they were spawned from.
+
+    Threads are tracked from when they are first spawned, deferred or queued, and
+    stop being tracked once they finish, error, or get cancelled. Threads that yield
+    outside of the scheduler stay tracked as [`TaskStatus::Suspended`].
+*/
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TaskRegistry {
+    inner: Rc<RefCell<TaskRegistryInner>>,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+        Sets the status of a thread, starting to track it if it is not yet tracked.
+
+        The traceback is only stored when the thread starts being tracked.
+    */
+    pub fn register(&self, thread: &LuaThread, status: TaskStatus, traceback: Option<String>) {
+        let mut inner = self.inner.borrow_mut();
+        let order = inner.next_order;
+        let mut created = false;
+        inner
+            .tasks
+            .entry(ThreadId::from(thread))
+            .or_insert_with(|| {
+                created = true;
+                TaskEntry {
+                    thread: thread.clone(),
+                    order,
+                    name: None,
+                    status,
+                    created: Instant::now(),
+                    traceback,
+                }
+            })
+            .status = status;
+        if created {
+            inner.next_order += 1;
+        }
+    }
+
+    /**
+        Sets the status of a thread, if it is tracked.
+    */
+    #[inline(always)]
+    pub fn set_status(&self, id: ThreadId, status: TaskStatus) {
+        if let Some(entry) = self.inner.borrow_mut().tasks.get_mut(&id) {
+            entry.status = status;
+        }
+    }
+
+    /**
+        Sets the name of a thread, if it is tracked.
+    */
+    pub fn set_name(&self, id: ThreadId, name: String) {
+        if let Some(entry) = self.inner.borrow_mut().tasks.get_mut(&id) {
+            entry.name = Some(name);
+        }
+    }
+
+    #[inline(always)]
+    pub fn remove(&self, id: ThreadId) {
+        self.inner.borrow_mut().tasks.remove(&id);
+    }
+
+    /**
+        Updates a tracked thread after it was resumed and yielded or finished.
+
+        Finished threads stop being tracked, threads waiting for an async function
+        are queued, and threads that yielded for any other reason are suspended.
+    */
+    pub fn after_resume(&self, thread: &LuaThread, pending: bool) {
+        let id = ThreadId::from(thread);
+        if thread.status() != LuaThreadStatus::Resumable {
+            self.remove(id);
+        } else if pending {
+            self.set_status(id, TaskStatus::Queued);
+        } else {
+            self.set_status(id, TaskStatus::Suspended);
+        }
+    }
+
+    /**
+        Runs the given future for a thread, marking the thread as running
+        while the future is polled, and as waiting while it is pending.
+    */
+    pub async fn track<F: Future>(&self, id: ThreadId, fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        poll_fn(|cx| {
+            self.set_status(id, TaskStatus::Running);
+            let poll = fut.as_mut().poll(cx);
+            if poll.is_pending() {
+                self.set_status(id, TaskStatus::Waiting);
+            }
+            poll
+        })
+        .await
+    }
+
+    /**
+        Marks threads that were running or waiting as suspended.
+
+        Used once the scheduler stops, after which nothing drives those threads anymore.
+    */
+    pub fn suspend_all(&self) {
+        for entry in self.inner.borrow_mut().tasks.values_mut() {
+            if matches!(entry.status, TaskStatus::Running | TaskStatus::Waiting) {
+                entry.status = TaskStatus::Suspended;
+            }
+        }
+    }
+
+    /**
+        Returns all live tasks, in the order they started being tracked.
+
+        Threads that were closed without going through the scheduler stop being tracked here.
+    */
+    pub fn dump(&self) -> Vec<TaskInfo> {
+        let mut inner = self.inner.borrow_mut();
+        inner.tasks.retain(|_, entry| {
+            matches!(
+                entry.thread.status(),
+                LuaThreadStatus::Resumable | LuaThreadStatus::Running
+            )
+        });
+
+        let mut entries = inner.tasks.values().collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.order);
+        entries
+            .into_iter()
+            .map(|entry| TaskInfo {
+                thread: entry.thread.clone(),
+                name: entry.name.clone(),
+                status: entry.status,
+                age: entry.created.elapsed(),
+                traceback: entry.traceback.clone(),
+            })
+            .collect()
+    }
+}
@@ -13,6 +13,8 @@ use crate::{
     exit::Exit,
     queue::{DeferredThreadQueue, FuturesQueue, SpawnedThreadQueue},
     scheduler::Scheduler,
+    shutdown::{PendingGuard, Shutdown},
+    tasks::{TaskInfo, TaskRegistry},
     threads::{ThreadId, ThreadMap},
 };
 
@@ -141,6 +143,87 @@ pub trait LuaSchedulerExt {
         Panics if called outside of a running [`Scheduler`].
     */
     fn wait_for_thread(&self, id: ThreadId) -> impl Future<Output = ()>;
+
+    /**
+        Stops the current scheduler from accepting new threads through `spawn` and `defer`.
+
+        See [`Scheduler::begin_shutdown`] for more information.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn begin_shutdown(&self);
+
+    /**
+        Returns `true` if the current scheduler has stopped accepting new threads.
+
+        See [`Scheduler::is_shutting_down`] for more information.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn is_shutting_down(&self) -> bool;
+
+    /**
+        Waits until the current scheduler stops accepting new threads.
+
+        Useful for background work that should wind down when a graceful shutdown begins,
+        such as servers that should stop accepting new connections.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn wait_for_shutdown(&self) -> impl Future<Output = ()>;
+
+    /**
+        Counts as a running Lua thread on the current scheduler until the returned guard is dropped.
+
+        Lets work that is not running in a Lua thread, such as writing a response
+        to a socket, delay a graceful shutdown until it has finished.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn track_pending(&self) -> PendingGuard;
+
+    /**
+        Waits until at most `remaining` Lua threads are still running on the current scheduler,
+        and no threads are waiting in its queues.
+
+        The thread calling this is itself counted as running, so
+        waiting for all other threads means passing a `remaining` of `1`.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn wait_for_pending_threads(&self, remaining: usize) -> impl Future<Output = ()>;
+
+    /**
+        Sets the name of a live task on the current scheduler.
+
+        See [`Scheduler::set_task_name`] for more information.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn set_task_name(&self, id: ThreadId, name: impl Into<String>);
+
+    /**
+        Returns a snapshot of all live tasks on the current scheduler.
+
+        See [`Scheduler::dump_tasks`] for more information.
+
+        # Panics
+
+        Panics if called outside of a running [`Scheduler`].
+    */
+    fn dump_tasks(&self) -> Vec<TaskInfo>;
 }
 
 /**
@@ -331,6 +414,69 @@ impl LuaSchedulerExt for Lua {
             .expect("lua threads results can only be retrieved from within an active scheduler");
         map.listen(id)
     }
+
+    fn begin_shutdown(&self) {
+        let shutdown = self
+            .app_data_ref::<Shutdown>()
+            .expect("shutdown can only be started from within an active scheduler");
+        shutdown.close();
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        let shutdown = self
+            .app_data_ref::<Shutdown>()
+            .expect("shutdown status can only be checked from within an active scheduler");
+        shutdown.is_closed()
+    }
+
+    fn wait_for_shutdown(&self) -> impl Future<Output = ()> {
+        let shutdown = self
+            .app_data_ref::<Shutdown>()
+            .expect("shutdown can only be waited for from within an active scheduler")
+            .clone();
+        async move { shutdown.wait_closed().await }
+    }
+
+    fn track_pending(&self) -> PendingGuard {
+        let shutdown = self
+            .app_data_ref::<Shutdown>()
+            .expect("pending work can only be tracked from within an active scheduler");
+        shutdown.track()
+    }
+
+    fn wait_for_pending_threads(&self, remaining: usize) -> impl Future<Output = ()> {
+        let shutdown = self
+            .app_data_ref::<Shutdown>()
+            .expect("lua threads can only be waited for from within an active scheduler")
+            .clone();
+        let spawned = self
+            .app_data_ref::<SpawnedThreadQueue>()
+            .expect("lua threads can only be waited for from within an active scheduler")
+            .clone();
+        let deferred = self
+            .app_data_ref::<DeferredThreadQueue>()
+            .expect("lua threads can only be waited for from within an active scheduler")
+            .clone();
+        async move {
+            shutdown
+                .wait_until(remaining, || spawned.is_empty() && deferred.is_empty())
+                .await;
+        }
+    }
+
+    fn set_task_name(&self, id: ThreadId, name: impl Into<String>) {
+        let tasks = self
+            .app_data_ref::<TaskRegistry>()
+            .expect("tasks can only be named from within an active scheduler");
+        tasks.set_name(id, name.into());
+    }
+
+    fn dump_tasks(&self) -> Vec<TaskInfo> {
+        let tasks = self
+            .app_data_ref::<TaskRegistry>()
+            .expect("tasks can only be dumped from within an active scheduler");
+        tasks.dump()
+    }
 }
 
 impl LuaSpawnExt for Lua {